@@ -0,0 +1,297 @@
+//! `debug.rs` - DWARF debug information, so that programs can be stepped through in a debugger.
+//!
+//! The gem is one compile unit, in the file of its root module. Every function gets a subprogram, every
+//! instruction the line and column of the statement or terminator it was generated for, and every named local
+//! a variable describing its stack slot. Structs are described with their fields, and `str` as a struct of
+//! a pointer to its bytes and its length.
+use crate::LLVMCodegen;
+use codespan_reporting::files::{Files, SimpleFiles};
+use inkwell::debug_info::{
+    debug_metadata_version, AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants, DIScope, DISubprogram, DIType,
+    DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+};
+use inkwell::module::FlagBehavior;
+use inkwell::types::StructType;
+use inkwell::values::{FunctionValue, PointerValue};
+use inkwell::AddressSpace;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use topaz_mir::{Body, Local};
+use topaz_resolve::def::DefId;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+/// The sources of a gem, which the debug information refers to.
+#[derive(Clone, Copy)]
+pub struct Sources<'a> {
+    pub files: &'a SimpleFiles<String, String>,
+    /// The file of the root module
+    pub root: usize,
+}
+
+// the encodings of `DW_ATE`, the kinds of basic types
+const DW_ATE_BOOLEAN: u32 = 0x02;
+const DW_ATE_SIGNED: u32 = 0x05;
+const DW_ATE_UNSIGNED: u32 = 0x08;
+const DW_ATE_UTF: u32 = 0x10;
+
+pub(crate) struct DebugInfo<'cx, 'g> {
+    builder: DebugInfoBuilder<'cx>,
+    unit: DICompileUnit<'cx>,
+    sources: Sources<'g>,
+    /// The file of every file ID of the gem
+    files: HashMap<usize, DIFile<'cx>>,
+    structs: HashMap<DefId, DIType<'cx>>,
+    str_type: Option<DIType<'cx>>,
+}
+
+impl<'cx, 'g> DebugInfo<'cx, 'g> {
+    /// Starts the debug information of the module of `codegen`, once its structs are declared.
+    pub(crate) fn new(codegen: &LLVMCodegen<'cx, 'g>, sources: Sources<'g>) -> Self {
+        let version = codegen.cx.i32_type().const_int(u64::from(debug_metadata_version()), false);
+        codegen.m.add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, version);
+        let dwarf = codegen.cx.i32_type().const_int(4, false);
+        codegen.m.add_basic_value_flag("Dwarf Version", FlagBehavior::Warning, dwarf);
+
+        let (file_name, directory) = split_path(&file_name(sources, sources.root));
+        let (builder, unit) = codegen.m.create_debug_info_builder(
+            true,
+            // Topaz has no code of its own, and C debuggers understand everything it generates
+            DWARFSourceLanguage::C,
+            &file_name,
+            &directory,
+            "topaz",
+            false,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+
+        let mut debug = Self { builder, unit, sources, files: HashMap::new(), structs: HashMap::new(), str_type: None };
+        for file_id in codegen.gem.bodies.iter().map(|body| body.file_id) {
+            if !debug.files.contains_key(&file_id) {
+                let (file_name, directory) = split_path(&file_name(sources, file_id));
+                debug.files.insert(file_id, debug.builder.create_file(&file_name, &directory));
+            }
+        }
+        debug.str_type = debug.create_str_type(codegen);
+        let mut in_progress = HashSet::new();
+        for struct_def in &codegen.gem.structs {
+            debug.create_struct_type(codegen, struct_def.def, &mut in_progress);
+        }
+        debug
+    }
+
+    /// Adds the subprogram of `body` to `function`, returning the scope of its code.
+    pub(crate) fn function(
+        &self,
+        codegen: &LLVMCodegen<'cx, 'g>,
+        body: &Body,
+        function: FunctionValue<'cx>,
+    ) -> DISubprogram<'cx> {
+        let file = self.file(body.file_id);
+        let (line, _) = self.line_col(body.file_id, body.span.start);
+        let ret = &body.local(Local::RETURN).ty;
+        let ret = if *ret.peel() == Ty::Void { None } else { self.di_type(codegen, ret) };
+        let params: Vec<_> = body.args().filter_map(|arg| self.di_type(codegen, &body.local(arg).ty)).collect();
+        let subroutine = self.builder.create_subroutine_type(file, ret, &params, DIFlags::PUBLIC);
+        let subprogram = self.builder.create_function(
+            self.unit.as_debug_info_scope(),
+            &body.name,
+            function.get_name().to_str().ok(),
+            file,
+            line,
+            subroutine,
+            false,
+            true,
+            line,
+            DIFlags::PUBLIC,
+            false,
+        );
+        function.set_subprogram(subprogram);
+        subprogram
+    }
+
+    /// Describes the named locals of `body`, stored in `slots`, as its parameters and variables.
+    ///
+    /// A variable assigned in several places is split into several locals by SSA construction; only the first
+    /// of them, the one it is declared with, is described.
+    pub(crate) fn declare_locals(
+        &self,
+        codegen: &LLVMCodegen<'cx, 'g>,
+        body: &Body,
+        scope: DIScope<'cx>,
+        slots: &[PointerValue<'cx>],
+    ) {
+        let file = self.file(body.file_id);
+        let Some(block) = codegen.b.get_insert_block() else { return };
+        let mut declared = HashSet::new();
+        for (index, decl) in body.locals.iter().enumerate() {
+            let Some(name) = &decl.name else { continue };
+            let Some(ty) = self.di_type(codegen, &decl.ty) else { continue };
+            if *decl.ty.peel() == Ty::Void || !declared.insert((name, decl.span.start)) {
+                continue;
+            }
+
+            let (line, column) = self.line_col(body.file_id, decl.span.start);
+            let variable = if (1..=body.arg_count).contains(&index) {
+                #[allow(clippy::cast_possible_truncation)]
+                let arg_no = index as u32;
+                self.builder.create_parameter_variable(scope, name, arg_no, file, line, ty, true, DIFlags::ZERO)
+            } else {
+                self.builder.create_auto_variable(scope, name, file, line, ty, true, DIFlags::ZERO, 0)
+            };
+            let location = self.builder.create_debug_location(codegen.cx, line, column, scope, None);
+            self.builder.insert_declare_at_end(slots[index], Some(variable), None, location, block);
+        }
+    }
+
+    /// Makes the code generated next belong to the line and column of the byte at `offset` in `scope`.
+    pub(crate) fn locate(&self, codegen: &LLVMCodegen<'cx, 'g>, scope: DIScope<'cx>, file_id: usize, offset: usize) {
+        let (line, column) = self.line_col(file_id, offset);
+        let location = self.builder.create_debug_location(codegen.cx, line, column, scope, None);
+        codegen.b.set_current_debug_location(location);
+    }
+
+    /// Resolves the debug information, which has to be done before the module is verified or written.
+    pub(crate) fn finalize(&self) {
+        self.builder.finalize();
+    }
+
+    /// The user-facing line and column of the byte at `offset`, `0` if they are not known.
+    fn line_col(&self, file_id: usize, offset: usize) -> (u32, u32) {
+        let Ok(location) = self.sources.files.location(file_id, offset) else { return (0, 0) };
+        let line = u32::try_from(location.line_number).unwrap_or(0);
+        let column = u32::try_from(location.column_number).unwrap_or(0);
+        (line, column)
+    }
+
+    fn file(&self, file_id: usize) -> DIFile<'cx> {
+        self.files.get(&file_id).copied().unwrap_or_else(|| self.unit.get_file())
+    }
+
+    /// The description of `ty`, `None` for the types the backend cannot represent.
+    fn di_type(&self, codegen: &LLVMCodegen<'cx, 'g>, ty: &Ty) -> Option<DIType<'cx>> {
+        let basic = |name: &str, bits: u32, encoding: u32| {
+            self.builder.create_basic_type(name, u64::from(bits), encoding, DIFlags::ZERO).ok().map(|ty| ty.as_type())
+        };
+        match ty.peel() {
+            Ty::Void => basic("()", 0, DW_ATE_UNSIGNED),
+            Ty::Int(int) => {
+                let encoding = if crate::is_signed(*int) { DW_ATE_SIGNED } else { DW_ATE_UNSIGNED };
+                basic(int.name(), codegen.int_bits(*int), encoding)
+            }
+            Ty::Bool => basic("bool", 8, DW_ATE_BOOLEAN),
+            Ty::Char => basic("char", 32, DW_ATE_UTF),
+            Ty::Str => self.str_type,
+            Ty::Ref { ty: pointee, .. } => {
+                let pointee = self.di_type(codegen, pointee)?;
+                Some(self.pointer_type(codegen, &ty.to_string(), pointee))
+            }
+            Ty::Struct { def, .. } => self.structs.get(def).copied(),
+            _ => None,
+        }
+    }
+
+    fn pointer_type(&self, codegen: &LLVMCodegen<'cx, 'g>, name: &str, pointee: DIType<'cx>) -> DIType<'cx> {
+        let bits = codegen.target.ptr_width();
+        self.builder.create_pointer_type(name, pointee, u64::from(bits), bits, AddressSpace::default()).as_type()
+    }
+
+    /// `str`, as the struct of a pointer to its first byte and its length it is generated as.
+    fn create_str_type(&self, codegen: &LLVMCodegen<'cx, 'g>) -> Option<DIType<'cx>> {
+        let byte = self.di_type(codegen, &Ty::Int(IntTy::Ubyte))?;
+        let ptr = self.pointer_type(codegen, "&ubyte", byte);
+        let len = self.di_type(codegen, &Ty::Int(IntTy::Usize))?;
+        let llvm_type = codegen.str_type();
+        Some(self.composite(codegen, "str", llvm_type, &[("ptr", ptr), ("len", len)]))
+    }
+
+    /// Describes the struct `def` and the structs its fields are of, once all of them are.
+    fn create_struct_type(&mut self, codegen: &LLVMCodegen<'cx, 'g>, def: DefId, in_progress: &mut HashSet<DefId>) {
+        if self.structs.contains_key(&def) || !in_progress.insert(def) {
+            return;
+        }
+        let (Some(struct_def), Some(llvm_type)) = (codegen.gem.struct_def(def), codegen.structs.get(&def)) else {
+            return;
+        };
+
+        let mut fields = Vec::with_capacity(struct_def.fields.len());
+        for field in &struct_def.fields {
+            if let Ty::Struct { def: field_def, .. } = field.ty.peel() {
+                self.create_struct_type(codegen, *field_def, in_progress);
+            }
+            let Some(ty) = self.di_type(codegen, &field.ty) else { return };
+            fields.push((field.name.as_str(), ty));
+        }
+        let ty = self.composite(codegen, &struct_def.name, *llvm_type, &fields);
+        self.structs.insert(def, ty);
+    }
+
+    /// A struct laid out as `llvm_type`, with a member for each of `fields`.
+    fn composite(
+        &self,
+        codegen: &LLVMCodegen<'cx, 'g>,
+        name: &str,
+        llvm_type: StructType<'cx>,
+        fields: &[(&str, DIType<'cx>)],
+    ) -> DIType<'cx> {
+        let data = &codegen.target_data;
+        let file = self.unit.get_file();
+        let scope = self.unit.as_debug_info_scope();
+        let members: Vec<_> = fields
+            .iter()
+            .zip(0..)
+            .map(|((field, ty), index)| {
+                let offset = data.offset_of_element(&llvm_type, index).unwrap_or(0) * 8;
+                self.builder
+                    .create_member_type(
+                        scope,
+                        field,
+                        file,
+                        0,
+                        ty.get_size_in_bits(),
+                        ty.get_align_in_bits(),
+                        offset,
+                        DIFlags::PUBLIC,
+                        *ty,
+                    )
+                    .as_type()
+            })
+            .collect();
+        self.builder
+            .create_struct_type(
+                scope,
+                name,
+                file,
+                0,
+                data.get_bit_size(&llvm_type),
+                data.get_abi_alignment(&llvm_type) * 8,
+                DIFlags::PUBLIC,
+                None,
+                &members,
+                0,
+                None,
+                "",
+            )
+            .as_type()
+    }
+}
+
+/// The name of the file `file_id`, as it was loaded.
+fn file_name(sources: Sources, file_id: usize) -> String {
+    sources.files.name(file_id).unwrap_or_else(|_| "<unknown>".to_owned())
+}
+
+/// The name of the file at `path` and the directory it is in.
+fn split_path(path: &str) -> (String, String) {
+    let path = Path::new(path);
+    let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+    let directory = path.parent().map(|dir| dir.display().to_string()).unwrap_or_default();
+    (name, directory)
+}
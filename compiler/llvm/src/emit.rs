@@ -0,0 +1,61 @@
+//! `emit.rs` - writes a module to a file: as LLVM IR or bitcode, or compiled to assembly or an object file.
+use inkwell::module::Module;
+use inkwell::targets::{FileType, TargetMachine};
+use std::path::Path;
+
+/// A kind of file made from a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// `.ll`
+    LlvmIr,
+    /// `.bc`
+    Bitcode,
+    /// `.s`
+    Assembly,
+    /// `.o`
+    Object,
+}
+
+impl FileKind {
+    /// The extension files of the kind usually have.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::LlvmIr => "ll",
+            Self::Bitcode => "bc",
+            Self::Assembly => "s",
+            Self::Object => "o",
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("cannot write `{path}`: {message}")]
+pub struct EmitError {
+    pub path: String,
+    pub message: String,
+}
+
+/// Writes `module` to `path` as a file of `kind`, compiled by `machine` for an assembly or object file.
+///
+/// # Errors
+/// If the file cannot be written, or LLVM fails to compile the module.
+pub fn write(module: &Module, machine: &TargetMachine, kind: FileKind, path: &Path) -> Result<(), EmitError> {
+    let error = |message: String| EmitError { path: path.display().to_string(), message };
+    match kind {
+        FileKind::LlvmIr => module.print_to_file(path).map_err(|message| error(message.to_string())),
+        FileKind::Bitcode => {
+            if module.write_bitcode_to_path(path) {
+                Ok(())
+            } else {
+                Err(error("LLVM failed to write the bitcode".to_owned()))
+            }
+        }
+        FileKind::Assembly => {
+            machine.write_to_file(module, FileType::Assembly, path).map_err(|message| error(message.to_string()))
+        }
+        FileKind::Object => {
+            machine.write_to_file(module, FileType::Object, path).map_err(|message| error(message.to_string()))
+        }
+    }
+}
@@ -0,0 +1,507 @@
+//! `function.rs` - the code of a function, generated from its MIR body.
+use crate::{intrinsics, is_signed, CodegenError, LLVMCodegen};
+use inkwell::basic_block::BasicBlock;
+use inkwell::debug_info::{AsDIScope, DIScope};
+use inkwell::intrinsics::Intrinsic;
+use inkwell::types::BasicTypeEnum;
+use inkwell::values::{
+    AggregateValueEnum, BasicMetadataValueEnum, BasicValueEnum, FunctionValue, IntValue, PointerValue,
+};
+use inkwell::IntPredicate;
+use topaz_codegen::PANIC_STATUS;
+use topaz_hir::Callee;
+use topaz_mir::{
+    BinOp, BlockId, Body, ConstValue, Constant, Local, Operand, Place, Rvalue, StatementKind, TerminatorKind, UnOp,
+};
+use topaz_type_checker::ty::{IntTy, Ty};
+
+/// Generates the code of `body` into `function`, which was declared for it.
+pub(crate) fn define<'cx>(
+    codegen: &LLVMCodegen<'cx, '_>,
+    body: &Body,
+    function: FunctionValue<'cx>,
+) -> Result<(), CodegenError> {
+    let start = codegen.cx.append_basic_block(function, "start");
+    let blocks =
+        body.block_ids().map(|block| codegen.cx.append_basic_block(function, &format!("bb{}", block.0))).collect();
+    codegen.b.position_at_end(start);
+    let scope = codegen.debug.as_ref().map(|debug| debug.function(codegen, body, function).as_debug_info_scope());
+
+    let mut locals = Vec::with_capacity(body.locals.len());
+    for (index, decl) in body.locals.iter().enumerate() {
+        let ty = codegen.basic_type(&decl.ty).ok_or_else(|| unsupported(body, &decl.ty))?;
+        let name = decl.name.clone().unwrap_or_else(|| format!("_{index}"));
+        locals.push(codegen.b.build_alloca(ty, &name));
+    }
+
+    let lowering = FnLowering { codegen, body, function, scope, blocks, locals };
+    lowering.locate(body.span.start);
+    if let (Some(debug), Some(scope)) = (&codegen.debug, scope) {
+        debug.declare_locals(codegen, body, scope, &lowering.locals);
+    }
+    for (param, arg) in function.get_param_iter().zip(body.args()) {
+        codegen.b.build_store(lowering.locals[arg.index()], param);
+    }
+
+    if let Some(first) = lowering.blocks.first() {
+        codegen.b.build_unconditional_branch(*first);
+        for block in body.block_ids() {
+            lowering.lower_block(block)?;
+        }
+    } else {
+        codegen.b.build_unreachable();
+    }
+    // the locations are of this function, the code generated next is not
+    codegen.b.unset_current_debug_location();
+    Ok(())
+}
+
+fn unsupported(body: &Body, ty: &Ty) -> CodegenError {
+    CodegenError::UnsupportedType { ty: ty.to_string(), function: body.name.clone() }
+}
+
+struct FnLowering<'a, 'cx, 'g> {
+    codegen: &'a LLVMCodegen<'cx, 'g>,
+    body: &'a Body,
+    function: FunctionValue<'cx>,
+    /// The subprogram of the function, if there is debug information
+    scope: Option<DIScope<'cx>>,
+    /// The block of every MIR block
+    blocks: Vec<BasicBlock<'cx>>,
+    /// The stack slot of every local
+    locals: Vec<PointerValue<'cx>>,
+}
+
+impl<'cx> FnLowering<'_, 'cx, '_> {
+    fn lower_block(&self, block: BlockId) -> Result<(), CodegenError> {
+        let b = &self.codegen.b;
+        b.position_at_end(self.blocks[block.0]);
+        let data = self.body.block(block);
+        for statement in &data.statements {
+            if let StatementKind::Assign(place, rvalue) = &statement.kind {
+                self.locate(statement.span.start);
+                self.assign(place, rvalue)?;
+            }
+        }
+
+        self.locate(data.terminator.span.start);
+        match &data.terminator.kind {
+            TerminatorKind::Goto(target) => {
+                b.build_unconditional_branch(self.edge(block, *target)?);
+            }
+            TerminatorKind::If { cond, then, otherwise } => {
+                let (cond, _) = self.operand(cond)?;
+                let (then, otherwise) = (self.edge(block, *then)?, self.edge(block, *otherwise)?);
+                b.build_conditional_branch(cond.into_int_value(), then, otherwise);
+            }
+            TerminatorKind::Switch { discr, targets } => {
+                let (discr, _) = self.operand(discr)?;
+                let discr = discr.into_int_value();
+                // the discriminant is always in range, so the last target is the one of every other value
+                if let Some((last, rest)) = targets.split_last() {
+                    let mut cases = Vec::with_capacity(rest.len());
+                    for (index, target) in rest.iter().enumerate() {
+                        cases.push((discr.get_type().const_int(index as u64, false), self.edge(block, *target)?));
+                    }
+                    b.build_switch(discr, self.edge(block, *last)?, &cases);
+                } else {
+                    b.build_unreachable();
+                }
+            }
+            TerminatorKind::Call { callee, args, dest, target, .. } => {
+                let args = args.iter().map(|arg| self.operand(arg)).collect::<Result<Vec<_>, _>>()?;
+                let value = match callee {
+                    Callee::Fn { name, .. } | Callee::Method { name, .. } => {
+                        let index = self.codegen.gem.callee_index(callee).ok_or_else(|| {
+                            CodegenError::UnknownFunction { callee: name.clone(), function: self.body.name.clone() }
+                        })?;
+                        let args: Vec<BasicMetadataValueEnum> = args.into_iter().map(|(arg, _)| arg.into()).collect();
+                        b.build_call(self.codegen.bodies[index], &args, "").try_as_basic_value().left()
+                    }
+                    Callee::External(path) => intrinsics::call(self.codegen, &self.body.name, path, &args)?,
+                };
+                if let Some(value) = value {
+                    let (dest, _) = self.place(dest)?;
+                    b.build_store(dest, value);
+                }
+                b.build_unconditional_branch(self.edge(block, *target)?);
+            }
+            TerminatorKind::Return => {
+                if self.function.get_type().get_return_type().is_none() {
+                    b.build_return(None);
+                } else {
+                    let (value, _) = self.operand(&Operand::Copy(Local::RETURN.into()))?;
+                    b.build_return(Some(&value));
+                }
+            }
+            TerminatorKind::Unreachable => {
+                b.build_unreachable();
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the code generated next belong to the byte at `offset` in the source of the function.
+    fn locate(&self, offset: usize) {
+        if let (Some(debug), Some(scope)) = (&self.codegen.debug, self.scope) {
+            debug.locate(self.codegen, scope, self.body.file_id, offset);
+        }
+    }
+
+    /// The block to jump to to go from `from` to `to`: `to` itself, or a block assigning its phis if it has any.
+    fn edge(&self, from: BlockId, to: BlockId) -> Result<BasicBlock<'cx>, CodegenError> {
+        let phis = &self.body.block(to).phis;
+        if phis.is_empty() {
+            return Ok(self.blocks[to.0]);
+        }
+
+        let b = &self.codegen.b;
+        let current = b.get_insert_block();
+        let edge = self.codegen.cx.append_basic_block(self.function, &format!("bb{}_to_bb{}", from.0, to.0));
+        b.position_at_end(edge);
+        // the phis are assigned at once, so every value is read before any of them is written
+        let mut values = Vec::with_capacity(phis.len());
+        for phi in phis {
+            if let Some((_, operand)) = phi.args.iter().find(|(block, _)| *block == from) {
+                values.push((phi.dest, self.operand(operand)?.0));
+            }
+        }
+        for (dest, value) in values {
+            b.build_store(self.locals[dest.index()], value);
+        }
+        b.build_unconditional_branch(self.blocks[to.0]);
+        if let Some(current) = current {
+            b.position_at_end(current);
+        }
+        Ok(edge)
+    }
+
+    fn assign(&self, place: &Place, rvalue: &Rvalue) -> Result<(), CodegenError> {
+        let b = &self.codegen.b;
+        match rvalue {
+            Rvalue::Use(operand) => {
+                let (value, _) = self.operand(operand)?;
+                let (dest, _) = self.place(place)?;
+                b.build_store(dest, value);
+            }
+            Rvalue::Ref { place: borrowed, .. } => {
+                let (borrowed, _) = self.place(borrowed)?;
+                let (dest, _) = self.place(place)?;
+                b.build_store(dest, borrowed);
+            }
+            Rvalue::Aggregate { def, operands } => {
+                let (dest, ty) = self.place(place)?;
+                let struct_ty = *self.codegen.structs.get(def).ok_or_else(|| unsupported(self.body, &ty))?;
+                for (index, operand) in operands.iter().enumerate() {
+                    let (value, _) = self.operand(operand)?;
+                    let field = b
+                        .build_struct_gep(struct_ty, dest, field_index(index), "")
+                        .map_err(|_| unsupported(self.body, &ty))?;
+                    b.build_store(field, value);
+                }
+            }
+            Rvalue::Variant { def, index, operands } => {
+                let (dest, ty) = self.place(place)?;
+                let enum_ty = *self.codegen.enums.get(def).ok_or_else(|| unsupported(self.body, &ty))?;
+                let tag = b.build_struct_gep(enum_ty, dest, 0, "").map_err(|_| unsupported(self.body, &ty))?;
+                b.build_store(tag, self.codegen.int_type(IntTy::Usize).const_int(*index as u64, false));
+                for (field, operand) in operands.iter().enumerate() {
+                    let (value, _) = self.operand(operand)?;
+                    b.build_store(self.payload_field(dest, &ty, *index, field)?, value);
+                }
+            }
+            Rvalue::Discriminant(operand) => {
+                let (ptr, ty) = self.operand_place(operand)?;
+                let Ty::Enum { def, .. } = ty.peel() else { return Err(unsupported(self.body, &ty)) };
+                let enum_ty = *self.codegen.enums.get(def).ok_or_else(|| unsupported(self.body, &ty))?;
+                let tag = b.build_struct_gep(enum_ty, ptr, 0, "").map_err(|_| unsupported(self.body, &ty))?;
+                let value = b.build_load(self.codegen.int_type(IntTy::Usize), tag, "");
+                let (dest, _) = self.place(place)?;
+                b.build_store(dest, value);
+            }
+            Rvalue::Payload { operand, variant, field } => {
+                let (ptr, ty) = self.operand_place(operand)?;
+                let field_ptr = self.payload_field(ptr, &ty, *variant, *field)?;
+                let (dest, dest_ty) = self.place(place)?;
+                let llvm_ty = self.codegen.basic_type(&dest_ty).ok_or_else(|| unsupported(self.body, &dest_ty))?;
+                b.build_store(dest, b.build_load(llvm_ty, field_ptr, ""));
+            }
+            Rvalue::Array(operands) => {
+                let (dest, ty) = self.place(place)?;
+                let array_ty = self.codegen.basic_type(&ty).ok_or_else(|| unsupported(self.body, &ty))?;
+                let mut array: AggregateValueEnum = array_ty.into_array_type().get_undef().into();
+                for (index, operand) in operands.iter().enumerate() {
+                    let (value, _) = self.operand(operand)?;
+                    array = b
+                        .build_insert_value(array, value, field_index(index), "")
+                        .ok_or_else(|| CodegenError::Invalid(format!("an array of `{ty}` has no element {index}")))?;
+                }
+                b.build_store(dest, array.into_array_value());
+            }
+            Rvalue::Index { operands: [array, index] } => {
+                let (ptr, array_ty) = self.operand_place(array)?;
+                let (index, index_ty) = self.operand(index)?;
+                let element = self.element(ptr, &array_ty, index.into_int_value(), &index_ty)?;
+                let (dest, ty) = self.place(place)?;
+                let llvm_ty = self.codegen.basic_type(&ty).ok_or_else(|| unsupported(self.body, &ty))?;
+                b.build_store(dest, b.build_load(llvm_ty, element, ""));
+            }
+            Rvalue::BinaryOp { op, operands: [lhs, rhs] } => {
+                let (lhs, ty) = self.operand(lhs)?;
+                let (rhs, _) = self.operand(rhs)?;
+                let value = self.binary_op(*op, &ty, lhs.into_int_value(), rhs.into_int_value())?;
+                let (dest, _) = self.place(place)?;
+                b.build_store(dest, value);
+            }
+            Rvalue::UnaryOp { op, operand } => {
+                let (operand, _) = self.operand(operand)?;
+                let operand = operand.into_int_value();
+                let value = match op {
+                    UnOp::Neg => {
+                        self.overflowing("llvm.ssub.with.overflow", operand.get_type().const_zero(), operand)?
+                    }
+                    UnOp::Not => b.build_not(operand, ""),
+                };
+                let (dest, _) = self.place(place)?;
+                b.build_store(dest, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// `lhs op rhs`, where both are of type `ty`: a `bool`, a `char` or an integer. Arithmetic panics if it
+    /// overflows or divides by zero.
+    fn binary_op(
+        &self,
+        op: BinOp,
+        ty: &Ty,
+        lhs: IntValue<'cx>,
+        rhs: IntValue<'cx>,
+    ) -> Result<IntValue<'cx>, CodegenError> {
+        let b = &self.codegen.b;
+        // `bool` and `char` are unsigned
+        let signed = matches!(ty.peel(), Ty::Int(int) if is_signed(*int));
+        let (name, predicate) = match (op, signed) {
+            (BinOp::Add, true) => ("llvm.sadd.with.overflow", None),
+            (BinOp::Add, false) => ("llvm.uadd.with.overflow", None),
+            (BinOp::Sub, true) => ("llvm.ssub.with.overflow", None),
+            (BinOp::Sub, false) => ("llvm.usub.with.overflow", None),
+            (BinOp::Mul, true) => ("llvm.smul.with.overflow", None),
+            (BinOp::Mul, false) => ("llvm.umul.with.overflow", None),
+            (BinOp::Div | BinOp::Rem, _) => return self.divide(op, signed, lhs, rhs),
+            (BinOp::Eq, _) => ("", Some(IntPredicate::EQ)),
+            (BinOp::Ne, _) => ("", Some(IntPredicate::NE)),
+            (BinOp::Lt, true) => ("", Some(IntPredicate::SLT)),
+            (BinOp::Lt, false) => ("", Some(IntPredicate::ULT)),
+            (BinOp::Le, true) => ("", Some(IntPredicate::SLE)),
+            (BinOp::Le, false) => ("", Some(IntPredicate::ULE)),
+            (BinOp::Gt, true) => ("", Some(IntPredicate::SGT)),
+            (BinOp::Gt, false) => ("", Some(IntPredicate::UGT)),
+            (BinOp::Ge, true) => ("", Some(IntPredicate::SGE)),
+            (BinOp::Ge, false) => ("", Some(IntPredicate::UGE)),
+        };
+        match predicate {
+            Some(predicate) => Ok(b.build_int_compare(predicate, lhs, rhs, "")),
+            None => self.overflowing(name, lhs, rhs),
+        }
+    }
+
+    /// `lhs / rhs` or `lhs % rhs`, panicking if `rhs` is zero or the quotient overflows.
+    fn divide(
+        &self,
+        op: BinOp,
+        signed: bool,
+        lhs: IntValue<'cx>,
+        rhs: IntValue<'cx>,
+    ) -> Result<IntValue<'cx>, CodegenError> {
+        let b = &self.codegen.b;
+        let int_type = lhs.get_type();
+        self.check(b.build_int_compare(IntPredicate::EQ, rhs, int_type.const_zero(), ""));
+        if !signed {
+            return Ok(if op == BinOp::Div {
+                b.build_int_unsigned_div(lhs, rhs, "")
+            } else {
+                b.build_int_unsigned_rem(lhs, rhs, "")
+            });
+        }
+
+        let minus_one = int_type.const_all_ones();
+        let by_minus_one = b.build_int_compare(IntPredicate::EQ, rhs, minus_one, "");
+        if op == BinOp::Div {
+            // the quotient of the smallest value by `-1` is one more than the largest
+            let negated = self.overflow_call("llvm.ssub.with.overflow", int_type.const_zero(), lhs)?;
+            self.check(b.build_and(by_minus_one, negated.1, ""));
+            Ok(b.build_int_signed_div(lhs, rhs, ""))
+        } else {
+            // the remainder by `-1` is the one by `1`, which cannot overflow
+            let rhs = b.build_select(by_minus_one, int_type.const_int(1, false), rhs, "").into_int_value();
+            Ok(b.build_int_signed_rem(lhs, rhs, ""))
+        }
+    }
+
+    /// The result of the overflow checking intrinsic `name` on `lhs` and `rhs`, panicking if it overflowed.
+    fn overflowing(&self, name: &str, lhs: IntValue<'cx>, rhs: IntValue<'cx>) -> Result<IntValue<'cx>, CodegenError> {
+        let (value, overflowed) = self.overflow_call(name, lhs, rhs)?;
+        self.check(overflowed);
+        Ok(value)
+    }
+
+    /// The result of the overflow checking intrinsic `name` on `lhs` and `rhs`, and whether it overflowed.
+    fn overflow_call(
+        &self,
+        name: &str,
+        lhs: IntValue<'cx>,
+        rhs: IntValue<'cx>,
+    ) -> Result<(IntValue<'cx>, IntValue<'cx>), CodegenError> {
+        let b = &self.codegen.b;
+        let intrinsic = self.intrinsic(name, &[lhs.get_type().into()])?;
+        let result = b.build_call(intrinsic, &[lhs.into(), rhs.into()], "").try_as_basic_value().left();
+        let result = result.ok_or_else(|| no_intrinsic(name))?.into_struct_value();
+        let value = b.build_extract_value(result, 0, "").ok_or_else(|| no_intrinsic(name))?;
+        let overflowed = b.build_extract_value(result, 1, "").ok_or_else(|| no_intrinsic(name))?;
+        Ok((value.into_int_value(), overflowed.into_int_value()))
+    }
+
+    /// Panics if `failed` holds, exiting with the status of [`PANIC_STATUS`], and goes on in a block of its own
+    /// otherwise.
+    fn check(&self, failed: IntValue<'cx>) {
+        let (cx, b) = (self.codegen.cx, &self.codegen.b);
+        let panic = cx.append_basic_block(self.function, "panic");
+        let checked = cx.append_basic_block(self.function, "checked");
+        b.build_conditional_branch(failed, panic, checked);
+        b.position_at_end(panic);
+        let status = cx.i32_type().const_int(PANIC_STATUS.unsigned_abs().into(), false);
+        b.build_call(intrinsics::exit(self.codegen), &[status.into()], "");
+        b.build_unreachable();
+        b.position_at_end(checked);
+    }
+
+    /// The declaration of the LLVM intrinsic `name`, for the overloaded `types`.
+    fn intrinsic(&self, name: &str, types: &[BasicTypeEnum<'cx>]) -> Result<FunctionValue<'cx>, CodegenError> {
+        Intrinsic::find(name)
+            .and_then(|intrinsic| intrinsic.get_declaration(&self.codegen.m, types))
+            .ok_or_else(|| no_intrinsic(name))
+    }
+
+    /// Where `place` is stored, with its type.
+    fn place(&self, place: &Place) -> Result<(PointerValue<'cx>, Ty), CodegenError> {
+        let b = &self.codegen.b;
+        let mut ptr = self.locals[place.local.index()];
+        let mut ty = self.body.local(place.local).ty.clone();
+        for field in &place.projection {
+            while let Ty::Ref { ty: pointee, .. } = ty.peel() {
+                let pointee = (**pointee).clone();
+                ptr = b.build_load(self.codegen.ptr_type(), ptr, "").into_pointer_value();
+                ty = pointee;
+            }
+            let Ty::Struct { def, .. } = ty.peel() else { return Err(unsupported(self.body, &ty)) };
+            let struct_ty = self.codegen.structs.get(def);
+            let field_def = self.codegen.gem.struct_def(*def).and_then(|struct_def| struct_def.fields.get(*field));
+            let (Some(struct_ty), Some(field_def)) = (struct_ty, field_def) else {
+                return Err(unsupported(self.body, &ty));
+            };
+            ptr = b
+                .build_struct_gep(*struct_ty, ptr, field_index(*field), "")
+                .map_err(|_| unsupported(self.body, &ty))?;
+            ty = field_def.ty.clone();
+        }
+        Ok((ptr, ty))
+    }
+
+    /// Where the place `operand` reads is stored, with its type. Enums and arrays are never constants.
+    fn operand_place(&self, operand: &Operand) -> Result<(PointerValue<'cx>, Ty), CodegenError> {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.place(place),
+            Operand::Const(constant) => Err(unsupported(self.body, &constant.ty)),
+        }
+    }
+
+    /// Where the field `field` of the variant `variant` is stored in the enum at `ptr`, of type `ty`.
+    fn payload_field(
+        &self,
+        ptr: PointerValue<'cx>,
+        ty: &Ty,
+        variant: usize,
+        field: usize,
+    ) -> Result<PointerValue<'cx>, CodegenError> {
+        let b = &self.codegen.b;
+        let Ty::Enum { def, .. } = ty.peel() else { return Err(unsupported(self.body, ty)) };
+        let enum_ty = self.codegen.enums.get(def);
+        let variant_ty = self.codegen.variants.get(def).and_then(|variants| variants.get(variant));
+        let (Some(enum_ty), Some(variant_ty)) = (enum_ty, variant_ty) else { return Err(unsupported(self.body, ty)) };
+        // the fields of the variant are laid out in the bytes after the tag
+        let payload = b.build_struct_gep(*enum_ty, ptr, 1, "").map_err(|_| unsupported(self.body, ty))?;
+        b.build_struct_gep(*variant_ty, payload, field_index(field), "").map_err(|_| unsupported(self.body, ty))
+    }
+
+    /// Where the element `index` of the array at `ptr`, of type `ty`, is stored, panicking if it is out of bounds.
+    fn element(
+        &self,
+        ptr: PointerValue<'cx>,
+        ty: &Ty,
+        index: IntValue<'cx>,
+        index_ty: &Ty,
+    ) -> Result<PointerValue<'cx>, CodegenError> {
+        let b = &self.codegen.b;
+        let Ty::Array { len, .. } = ty.peel() else { return Err(unsupported(self.body, ty)) };
+        let array_ty = self.codegen.basic_type(ty).ok_or_else(|| unsupported(self.body, ty))?;
+        // compared as wide as a `usize` at least, where a negative index is larger than any length
+        let signed = matches!(index_ty.peel(), Ty::Int(int) if is_signed(*int));
+        let usize_ty = self.codegen.int_type(IntTy::Usize);
+        let width = index.get_type().get_bit_width().max(usize_ty.get_bit_width());
+        let wide_ty = self.codegen.cx.custom_width_int_type(width);
+        let index = if signed {
+            b.build_int_s_extend_or_bit_cast(index, wide_ty, "")
+        } else {
+            b.build_int_z_extend_or_bit_cast(index, wide_ty, "")
+        };
+        self.check(b.build_int_compare(IntPredicate::UGE, index, wide_ty.const_int(*len as u64, false), ""));
+        let zero = wide_ty.const_zero();
+        // SAFETY: the index was checked to be in bounds
+        Ok(unsafe { b.build_in_bounds_gep(array_ty, ptr, &[zero, index], "") })
+    }
+
+    /// The value of `operand`, with its type.
+    fn operand(&self, operand: &Operand) -> Result<(BasicValueEnum<'cx>, Ty), CodegenError> {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => {
+                let (ptr, ty) = self.place(place)?;
+                let llvm_ty = self.codegen.basic_type(&ty).ok_or_else(|| unsupported(self.body, &ty))?;
+                Ok((self.codegen.b.build_load(llvm_ty, ptr, ""), ty))
+            }
+            Operand::Const(constant) => Ok((self.constant(constant)?, constant.ty.clone())),
+        }
+    }
+
+    fn constant(&self, constant: &Constant) -> Result<BasicValueEnum<'cx>, CodegenError> {
+        let cx = self.codegen.cx;
+        Ok(match &constant.value {
+            ConstValue::Int(value) => {
+                let int = match constant.ty.peel() {
+                    Ty::Int(int) => *int,
+                    _ => IntTy::DEFAULT,
+                };
+                // the bits of the value, lowest word first, cut to the width of the type
+                #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+                let words = [*value as u64, (*value >> 64) as u64];
+                self.codegen.int_type(int).const_int_arbitrary_precision(&words).into()
+            }
+            ConstValue::Bool(value) => cx.bool_type().const_int(u64::from(*value), false).into(),
+            ConstValue::Char(value) => cx.i32_type().const_int(u64::from(u32::from(*value)), false).into(),
+            ConstValue::Str(value) => self.codegen.const_str(value).into(),
+            ConstValue::Void => cx.const_struct(&[], false).into(),
+            ConstValue::Named(name) => {
+                return Err(CodegenError::UnknownConstant { name: name.clone(), function: self.body.name.clone() })
+            }
+        })
+    }
+}
+
+fn no_intrinsic(name: &str) -> CodegenError {
+    CodegenError::Invalid(format!("LLVM has no intrinsic `{name}`"))
+}
+
+/// The index of a field, as LLVM takes it. Structs do not have anywhere near `u32::MAX` fields.
+fn field_index(index: usize) -> u32 {
+    u32::try_from(index).unwrap_or(u32::MAX)
+}
@@ -0,0 +1,140 @@
+//! `intrinsics.rs` - the functions of the standard library the backend generates the code of itself.
+//!
+//! The standard library only declares them, e.g. `println` in `tstd/src/io.tp`, so a call to one is a call
+//! to a function outside of the gem, by the path it resolved to. They are built on the C library,
+//! which every program is linked with.
+use crate::{is_signed, CodegenError, LLVMCodegen};
+use inkwell::module::Linkage;
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue};
+use topaz_hir::FORMAT;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+/// Generates the call to the function of the standard library at `path` made from `function`,
+/// returning its result.
+pub(crate) fn call<'cx>(
+    codegen: &LLVMCodegen<'cx, '_>,
+    function: &str,
+    path: &str,
+    args: &[(BasicValueEnum<'cx>, Ty)],
+) -> Result<Option<BasicValueEnum<'cx>>, CodegenError> {
+    match (path, args) {
+        ("std::io::println", [(value, ty)]) => {
+            println(codegen, *value, ty)?;
+            Ok(Some(codegen.cx.const_struct(&[], false).into()))
+        }
+        (FORMAT, args) => format(codegen, args).map(Some),
+        _ => Err(CodegenError::UnknownFunction { callee: path.to_owned(), function: function.to_owned() }),
+    }
+}
+
+/// Prints `value` and a new line with `printf`.
+fn println<'cx>(codegen: &LLVMCodegen<'cx, '_>, value: BasicValueEnum<'cx>, ty: &Ty) -> Result<(), CodegenError> {
+    let b = &codegen.b;
+    let (format, args) = conversion(codegen, value, ty)?;
+    let format = b.build_global_string_ptr(&format!("{format}\n"), "format").as_pointer_value();
+    let args: Vec<BasicMetadataValueEnum> = std::iter::once(format.into()).chain(args).collect();
+    b.build_call(printf(codegen), &args, "");
+    Ok(())
+}
+
+/// The string of `args` one after the other, in bytes from `malloc`, which are never freed. `snprintf` is
+/// called twice: to count the bytes, then to write them.
+fn format<'cx>(
+    codegen: &LLVMCodegen<'cx, '_>,
+    args: &[(BasicValueEnum<'cx>, Ty)],
+) -> Result<BasicValueEnum<'cx>, CodegenError> {
+    let b = &codegen.b;
+    let mut format = String::new();
+    let mut values = Vec::with_capacity(args.len());
+    for (value, ty) in args {
+        let (conversion, taken) = conversion(codegen, *value, ty)?;
+        format.push_str(conversion);
+        values.extend(taken);
+    }
+    let format = b.build_global_string_ptr(&format, "format").as_pointer_value();
+    let usize_type = codegen.int_type(IntTy::Usize);
+    let invalid = || CodegenError::Invalid("`snprintf` returns an `int`".to_owned());
+
+    let mut args: Vec<BasicMetadataValueEnum> =
+        vec![codegen.ptr_type().const_null().into(), usize_type.const_zero().into(), format.into()];
+    args.extend(&values);
+    let len = b.build_call(snprintf(codegen), &args, "len").try_as_basic_value().left().ok_or_else(invalid)?;
+    let len = b.build_int_s_extend_or_bit_cast(len.into_int_value(), usize_type, "");
+    let size = b.build_int_add(len, usize_type.const_int(1, false), "size");
+    let ptr = b.build_call(malloc(codegen), &[size.into()], "ptr").try_as_basic_value().left().ok_or_else(invalid)?;
+
+    let mut args: Vec<BasicMetadataValueEnum> = vec![ptr.into(), size.into(), format.into()];
+    args.extend(values);
+    b.build_call(snprintf(codegen), &args, "");
+
+    let text = b.build_insert_value(codegen.str_type().get_undef(), ptr, 0, "").ok_or_else(invalid)?;
+    let text = b.build_insert_value(text, len, 1, "").ok_or_else(invalid)?;
+    Ok(text.into_struct_value().into())
+}
+
+/// The conversion printing `value` with `printf`, and the arguments it takes.
+fn conversion<'cx>(
+    codegen: &LLVMCodegen<'cx, '_>,
+    value: BasicValueEnum<'cx>,
+    ty: &Ty,
+) -> Result<(&'static str, Vec<BasicMetadataValueEnum<'cx>>), CodegenError> {
+    let b = &codegen.b;
+    let unsupported = || CodegenError::UnsupportedPrint(ty.to_string());
+    Ok(match ty.peel() {
+        Ty::Str => {
+            let text = value.into_struct_value();
+            let bytes = b.build_extract_value(text, 0, "bytes").ok_or_else(unsupported)?;
+            let len = b.build_extract_value(text, 1, "len").ok_or_else(unsupported)?;
+            // the precision of `%.*s` is an `int`
+            let len = b.build_int_truncate_or_bit_cast(len.into_int_value(), codegen.cx.i32_type(), "");
+            ("%.*s", vec![len.into(), bytes.into()])
+        }
+        Ty::Int(int) if codegen.int_bits(*int) <= 64 => {
+            let (value, i64_type) = (value.into_int_value(), codegen.cx.i64_type());
+            if is_signed(*int) {
+                ("%lld", vec![b.build_int_s_extend_or_bit_cast(value, i64_type, "").into()])
+            } else {
+                ("%llu", vec![b.build_int_z_extend_or_bit_cast(value, i64_type, "").into()])
+            }
+        }
+        Ty::Bool => {
+            let yes = b.build_global_string_ptr("true", "true").as_pointer_value();
+            let no = b.build_global_string_ptr("false", "false").as_pointer_value();
+            ("%s", vec![b.build_select(value.into_int_value(), yes, no, "").into()])
+        }
+        _ => return Err(unsupported()),
+    })
+}
+
+/// `int printf(const char *format, ...)`, declared the first time it is called.
+fn printf<'cx>(codegen: &LLVMCodegen<'cx, '_>) -> FunctionValue<'cx> {
+    codegen.m.get_function("printf").unwrap_or_else(|| {
+        let fn_type = codegen.cx.i32_type().fn_type(&[codegen.ptr_type().into()], true);
+        codegen.m.add_function("printf", fn_type, Some(Linkage::External))
+    })
+}
+
+/// `int snprintf(char *buffer, size_t size, const char *format, ...)`, declared the first time it is called.
+fn snprintf<'cx>(codegen: &LLVMCodegen<'cx, '_>) -> FunctionValue<'cx> {
+    codegen.m.get_function("snprintf").unwrap_or_else(|| {
+        let params = [codegen.ptr_type().into(), codegen.int_type(IntTy::Usize).into(), codegen.ptr_type().into()];
+        let fn_type = codegen.cx.i32_type().fn_type(&params, true);
+        codegen.m.add_function("snprintf", fn_type, Some(Linkage::External))
+    })
+}
+
+/// `void *malloc(size_t size)`, declared the first time it is called.
+fn malloc<'cx>(codegen: &LLVMCodegen<'cx, '_>) -> FunctionValue<'cx> {
+    codegen.m.get_function("malloc").unwrap_or_else(|| {
+        let fn_type = codegen.ptr_type().fn_type(&[codegen.int_type(IntTy::Usize).into()], false);
+        codegen.m.add_function("malloc", fn_type, Some(Linkage::External))
+    })
+}
+
+/// `void exit(int status)`, declared the first time it is called.
+pub(crate) fn exit<'cx>(codegen: &LLVMCodegen<'cx, '_>) -> FunctionValue<'cx> {
+    codegen.m.get_function("exit").unwrap_or_else(|| {
+        let fn_type = codegen.cx.void_type().fn_type(&[codegen.cx.i32_type().into()], false);
+        codegen.m.add_function("exit", fn_type, Some(Linkage::External))
+    })
+}
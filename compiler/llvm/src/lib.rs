@@ -1,77 +1,426 @@
-#![feature(default_free_fn)]
-#![feature(decl_macro)]
 #![warn(clippy::pedantic, clippy::nursery, clippy::unwrap_used, clippy::expect_used)]
 
+//! Generates LLVM IR for the MIR of a gem.
+//!
+//! Every local of a function gets a stack slot, made by an `alloca` in a `start` block that then jumps to `bb0`,
+//! and every MIR block becomes an LLVM block of the same name. Locals are read and written with loads and stores,
+//! which LLVM's `mem2reg` turns back into registers. The phis of a block are assigned on the edges into it: a
+//! jump to a block with phis goes through a block of its own, storing the value for that edge into every phi.
+//! Arithmetic is checked with the `*.with.overflow` intrinsics: a program that overflows or divides by zero
+//! panics, exiting with [`topaz_codegen::PANIC_STATUS`], and so does indexing an array out of bounds.
+//!
+//! An enum is a struct of the index of its variant, a `usize`, and of bytes enough for the fields of any variant,
+//! as an array of integers as aligned as the most aligned of them. The fields of a variant are read and written
+//! through a struct of their own, at the address of those bytes.
+//!
+//! The functions of the standard library are generated by the backend itself, see `intrinsics.rs`, and the debug
+//! information is generated along with the code when the sources of the gem are given, see `debug.rs`.
+
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::module::Module;
-use inkwell::types::{AnyType, BasicType, BasicTypeEnum, FunctionType, IntType};
-use std::default::default;
-use topaz_ast::item::func::Func;
-use topaz_ast::token::Arrow;
-use topaz_ast::types::{PrimitiveType, Type};
-use topaz_ast::visit::Visit;
-use topaz_ast::Token;
+use inkwell::targets::{RelocMode, TargetData};
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, IntType, PointerType, StructType};
+use inkwell::values::{FunctionValue, StructValue};
+use inkwell::AddressSpace;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use topaz_mir::{Local, MirGem};
+use topaz_resolve::def::DefId;
+use topaz_type_checker::ty::{IntTy, Ty};
 
+mod debug;
+pub mod emit;
+mod function;
+mod intrinsics;
 pub mod target;
 
-struct LLVMCodegen<'cx> {
-    cx: Context,
-    b: Builder<'cx>,
-    m: Module<'cx>,
-    target: target::Target
+pub use debug::Sources;
+pub use emit::FileKind;
+pub use topaz_codegen::link::{self, GemType};
+
+/// What keeps a gem from being compiled to LLVM IR.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    #[error("cannot generate code for a value of type `{ty}` in `{function}`")]
+    UnsupportedType { ty: String, function: String },
+    #[error("cannot call `{callee}` from `{function}`, there is no code for it")]
+    UnknownFunction { callee: String, function: String },
+    #[error("the value of the constant `{name}` used in `{function}` is not known")]
+    UnknownConstant { name: String, function: String },
+    #[error("a value of type `{0}` cannot be printed or interpolated into a string")]
+    UnsupportedPrint(String),
+    #[error("there is no function to start the program with")]
+    NoEntryPoint,
+    #[error("LLVM rejected the generated module: {0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Target(#[from] target::TargetError),
 }
 
-impl Visit for LLVMCodegen {
-    fn visit_func(&mut self, func: &Func) {
-        let func = self.m.add_function(
-            func.2.value(),
-            make_type(&func.4, &self.cx, self.target.ptr_width()),
-            None,
-        );
+/// Generates the module for `gem`, with code for `target`, and checks it with [`Module::verify`].
+///
+/// If there is an `entry` function, the module is a program: it gets the `main` function the C runtime
+/// starts it with, which calls `entry` and exits with `0`. With the `sources` of the gem, it gets DWARF debug
+/// information too.
+///
+/// # Errors
+/// If the gem uses something the backend has no code for, e.g. a `maybe` type, or LLVM cannot generate code for
+/// `target`.
+pub fn codegen_gem<'cx>(
+    cx: &'cx Context,
+    name: &str,
+    gem: &MirGem,
+    target: &target::Target,
+    entry: Option<DefId>,
+    sources: Option<Sources<'_>>,
+) -> Result<Module<'cx>, CodegenError> {
+    let mut codegen = LLVMCodegen::new(cx, name, gem, target, target.target_data()?);
+    codegen.m.set_triple(&target.llvm_triple());
+    codegen.m.set_data_layout(&codegen.target_data.get_data_layout());
+    codegen.declare_types()?;
+    codegen.debug = sources.map(|sources| debug::DebugInfo::new(&codegen, sources));
+    // declared first, so that a function of the gem called `main` gets another name
+    let c_main = entry.map(|_| codegen.m.add_function("main", cx.i32_type().fn_type(&[], false), None));
+    codegen.declare_functions()?;
+    for (body, function) in gem.bodies.iter().zip(&codegen.bodies) {
+        function::define(&codegen, body, *function)?;
+    }
+    // the wrapper has no code of the gem, so no debug information
+    codegen.b.unset_current_debug_location();
+    if let (Some(c_main), Some(entry)) = (c_main, entry) {
+        let entry = *codegen.functions.get(&entry).ok_or(CodegenError::NoEntryPoint)?;
+        codegen.b.position_at_end(cx.append_basic_block(c_main, "start"));
+        codegen.b.build_call(entry, &[], "");
+        codegen.b.build_return(Some(&cx.i32_type().const_zero()));
+    }
+    if let Some(debug) = &codegen.debug {
+        debug.finalize();
+    }
+    codegen.m.verify().map_err(|message| CodegenError::Invalid(message.to_string()))?;
+    Ok(codegen.m)
+}
 
-        self.cx.append_basic_block(func, func.2.value());
+/// What [`build`] makes of a gem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// The module, as a file of the kind
+    File(FileKind),
+    /// An object file, linked into a program or a library
+    Linked(GemType),
+}
+
+/// What keeps a gem from being built.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    #[error(transparent)]
+    Codegen(#[from] CodegenError),
+    #[error(transparent)]
+    Target(#[from] target::TargetError),
+    #[error(transparent)]
+    Emit(#[from] emit::EmitError),
+    #[error(transparent)]
+    Link(#[from] link::LinkError),
+}
+
+/// Compiles `gem` for `target`, with debug information if there are `sources`, and writes `output` to `path`.
+///
+/// A linked output is built from an object file next to `path`, which is removed once it is linked.
+///
+/// # Errors
+/// If the module cannot be generated, LLVM cannot compile it, or it cannot be linked.
+pub fn build(
+    gem: &MirGem,
+    name: &str,
+    target: &target::Target,
+    entry: Option<DefId>,
+    sources: Option<Sources<'_>>,
+    output: Output,
+    path: &Path,
+) -> Result<(), BuildError> {
+    let cx = Context::create();
+    let module = codegen_gem(&cx, name, gem, target, entry, sources)?;
+    // libraries can be loaded anywhere in memory, and programs are position independent on most systems
+    let machine = target.machine(RelocMode::PIC)?;
 
+    match output {
+        Output::File(kind) => emit::write(&module, &machine, kind, path)?,
+        Output::Linked(gem_type) => {
+            let object = path.with_extension(FileKind::Object.extension());
+            emit::write(&module, &machine, FileKind::Object, &object)?;
+            let linked = link::link(&object, gem_type, path);
+            let _ = std::fs::remove_file(&object);
+            linked?;
+        }
     }
+    Ok(())
+}
+
+/// The LLVM backend, compiling for `target`, with debug information if there are `sources`.
+pub struct LlvmBackend<'a> {
+    pub target: target::Target,
+    pub sources: Option<Sources<'a>>,
 }
 
-fn make_type(ast: &Option<(Arrow, Type)>, cx: &Context, ptr_width: u32) -> impl AnyType {
-    let ast = if let Some((_, ty)) = ast {
-        ty
-    } else {
-        &Type::Primitive(PrimitiveType::Void)
-    };
-
-    match ast {
-        Type::Primitive(primitive) => match primitive {
-            PrimitiveType::Byte | PrimitiveType::Ubyte => cx.i8_type(),
-            PrimitiveType::Short | PrimitiveType::Ushort => cx.i16_type(),
-            PrimitiveType::Int | PrimitiveType::Uint => cx.i32_type(),
-            PrimitiveType::Long | PrimitiveType::Ulong => cx.i64_type(),
-            PrimitiveType::Explod | PrimitiveType::Uexplod => cx.i128_type(),
-            PrimitiveType::String => todo!(),
-            PrimitiveType::Void => cx.void_type(),
-            PrimitiveType::Char => cx.i8_type(),
-            PrimitiveType::Isize | PrimitiveType::Usize => cx.custom_width_int_type(ptr_width),
-        },
-        _ => todo!(),
+impl topaz_codegen::Backend for LlvmBackend<'_> {
+    type Error = BuildError;
+
+    fn name(&self) -> &'static str {
+        "llvm"
+    }
+
+    fn build(
+        &self,
+        gem: &MirGem,
+        name: &str,
+        entry: Option<DefId>,
+        output: topaz_codegen::Output,
+        path: &Path,
+    ) -> Result<(), BuildError> {
+        let output = match output {
+            topaz_codegen::Output::Assembly => Output::File(FileKind::Assembly),
+            topaz_codegen::Output::Object => Output::File(FileKind::Object),
+            topaz_codegen::Output::Linked(gem_type) => Output::Linked(gem_type),
+        };
+        build(gem, name, &self.target, entry, self.sources, output, path)
     }
 }
 
-impl LLVMCodegen {
-    fn new(mod_name: &str, target: target::Target) -> Self {
-        let cx = Context::create();
-        let m = cx.create_module(mod_name);
-        let bld = cx.create_builder();
+struct LLVMCodegen<'cx, 'g> {
+    cx: &'cx Context,
+    b: Builder<'cx>,
+    m: Module<'cx>,
+    gem: &'g MirGem,
+    target: &'g target::Target,
+    /// The layout of the machine for `target`, see [`target::Target::target_data`]
+    target_data: TargetData,
+    /// The function of every body, in the order of [`MirGem::bodies`]
+    bodies: Vec<FunctionValue<'cx>>,
+    /// The functions of the gem by their definition, which methods have none of
+    functions: HashMap<DefId, FunctionValue<'cx>>,
+    structs: HashMap<DefId, StructType<'cx>>,
+    enums: HashMap<DefId, StructType<'cx>>,
+    /// The struct of the fields of every variant of every enum, laid out in its bytes
+    variants: HashMap<DefId, Vec<StructType<'cx>>>,
+    debug: Option<debug::DebugInfo<'cx, 'g>>,
+}
+
+impl<'cx, 'g> LLVMCodegen<'cx, 'g> {
+    fn new(
+        cx: &'cx Context,
+        mod_name: &str,
+        gem: &'g MirGem,
+        target: &'g target::Target,
+        target_data: TargetData,
+    ) -> Self {
         Self {
             cx,
-            m,
-            b: bld,
-            target
+            b: cx.create_builder(),
+            m: cx.create_module(mod_name),
+            gem,
+            target,
+            target_data,
+            bodies: Vec::new(),
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            variants: HashMap::new(),
+            debug: None,
+        }
+    }
+
+    /// Declares a named type for every struct and enum, then fills in their fields, so that they can refer to each
+    /// other. An enum is filled in after the enums it contains, since it needs their size.
+    fn declare_types(&mut self) -> Result<(), CodegenError> {
+        for struct_def in &self.gem.structs {
+            self.structs.insert(struct_def.def, self.cx.opaque_struct_type(&struct_def.name));
+        }
+        for enum_def in &self.gem.enums {
+            self.enums.insert(enum_def.def, self.cx.opaque_struct_type(&enum_def.name));
+        }
+        for struct_def in &self.gem.structs {
+            let fields = struct_def
+                .fields
+                .iter()
+                .map(|field| {
+                    self.basic_type(&field.ty).ok_or_else(|| CodegenError::UnsupportedType {
+                        ty: field.ty.to_string(),
+                        function: struct_def.name.clone(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            self.structs[&struct_def.def].set_body(&fields, false);
+        }
+        let (mut order, mut in_progress) = (Vec::new(), HashSet::new());
+        for enum_def in &self.gem.enums {
+            self.enum_order(enum_def.def, &mut order, &mut in_progress)?;
+        }
+        for def in order {
+            let variants = self.define_enum(def)?;
+            self.variants.insert(def, variants);
         }
+        Ok(())
     }
 
-    fn init(&mut self) {
-        self.target.init()
+    /// Adds the enum `def` to `order` after the enums in the fields of its variants.
+    fn enum_order(
+        &self,
+        def: DefId,
+        order: &mut Vec<DefId>,
+        in_progress: &mut HashSet<DefId>,
+    ) -> Result<(), CodegenError> {
+        let Some(enum_def) = self.gem.enum_def(def) else { return Ok(()) };
+        if order.contains(&def) {
+            return Ok(());
+        }
+        // an enum containing itself would be of infinite size
+        if !in_progress.insert(def) {
+            let ty = Ty::Enum { def, name: enum_def.name.clone() };
+            return Err(CodegenError::UnsupportedType { ty: ty.to_string(), function: enum_def.name.clone() });
+        }
+        for ty in enum_def.variants.iter().flat_map(|variant| &variant.fields) {
+            for contained in self.contained_enums(ty) {
+                self.enum_order(contained, order, in_progress)?;
+            }
+        }
+        in_progress.remove(&def);
+        order.push(def);
+        Ok(())
+    }
+
+    /// Fills in the fields of the enum `def`, returning the struct of the fields of every variant.
+    fn define_enum(&self, def: DefId) -> Result<Vec<StructType<'cx>>, CodegenError> {
+        let Some(enum_def) = self.gem.enum_def(def) else { return Ok(Vec::new()) };
+        let unsupported =
+            |ty: &Ty| CodegenError::UnsupportedType { ty: ty.to_string(), function: enum_def.name.clone() };
+        let mut variants = Vec::with_capacity(enum_def.variants.len());
+        for variant in &enum_def.variants {
+            let fields = variant
+                .fields
+                .iter()
+                .map(|ty| self.basic_type(ty).ok_or_else(|| unsupported(ty)))
+                .collect::<Result<Vec<_>, _>>()?;
+            variants.push(self.cx.struct_type(&fields, false));
+        }
+        let data = &self.target_data;
+        let size = variants.iter().map(|variant| data.get_abi_size(variant)).max().unwrap_or(0);
+        let align = variants.iter().map(|variant| data.get_abi_alignment(variant)).max().unwrap_or(1).max(1);
+        let tag = self.int_type(IntTy::Usize).into();
+        if size == 0 {
+            self.enums[&def].set_body(&[tag], false);
+        } else {
+            let word = self.cx.custom_width_int_type(align * 8);
+            let words = u32::try_from((size + u64::from(align) - 1) / u64::from(align))
+                .map_err(|_| unsupported(&Ty::Enum { def, name: enum_def.name.clone() }))?;
+            self.enums[&def].set_body(&[tag, word.array_type(words).into()], false);
+        }
+        Ok(variants)
     }
+
+    /// The enums a value of type `ty` contains, not behind a reference.
+    fn contained_enums(&self, ty: &Ty) -> Vec<DefId> {
+        match ty.peel() {
+            Ty::Enum { def, .. } => vec![*def],
+            Ty::Struct { def, .. } => self
+                .gem
+                .struct_def(*def)
+                .map(|struct_def| struct_def.fields.iter().flat_map(|field| self.contained_enums(&field.ty)).collect())
+                .unwrap_or_default(),
+            Ty::Array { element, .. } => self.contained_enums(element),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Adds a function without a body for every body of the gem, so that calls can refer to functions defined later.
+    fn declare_functions(&mut self) -> Result<(), CodegenError> {
+        for body in &self.gem.bodies {
+            let unsupported =
+                |ty: &Ty| CodegenError::UnsupportedType { ty: ty.to_string(), function: body.name.clone() };
+            let params = body
+                .args()
+                .map(|arg| {
+                    let ty = &body.local(arg).ty;
+                    self.basic_type(ty).map(BasicMetadataTypeEnum::from).ok_or_else(|| unsupported(ty))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let ret = &body.local(Local::RETURN).ty;
+            let fn_type = if *ret.peel() == Ty::Void {
+                self.cx.void_type().fn_type(&params, false)
+            } else {
+                self.basic_type(ret).ok_or_else(|| unsupported(ret))?.fn_type(&params, false)
+            };
+
+            // functions of different modules can have the same name
+            let mut name = body.name.clone();
+            let mut suffix = 1;
+            while self.m.get_function(&name).is_some() {
+                name = format!("{}.{suffix}", body.name);
+                suffix += 1;
+            }
+            let function = self.m.add_function(&name, fn_type, None);
+            for (param, arg) in function.get_param_iter().zip(body.args()) {
+                if let Some(name) = &body.local(arg).name {
+                    param.set_name(name);
+                }
+            }
+            self.bodies.push(function);
+            if let Some(def) = body.def {
+                self.functions.insert(def, function);
+            }
+        }
+        Ok(())
+    }
+
+    /// The type values of `ty` have, `None` for the types the backend cannot represent.
+    ///
+    /// `()` is an empty struct, `str` a pointer to its first byte with its length, and `char` a unicode scalar value.
+    fn basic_type(&self, ty: &Ty) -> Option<BasicTypeEnum<'cx>> {
+        Some(match ty.peel() {
+            // the results of the functions outside of the gem but `std::fmt::format` are not typed, and are `()`
+            Ty::Void | Ty::Var(_) | Ty::Error => self.cx.struct_type(&[], false).into(),
+            Ty::Int(int) => self.int_type(*int).into(),
+            Ty::Bool => self.cx.bool_type().into(),
+            Ty::Char => self.cx.i32_type().into(),
+            Ty::Str => self.str_type().into(),
+            Ty::Ref { .. } => self.ptr_type().into(),
+            Ty::Struct { def, .. } => (*self.structs.get(def)?).into(),
+            Ty::Enum { def, .. } => (*self.enums.get(def)?).into(),
+            Ty::Array { element, len } => self.basic_type(element)?.array_type(u32::try_from(*len).ok()?).into(),
+            _ => return None,
+        })
+    }
+
+    fn int_type(&self, int: IntTy) -> IntType<'cx> {
+        self.cx.custom_width_int_type(self.int_bits(int))
+    }
+
+    fn int_bits(&self, int: IntTy) -> u32 {
+        match int {
+            IntTy::Byte | IntTy::Ubyte => 8,
+            IntTy::Short | IntTy::Ushort => 16,
+            IntTy::Int | IntTy::Uint => 32,
+            IntTy::Long | IntTy::Ulong => 64,
+            IntTy::Explod | IntTy::Uexplod => 128,
+            IntTy::Isize | IntTy::Usize => self.target.ptr_width(),
+        }
+    }
+
+    fn ptr_type(&self) -> PointerType<'cx> {
+        self.cx.i8_type().ptr_type(AddressSpace::default())
+    }
+
+    fn str_type(&self) -> StructType<'cx> {
+        self.cx.struct_type(&[self.ptr_type().into(), self.int_type(IntTy::Usize).into()], false)
+    }
+
+    /// A `str` whose bytes are in a global of their own.
+    fn const_str(&self, value: &str) -> StructValue<'cx> {
+        let bytes = self.b.build_global_string_ptr(value, "str").as_pointer_value();
+        let len = self.int_type(IntTy::Usize).const_int(value.len() as u64, false);
+        self.cx.const_struct(&[bytes.into(), len.into()], false)
+    }
+}
+
+const fn is_signed(int: IntTy) -> bool {
+    matches!(int, IntTy::Byte | IntTy::Short | IntTy::Int | IntTy::Long | IntTy::Explod | IntTy::Isize)
 }
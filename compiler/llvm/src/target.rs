@@ -1,43 +1,240 @@
-macro tar(init_mod=$im:path; $($name:ident ptr_width=$ptr_width:literal init=$init_fn:ident);*$(;)?) {
-    pub enum Target {
-        $(
-        $name,
-        )*
-    }
-
-    impl Target {
-        pub fn ptr_width(&self) -> u32 {
-            match self {
-                $(Self::$name => $ptr_width,)*
-            }
+//! `target.rs` - the systems the backend generates code for.
+//!
+//! A target is chosen by its triple, `<arch>-<vendor>-<os>[-<env>]`, e.g. `aarch64-unknown-linux-gnu`. The
+//! architecture decides the width of pointers, and so of `usize` and `isize`; the rest of the triple is given to
+//! LLVM as is. The data layout of the module is the one of LLVM's machine for the whole target, since it depends
+//! on the system too, e.g. symbols are mangled differently on Darwin and Windows.
+use inkwell::targets::{
+    CodeModel, InitializationConfig, RelocMode, Target as LLVMTarget, TargetData, TargetMachine, TargetTriple,
+};
+use inkwell::OptimizationLevel;
+use std::fmt;
+use std::str::FromStr;
+
+/// An architecture the backend generates code for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arch {
+    X86_64,
+    Aarch64,
+    Riscv64,
+    Wasm32,
+    I686,
+}
+
+impl Arch {
+    pub const ALL: [Self; 5] = [Self::X86_64, Self::Aarch64, Self::Riscv64, Self::Wasm32, Self::I686];
+
+    /// The name of the architecture in the triples LLVM takes.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::X86_64 => "x86_64",
+            Self::Aarch64 => "aarch64",
+            Self::Riscv64 => "riscv64",
+            Self::Wasm32 => "wasm32",
+            Self::I686 => "i686",
+        }
+    }
+
+    /// The width of a pointer in bits.
+    #[must_use]
+    pub const fn ptr_width(self) -> u32 {
+        match self {
+            Self::X86_64 | Self::Aarch64 | Self::Riscv64 => 64,
+            Self::Wasm32 | Self::I686 => 32,
+        }
+    }
+
+    /// The CPU code is generated for when none is given, one every system of the architecture has.
+    #[must_use]
+    pub const fn default_cpu(self) -> &'static str {
+        match self {
+            Self::X86_64 => "x86-64",
+            Self::Aarch64 | Self::Wasm32 => "generic",
+            Self::Riscv64 => "generic-rv64",
+            Self::I686 => "pentium4",
         }
+    }
 
-        pub fn init(&self) {
-            match self {
-                $(Self::$name => $im::$init_fn(),)*
-            }
+    /// The features enabled when none are given: those of `rv64gc` on RISC-V, which Linux needs.
+    #[must_use]
+    pub const fn default_features(self) -> &'static str {
+        match self {
+            Self::Riscv64 => "+m,+a,+f,+d,+c",
+            _ => "",
+        }
+    }
+
+    /// Registers the code generator of the architecture with LLVM.
+    pub fn init(self) {
+        const INIT: InitializationConfig = InitializationConfig {
+            base: true,
+            info: true,
+            asm_printer: true,
+            asm_parser: true,
+            disassembler: false,
+            machine_code: true,
+        };
+
+        match self {
+            Self::X86_64 | Self::I686 => LLVMTarget::initialize_x86(&INIT),
+            Self::Aarch64 => LLVMTarget::initialize_aarch64(&INIT),
+            Self::Riscv64 => LLVMTarget::initialize_riscv(&INIT),
+            Self::Wasm32 => LLVMTarget::initialize_webassembly(&INIT),
         }
     }
 }
 
-tar! {
-    init_mod=init;
-    X86 ptr_width=8 init=init_x86;
+impl FromStr for Arch {
+    type Err = ();
+
+    /// Parses the architecture of a triple, with the other names it goes by.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "x86_64" | "amd64" => Self::X86_64,
+            "aarch64" | "arm64" => Self::Aarch64,
+            "riscv64" | "riscv64gc" => Self::Riscv64,
+            "wasm32" => Self::Wasm32,
+            "i686" | "i586" | "i386" => Self::I686,
+            _ => return Err(()),
+        })
+    }
 }
 
-mod init {
-    const INIT: InitializationConfig = InitializationConfig {
-        base: true,
-        info: true,
-        asm_printer: true,
-        asm_parser: true,
-        disassembler: false,
-        machine_code: true,
-    };
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum TargetError {
+    #[error("unknown target `{0}`, expected a triple like `x86_64-unknown-linux-gnu`")]
+    InvalidTriple(String),
+    #[error(
+        "unsupported architecture `{arch}` in `{triple}`, expected one of: x86_64, aarch64, riscv64, wasm32, i686"
+    )]
+    UnknownArch { arch: String, triple: String },
+    #[error("LLVM cannot generate code for `{triple}`: {message}")]
+    Unsupported { triple: String, message: String },
+}
+
+/// A system to generate code for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    arch: Arch,
+    triple: String,
+    cpu: String,
+    features: String,
+}
+
+impl Target {
+    /// The target of `triple`, for the default CPU of its architecture.
+    ///
+    /// # Errors
+    /// If `triple` is not a triple, or the backend does not support its architecture.
+    pub fn from_triple(triple: &str) -> Result<Self, TargetError> {
+        let mut parts = triple.split('-');
+        let arch_name = parts.next().unwrap_or_default();
+        let rest: Vec<_> = parts.collect();
+        if !(2..=3).contains(&rest.len()) || rest.iter().any(|part| part.is_empty()) {
+            return Err(TargetError::InvalidTriple(triple.to_owned()));
+        }
+        let arch = arch_name
+            .parse::<Arch>()
+            .map_err(|()| TargetError::UnknownArch { arch: arch_name.to_owned(), triple: triple.to_owned() })?;
+
+        Ok(Self {
+            arch,
+            triple: format!("{}-{}", arch.name(), rest.join("-")),
+            cpu: arch.default_cpu().to_owned(),
+            features: arch.default_features().to_owned(),
+        })
+    }
+
+    /// The system running the compiler, with the CPU and features it has.
+    ///
+    /// # Errors
+    /// If the backend does not support the architecture of the system.
+    pub fn host() -> Result<Self, TargetError> {
+        let triple = TargetMachine::get_default_triple();
+        let target = Self::from_triple(&triple.as_str().to_string_lossy())?;
+        Ok(target
+            .with_cpu(&TargetMachine::get_host_cpu_name().to_string())
+            .with_features(&TargetMachine::get_host_cpu_features().to_string()))
+    }
+
+    /// The target generating code for `cpu`, as LLVM names it, e.g. `cortex-a72`.
+    #[must_use]
+    pub fn with_cpu(mut self, cpu: &str) -> Self {
+        self.cpu = cpu.to_owned();
+        self
+    }
+
+    /// The target with `features`, as LLVM takes them, e.g. `+avx2,-sse4.1`.
+    #[must_use]
+    pub fn with_features(mut self, features: &str) -> Self {
+        self.features = features.to_owned();
+        self
+    }
+
+    #[must_use]
+    pub const fn arch(&self) -> Arch {
+        self.arch
+    }
 
-    use inkwell::targets::{InitializationConfig, Target as LLVMT};
+    #[must_use]
+    pub fn triple(&self) -> &str {
+        &self.triple
+    }
+
+    #[must_use]
+    pub fn cpu(&self) -> &str {
+        &self.cpu
+    }
+
+    #[must_use]
+    pub fn features(&self) -> &str {
+        &self.features
+    }
+
+    /// The width of a pointer, `usize` and `isize` in bits.
+    #[must_use]
+    pub const fn ptr_width(&self) -> u32 {
+        self.arch.ptr_width()
+    }
+
+    pub(crate) fn llvm_triple(&self) -> TargetTriple {
+        TargetTriple::create(&self.triple)
+    }
+
+    /// The data layout of the machine for the target, which the module is generated with.
+    ///
+    /// # Errors
+    /// If LLVM does not know the triple, CPU or features of the target.
+    pub fn target_data(&self) -> Result<TargetData, TargetError> {
+        // how the code is relocated does not change the layout
+        Ok(self.machine(RelocMode::PIC)?.get_target_data())
+    }
+
+    /// The machine generating code for the target.
+    ///
+    /// # Errors
+    /// If LLVM does not know the triple, CPU or features of the target.
+    pub fn machine(&self, reloc: RelocMode) -> Result<TargetMachine, TargetError> {
+        self.arch.init();
+        let triple = self.llvm_triple();
+        let unsupported = |message: String| TargetError::Unsupported { triple: self.triple.clone(), message };
+        LLVMTarget::from_triple(&triple)
+            .map_err(|message| unsupported(message.to_string()))?
+            .create_target_machine(
+                &triple,
+                &self.cpu,
+                &self.features,
+                OptimizationLevel::Default,
+                reloc,
+                CodeModel::Default,
+            )
+            .ok_or_else(|| unsupported(format!("cannot create a machine for the CPU `{}`", self.cpu)))
+    }
+}
 
-    fn init_x86() {
-        LLVMT::initialize_x86(&INIT);
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.triple)
     }
 }
@@ -0,0 +1,167 @@
+use inkwell::context::Context;
+use topaz_ast::file::TopazFile;
+use topaz_hir::lower::lower_gem;
+use topaz_mir::build::build_gem;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::transform::{OptLevel, PassManager};
+use topaz_mir::MirGem;
+use topaz_parser_next::Parse;
+use topaz_report::Reporter;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+use topazc_llvm::codegen_gem;
+use topazc_llvm::target::Target;
+
+fn build(source: &str, level: OptLevel) -> MirGem {
+    let file = TopazFile::parse(source).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("test", true);
+    cx.set_prelude(topaz_session::prelude::std_prelude());
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let errors: Vec<_> = session
+        .output
+        .iter()
+        .filter(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)))
+        .map(|(file_id, unit)| unit.build_diagnostic(*file_id).message)
+        .collect();
+    assert!(errors.is_empty(), "{errors:?}");
+    let mut gem = build_gem(&lower_gem(&session));
+    construct_gem(&mut gem);
+    PassManager::new(level).run(&mut gem).expect("invalid MIR");
+    gem
+}
+
+/// The LLVM IR of `source`, which has to pass [`inkwell::module::Module::verify`].
+fn codegen(source: &str, level: OptLevel) -> String {
+    let gem = build(source, level);
+    let cx = Context::create();
+    let target = Target::from_triple("x86_64-unknown-linux-gnu").expect("unknown target");
+    let module = codegen_gem(&cx, "test", &gem, &target, None, None).expect("failed to generate the module");
+    module.print_to_string().to_string()
+}
+
+const SOURCE: &str = r#"struct Point { x: int, y: long }
+
+func pick(point: Point, flag: bool) -> int {
+    if flag {
+        return point.x;
+    }
+    return 0;
+}
+
+func next(value: int, flag: bool) -> int {
+    if flag {
+        return value;
+    }
+    return 0;
+}
+
+func count(start: int) -> int {
+    let mut going = true;
+    let mut x = start;
+    while going {
+        x = next(x, going);
+        going = false;
+    }
+    return x;
+}
+
+public func main() {
+    let point = Point(x = 1, y = 2);
+    let seen = &point;
+    println("hello world");
+    println(count(pick(point, true)));
+}
+"#;
+
+#[test]
+fn test_functions() {
+    let ir = codegen(SOURCE, OptLevel::O0);
+    assert!(ir.contains("%Point = type { i32, i64 }"), "{ir}");
+    assert!(ir.contains("define i32 @pick(%Point %point, i1 %flag)"), "{ir}");
+    assert!(ir.contains("define i32 @count(i32 %start)"), "{ir}");
+    assert!(ir.contains("define void @main()"), "{ir}");
+    assert!(ir.contains("call i32 @pick("), "{ir}");
+    assert!(ir.contains("declare i32 @printf(ptr, ...)"), "{ir}");
+}
+
+#[test]
+fn test_control_flow() {
+    let ir = codegen(SOURCE, OptLevel::O0);
+    // the loop of `count` has phis, assigned on the edges into its header
+    assert!(ir.contains("br i1"), "{ir}");
+    assert!(ir.contains("_to_bb"), "{ir}");
+    assert!(ir.contains("alloca %Point"), "{ir}");
+}
+
+#[test]
+fn test_levels() {
+    for level in [OptLevel::O1, OptLevel::O2, OptLevel::O3] {
+        codegen(SOURCE, level);
+    }
+}
+
+const OPERATORS: &str = r#"func operators(a: int, b: uint, flag: bool) -> bool {
+    let sum = a + a * 2 - -a;
+    let quotient = sum / a % 3;
+    let halved = b / 2uint;
+    return (!flag == (quotient < 0)) != (halved >= b);
+}
+"#;
+
+#[test]
+fn test_operators() {
+    let ir = codegen(OPERATORS, OptLevel::O0);
+    // arithmetic is checked, and a program that overflows exits
+    assert!(ir.contains("call { i32, i1 } @llvm.sadd.with.overflow.i32("), "{ir}");
+    assert!(ir.contains("call { i32, i1 } @llvm.smul.with.overflow.i32("), "{ir}");
+    assert!(ir.contains("call void @exit(i32 101)"), "{ir}");
+    assert!(ir.contains("sdiv i32"), "{ir}");
+    assert!(ir.contains("srem i32"), "{ir}");
+    assert!(ir.contains("udiv i32"), "{ir}");
+    assert!(ir.contains("icmp slt i32"), "{ir}");
+    assert!(ir.contains("icmp uge i32"), "{ir}");
+    assert!(ir.contains("xor i1"), "{ir}");
+}
+
+const ENUMS: &str = r#"enum Shape {
+    Circle(int),
+    Rect(int, long),
+    Empty
+}
+
+func area(shape: Shape) -> long {
+    match shape {
+        Shape::Circle(radius) => {
+            return 3long;
+        }
+        Shape::Rect(width, height) => {
+            return height;
+        }
+        _ => {
+            return 0long;
+        }
+    }
+}
+
+func third(index: usize) -> long {
+    let areas = [area(Shape::Circle(2)), area(Shape::Rect(3, 5long)), area(Shape::Empty)];
+    return areas[index];
+}
+"#;
+
+#[test]
+fn test_enums() {
+    let ir = codegen(ENUMS, OptLevel::O0);
+    // the tag, then bytes enough for `Rect`, as aligned as its `long`
+    assert!(ir.contains("%Shape = type { i64, [2 x i64] }"), "{ir}");
+    assert!(ir.contains("switch i64"), "{ir}");
+    assert!(ir.contains("alloca [3 x i64]"), "{ir}");
+    // indexing checks the bounds, and a program indexing out of them exits
+    assert!(ir.contains("icmp uge i64"), "{ir}");
+    assert!(ir.contains("getelementptr inbounds [3 x i64]"), "{ir}");
+    codegen(ENUMS, OptLevel::O3);
+}
@@ -0,0 +1,83 @@
+use codespan_reporting::files::SimpleFiles;
+use inkwell::context::Context;
+use topaz_ast::file::TopazFile;
+use topaz_hir::lower::lower_gem;
+use topaz_mir::build::build_gem;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::MirGem;
+use topaz_parser_next::Parse;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::StaticAnalyzer;
+use topazc_llvm::target::Target;
+use topazc_llvm::{build, codegen_gem, FileKind, Output, Sources};
+
+const SOURCE: &str = r#"struct Point { x: int, y: long }
+
+func pick(point: Point, flag: bool) -> int {
+    if flag {
+        return point.x;
+    }
+    return 0;
+}
+
+public func main() {
+    let point = Point(x = 1, y = 2);
+    let text = "hello world";
+    println(text);
+    println(pick(point, true));
+}
+"#;
+
+fn mir(files: &mut SimpleFiles<String, String>, source: &str) -> MirGem {
+    let file_id = files.add("/src/gems/hello/main.tp".to_owned(), source.to_owned());
+    let file = TopazFile::parse(source).expect("failed to parse");
+    let modules = [SourceModule { file_id, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("hello", true);
+    cx.set_prelude(topaz_session::prelude::std_prelude());
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let mut gem = build_gem(&lower_gem(&session));
+    construct_gem(&mut gem);
+    gem
+}
+
+#[test]
+fn test_debug_info() {
+    let mut files = SimpleFiles::new();
+    let gem = mir(&mut files, SOURCE);
+    let target = Target::from_triple("x86_64-unknown-linux-gnu").expect("unknown target");
+    let cx = Context::create();
+    let sources = Sources { files: &files, root: 0 };
+    let module = codegen_gem(&cx, "hello", &gem, &target, gem.entry_point(0).and_then(|main| main.def), Some(sources))
+        .expect("failed to generate the module");
+    let ir = module.print_to_string().to_string();
+
+    assert!(ir.contains(r#"!DIFile(filename: "main.tp", directory: "/src/gems/hello")"#), "{ir}");
+    assert!(ir.contains(r#"!DISubprogram(name: "pick""#), "{ir}");
+    assert!(ir.contains(r#"!DILocalVariable(name: "point", arg: 1"#), "{ir}");
+    assert!(ir.contains(r#"!DILocalVariable(name: "text""#), "{ir}");
+    assert!(ir.contains(r#"!DICompositeType(tag: DW_TAG_structure_type, name: "Point""#), "{ir}");
+    assert!(ir.contains(r#"!DIDerivedType(tag: DW_TAG_member, name: "y""#), "{ir}");
+    assert!(ir.contains(r#"!DIBasicType(name: "long", size: 64, encoding: DW_ATE_signed)"#), "{ir}");
+    assert!(ir.contains("call void @llvm.dbg.declare("), "{ir}");
+    // `return point.x;` is on the fifth line
+    assert!(ir.contains("!DILocation(line: 5,"), "{ir}");
+}
+
+#[test]
+fn test_object() {
+    let mut files = SimpleFiles::new();
+    let gem = mir(&mut files, SOURCE);
+    let target = Target::host().expect("unsupported host");
+    let path = std::env::temp_dir().join(format!("topazc-llvm-debug-{}.o", std::process::id()));
+    let sources = Some(Sources { files: &files, root: 0 });
+    build(&gem, "hello", &target, None, sources, Output::File(FileKind::Object), &path).expect("failed to build");
+
+    let object = std::fs::read(&path).expect("failed to read the object");
+    let _ = std::fs::remove_file(&path);
+    for section in [&b".debug_info"[..], b".debug_line"] {
+        assert!(object.windows(section.len()).any(|bytes| bytes == section), "no {}", String::from_utf8_lossy(section));
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+use std::process::Command;
+use topaz_ast::file::TopazFile;
+use topaz_hir::lower::lower_gem;
+use topaz_mir::build::build_gem;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::MirGem;
+use topaz_parser_next::Parse;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::StaticAnalyzer;
+use topazc_llvm::target::Target;
+use topazc_llvm::{build, FileKind, GemType, Output};
+
+const SOURCE: &str = r#"func one() -> int {
+    return 1;
+}
+
+public func main() {
+    println("hello world");
+    println(one());
+}
+"#;
+
+fn mir(source: &str) -> MirGem {
+    let file = TopazFile::parse(source).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("hello", true);
+    cx.set_prelude(topaz_session::prelude::std_prelude());
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let mut gem = build_gem(&lower_gem(&session));
+    construct_gem(&mut gem);
+    gem
+}
+
+/// A directory of its own for every test, as they run at the same time.
+fn out_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("topazc-llvm-{test}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("failed to create the output directory");
+    dir
+}
+
+#[test]
+fn test_files() {
+    let gem = mir(SOURCE);
+    let target = Target::host().expect("unsupported host");
+    let entry = gem.entry_point(0).and_then(|main| main.def);
+    let dir = out_dir("files");
+    for kind in [FileKind::LlvmIr, FileKind::Bitcode, FileKind::Assembly, FileKind::Object] {
+        let path = dir.join(format!("hello.{}", kind.extension()));
+        build(&gem, "hello", &target, entry, None, Output::File(kind), &path).expect("failed to build");
+        assert!(std::fs::metadata(&path).map_or(false, |file| file.len() > 0), "{kind:?} is empty");
+    }
+
+    let ir = std::fs::read_to_string(dir.join("hello.ll")).expect("failed to read the IR");
+    assert!(ir.contains("target triple"), "{ir}");
+    assert!(ir.contains("define i32 @main()"), "{ir}");
+    assert!(ir.contains("call void @main.1()"), "{ir}");
+}
+
+#[test]
+fn test_executable() {
+    let gem = mir(SOURCE);
+    let target = Target::host().expect("unsupported host");
+    let entry = gem.entry_point(0).and_then(|main| main.def);
+    let path = out_dir("executable").join(GemType::Bin.file_name("hello"));
+    build(&gem, "hello", &target, entry, None, Output::Linked(GemType::Bin), &path).expect("failed to build");
+
+    let output = Command::new(&path).output().expect("failed to run the program");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello world\n1\n");
+}
+
+#[test]
+fn test_libraries() {
+    let gem = mir(SOURCE);
+    let target = Target::host().expect("unsupported host");
+    let dir = out_dir("libraries");
+    for gem_type in [GemType::StaticLib, GemType::SharedLib] {
+        let path = dir.join(gem_type.file_name("hello"));
+        build(&gem, "hello", &target, None, None, Output::Linked(gem_type), &path).expect("failed to build");
+        assert!(path.exists(), "{gem_type:?}");
+        assert!(!path.with_extension("o").exists(), "the object file of {gem_type:?} was left behind");
+    }
+}
+
+#[test]
+fn test_enums_and_arrays() {
+    let source = r#"enum Shape {
+    Circle(int),
+    Rect(int, int),
+    Empty
+}
+
+func area(shape: Shape) -> int {
+    match shape {
+        Shape::Circle(radius) => {
+            return 3 * radius * radius;
+        }
+        Shape::Rect(width, height) => {
+            return width * height;
+        }
+        _ => {
+            return 0;
+        }
+    }
+}
+
+public func main() {
+    let areas = [area(Shape::Circle(2)), area(Shape::Rect(3, 5)), area(Shape::Empty)];
+    let mut index = 0;
+    while index < 4 {
+        println(areas[index]);
+        index = index + 1;
+    }
+}
+"#;
+    let gem = mir(source);
+    let target = Target::host().expect("unsupported host");
+    let entry = gem.entry_point(0).and_then(|main| main.def);
+    let path = out_dir("enums").join(GemType::Bin.file_name("shapes"));
+    build(&gem, "shapes", &target, entry, None, Output::Linked(GemType::Bin), &path).expect("failed to build");
+
+    // the fourth element is out of bounds
+    let output = Command::new(&path).output().expect("failed to run the program");
+    assert_eq!(output.status.code(), Some(topaz_codegen::PANIC_STATUS));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "12\n15\n0\n");
+}
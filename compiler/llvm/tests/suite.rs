@@ -0,0 +1,27 @@
+use topaz_codegen::suite;
+use topazc_llvm::target::Target;
+use topazc_llvm::LlvmBackend;
+
+fn backend() -> LlvmBackend<'static> {
+    LlvmBackend { target: Target::host().expect("unsupported host"), sources: None }
+}
+
+#[test]
+fn test_programs() {
+    suite::programs(&backend());
+}
+
+#[test]
+fn test_panics() {
+    suite::panics(&backend());
+}
+
+#[test]
+fn test_outputs() {
+    suite::outputs(&backend());
+}
+
+#[test]
+fn test_rejected() {
+    suite::rejected();
+}
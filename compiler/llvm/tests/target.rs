@@ -0,0 +1,112 @@
+use inkwell::context::Context;
+use topaz_ast::file::TopazFile;
+use topaz_hir::lower::lower_gem;
+use topaz_mir::build::build_gem;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::MirGem;
+use topaz_parser_next::Parse;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::StaticAnalyzer;
+use topazc_llvm::target::{Arch, Target, TargetError};
+use topazc_llvm::{build, codegen_gem, FileKind, Output};
+
+const SOURCE: &str = r#"func size(value: usize) -> usize {
+    return value;
+}
+
+public func main() {
+    println("hello world");
+    println(size(1));
+}
+"#;
+
+const TRIPLES: [&str; 5] = [
+    "x86_64-unknown-linux-gnu",
+    "aarch64-unknown-linux-gnu",
+    "riscv64gc-unknown-linux-gnu",
+    "wasm32-unknown-unknown",
+    "i686-unknown-linux-gnu",
+];
+
+fn mir(source: &str) -> MirGem {
+    let file = TopazFile::parse(source).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("test", true);
+    cx.set_prelude(topaz_session::prelude::std_prelude());
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let mut gem = build_gem(&lower_gem(&session));
+    construct_gem(&mut gem);
+    gem
+}
+
+#[test]
+fn test_triples() {
+    let arches: Vec<_> = TRIPLES.iter().map(|triple| Target::from_triple(triple).map(|target| target.arch())).collect();
+    assert_eq!(arches, Arch::ALL.map(Ok));
+
+    // the architecture is given to LLVM by the name it knows
+    let riscv = Target::from_triple("riscv64gc-unknown-linux-gnu").expect("unknown target");
+    assert_eq!(riscv.triple(), "riscv64-unknown-linux-gnu");
+    assert_eq!(riscv.features(), "+m,+a,+f,+d,+c");
+    let arm = Target::from_triple("arm64-apple-darwin").expect("unknown target").with_cpu("apple-m1");
+    assert_eq!((arm.triple(), arm.cpu()), ("aarch64-apple-darwin", "apple-m1"));
+
+    assert!(matches!(Target::from_triple("mips-unknown-linux-gnu"), Err(TargetError::UnknownArch { .. })));
+    assert!(matches!(Target::from_triple("x86_64"), Err(TargetError::InvalidTriple(_))));
+}
+
+#[test]
+fn test_ir() {
+    let gem = mir(SOURCE);
+    for triple in TRIPLES {
+        let target = Target::from_triple(triple).expect("unknown target");
+        let cx = Context::create();
+        let ir = codegen_gem(&cx, "test", &gem, &target, None, None).expect("failed to generate the module");
+        let ir = ir.print_to_string().to_string();
+
+        let usize = format!("i{}", target.ptr_width());
+        assert!(ir.contains(&format!("target triple = \"{}\"", target.triple())), "{ir}");
+        let layout = target.target_data().expect("no machine for the target").get_data_layout();
+        assert!(ir.contains(&format!("target datalayout = \"{}\"", layout.as_str().to_string_lossy())), "{ir}");
+        assert!(ir.contains(&format!("define {usize} @size({usize} %value)")), "{ir}");
+        assert!(ir.contains(&format!("{{ ptr, {usize} }}")), "{ir}");
+    }
+}
+
+#[test]
+fn test_layouts() {
+    // the layout depends on the system too, e.g. on how it mangles symbols
+    let gem = mir(SOURCE);
+    for (triple, component) in [
+        ("x86_64-unknown-linux-gnu", "-m:e-"),
+        ("x86_64-apple-darwin", "-m:o-"),
+        ("aarch64-apple-darwin", "-m:o-"),
+        ("x86_64-pc-windows-msvc", "-m:w-"),
+        ("i686-pc-windows-msvc", "-m:x-"),
+        ("wasm32-unknown-unknown", "-m:e-"),
+    ] {
+        let target = Target::from_triple(triple).expect("unknown target");
+        let cx = Context::create();
+        let ir = codegen_gem(&cx, "test", &gem, &target, None, None).expect("failed to generate the module");
+        let ir = ir.print_to_string().to_string();
+        let layout = ir.lines().find(|line| line.starts_with("target datalayout")).expect("no data layout");
+        assert!(layout.contains(component), "{triple}: {layout}");
+    }
+}
+
+#[test]
+fn test_assembly() {
+    let gem = mir(SOURCE);
+    let dir = std::env::temp_dir().join(format!("topazc-llvm-assembly-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("failed to create the output directory");
+    for triple in TRIPLES {
+        let target = Target::from_triple(triple).expect("unknown target");
+        let path = dir.join(format!("{triple}.s"));
+        build(&gem, "test", &target, None, None, Output::File(FileKind::Assembly), &path).expect("failed to build");
+        let assembly = std::fs::read_to_string(&path).expect("failed to read the assembly");
+        assert!(assembly.contains("size"), "{triple}:\n{assembly}");
+    }
+}
@@ -0,0 +1,56 @@
+use crate::ident::Ident;
+use crate::punctuated::Punctuated;
+use crate::token::delim::{Parentheses, Surround};
+use crate::token::stream::{ToTokens, TokenStream};
+use crate::Token;
+use std::fmt::{Display, Formatter};
+
+/// The attributes setting the level of lints, e.g. `@allow(dead_code)`.
+pub const LINT_LEVELS: [&str; 4] = ["allow", "warn", "deny", "forbid"];
+
+/// `@allow(unused_variables, dead_code)`, `@!no_std`
+///
+/// Attributes in front of an item or a statement apply to it. The `@!` ones at the top of a file,
+/// before its first item, apply to the whole file (or gem, in the root module).
+#[tokens]
+#[derive(Clone, Eq, PartialEq)]
+pub struct Attribute(pub Token![@], pub Ident, pub Option<Surround<Parentheses, Punctuated<Ident, Token![,]>>>);
+
+impl Attribute {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.1.value()
+    }
+
+    /// The names in parentheses after the name of the attribute.
+    pub fn args(&self) -> impl Iterator<Item = &Ident> {
+        self.2.iter().flat_map(|args| args.content().iter())
+    }
+
+    /// Whether the attribute sets the level of lints, see [`LINT_LEVELS`].
+    #[must_use]
+    pub fn is_lint_level(&self) -> bool {
+        LINT_LEVELS.contains(&self.name())
+    }
+}
+
+impl ToTokens for Attribute {
+    fn write_tokens(&self, tokens: &mut TokenStream) {
+        tokens.append(&self.0);
+        tokens.append(&self.1);
+        for arg in self.args() {
+            tokens.append(arg);
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}", self.1)?;
+        if let Some(args) = &self.2 {
+            let args: Vec<_> = args.content().iter().map(ToString::to_string).collect();
+            write!(f, "({})", args.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,26 @@
+use crate::attribute::Attribute;
+use crate::location::WithSpan;
 use crate::statement::Statement;
 use crate::token::delim::{Curly, Surround};
 
+/// The statements of a block, with the attributes in front of every statement.
 #[tokens]
 #[derive(Eq, PartialEq)]
-pub struct Block(pub Surround<Curly, Vec<Statement>>);
+pub struct Block(pub Surround<Curly, Vec<WithSpan<Statement>>>, pub Vec<Vec<Attribute>>);
+
+impl Block {
+    pub fn statements(&self) -> impl Iterator<Item = &WithSpan<Statement>> {
+        self.0.content().iter()
+    }
+
+    /// Every statement, with the attributes in front of it.
+    pub fn attributed_statements(&self) -> impl Iterator<Item = (&[Attribute], &WithSpan<Statement>)> {
+        self.1.iter().map(Vec::as_slice).zip(self.statements())
+    }
+
+    /// Whether every path through the block leaves it early.
+    #[must_use]
+    pub fn diverges(&self) -> bool {
+        self.statements().any(|statement| statement.value.diverges())
+    }
+}
@@ -1,6 +1,11 @@
+use derive_more::Display;
+use crate::ident::Ident;
 use crate::literal::Literal;
+use crate::location::WithSpan;
 use crate::path::Path;
+use crate::punctuated::Punctuated;
 use crate::statement::func_call::FuncCallStmt;
+use crate::token::delim::{Brackets, Surround};
 use crate::Token;
 
 #[tokens]
@@ -10,7 +15,13 @@ pub enum Expr {
     Borrow(ExprBorrow),
     VariableAccess(ExprVarAccess),
     ConstAccess(ExprConstAccess),
-    FuncCall(FuncCallStmt)
+    FuncCall(FuncCallStmt),
+    Field(ExprField),
+    Binary(ExprBinary),
+    Unary(ExprUnary),
+    Array(ExprArray),
+    Index(ExprIndex),
+    Interpolated(ExprInterpolated)
 }
 
 #[tokens]
@@ -30,4 +41,78 @@ pub struct ExprVarAccess(pub Path);
 #[tokens]
 #[derive(Eq, PartialEq)]
 /// &thing or &mut thing
-pub struct ExprBorrow(pub Token![&], pub Option<Token![mut]>, pub Box<Expr>);
+pub struct ExprBorrow(pub Token![&], pub Option<Token![mut]>, pub Box<WithSpan<Expr>>);
+
+#[tokens]
+#[derive(Eq, PartialEq)]
+/// thing.field
+pub struct ExprField(pub Box<WithSpan<Expr>>, pub Token![.], pub Ident);
+
+#[tokens]
+#[derive(Eq, PartialEq)]
+/// thing + 1, thing < other
+pub struct ExprBinary(pub Box<WithSpan<Expr>>, pub BinOp, pub Box<WithSpan<Expr>>);
+
+#[tokens]
+#[derive(Eq, PartialEq)]
+/// -thing or !thing
+pub struct ExprUnary(pub UnOp, pub Box<WithSpan<Expr>>);
+
+#[tokens]
+#[derive(Eq, PartialEq)]
+/// [first, second, third]
+pub struct ExprArray(pub Surround<Brackets, Punctuated<WithSpan<Expr>, Token![,]>>);
+
+impl ExprArray {
+    pub fn elements(&self) -> impl Iterator<Item = &WithSpan<Expr>> {
+        self.0.content().iter()
+    }
+}
+
+#[tokens]
+#[derive(Eq, PartialEq)]
+/// things[index]
+pub struct ExprIndex(pub Box<WithSpan<Expr>>, pub Box<WithSpan<Expr>>);
+
+#[tokens]
+#[derive(Eq, PartialEq)]
+/// "$greeting, $name!", a string literal with variables in it: its parts are the string literals between the
+/// variables and the accesses to the variables, in order
+pub struct ExprInterpolated(pub Vec<WithSpan<Expr>>);
+
+impl ExprInterpolated {
+    pub fn parts(&self) -> impl Iterator<Item = &WithSpan<Expr>> {
+        self.0.iter()
+    }
+}
+
+#[tokens]
+#[derive(Eq, PartialEq, Copy, Clone, Display)]
+pub enum BinOp {
+    Add(Token![+]),
+    Sub(Token![-]),
+    Mul(Token![*]),
+    Div(Token![/]),
+    Rem(Token![%]),
+    Eq(Token![==]),
+    Ne(Token![!=]),
+    Lt(Token![<]),
+    Le(Token![<=]),
+    Gt(Token![>]),
+    Ge(Token![>=])
+}
+
+impl BinOp {
+    /// Whether it compares its operands, producing a `bool`.
+    #[must_use]
+    pub const fn is_comparison(self) -> bool {
+        !matches!(self, Self::Add(_) | Self::Sub(_) | Self::Mul(_) | Self::Div(_) | Self::Rem(_))
+    }
+}
+
+#[tokens]
+#[derive(Eq, PartialEq, Copy, Clone, Display)]
+pub enum UnOp {
+    Neg(Token![-]),
+    Not(Token![!])
+}
@@ -1,7 +1,19 @@
+use crate::attribute::Attribute;
 use crate::item::Item;
+use crate::location::WithSpan;
 
 #[tokens(no_debug)]
 #[derive(Debug, PartialEq, Eq)]
 pub struct TopazFile {
-    pub items: Vec<Item>
+    pub attributes: Vec<Attribute>,
+    pub items: Vec<WithSpan<Item>>,
+    /// The attributes in front of every item, `item_attributes[i]` belong to `items[i]`
+    pub item_attributes: Vec<Vec<Attribute>>,
+}
+
+impl TopazFile {
+    #[must_use]
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|attribute| attribute.name() == name)
+    }
 }
@@ -1,7 +1,9 @@
+use crate::location::Span;
 use crate::token::stream::{ToTokens, TokenStream};
 use crate::token::{PathPartKeyword, SingleToken, TokenTree, EVERYTHING};
 use once_cell::sync::Lazy;
 use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
 use string_interner::backend::BufferBackend;
 use string_interner::symbol::SymbolU32;
 use string_interner::StringInterner;
@@ -35,15 +37,35 @@ fn check_ident(ident: &str) -> &str {
 }
 
 //pub struct Ident(Cow<'static, str>);
+/// An interned identifier.
+///
+/// The [`Span`] is where the identifier was found in the source text.
+/// It is not taken into account when comparing or hashing identifiers,
+/// so `Ident::new("main")` equals any parsed `main`.
 #[tokens(_)]
-#[derive(Copy, Clone, PartialEq, Eq)]
-pub struct Ident(SymbolU32);
+#[derive(Copy, Clone)]
+pub struct Ident(SymbolU32, Span);
+
+impl PartialEq for Ident {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Ident {}
+
+impl Hash for Ident {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
 
 impl Debug for Ident {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Ident")
             .field("symbol", &self.0)
             .field("value", &self.maybe_value().unwrap_or("__BROKEN_SYMBOL__"))
+            .field("span", &self.1)
             .finish()
     }
 }
@@ -74,11 +96,11 @@ impl Ident {
     /// others are alphanumeric or underscore)
     #[must_use]
     pub fn new(ident: &str) -> Self {
-        Self(Self::interner().get_or_intern(check_ident(ident)))
+        Self(Self::interner().get_or_intern(check_ident(ident)), Span::default())
     }
     #[must_use]
     pub fn new_static(ident: &'static str) -> Self {
-        Self(Self::interner().get_or_intern_static(check_ident(ident)))
+        Self(Self::interner().get_or_intern_static(check_ident(ident)), Span::default())
     }
 
     /// # Safety
@@ -86,7 +108,7 @@ impl Ident {
     /// it is marked so because creating unchecked identifiers could lead to undefined behavior.
     #[must_use]
     pub unsafe fn new_unchecked(ident: &str) -> Self {
-        Self(Self::interner().get_or_intern(ident))
+        Self(Self::interner().get_or_intern(ident), Span::default())
     }
 
     #[must_use]
@@ -94,12 +116,12 @@ impl Ident {
         if !check_identifier(ident) {
             return None;
         }
-        Some(Self(Self::interner().get_or_intern(ident)))
+        Some(Self(Self::interner().get_or_intern(ident), Span::default()))
     }
 
     #[must_use]
     pub fn from_keyword<K: PathPartKeyword>() -> Self {
-        Self(Self::interner().get_or_intern_static(K::REPR))
+        Self(Self::interner().get_or_intern_static(K::REPR), Span::default())
     }
 
     #[must_use]
@@ -107,6 +129,22 @@ impl Ident {
         Self::from_keyword::<K>()
     }
 
+    /// Returns the same identifier, located at `span`.
+    #[must_use]
+    pub const fn spanned(self, span: Span) -> Self {
+        Self(self.0, span)
+    }
+
+    #[must_use]
+    pub const fn span(&self) -> Span {
+        self.1
+    }
+
+    #[must_use]
+    pub const fn symbol(&self) -> SymbolU32 {
+        self.0
+    }
+
     #[must_use]
     pub fn maybe_value(&self) -> Option<&str> {
         unsafe { Lazy::force(&crate::INTERNER) }.resolve(self.0)
@@ -0,0 +1,30 @@
+use crate::ident::Ident;
+use crate::location::WithSpan;
+use crate::token::delim::{Curly, Surround};
+use crate::types::Type;
+use crate::visibility::Visibility;
+use crate::Token;
+
+/// ```tp
+/// public enum Shape {
+///     Circle(int),
+///     Rect(int, int),
+///     Empty
+/// }
+/// ```
+/// Values are built by calling a variant with fields like a function, e.g. `Shape::Circle(2)`,
+/// or by naming a variant without any, e.g. `Shape::Empty`.
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct Enum(pub Visibility, pub Token![enum], pub Ident, pub Surround<Curly, Vec<Variant>>);
+
+impl Enum {
+    pub fn variants(&self) -> impl Iterator<Item = &Variant> {
+        self.3.content().iter()
+    }
+}
+
+/// `Name`, or `Name(Type, Type)` with the types of its fields
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct Variant(pub Ident, pub Vec<WithSpan<Type>>);
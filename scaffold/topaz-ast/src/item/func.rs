@@ -1,6 +1,8 @@
+use crate::attribute::Attribute;
 use crate::block::Block;
 use crate::expr::Expr;
 use crate::ident::Ident;
+use crate::location::WithSpan;
 use crate::pattern::Pattern;
 use crate::Token;
 use crate::types::Type;
@@ -8,8 +10,54 @@ use crate::visibility::Visibility;
 
 #[tokens]
 #[derive(Eq, PartialEq)]
-pub struct Func(pub Token![func], pub Visibility, pub Ident, pub Vec<FuncArg>, pub Option<(Token![->], Type)>, pub Block);
+pub struct Func(pub Token![func], pub Visibility, pub Ident, pub Vec<FuncArg>, pub Option<(Token![->], WithSpan<Type>)>, pub FuncBody);
 
+/// The body of a function: a block, or the expression it returns.
+///
+/// ```tp
+/// func double(value: int) -> int = value * 2;
+/// ```
 #[tokens]
 #[derive(Eq, PartialEq)]
-pub struct FuncArg(pub Pattern, pub Type, pub Option<(Token![=], Expr)>);
+pub enum FuncBody {
+    Block(Block),
+    Expr(Token![=], WithSpan<Expr>),
+}
+
+/// ```tp
+/// func consume(move value: str, count: int = 1)
+/// ```
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct FuncArg(
+    /// whether the function takes ownership of the argument
+    pub Option<Token![move]>,
+    pub Pattern,
+    pub WithSpan<Type>,
+    pub Option<(Token![=], WithSpan<Expr>)>,
+);
+
+impl FuncArg {
+    /// Whether the argument is marked `move`.
+    #[must_use]
+    pub const fn is_move(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+/// A function of a trait or an impl, with the attributes in front of it.
+///
+/// ```tp
+/// @override
+/// public func drop(this) {}
+/// ```
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct Method(pub Vec<Attribute>, pub WithSpan<Func>);
+
+impl Method {
+    #[must_use]
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.0.iter().any(|attribute| attribute.name() == name)
+    }
+}
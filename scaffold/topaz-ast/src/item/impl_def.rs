@@ -0,0 +1,33 @@
+use crate::ident::Ident;
+use crate::item::func::Method;
+use crate::location::WithSpan;
+use crate::path::Path;
+use crate::token::delim::{Curly, Surround};
+use crate::types::Type;
+use crate::Token;
+
+/// ```tp
+/// impl<T> Drop for T {
+///     @override
+///     public func drop(this) {}
+/// }
+/// ```
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct Impl(
+    pub Token![impl],
+    /// generic parameters
+    pub Vec<Ident>,
+    /// the implemented trait
+    pub Path,
+    pub Token![for],
+    /// the type the trait is implemented for
+    pub WithSpan<Type>,
+    pub Surround<Curly, Vec<Method>>,
+);
+
+impl Impl {
+    pub fn methods(&self) -> impl Iterator<Item = &Method> {
+        self.5.content().iter()
+    }
+}
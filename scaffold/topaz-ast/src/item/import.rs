@@ -1,8 +1,17 @@
-use crate::path::Path;
+use crate::path::{AsClause, Path};
 
 /// ```tp
 /// import gem::real::create_human;
+/// import gem::real::create_human as create;
 /// ```
 #[tokens(_)]
 #[derive(Debug, Eq, PartialEq)]
-pub struct Import(pub Path);
+pub struct Import(pub Path, pub Option<AsClause>);
+
+impl Import {
+    /// The name this import introduces into scope.
+    #[must_use]
+    pub fn binding(&self) -> Option<&crate::ident::Ident> {
+        self.1.as_ref().map_or_else(|| self.0.last(), |AsClause(_, alias)| Some(alias))
+    }
+}
@@ -1,11 +1,19 @@
 pub mod func;
 pub mod type_alias;
 pub mod import;
+pub mod trait_def;
+pub mod impl_def;
+pub mod struct_def;
+pub mod enum_def;
 
 #[tokens]
 #[derive(Eq, PartialEq)]
 pub enum Item {
     Import(import::Import),
     Func(func::Func),
-    TypeAlias(type_alias::TypeAlias)
+    TypeAlias(type_alias::TypeAlias),
+    Trait(trait_def::Trait),
+    Impl(impl_def::Impl),
+    Struct(struct_def::Struct),
+    Enum(enum_def::Enum),
 }
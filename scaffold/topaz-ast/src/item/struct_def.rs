@@ -0,0 +1,33 @@
+use crate::ident::Ident;
+use crate::location::WithSpan;
+use crate::token::delim::{Curly, Surround};
+use crate::types::Type;
+use crate::visibility::Visibility;
+use crate::Token;
+
+/// ```tp
+/// public struct Point {
+///     x: int,
+///     y: int
+/// }
+/// ```
+/// Values are built by calling the struct like a function, e.g. `Point(x = 1, y = 2)`.
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct Struct(
+    pub Visibility,
+    pub Token![struct],
+    pub Ident,
+    pub Surround<Curly, Vec<StructField>>,
+);
+
+impl Struct {
+    pub fn fields(&self) -> impl Iterator<Item = &StructField> {
+        self.3.content().iter()
+    }
+}
+
+/// `name: Type`
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct StructField(pub Ident, pub WithSpan<Type>);
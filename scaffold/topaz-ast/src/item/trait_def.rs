@@ -0,0 +1,58 @@
+use crate::attribute::Attribute;
+use crate::ident::Ident;
+use crate::item::func::{Func, FuncArg, Method};
+use crate::location::WithSpan;
+use crate::token::delim::{Curly, Surround};
+use crate::types::Type;
+use crate::visibility::Visibility;
+use crate::Token;
+
+/// ```tp
+/// public trait Default {
+///     func default() -> this;
+/// }
+/// ```
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct Trait(
+    pub Visibility,
+    pub Token![trait],
+    pub Ident,
+    pub Surround<Curly, Vec<TraitItem>>,
+);
+
+impl Trait {
+    pub fn items(&self) -> impl Iterator<Item = &TraitItem> {
+        self.3.content().iter()
+    }
+}
+
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub enum TraitItem {
+    /// A method every impl has to define
+    Required(RequiredMethod),
+    /// A method with a default body, impls can override it
+    Provided(Method),
+}
+
+impl TraitItem {
+    #[must_use]
+    pub const fn name(&self) -> &Ident {
+        match self {
+            Self::Required(RequiredMethod(_, _, _, name, ..)) | Self::Provided(Method(_, WithSpan { value: Func(_, _, name, ..), .. })) => name,
+        }
+    }
+}
+
+/// `func default() -> this;`
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct RequiredMethod(
+    pub Vec<Attribute>,
+    pub Visibility,
+    pub Token![func],
+    pub Ident,
+    pub Vec<FuncArg>,
+    pub Option<(Token![->], WithSpan<Type>)>,
+);
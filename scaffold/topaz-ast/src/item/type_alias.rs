@@ -1,6 +1,6 @@
 use crate::ident::Ident;
 use crate::Token;
-use crate::types::{Type, TypeArguments};
+use crate::types::{NormalTypeArguments, Type, TypeArguments, TypePath};
 use crate::visibility::Visibility;
 
 /// ```tp
@@ -16,3 +16,17 @@ pub struct TypeAlias(
     /// the aliased type
     pub Box<Type>,
 );
+
+impl TypeAlias {
+    /// The names of the generic parameters.
+    pub fn params(&self) -> impl Iterator<Item = &Ident> {
+        let params = match &self.2 {
+            TypeArguments::Normal(NormalTypeArguments(params)) => Some(params.content().iter()),
+            _ => None,
+        };
+        params.into_iter().flatten().filter_map(|param| match param {
+            Type::Path(TypePath { path, .. }) if path.len() == 1 => path.first(),
+            _ => None,
+        })
+    }
+}
@@ -32,6 +32,7 @@ static mut INTERNER: Lazy<StringInterner<BufferBackend>> =
 use crate::util::unit_impl;
 use location::{Span, WithSpan};
 
+pub mod attribute;
 pub mod block;
 pub mod expr;
 pub mod file;
@@ -64,11 +65,13 @@ impl<T: private::_Tokens> Tokens for T {}
 
 impl<T: Tokens> private::_Tokens for Vec<T> {}
 
+impl<T: Tokens> private::_Tokens for WithSpan<T> {}
+
 pub trait CustomTokens: Debug {}
 
 impl<T: CustomTokens> private::_Tokens for T {}
 
-unit_impl!(crate::private::_Tokens [char, String, i32, u8]);
+unit_impl!(crate::private::_Tokens [bool, char, String, i32, u8]);
 
 pub trait WithSpannable {
     fn with_span(self, span: impl Into<Span>) -> WithSpan<Self>
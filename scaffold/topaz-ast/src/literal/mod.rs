@@ -8,7 +8,8 @@ pub mod number;
 pub enum Literal {
     String(LiteralString),
     Number(LiteralNumber),
-    Char(LiteralChar)
+    Char(LiteralChar),
+    Bool(LiteralBool)
 }
 
 auto trait NotLiteral {}
@@ -17,6 +18,7 @@ impl !NotLiteral for Literal {}
 impl !NotLiteral for LiteralString {}
 impl !NotLiteral for LiteralChar {}
 impl !NotLiteral for LiteralNumber {}
+impl !NotLiteral for LiteralBool {}
 
 #[tokens]
 #[derive(Eq, PartialEq, Clone, Display)]
@@ -37,3 +39,8 @@ impl<C: Into<char>> From<C> for LiteralChar {
         Self(CharLit::new(value.into()))
     }
 }
+
+/// `true` or `false`
+#[tokens]
+#[derive(Eq, PartialEq, Clone, Copy, Display)]
+pub struct LiteralBool(pub bool);
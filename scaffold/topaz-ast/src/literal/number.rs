@@ -5,9 +5,35 @@ use std::fmt::Display;
 #[tokens]
 #[derive(Eq, PartialEq, Clone, Display)]
 pub enum LiteralNumber {
+    Normal(NormalNumber),
     Binary(BinaryNumber),
 }
 
+impl LiteralNumber {
+    #[must_use]
+    pub const fn suffix(&self) -> &NumberSuffix {
+        match self {
+            Self::Normal(NormalNumber(.., suffix)) | Self::Binary(BinaryNumber(.., suffix)) => suffix,
+        }
+    }
+
+    #[must_use]
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Self::Normal(NormalNumber(sign, ..)) | Self::Binary(BinaryNumber(sign, ..)) => !sign.sign(),
+        }
+    }
+
+    /// The value of the digits, ignoring the sign, `None` if it does not fit in a `u128`.
+    #[must_use]
+    pub fn magnitude(&self) -> Option<u128> {
+        match self {
+            Self::Normal(NormalNumber(_, digits, _)) => fold_digits(digits.iter().map(DecimalDigit::value), 10),
+            Self::Binary(BinaryNumber(_, _, digits, _)) => fold_digits(digits.iter().map(BinaryDigit::value), 2),
+        }
+    }
+}
+
 impl crate::private::_Tokens for () {}
 
 #[tokens]
@@ -41,36 +67,73 @@ pub enum NumberSuffix {
     None,
 
     /// usize
+    #[display(fmt = "usize")]
     Usize,
     /// isize
+    #[display(fmt = "isize")]
     Isize,
 
     /// u8
+    #[display(fmt = "ubyte")]
     Ubyte,
     /// i8
+    #[display(fmt = "byte")]
     Byte,
 
     /// u16
+    #[display(fmt = "ushort")]
     Ushort,
     /// i16
+    #[display(fmt = "short")]
     Short,
 
     /// u32
+    #[display(fmt = "uint")]
     Uint,
     /// i32
+    #[display(fmt = "int")]
     Int,
 
     /// u64
+    #[display(fmt = "ulong")]
     Ulong,
     /// i64
+    #[display(fmt = "long")]
     Long,
 
     /// u128
+    #[display(fmt = "uexplod")]
     Uexplod,
     /// i128
+    #[display(fmt = "explod")]
     Explod,
 }
 
+impl NumberSuffix {
+    /// Every suffix except [`NumberSuffix::None`], by the name it is written with.
+    pub const NAMED: [(&'static str, Self); 12] = [
+        ("usize", Self::Usize),
+        ("isize", Self::Isize),
+        ("ubyte", Self::Ubyte),
+        ("byte", Self::Byte),
+        ("ushort", Self::Ushort),
+        ("short", Self::Short),
+        ("uint", Self::Uint),
+        ("int", Self::Int),
+        ("ulong", Self::Ulong),
+        ("long", Self::Long),
+        ("uexplod", Self::Uexplod),
+        ("explod", Self::Explod),
+    ];
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMED
+            .into_iter()
+            .find_map(|(suffix_name, suffix)| (suffix_name == name).then_some(suffix))
+    }
+}
+
 macro_rules! digits {
     ($($name:ident ::: $($number:literal $variant:ident),*);*$(;)?) => {
         $(
@@ -92,6 +155,16 @@ macro_rules! digits {
                     )*
                 }
             }
+
+            #[must_use]
+            pub const fn from_value(value: u8) -> Option<Self> {
+                match value {
+                    $(
+                    $number => Some(Self::$variant),
+                    )*
+                    _ => None,
+                }
+            }
         }
         )*
     }
@@ -151,6 +224,10 @@ impl BinaryDigit {
     }
 }
 
+fn fold_digits(mut digits: impl Iterator<Item = u8>, radix: u128) -> Option<u128> {
+    digits.try_fold(0_u128, |value, digit| value.checked_mul(radix)?.checked_add(u128::from(digit)))
+}
+
 #[must_use]
 pub fn concat_digits<T: Display>(digits: &[T]) -> String {
     digits
@@ -164,6 +241,14 @@ pub fn concat_digits<T: Display>(digits: &[T]) -> String {
 #[derive(Eq, PartialEq, Clone)]
 pub struct NormalNumber(pub NumberSign, pub Vec<DecimalDigit>, pub NumberSuffix);
 
+impl NormalNumber {
+    /// The value of the digits, ignoring the sign, `None` if it does not fit in a `usize`.
+    #[must_use]
+    pub fn to_usize(&self) -> Option<usize> {
+        self.1.iter().try_fold(0_usize, |value, digit| value.checked_mul(10)?.checked_add(usize::from(digit.value())))
+    }
+}
+
 #[derive(Display)]
 #[display(
     fmt = "{}{}{}{}",
@@ -8,7 +8,7 @@ use std::ops::Range;
 use derive_more::Display;
 
 /// Represents code block location in source text.
-#[derive(Clone, Debug, PartialEq, Default, Copy, Display, Eq)]
+#[derive(Clone, Debug, PartialEq, Default, Copy, Display, Eq, Hash)]
 #[display(fmt = "{start}..{end}")]
 pub struct Span {
     pub start: usize,
@@ -1,25 +1,20 @@
 use crate::ident::Ident;
+use crate::location::Span;
 use crate::punctuated::Punctuated;
 use crate::token::stream::{ToTokens, TokenStream};
 use crate::Token;
-use derive_more::From;
 use std::fmt::{Display, Formatter};
 
+/// `gem::real::create_human`
+///
+/// The first segment may be one of the path keywords (`gem`, `this`, `super`),
+/// see [`crate::token::PathPartKeyword`].
 #[tokens]
-#[derive(Eq, From)]
-pub struct Path(PathInner);
+#[derive(Eq)]
+pub struct Path(pub Punctuated<Ident, Token![::]>);
 
 #[tokens]
-#[derive(Eq, PartialEq)]
-pub enum PathInner {
-    Namespace(Ident, Option<AsClause>),
-    Curly(Ident, Punctuated<Ident, Token![,]>),
-}
-
-
-
-#[tokens]
-#[derive(Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq)]
 pub struct AsClause(pub Token![as], pub Ident);
 
 impl ToTokens for AsClause {
@@ -31,13 +26,46 @@ impl ToTokens for AsClause {
 
 impl Display for AsClause {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        self.to_tokens().fmt(f)
+        write!(f, "as {}", self.1)
     }
 }
 
 impl Path {
     pub fn namespace(namespace: Ident) -> Self {
-        Self(PathInner::Namespace(namespace, None))
+        Self(Punctuated::single(namespace))
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &Ident> {
+        self.0.iter()
+    }
+
+    #[must_use]
+    pub fn first(&self) -> Option<&Ident> {
+        self.0.iter().next()
+    }
+
+    #[must_use]
+    pub fn last(&self) -> Option<&Ident> {
+        self.0.last()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.iter().count()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.first().is_none()
+    }
+
+    /// The span from the first to the last segment.
+    #[must_use]
+    pub fn span(&self) -> Span {
+        match (self.first(), self.last()) {
+            (Some(first), Some(last)) => Span::new(first.span().start, last.span().end),
+            _ => Span::default(),
+        }
     }
 }
 
@@ -47,6 +75,19 @@ impl From<Ident> for Path {
     }
 }
 
+impl Display for Path {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.segments().enumerate() {
+            if i != 0 {
+                f.write_str("::")?;
+            }
+            Display::fmt(segment, f)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl PartialEq for Path {
     fn eq(&self, other: &Self) -> bool {
         self.to_string() == other.to_string()
@@ -1,4 +1,6 @@
 use crate::ident::Ident;
+use crate::path::Path;
+use crate::Token;
 
 #[tokens]
 #[derive(Eq, PartialEq)]
@@ -6,3 +8,13 @@ pub enum Pattern {
     Ident(Ident),
 
 }
+
+/// What the arm of a `match` matches.
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub enum ArmPattern {
+    /// `Shape::Rect(width, height)`, binding the fields of the variant to new locals
+    Variant(Path, Vec<Ident>),
+    /// `_`, any value
+    Wildcard(Token![_]),
+}
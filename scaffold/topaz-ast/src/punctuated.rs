@@ -101,6 +101,15 @@ impl<T: Tokens + Display, P: Tokens + Display> Display for Punctuated<T, P> {
     }
 }
 
+impl<T: Tokens + Clone, P: Tokens> Clone for Punctuated<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            segments: self.segments.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
 impl<T: Tokens, P: Tokens> Default for Punctuated<T, P> {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,87 @@
+use crate::block::Block;
+use crate::expr::Expr;
+use crate::location::WithSpan;
+use crate::pattern::ArmPattern;
+use crate::token::delim::{Curly, Surround};
+use crate::Token;
+
+/// ```tp
+/// return;
+/// return value;
+/// ```
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct ReturnStmt(pub Token![return], pub Option<WithSpan<Expr>>);
+
+/// ```tp
+/// while running {
+///     step();
+/// }
+/// ```
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct WhileStmt(pub Token![while], pub WithSpan<Expr>, pub Block);
+
+/// ```tp
+/// if ready {
+///     start();
+/// } else if waiting {
+///     wait();
+/// } else {
+///     stop();
+/// }
+/// ```
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct IfStmt(pub Token![if], pub WithSpan<Expr>, pub WithSpan<Block>, pub Option<(Token![else], ElseBranch)>);
+
+impl IfStmt {
+    /// Whether control never continues after the statement, because every branch diverges.
+    #[must_use]
+    pub fn diverges(&self) -> bool {
+        self.2.value.diverges()
+            && match &self.3 {
+                Some((_, ElseBranch::Block(block))) => block.value.diverges(),
+                Some((_, ElseBranch::If(if_stmt))) => if_stmt.diverges(),
+                None => false,
+            }
+    }
+}
+
+/// What follows `else`.
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub enum ElseBranch {
+    Block(WithSpan<Block>),
+    If(Box<IfStmt>),
+}
+
+/// ```tp
+/// match shape {
+///     Shape::Circle(radius) => {
+///         circle(radius);
+///     }
+///     _ => {}
+/// }
+/// ```
+/// Runs the first arm matching the value, of which there has to be one for every variant of its enum.
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct MatchStmt(pub Token![match], pub WithSpan<Expr>, pub Surround<Curly, Vec<MatchArm>>);
+
+impl MatchStmt {
+    pub fn arms(&self) -> impl Iterator<Item = &MatchArm> {
+        self.2.content().iter()
+    }
+
+    /// Whether control never continues after the statement, because every arm diverges.
+    #[must_use]
+    pub fn diverges(&self) -> bool {
+        self.arms().all(|MatchArm(_, _, body)| body.value.diverges())
+    }
+}
+
+/// `pattern => { ... }`
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct MatchArm(pub WithSpan<ArmPattern>, pub Token![=>], pub WithSpan<Block>);
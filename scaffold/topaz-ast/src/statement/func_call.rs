@@ -1,5 +1,6 @@
 use crate::expr::Expr;
 use crate::ident::Ident;
+use crate::location::WithSpan;
 use crate::path::Path;
 use crate::punctuated::Punctuated;
 use crate::Token;
@@ -13,4 +14,4 @@ pub struct FuncCallStmt(pub Path, pub Surround<Parentheses, Punctuated<FuncCallA
 /// Some(8u128)
 #[tokens]
 #[derive(Eq, PartialEq)]
-pub struct FuncCallArg(pub Option<(Ident, Token![=])>, pub Expr);
+pub struct FuncCallArg(pub Option<(Ident, Token![=])>, pub WithSpan<Expr>);
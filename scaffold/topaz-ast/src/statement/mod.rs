@@ -1,3 +1,6 @@
+use crate::block::Block;
+
+pub mod control;
 pub mod variable;
 pub mod func_call;
 
@@ -5,5 +8,27 @@ pub mod func_call;
 #[derive(Eq, PartialEq)]
 pub enum Statement {
     Let(variable::LetStmt),
-    FuncCall(func_call::FuncCallStmt)
+    Assign(variable::AssignStmt),
+    FuncCall(func_call::FuncCallStmt),
+    Return(control::ReturnStmt),
+    While(control::WhileStmt),
+    If(control::IfStmt),
+    Match(control::MatchStmt),
+    /// A nested block, opening a new scope
+    Block(Block)
+}
+
+impl Statement {
+    /// Whether control never continues after this statement.
+    #[must_use]
+    pub fn diverges(&self) -> bool {
+        match self {
+            Self::Return(_) => true,
+            Self::Block(block) => block.diverges(),
+            Self::If(if_stmt) => if_stmt.diverges(),
+            Self::Match(match_stmt) => match_stmt.diverges(),
+            // the condition may not hold in the first place
+            Self::Let(_) | Self::Assign(_) | Self::FuncCall(_) | Self::While(_) => false,
+        }
+    }
 }
@@ -1,5 +1,7 @@
 use crate::expr::Expr;
 use crate::ident::Ident;
+use crate::location::WithSpan;
+use crate::path::Path;
 use crate::{Token, types::Type};
 
 /// ```tp
@@ -9,8 +11,15 @@ use crate::{Token, types::Type};
 #[derive(Eq, PartialEq)]
 pub struct LetStmt(
     pub Token![let],
-    pub Option<Token![mut]>, // mutability
+    pub Option<WithSpan<Token![mut]>>, // mutability
     pub Ident, // name
-    pub Option<(Token![:], Type)>, // type
-    pub Option<(Token![=], Expr)>, // initializer
+    pub Option<(Token![:], WithSpan<Type>)>, // type
+    pub Option<(Token![=], WithSpan<Expr>)>, // initializer
 );
+
+/// ```tp
+/// why = (nope, nope);
+/// ```
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct AssignStmt(pub Path, pub Token![=], pub WithSpan<Expr>);
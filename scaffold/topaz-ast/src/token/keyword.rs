@@ -1,4 +1,4 @@
-use topaz_macro::lowercase_ident;
+use topaz_macro::lowercase_str;
 
 mod private {
     use crate::Tokens;
@@ -21,7 +21,7 @@ macro_rules! kw {
         #[derive(Default, Copy, Clone, Eq, PartialEq)]
         pub struct $name;
         impl private::Keyword for $name {
-            const REPR: &'static str = stringify!(lowercase_ident!($name));
+            const REPR: &'static str = lowercase_str!($name);
         }
 
         impl std::fmt::Display for $name {
@@ -69,4 +69,28 @@ kw!(TypeAlias);
 kw!(pathpart This);
 kw!(pathpart Super);
 kw!(pathpart Gem);
-kw!(As);
\ No newline at end of file
+kw!(As);
+/// The `return` keyword, leaving the current function with a value.
+kw!(Return);
+/// The `trait` keyword, declaring a set of methods types can implement.
+kw!(Trait);
+/// The `impl` keyword, implementing a trait for a type.
+kw!(Impl);
+/// The `for` keyword, as in `impl Trait for Type`.
+kw!(For);
+/// The `move` keyword, marking a parameter (or receiver) the function takes ownership of.
+kw!(Move);
+/// The `struct` keyword, declaring a type with named fields.
+kw!(Struct);
+/// The `enum` keyword, declaring a type whose values are one of its variants.
+kw!(Enum);
+/// The `match` keyword, running the arm of the variant a value is.
+kw!(Match);
+/// The `while` keyword, repeating a block as long as a condition holds.
+kw!(While);
+/// The `if` keyword, running a block only if a condition holds.
+kw!(If);
+/// The `else` keyword, running a block if the condition of an `if` does not hold.
+kw!(Else);
+kw!(True);
+kw!(False);
\ No newline at end of file
@@ -15,10 +15,23 @@ everything! {
     [;] => punctuation::Semi,
     [.] => punctuation::Dot,
     [->] => punctuation::Arrow,
+    [=>] => punctuation::FatArrow,
     [::] => punctuation::DoubleColon,
     [=] => punctuation::Equal,
     [-] => punctuation::Minus,
     [+] => punctuation::Plus,
+    [*] => punctuation::Star,
+    [/] => punctuation::Slash,
+    [%] => punctuation::Percent,
+    [==] => punctuation::EqEq,
+    [!=] => punctuation::Ne,
+    [<] => punctuation::Lt,
+    [<=] => punctuation::Le,
+    [>] => punctuation::Gt,
+    [>=] => punctuation::Ge,
+    [!] => punctuation::Not,
+    [@] => punctuation::At,
+    [_] => punctuation::Underscore,
     // prefixes
     [&] => prefix::Ref,
     // keywords
@@ -33,7 +46,20 @@ everything! {
     [super] => keyword::Super,
     [gem] => keyword::Gem,
     [import] => keyword::Import,
-    [as] => keyword::As
+    [as] => keyword::As,
+    [return] => keyword::Return,
+    [trait] => keyword::Trait,
+    [impl] => keyword::Impl,
+    [for] => keyword::For,
+    [move] => keyword::Move,
+    [struct] => keyword::Struct,
+    [enum] => keyword::Enum,
+    [match] => keyword::Match,
+    [while] => keyword::While,
+    [if] => keyword::If,
+    [else] => keyword::Else,
+    [true] => keyword::True,
+    [false] => keyword::False
 }
 
 #[tokens]
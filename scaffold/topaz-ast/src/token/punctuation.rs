@@ -29,9 +29,22 @@ punctuation! {
     ":" Colon;
     ";" Semi;
     "->" Arrow;
+    "=>" FatArrow;
     "::" DoubleColon;
     "=" Equal;
     "." Dot;
     "+" Plus;
     "-" Minus;
+    "*" Star;
+    "/" Slash;
+    "%" Percent;
+    "==" EqEq;
+    "!=" Ne;
+    "<" Lt;
+    "<=" Le;
+    ">" Gt;
+    ">=" Ge;
+    "!" Not;
+    "@" At;
+    "_" Underscore;
 }
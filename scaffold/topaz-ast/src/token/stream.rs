@@ -51,7 +51,7 @@ impl TokenStream {
     }
 
     pub fn append<T: ToTokens>(&mut self, token: &T) {
-        token.write_tokens(&mut self);
+        token.write_tokens(self);
     }
 
     pub fn append_token(&mut self, token: TokenTree, span: impl Into<Span>) {
@@ -73,7 +73,7 @@ impl TokenStream {
 
 impl Display for TokenStream {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for x in self.tokens {
+        for x in &self.tokens {
             x.value.fmt(f)?;
         }
 
@@ -90,8 +90,8 @@ impl Clone for TokenStream {
     }
 
     fn clone_from(&mut self, source: &Self) where Self: Destruct {
-        for i in source.tokens {
-            self.tokens.push(i)
+        for i in &source.tokens {
+            self.tokens.push(i.clone())
         }
 
         self.last_span = source.last_span;
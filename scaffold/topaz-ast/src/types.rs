@@ -1,3 +1,4 @@
+use crate::literal::number::NormalNumber;
 use crate::path::Path;
 use crate::punctuated::Punctuated;
 use crate::token::delim::{AngleBracket, Parentheses, Surround};
@@ -25,7 +26,8 @@ pub enum PrimitiveType {
     Explod,
 
     Char,
-    String
+    String,
+    Bool
 }
 #[tokens]
 #[derive(Eq, PartialEq)]
@@ -66,6 +68,15 @@ pub enum TypeMaybe {
     Nope,
 }
 
+/// `[int; 3]`
+#[tokens]
+#[derive(Eq, PartialEq)]
+pub struct TypeArray {
+    pub element: Box<Type>,
+    pub semi_token: Token![;],
+    pub len: NormalNumber,
+}
+
 #[tokens]
 #[derive(Eq, PartialEq)]
 pub enum Type {
@@ -74,6 +85,7 @@ pub enum Type {
     Reference(TypeReference),
     Func(TypeFunc),
     Maybe(TypeMaybe),
+    Array(TypeArray),
 }
 
 impl Type {
@@ -82,6 +94,7 @@ impl Type {
         match self {
             Self::Path(path) => &path.arguments,
             Self::Reference(reference) => reference.referenced.type_arguments(),
+            Self::Array(array) => array.element.type_arguments(),
             Self::Func(func) => &func.arguments,
             Self::Maybe(maybe) => match maybe {
                 TypeMaybe::Unknown(TypeMaybeUnknown { real_type, .. })
@@ -1,18 +1,31 @@
 use crate::block::Block;
-use crate::expr::{Expr, ExprBorrow, ExprConstAccess, ExprLit, ExprVarAccess};
+use crate::expr::{
+    Expr, ExprArray, ExprBinary, ExprBorrow, ExprConstAccess, ExprField, ExprIndex, ExprInterpolated, ExprLit,
+    ExprUnary, ExprVarAccess,
+};
 use crate::file::TopazFile;
 use crate::ident::Ident;
-use crate::item::func::{Func, FuncArg};
+use crate::item::func::{Func, FuncArg, FuncBody, Method};
+use crate::item::enum_def::{Enum, Variant};
+use crate::item::impl_def::Impl;
+use crate::item::struct_def::{Struct, StructField};
+use crate::item::trait_def::{RequiredMethod, Trait, TraitItem};
 use crate::item::import::Import;
 use crate::item::type_alias::TypeAlias;
 use crate::item::Item;
-use crate::literal::number::{BinaryNumber, LiteralNumber};
-use crate::literal::{Literal, LiteralString};
+use crate::literal::number::{BinaryNumber, LiteralNumber, NormalNumber};
+use crate::literal::{Literal, LiteralBool, LiteralString};
 use crate::path::Path;
-use crate::pattern::Pattern;
+use crate::pattern::{ArmPattern, Pattern};
+use crate::statement::func_call::{FuncCallArg, FuncCallStmt};
+use crate::statement::control::{ElseBranch, IfStmt, MatchArm, MatchStmt, ReturnStmt, WhileStmt};
+use crate::statement::variable::{AssignStmt, LetStmt};
 use crate::statement::Statement;
 use crate::token::delim::Surround;
-use crate::types::{Type, TypeArguments};
+use crate::types::{
+    NormalTypeArguments, ParenthesizedTypeArguments, Type, TypeArguments, TypeArray, TypeFunc, TypeMaybe,
+    TypeMaybeSome, TypeMaybeUnknown, TypePath, TypeReference,
+};
 use crate::visibility::Visibility;
 
 pub trait Visit: Sized {
@@ -44,8 +57,8 @@ pub trait Visit: Sized {
         // noop
     }
 
-    fn visit_type_arguments(&mut self, _type_arguments: &TypeArguments) {
-        // noop
+    fn visit_type_arguments(&mut self, type_arguments: &TypeArguments) {
+        walk_type_arguments(self, type_arguments);
     }
 
     fn visit_func_arg(&mut self, func_arg: &FuncArg) {
@@ -56,6 +69,38 @@ pub trait Visit: Sized {
         walk_typealias(self, typealias);
     }
 
+    fn visit_trait(&mut self, trait_def: &Trait) {
+        walk_trait(self, trait_def);
+    }
+
+    fn visit_required_method(&mut self, method: &RequiredMethod) {
+        walk_required_method(self, method);
+    }
+
+    fn visit_impl(&mut self, impl_def: &Impl) {
+        walk_impl(self, impl_def);
+    }
+
+    fn visit_method(&mut self, method: &Method) {
+        walk_method(self, method);
+    }
+
+    fn visit_struct(&mut self, struct_def: &Struct) {
+        walk_struct(self, struct_def);
+    }
+
+    fn visit_struct_field(&mut self, field: &StructField) {
+        walk_struct_field(self, field);
+    }
+
+    fn visit_enum(&mut self, enum_def: &Enum) {
+        walk_enum(self, enum_def);
+    }
+
+    fn visit_variant(&mut self, variant: &Variant) {
+        walk_variant(self, variant);
+    }
+
     fn visit_literal(&mut self, literal: &Literal) {
         walk_literal(self, literal);
     }
@@ -80,6 +125,30 @@ pub trait Visit: Sized {
         walk_expr_var_access(self, expr_var);
     }
 
+    fn visit_expr_field(&mut self, expr_field: &ExprField) {
+        walk_expr_field(self, expr_field);
+    }
+
+    fn visit_expr_binary(&mut self, expr_binary: &ExprBinary) {
+        walk_expr_binary(self, expr_binary);
+    }
+
+    fn visit_expr_unary(&mut self, expr_unary: &ExprUnary) {
+        walk_expr_unary(self, expr_unary);
+    }
+
+    fn visit_expr_array(&mut self, expr_array: &ExprArray) {
+        walk_expr_array(self, expr_array);
+    }
+
+    fn visit_expr_index(&mut self, expr_index: &ExprIndex) {
+        walk_expr_index(self, expr_index);
+    }
+
+    fn visit_expr_interpolated(&mut self, expr_interpolated: &ExprInterpolated) {
+        walk_expr_interpolated(self, expr_interpolated);
+    }
+
     fn visit_dotted_path(&mut self, path: &Path) {
         walk_dotted_path(self, path);
     }
@@ -96,6 +165,14 @@ pub trait Visit: Sized {
         // noop
     }
 
+    fn visit_bool_literal(&mut self, _bool: &LiteralBool) {
+        // noop
+    }
+
+    fn visit_normal_number_literal(&mut self, _normal: &NormalNumber) {
+        // noop
+    }
+
     fn visit_binary_number_literal(&mut self, _bin: &BinaryNumber) {
         // noop
     }
@@ -112,11 +189,55 @@ pub trait Visit: Sized {
     fn visit_statement(&mut self, statement: &Statement) {
         walk_statement(self, statement);
     }
+
+    fn visit_let_stmt(&mut self, let_stmt: &LetStmt) {
+        walk_let_stmt(self, let_stmt);
+    }
+
+    fn visit_assign_stmt(&mut self, assign_stmt: &AssignStmt) {
+        walk_assign_stmt(self, assign_stmt);
+    }
+
+    fn visit_return_stmt(&mut self, return_stmt: &ReturnStmt) {
+        walk_return_stmt(self, return_stmt);
+    }
+
+    fn visit_while_stmt(&mut self, while_stmt: &WhileStmt) {
+        walk_while_stmt(self, while_stmt);
+    }
+
+    fn visit_if_stmt(&mut self, if_stmt: &IfStmt) {
+        walk_if_stmt(self, if_stmt);
+    }
+
+    fn visit_match_stmt(&mut self, match_stmt: &MatchStmt) {
+        walk_match_stmt(self, match_stmt);
+    }
+
+    fn visit_match_arm(&mut self, arm: &MatchArm) {
+        walk_match_arm(self, arm);
+    }
+
+    fn visit_arm_pattern(&mut self, pattern: &ArmPattern) {
+        walk_arm_pattern(self, pattern);
+    }
+
+    fn visit_func_call(&mut self, func_call: &FuncCallStmt) {
+        walk_func_call(self, func_call);
+    }
+
+    fn visit_func_call_arg(&mut self, arg: &FuncCallArg) {
+        walk_func_call_arg(self, arg);
+    }
+
+    fn visit_type_path(&mut self, type_path: &TypePath) {
+        walk_type_path(self, type_path);
+    }
 }
 
 pub fn walk_file(visitor: &mut impl Visit, file: &TopazFile) {
-    for i in &file.items {
-        visitor.visit_item(i);
+    for item in &file.items {
+        visitor.visit_item(&item.value);
     }
 }
 
@@ -128,31 +249,138 @@ pub fn walk_item(visitor: &mut impl Visit, item: &Item) {
     match item {
         Item::Func(func) => visitor.visit_func(func),
         Item::TypeAlias(typealias) => visitor.visit_typealias(typealias),
-        Item::Import(import) => visitor.visit_import(import)
+        Item::Import(import) => visitor.visit_import(import),
+        Item::Trait(trait_def) => visitor.visit_trait(trait_def),
+        Item::Impl(impl_def) => visitor.visit_impl(impl_def),
+        Item::Struct(struct_def) => visitor.visit_struct(struct_def),
+        Item::Enum(enum_def) => visitor.visit_enum(enum_def),
     }
 }
 
-pub fn walk_func(visitor: &mut impl Visit, Func(_, vis, ident, args, ty, block): &Func) {
+pub fn walk_trait(visitor: &mut impl Visit, trait_def @ Trait(vis, _, ident, _): &Trait) {
+    visitor.visit_visibility(vis);
+    visitor.visit_ident(ident);
+    for item in trait_def.items() {
+        match item {
+            TraitItem::Required(method) => visitor.visit_required_method(method),
+            TraitItem::Provided(method) => visitor.visit_method(method),
+        }
+    }
+}
+
+pub fn walk_required_method(visitor: &mut impl Visit, RequiredMethod(_, vis, _, ident, args, ty): &RequiredMethod) {
     visitor.visit_visibility(vis);
     visitor.visit_ident(ident);
     for arg in args {
         visitor.visit_func_arg(arg);
     }
     if let Some((_, ty)) = ty {
-        visitor.visit_type(ty);
+        visitor.visit_type(&ty.value);
+    }
+}
+
+pub fn walk_impl(visitor: &mut impl Visit, impl_def @ Impl(_, generics, trait_path, _, ty, _): &Impl) {
+    for generic in generics {
+        visitor.visit_ident(generic);
+    }
+    visitor.visit_path(trait_path);
+    visitor.visit_type(&ty.value);
+    for method in impl_def.methods() {
+        visitor.visit_method(method);
+    }
+}
+
+pub fn walk_struct(visitor: &mut impl Visit, struct_def @ Struct(vis, _, ident, _): &Struct) {
+    visitor.visit_visibility(vis);
+    visitor.visit_ident(ident);
+    for field in struct_def.fields() {
+        visitor.visit_struct_field(field);
+    }
+}
+
+pub fn walk_struct_field(visitor: &mut impl Visit, StructField(ident, ty): &StructField) {
+    visitor.visit_ident(ident);
+    visitor.visit_type(&ty.value);
+}
+
+pub fn walk_enum(visitor: &mut impl Visit, enum_def @ Enum(vis, _, ident, _): &Enum) {
+    visitor.visit_visibility(vis);
+    visitor.visit_ident(ident);
+    for variant in enum_def.variants() {
+        visitor.visit_variant(variant);
+    }
+}
+
+pub fn walk_variant(visitor: &mut impl Visit, Variant(ident, fields): &Variant) {
+    visitor.visit_ident(ident);
+    for ty in fields {
+        visitor.visit_type(&ty.value);
+    }
+}
+
+pub fn walk_method(visitor: &mut impl Visit, Method(_, func): &Method) {
+    visitor.visit_func(&func.value);
+}
+
+pub fn walk_func(visitor: &mut impl Visit, Func(_, vis, ident, args, ty, body): &Func) {
+    visitor.visit_visibility(vis);
+    visitor.visit_ident(ident);
+    for arg in args {
+        visitor.visit_func_arg(arg);
+    }
+    if let Some((_, ty)) = ty {
+        visitor.visit_type(&ty.value);
+    }
+    walk_func_body(visitor, body);
+}
+
+pub fn walk_func_body(visitor: &mut impl Visit, body: &FuncBody) {
+    match body {
+        FuncBody::Block(block) => visitor.visit_block(block),
+        FuncBody::Expr(_, expr) => visitor.visit_expr(&expr.value),
     }
-    visitor.visit_block(block);
 }
 
 pub fn walk_type(visitor: &mut impl Visit, ty: &Type) {
-    visitor.visit_type_arguments(ty.type_arguments());
+    match ty {
+        Type::Path(type_path) => visitor.visit_type_path(type_path),
+        Type::Reference(TypeReference { referenced, .. }) => visitor.visit_type(referenced),
+        Type::Array(TypeArray { element, .. }) => visitor.visit_type(element),
+        Type::Maybe(TypeMaybe::Unknown(TypeMaybeUnknown { real_type, .. }) | TypeMaybe::Some(TypeMaybeSome { real_type, .. })) => {
+            visitor.visit_type(real_type);
+        }
+        Type::Func(TypeFunc { arguments, .. }) => visitor.visit_type_arguments(arguments),
+        Type::Primitive(_) | Type::Maybe(TypeMaybe::Nope) => {}
+    }
+}
+
+pub fn walk_type_arguments(visitor: &mut impl Visit, type_arguments: &TypeArguments) {
+    match type_arguments {
+        TypeArguments::None => {}
+        TypeArguments::Parenthesized(ParenthesizedTypeArguments { arguments, return_type, .. }) => {
+            for argument in arguments.content().iter() {
+                visitor.visit_type(argument);
+            }
+            visitor.visit_type(return_type);
+        }
+        TypeArguments::Normal(NormalTypeArguments(arguments)) => {
+            for argument in arguments.content().iter() {
+                visitor.visit_type(argument);
+            }
+        }
+    }
+}
+
+pub fn walk_type_path(visitor: &mut impl Visit, TypePath { path, arguments }: &TypePath) {
+    visitor.visit_path(path);
+    visitor.visit_type_arguments(arguments);
 }
 
-pub fn walk_func_arg(visitor: &mut impl Visit, FuncArg(pattern, ty, init): &FuncArg) {
+pub fn walk_func_arg(visitor: &mut impl Visit, FuncArg(_, pattern, ty, init): &FuncArg) {
     visitor.visit_pattern(pattern);
-    visitor.visit_type(ty);
+    visitor.visit_type(&ty.value);
     if let Some((_, initializer)) = init {
-        visitor.visit_expr(initializer);
+        visitor.visit_expr(&initializer.value);
     }
 }
 
@@ -177,22 +405,59 @@ pub fn walk_dotted_path(visitor: &mut impl Visit, path: &Path) {
 
 pub fn walk_expr(visitor: &mut impl Visit, expr: &Expr) {
     match expr {
-        Expr::FuncCall(call) => (),
+        Expr::FuncCall(call) => visitor.visit_func_call(call),
         Expr::Literal(literal) => visitor.visit_expr_literal(literal),
         Expr::Borrow(borrow) => visitor.visit_expr_borrow(borrow),
         Expr::ConstAccess(const_access) => visitor.visit_expr_const_access(const_access),
         Expr::VariableAccess(var_access) => visitor.visit_expr_var_access(var_access),
+        Expr::Field(field) => visitor.visit_expr_field(field),
+        Expr::Binary(binary) => visitor.visit_expr_binary(binary),
+        Expr::Unary(unary) => visitor.visit_expr_unary(unary),
+        Expr::Array(array) => visitor.visit_expr_array(array),
+        Expr::Index(index) => visitor.visit_expr_index(index),
+        Expr::Interpolated(interpolated) => visitor.visit_expr_interpolated(interpolated),
     }
 }
 
 pub fn walk_expr_borrow(visitor: &mut impl Visit, ExprBorrow(_, _, borrowed): &ExprBorrow) {
-    visitor.visit_expr(&borrowed);
+    visitor.visit_expr(&borrowed.value);
 }
 
 pub fn walk_expr_var_access(visitor: &mut impl Visit, ExprVarAccess(ident): &ExprVarAccess) {
     visitor.visit_dotted_path(ident);
 }
 
+pub fn walk_expr_field(visitor: &mut impl Visit, ExprField(base, _, field): &ExprField) {
+    visitor.visit_expr(&base.value);
+    visitor.visit_ident(field);
+}
+
+pub fn walk_expr_binary(visitor: &mut impl Visit, ExprBinary(lhs, _, rhs): &ExprBinary) {
+    visitor.visit_expr(&lhs.value);
+    visitor.visit_expr(&rhs.value);
+}
+
+pub fn walk_expr_unary(visitor: &mut impl Visit, ExprUnary(_, operand): &ExprUnary) {
+    visitor.visit_expr(&operand.value);
+}
+
+pub fn walk_expr_array(visitor: &mut impl Visit, ExprArray(elements): &ExprArray) {
+    for element in elements.content().iter() {
+        visitor.visit_expr(&element.value);
+    }
+}
+
+pub fn walk_expr_index(visitor: &mut impl Visit, ExprIndex(base, index): &ExprIndex) {
+    visitor.visit_expr(&base.value);
+    visitor.visit_expr(&index.value);
+}
+
+pub fn walk_expr_interpolated(visitor: &mut impl Visit, interpolated: &ExprInterpolated) {
+    for part in interpolated.parts() {
+        visitor.visit_expr(&part.value);
+    }
+}
+
 pub fn walk_expr_const_access(
     visitor: &mut impl Visit,
     ExprConstAccess(const_path): &ExprConstAccess,
@@ -208,26 +473,109 @@ pub fn walk_literal(visitor: &mut impl Visit, literal: &Literal) {
     match literal {
         Literal::String(str) => visitor.visit_str_literal(str),
         Literal::Number(number) => visitor.visit_number_literal(number),
+        Literal::Bool(bool) => visitor.visit_bool_literal(bool),
         Literal::Char(chr) => todo!("char literals")
     }
 }
 
 pub fn walk_number_literal(visitor: &mut impl Visit, number: &LiteralNumber) {
     match number {
+        LiteralNumber::Normal(normal) => visitor.visit_normal_number_literal(normal),
         LiteralNumber::Binary(bin) => visitor.visit_binary_number_literal(bin),
     }
 }
 
-pub fn walk_import(visitor: &mut impl Visit, Import(path): &Import) {
+pub fn walk_import(visitor: &mut impl Visit, Import(path, _): &Import) {
     visitor.visit_path(path);
 }
 
-pub fn walk_block(visitor: &mut impl Visit, Block(Surround(_, statements, _)): &Block) {
+pub fn walk_block(visitor: &mut impl Visit, Block(Surround(_, statements, _), _): &Block) {
     for stmt in statements {
-        visitor.visit_statement(stmt);
+        visitor.visit_statement(&stmt.value);
+    }
+}
+
+pub fn walk_statement(visitor: &mut impl Visit, stmt: &Statement) {
+    match stmt {
+        Statement::Let(let_stmt) => visitor.visit_let_stmt(let_stmt),
+        Statement::Assign(assign_stmt) => visitor.visit_assign_stmt(assign_stmt),
+        Statement::FuncCall(call) => visitor.visit_func_call(call),
+        Statement::Return(return_stmt) => visitor.visit_return_stmt(return_stmt),
+        Statement::While(while_stmt) => visitor.visit_while_stmt(while_stmt),
+        Statement::If(if_stmt) => visitor.visit_if_stmt(if_stmt),
+        Statement::Match(match_stmt) => visitor.visit_match_stmt(match_stmt),
+        Statement::Block(block) => visitor.visit_block(block),
+    }
+}
+
+pub fn walk_let_stmt(visitor: &mut impl Visit, LetStmt(_, _, name, ty, init): &LetStmt) {
+    if let Some((_, ty)) = ty {
+        visitor.visit_type(&ty.value);
+    }
+    // the initializer is evaluated before the binding exists
+    if let Some((_, init)) = init {
+        visitor.visit_expr(&init.value);
+    }
+    visitor.visit_ident(name);
+}
+
+pub fn walk_assign_stmt(visitor: &mut impl Visit, AssignStmt(place, _, value): &AssignStmt) {
+    visitor.visit_expr(&value.value);
+    visitor.visit_path(place);
+}
+
+pub fn walk_return_stmt(visitor: &mut impl Visit, ReturnStmt(_, value): &ReturnStmt) {
+    if let Some(value) = value {
+        visitor.visit_expr(&value.value);
+    }
+}
+
+pub fn walk_while_stmt(visitor: &mut impl Visit, WhileStmt(_, condition, body): &WhileStmt) {
+    visitor.visit_expr(&condition.value);
+    visitor.visit_block(body);
+}
+
+pub fn walk_if_stmt(visitor: &mut impl Visit, IfStmt(_, condition, then, otherwise): &IfStmt) {
+    visitor.visit_expr(&condition.value);
+    visitor.visit_block(&then.value);
+    match otherwise {
+        Some((_, ElseBranch::Block(block))) => visitor.visit_block(&block.value),
+        Some((_, ElseBranch::If(if_stmt))) => visitor.visit_if_stmt(if_stmt),
+        None => {}
+    }
+}
+
+pub fn walk_match_stmt(visitor: &mut impl Visit, match_stmt @ MatchStmt(_, scrutinee, _): &MatchStmt) {
+    visitor.visit_expr(&scrutinee.value);
+    for arm in match_stmt.arms() {
+        visitor.visit_match_arm(arm);
+    }
+}
+
+pub fn walk_match_arm(visitor: &mut impl Visit, MatchArm(pattern, _, body): &MatchArm) {
+    visitor.visit_arm_pattern(&pattern.value);
+    visitor.visit_block(&body.value);
+}
+
+pub fn walk_arm_pattern(visitor: &mut impl Visit, pattern: &ArmPattern) {
+    match pattern {
+        ArmPattern::Variant(path, bindings) => {
+            visitor.visit_path(path);
+            for binding in bindings {
+                visitor.visit_ident(binding);
+            }
+        }
+        ArmPattern::Wildcard(_) => {}
+    }
+}
+
+pub fn walk_func_call(visitor: &mut impl Visit, FuncCallStmt(path, args): &FuncCallStmt) {
+    visitor.visit_path(path);
+    for arg in args.content().iter() {
+        visitor.visit_func_call_arg(arg);
     }
 }
 
-pub fn walk_statement(_visitor: &mut impl Visit, _stmt: &Statement) {
-    // TODO
+pub fn walk_func_call_arg(visitor: &mut impl Visit, FuncCallArg(_, expr): &FuncCallArg) {
+    visitor.visit_expr(&expr.value);
 }
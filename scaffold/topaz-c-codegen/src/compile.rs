@@ -0,0 +1,104 @@
+//! `compile.rs` - compiles the generated C with the C compiler of the system.
+//!
+//! The compiler is `cc` and the archiver `ar`, which can be replaced with the `CC` and `AR` environment variables,
+//! as with most build systems. The source is only written to a temporary file for them.
+use crate::CodegenError;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use topaz_codegen::GemType;
+
+/// What [`crate::build`] makes of a gem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// The C source, `.c`
+    Source,
+    /// `.s`
+    Assembly,
+    /// `.o`
+    Object,
+    /// A program or a library
+    Linked(GemType),
+}
+
+/// What keeps a gem from being built.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    #[error(transparent)]
+    Codegen(#[from] CodegenError),
+    #[error("cannot write `{path}`: {message}")]
+    Write { path: String, message: String },
+    #[error("cannot run `{program}`: {message}")]
+    Spawn { program: String, message: String },
+    #[error("`{program}` failed with {status}:\n{stderr}")]
+    Failed { program: String, status: String, stderr: String },
+}
+
+/// Compiles the C `source` into `output`, written to `path`.
+///
+/// # Errors
+/// If a file cannot be written, or the compiler or archiver cannot be run or fails.
+pub fn compile(source: &str, output: Output, path: &Path) -> Result<(), BuildError> {
+    if output == Output::Source {
+        return write(path, source);
+    }
+
+    let stem = path.file_stem().map_or_else(|| "gem".into(), |stem| stem.to_string_lossy().into_owned());
+    let c_file = temporary(&format!("{stem}.c"));
+    write(&c_file, source)?;
+    let result = match output {
+        Output::Source => Ok(()),
+        Output::Assembly => run(cc(&c_file).arg("-S").arg("-o").arg(path)),
+        Output::Object => run(cc(&c_file).arg("-c").arg("-fPIC").arg("-o").arg(path)),
+        Output::Linked(GemType::Bin) => run(cc(&c_file).arg("-o").arg(path)),
+        Output::Linked(GemType::SharedLib) => run(cc(&c_file).arg("-shared").arg("-fPIC").arg("-o").arg(path)),
+        Output::Linked(GemType::StaticLib) => {
+            let object = temporary(&format!("{stem}.o"));
+            let result = run(cc(&c_file).arg("-c").arg("-o").arg(&object))
+                .and_then(|()| run(Command::new(tool("AR", "ar")).arg("rcs").arg(path).arg(&object)));
+            let _ = fs::remove_file(&object);
+            result
+        }
+    };
+    let _ = fs::remove_file(&c_file);
+    result
+}
+
+/// The compiler, compiling `c_file` as C11.
+fn cc(c_file: &Path) -> Command {
+    let mut command = Command::new(tool("CC", "cc"));
+    command.arg("-std=c11").arg(c_file);
+    command
+}
+
+fn run(command: &mut Command) -> Result<(), BuildError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let result =
+        command.output().map_err(|error| BuildError::Spawn { program: program.clone(), message: error.to_string() })?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(BuildError::Failed {
+            program,
+            status: result.status.to_string(),
+            stderr: String::from_utf8_lossy(&result.stderr).into_owned(),
+        })
+    }
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), BuildError> {
+    fs::write(path, contents)
+        .map_err(|error| BuildError::Write { path: path.display().to_string(), message: error.to_string() })
+}
+
+/// A file in the temporary directory, of this process only.
+fn temporary(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("topaz-{}-{name}", std::process::id()))
+}
+
+/// The program set by the environment variable `var`, `default` if it is not set.
+fn tool(var: &str, default: &str) -> OsString {
+    env::var_os(var).filter(|program| !program.is_empty()).unwrap_or_else(|| default.into())
+}
@@ -0,0 +1,495 @@
+//! `function.rs` - the C of a function, generated from its MIR body.
+use crate::types::{declaration, int_limits, is_void, Types};
+use crate::{intrinsics, CodegenError};
+use std::collections::HashSet;
+use std::fmt::Write;
+use topaz_codegen::PANIC_STATUS;
+use topaz_hir::Callee;
+use topaz_mir::{
+    BinOp, BlockId, Body, ConstValue, Constant, Local, MirGem, Operand, Place, Rvalue, StatementKind, TerminatorKind,
+    UnOp,
+};
+use topaz_type_checker::ty::{IntTy, Ty};
+
+pub(crate) struct FnCodegen<'a, 'g> {
+    gem: &'g MirGem,
+    /// The name of every function, in the order of [`MirGem::bodies`]
+    functions: &'a [String],
+    types: &'a Types<'g>,
+    body: &'g Body,
+    /// The names of the locals and temporaries of the function
+    names: crate::Names,
+    /// The name and C type of every local, `None` for those of `()`
+    locals: Vec<Option<(String, String)>>,
+    /// The blocks that are jumped to rather than followed, which get a label
+    targets: HashSet<BlockId>,
+    out: String,
+}
+
+impl<'a, 'g> FnCodegen<'a, 'g> {
+    pub(crate) fn new(
+        gem: &'g MirGem,
+        functions: &'a [String],
+        types: &'a mut Types<'g>,
+        body: &'g Body,
+    ) -> Result<Self, CodegenError> {
+        let mut local_types = Vec::with_capacity(body.locals.len());
+        for decl in &body.locals {
+            // the results of the functions outside of the gem but `std::fmt::format` are not typed, and are `()`
+            if is_void(&decl.ty) || matches!(decl.ty.peel(), Ty::Var(_) | Ty::Error) {
+                local_types.push(None);
+            } else {
+                local_types.push(Some(types.c_type(&decl.ty).ok_or_else(|| unsupported(body, &decl.ty))?));
+            }
+        }
+
+        // named after the types, so that no local hides one
+        let mut names = types.names.clone();
+        let locals = local_types
+            .into_iter()
+            .enumerate()
+            .map(|(index, ty)| {
+                let decl = &body.locals[index];
+                ty.map(|ty| (names.fresh(decl.name.as_deref().unwrap_or(&format!("_{index}"))), ty))
+            })
+            .collect();
+        // the last successor of a block comes after it if it is the next one
+        let targets = body
+            .block_ids()
+            .flat_map(|block| {
+                let successors = body.successors(block);
+                let last = successors.len().saturating_sub(1);
+                successors.into_iter().enumerate().filter(move |&(index, to)| index < last || !follows(block, to))
+            })
+            .map(|(_, to)| to)
+            .collect();
+        Ok(Self { gem, functions, types, body, names, locals, targets, out: String::new() })
+    }
+
+    /// The definition of the function, called `name`.
+    pub(crate) fn define(mut self, name: &str) -> Result<String, CodegenError> {
+        let body = self.body;
+        let ret = self.locals[0].as_ref().map_or("void", |(_, ty)| ty.as_str());
+        let params: Vec<_> =
+            body.args().filter_map(|arg| self.locals[arg.index()].as_ref()).map(|(n, ty)| declaration(ty, n)).collect();
+        let params = if params.is_empty() { "void".to_owned() } else { params.join(", ") };
+        let _ = writeln!(self.out, "{ret} {name}({params}) {{");
+
+        let declarations: Vec<_> = self
+            .locals
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !(1..=body.arg_count).contains(index))
+            .filter_map(|(_, local)| local.as_ref())
+            .map(|(n, ty)| format!("    {};\n", declaration(ty, n)))
+            .collect();
+        if !declarations.is_empty() {
+            self.out.push_str(&declarations.concat());
+            self.out.push('\n');
+        }
+
+        for block in body.block_ids() {
+            self.block(block)?;
+        }
+        self.out.push_str("}\n");
+        Ok(self.out)
+    }
+
+    fn block(&mut self, block: BlockId) -> Result<(), CodegenError> {
+        if self.targets.contains(&block) {
+            let _ = writeln!(self.out, "bb{}:", block.0);
+        }
+        let data = self.body.block(block);
+        for statement in &data.statements {
+            if let StatementKind::Assign(place, rvalue) = &statement.kind {
+                self.assign(place, rvalue)?;
+            }
+        }
+
+        match &data.terminator.kind {
+            TerminatorKind::Goto(target) => self.jump(block, *target, 1)?,
+            TerminatorKind::If { cond, then, otherwise } => {
+                let cond = self.operand(cond)?;
+                if self.has_phis(*then) {
+                    let _ = writeln!(self.out, "    if ({cond}) {{");
+                    self.jump(block, *then, 2)?;
+                    self.out.push_str("    }\n");
+                } else {
+                    let _ = writeln!(self.out, "    if ({cond}) goto bb{};", then.0);
+                }
+                self.jump(block, *otherwise, 1)?;
+            }
+            TerminatorKind::Switch { discr, targets } => {
+                let discr = self.operand(discr)?;
+                // the discriminant is always in range, so the last target is the one of every other value
+                if let Some((last, rest)) = targets.split_last() {
+                    let _ = writeln!(self.out, "    switch ({discr}) {{");
+                    for (index, target) in rest.iter().enumerate() {
+                        let _ = writeln!(self.out, "    case {index}:");
+                        self.jump(block, *target, 2)?;
+                    }
+                    self.out.push_str("    }\n");
+                    self.jump(block, *last, 1)?;
+                } else {
+                    self.out.push_str("    abort();\n");
+                }
+            }
+            TerminatorKind::Call {
+                callee: callee @ (Callee::Fn { name, .. } | Callee::Method { name, .. }),
+                args,
+                dest,
+                target,
+                ..
+            } => {
+                let call = self.call(callee, name, args)?;
+                match self.place(dest)? {
+                    Some((dest, _)) => {
+                        let _ = writeln!(self.out, "    {dest} = {call};");
+                    }
+                    None => {
+                        let _ = writeln!(self.out, "    {call};");
+                    }
+                }
+                self.jump(block, *target, 1)?;
+            }
+            TerminatorKind::Call { callee: Callee::External(path), args, dest, target, .. } => {
+                let args = args
+                    .iter()
+                    .map(|arg| Ok(intrinsics::Arg { operand: arg, expr: self.operand(arg)?, ty: self.operand_ty(arg) }))
+                    .collect::<Result<Vec<_>, CodegenError>>()?;
+                let dest = self.place(dest)?.map(|(dest, _)| dest);
+                let statement = intrinsics::call(&self.body.name, path, &args, dest.as_deref())?;
+                let _ = writeln!(self.out, "    {statement}");
+                self.jump(block, *target, 1)?;
+            }
+            TerminatorKind::Return => match &self.locals[Local::RETURN.index()] {
+                Some((name, _)) => {
+                    let _ = writeln!(self.out, "    return {name};");
+                }
+                None => self.out.push_str("    return;\n"),
+            },
+            TerminatorKind::Unreachable => self.out.push_str("    abort();\n"),
+        }
+        Ok(())
+    }
+
+    fn has_phis(&self, block: BlockId) -> bool {
+        self.body.block(block).phis.iter().any(|phi| self.locals[phi.dest.index()].is_some())
+    }
+
+    /// Goes from `from` to `to`, assigning the phis of `to` first.
+    fn jump(&mut self, from: BlockId, to: BlockId, depth: usize) -> Result<(), CodegenError> {
+        let indent = "    ".repeat(depth);
+        let mut values = Vec::new();
+        for phi in &self.body.block(to).phis {
+            let Some((dest, ty)) = self.locals[phi.dest.index()].clone() else { continue };
+            if let Some((_, operand)) = phi.args.iter().find(|(block, _)| *block == from) {
+                values.push((dest, ty, self.operand(operand)?));
+            }
+        }
+
+        if let [(dest, _, value)] = values.as_slice() {
+            let _ = writeln!(self.out, "{indent}{dest} = {value};");
+        } else if !values.is_empty() {
+            // the phis are assigned at once, so every value is read before any of them is written
+            let _ = writeln!(self.out, "{indent}{{");
+            let mut temporaries = Vec::with_capacity(values.len());
+            for (dest, ty, value) in &values {
+                let temporary = self.names.fresh(&format!("{dest}_next"));
+                let _ = writeln!(self.out, "{indent}    {} = {value};", declaration(ty, &temporary));
+                temporaries.push(temporary);
+            }
+            for ((dest, _, _), temporary) in values.iter().zip(&temporaries) {
+                let _ = writeln!(self.out, "{indent}    {dest} = {temporary};");
+            }
+            let _ = writeln!(self.out, "{indent}}}");
+        }
+        // the jumps of a block that are not nested are its last statement
+        if depth > 1 || !follows(from, to) {
+            let _ = writeln!(self.out, "{indent}goto bb{};", to.0);
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, place: &Place, rvalue: &Rvalue) -> Result<(), CodegenError> {
+        let Some((dest, ty)) = self.place(place)? else { return Ok(()) };
+        let value = match rvalue {
+            Rvalue::Use(operand) => self.operand(operand)?,
+            Rvalue::Ref { place: borrowed, .. } => {
+                let Some((borrowed, _)) = self.place(borrowed)? else { return Err(unsupported(self.body, &ty)) };
+                format!("&{borrowed}")
+            }
+            Rvalue::Aggregate { def, operands } => {
+                let name = self.types.struct_name(*def).ok_or_else(|| unsupported(self.body, &ty))?.to_owned();
+                let mut fields = Vec::with_capacity(operands.len());
+                for (index, operand) in operands.iter().enumerate() {
+                    let field = self.types.field_name(*def, index).ok_or_else(|| unsupported(self.body, &ty))?;
+                    fields.push(format!(".{field} = {}", self.operand(operand)?));
+                }
+                if fields.is_empty() {
+                    format!("({name}){{0}}")
+                } else {
+                    format!("({name}){{ {} }}", fields.join(", "))
+                }
+            }
+            Rvalue::Variant { def, index, operands } => {
+                let name = self.types.enum_name(*def).ok_or_else(|| unsupported(self.body, &ty))?.to_owned();
+                let mut fields = Vec::with_capacity(operands.len());
+                for (field, operand) in operands.iter().enumerate() {
+                    fields.push(format!("._{field} = {}", self.operand(operand)?));
+                }
+                if fields.is_empty() {
+                    format!("({name}){{ .tag = {index} }}")
+                } else {
+                    let variant = self.types.variant_name(*def, *index).ok_or_else(|| unsupported(self.body, &ty))?;
+                    format!("({name}){{ .tag = {index}, .payload.{variant} = {{ {} }} }}", fields.join(", "))
+                }
+            }
+            Rvalue::Discriminant(operand) => format!("{}.tag", self.operand(operand)?),
+            Rvalue::Payload { operand, variant, field } => {
+                let enum_ty = self.operand_ty(operand);
+                let Ty::Enum { def, .. } = enum_ty.peel() else { return Err(unsupported(self.body, &enum_ty)) };
+                let variant =
+                    self.types.variant_name(*def, *variant).ok_or_else(|| unsupported(self.body, &enum_ty))?;
+                format!("{}.payload.{variant}._{field}", self.operand(operand)?)
+            }
+            Rvalue::Array(operands) => {
+                let name = self.types.array_name(&ty).ok_or_else(|| unsupported(self.body, &ty))?.to_owned();
+                let elements = operands.iter().map(|operand| self.operand(operand)).collect::<Result<Vec<_>, _>>()?;
+                if elements.is_empty() {
+                    format!("({name}){{0}}")
+                } else {
+                    format!("({name}){{ {{ {} }} }}", elements.join(", "))
+                }
+            }
+            Rvalue::Index { operands: [array, index] } => {
+                let (array_ty, index_ty) = (self.operand_ty(array), self.operand_ty(index));
+                let Ty::Array { len, .. } = array_ty.peel() else { return Err(unsupported(self.body, &array_ty)) };
+                let Ty::Int(int) = index_ty.peel() else { return Err(unsupported(self.body, &index_ty)) };
+                // a constant index is only checked if it may be out of bounds
+                let in_bounds = matches!(index, Operand::Const(Constant { value: ConstValue::Int(index), .. })
+                    if usize::try_from(*index).map_or(false, |index| index < *len));
+                let (array, index) = (self.operand(array)?, self.operand(index)?);
+                if !in_bounds {
+                    self.check_bounds(&index, *int, *len);
+                }
+                format!("{array}.items[{index}]")
+            }
+            Rvalue::BinaryOp { op, operands: [lhs, rhs] } => {
+                let (lhs, rhs) = (self.operand(lhs)?, self.operand(rhs)?);
+                if op.is_comparison() {
+                    format!("{lhs} {} {rhs}", op.symbol())
+                } else {
+                    let Ty::Int(int) = ty.peel() else { return Err(unsupported(self.body, &ty)) };
+                    self.arithmetic(*op, *int, &dest, &lhs, &rhs);
+                    return Ok(());
+                }
+            }
+            Rvalue::UnaryOp { op: UnOp::Not, operand } => format!("!{}", self.operand(operand)?),
+            Rvalue::UnaryOp { op: UnOp::Neg, operand } => {
+                let Ty::Int(int) = ty.peel() else { return Err(unsupported(self.body, &ty)) };
+                let operand = self.operand(operand)?;
+                self.overflowing(BinOp::Sub, *int, &dest, "0", &operand);
+                return Ok(());
+            }
+        };
+        let _ = writeln!(self.out, "    {dest} = {value};");
+        Ok(())
+    }
+
+    /// Assigns `lhs op rhs` of the integer type `int` to `dest`, panicking if it overflows or divides by zero.
+    fn arithmetic(&mut self, op: BinOp, int: IntTy, dest: &str, lhs: &str, rhs: &str) {
+        match op {
+            BinOp::Add | BinOp::Sub | BinOp::Mul => self.overflowing(op, int, dest, lhs, rhs),
+            _ => {
+                let symbol = op.symbol();
+                self.panic_if(&format!("{rhs} == 0"), &format!("`{symbol}` divides by zero"));
+                if !int.is_signed() {
+                    let _ = writeln!(self.out, "    {dest} = {lhs} {symbol} {rhs};");
+                } else if op == BinOp::Div {
+                    // the quotient of the smallest value by `-1` is one more than the largest
+                    let (min, _) = int_limits(int);
+                    self.panic_if(&format!("{rhs} == -1 && {lhs} == {min}"), &overflows(op, int));
+                    let _ = writeln!(self.out, "    {dest} = {rhs} == -1 ? -({lhs}) : {lhs} / {rhs};");
+                } else {
+                    // the remainder by `-1` is always `0`, but C leaves the one of the smallest value undefined
+                    let _ = writeln!(self.out, "    {dest} = {rhs} == -1 ? 0 : {lhs} % {rhs};");
+                }
+            }
+        }
+    }
+
+    /// Assigns `lhs op rhs` of the integer type `int` to `dest`, where `op` is `+`, `-` or `*`, panicking if the
+    /// result does not fit the type. The operands are compared with the limits of the type first, as computing the
+    /// result would be undefined for signed integers.
+    fn overflowing(&mut self, op: BinOp, int: IntTy, dest: &str, lhs: &str, rhs: &str) {
+        let (min, max) = int_limits(int);
+        let condition = match (op, int.is_signed()) {
+            (BinOp::Add, true) => format!("({rhs} > 0 && {lhs} > {max} - {rhs}) || ({rhs} < 0 && {lhs} < {min} - {rhs})"),
+            (BinOp::Add, false) => format!("{lhs} > {max} - {rhs}"),
+            (BinOp::Sub, true) => format!("({rhs} < 0 && {lhs} > {max} + {rhs}) || ({rhs} > 0 && {lhs} < {min} + {rhs})"),
+            (BinOp::Sub, false) => format!("{lhs} < {rhs}"),
+            (_, true) => format!(
+                "({lhs} > 0 && {rhs} > 0 && {lhs} > {max} / {rhs}) || ({lhs} > 0 && {rhs} < 0 && {rhs} < {min} / {lhs}) \
+                 || ({lhs} < 0 && {rhs} > 0 && {lhs} < {min} / {rhs}) || ({lhs} < 0 && {rhs} < 0 && {rhs} < {max} / {lhs})"
+            ),
+            (_, false) => format!("{rhs} != 0 && {lhs} > {max} / {rhs}"),
+        };
+        self.panic_if(&condition, &overflows(op, int));
+        let _ = writeln!(self.out, "    {dest} = {lhs} {} {rhs};", op.symbol());
+    }
+
+    /// Panics if `index`, of the integer type `int`, is out of the bounds of an array of `len` elements, printing it
+    /// like the interpreter does.
+    fn check_bounds(&mut self, index: &str, int: IntTy, len: usize) {
+        let condition =
+            if int.is_signed() { format!("{index} < 0 || {index} >= {len}") } else { format!("{index} >= {len}") };
+        let message =
+            format!("`{}` panicked: index out of bounds: the length is {len} but the index is ", self.body.name);
+        let message = string_literal(&format!("{}%", message.replace('%', "%%")));
+        // the widest integers are printed as wide as the C library can
+        let (format, value) = if int.is_signed() { ("PRId64", "int64_t") } else { ("PRIu64", "uint64_t") };
+        let _ = writeln!(self.out, "    if ({condition}) {{");
+        let _ = writeln!(self.out, "        fprintf(stderr, {message} {format} \"\\n\", ({value}){index});");
+        let _ = writeln!(self.out, "        exit({PANIC_STATUS});\n    }}");
+    }
+
+    /// Panics with `message` if `condition` holds: prints it and exits with the status of [`PANIC_STATUS`], once
+    /// what was printed before is written out.
+    fn panic_if(&mut self, condition: &str, message: &str) {
+        let message = string_literal(&format!("`{}` panicked: {message}\n", self.body.name));
+        let _ = writeln!(
+            self.out,
+            "    if ({condition}) {{\n        fputs({message}, stderr);\n        exit({PANIC_STATUS});\n    }}"
+        );
+    }
+
+    /// The expression of `place`, with its type, `None` if it is of `()`.
+    fn place(&self, place: &Place) -> Result<Option<(String, Ty)>, CodegenError> {
+        let Some((mut expr, _)) = self.locals[place.local.index()].clone() else { return Ok(None) };
+        let mut ty = self.body.local(place.local).ty.clone();
+        for field in &place.projection {
+            let mut through_ref = false;
+            while let Ty::Ref { ty: pointee, .. } = ty.peel() {
+                if through_ref {
+                    expr = format!("(*{expr})");
+                }
+                ty = (**pointee).clone();
+                through_ref = true;
+            }
+            let Ty::Struct { def, .. } = ty.peel() else { return Err(unsupported(self.body, &ty)) };
+            let def = *def;
+            let field_name = self.types.field_name(def, *field).ok_or_else(|| unsupported(self.body, &ty))?;
+            expr = if through_ref { format!("{expr}->{field_name}") } else { format!("{expr}.{field_name}") };
+            ty = self
+                .gem
+                .struct_def(def)
+                .and_then(|struct_def| struct_def.fields.get(*field))
+                .map_or(Ty::Error, |field| field.ty.clone());
+        }
+        Ok((!is_void(&ty)).then_some((expr, ty)))
+    }
+
+    /// The C expression of `operand`, which is not of `()`.
+    fn operand(&self, operand: &Operand) -> Result<String, CodegenError> {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => {
+                let ty = self.gem.place_ty(self.body, place);
+                Ok(self.place(place)?.ok_or_else(|| unsupported(self.body, &ty))?.0)
+            }
+            Operand::Const(constant) => self.constant(constant),
+        }
+    }
+
+    fn constant(&self, constant: &Constant) -> Result<String, CodegenError> {
+        Ok(match &constant.value {
+            ConstValue::Int(value) => int_literal(*value),
+            ConstValue::Bool(value) => value.to_string(),
+            ConstValue::Char(value) => format!("0x{:X}", u32::from(*value)),
+            ConstValue::Str(value) => format!("(str){{ {}, {} }}", string_literal(value), value.len()),
+            ConstValue::Void => return Err(unsupported(self.body, &Ty::Void)),
+            ConstValue::Named(name) => {
+                return Err(CodegenError::UnknownConstant { name: name.clone(), function: self.body.name.clone() })
+            }
+        })
+    }
+
+    /// The call to `callee`, a function of the gem called `name`.
+    fn call(&self, callee: &Callee, name: &str, args: &[Operand]) -> Result<String, CodegenError> {
+        let index = self.gem.callee_index(callee).ok_or_else(|| CodegenError::UnknownFunction {
+            callee: name.to_owned(),
+            function: self.body.name.clone(),
+        })?;
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            if !is_void(&self.operand_ty(arg)) {
+                values.push(self.operand(arg)?);
+            }
+        }
+        Ok(format!("{}({})", self.functions[index], values.join(", ")))
+    }
+
+    fn operand_ty(&self, operand: &Operand) -> Ty {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.gem.place_ty(self.body, place),
+            Operand::Const(constant) => constant.ty.clone(),
+        }
+    }
+}
+
+/// The message of the panic of `op` overflowing `int`.
+fn overflows(op: BinOp, int: IntTy) -> String {
+    format!("`{}` overflows `{}`", op.symbol(), int.name())
+}
+
+fn unsupported(body: &Body, ty: &Ty) -> CodegenError {
+    CodegenError::UnsupportedType { ty: ty.to_string(), function: body.name.clone() }
+}
+
+/// `value` as an integer literal, which C converts to the type it is assigned to.
+fn int_literal(value: i128) -> String {
+    if let Ok(value) = i32::try_from(value) {
+        // `-2147483648` is the negation of a literal too big for an `int`
+        return if value == i32::MIN { "(-2147483647 - 1)".to_owned() } else { value.to_string() };
+    }
+    if let Ok(value) = i64::try_from(value) {
+        return if value == i64::MIN { "INT64_MIN".to_owned() } else { format!("INT64_C({value})") };
+    }
+    if let Ok(value) = u64::try_from(value) {
+        return format!("UINT64_C({value})");
+    }
+
+    // the high and low 64 bits of a value only a 128-bit integer can hold
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    let (high, low) = ((value >> 64) as u64, value as u64);
+    format!("(((unsigned __int128)UINT64_C({high}) << 64) | UINT64_C({low}))")
+}
+
+/// `value` as a C string literal: bytes that are not printable ASCII are escaped in octal.
+pub(crate) fn string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    let mut previous = 0;
+    for byte in value.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'\n' => literal.push_str("\\n"),
+            b'\t' => literal.push_str("\\t"),
+            // `??` starts a trigraph
+            b'?' if previous == b'?' => literal.push_str("\\?"),
+            b' '..=b'~' => literal.push(char::from(byte)),
+            _ => {
+                let _ = write!(literal, "\\{byte:03o}");
+            }
+        }
+        previous = byte;
+    }
+    literal.push('"');
+    literal
+}
+
+/// Whether `to` is generated right after `from`, so that going to it needs no `goto`.
+fn follows(from: BlockId, to: BlockId) -> bool {
+    to.0 == from.0 + 1
+}
@@ -0,0 +1,123 @@
+//! `intrinsics.rs` - the functions of the standard library the backend generates the code of itself.
+//!
+//! The standard library only declares them, e.g. `println` in `tstd/src/io.tp`, so a call to one is a call
+//! to a function outside of the gem, by the path it resolved to. They are built on the C library.
+use crate::function::string_literal;
+use crate::types::int_type;
+use crate::CodegenError;
+use topaz_hir::{Callee, FORMAT};
+use topaz_mir::{ConstValue, Constant, MirGem, Operand, TerminatorKind};
+use topaz_type_checker::ty::{IntTy, Ty};
+
+/// The name of the C function [`FORMAT`] is a call to.
+pub(crate) const FORMAT_FUNCTION: &str = "topaz_format";
+
+/// The definition of [`FORMAT_FUNCTION`], which the source only has if the gem calls [`FORMAT`], see [`formats`].
+pub(crate) const FORMAT_DEFINITION: &str = r"/* Formats what follows `format` like `printf`, into bytes that are never freed. */
+static str topaz_format(const char *format, ...) {
+    va_list args;
+    va_list again;
+    va_start(args, format);
+    va_copy(again, args);
+    int len = vsnprintf(NULL, 0, format, args);
+    va_end(args);
+    char *ptr = malloc((size_t)len + 1);
+    vsnprintf(ptr, (size_t)len + 1, format, again);
+    va_end(again);
+    return (str){ ptr, (size_t)len };
+}
+";
+
+/// An argument of a call.
+pub(crate) struct Arg<'a> {
+    pub(crate) operand: &'a Operand,
+    /// The C expression of the operand
+    pub(crate) expr: String,
+    pub(crate) ty: Ty,
+}
+
+/// The statement calling the function of the standard library at `path` from `function`, assigning what it
+/// returns to `dest` if that is stored.
+pub(crate) fn call(function: &str, path: &str, args: &[Arg], dest: Option<&str>) -> Result<String, CodegenError> {
+    match (path, args) {
+        ("std::io::println", [arg]) => println(arg),
+        (FORMAT, args) => {
+            let (format, values) = conversions(args)?;
+            let call =
+                format!("{FORMAT_FUNCTION}({})", std::iter::once(format).chain(values).collect::<Vec<_>>().join(", "));
+            // formatting has no effect of its own
+            Ok(dest.map_or_else(|| format!("(void){call};"), |dest| format!("{dest} = {call};")))
+        }
+        _ => Err(CodegenError::UnknownFunction { callee: path.to_owned(), function: function.to_owned() }),
+    }
+}
+
+/// Whether a function of `gem` calls [`FORMAT`].
+pub(crate) fn formats(gem: &MirGem) -> bool {
+    gem.bodies.iter().flat_map(|body| &body.blocks).any(|block| {
+        matches!(&block.terminator.kind, TerminatorKind::Call { callee: Callee::External(path), .. } if path == FORMAT)
+    })
+}
+
+/// Prints `arg` and a new line.
+fn println(arg: &Arg) -> Result<String, CodegenError> {
+    let expr = &arg.expr;
+    Ok(match (arg.ty.peel(), arg.operand) {
+        // a string without a null byte can be printed as it is
+        (Ty::Str, Operand::Const(Constant { value: ConstValue::Str(text), .. })) if !text.contains('\0') => {
+            format!("puts({});", string_literal(text))
+        }
+        (Ty::Bool, _) => format!("puts({expr} ? \"true\" : \"false\");"),
+        _ => {
+            let (format, values) = conversions(std::slice::from_ref(arg))?;
+            format!("printf({format} \"\\n\", {});", values.join(", "))
+        }
+    })
+}
+
+/// The format string printing `args` one after the other with `printf`, and the values it takes.
+fn conversions(args: &[Arg]) -> Result<(String, Vec<String>), CodegenError> {
+    let mut format = Vec::with_capacity(args.len());
+    let mut values = Vec::with_capacity(args.len());
+    for arg in args {
+        let expr = &arg.expr;
+        match arg.ty.peel() {
+            Ty::Str => match arg.operand {
+                // the text of a literal is part of the format
+                Operand::Const(Constant { value: ConstValue::Str(text), .. }) if !text.contains('\0') => {
+                    format.push(string_literal(&text.replace('%', "%%")));
+                }
+                _ => {
+                    format.push("\"%.*s\"".to_owned());
+                    values.extend([format!("(int){expr}.len"), format!("{expr}.ptr")]);
+                }
+            },
+            Ty::Int(int) => {
+                // a variadic argument has to be of the type the format expects, which a literal is not
+                values.push(format!("({}){expr}", int_type(*int)));
+                format.push(match int {
+                    IntTy::Usize => "\"%zu\"".to_owned(),
+                    IntTy::Isize => "\"%td\"".to_owned(),
+                    IntTy::Explod | IntTy::Uexplod => return Err(CodegenError::UnsupportedPrint(arg.ty.to_string())),
+                    // `PRId32` for `int32_t`
+                    _ => {
+                        let int = int_type(*int);
+                        let bits = int.trim_start_matches("uint").trim_start_matches("int").trim_end_matches("_t");
+                        let conversion = if int.starts_with('u') { 'u' } else { 'd' };
+                        format!("\"%\" PRI{conversion}{bits}")
+                    }
+                });
+            }
+            Ty::Bool => {
+                format.push("\"%s\"".to_owned());
+                values.push(format!("{expr} ? \"true\" : \"false\""));
+            }
+            _ => return Err(CodegenError::UnsupportedPrint(arg.ty.to_string())),
+        }
+    }
+    // an empty format is still a string
+    if format.is_empty() {
+        format.push("\"\"".to_owned());
+    }
+    Ok((format.join(" "), values))
+}
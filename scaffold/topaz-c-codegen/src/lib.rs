@@ -0,0 +1,407 @@
+//! Generates C for the MIR of a gem, so that it can be compiled by any C11 compiler.
+//!
+//! The C is meant to be read: structs keep their names and fields, and locals the names of the bindings they
+//! are for. Every function declares its locals first, and every MIR block that is not just followed becomes
+//! a label of the same name, jumped to with `goto`. The phis of a block are assigned before the jumps to it, through temporaries when
+//! there are several of them, as they are all assigned at once:
+//!
+//! ```c
+//! int32_t count(int32_t start) {
+//!     int32_t _0;
+//!     int32_t x;
+//!     ...
+//! bb1:
+//!     if (going) {
+//!         x = start;
+//!         goto bb2;
+//!     }
+//!     ...
+//! }
+//! ```
+//!
+//! `str` is a struct of a pointer to its bytes and its length, and `maybe T` a struct of whether there is a
+//! value and the value. An enum is a tagged union, the index of its variant and a union of the fields of each
+//! variant, and an array a struct of a C array, so that it is copied like any other value. Values of `()` are
+//! never stored. Arithmetic is checked against the limits of `<stdint.h>`: a program that overflows,
+//! divides by zero or indexes out of bounds panics, exiting with [`topaz_codegen::PANIC_STATUS`].
+//! The functions of the standard library are generated by the backend itself, see `intrinsics.rs`, and the C is
+//! compiled with the system compiler, see [`compile`].
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+use topaz_mir::{Local, MirGem};
+use topaz_resolve::def::DefId;
+use topaz_type_checker::ty::Ty;
+
+pub mod compile;
+mod function;
+mod intrinsics;
+mod types;
+
+pub use compile::Output;
+pub use topaz_codegen::GemType;
+use types::Types;
+
+/// What keeps a gem from being compiled to C.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    #[error("cannot generate code for a value of type `{ty}` in `{function}`")]
+    UnsupportedType { ty: String, function: String },
+    #[error("cannot call `{callee}` from `{function}`, there is no code for it")]
+    UnknownFunction { callee: String, function: String },
+    #[error("the value of the constant `{name}` used in `{function}` is not known")]
+    UnknownConstant { name: String, function: String },
+    #[error("a value of type `{0}` cannot be printed or interpolated into a string")]
+    UnsupportedPrint(String),
+    #[error("there is no function to start the program with")]
+    NoEntryPoint,
+}
+
+/// The keywords of C11 and the names the headers the generated code includes declare, which nothing of the gem
+/// can be called, as well as the names the generated code itself defines.
+const RESERVED: &[&str] = &[
+    // C11
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "bool",
+    "true",
+    "false",
+    // <stdlib.h>
+    "atof",
+    "atoi",
+    "atol",
+    "atoll",
+    "strtod",
+    "strtof",
+    "strtold",
+    "strtol",
+    "strtoll",
+    "strtoul",
+    "strtoull",
+    "rand",
+    "srand",
+    "aligned_alloc",
+    "calloc",
+    "free",
+    "malloc",
+    "realloc",
+    "abort",
+    "atexit",
+    "at_quick_exit",
+    "exit",
+    "_Exit",
+    "getenv",
+    "quick_exit",
+    "system",
+    "bsearch",
+    "qsort",
+    "abs",
+    "labs",
+    "llabs",
+    "div",
+    "ldiv",
+    "lldiv",
+    "mblen",
+    "mbtowc",
+    "wctomb",
+    "mbstowcs",
+    "wcstombs",
+    "div_t",
+    "ldiv_t",
+    "lldiv_t",
+    "wchar_t",
+    "EXIT_FAILURE",
+    "EXIT_SUCCESS",
+    "RAND_MAX",
+    "MB_CUR_MAX",
+    // <stdio.h>
+    "remove",
+    "rename",
+    "tmpfile",
+    "tmpnam",
+    "fclose",
+    "fflush",
+    "fopen",
+    "freopen",
+    "setbuf",
+    "setvbuf",
+    "fprintf",
+    "fscanf",
+    "printf",
+    "scanf",
+    "snprintf",
+    "sprintf",
+    "sscanf",
+    "vfprintf",
+    "vfscanf",
+    "vprintf",
+    "vscanf",
+    "vsnprintf",
+    "vsprintf",
+    "vsscanf",
+    "fgetc",
+    "fgets",
+    "fputc",
+    "fputs",
+    "getc",
+    "getchar",
+    "gets",
+    "putc",
+    "putchar",
+    "puts",
+    "ungetc",
+    "fread",
+    "fwrite",
+    "fgetpos",
+    "fseek",
+    "fsetpos",
+    "ftell",
+    "rewind",
+    "clearerr",
+    "feof",
+    "ferror",
+    "perror",
+    "FILE",
+    "fpos_t",
+    "stdin",
+    "stdout",
+    "stderr",
+    "EOF",
+    "BUFSIZ",
+    "FILENAME_MAX",
+    "FOPEN_MAX",
+    "L_tmpnam",
+    "SEEK_CUR",
+    "SEEK_END",
+    "SEEK_SET",
+    "TMP_MAX",
+    "_IOFBF",
+    "_IOLBF",
+    "_IONBF",
+    // <stddef.h>
+    "NULL",
+    "offsetof",
+    "size_t",
+    "ptrdiff_t",
+    "max_align_t",
+    // <stdint.h>
+    "int8_t",
+    "uint8_t",
+    "INT8_MIN",
+    "INT8_MAX",
+    "UINT8_MAX",
+    "INT8_C",
+    "UINT8_C",
+    "int16_t",
+    "uint16_t",
+    "INT16_MIN",
+    "INT16_MAX",
+    "UINT16_MAX",
+    "INT16_C",
+    "UINT16_C",
+    "int32_t",
+    "uint32_t",
+    "INT32_MIN",
+    "INT32_MAX",
+    "UINT32_MAX",
+    "INT32_C",
+    "UINT32_C",
+    "int64_t",
+    "uint64_t",
+    "INT64_MIN",
+    "INT64_MAX",
+    "UINT64_MAX",
+    "INT64_C",
+    "UINT64_C",
+    "intptr_t",
+    "uintptr_t",
+    "intmax_t",
+    "uintmax_t",
+    "PTRDIFF_MIN",
+    "PTRDIFF_MAX",
+    "SIZE_MAX",
+    "INTMAX_C",
+    "UINTMAX_C",
+    // <inttypes.h>
+    "imaxabs",
+    "imaxdiv",
+    "strtoimax",
+    "strtoumax",
+    "imaxdiv_t",
+    "PRId64",
+    "PRIu64",
+    // <stdarg.h>
+    "va_list",
+    "va_start",
+    "va_arg",
+    "va_copy",
+    "va_end",
+    // the generated code
+    "main",
+    "str",
+    intrinsics::FORMAT_FUNCTION,
+];
+
+/// Names that are valid C identifiers, and different from each other and from [`RESERVED`].
+#[derive(Debug, Clone)]
+pub(crate) struct Names {
+    taken: HashSet<String>,
+}
+
+impl Names {
+    fn new() -> Self {
+        Self { taken: RESERVED.iter().map(|&name| name.to_owned()).collect() }
+    }
+
+    /// A name like `name` that is not taken yet, which it then is.
+    pub(crate) fn fresh(&mut self, name: &str) -> String {
+        let mut base: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        if base.is_empty() || base.starts_with(|c: char| c.is_ascii_digit()) {
+            base.insert(0, '_');
+        }
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while !self.taken.insert(name.clone()) {
+            name = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+        name
+    }
+}
+
+/// Generates the C source of `gem`.
+///
+/// If there is an `entry` function, the source is a program: it gets the `main` function the C runtime
+/// starts it with, which calls `entry` and exits with `0`.
+///
+/// # Errors
+/// If the gem uses something the backend has no code for, e.g. a constant with no known value.
+pub fn codegen_gem(gem: &MirGem, name: &str, entry: Option<DefId>) -> Result<String, CodegenError> {
+    let mut types = Types::new(gem, Names::new());
+    for struct_def in &gem.structs {
+        types.define_struct(struct_def.def)?;
+    }
+    for enum_def in &gem.enums {
+        types.define_enum(enum_def.def)?;
+    }
+
+    let mut functions = Vec::with_capacity(gem.bodies.len());
+    let mut prototypes = String::new();
+    for body in &gem.bodies {
+        let unsupported = |ty: &Ty| CodegenError::UnsupportedType { ty: ty.to_string(), function: body.name.clone() };
+        let function = types.names.fresh(&body.name);
+        let ret = &body.local(Local::RETURN).ty;
+        let ret =
+            if types::is_void(ret) { "void".to_owned() } else { types.c_type(ret).ok_or_else(|| unsupported(ret))? };
+        let params = body
+            .args()
+            .filter(|arg| !types::is_void(&body.local(*arg).ty))
+            .map(|arg| types.c_type(&body.local(arg).ty).ok_or_else(|| unsupported(&body.local(arg).ty)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let params = if params.is_empty() { "void".to_owned() } else { params.join(", ") };
+        let _ = writeln!(prototypes, "{ret} {function}({params});");
+        functions.push(function);
+    }
+
+    let mut definitions = String::new();
+    for (body, function) in gem.bodies.iter().zip(&functions) {
+        let codegen = function::FnCodegen::new(gem, &functions, &mut types, body)?;
+        definitions.push('\n');
+        definitions.push_str(&codegen.define(function)?);
+    }
+
+    let mut source = format!("/* The gem `{name}`, generated by topaz. */\n");
+    source.push_str("#include <inttypes.h>\n#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n");
+    source.push_str("#include <stdarg.h>\n#include <stdio.h>\n#include <stdlib.h>\n\n");
+    source.push_str(&types.into_definitions());
+    if intrinsics::formats(gem) {
+        source.push('\n');
+        source.push_str(intrinsics::FORMAT_DEFINITION);
+    }
+    if !prototypes.is_empty() {
+        source.push('\n');
+        source.push_str(&prototypes);
+    }
+    source.push_str(&definitions);
+
+    if let Some(entry) = entry {
+        let index = gem.bodies.iter().position(|body| body.def == Some(entry)).ok_or(CodegenError::NoEntryPoint)?;
+        let _ = write!(source, "\nint main(void) {{\n    {}();\n    return 0;\n}}\n", functions[index]);
+    }
+    Ok(source)
+}
+
+/// Generates the C source of `gem`, compiles it with the system C compiler and writes `output` to `path`.
+///
+/// # Errors
+/// If the source cannot be generated, or the compiler fails.
+pub fn build(
+    gem: &MirGem,
+    name: &str,
+    entry: Option<DefId>,
+    output: Output,
+    path: &Path,
+) -> Result<(), compile::BuildError> {
+    let source = codegen_gem(gem, name, entry)?;
+    compile::compile(&source, output, path)
+}
+
+/// The C backend, compiling with the system C compiler.
+pub struct CBackend;
+
+impl topaz_codegen::Backend for CBackend {
+    type Error = compile::BuildError;
+
+    fn name(&self) -> &'static str {
+        "c"
+    }
+
+    fn build(
+        &self,
+        gem: &MirGem,
+        name: &str,
+        entry: Option<DefId>,
+        output: topaz_codegen::Output,
+        path: &Path,
+    ) -> Result<(), compile::BuildError> {
+        let output = match output {
+            topaz_codegen::Output::Assembly => Output::Assembly,
+            topaz_codegen::Output::Object => Output::Object,
+            topaz_codegen::Output::Linked(gem_type) => Output::Linked(gem_type),
+        };
+        build(gem, name, entry, output, path)
+    }
+}
@@ -0,0 +1,291 @@
+//! `types.rs` - the C types of the values of a gem, and their definitions.
+use crate::{CodegenError, Names};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use topaz_mir::MirGem;
+use topaz_resolve::def::DefId;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+/// The types of a gem, defined as they are first used, after the types they are made of.
+pub(crate) struct Types<'g> {
+    gem: &'g MirGem,
+    /// The names of the types and functions of the gem
+    pub(crate) names: Names,
+    /// The name of every struct, which is declared before anything is defined
+    structs: HashMap<DefId, String>,
+    /// The names of the fields of every struct defined
+    fields: HashMap<DefId, Vec<String>>,
+    /// The name of every enum, which is declared before anything is defined too
+    enums: HashMap<DefId, String>,
+    /// The names of the payloads of the variants of every enum defined
+    variants: HashMap<DefId, Vec<String>>,
+    /// The name of every `maybe` type, by the type of its value
+    maybes: HashMap<Ty, String>,
+    /// The name of every array type, by the type itself
+    arrays: HashMap<Ty, String>,
+    /// The structs and enums being defined, whose fields can only refer to them
+    in_progress: HashSet<DefId>,
+    defined: HashSet<DefId>,
+    definitions: String,
+}
+
+impl<'g> Types<'g> {
+    pub(crate) fn new(gem: &'g MirGem, mut names: Names) -> Self {
+        let structs = gem.structs.iter().map(|struct_def| (struct_def.def, names.fresh(&struct_def.name))).collect();
+        let enums = gem.enums.iter().map(|enum_def| (enum_def.def, names.fresh(&enum_def.name))).collect();
+        Self {
+            gem,
+            names,
+            structs,
+            fields: HashMap::new(),
+            enums,
+            variants: HashMap::new(),
+            maybes: HashMap::new(),
+            arrays: HashMap::new(),
+            in_progress: HashSet::new(),
+            defined: HashSet::new(),
+            definitions: String::new(),
+        }
+    }
+
+    /// The name of the struct `def`.
+    pub(crate) fn struct_name(&self, def: DefId) -> Option<&str> {
+        self.structs.get(&def).map(String::as_str)
+    }
+
+    /// The name of the field `index` of the struct `def`, once it is defined.
+    pub(crate) fn field_name(&self, def: DefId, index: usize) -> Option<&str> {
+        self.fields.get(&def)?.get(index).map(String::as_str)
+    }
+
+    /// The name of the enum `def`.
+    pub(crate) fn enum_name(&self, def: DefId) -> Option<&str> {
+        self.enums.get(&def).map(String::as_str)
+    }
+
+    /// The name of the payload of the variant `index` of the enum `def`, once it is defined.
+    pub(crate) fn variant_name(&self, def: DefId, index: usize) -> Option<&str> {
+        self.variants.get(&def)?.get(index).map(String::as_str)
+    }
+
+    /// The name of the array type `ty`, once it is defined.
+    pub(crate) fn array_name(&self, ty: &Ty) -> Option<&str> {
+        self.arrays.get(&ty.expanded()).map(String::as_str)
+    }
+
+    /// The C type of `ty`, defined if it was not yet. `None` for the types the backend cannot represent.
+    pub(crate) fn c_type(&mut self, ty: &Ty) -> Option<String> {
+        Some(match ty.peel() {
+            Ty::Int(int) => int_type(*int).to_owned(),
+            Ty::Bool => "bool".to_owned(),
+            // a unicode scalar value
+            Ty::Char => "uint32_t".to_owned(),
+            Ty::Str => "str".to_owned(),
+            Ty::Ref { mutable, ty } => {
+                let pointee = self.c_type(ty)?;
+                match (mutable, pointee.ends_with('*')) {
+                    (true, _) => format!("{pointee} *"),
+                    // a pointer to a pointer that cannot be changed
+                    (false, true) => format!("{pointee}const *"),
+                    (false, false) => format!("const {pointee} *"),
+                }
+            }
+            Ty::Maybe(ty) => self.maybe(ty)?,
+            Ty::Struct { def, .. } => {
+                if !self.in_progress.contains(def) {
+                    self.define_struct(*def).ok()?;
+                }
+                self.structs.get(def)?.clone()
+            }
+            Ty::Enum { def, .. } => {
+                if !self.in_progress.contains(def) {
+                    self.define_enum(*def).ok()?;
+                }
+                self.enums.get(def)?.clone()
+            }
+            Ty::Array { element, len } => self.array(element, *len)?,
+            _ => return None,
+        })
+    }
+
+    /// Defines the struct `def`, after the types of its fields.
+    pub(crate) fn define_struct(&mut self, def: DefId) -> Result<(), CodegenError> {
+        if self.defined.contains(&def) {
+            return Ok(());
+        }
+        let gem = self.gem;
+        let (Some(struct_def), Some(name)) = (gem.struct_def(def), self.structs.get(&def).cloned()) else {
+            return Ok(());
+        };
+
+        self.in_progress.insert(def);
+        let mut fields = String::new();
+        let mut names = Names::new();
+        let mut field_names = Vec::with_capacity(struct_def.fields.len());
+        for field in &struct_def.fields {
+            let unsupported = || CodegenError::UnsupportedType { ty: field.ty.to_string(), function: name.clone() };
+            if is_void(&field.ty) {
+                return Err(unsupported());
+            }
+            let ty = self.c_type(&field.ty).ok_or_else(unsupported)?;
+            let field_name = names.fresh(&field.name);
+            let _ = writeln!(fields, "    {};", declaration(&ty, &field_name));
+            field_names.push(field_name);
+        }
+        self.in_progress.remove(&def);
+        self.fields.insert(def, field_names);
+
+        // C has no empty structs
+        if struct_def.fields.is_empty() {
+            fields.push_str("    char empty;\n");
+        }
+        let _ = writeln!(self.definitions, "typedef struct {name} {{\n{fields}}} {name};\n");
+        self.defined.insert(def);
+        Ok(())
+    }
+
+    /// Defines the enum `def` as a tagged union, after the types of its fields: the index of its variant in `tag`,
+    /// and the fields of the variants that have any in a struct each, named after the variant, in `payload`.
+    pub(crate) fn define_enum(&mut self, def: DefId) -> Result<(), CodegenError> {
+        if self.defined.contains(&def) {
+            return Ok(());
+        }
+        let gem = self.gem;
+        let (Some(enum_def), Some(name)) = (gem.enum_def(def), self.enums.get(&def).cloned()) else { return Ok(()) };
+
+        self.in_progress.insert(def);
+        let mut payloads = String::new();
+        let mut names = Names::new();
+        let mut variant_names = Vec::with_capacity(enum_def.variants.len());
+        for variant in &enum_def.variants {
+            let variant_name = names.fresh(&variant.name);
+            if !variant.fields.is_empty() {
+                payloads.push_str("        struct {\n");
+                for (index, ty) in variant.fields.iter().enumerate() {
+                    let unsupported = || CodegenError::UnsupportedType { ty: ty.to_string(), function: name.clone() };
+                    if is_void(ty) {
+                        return Err(unsupported());
+                    }
+                    let ty = self.c_type(ty).ok_or_else(unsupported)?;
+                    let _ = writeln!(payloads, "            {};", declaration(&ty, &format!("_{index}")));
+                }
+                let _ = writeln!(payloads, "        }} {variant_name};");
+            }
+            variant_names.push(variant_name);
+        }
+        self.in_progress.remove(&def);
+        self.variants.insert(def, variant_names);
+
+        let _ = write!(self.definitions, "typedef struct {name} {{\n    size_t tag;\n");
+        if !payloads.is_empty() {
+            let _ = writeln!(self.definitions, "    union {{\n{payloads}    }} payload;");
+        }
+        let _ = writeln!(self.definitions, "}} {name};\n");
+        self.defined.insert(def);
+        Ok(())
+    }
+
+    /// `[element; len]`, as a struct of a C array, which C would not copy otherwise.
+    fn array(&mut self, element: &Ty, len: usize) -> Option<String> {
+        let ty = Ty::Array { element: Box::new(element.expanded()), len };
+        if let Some(name) = self.arrays.get(&ty) {
+            return Some(name.clone());
+        }
+
+        if is_void(element) {
+            return None;
+        }
+        let name = self.names.fresh(&format!("array_{len}_{}", element.expanded().to_string().replace('&', "ref ")));
+        let element = self.c_type(element)?;
+        // C has no empty arrays
+        let items = declaration(&element, &format!("items[{}]", len.max(1)));
+        let _ = writeln!(self.definitions, "typedef struct {name} {{\n    {items};\n}} {name};\n");
+        self.arrays.insert(ty, name.clone());
+        Some(name)
+    }
+
+    /// `maybe ty`, as a struct of whether there is a value and the value.
+    fn maybe(&mut self, ty: &Ty) -> Option<String> {
+        let ty = ty.expanded();
+        if let Some(name) = self.maybes.get(&ty) {
+            return Some(name.clone());
+        }
+
+        let value = if is_void(&ty) { None } else { Some(self.c_type(&ty)?) };
+        let name = self.names.fresh(&format!("maybe_{}", ty.to_string().replace('&', "ref ")));
+        let _ = write!(self.definitions, "typedef struct {name} {{\n    bool some;\n");
+        if let Some(value) = value {
+            let _ = writeln!(self.definitions, "    {};", declaration(&value, "value"));
+        }
+        let _ = writeln!(self.definitions, "}} {name};\n");
+        self.maybes.insert(ty, name.clone());
+        Some(name)
+    }
+
+    /// The definitions of the types, every one after those it uses.
+    pub(crate) fn into_definitions(self) -> String {
+        // structs and enums can refer to each other through references
+        let structs = self.gem.structs.iter().filter_map(|struct_def| self.structs.get(&struct_def.def));
+        let enums = self.gem.enums.iter().filter_map(|enum_def| self.enums.get(&enum_def.def));
+        let mut declarations: String =
+            structs.chain(enums).map(|name| format!("typedef struct {name} {name};\n")).collect();
+        declarations.insert_str(0, "typedef struct str {\n    const char *ptr;\n    size_t len;\n} str;\n\n");
+        if !self.gem.structs.is_empty() || !self.gem.enums.is_empty() {
+            declarations.push('\n');
+        }
+        declarations + &self.definitions
+    }
+}
+
+/// Whether values of `ty` are `()`, which are never stored.
+pub(crate) fn is_void(ty: &Ty) -> bool {
+    *ty.peel() == Ty::Void
+}
+
+/// The declaration of `name` as a `ty`, e.g. `const Point *origin`.
+pub(crate) fn declaration(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{ty}{name}")
+    } else {
+        format!("{ty} {name}")
+    }
+}
+
+pub(crate) const fn int_type(int: IntTy) -> &'static str {
+    match int {
+        IntTy::Byte => "int8_t",
+        IntTy::Ubyte => "uint8_t",
+        IntTy::Short => "int16_t",
+        IntTy::Ushort => "uint16_t",
+        IntTy::Int => "int32_t",
+        IntTy::Uint => "uint32_t",
+        IntTy::Long => "int64_t",
+        IntTy::Ulong => "uint64_t",
+        // not in C11, but in every compiler for a 64-bit system
+        IntTy::Explod => "__int128",
+        IntTy::Uexplod => "unsigned __int128",
+        IntTy::Isize => "ptrdiff_t",
+        IntTy::Usize => "size_t",
+    }
+}
+
+/// The smallest and the largest value of `int`, as the C expressions of `<stdint.h>`.
+pub(crate) const fn int_limits(int: IntTy) -> (&'static str, &'static str) {
+    match int {
+        IntTy::Byte => ("INT8_MIN", "INT8_MAX"),
+        IntTy::Ubyte => ("0", "UINT8_MAX"),
+        IntTy::Short => ("INT16_MIN", "INT16_MAX"),
+        IntTy::Ushort => ("0", "UINT16_MAX"),
+        IntTy::Int => ("INT32_MIN", "INT32_MAX"),
+        IntTy::Uint => ("0", "UINT32_MAX"),
+        IntTy::Long => ("INT64_MIN", "INT64_MAX"),
+        IntTy::Ulong => ("0", "UINT64_MAX"),
+        // `<stdint.h>` has no limits for `__int128`
+        IntTy::Explod => {
+            ("(-(__int128)(((unsigned __int128)1 << 127) - 1) - 1)", "((__int128)(((unsigned __int128)1 << 127) - 1))")
+        }
+        IntTy::Uexplod => ("0", "(~(unsigned __int128)0)"),
+        IntTy::Isize => ("PTRDIFF_MIN", "PTRDIFF_MAX"),
+        IntTy::Usize => ("0", "SIZE_MAX"),
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+use std::process::Command;
+use topaz_c_codegen::{build, codegen_gem, GemType, Output};
+use topaz_codegen::suite::mir;
+use topaz_mir::transform::OptLevel;
+use topaz_mir::MirGem;
+
+/// A directory of its own for every test, as they run at the same time.
+fn out_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("topaz-c-codegen-{test}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("failed to create the output directory");
+    dir
+}
+
+/// Compiles `gem` into a program and runs it, returning what it printed.
+fn compile_and_run(gem: &MirGem, path: PathBuf) -> String {
+    let entry = gem.entry_point(0).and_then(|main| main.def);
+    build(gem, "test", entry, Output::Linked(GemType::Bin), &path).expect("failed to build");
+    let output = Command::new(&path).output().expect("failed to run the program");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).expect("printed invalid UTF-8")
+}
+
+#[test]
+fn test_source() {
+    let source = r#"struct Point { x: int, y: int }
+
+func double(value: maybe &Point, fallback: int) -> int {
+    return fallback;
+}
+
+func count(start: int) -> int {
+    let mut going = true;
+    let mut first = true;
+    let mut x = start;
+    while going {
+        x = start;
+        going = first;
+        first = false;
+    }
+    return x;
+}
+
+public func main() {
+    let point = Point(x = 1, y = 2);
+    let seen = &point;
+    println("back\slash, 'quote' and ??!");
+    println(count(point.y));
+}
+"#;
+    let gem = mir(source, OptLevel::O0);
+    let c = codegen_gem(&gem, "test", gem.entry_point(0).and_then(|main| main.def)).expect("failed to generate C");
+    assert!(c.contains("typedef struct Point {\n    int32_t x;\n    int32_t y;\n} Point;"), "{c}");
+    assert!(c.contains("typedef struct maybe_ref_Point {\n    bool some;\n    const Point *value;\n}"), "{c}");
+    // C keywords and the names of the C library are not used for the functions of the gem
+    assert!(c.contains("int32_t double_1(maybe_ref_Point value, int32_t fallback) {"), "{c}");
+    assert!(c.contains("void main_1(void) {"), "{c}");
+    assert!(c.contains("int main(void) {\n    main_1();\n    return 0;\n}"), "{c}");
+    assert!(c.contains("seen = &point;"), "{c}");
+    // the phis of the loop header are assigned at once
+    assert!(c.contains("_next = "), "{c}");
+
+    let out = compile_and_run(&gem, out_dir("source").join("test"));
+    assert_eq!(out, "back\\slash, 'quote' and ??!\n2\n");
+}
+
+#[test]
+fn test_enums() {
+    let source = r#"enum Shape {
+    Circle(int),
+    Rect(int, int),
+    Empty
+}
+
+func area(shape: Shape) -> int {
+    match shape {
+        Shape::Circle(radius) => {
+            return 3 * radius * radius;
+        }
+        Shape::Rect(width, height) => {
+            return width * height;
+        }
+        _ => {
+            return 0;
+        }
+    }
+}
+
+public func main() {
+    let areas = [area(Shape::Circle(2)), area(Shape::Rect(3, 5)), area(Shape::Empty)];
+    let mut index = 0;
+    while index < 3 {
+        println(areas[index]);
+        index = index + 1;
+    }
+}
+"#;
+    for level in [OptLevel::O0, OptLevel::O3] {
+        let gem = mir(source, level);
+        let c = codegen_gem(&gem, "test", gem.entry_point(0).and_then(|main| main.def)).expect("failed to generate C");
+        assert!(c.contains("typedef struct Shape Shape;"), "{c}");
+        assert!(
+            c.contains(
+                "typedef struct Shape {\n    size_t tag;\n    union {\n        struct {\n            int32_t _0;\n        } \
+                 Circle;\n        struct {\n            int32_t _0;\n            int32_t _1;\n        } Rect;\n    } \
+                 payload;\n} Shape;"
+            ),
+            "{c}"
+        );
+        assert!(c.contains("typedef struct array_3_int {\n    int32_t items[3];\n} array_3_int;"), "{c}");
+
+        let out = compile_and_run(&gem, out_dir(&format!("enums-{level:?}")).join("test"));
+        assert_eq!(out, "12\n15\n0\n", "at {level:?}");
+    }
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    let source = r#"public func main() {
+    let numbers = [1, 2, 3];
+    let mut index = 0;
+    while index < 4 {
+        println(numbers[index]);
+        index = index + 1;
+    }
+}
+"#;
+    let gem = mir(source, OptLevel::O0);
+    let path = out_dir("bounds").join("test");
+    build(&gem, "test", gem.entry_point(0).and_then(|main| main.def), Output::Linked(GemType::Bin), &path)
+        .expect("failed to build");
+    let output = Command::new(&path).output().expect("failed to run the program");
+    assert_eq!(output.status.code(), Some(topaz_codegen::PANIC_STATUS));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n2\n3\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "`main` panicked: index out of bounds: the length is 3 but the index is 3\n"
+    );
+}
+
+/// Every example, with what it prints.
+const EXAMPLES: [(&str, &str); 11] = [
+    ("default-values", "hello\nhi\nhello\nhello\n"),
+    ("enum", "west\neast\n"),
+    ("generics", "42\n2\n"),
+    ("hello-world", "hello world\n"),
+    ("import-after-top-levelstatement", "4\n"),
+    ("le_syntax", "world\n"),
+    ("list", "1\n2\n5\n"),
+    ("option-types", "3\nnone\n"),
+    ("segments", "4\n0\n3\ndiagonal\ntrue\n"),
+    ("var", "hello world 2\n"),
+    ("while-loop", "6\n5\n4\n3\n2\na < 2\n1\n"),
+];
+
+#[test]
+fn test_examples() {
+    // the examples as they are on disk, built by the system C compiler
+    let examples = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+    let on_disk = std::fs::read_dir(&examples).expect("failed to list the examples").count();
+    assert_eq!(on_disk, EXAMPLES.len(), "every example is run");
+    for (name, expected) in EXAMPLES {
+        let source =
+            std::fs::read_to_string(examples.join(name).with_extension("tp")).expect("failed to read the example");
+        let gem = mir(&source, OptLevel::O0);
+        let out = compile_and_run(&gem, out_dir(name).join(name));
+        assert_eq!(out, expected, "{name}");
+    }
+}
@@ -0,0 +1,22 @@
+use topaz_c_codegen::CBackend;
+use topaz_codegen::suite;
+
+#[test]
+fn test_programs() {
+    suite::programs(&CBackend);
+}
+
+#[test]
+fn test_panics() {
+    suite::panics(&CBackend);
+}
+
+#[test]
+fn test_outputs() {
+    suite::outputs(&CBackend);
+}
+
+#[test]
+fn test_rejected() {
+    suite::rejected();
+}
@@ -0,0 +1,102 @@
+//! What the backends have in common: the [`Backend`] trait the compiler builds a gem with, whichever backend
+//! it is, linking object files into programs and libraries, see [`link`], and the suite every backend is
+//! tested with, see `suite.rs`, behind the `suite` feature.
+//!
+//! A backend compiles the MIR of a gem into machine code. It can write it to a file, as assembly or an object
+//! file, or link it into a program or a library:
+//!
+//! ```ignore
+//! let entry = gem.entry_point(root).and_then(|main| main.def);
+//! backend.build(&gem, "hello", entry, Output::Linked(GemType::Bin), Path::new("hello"))?;
+//! ```
+//!
+//! Backends that can also compile a gem into the memory of the compiler, and run it there, implement [`Jit`].
+
+use std::path::Path;
+use topaz_mir::MirGem;
+use topaz_resolve::def::DefId;
+
+pub mod link;
+#[cfg(feature = "suite")]
+pub mod suite;
+
+pub use link::GemType;
+
+/// The status a compiled program exits with when it panics, the one `topaz run` exits with when the program it
+/// interprets does.
+pub const PANIC_STATUS: i32 = 101;
+
+/// What a [`Backend`] makes of a gem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// `.s`
+    Assembly,
+    /// `.o`
+    Object,
+    /// A program or a library
+    Linked(GemType),
+}
+
+impl Output {
+    /// The name of the file a gem called `name` is written to.
+    #[must_use]
+    pub fn file_name(self, name: &str) -> String {
+        match self {
+            Self::Assembly => format!("{name}.s"),
+            Self::Object => format!("{name}.o"),
+            Self::Linked(gem_type) => gem_type.file_name(name),
+        }
+    }
+}
+
+/// Compiles the MIR of a gem into machine code.
+pub trait Backend {
+    type Error: std::error::Error;
+
+    /// The name of the backend, as `topaz build --backend` takes it.
+    fn name(&self) -> &'static str;
+
+    /// Compiles `gem`, called `name`, and writes `output` to `path`.
+    ///
+    /// If there is an `entry` function, the gem is a program started with it.
+    ///
+    /// # Errors
+    /// If the gem uses something the backend has no code for, or it cannot be compiled or linked.
+    fn build(
+        &self,
+        gem: &MirGem,
+        name: &str,
+        entry: Option<DefId>,
+        output: Output,
+        path: &Path,
+    ) -> Result<(), Self::Error>;
+}
+
+/// A backend that compiles a gem into memory, to be run by the compiler itself.
+pub trait Jit: Backend {
+    type Code: JitCode;
+
+    /// Compiles `gem` into the memory of this process.
+    ///
+    /// # Errors
+    /// If the gem uses something the backend has no code for, or it cannot be compiled.
+    fn jit(&self, gem: &MirGem) -> Result<Self::Code, Self::Error>;
+}
+
+/// A gem compiled into memory, which lives as long as it.
+pub trait JitCode {
+    /// The machine code of the first body called `name`, e.g. `main`, a function of the C calling convention
+    /// taking and returning what the body does. `()` is never taken nor returned.
+    fn function(&self, name: &str) -> Option<*const ()>;
+
+    /// Runs the body called `name`, returning whether there is one.
+    ///
+    /// # Safety
+    /// The body takes and returns nothing.
+    unsafe fn call(&self, name: &str) -> bool {
+        let Some(function) = self.function(name) else { return false };
+        let function: extern "C" fn() = std::mem::transmute(function);
+        function();
+        true
+    }
+}
@@ -0,0 +1,82 @@
+//! `link.rs` - turns an object file into a program or a library with the tools of the system.
+//!
+//! Programs and shared libraries are linked by the C compiler, which brings in the C library the intrinsics
+//! are built on, and static libraries are archived with `ar`. Both can be replaced with the `CC` and `AR`
+//! environment variables, as with most build systems.
+use std::env;
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+
+/// What a gem is built into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GemType {
+    /// A program, started with the `main` function of the root module
+    Bin,
+    /// `lib<name>.a`
+    StaticLib,
+    /// `lib<name>.so`
+    SharedLib,
+}
+
+impl GemType {
+    /// The name of the file a gem called `name` is built into.
+    #[must_use]
+    pub fn file_name(self, name: &str) -> String {
+        match self {
+            Self::Bin => name.to_owned(),
+            Self::StaticLib => format!("lib{name}.a"),
+            Self::SharedLib => format!("lib{name}.so"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    #[error("cannot run `{program}`: {message}")]
+    Spawn { program: String, message: String },
+    #[error("`{program}` failed with {status}:\n{stderr}")]
+    Failed { program: String, status: String, stderr: String },
+}
+
+/// Links `object` into `output`, a file of `gem_type`.
+///
+/// # Errors
+/// If the linker or archiver cannot be run, or fails.
+pub fn link(object: &Path, gem_type: GemType, output: &Path) -> Result<(), LinkError> {
+    let mut command = match gem_type {
+        GemType::Bin => {
+            let mut command = Command::new(tool("CC", "cc"));
+            command.arg(object).arg("-o").arg(output);
+            command
+        }
+        GemType::SharedLib => {
+            let mut command = Command::new(tool("CC", "cc"));
+            command.arg("-shared").arg(object).arg("-o").arg(output);
+            command
+        }
+        GemType::StaticLib => {
+            let mut command = Command::new(tool("AR", "ar"));
+            command.arg("rcs").arg(output).arg(object);
+            command
+        }
+    };
+
+    let program = command.get_program().to_string_lossy().into_owned();
+    let result =
+        command.output().map_err(|error| LinkError::Spawn { program: program.clone(), message: error.to_string() })?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(LinkError::Failed {
+            program,
+            status: result.status.to_string(),
+            stderr: String::from_utf8_lossy(&result.stderr).into_owned(),
+        })
+    }
+}
+
+/// The program set by the environment variable `var`, `default` if it is not set.
+fn tool(var: &str, default: &str) -> OsString {
+    env::var_os(var).filter(|program| !program.is_empty()).unwrap_or_else(|| default.into())
+}
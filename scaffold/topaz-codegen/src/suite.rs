@@ -0,0 +1,402 @@
+//! `suite.rs` - the tests every backend has to pass, called from the tests of each backend:
+//!
+//! ```ignore
+//! #[test]
+//! fn test_programs() {
+//!     topaz_codegen::suite::programs(&CBackend);
+//! }
+//! ```
+//!
+//! The programs are run with the MIR interpreter too, and what a compiled program prints has to be what it printed,
+//! up to where it panicked if it did.
+use crate::{Backend, GemType, Jit, JitCode, Output, PANIC_STATUS};
+use std::path::PathBuf;
+use std::process::Command;
+use topaz_ast::file::TopazFile;
+use topaz_hir::lower::lower_gem;
+use topaz_hir::HirGem;
+use topaz_interpreter::Interpreter;
+use topaz_mir::build::build_gem;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::transform::{OptLevel, PassManager};
+use topaz_mir::MirGem;
+use topaz_parser_next::Parse;
+use topaz_report::Reporter;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+/// The programs of the suite, by name.
+pub const PROGRAMS: [(&str, &str); 7] = [
+    ("hello-world", include_str!("../../../examples/hello-world.tp")),
+    ("segments", include_str!("../../../examples/segments.tp")),
+    ("values", VALUES),
+    ("operators", OPERATORS),
+    ("drops", DROPS),
+    ("strings", STRINGS),
+    ("traits", TRAITS),
+];
+
+/// Programs that panic after printing `before`, by name.
+pub const PANICS: [(&str, &str); 4] = [
+    ("add", "let big: int = 2147483647;\n    println(big + 1);"),
+    ("negate", "let min = -9223372036854775808long;\n    println(-min);"),
+    ("divide", "let min = -128byte;\n    println(min / -1byte);"),
+    ("zero", "let zero = 0usize;\n    println(1usize % zero);"),
+];
+
+/// Programs the type checker rejects, so that no backend is given them, by name, with the code of the error.
+pub const REJECTED: [(&str, &str, &str); 3] = [
+    ("suffixed", "let small: ubyte = 300ubyte;", "E052"),
+    ("inferred", "let big: int = 2147483648;", "E052"),
+    ("negated", "let low = -129byte;", "E052"),
+];
+
+/// References, integers of every width, and a loop.
+const VALUES: &str = r#"struct Point { x: long, y: long }
+
+struct Pair { left: Point, right: Point }
+
+func either(first: &Point, second: &Point, pick_first: bool) -> &Point {
+    if pick_first {
+        return first;
+    }
+    return second;
+}
+
+func widths(small: ubyte, big: ulong, size: usize, signed: isize) {
+    println(small);
+    println(big);
+    println(size);
+    println(signed);
+}
+
+public func main() {
+    let pair = Pair(left = Point(x = 1, y = 2), right = Point(x = 30, y = 40));
+    let _chosen = either(&pair.left, &pair.right, false);
+    let mut going = true;
+    let mut count = 0;
+    while going {
+        count = 7;
+        going = false;
+    }
+    println(count);
+    println(pair.right.y);
+    widths(255ubyte, 18446744073709551615ulong, 3usize, 4isize);
+    println(9223372036854775807long);
+    println(false);
+}
+"#;
+
+/// Arithmetic and comparisons, signed and unsigned.
+const OPERATORS: &str = r#"func collatz(start: ulong) -> int {
+    let mut value = start;
+    let mut steps = 0;
+    while value != 1 {
+        if value % 2 == 0 {
+            value = value / 2;
+        } else {
+            value = 3 * value + 1;
+        }
+        steps = steps + 1;
+    }
+    return steps;
+}
+
+func below(value: ubyte, limit: ubyte) -> bool {
+    return value < limit;
+}
+
+public func main() {
+    println(collatz(27));
+    println(-7 / 2);
+    println(-7 % 2);
+    println(-128byte % -1byte);
+    println(-(-127byte - 1byte + 1byte));
+    println(255ubyte - 1ubyte * 2ubyte);
+    println(below(200ubyte, 100ubyte));
+    println(18446744073709551615ulong >= 1);
+    println(!(collatz(1) != 0) == true);
+}
+"#;
+
+/// Values with a `Drop` impl, dropped at the end of their scope unless they were moved, maybe only on some paths.
+const DROPS: &str = r#"struct Guard { id: int }
+
+struct Pair { first: Guard, second: Guard }
+
+enum Slot {
+    Full(Guard),
+    Empty
+}
+
+impl Drop for Guard {
+    func drop(move this) {
+        println(this.id);
+    }
+}
+
+func take(move guard: Guard) {
+    println(0);
+}
+
+func early(done: bool) {
+    let guard = Guard(id = 10);
+    if done {
+        return;
+    }
+    println(11);
+}
+
+func conditional(flag: bool) {
+    let guard = Guard(id = 20);
+    if flag {
+        take(guard);
+    }
+    println(21);
+}
+
+func unpack(move slot: Slot) {
+    match slot {
+        Slot::Full(guard) => {
+            println(30);
+        }
+        Slot::Empty => {
+            println(31);
+        }
+    }
+}
+
+public func main() {
+    let a = Guard(id = 1);
+    {
+        let b = Guard(id = 2);
+    }
+    let mut c = Guard(id = 3);
+    c = Guard(id = 4);
+    early(true);
+    early(false);
+    conditional(true);
+    conditional(false);
+    let pair = Pair(first = Guard(id = 5), second = Guard(id = 6));
+    take(pair.first);
+    unpack(Slot::Full(Guard(id = 7)));
+    unpack(Slot::Empty);
+}
+"#;
+
+/// Interpolated strings, of every type that can be printed, and functions returning an expression.
+const STRINGS: &str = r#"func double(value: long) -> long = value * 2;
+
+func describe(name: str, count: ubyte, ripe: bool) -> str = "$name: $count, $ripe";
+
+public func main() {
+    let doubled = double(-21);
+    let line = describe("apples", 255ubyte, false);
+    println("$line, $doubled at 100%");
+    println("$doubled");
+    let size = 3usize;
+    println("size $size$size, $5");
+}
+"#;
+
+/// Methods of traits called through them, with the impl for the type of the argument, and a default body
+/// calling a method of the impl.
+const TRAITS: &str = r#"trait Shape {
+    func area(this) -> long;
+
+    func describe(this) -> str {
+        let area = Shape::area(this);
+        return "a shape of area $area";
+    }
+}
+
+struct Square { side: long }
+
+struct Rect { width: long, height: long }
+
+impl Shape for Square {
+    func area(this) -> long = this.side * this.side;
+}
+
+impl Shape for Rect {
+    func area(this) -> long = this.width * this.height;
+
+    @override
+    func describe(this) -> str = "a rectangle";
+}
+
+public func main() {
+    let square = Square(side = 3);
+    let rect = Rect(width = 2, height = 5);
+    println(Shape::area(square));
+    println(Shape::area(rect));
+    println(Shape::describe(square));
+    println(Shape::describe(rect));
+}
+"#;
+
+/// A function to call once the gem is compiled into memory.
+const ANSWER: &str = r#"func answer() -> long {
+    return 42;
+}
+
+public func main() {
+    let _answer = answer();
+}
+"#;
+
+/// The MIR of the gem made of the single module `source`, optimized at `level`.
+///
+/// # Panics
+/// If the module has errors.
+#[must_use]
+pub fn mir(source: &str, level: OptLevel) -> MirGem {
+    let hir = match hir(source) {
+        Ok(hir) => hir,
+        Err(errors) => panic!("{errors:?}"),
+    };
+    let mut gem = build_gem(&hir);
+    construct_gem(&mut gem);
+    PassManager::new(level).run(&mut gem).expect("invalid MIR");
+    gem
+}
+
+/// The HIR of the gem made of the single module `source`, or the code and message of each error of the analysis.
+///
+/// # Panics
+/// If the module cannot be parsed or resolved.
+fn hir(source: &str) -> Result<HirGem, Vec<(Option<String>, String)>> {
+    let file = TopazFile::parse(source).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("test", true);
+    cx.set_prelude(topaz_session::prelude::std_prelude());
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let errors: Vec<_> = session
+        .output
+        .iter()
+        .filter(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)))
+        .map(|(file_id, unit)| unit.build_diagnostic(*file_id))
+        .map(|diagnostic| (diagnostic.code, diagnostic.message))
+        .collect();
+    if errors.is_empty() {
+        Ok(lower_gem(&session))
+    } else {
+        Err(errors)
+    }
+}
+
+/// A directory of its own for every test of every backend, as they run at the same time.
+fn out_dir(backend: &impl Backend, test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("topaz-{}-{test}-{}", backend.name(), std::process::id()));
+    std::fs::create_dir_all(&dir).expect("failed to create the output directory");
+    dir
+}
+
+/// Builds every program of the suite, unoptimized and fully optimized, runs it, and checks that it prints what
+/// the interpreter does.
+///
+/// # Panics
+/// If a program cannot be built, fails, or prints something else.
+pub fn programs<B: Backend>(backend: &B) {
+    let dir = out_dir(backend, "programs");
+    for (name, source) in PROGRAMS {
+        for level in [OptLevel::O0, OptLevel::O3] {
+            let gem = mir(source, level);
+            let main = gem.entry_point(0).expect("no `main`");
+            let mut expected = Vec::new();
+            Interpreter::new(&gem, &mut expected).run(main).expect("the program panicked");
+
+            let path = dir.join(format!("{name}-{level:?}"));
+            if let Err(error) = backend.build(&gem, name, main.def, Output::Linked(GemType::Bin), &path) {
+                panic!("failed to build {name} at {level:?}: {error}");
+            }
+            let output = Command::new(&path).output().expect("failed to run the program");
+            assert!(output.status.success(), "{name} at {level:?} failed: {}", String::from_utf8_lossy(&output.stderr));
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&expected),
+                "{name} at {level:?}"
+            );
+        }
+    }
+}
+
+/// Builds every program of [`PANICS`], unoptimized, runs it, and checks that it exits with [`PANIC_STATUS`] after
+/// printing what the interpreter printed before it panicked.
+///
+/// # Panics
+/// If a program cannot be built, does not fail, or prints something else.
+pub fn panics<B: Backend>(backend: &B) {
+    let dir = out_dir(backend, "panics");
+    for (name, body) in PANICS {
+        let source = format!("public func main() {{\n    println(\"before\");\n    {body}\n}}\n");
+        let gem = mir(&source, OptLevel::O0);
+        let main = gem.entry_point(0).expect("no `main`");
+        let mut expected = Vec::new();
+        assert!(Interpreter::new(&gem, &mut expected).run(main).is_err(), "{name} did not panic in the interpreter");
+
+        let path = dir.join(name);
+        if let Err(error) = backend.build(&gem, name, main.def, Output::Linked(GemType::Bin), &path) {
+            panic!("failed to build {name}: {error}");
+        }
+        let output = Command::new(&path).output().expect("failed to run the program");
+        assert_eq!(output.status.code(), Some(PANIC_STATUS), "{name} did not panic");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&expected), "{name}");
+    }
+}
+
+/// Checks that every program of [`REJECTED`] fails to type check with its error, before it can reach a backend.
+///
+/// # Panics
+/// If a program is accepted, or rejected with other errors.
+pub fn rejected() {
+    for (name, body, code) in REJECTED {
+        let source = format!("public func main() {{\n    {body}\n}}\n");
+        let codes: Vec<_> = match hir(&source) {
+            Ok(_) => panic!("{name} was accepted"),
+            Err(errors) => errors.into_iter().map(|(code, _)| code).collect(),
+        };
+        assert_eq!(codes, [Some(code.to_owned())], "{name}");
+    }
+}
+
+/// Writes every kind of [`Output`] of a library, which has to be a file that is not empty.
+///
+/// # Panics
+/// If an output cannot be built or is empty.
+pub fn outputs<B: Backend>(backend: &B) {
+    let gem = mir(include_str!("../../../examples/segments.tp"), OptLevel::O0);
+    let dir = out_dir(backend, "outputs");
+    for output in
+        [Output::Assembly, Output::Object, Output::Linked(GemType::StaticLib), Output::Linked(GemType::SharedLib)]
+    {
+        let path = dir.join(output.file_name("segments"));
+        if let Err(error) = backend.build(&gem, "segments", None, output, &path) {
+            panic!("failed to build {output:?}: {error}");
+        }
+        assert!(std::fs::metadata(&path).map_or(false, |file| file.len() > 0), "{output:?} is empty");
+    }
+}
+
+/// Compiles a gem into memory, then calls its functions.
+///
+/// # Panics
+/// If the gem cannot be compiled, or its functions cannot be found or return something else.
+pub fn jit<B: Jit>(backend: &B) {
+    let gem = mir(ANSWER, OptLevel::O0);
+    let code = match backend.jit(&gem) {
+        Ok(code) => code,
+        Err(error) => panic!("failed to compile into memory: {error}"),
+    };
+
+    let answer = code.function("answer").expect("no `answer`");
+    // SAFETY: `answer` takes nothing and returns a `long`
+    let answer: extern "C" fn() -> i64 = unsafe { std::mem::transmute(answer) };
+    assert_eq!(answer(), 42);
+    // SAFETY: `main` takes and returns nothing
+    assert!(unsafe { code.call("main") });
+    assert!(code.function("question").is_none());
+}
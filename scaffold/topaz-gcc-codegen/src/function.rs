@@ -0,0 +1,402 @@
+//! `function.rs` - the code of a function, generated from its MIR body.
+use crate::{intrinsics, is_signed, is_void, CodegenError, GccCodegen};
+use gccjit::{BinaryOp, Block, ComparisonOp, Function, LValue, RValue, ToRValue, UnaryOp};
+use topaz_codegen::PANIC_STATUS;
+use topaz_hir::Callee;
+use topaz_mir::{
+    BinOp, BlockId, Body, ConstValue, Constant, Local, Operand, Place, Rvalue, StatementKind, TerminatorKind, UnOp,
+};
+use topaz_type_checker::ty::{IntTy, Ty};
+
+/// Generates the code of `body` into `function`, which was declared for it.
+pub(crate) fn define<'ctx>(
+    codegen: &GccCodegen<'ctx, '_>,
+    body: &Body,
+    function: Function<'ctx>,
+) -> Result<(), CodegenError> {
+    let start = function.new_block("start");
+    let blocks = body.block_ids().map(|block| function.new_block(format!("bb{}", block.0))).collect();
+
+    let mut locals = Vec::with_capacity(body.locals.len());
+    for (index, decl) in body.locals.iter().enumerate() {
+        if is_void(&decl.ty) {
+            locals.push(None);
+            continue;
+        }
+        let ty = codegen.ty(&decl.ty).ok_or_else(|| unsupported(body, &decl.ty))?;
+        let name = decl.name.clone().unwrap_or_else(|| format!("_{index}"));
+        locals.push(Some(function.new_local(None, ty, name)));
+    }
+
+    let lowering = FnLowering { codegen, body, function, blocks, locals };
+    // the parameters of `()` were left out
+    let args = body.args().filter_map(|arg| lowering.locals[arg.index()]);
+    for (index, local) in (0..).zip(args) {
+        start.add_assignment(None, local, function.get_param(index).to_rvalue());
+    }
+
+    if let Some(first) = lowering.blocks.first() {
+        start.end_with_jump(None, *first);
+        for block in body.block_ids() {
+            lowering.lower_block(block)?;
+        }
+    } else {
+        lowering.abort(start);
+    }
+    Ok(())
+}
+
+fn unsupported(body: &Body, ty: &Ty) -> CodegenError {
+    CodegenError::UnsupportedType { ty: ty.to_string(), function: body.name.clone() }
+}
+
+struct FnLowering<'a, 'ctx, 'g> {
+    codegen: &'a GccCodegen<'ctx, 'g>,
+    body: &'a Body,
+    function: Function<'ctx>,
+    /// The block of every MIR block
+    blocks: Vec<Block<'ctx>>,
+    /// The local of every MIR local, `None` for those of `()`
+    locals: Vec<Option<LValue<'ctx>>>,
+}
+
+impl<'ctx> FnLowering<'_, 'ctx, '_> {
+    fn lower_block(&self, block: BlockId) -> Result<(), CodegenError> {
+        let cx = self.codegen.cx;
+        let mut current = self.blocks[block.0];
+        let data = self.body.block(block);
+        for statement in &data.statements {
+            if let StatementKind::Assign(place, rvalue) = &statement.kind {
+                current = self.assign(current, place, rvalue)?;
+            }
+        }
+
+        match &data.terminator.kind {
+            TerminatorKind::Goto(target) => current.end_with_jump(None, self.edge(block, *target)?),
+            TerminatorKind::If { cond, then, otherwise } => {
+                let cond = self.operand(current, cond)?.ok_or_else(|| unsupported(self.body, &Ty::Bool))?;
+                let (then, otherwise) = (self.edge(block, *then)?, self.edge(block, *otherwise)?);
+                current.end_with_conditional(None, cond, then, otherwise);
+            }
+            TerminatorKind::Switch { discr, targets } => {
+                let discr =
+                    self.operand(current, discr)?.ok_or_else(|| unsupported(self.body, &Ty::Int(IntTy::Usize)))?;
+                // the discriminant is always in range, so the last target is the one of every other value
+                let Some((last, rest)) = targets.split_last() else {
+                    self.abort(current);
+                    return Ok(());
+                };
+                let mut cases = Vec::with_capacity(rest.len());
+                for (index, target) in rest.iter().enumerate() {
+                    let value = cx.new_rvalue_from_long(discr.get_type(), i64::try_from(index).unwrap_or(i64::MAX));
+                    cases.push(cx.new_case(value, value, self.edge(block, *target)?));
+                }
+                current.end_with_switch(None, discr, self.edge(block, *last)?, &cases);
+            }
+            TerminatorKind::Call {
+                callee: callee @ (Callee::Fn { name, .. } | Callee::Method { name, .. }),
+                args,
+                dest,
+                target,
+                ..
+            } => {
+                let index = self.codegen.gem.callee_index(callee).ok_or_else(|| CodegenError::UnknownFunction {
+                    callee: name.clone(),
+                    function: self.body.name.clone(),
+                })?;
+                let function = self.codegen.bodies[index];
+                // the arguments of `()` are not passed
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.extend(self.operand(current, arg)?);
+                }
+                let call = cx.new_call(None, function, &values);
+                match self.place(dest)? {
+                    Some((dest, _)) => current.add_assignment(None, dest, call),
+                    None => current.add_eval(None, call),
+                }
+                current.end_with_jump(None, self.edge(block, *target)?);
+            }
+            TerminatorKind::Call { callee: Callee::External(path), args, dest, target, .. } => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    if let Some(value) = self.operand(current, arg)? {
+                        values.push((value, self.operand_ty(arg)));
+                    }
+                }
+                let dest = self.place(dest)?.map(|(dest, _)| dest);
+                intrinsics::call(self.codegen, current, &self.body.name, path, &values, dest)?;
+                current.end_with_jump(None, self.edge(block, *target)?);
+            }
+            TerminatorKind::Return => self.locals.get(Local::RETURN.index()).copied().flatten().map_or_else(
+                || current.end_with_void_return(None),
+                |value| current.end_with_return(None, value.to_rvalue()),
+            ),
+            TerminatorKind::Unreachable => self.abort(current),
+        }
+        Ok(())
+    }
+
+    /// Ends `block` with a call to `abort`, which never returns.
+    fn abort(&self, block: Block<'ctx>) {
+        block.add_eval(None, self.codegen.cx.new_call(None, self.codegen.abort, &[]));
+        // a block has to end with a jump, which is never taken
+        block.end_with_jump(None, block);
+    }
+
+    /// The block to jump to to go from `from` to `to`: `to` itself, or a block assigning its phis if it has any.
+    fn edge(&self, from: BlockId, to: BlockId) -> Result<Block<'ctx>, CodegenError> {
+        let phis: Vec<_> = self
+            .body
+            .block(to)
+            .phis
+            .iter()
+            .filter_map(|phi| Some((phi.dest, self.locals[phi.dest.index()]?, phi)))
+            .collect();
+        if phis.is_empty() {
+            return Ok(self.blocks[to.0]);
+        }
+
+        let edge = self.function.new_block(format!("bb{}_to_bb{}", from.0, to.0));
+        // the phis are assigned at once, so every value is read before any of them is written
+        let mut values = Vec::with_capacity(phis.len());
+        for (dest, local, phi) in phis {
+            let Some((_, operand)) = phi.args.iter().find(|(block, _)| *block == from) else { continue };
+            let Some(value) = self.operand(edge, operand)? else { continue };
+            let ty = &self.body.local(dest).ty;
+            let temporary =
+                self.function.new_local(None, self.codegen.ty(ty).ok_or_else(|| unsupported(self.body, ty))?, "phi");
+            edge.add_assignment(None, temporary, value);
+            values.push((local, temporary));
+        }
+        for (local, temporary) in values {
+            edge.add_assignment(None, local, temporary.to_rvalue());
+        }
+        edge.end_with_jump(None, self.blocks[to.0]);
+        Ok(edge)
+    }
+
+    /// Assigns `rvalue` to `place` in `block`, returning the block the code goes on in, which is another one if
+    /// the assignment can panic.
+    fn assign(&self, block: Block<'ctx>, place: &Place, rvalue: &Rvalue) -> Result<Block<'ctx>, CodegenError> {
+        let cx = self.codegen.cx;
+        let Some((dest, ty)) = self.place(place)? else { return Ok(block) };
+        match rvalue {
+            Rvalue::Use(operand) => {
+                if let Some(value) = self.operand(block, operand)? {
+                    block.add_assignment(None, dest, value);
+                }
+            }
+            Rvalue::Ref { place: borrowed, .. } => {
+                let (borrowed, _) = self.place(borrowed)?.ok_or_else(|| unsupported(self.body, &ty))?;
+                block.add_assignment(None, dest, borrowed.get_address(None));
+            }
+            Rvalue::Aggregate { def, operands } => {
+                let (_, fields) = self.codegen.structs.get(def).ok_or_else(|| unsupported(self.body, &ty))?;
+                for (field, operand) in fields.iter().zip(operands) {
+                    if let Some(value) = self.operand(block, operand)? {
+                        block.add_assignment(None, dest.access_field(None, *field), value);
+                    }
+                }
+            }
+            Rvalue::Variant { .. }
+            | Rvalue::Discriminant(_)
+            | Rvalue::Payload { .. }
+            | Rvalue::Array(_)
+            | Rvalue::Index { .. } => return Err(unsupported(self.body, &ty)),
+            Rvalue::BinaryOp { op, operands: [lhs, rhs] } => {
+                let (Some(lhs), Some(rhs)) = (self.operand(block, lhs)?, self.operand(block, rhs)?) else {
+                    return Err(unsupported(self.body, &ty));
+                };
+                let Some(comparison) = comparison_op(*op) else { return self.arithmetic(block, *op, dest, &ty, lhs, rhs) };
+                block.add_assignment(None, dest, cx.new_comparison(None, comparison, lhs, rhs));
+            }
+            Rvalue::UnaryOp { op, operand } => {
+                let operand = self.operand(block, operand)?.ok_or_else(|| unsupported(self.body, &ty))?;
+                match op {
+                    UnOp::Neg => {
+                        let zero = cx.new_rvalue_zero(operand.get_type());
+                        return Ok(self.check(block, self.overflowing("sub", dest, zero, operand)));
+                    }
+                    UnOp::Not => {
+                        let not = cx.new_unary_op(None, UnaryOp::LogicalNegate, cx.new_type::<bool>(), operand);
+                        block.add_assignment(None, dest, not);
+                    }
+                }
+            }
+        }
+        Ok(block)
+    }
+
+    /// Assigns `lhs op rhs` of the integer type `ty` to `dest`, panicking if it overflows or divides by zero.
+    fn arithmetic(
+        &self,
+        block: Block<'ctx>,
+        op: BinOp,
+        dest: LValue<'ctx>,
+        ty: &Ty,
+        lhs: RValue<'ctx>,
+        rhs: RValue<'ctx>,
+    ) -> Result<Block<'ctx>, CodegenError> {
+        let cx = self.codegen.cx;
+        let builtin = match op {
+            BinOp::Add => "add",
+            BinOp::Sub => "sub",
+            BinOp::Mul => "mul",
+            _ => {
+                let Ty::Int(int) = ty.peel() else { return Err(unsupported(self.body, ty)) };
+                let int_type = crate::int_type(cx, *int);
+                let by_zero = cx.new_comparison(None, ComparisonOp::Equals, rhs, cx.new_rvalue_zero(int_type));
+                let block = self.check(block, by_zero);
+                let binary_op = if op == BinOp::Div { BinaryOp::Divide } else { BinaryOp::Modulo };
+                if !is_signed(*int) {
+                    block.add_assignment(None, dest, cx.new_binary_op(None, binary_op, int_type, lhs, rhs));
+                    return Ok(block);
+                }
+
+                // the quotient of the smallest value by `-1` is one more than the largest, and its remainder
+                // traps on most machines, so dividing by `-1` is negating
+                let by_minus_one = self.function.new_block("by_minus_one");
+                let divide = self.function.new_block("divide");
+                let divided = self.function.new_block("divided");
+                let minus_one = cx.new_rvalue_from_int(int_type, -1);
+                block.end_with_conditional(
+                    None,
+                    cx.new_comparison(None, ComparisonOp::Equals, rhs, minus_one),
+                    by_minus_one,
+                    divide,
+                );
+                let by_minus_one = if op == BinOp::Div {
+                    self.check(by_minus_one, self.overflowing("sub", dest, cx.new_rvalue_zero(int_type), lhs))
+                } else {
+                    by_minus_one.add_assignment(None, dest, cx.new_rvalue_zero(int_type));
+                    by_minus_one
+                };
+                by_minus_one.end_with_jump(None, divided);
+                divide.add_assignment(None, dest, cx.new_binary_op(None, binary_op, int_type, lhs, rhs));
+                divide.end_with_jump(None, divided);
+                return Ok(divided);
+            }
+        };
+        Ok(self.check(block, self.overflowing(builtin, dest, lhs, rhs)))
+    }
+
+    /// Stores `lhs` and `rhs` combined by the overflow checking builtin of GCC `builtin` in `dest`, returning
+    /// whether the result did not fit its type.
+    fn overflowing(&self, builtin: &str, dest: LValue<'ctx>, lhs: RValue<'ctx>, rhs: RValue<'ctx>) -> RValue<'ctx> {
+        let cx = self.codegen.cx;
+        let builtin = cx.get_builtin_function(format!("__builtin_{builtin}_overflow"));
+        cx.new_call(None, builtin, &[lhs, rhs, dest.get_address(None)])
+    }
+
+    /// Ends `block` with a jump to a block that panics if `failed` holds, exiting with the status of
+    /// [`PANIC_STATUS`], and returns the block the code goes on in otherwise.
+    fn check(&self, block: Block<'ctx>, failed: RValue<'ctx>) -> Block<'ctx> {
+        let cx = self.codegen.cx;
+        let panic = self.function.new_block("panic");
+        let next = self.function.new_block("checked");
+        block.end_with_conditional(None, failed, panic, next);
+        let status = cx.new_rvalue_from_int(cx.new_type::<i32>(), PANIC_STATUS);
+        panic.add_eval(None, cx.new_call(None, self.codegen.exit, &[status]));
+        // a block has to end with a jump, which is never taken
+        panic.end_with_jump(None, panic);
+        next
+    }
+
+    /// Where `place` is stored, with its type, `None` if it is `()`.
+    fn place(&self, place: &Place) -> Result<Option<(LValue<'ctx>, Ty)>, CodegenError> {
+        let Some(mut lvalue) = self.locals[place.local.index()] else { return Ok(None) };
+        let mut ty = self.body.local(place.local).ty.clone();
+        for field in &place.projection {
+            while let Ty::Ref { ty: pointee, .. } = ty.peel() {
+                let pointee = (**pointee).clone();
+                lvalue = lvalue.to_rvalue().dereference(None);
+                ty = pointee;
+            }
+            let Ty::Struct { def, .. } = ty.peel() else { return Err(unsupported(self.body, &ty)) };
+            let fields = self.codegen.structs.get(def).and_then(|(_, fields)| fields.get(*field));
+            let field_def = self.codegen.gem.struct_def(*def).and_then(|struct_def| struct_def.fields.get(*field));
+            let (Some(field), Some(field_def)) = (fields, field_def) else {
+                return Err(unsupported(self.body, &ty));
+            };
+            lvalue = lvalue.access_field(None, *field);
+            ty = field_def.ty.clone();
+        }
+        Ok((!is_void(&ty)).then_some((lvalue, ty)))
+    }
+
+    fn operand_ty(&self, operand: &Operand) -> Ty {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.codegen.gem.place_ty(self.body, place),
+            Operand::Const(constant) => constant.ty.clone(),
+        }
+    }
+
+    /// The value of `operand`, `None` if it is `()`. Strings are built in `block`.
+    fn operand(&self, block: Block<'ctx>, operand: &Operand) -> Result<Option<RValue<'ctx>>, CodegenError> {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => Ok(self.place(place)?.map(|(lvalue, _)| lvalue.to_rvalue())),
+            Operand::Const(constant) => self.constant(block, constant),
+        }
+    }
+
+    fn constant(&self, block: Block<'ctx>, constant: &Constant) -> Result<Option<RValue<'ctx>>, CodegenError> {
+        let cx = self.codegen.cx;
+        Ok(Some(match &constant.value {
+            ConstValue::Int(value) => {
+                let int = match constant.ty.peel() {
+                    Ty::Int(int) => *int,
+                    _ => IntTy::DEFAULT,
+                };
+                let ty = crate::int_type(cx, int);
+                // the bits of the value, cut to the width of the type
+                #[allow(clippy::cast_possible_truncation)]
+                let (low, high) = (*value as i64, (*value >> 64) as i64);
+                if matches!(int, IntTy::Explod | IntTy::Uexplod) {
+                    // there are no literals wider than a `long`
+                    let low = cx.new_cast(None, cx.new_rvalue_from_long(cx.new_type::<u64>(), low), ty);
+                    let high = cx.new_cast(None, cx.new_rvalue_from_long(cx.new_type::<i64>(), high), ty);
+                    let high = cx.new_binary_op(None, BinaryOp::LShift, ty, high, cx.new_rvalue_from_int(ty, 64));
+                    cx.new_binary_op(None, BinaryOp::BitwiseOr, ty, high, low)
+                } else {
+                    cx.new_rvalue_from_long(ty, low)
+                }
+            }
+            ConstValue::Bool(value) => cx.new_rvalue_from_int(cx.new_type::<bool>(), i32::from(*value)),
+            ConstValue::Char(value) => cx.new_rvalue_from_long(cx.new_type::<u32>(), i64::from(u32::from(*value))),
+            ConstValue::Str(value) => {
+                if value.contains('\0') {
+                    return Err(CodegenError::NullByte(self.body.name.clone()));
+                }
+                let str_type = self.codegen.str_type;
+                let text = self.function.new_local(None, str_type.ty, "str");
+                let len = i64::try_from(value.len()).unwrap_or(i64::MAX);
+                block.add_assignment(None, text.access_field(None, str_type.ptr), cx.new_string_literal(value));
+                let len_type = crate::int_type(cx, IntTy::Usize);
+                block.add_assignment(
+                    None,
+                    text.access_field(None, str_type.len),
+                    cx.new_rvalue_from_long(len_type, len),
+                );
+                text.to_rvalue()
+            }
+            ConstValue::Void => return Ok(None),
+            ConstValue::Named(name) => {
+                return Err(CodegenError::UnknownConstant { name: name.clone(), function: self.body.name.clone() })
+            }
+        }))
+    }
+}
+
+/// The comparison of libgccjit `op` is, `None` for arithmetic.
+const fn comparison_op(op: BinOp) -> Option<ComparisonOp> {
+    Some(match op {
+        BinOp::Eq => ComparisonOp::Equals,
+        BinOp::Ne => ComparisonOp::NotEquals,
+        BinOp::Lt => ComparisonOp::LessThan,
+        BinOp::Le => ComparisonOp::LessThanEquals,
+        BinOp::Gt => ComparisonOp::GreaterThan,
+        BinOp::Ge => ComparisonOp::GreaterThanEquals,
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem => return None,
+    })
+}
@@ -0,0 +1,115 @@
+//! `intrinsics.rs` - the functions of the standard library the backend generates the code of itself.
+//!
+//! The standard library only declares them, e.g. `println` in `tstd/src/io.tp`, so a call to one is a call
+//! to a function outside of the gem, by the path it resolved to. They are built on the C library,
+//! which every program is linked with and the compiler itself runs with.
+use crate::{int_type, is_signed, CodegenError, GccCodegen};
+use gccjit::{BinaryOp, Block, LValue, RValue, ToRValue};
+use topaz_hir::FORMAT;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+/// Generates the call to the function of the standard library at `path` made from `function` into `block`,
+/// assigning what it returns to `dest` if that is stored.
+pub(crate) fn call<'ctx>(
+    codegen: &GccCodegen<'ctx, '_>,
+    block: Block<'ctx>,
+    function: &str,
+    path: &str,
+    args: &[(RValue<'ctx>, Ty)],
+    dest: Option<LValue<'ctx>>,
+) -> Result<(), CodegenError> {
+    match (path, args) {
+        ("std::io::println", [(value, ty)]) => println(codegen, block, *value, ty),
+        (FORMAT, args) => format(codegen, block, args, dest),
+        _ => Err(CodegenError::UnknownFunction { callee: path.to_owned(), function: function.to_owned() }),
+    }
+}
+
+/// Prints `value` and a new line with `printf`.
+fn println<'ctx>(
+    codegen: &GccCodegen<'ctx, '_>,
+    block: Block<'ctx>,
+    value: RValue<'ctx>,
+    ty: &Ty,
+) -> Result<(), CodegenError> {
+    let cx = codegen.cx;
+    let (format, args) = conversion(codegen, value, ty)?;
+    let args: Vec<_> = std::iter::once(cx.new_string_literal(format!("{format}\n"))).chain(args).collect();
+    block.add_eval(None, cx.new_call(None, codegen.printf, &args));
+    Ok(())
+}
+
+/// Formats `args` one after the other into bytes from `malloc`, which are never freed, and assigns the string to
+/// `dest`. `snprintf` is called twice: to count the bytes, then to write them.
+fn format<'ctx>(
+    codegen: &GccCodegen<'ctx, '_>,
+    block: Block<'ctx>,
+    args: &[(RValue<'ctx>, Ty)],
+    dest: Option<LValue<'ctx>>,
+) -> Result<(), CodegenError> {
+    let cx = codegen.cx;
+    let mut format = String::new();
+    let mut values = Vec::with_capacity(args.len());
+    for (value, ty) in args {
+        let (conversion, taken) = conversion(codegen, *value, ty)?;
+        format.push_str(conversion);
+        values.extend(taken);
+    }
+    // formatting has no effect of its own
+    let Some(dest) = dest else { return Ok(()) };
+
+    let (int, size_t) = (cx.new_type::<i32>(), int_type(cx, IntTy::Usize));
+    let c_str = cx.new_string_literal("").get_type();
+    let format = cx.new_string_literal(format);
+    let function = block.get_function();
+    let len = function.new_local(None, int, "format_len");
+    let mut args = vec![cx.new_null(c_str), cx.new_rvalue_zero(size_t), format];
+    args.extend(&values);
+    block.add_assignment(None, len, cx.new_call(None, codegen.snprintf, &args));
+
+    let size = cx.new_cast(None, len.to_rvalue(), size_t);
+    let size = cx.new_binary_op(None, BinaryOp::Plus, size_t, size, cx.new_rvalue_one(size_t));
+    let ptr = function.new_local(None, c_str, "format_ptr");
+    block.add_assignment(None, ptr, cx.new_cast(None, cx.new_call(None, codegen.malloc, &[size]), c_str));
+    let mut args = vec![ptr.to_rvalue(), size, format];
+    args.extend(values);
+    block.add_eval(None, cx.new_call(None, codegen.snprintf, &args));
+
+    block.add_assignment(None, dest.access_field(None, codegen.str_type.ptr), ptr);
+    block.add_assignment(None, dest.access_field(None, codegen.str_type.len), cx.new_cast(None, len, size_t));
+    Ok(())
+}
+
+/// The conversion printing `value` with `printf`, and the arguments it takes.
+fn conversion<'ctx>(
+    codegen: &GccCodegen<'ctx, '_>,
+    value: RValue<'ctx>,
+    ty: &Ty,
+) -> Result<(&'static str, Vec<RValue<'ctx>>), CodegenError> {
+    let cx = codegen.cx;
+    let int = cx.new_type::<i32>();
+    Ok(match ty.peel() {
+        Ty::Str => {
+            // the precision of `%.*s` is an `int`
+            let len = cx.new_cast(None, value.access_field(None, codegen.str_type.len), int);
+            ("%.*s", vec![len, value.access_field(None, codegen.str_type.ptr)])
+        }
+        Ty::Int(int) if !matches!(int, IntTy::Explod | IntTy::Uexplod) => {
+            if is_signed(*int) {
+                ("%lld", vec![cx.new_cast(None, value, cx.new_type::<i64>())])
+            } else {
+                ("%llu", vec![cx.new_cast(None, value, cx.new_type::<u64>())])
+            }
+        }
+        Ty::Bool => {
+            // `false` is the first 5 bytes of the text, and `true` the 4 after them
+            let flag = cx.new_cast(None, value, int);
+            let five = cx.new_rvalue_from_int(int, 5);
+            let start = cx.new_binary_op(None, BinaryOp::Mult, int, flag, five);
+            let text = cx.new_array_access(None, cx.new_string_literal("falsetrue"), start).get_address(None);
+            let len = cx.new_binary_op(None, BinaryOp::Minus, int, five, flag);
+            ("%.*s", vec![len, text])
+        }
+        _ => return Err(CodegenError::UnsupportedPrint(ty.to_string())),
+    })
+}
@@ -1,14 +1,379 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
+#![warn(clippy::pedantic, clippy::nursery, clippy::unwrap_used, clippy::expect_used)]
+#![cfg(feature = "libgccjit")]
+
+//! Compiles the MIR of a gem with libgccjit, the library GCC generates code with, ahead of time or into memory.
+//!
+//! Every local of a function becomes a local of the function libgccjit builds, and every MIR block a block of
+//! the same name, after a `start` block copying the parameters into their locals. Values of `()` are never
+//! stored, so they have no locals and are neither passed nor returned. The phis of a block are assigned on the
+//! edges into it: a jump to a block with phis goes through a block of its own, which copies the value for that
+//! edge into a temporary for every phi before assigning any of them, as they are all assigned at once.
+//! Arithmetic is checked with the overflow builtins of GCC: a program that overflows or divides by zero panics,
+//! exiting with [`topaz_codegen::PANIC_STATUS`].
+//!
+//! The code is compiled into an object file, which is linked with the tools of the system like the code of the
+//! other backends, see [`topaz_codegen::link`], or into the memory of the compiler, see [`jit`]. The functions of
+//! the standard library are generated by the backend itself, see `intrinsics.rs`.
+//!
+//! Linking with libgccjit needs it installed, so the backend is only built with the `libgccjit` feature, which
+//! the `gcc` feature of topaz enables. Without it the crate is empty, and the workspace builds anywhere.
+
+use gccjit::{CompileResult, Context, Field, Function, FunctionType, OutputKind, Struct, Type};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use topaz_codegen::{link, Backend, Jit, JitCode, Output};
+use topaz_mir::{Local, MirGem};
+use topaz_resolve::def::DefId;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+// they take a `GccCodegen`, which a `pub` function cannot, however private its module
+#[allow(clippy::redundant_pub_crate)]
+mod function;
+#[allow(clippy::redundant_pub_crate)]
+mod intrinsics;
+
+pub use topaz_codegen::GemType;
+
+/// What keeps a gem from being compiled by libgccjit.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    #[error("cannot generate code for a value of type `{ty}` in `{function}`")]
+    UnsupportedType { ty: String, function: String },
+    #[error("cannot call `{callee}` from `{function}`, there is no code for it")]
+    UnknownFunction { callee: String, function: String },
+    #[error("the value of the constant `{name}` used in `{function}` is not known")]
+    UnknownConstant { name: String, function: String },
+    #[error("the string used in `{0}` has a null byte, which libgccjit cannot generate")]
+    NullByte(String),
+    #[error("a value of type `{0}` cannot be printed or interpolated into a string")]
+    UnsupportedPrint(String),
+    #[error("there is no function to start the program with")]
+    NoEntryPoint,
+    #[error("libgccjit rejected the generated code: {0}")]
+    Invalid(String),
+}
+
+/// What keeps a gem from being built.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    #[error(transparent)]
+    Codegen(#[from] CodegenError),
+    #[error("libgccjit cannot compile `{path}`: {message}")]
+    Compile { path: String, message: String },
+    #[error(transparent)]
+    Link(#[from] link::LinkError),
+}
+
+/// Generates the code of `gem` in `cx`, returning the name of the function of every body, in the order of
+/// [`MirGem::bodies`].
+///
+/// If there is an `entry` function, the code is a program: it gets the `main` function the C runtime starts it
+/// with, which calls `entry` and exits with `0`.
+///
+/// # Errors
+/// If the gem uses something the backend has no code for, e.g. a `maybe` type.
+pub fn codegen_gem<'ctx>(
+    cx: &'ctx Context<'ctx>,
+    gem: &MirGem,
+    entry: Option<DefId>,
+) -> Result<Vec<String>, CodegenError> {
+    let mut codegen = GccCodegen::new(cx, gem);
+    codegen.declare_structs()?;
+    codegen.declare_functions(entry.is_some())?;
+    for (body, function) in gem.bodies.iter().zip(&codegen.bodies) {
+        function::define(&codegen, body, *function)?;
+    }
+    if let Some(entry) = entry {
+        let entry = *codegen.functions.get(&entry).ok_or(CodegenError::NoEntryPoint)?;
+        let int = cx.new_type::<i32>();
+        let main = cx.new_function(None, FunctionType::Exported, int, &[], "main", false);
+        let start = main.new_block("start");
+        start.add_eval(None, cx.new_call(None, entry, &[]));
+        start.end_with_return(None, cx.new_rvalue_zero(int));
+    }
+    first_error(cx).map_or(Ok(codegen.symbols), |message| Err(CodegenError::Invalid(message)))
+}
+
+/// Compiles `gem` and writes `output` to `path`.
+///
+/// A linked output is built from an object file next to `path`, which is removed once it is linked.
+///
+/// # Errors
+/// If the code cannot be generated, libgccjit cannot compile it, or it cannot be linked.
+pub fn build(gem: &MirGem, entry: Option<DefId>, output: Output, path: &Path) -> Result<(), BuildError> {
+    let cx = Context::default();
+    // libraries can be loaded anywhere in memory, and programs are position independent on most systems
+    cx.add_command_line_option("-fPIC");
+    codegen_gem(&cx, gem, entry)?;
+
+    match output {
+        Output::Assembly => compile_to_file(&cx, OutputKind::Assembler, path)?,
+        Output::Object => compile_to_file(&cx, OutputKind::ObjectFile, path)?,
+        Output::Linked(gem_type) => {
+            let object = path.with_extension("o");
+            compile_to_file(&cx, OutputKind::ObjectFile, &object)?;
+            let linked = link::link(&object, gem_type, path);
+            let _ = std::fs::remove_file(&object);
+            linked?;
+        }
+    }
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn compile_to_file(cx: &Context<'_>, kind: OutputKind, path: &Path) -> Result<(), BuildError> {
+    cx.compile_to_file(kind, path.to_string_lossy());
+    first_error(cx).map_or(Ok(()), |message| Err(BuildError::Compile { path: path.display().to_string(), message }))
+}
+
+/// Compiles `gem` into the memory of this process.
+///
+/// # Errors
+/// If the code cannot be generated, or libgccjit cannot compile it.
+pub fn jit(gem: &MirGem) -> Result<JitGem, CodegenError> {
+    let cx = Context::default();
+    let symbols = codegen_gem(&cx, gem, None)?;
+    let result = cx.compile();
+    if let Some(message) = first_error(&cx) {
+        return Err(CodegenError::Invalid(message));
+    }
+    // the first body of a name is the one it refers to
+    let symbols = gem.bodies.iter().zip(symbols).rev().map(|(body, symbol)| (body.name.clone(), symbol)).collect();
+    Ok(JitGem { result, symbols })
+}
+
+/// A gem compiled into memory by [`jit`], whose code lives as long as it.
+pub struct JitGem {
+    result: CompileResult,
+    /// The name of the function of every body, by the name of the body
+    symbols: HashMap<String, String>,
+}
+
+impl JitCode for JitGem {
+    fn function(&self, name: &str) -> Option<*const ()> {
+        let function = self.result.get_function(self.symbols.get(name)?);
+        (!function.is_null()).then_some(function.cast_const())
+    }
+}
+
+/// The libgccjit backend.
+pub struct GccBackend;
+
+impl Backend for GccBackend {
+    type Error = BuildError;
+
+    fn name(&self) -> &'static str {
+        "gcc"
+    }
+
+    fn build(
+        &self,
+        gem: &MirGem,
+        _name: &str,
+        entry: Option<DefId>,
+        output: Output,
+        path: &Path,
+    ) -> Result<(), BuildError> {
+        build(gem, entry, output, path)
+    }
+}
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+impl Jit for GccBackend {
+    type Code = JitGem;
+
+    fn jit(&self, gem: &MirGem) -> Result<JitGem, BuildError> {
+        Ok(jit(gem)?)
+    }
+}
+
+/// The first error libgccjit ran into in `cx`, if any.
+fn first_error(cx: &Context<'_>) -> Option<String> {
+    cx.get_first_error().ok().flatten().map(str::to_owned)
+}
+
+/// `str`, with the fields of its bytes and its length.
+#[derive(Clone, Copy)]
+struct StrType<'ctx> {
+    ty: Type<'ctx>,
+    ptr: Field<'ctx>,
+    len: Field<'ctx>,
+}
+
+pub(crate) struct GccCodegen<'ctx, 'g> {
+    cx: &'ctx Context<'ctx>,
+    gem: &'g MirGem,
+    /// The function of every body, in the order of [`MirGem::bodies`]
+    bodies: Vec<Function<'ctx>>,
+    /// The name of the function of every body, in the same order
+    symbols: Vec<String>,
+    /// The functions of the gem by their definition, which methods have none of
+    functions: HashMap<DefId, Function<'ctx>>,
+    /// Every struct, with its fields
+    structs: HashMap<DefId, (Struct<'ctx>, Vec<Field<'ctx>>)>,
+    str_type: StrType<'ctx>,
+    printf: Function<'ctx>,
+    /// `snprintf` and `malloc`, which strings are formatted with
+    snprintf: Function<'ctx>,
+    malloc: Function<'ctx>,
+    abort: Function<'ctx>,
+    /// `exit`, which a program that panics exits with, see [`topaz_codegen::PANIC_STATUS`]
+    exit: Function<'ctx>,
+}
+
+impl<'ctx, 'g> GccCodegen<'ctx, 'g> {
+    fn new(cx: &'ctx Context<'ctx>, gem: &'g MirGem) -> Self {
+        // libgccjit has string literals be `const char *`
+        let c_str = cx.new_string_literal("").get_type();
+        let ptr = cx.new_field(None, c_str, "ptr");
+        let len = cx.new_field(None, int_type(cx, IntTy::Usize), "len");
+        let str_type = StrType { ty: cx.new_struct_type(None, "str", &[ptr, len]).as_type(), ptr, len };
+
+        let int = cx.new_type::<i32>();
+        let format = cx.new_parameter(None, c_str, "format");
+        let printf = cx.new_function(None, FunctionType::Extern, int, &[format], "printf", true);
+        let size_t = int_type(cx, IntTy::Usize);
+        let buffer = cx.new_parameter(None, c_str, "buffer");
+        let size = cx.new_parameter(None, size_t, "size");
+        let format = cx.new_parameter(None, c_str, "format");
+        let snprintf = cx.new_function(None, FunctionType::Extern, int, &[buffer, size, format], "snprintf", true);
+        let size = cx.new_parameter(None, size_t, "size");
+        let void_ptr = cx.new_type::<()>().make_pointer();
+        let malloc = cx.new_function(None, FunctionType::Extern, void_ptr, &[size], "malloc", false);
+        let abort = cx.new_function(None, FunctionType::Extern, cx.new_type::<()>(), &[], "abort", false);
+        let status = cx.new_parameter(None, int, "status");
+        let exit = cx.new_function(None, FunctionType::Extern, cx.new_type::<()>(), &[status], "exit", false);
+        Self {
+            cx,
+            gem,
+            bodies: Vec::new(),
+            symbols: Vec::new(),
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            str_type,
+            printf,
+            snprintf,
+            malloc,
+            abort,
+            exit,
+        }
+    }
+
+    /// Declares every struct without its fields, then sets their fields, so that they can refer to each other.
+    fn declare_structs(&mut self) -> Result<(), CodegenError> {
+        for struct_def in &self.gem.structs {
+            let opaque = self.cx.new_opaque_struct_type(None, &struct_def.name);
+            self.structs.insert(struct_def.def, (opaque, Vec::new()));
+        }
+        for struct_def in &self.gem.structs {
+            let fields = struct_def
+                .fields
+                .iter()
+                .map(|field| {
+                    let ty = self.ty(&field.ty).filter(|_| !is_void(&field.ty)).ok_or_else(|| {
+                        CodegenError::UnsupportedType { ty: field.ty.to_string(), function: struct_def.name.clone() }
+                    })?;
+                    Ok(self.cx.new_field(None, ty, &field.name))
+                })
+                .collect::<Result<Vec<_>, CodegenError>>()?;
+            if let Some((opaque, declared)) = self.structs.get_mut(&struct_def.def) {
+                opaque.set_fields(None, &fields);
+                *declared = fields;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a function without a body for every body of the gem, so that calls can refer to functions defined
+    /// later. The C `main` is left to the program's, if it has one.
+    fn declare_functions(&mut self, c_main: bool) -> Result<(), CodegenError> {
+        let mut taken: HashSet<String> =
+            ["printf", "snprintf", "malloc", "abort", "exit"].into_iter().map(str::to_owned).collect();
+        if c_main {
+            taken.insert("main".to_owned());
+        }
+
+        for body in &self.gem.bodies {
+            let unsupported =
+                |ty: &Ty| CodegenError::UnsupportedType { ty: ty.to_string(), function: body.name.clone() };
+            let mut params = Vec::with_capacity(body.arg_count);
+            for arg in body.args() {
+                let decl = body.local(arg);
+                if !is_void(&decl.ty) {
+                    let ty = self.ty(&decl.ty).ok_or_else(|| unsupported(&decl.ty))?;
+                    let name = decl.name.clone().unwrap_or_else(|| format!("_{}", arg.index()));
+                    params.push(self.cx.new_parameter(None, ty, name));
+                }
+            }
+            let ret = &body.local(Local::RETURN).ty;
+            let ret =
+                if is_void(ret) { self.cx.new_type::<()>() } else { self.ty(ret).ok_or_else(|| unsupported(ret))? };
+
+            // functions of different modules can have the same name
+            let symbol = fresh_symbol(&mut taken, &body.name);
+            let function = self.cx.new_function(None, FunctionType::Exported, ret, &params, &symbol, false);
+            self.bodies.push(function);
+            self.symbols.push(symbol);
+            if let Some(def) = body.def {
+                self.functions.insert(def, function);
+            }
+        }
+        Ok(())
+    }
+
+    /// The type values of `ty` have, `None` for the types the backend cannot represent.
+    ///
+    /// `str` is a struct of a pointer to its bytes and its length, and `char` a unicode scalar value.
+    fn ty(&self, ty: &Ty) -> Option<Type<'ctx>> {
+        Some(match ty.peel() {
+            Ty::Int(int) => int_type(self.cx, *int),
+            Ty::Bool => self.cx.new_type::<bool>(),
+            Ty::Char => self.cx.new_type::<u32>(),
+            Ty::Str => self.str_type.ty,
+            // a pointer to `()` points to nothing in particular
+            Ty::Ref { ty: pointee, .. } if is_void(pointee) => self.cx.new_type::<u8>().make_pointer(),
+            Ty::Ref { ty: pointee, .. } => self.ty(pointee)?.make_pointer(),
+            Ty::Struct { def, .. } => self.structs.get(def)?.0.as_type(),
+            _ => return None,
+        })
+    }
+}
+
+/// Whether values of `ty` are `()`, which are never stored. The results of the functions outside of the gem are
+/// not typed, and all of them are `()`.
+fn is_void(ty: &Ty) -> bool {
+    matches!(ty.peel(), Ty::Void | Ty::Var(_) | Ty::Error)
+}
+
+/// The integer type of `int`. libgccjit generates code for the system it runs on, whose pointers are as wide as
+/// those of the compiler.
+fn int_type<'ctx>(cx: &'ctx Context<'ctx>, int: IntTy) -> Type<'ctx> {
+    let bytes = match int {
+        IntTy::Byte | IntTy::Ubyte => 1,
+        IntTy::Short | IntTy::Ushort => 2,
+        IntTy::Int | IntTy::Uint => 4,
+        IntTy::Long | IntTy::Ulong => 8,
+        IntTy::Explod | IntTy::Uexplod => 16,
+        IntTy::Isize | IntTy::Usize => PTR_BYTES,
+    };
+    cx.new_int_type(bytes, is_signed(int))
+}
+
+const PTR_BYTES: i32 = if cfg!(target_pointer_width = "64") { 8 } else { 4 };
+
+const fn is_signed(int: IntTy) -> bool {
+    matches!(int, IntTy::Byte | IntTy::Short | IntTy::Int | IntTy::Long | IntTy::Explod | IntTy::Isize)
+}
+
+/// A name like `name` that is a C identifier and not `taken` yet, which it then is.
+fn fresh_symbol(taken: &mut HashSet<String>, name: &str) -> String {
+    let mut base: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if base.is_empty() || base.starts_with(|c: char| c.is_ascii_digit()) {
+        base.insert(0, '_');
+    }
+    let mut symbol = base.clone();
+    let mut suffix = 1;
+    while !taken.insert(symbol.clone()) {
+        symbol = format!("{base}_{suffix}");
+        suffix += 1;
     }
+    symbol
 }
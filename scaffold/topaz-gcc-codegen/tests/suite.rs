@@ -0,0 +1,29 @@
+#![cfg(feature = "libgccjit")]
+
+use topaz_codegen::suite;
+use topaz_gcc_codegen::GccBackend;
+
+#[test]
+fn test_programs() {
+    suite::programs(&GccBackend);
+}
+
+#[test]
+fn test_panics() {
+    suite::panics(&GccBackend);
+}
+
+#[test]
+fn test_outputs() {
+    suite::outputs(&GccBackend);
+}
+
+#[test]
+fn test_rejected() {
+    suite::rejected();
+}
+
+#[test]
+fn test_jit() {
+    suite::jit(&GccBackend);
+}
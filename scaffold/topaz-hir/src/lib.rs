@@ -0,0 +1,365 @@
+//! The high-level intermediate representation: the syntax tree of a checked gem, with every name
+//! resolved to its definition, the sugar of the surface syntax gone and the type of every expression.
+//!
+//! The HIR of a gem is built by [`lower::lower_gem`], after [`topaz_type_checker::StaticAnalyzer`]
+//! found no errors in it. Later stages of the compiler work on it instead of the syntax tree.
+use topaz_ast::location::Span;
+use topaz_resolve::def::DefId;
+use topaz_type_checker::traits::ImplId;
+use topaz_type_checker::ty::Ty;
+
+pub mod lower;
+mod pretty;
+
+/// Every function, struct and enum of a gem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HirGem {
+    /// Functions, then the methods of impls and traits, in the order of the files and the items in them. The
+    /// methods an impl takes from its trait follow its own
+    pub functions: Vec<Function>,
+    /// In the order they are defined
+    pub structs: Vec<Struct>,
+    /// In the order they are defined
+    pub enums: Vec<Enum>,
+}
+
+impl HirGem {
+    /// The function defined by `def`.
+    #[must_use]
+    pub fn function(&self, def: DefId) -> Option<&Function> {
+        self.functions.iter().find(|func| func.def == Some(def))
+    }
+
+    /// The function `callee` calls, if it is in the gem.
+    #[must_use]
+    pub fn callee(&self, callee: &Callee) -> Option<&Function> {
+        match callee {
+            Callee::Fn { def, .. } => self.function(*def),
+            Callee::Method { impl_id, name } => self.functions.iter().find(|func| {
+                func.impl_id == Some(*impl_id) && name.rsplit("::").next() == Some(func.name.as_str())
+            }),
+            Callee::External(_) => None,
+        }
+    }
+
+    #[must_use]
+    pub fn struct_def(&self, def: DefId) -> Option<&Struct> {
+        self.structs.iter().find(|struct_def| struct_def.def == def)
+    }
+
+    /// The struct `ty` is, if it is one.
+    #[must_use]
+    pub fn struct_of(&self, ty: &Ty) -> Option<&Struct> {
+        match ty {
+            Ty::Struct { def, .. } => self.struct_def(*def),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn enum_def(&self, def: DefId) -> Option<&Enum> {
+        self.enums.iter().find(|enum_def| enum_def.def == def)
+    }
+
+    /// Whether using a value of type `ty` copies it, instead of moving it.
+    #[must_use]
+    pub fn is_copy(&self, ty: &Ty) -> bool {
+        is_copy(&self.structs, &self.enums, ty)
+    }
+}
+
+/// Whether using a value of type `ty` copies it, instead of moving it,
+/// with `structs` and `enums` the structs and enums of the gem.
+#[must_use]
+pub fn is_copy(structs: &[Struct], enums: &[Enum], ty: &Ty) -> bool {
+    match ty {
+        Ty::Void | Ty::Int(_) | Ty::Char | Ty::Bool | Ty::Var(_) | Ty::IntVar(_) | Ty::Error => true,
+        Ty::Ref { mutable, .. } => !mutable,
+        Ty::Maybe(ty) | Ty::Alias { ty, .. } | Ty::Array { element: ty, .. } => is_copy(structs, enums, ty),
+        Ty::Struct { def, .. } => structs.iter().any(|struct_def| struct_def.def == *def && struct_def.copy),
+        Ty::Enum { def, .. } => enums.iter().any(|enum_def| enum_def.def == *def && enum_def.copy),
+        Ty::Str | Ty::Named(_) | Ty::Param(_) => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Struct {
+    pub def: DefId,
+    pub name: String,
+    pub fields: Vec<Field>,
+    /// Whether it implements `Copy`, so that using it copies it instead of moving it
+    pub copy: bool,
+    /// The impl of `Drop` run when it is dropped, unless only the blanket impl of `std` applies, which does nothing
+    pub drop: Option<ImplId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub ty: Ty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enum {
+    pub def: DefId,
+    pub name: String,
+    /// In the order they are declared, which is the order of their discriminants
+    pub variants: Vec<Variant>,
+    /// Whether it implements `Copy`, so that using it copies it instead of moving it
+    pub copy: bool,
+    /// The impl of `Drop` run when it is dropped, unless only the blanket impl of `std` applies, which does nothing
+    pub drop: Option<ImplId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub def: DefId,
+    pub name: String,
+    /// The types of the fields, which are named after their index
+    pub fields: Vec<Ty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    /// `None` for methods, which are not definitions of their own
+    pub def: Option<DefId>,
+    /// The impl the function is a method of, if it is one, including the methods it takes from its trait
+    pub impl_id: Option<ImplId>,
+    /// Whether it is the default body of a method of a trait. It is lowered as the trait declares it, with `this` a
+    /// [`Ty::Param`], and again for every impl that does not define the method
+    pub provided: bool,
+    pub name: String,
+    pub file_id: usize,
+    pub params: Vec<Param>,
+    pub ret: Ty,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub def: DefId,
+    pub name: String,
+    pub ty: Ty,
+    /// Whether the function takes ownership of the argument, instead of borrowing it
+    pub moved: bool,
+    /// The name of the parameter
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StmtKind {
+    /// `let mut name: ty = init;`, without an initializer if the binding is assigned later
+    Let {
+        def: DefId,
+        name: String,
+        /// The name of the binding
+        name_span: Span,
+        mutable: bool,
+        ty: Ty,
+        init: Option<Expr>,
+    },
+    /// `local = value;`
+    Assign {
+        local: DefId,
+        name: String,
+        value: Expr,
+    },
+    /// An expression evaluated for its side effects, e.g. a call
+    Expr(Expr),
+    Return(Option<Expr>),
+    While {
+        cond: Expr,
+        body: Block,
+    },
+    /// An `else if` is an `else` block holding the next `if`
+    If {
+        cond: Expr,
+        then: Block,
+        otherwise: Option<Block>,
+    },
+    /// Runs the first arm matching the variant of `scrutinee`, an enum
+    Match {
+        scrutinee: Expr,
+        arms: Vec<Arm>,
+    },
+    Block(Block),
+}
+
+/// An arm of a `match`, binding the fields of its variant to locals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arm {
+    /// The index of the variant in [`Enum::variants`], `None` for `_`, which matches every variant
+    pub variant: Option<usize>,
+    pub bindings: Vec<Binding>,
+    pub body: Block,
+}
+
+/// A local bound to the field of a variant, in the order of the fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub def: DefId,
+    pub name: String,
+    pub ty: Ty,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    /// The inferred type, with every alias expanded. [`Ty::Error`] for calls outside of the gem
+    pub ty: Ty,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprKind {
+    Literal(Lit),
+    /// A parameter or a `let` binding of the function
+    Local {
+        def: DefId,
+        name: String,
+    },
+    /// A constant, which nothing knows the value of yet
+    Const(String),
+    /// A call with an argument for every parameter, in the order of the parameters
+    Call {
+        callee: Callee,
+        args: Vec<Expr>,
+    },
+    /// A struct built by calling it, with a value for every field, in the order of the fields
+    Struct {
+        def: DefId,
+        fields: Vec<Expr>,
+    },
+    /// A variant of an enum, by its index in [`Enum::variants`], with a value for every field
+    Variant {
+        def: DefId,
+        index: usize,
+        fields: Vec<Expr>,
+    },
+    /// `[first, second]`
+    Array(Vec<Expr>),
+    /// `array[index]`, which panics if `index` is out of bounds
+    Index {
+        base: Box<Expr>,
+        index: Box<Expr>,
+    },
+    /// The field of a struct, by its index in [`Struct::fields`]. `base` may be a reference to the struct,
+    /// or to a reference to it
+    Field {
+        base: Box<Expr>,
+        index: usize,
+        name: String,
+    },
+    Ref {
+        mutable: bool,
+        expr: Box<Expr>,
+    },
+    /// Both operands are of the same type, see [`BinOp`]
+    Binary {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Unary {
+        op: UnOp,
+        operand: Box<Expr>,
+    },
+    /// Something that failed to check, e.g. the field of a type that has none
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lit {
+    Int(i128),
+    Str(String),
+    Char(char),
+    Bool(bool),
+}
+
+/// An operator taking two operands of the same type. Arithmetic is done on integers and panics
+/// when the result does not fit in their type, or on dividing by zero. Comparisons produce a `bool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinOp {
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+
+    #[must_use]
+    pub const fn is_comparison(self) -> bool {
+        !matches!(self, Self::Add | Self::Sub | Self::Mul | Self::Div | Self::Rem)
+    }
+}
+
+/// `-` negates a signed integer, panicking on the smallest one, `!` negates a `bool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+impl UnOp {
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Neg => "-",
+            Self::Not => "!",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Callee {
+    /// A function of the gem
+    Fn { def: DefId, name: String },
+    /// A method of an impl, by its name after the type the impl is for, e.g. `Guard::drop`. A method of a
+    /// trait is called through it, and the MIR calls them to drop values
+    Method { impl_id: ImplId, name: String },
+    /// A function outside of the gem, by its path, e.g. `std::io::println`. Its arguments are passed as written
+    External(String),
+}
+
+/// The path of the function an interpolated string is lowered to a call of, with its parts as the arguments.
+/// It returns them formatted like `std::io::println` prints them, one after the other. No source can declare it,
+/// as it takes any number of arguments of any type, so the standard library does not.
+pub const FORMAT: &str = "std::fmt::format";
@@ -0,0 +1,516 @@
+//! `lower.rs` - builds the HIR of a gem from its syntax tree and what the analysis found out about it.
+//!
+//! Calls are desugared on the way: named arguments are put in the order of the parameters,
+//! the parameters left out get their default value, and calling a struct or a variant builds it.
+//! A method of a trait is called through the impl of the trait for the type `this` stands for,
+//! and every impl gets a copy of the methods with a default body it does not define, in which
+//! `this` is its type. An interpolated string becomes a call to [`FORMAT`], and the body of a
+//! function that is an expression a block returning it.
+use crate::{
+    Arm, BinOp, Binding, Block, Callee, Enum, Expr, ExprKind, Field, Function, HirGem, Lit, Param, Stmt, StmtKind,
+    Struct, UnOp, Variant, FORMAT,
+};
+use std::collections::HashMap;
+use topaz_ast::block::Block as AstBlock;
+use topaz_ast::expr::{
+    BinOp as AstBinOp, Expr as AstExpr, ExprBinary, ExprBorrow, ExprConstAccess, ExprField, ExprIndex, ExprLit,
+    ExprUnary, ExprVarAccess, UnOp as AstUnOp,
+};
+use topaz_ast::item::func::{Func, FuncArg, FuncBody, Method};
+use topaz_ast::item::trait_def::TraitItem;
+use topaz_ast::item::Item;
+use topaz_ast::literal::Literal;
+use topaz_ast::location::{Span, WithSpan};
+use topaz_ast::path::Path;
+use topaz_ast::pattern::{ArmPattern, Pattern};
+use topaz_ast::statement::control::{ElseBranch, IfStmt, MatchArm, MatchStmt, ReturnStmt, WhileStmt};
+use topaz_ast::statement::func_call::{FuncCallArg, FuncCallStmt};
+use topaz_ast::statement::variable::{AssignStmt, LetStmt};
+use topaz_ast::statement::Statement;
+use topaz_resolve::def::{DefId, DefKind};
+use topaz_resolve::{Namespace, Res, ResolvedPath};
+use topaz_type_checker::pass::AnalysisSession;
+use topaz_type_checker::traits::{ImplId, TraitRef};
+use topaz_type_checker::ty::{Ty, THIS};
+
+/// Lowers every function, struct and enum of the gem `session` analyzed.
+///
+/// The analysis is expected to have run every pass without errors,
+/// whatever failed to check is lowered to [`ExprKind::Error`].
+#[must_use]
+pub fn lower_gem(session: &AnalysisSession) -> HirGem {
+    let mut funcs = HashMap::new();
+    let mut provided = HashMap::new();
+    for module in session.modules {
+        for item in &module.file.items {
+            match &item.value {
+                Item::Func(func) => {
+                    if let Some(def) = session.resolutions.decl_of(module.file_id, &func.2) {
+                        funcs.insert(def, (module.file_id, func));
+                    }
+                }
+                Item::Trait(trait_def) => {
+                    if let Some(def) = session.resolutions.decl_of(module.file_id, &trait_def.2) {
+                        let methods = trait_def.items().filter_map(|item| match item {
+                            TraitItem::Provided(Method(_, func)) => Some(func),
+                            TraitItem::Required(_) => None,
+                        });
+                        provided.insert(def, (module.file_id, methods.collect::<Vec<_>>()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    let cx = LoweringContext { session, funcs };
+
+    let mut functions = Vec::new();
+    // the impls are numbered in the order they are in, like in the `TraitEnv` of the session
+    let mut impls = (0..).map(ImplId);
+    for module in session.modules {
+        for item in &module.file.items {
+            match &item.value {
+                Item::Func(func) => functions.push(cx.lower_func(module.file_id, func, None, false, item.span)),
+                Item::Impl(impl_def) => {
+                    let impl_id = impls.next().expect("the impls are numbered endlessly");
+                    for Method(_, func) in impl_def.methods() {
+                        functions.push(cx.lower_func(module.file_id, &func.value, Some(impl_id), false, func.span));
+                    }
+                    let Some(TraitRef::Local(trait_def)) = &session.traits.get(impl_id).trait_ref else { continue };
+                    let Some((file_id, methods)) = provided.get(trait_def) else { continue };
+                    for func in methods {
+                        let overridden =
+                            impl_def.methods().any(|Method(_, method)| method.value.2.value() == func.value.2.value());
+                        if !overridden {
+                            functions.push(cx.lower_func(*file_id, &func.value, Some(impl_id), true, func.span));
+                        }
+                    }
+                }
+                Item::Trait(trait_def) => {
+                    for item in trait_def.items() {
+                        if let TraitItem::Provided(Method(_, func)) = item {
+                            functions.push(cx.lower_func(module.file_id, &func.value, None, true, func.span));
+                        }
+                    }
+                }
+                Item::Import(_) | Item::TypeAlias(_) | Item::Struct(_) | Item::Enum(_) => {}
+            }
+        }
+    }
+
+    let mut structs: Vec<_> = session
+        .structs
+        .structs
+        .values()
+        .map(|struct_def| Struct {
+            def: struct_def.def,
+            name: struct_def.name.to_string(),
+            fields: struct_def
+                .fields
+                .iter()
+                .map(|field| Field { name: field.name.to_string(), ty: field.ty.expanded() })
+                .collect(),
+            copy: session.structs.copy.contains(&struct_def.def),
+            drop: session.traits.drop_impl(&Ty::Struct { def: struct_def.def, name: struct_def.name.to_string() }),
+        })
+        .collect();
+    structs.sort_by_key(|struct_def| struct_def.def);
+
+    let mut enums: Vec<_> = session
+        .structs
+        .enums
+        .values()
+        .map(|enum_def| Enum {
+            def: enum_def.def,
+            name: enum_def.name.to_string(),
+            variants: enum_def
+                .variants
+                .iter()
+                .map(|variant| Variant {
+                    def: variant.def,
+                    name: variant.name.to_string(),
+                    fields: variant.fields.iter().map(|field| field.ty.expanded()).collect(),
+                })
+                .collect(),
+            copy: session.structs.copy.contains(&enum_def.def),
+            drop: session.traits.drop_impl(&Ty::Enum { def: enum_def.def, name: enum_def.name.to_string() }),
+        })
+        .collect();
+    enums.sort_by_key(|enum_def| enum_def.def);
+
+    HirGem { functions, structs, enums }
+}
+
+struct LoweringContext<'a> {
+    session: &'a AnalysisSession<'a>,
+    /// Every function of the gem and its file, to find the default values of its parameters in
+    funcs: HashMap<DefId, (usize, &'a Func)>,
+}
+
+impl<'a> LoweringContext<'a> {
+    /// Lowers `func`, a method of the impl `method_of` if it has one, in which `this` stands for the type of the impl.
+    fn lower_func(
+        &self,
+        file_id: usize,
+        Func(_, _, name, args, ret, body): &Func,
+        method_of: Option<ImplId>,
+        provided: bool,
+        span: Span,
+    ) -> Function {
+        let session = self.session;
+        let def = session.resolutions.decl_of(file_id, name);
+        let this: HashMap<_, _> = method_of
+            .map(|impl_id| (THIS.to_owned(), session.traits.get(impl_id).self_ty.clone()))
+            .into_iter()
+            .collect();
+        let ret = match def.and_then(|def| session.types.signatures.get(&def)) {
+            Some(sig) => sig.ret.expanded(),
+            None => ret.as_ref().map_or(Ty::Void, |(_, ty)| {
+                session.aliases.lower(session.resolutions, file_id, &ty.value).subst(&this).expanded()
+            }),
+        };
+
+        let lowering = FnLowering { cx: self, file_id, this, substs: HashMap::new() };
+        let params = args
+            .iter()
+            .filter_map(|arg @ FuncArg(_, pattern, _, _)| {
+                let Pattern::Ident(name) = pattern;
+                let def = session.resolutions.decl_of(file_id, name)?;
+                Some(Param {
+                    def,
+                    name: name.to_string(),
+                    ty: lowering.local_ty(def),
+                    moved: arg.is_move(),
+                    span: name.span(),
+                })
+            })
+            .collect();
+
+        let body = match body {
+            FuncBody::Block(block) => lowering.lower_block(block, span),
+            FuncBody::Expr(_, expr) => {
+                let expr = lowering.lower_expr(expr);
+                // nothing is returned from a function returning `()`, the expression is only evaluated
+                let stmt_span = expr.span;
+                let kind = if ret == Ty::Void { StmtKind::Expr(expr) } else { StmtKind::Return(Some(expr)) };
+                Block { stmts: vec![Stmt { kind, span: stmt_span }], span }
+            }
+        };
+        Function { def, impl_id: method_of, provided, name: name.to_string(), file_id, params, ret, body, span }
+    }
+}
+
+/// Lowers the body of a function, or the default value of a parameter.
+struct FnLowering<'a, 'b> {
+    cx: &'b LoweringContext<'a>,
+    /// The file the syntax tree being lowered is in
+    file_id: usize,
+    /// What `this` stands for, in a method of an impl
+    this: HashMap<String, Ty>,
+    /// In a default value: the arguments passed for the parameters of the callee it refers to
+    substs: HashMap<DefId, Expr>,
+}
+
+impl FnLowering<'_, '_> {
+    fn local_ty(&self, def: DefId) -> Ty {
+        self.cx.session.types.locals.get(&def).map_or(Ty::Error, |ty| ty.subst(&self.this).expanded())
+    }
+
+    fn lower_block(&self, block: &AstBlock, span: Span) -> Block {
+        Block { stmts: block.statements().filter_map(|statement| self.lower_statement(statement)).collect(), span }
+    }
+
+    fn lower_statement(&self, WithSpan { value: statement, span }: &WithSpan<Statement>) -> Option<Stmt> {
+        let kind = match statement {
+            Statement::Let(LetStmt(_, mutable, name, _, init)) => {
+                let def = self.cx.session.resolutions.decl_of(self.file_id, name)?;
+                StmtKind::Let {
+                    def,
+                    name: name.to_string(),
+                    name_span: name.span(),
+                    mutable: mutable.is_some(),
+                    ty: self.local_ty(def),
+                    init: init.as_ref().map(|(_, init)| self.lower_expr(init)),
+                }
+            }
+            Statement::Assign(AssignStmt(place, _, value)) => {
+                let value = self.lower_expr(value);
+                match self.local_of(place) {
+                    Some(local) => StmtKind::Assign { local, name: place.to_string(), value },
+                    // only locals can be assigned to, anything else is just evaluated
+                    None => StmtKind::Expr(value),
+                }
+            }
+            Statement::FuncCall(call) => StmtKind::Expr(self.lower_call(call, None, *span)),
+            Statement::Return(ReturnStmt(_, value)) => {
+                StmtKind::Return(value.as_ref().map(|value| self.lower_expr(value)))
+            }
+            Statement::While(WhileStmt(_, condition, body)) => {
+                StmtKind::While { cond: self.lower_expr(condition), body: self.lower_block(body, *span) }
+            }
+            Statement::If(if_stmt) => self.lower_if(if_stmt),
+            Statement::Match(MatchStmt(_, scrutinee, arms)) => StmtKind::Match {
+                scrutinee: self.lower_expr(scrutinee),
+                arms: arms.content().iter().map(|arm| self.lower_arm(arm)).collect(),
+            },
+            Statement::Block(block) => StmtKind::Block(self.lower_block(block, *span)),
+        };
+        Some(Stmt { kind, span: *span })
+    }
+
+    fn lower_arm(&self, MatchArm(pattern, _, body): &MatchArm) -> Arm {
+        let (variant, bindings) = match &pattern.value {
+            ArmPattern::Variant(path, bindings) => (self.variant_of(path).map(|(_, index)| index), bindings.as_slice()),
+            ArmPattern::Wildcard(_) => (None, [].as_slice()),
+        };
+        let bindings = bindings
+            .iter()
+            .filter_map(|name| {
+                let def = self.cx.session.resolutions.decl_of(self.file_id, name)?;
+                Some(Binding { def, name: name.to_string(), ty: self.local_ty(def), span: name.span() })
+            })
+            .collect();
+        Arm { variant, bindings, body: self.lower_block(&body.value, body.span) }
+    }
+
+    /// The enum `path` is a variant of and the index of the variant, if it is one.
+    fn variant_of(&self, path: &Path) -> Option<(DefId, usize)> {
+        let session = self.cx.session;
+        let Res::Def(def) = session.resolutions.res_of(self.file_id, path)? else { return None };
+        session.structs.variant(*def).map(|(enum_def, index)| (enum_def.def, index))
+    }
+
+    fn lower_if(&self, IfStmt(_, condition, then, otherwise): &IfStmt) -> StmtKind {
+        let otherwise = otherwise.as_ref().map(|(_, otherwise)| match otherwise {
+            ElseBranch::Block(block) => self.lower_block(&block.value, block.span),
+            ElseBranch::If(if_stmt) => {
+                let span = Span::new(if_stmt.1.span.start, if_stmt.2.span.end);
+                Block { stmts: vec![Stmt { kind: self.lower_if(if_stmt), span }], span }
+            }
+        });
+        StmtKind::If { cond: self.lower_expr(condition), then: self.lower_block(&then.value, then.span), otherwise }
+    }
+
+    /// The parameter or `let` binding `path` refers to, if it is one.
+    fn local_of(&self, path: &Path) -> Option<DefId> {
+        let session = self.cx.session;
+        match session.resolutions.res_of(self.file_id, path)? {
+            Res::Def(def) if matches!(session.resolutions.defs.get(*def).kind, DefKind::Param | DefKind::Local) => {
+                Some(*def)
+            }
+            _ => None,
+        }
+    }
+
+    fn expr_ty(&self, span: Span) -> Ty {
+        self.cx.session.types.exprs.get(&(self.file_id, span)).map_or(Ty::Error, |ty| ty.subst(&self.this).expanded())
+    }
+
+    fn lower_expr(&self, WithSpan { value: expr, span }: &WithSpan<AstExpr>) -> Expr {
+        self.lower_expr_kind(expr, self.expr_ty(*span), *span)
+    }
+
+    /// Lowers `expr` of type `ty`, spanning `span`.
+    fn lower_expr_kind(&self, expr: &AstExpr, ty: Ty, span: Span) -> Expr {
+        let kind = match expr {
+            AstExpr::Literal(ExprLit(literal)) => ExprKind::Literal(lower_literal(literal)),
+            AstExpr::Borrow(ExprBorrow(_, mutable, borrowed)) => {
+                ExprKind::Ref { mutable: mutable.is_some(), expr: Box::new(self.lower_expr(borrowed)) }
+            }
+            AstExpr::VariableAccess(ExprVarAccess(path)) => match self.local_of(path) {
+                Some(def) => match self.substs.get(&def) {
+                    Some(arg) => return arg.clone(),
+                    None => ExprKind::Local { def, name: path.to_string() },
+                },
+                // a variant without fields
+                None => match self.variant_of(path) {
+                    Some((def, index)) => ExprKind::Variant { def, index, fields: Vec::new() },
+                    None => ExprKind::Error,
+                },
+            },
+            AstExpr::ConstAccess(ExprConstAccess(path)) => ExprKind::Const(path.to_string()),
+            AstExpr::FuncCall(call) => return self.lower_call(call, Some(ty), span),
+            AstExpr::Field(ExprField(base, _, field)) => {
+                let base = self.lower_expr(base);
+                let index =
+                    self.cx.session.structs.struct_of(base.ty.referent()).and_then(|struct_def| {
+                        struct_def.fields.iter().position(|def| def.name.value() == field.value())
+                    });
+                match index {
+                    Some(index) => ExprKind::Field { base: Box::new(base), index, name: field.to_string() },
+                    None => ExprKind::Error,
+                }
+            }
+            AstExpr::Array(array) => {
+                ExprKind::Array(array.elements().map(|element| self.lower_expr(element)).collect())
+            }
+            AstExpr::Index(ExprIndex(base, index)) => {
+                ExprKind::Index { base: Box::new(self.lower_expr(base)), index: Box::new(self.lower_expr(index)) }
+            }
+            AstExpr::Interpolated(interpolated) => ExprKind::Call {
+                callee: Callee::External(FORMAT.to_owned()),
+                args: interpolated.parts().map(|part| self.lower_expr(part)).collect(),
+            },
+            AstExpr::Binary(ExprBinary(lhs, op, rhs)) => ExprKind::Binary {
+                op: lower_bin_op(*op),
+                lhs: Box::new(self.lower_expr(lhs)),
+                rhs: Box::new(self.lower_expr(rhs)),
+            },
+            AstExpr::Unary(ExprUnary(op, operand)) => {
+                let operand = self.lower_expr(operand);
+                match (op, operand.kind) {
+                    // a negative literal, e.g. `-128byte`, which would not fit its type before being negated
+                    (AstUnOp::Neg(_), ExprKind::Literal(Lit::Int(value))) => {
+                        ExprKind::Literal(Lit::Int(value.wrapping_neg()))
+                    }
+                    (op, kind) => ExprKind::Unary {
+                        op: match op {
+                            AstUnOp::Neg(_) => UnOp::Neg,
+                            AstUnOp::Not(_) => UnOp::Not,
+                        },
+                        operand: Box::new(Expr { kind, ..operand }),
+                    },
+                }
+            }
+        };
+        Expr { kind, ty, span }
+    }
+
+    /// Lowers a call, with an argument for every parameter of the callee if it is in the gem.
+    /// The type of a call used as a statement was never recorded, so it is the return type of the callee.
+    fn lower_call(&self, FuncCallStmt(callee, args): &FuncCallStmt, ty: Option<Ty>, span: Span) -> Expr {
+        let session = self.cx.session;
+        let res = session.resolutions.res_of(self.file_id, callee);
+        let def = match res {
+            Some(Res::Def(def)) => Some(*def),
+            _ => None,
+        };
+        let sig = def.and_then(|def| session.types.signatures.get(&def));
+        let (Some(def), Some(sig)) = (def, sig) else {
+            let args = args.content().iter().map(|FuncCallArg(_, value)| self.lower_expr(value)).collect();
+            return Expr {
+                kind: ExprKind::Call {
+                    callee: Callee::External(external_path(res).unwrap_or_else(|| callee.to_string())),
+                    args,
+                },
+                ty: ty.unwrap_or(Ty::Error),
+                span,
+            };
+        };
+        // a method of a trait is called through the impl for the type `this` stands for
+        let method = session.traits.method(def).map(|(trait_def, _)| {
+            let this = session.types.methods.get(&(self.file_id, callee.span()));
+            let this = this.map_or(Ty::Error, |this| this.subst(&self.this).expanded());
+            let impl_id = session.traits.select(&TraitRef::Local(trait_def.def), &this).ok();
+            (impl_id, this)
+        });
+        let ret = match &method {
+            Some((_, this)) => sig.ret.subst(&HashMap::from([(THIS.to_owned(), this.clone())])),
+            None => sig.ret.clone(),
+        };
+        let ty = ty.unwrap_or_else(|| ret.expanded());
+
+        let mut passed: Vec<Option<Expr>> = vec![None; sig.params.len()];
+        let mut positional = 0;
+        for FuncCallArg(named, value) in args.content().iter() {
+            let index = match named {
+                Some((name, _)) => sig.params.iter().position(|param| param.name.value() == name.value()),
+                None => {
+                    positional += 1;
+                    Some(positional - 1)
+                }
+            };
+            if let Some(slot) = index.and_then(|index| passed.get_mut(index)) {
+                *slot = Some(self.lower_expr(value));
+            }
+        }
+
+        // the defaults are filled in in order, as each one can refer to the parameters before it
+        let mut args = Vec::with_capacity(passed.len());
+        for (index, arg) in passed.into_iter().enumerate() {
+            let arg = arg.unwrap_or_else(|| self.lower_default(def, index, &args, span));
+            args.push(arg);
+        }
+
+        let kind = match session.resolutions.defs.get(def).kind {
+            DefKind::Struct => ExprKind::Struct { def, fields: args },
+            DefKind::Variant => match session.structs.variant(def) {
+                Some((enum_def, index)) => ExprKind::Variant { def: enum_def.def, index, fields: args },
+                None => ExprKind::Error,
+            },
+            DefKind::Method => match method {
+                Some((Some(impl_id), this)) => {
+                    ExprKind::Call { callee: Callee::Method { impl_id, name: format!("{this}::{}", sig.name) }, args }
+                }
+                _ => ExprKind::Error,
+            },
+            _ => ExprKind::Call { callee: Callee::Fn { def, name: sig.name.to_string() }, args },
+        };
+        Expr { kind, ty, span }
+    }
+
+    /// The default value of the parameter `index` of the function `def`, in a call at `span`
+    /// passing `args` to the parameters before it.
+    ///
+    /// A parameter the default value refers to is replaced by its argument,
+    /// which is evaluated again if it has side effects.
+    fn lower_default(&self, def: DefId, index: usize, args: &[Expr], span: Span) -> Expr {
+        let error = Expr { kind: ExprKind::Error, ty: Ty::Error, span };
+        let Some(&(file_id, Func(_, _, _, params, _, _))) = self.cx.funcs.get(&def) else { return error };
+        let Some(FuncArg(_, _, _, Some((_, default)))) = params.get(index) else { return error };
+
+        let resolutions = self.cx.session.resolutions;
+        let substs = params
+            .iter()
+            .zip(args)
+            .filter_map(|(FuncArg(_, Pattern::Ident(name), _, _), arg)| {
+                Some((resolutions.decl_of(file_id, name)?, arg.clone()))
+            })
+            .collect();
+        let lowering = FnLowering { cx: self.cx, file_id, this: self.this.clone(), substs };
+        lowering.lower_expr(default)
+    }
+}
+
+fn lower_literal(literal: &Literal) -> Lit {
+    match literal {
+        Literal::String(string) => Lit::Str(string.0.content().clone()),
+        Literal::Char(char) => Lit::Char(*char.0.content()),
+        Literal::Bool(bool) => Lit::Bool(bool.0),
+        Literal::Number(number) => {
+            // the type checker rejects the literals that do not fit their type, and only `uexplod` literals go
+            // beyond `i128::MAX`, they keep their bits
+            #[allow(clippy::cast_possible_wrap)]
+            let value = number.magnitude().unwrap_or_default() as i128;
+            Lit::Int(if number.is_negative() { value.wrapping_neg() } else { value })
+        }
+    }
+}
+
+const fn lower_bin_op(op: AstBinOp) -> BinOp {
+    match op {
+        AstBinOp::Add(_) => BinOp::Add,
+        AstBinOp::Sub(_) => BinOp::Sub,
+        AstBinOp::Mul(_) => BinOp::Mul,
+        AstBinOp::Div(_) => BinOp::Div,
+        AstBinOp::Rem(_) => BinOp::Rem,
+        AstBinOp::Eq(_) => BinOp::Eq,
+        AstBinOp::Ne(_) => BinOp::Ne,
+        AstBinOp::Lt(_) => BinOp::Lt,
+        AstBinOp::Le(_) => BinOp::Le,
+        AstBinOp::Gt(_) => BinOp::Gt,
+        AstBinOp::Ge(_) => BinOp::Ge,
+    }
+}
+
+/// The path of a function of `std` or `core` a call resolved to, e.g. `std::io::println` for `println`.
+fn external_path(res: Option<&Res>) -> Option<String> {
+    let Some(Res::External(ResolvedPath::Path(namespace, segments, _))) = res else { return None };
+    let namespace = match namespace {
+        Namespace::Std => "std",
+        Namespace::Core => "core",
+        Namespace::Gem(_) => return None,
+    };
+    let segments = segments.iter().map(ToString::to_string);
+    Some(std::iter::once(namespace.to_owned()).chain(segments).collect::<Vec<_>>().join("::"))
+}
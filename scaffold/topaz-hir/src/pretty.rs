@@ -0,0 +1,242 @@
+//! `pretty.rs` - prints the HIR of a gem close to the source it was lowered from,
+//! e.g. `greet(name = "Ruby")` with a default `times: int = 1` is printed as `greet("Ruby", 1int)`.
+use crate::{Block, Callee, Expr, ExprKind, Function, HirGem, Lit, Stmt, StmtKind};
+use std::fmt::{Display, Formatter, Result, Write};
+use topaz_type_checker::ty::Ty;
+
+const INDENT: &str = "    ";
+
+impl Display for HirGem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for struct_def in &self.structs {
+            let fields: Vec<_> =
+                struct_def.fields.iter().map(|field| format!("{}: {}", field.name, field.ty)).collect();
+            writeln!(f, "struct {} {{ {} }}", struct_def.name, fields.join(", "))?;
+        }
+        for enum_def in &self.enums {
+            let variants: Vec<_> = enum_def
+                .variants
+                .iter()
+                .map(|variant| {
+                    if variant.fields.is_empty() {
+                        return variant.name.clone();
+                    }
+                    let fields: Vec<_> = variant.fields.iter().map(ToString::to_string).collect();
+                    format!("{}({})", variant.name, fields.join(", "))
+                })
+                .collect();
+            writeln!(f, "enum {} {{ {} }}", enum_def.name, variants.join(", "))?;
+        }
+        for (index, func) in self.functions.iter().enumerate() {
+            if index > 0 || !self.structs.is_empty() || !self.enums.is_empty() {
+                writeln!(f)?;
+            }
+            Printer { gem: self, f: &mut *f, depth: 0 }.function(func)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Callee {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::Fn { name, .. } | Self::Method { name, .. } => write!(f, "{name}"),
+            Self::External(path) => write!(f, "{path}"),
+        }
+    }
+}
+
+struct Printer<'a, 'f, 'g> {
+    gem: &'a HirGem,
+    f: &'f mut Formatter<'g>,
+    depth: usize,
+}
+
+impl Printer<'_, '_, '_> {
+    fn function(&mut self, func: &Function) -> Result {
+        let params: Vec<_> = func
+            .params
+            .iter()
+            .map(|param| format!("{}{}: {}", if param.moved { "move " } else { "" }, param.name, param.ty))
+            .collect();
+        write!(self.f, "func {}({})", func.name, params.join(", "))?;
+        if func.ret != Ty::Void {
+            write!(self.f, " -> {}", func.ret)?;
+        }
+        write!(self.f, " ")?;
+        self.block(&func.body)?;
+        writeln!(self.f)
+    }
+
+    fn block(&mut self, block: &Block) -> Result {
+        if block.stmts.is_empty() {
+            return write!(self.f, "{{}}");
+        }
+        writeln!(self.f, "{{")?;
+        self.depth += 1;
+        for stmt in &block.stmts {
+            self.stmt(stmt)?;
+        }
+        self.depth -= 1;
+        write!(self.f, "{}}}", INDENT.repeat(self.depth))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result {
+        write!(self.f, "{}", INDENT.repeat(self.depth))?;
+        match &stmt.kind {
+            StmtKind::Let { name, mutable, ty, init, .. } => {
+                write!(self.f, "let {}{name}: {ty}", if *mutable { "mut " } else { "" })?;
+                if let Some(init) = init {
+                    write!(self.f, " = {}", self.expr(init))?;
+                }
+                write!(self.f, ";")?;
+            }
+            StmtKind::Assign { name, value, .. } => write!(self.f, "{name} = {};", self.expr(value))?,
+            StmtKind::Expr(expr) => write!(self.f, "{};", self.expr(expr))?,
+            StmtKind::Return(None) => write!(self.f, "return;")?,
+            StmtKind::Return(Some(value)) => write!(self.f, "return {};", self.expr(value))?,
+            StmtKind::While { cond, body } => {
+                write!(self.f, "while {} ", self.expr(cond))?;
+                self.block(body)?;
+            }
+            StmtKind::If { cond, then, otherwise } => {
+                write!(self.f, "if {} ", self.expr(cond))?;
+                self.block(then)?;
+                if let Some(otherwise) = otherwise {
+                    write!(self.f, " else ")?;
+                    self.block(otherwise)?;
+                }
+            }
+            StmtKind::Match { scrutinee, arms } => {
+                writeln!(self.f, "match {} {{", self.expr(scrutinee))?;
+                self.depth += 1;
+                for arm in arms {
+                    write!(self.f, "{}", INDENT.repeat(self.depth))?;
+                    let enum_def = match &scrutinee.ty {
+                        Ty::Enum { def, .. } => self.gem.enum_def(*def),
+                        _ => None,
+                    };
+                    let variant = arm.variant.and_then(|index| {
+                        let enum_def = enum_def?;
+                        Some(format!("{}::{}", enum_def.name, enum_def.variants.get(index)?.name))
+                    });
+                    match variant {
+                        Some(variant) if arm.bindings.is_empty() => write!(self.f, "{variant}")?,
+                        Some(variant) => {
+                            let bindings: Vec<_> = arm.bindings.iter().map(|binding| binding.name.as_str()).collect();
+                            write!(self.f, "{variant}({})", bindings.join(", "))?;
+                        }
+                        None => write!(self.f, "_")?,
+                    }
+                    write!(self.f, " => ")?;
+                    self.block(&arm.body)?;
+                    writeln!(self.f)?;
+                }
+                self.depth -= 1;
+                write!(self.f, "{}}}", INDENT.repeat(self.depth))?;
+            }
+            StmtKind::Block(block) => self.block(block)?,
+        }
+        writeln!(self.f)
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        let mut out = String::new();
+        self.write_expr(&mut out, expr).expect("writing to a string cannot fail");
+        out
+    }
+
+    fn write_expr(&self, out: &mut String, expr: &Expr) -> Result {
+        match &expr.kind {
+            ExprKind::Literal(Lit::Int(value)) => write!(out, "{value}{}", expr.ty),
+            ExprKind::Literal(Lit::Str(value)) => write!(out, "{value:?}"),
+            ExprKind::Literal(Lit::Char(value)) => write!(out, "{value:?}"),
+            ExprKind::Literal(Lit::Bool(value)) => write!(out, "{value}"),
+            ExprKind::Local { name, .. } => write!(out, "{name}"),
+            ExprKind::Const(path) => write!(out, "{path}"),
+            ExprKind::Call { callee, args } => {
+                write!(out, "{callee}(")?;
+                self.write_list(out, args.iter().map(|arg| (None, arg)))?;
+                write!(out, ")")
+            }
+            ExprKind::Struct { def, fields } => {
+                let struct_def = self.gem.struct_def(*def);
+                write!(out, "{}(", struct_def.map_or("{struct}", |struct_def| &struct_def.name))?;
+                let names = struct_def
+                    .into_iter()
+                    .flat_map(|struct_def| &struct_def.fields)
+                    .map(|field| Some(field.name.as_str()));
+                self.write_list(out, names.chain(std::iter::repeat(None)).zip(fields))?;
+                write!(out, ")")
+            }
+            ExprKind::Variant { def, index, fields } => {
+                let enum_def = self.gem.enum_def(*def);
+                let variant = enum_def.and_then(|enum_def| enum_def.variants.get(*index));
+                match (enum_def, variant) {
+                    (Some(enum_def), Some(variant)) => write!(out, "{}::{}", enum_def.name, variant.name)?,
+                    _ => write!(out, "{{variant}}")?,
+                }
+                if fields.is_empty() {
+                    return Ok(());
+                }
+                write!(out, "(")?;
+                self.write_list(out, fields.iter().map(|field| (None, field)))?;
+                write!(out, ")")
+            }
+            ExprKind::Array(elements) => {
+                write!(out, "[")?;
+                self.write_list(out, elements.iter().map(|element| (None, element)))?;
+                write!(out, "]")
+            }
+            ExprKind::Index { base, index } => {
+                self.write_operand(out, base)?;
+                write!(out, "[")?;
+                self.write_expr(out, index)?;
+                write!(out, "]")
+            }
+            ExprKind::Field { base, name, .. } => {
+                self.write_expr(out, base)?;
+                write!(out, ".{name}")
+            }
+            ExprKind::Ref { mutable, expr } => {
+                write!(out, "&{}", if *mutable { "mut " } else { "" })?;
+                self.write_expr(out, expr)
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.write_operand(out, lhs)?;
+                write!(out, " {} ", op.symbol())?;
+                self.write_operand(out, rhs)
+            }
+            ExprKind::Unary { op, operand } => {
+                write!(out, "{}", op.symbol())?;
+                self.write_operand(out, operand)
+            }
+            ExprKind::Error => write!(out, "{{error}}"),
+        }
+    }
+
+    /// Writes the operand of an operator, in parentheses if it is another operator.
+    fn write_operand(&self, out: &mut String, operand: &Expr) -> Result {
+        if matches!(operand.kind, ExprKind::Binary { .. } | ExprKind::Unary { .. }) {
+            write!(out, "(")?;
+            self.write_expr(out, operand)?;
+            write!(out, ")")
+        } else {
+            self.write_expr(out, operand)
+        }
+    }
+
+    /// Writes `values` separated by commas, each one after its name if it has one.
+    fn write_list<'e>(&self, out: &mut String, values: impl Iterator<Item = (Option<&'e str>, &'e Expr)>) -> Result {
+        for (index, (name, value)) in values.enumerate() {
+            if index > 0 {
+                write!(out, ", ")?;
+            }
+            if let Some(name) = name {
+                write!(out, "{name} = ")?;
+            }
+            self.write_expr(out, value)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_hir::lower::lower_gem;
+use topaz_hir::{ExprKind, HirGem, StmtKind};
+use topaz_parser_next::Parse;
+use topaz_report::Reporter;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::ty::{IntTy, Ty};
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+const MAIN: &str = r#"import gem::greet::greet;
+
+typealias Meters = long;
+
+struct Point { x: Meters, y: Meters }
+
+func scale(point: Point, by: Meters = 2, offset: Meters = by) -> Point {
+    return Point(y = point.y, x = point.x);
+}
+
+func main(ready: bool) {
+    let origin = Point(x = 0, y = 0);
+    let mut far = scale(origin, offset = 3);
+    far = scale(point = origin);
+    let wide: &Meters = &far.x;
+    greet("Ruby");
+    if ready {
+        greet(times = 2, name = "Topaz");
+    } else if false {
+        while ready {}
+    } else {}
+}
+"#;
+
+const GREET: &str = r#"public func greet(name: str, times: int = 1) {}
+"#;
+
+const OPERATORS: &str = r#"func operators(a: int, b: int, yes: bool) -> bool {
+    let sum = a + b * 2 - (a - b);
+    let small = -128byte;
+    let quotient = -(a % b) / 3;
+    return !yes == (sum <= quotient);
+}
+"#;
+
+/// The HIR of the gem made of the module `main`, and of a module `greet` with a function of the same name.
+fn lower(main: &str) -> HirGem {
+    let main = TopazFile::parse(main).expect("failed to parse");
+    let greet = TopazFile::parse(GREET).expect("failed to parse");
+    let modules = [
+        SourceModule { file_id: 0, path: vec![], file: &main },
+        SourceModule { file_id: 1, path: vec![Ident::new("greet")], file: &greet },
+    ];
+    let mut cx = ResolveContext::new("test", true);
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let errors: Vec<_> = session
+        .output
+        .iter()
+        .filter(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)))
+        .map(|(file_id, unit)| unit.build_diagnostic(*file_id).message)
+        .collect();
+    assert!(errors.is_empty(), "{errors:?}");
+    lower_gem(&session)
+}
+
+#[test]
+fn test_desugar() {
+    // named arguments are reordered, defaults filled in from the callee, even when in another file
+    assert_eq!(
+        lower(MAIN).to_string(),
+        r#"struct Point { x: long, y: long }
+
+func scale(point: Point, by: long, offset: long) -> Point {
+    return Point(x = point.x, y = point.y);
+}
+
+func main(ready: bool) {
+    let origin: Point = Point(x = 0long, y = 0long);
+    let mut far: Point = scale(origin, 2long, 3long);
+    far = scale(origin, 2long, 2long);
+    let wide: &long = &far.x;
+    greet("Ruby", 1int);
+    if ready {
+        greet("Topaz", 2int);
+    } else {
+        if false {
+            while ready {}
+        } else {}
+    }
+}
+
+func greet(name: str, times: int) {}
+"#
+    );
+}
+
+#[test]
+fn test_resolved() {
+    let gem = lower(MAIN);
+    let main = gem.functions.iter().find(|func| func.name == "main").expect("no `main`");
+    let scale = gem.functions.iter().find(|func| func.name == "scale").and_then(|func| func.def);
+
+    let StmtKind::Let { def: far, init: Some(init), .. } = &main.body.stmts[1].kind else { panic!("not a `let`") };
+    let ExprKind::Call { callee: topaz_hir::Callee::Fn { def, .. }, args } = &init.kind else { panic!("not a call") };
+    assert_eq!(Some(*def), scale);
+    assert!(matches!(args[0].kind, ExprKind::Local { ref name, .. } if name == "origin"));
+
+    let StmtKind::Assign { local, value, .. } = &main.body.stmts[2].kind else { panic!("not an assignment") };
+    assert_eq!(local, far);
+    assert_eq!(value.ty, Ty::Struct { def: gem.structs[0].def, name: "Point".to_owned() });
+
+    let StmtKind::Let { init: Some(wide), .. } = &main.body.stmts[3].kind else { panic!("not a `let`") };
+    let ExprKind::Ref { expr, .. } = &wide.kind else { panic!("not a borrow") };
+    assert!(matches!(expr.kind, ExprKind::Field { index: 0, .. }));
+    assert_eq!(expr.ty, Ty::Int(IntTy::Long));
+}
+
+#[test]
+fn test_operators() {
+    // operators bind as tightly as usual, and a negated literal is a literal
+    assert_eq!(
+        lower(OPERATORS).to_string(),
+        r#"func operators(a: int, b: int, yes: bool) -> bool {
+    let sum: int = (a + (b * 2int)) - (a - b);
+    let small: byte = -128byte;
+    let quotient: int = (-(a % b)) / 3int;
+    return (!yes) == (sum <= quotient);
+}
+
+func greet(name: str, times: int) {}
+"#
+    );
+}
+
+const ENUMS: &str = r#"enum Shape {
+    Circle(int),
+    Rect(int, int),
+    Empty
+}
+
+func area(shape: Shape) -> int {
+    match shape {
+        Shape::Circle(radius) => {
+            return 3 * radius * radius;
+        }
+        _ => {
+            return 0;
+        }
+    }
+}
+
+func main() {
+    let circle = Shape::Circle(2);
+    let empty = Shape::Empty;
+    let sizes = [area(circle), area(empty)];
+    let first = sizes[0];
+}
+"#;
+
+#[test]
+fn test_enums_and_arrays() {
+    // a `_` arm has no variant, and naming a variant without fields builds it
+    assert_eq!(
+        lower(ENUMS).to_string(),
+        r#"enum Shape { Circle(int), Rect(int, int), Empty }
+
+func area(shape: Shape) -> int {
+    match shape {
+        Shape::Circle(radius) => {
+            return (3int * radius) * radius;
+        }
+        _ => {
+            return 0int;
+        }
+    }
+}
+
+func main() {
+    let circle: Shape = Shape::Circle(2int);
+    let empty: Shape = Shape::Empty;
+    let sizes: [int; 2] = [area(circle), area(empty)];
+    let first: int = sizes[0int];
+}
+
+func greet(name: str, times: int) {}
+"#
+    );
+}
+
+#[test]
+fn test_match_bindings() {
+    let gem = lower(ENUMS);
+    let shape = &gem.enums[0];
+    assert_eq!(shape.variants[1].fields, [Ty::Int(IntTy::Int), Ty::Int(IntTy::Int)]);
+    assert!(!shape.copy);
+
+    let area = gem.functions.iter().find(|func| func.name == "area").expect("no `area`");
+    let StmtKind::Match { scrutinee, arms } = &area.body.stmts[0].kind else { panic!("not a `match`") };
+    assert_eq!(scrutinee.ty, Ty::Enum { def: shape.def, name: "Shape".to_owned() });
+    assert_eq!(arms.iter().map(|arm| arm.variant).collect::<Vec<_>>(), [Some(0), None]);
+    let [radius] = &arms[0].bindings[..] else { panic!("expected one binding") };
+    assert_eq!((radius.name.as_str(), &radius.ty), ("radius", &Ty::Int(IntTy::Int)));
+
+    let main = gem.functions.iter().find(|func| func.name == "main").expect("no `main`");
+    let StmtKind::Let { init: Some(empty), .. } = &main.body.stmts[1].kind else { panic!("not a `let`") };
+    assert!(matches!(empty.kind, ExprKind::Variant { index: 2, ref fields, .. } if fields.is_empty()));
+    let StmtKind::Let { init: Some(first), .. } = &main.body.stmts[3].kind else { panic!("not a `let`") };
+    assert!(matches!(first.kind, ExprKind::Index { .. }));
+    assert_eq!(first.ty, Ty::Int(IntTy::Int));
+}
+
+const METHODS: &str = r#"struct Counter { count: int }
+
+trait Count {
+    func first(this) -> int {
+        return 1;
+    }
+
+    func next(this) -> int;
+}
+
+impl Count for Counter {
+    func next(this) -> int {
+        return 2;
+    }
+}
+
+func main() {
+    let counter = Counter(count = 1);
+}
+"#;
+
+#[test]
+fn test_methods() {
+    // the methods of impls and the provided methods of traits are functions of their own
+    let gem = lower(METHODS);
+    let names: Vec<_> = gem.functions.iter().map(|func| func.name.as_str()).collect();
+    assert_eq!(names, ["first", "next", "first", "main", "greet"]);
+    assert!(gem.functions[..3].iter().all(|method| method.def.is_none()));
+
+    // in an impl, `this` is of the type the trait is implemented for
+    let counter = Ty::Struct { def: gem.structs[0].def, name: "Counter".to_owned() };
+    assert_eq!(gem.functions[1].params[0].name, "this");
+    assert_eq!(gem.functions[1].params[0].ty, counter);
+
+    // and so it is in the copy of a default body the impl takes from its trait
+    assert!(gem.functions[0].provided && gem.functions[0].impl_id.is_none());
+    assert_eq!(gem.functions[0].params[0].ty, Ty::Param("this".to_owned()));
+    assert!(gem.functions[2].provided && gem.functions[2].impl_id == gem.functions[1].impl_id);
+    assert_eq!(gem.functions[2].params[0].ty, counter);
+}
+
+const SUGAR: &str = r#"func double(value: long) -> long = value * 2;
+
+func shout(name: str) = greet("$name!", times = 2);
+
+func main() {
+    let count = double(21);
+    let label = "$count for $5";
+}
+"#;
+
+#[test]
+fn test_interpolation_and_expression_bodies() {
+    // an expression body is returned, unless the function returns `()`, and the parts of an interpolated string
+    // are formatted by a call
+    let gem = lower(SUGAR);
+    assert_eq!(
+        gem.to_string(),
+        r#"func double(value: long) -> long {
+    return value * 2long;
+}
+
+func shout(name: str) {
+    greet(std::fmt::format(name, "!"), 2int);
+}
+
+func main() {
+    let count: long = double(21long);
+    let label: str = std::fmt::format(count, " for $5");
+}
+
+func greet(name: str, times: int) {}
+"#
+    );
+
+    let main = gem.functions.iter().find(|func| func.name == "main").expect("no `main`");
+    let StmtKind::Let { def: count, .. } = &main.body.stmts[0].kind else { panic!("not a `let`") };
+    let StmtKind::Let { init: Some(label), .. } = &main.body.stmts[1].kind else { panic!("not a `let`") };
+    let ExprKind::Call { args, .. } = &label.kind else { panic!("not a call") };
+    assert!(matches!(args[0].kind, ExprKind::Local { def, .. } if def == *count));
+    assert_eq!(args[0].ty, Ty::Int(IntTy::Long));
+}
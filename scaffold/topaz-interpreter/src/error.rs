@@ -0,0 +1,80 @@
+//! `error.rs` - the panics stopping a program, reported where they happened and with the calls that led there.
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use topaz_ast::location::Span;
+use topaz_report::Reporter;
+
+/// What went wrong when a program panicked.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum PanicKind {
+    #[error("attempt to store `{value}` in a `{ty}`, which overflows")]
+    Overflow { value: i128, ty: &'static str },
+    #[error("attempt to compute `{expr}`, which overflows `{ty}`")]
+    ArithmeticOverflow { expr: String, ty: &'static str },
+    #[error("attempt to compute `{0}`, which divides by zero")]
+    DivisionByZero(String),
+    #[error("index {index} is out of bounds of a value with {len} fields")]
+    OutOfBounds { index: usize, len: usize },
+    #[error("index out of bounds: the length is {len} but the index is {index}")]
+    IndexOutOfBounds { index: String, len: usize },
+    #[error("stack overflow, more than {depth} calls deep")]
+    StackOverflow { depth: usize },
+    #[error("entered unreachable code")]
+    Unreachable,
+    #[error("the value of the constant `{0}` is not known")]
+    UnknownConstant(String),
+    #[error("cannot call `{0}`, the interpreter does not know it")]
+    UnknownFunction(String),
+    #[error("`{function}` takes {expected} arguments, but {found} were passed")]
+    ArgumentCount { function: String, expected: usize, found: usize },
+    #[error("expected {expected}, found `{found}`")]
+    TypeMismatch { expected: &'static str, found: String },
+    #[error("read `{0}` before it was assigned")]
+    Uninitialized(String),
+    #[error("read through a reference into a call that already returned")]
+    DanglingReference,
+    #[error("failed to write the output: {0}")]
+    Output(String),
+}
+
+/// A call leading to a panic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallSite {
+    /// The function making the call
+    pub caller: String,
+    pub file_id: usize,
+    pub span: Span,
+}
+
+/// A program panicked.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("`{function}` panicked: {kind}")]
+pub struct Panic {
+    pub kind: PanicKind,
+    /// The function that panicked
+    pub function: String,
+    /// The file the panic happened in, for [`Reporter::emit_diagnostic`]
+    pub file_id: usize,
+    pub span: Span,
+    /// The calls that led to the function that panicked, innermost first
+    pub calls: Vec<CallSite>,
+}
+
+impl Reporter<'_> for Panic {
+    fn build_diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+        let mut labels = vec![Label::primary(file_id, self.span).with_message(self.kind.to_string())];
+        // a recursive function calls from the same place over and over, which is labelled once
+        let mut calls = self.calls.iter().peekable();
+        while let Some(call) = calls.next() {
+            let mut times = 1;
+            while calls.next_if_eq(&call).is_some() {
+                times += 1;
+            }
+            let message = match times {
+                1 => format!("called from `{}`", call.caller),
+                _ => format!("called from `{}`, {times} times in a row", call.caller),
+            };
+            labels.push(Label::secondary(call.file_id, call.span).with_message(message));
+        }
+        Diagnostic::error().with_message(format!("`{}` panicked", self.function)).with_labels(labels)
+    }
+}
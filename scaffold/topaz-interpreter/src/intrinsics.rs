@@ -0,0 +1,29 @@
+//! `intrinsics.rs` - the functions of the standard library the interpreter runs itself.
+//!
+//! The standard library only declares them, e.g. `println` in `tstd/src/io.tp`, so a call to one is a call
+//! to a function outside of the gem, by the path it resolved to.
+use crate::error::PanicKind;
+use crate::value::Value;
+use crate::Interpreter;
+use std::rc::Rc;
+use topaz_hir::FORMAT;
+
+/// Calls the function of the standard library at `path` with `args`.
+pub fn call(interpreter: &mut Interpreter, path: &str, args: &[Value]) -> Result<Value, PanicKind> {
+    match path {
+        "std::io::println" => {
+            let [text] = args else {
+                return Err(PanicKind::ArgumentCount { function: path.to_owned(), expected: 1, found: args.len() });
+            };
+            let mut line = interpreter.display(text)?;
+            line.push('\n');
+            interpreter.out.write_all(line.as_bytes()).map_err(|error| PanicKind::Output(error.to_string()))?;
+            Ok(Value::Void)
+        }
+        FORMAT => {
+            let parts = args.iter().map(|arg| interpreter.display(arg)).collect::<Result<String, _>>()?;
+            Ok(Value::Str(Rc::from(parts)))
+        }
+        _ => Err(PanicKind::UnknownFunction(path.to_owned())),
+    }
+}
@@ -0,0 +1,432 @@
+//! Runs a gem without compiling it to machine code, by interpreting its MIR.
+//!
+//! Every call gets a frame holding the values of the locals of the function called, and the frames
+//! are kept on a stack of their own rather than on the one of the interpreter, so that deep recursion in
+//! the program stops with a [`PanicKind::StackOverflow`] instead of crashing the compiler.
+//! What goes wrong while running stops the program with a [`Panic`], reported where it happened:
+//!
+//! ```text
+//! error: `main` panicked
+//!   ┌─ main.tp:3:13
+//!   │
+//! 3 │     println(numbers[3]);
+//!   │             ^^^^^^^^^^ index out of bounds: the length is 3 but the index is 3
+//! ```
+//!
+//! The functions of the standard library are run by the interpreter itself, see [`intrinsics`].
+use std::io::Write;
+use std::rc::Rc;
+use topaz_ast::location::Span;
+use topaz_hir::Callee;
+use topaz_mir::{
+    fold, BinOp, BlockId, Body, ConstValue, Constant, MirGem, Operand, Place, Rvalue, StatementKind, TerminatorKind, UnOp,
+};
+use topaz_type_checker::ty::{IntTy, Ty};
+
+pub mod error;
+pub mod intrinsics;
+pub mod value;
+
+pub use error::{CallSite, Panic, PanicKind};
+use value::{fits, FrameId, Pointer, Value};
+
+/// How many calls can be running at once before the program is stopped by a stack overflow.
+pub const MAX_DEPTH: usize = 10_000;
+
+/// A call that is running.
+struct Frame<'g> {
+    id: FrameId,
+    body: &'g Body,
+    /// The value of every local of the body, `None` until it is assigned
+    locals: Vec<Option<Value>>,
+    block: BlockId,
+    /// The index of the next statement to run in `block`, the terminator once it is past the statements
+    statement: usize,
+}
+
+pub struct Interpreter<'g, 'o> {
+    gem: &'g MirGem,
+    /// Where `println` and the like write to
+    out: &'o mut dyn Write,
+    frames: Vec<Frame<'g>>,
+    next_frame: usize,
+}
+
+impl<'g, 'o> Interpreter<'g, 'o> {
+    #[must_use]
+    pub fn new(gem: &'g MirGem, out: &'o mut dyn Write) -> Self {
+        Self { gem, out, frames: Vec::new(), next_frame: 0 }
+    }
+
+    /// Runs `entry`, a function of the gem taking no arguments, returning what it returns.
+    ///
+    /// # Errors
+    /// If the program panics, with where it did.
+    pub fn run(&mut self, entry: &'g Body) -> Result<Value, Panic> {
+        self.frames.clear();
+        if let Err(kind) = self.enter(entry, Vec::new()) {
+            return Err(Panic {
+                kind,
+                function: entry.name.clone(),
+                file_id: entry.file_id,
+                span: entry.span,
+                calls: Vec::new(),
+            });
+        }
+        loop {
+            if let Some(value) = self.step()? {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Starts a call of `body` with `args`.
+    fn enter(&mut self, body: &'g Body, args: Vec<Value>) -> Result<(), PanicKind> {
+        if args.len() != body.arg_count {
+            return Err(PanicKind::ArgumentCount {
+                function: body.name.clone(),
+                expected: body.arg_count,
+                found: args.len(),
+            });
+        }
+        if self.frames.len() >= MAX_DEPTH {
+            return Err(PanicKind::StackOverflow { depth: MAX_DEPTH });
+        }
+        let mut locals = vec![None; body.locals.len()];
+        for (local, arg) in body.args().zip(args) {
+            locals[local.index()] = Some(arg);
+        }
+        let id = FrameId(self.next_frame);
+        self.next_frame += 1;
+        self.frames.push(Frame { id, body, locals, block: BlockId::START, statement: 0 });
+        Ok(())
+    }
+
+    fn frame(&self) -> &Frame<'g> {
+        self.frames.last().expect("there is a frame while running")
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame<'g> {
+        self.frames.last_mut().expect("there is a frame while running")
+    }
+
+    /// Runs the next statement or terminator, returning what the entry point returns once it does.
+    fn step(&mut self) -> Result<Option<Value>, Panic> {
+        let Frame { body, block, statement, .. } = *self.frame();
+        let data = body.block(block);
+        if let Some(statement) = data.statements.get(statement) {
+            self.frame_mut().statement += 1;
+            return match &statement.kind {
+                StatementKind::Assign(place, rvalue) => {
+                    self.eval_rvalue(rvalue).and_then(|value| self.store(place, value))
+                }
+                StatementKind::StorageDead(_) | StatementKind::Nop => Ok(()),
+            }
+            .map(|()| None)
+            .map_err(|kind| self.panic(kind, statement.span));
+        }
+
+        let span = data.terminator.span;
+        self.terminate(&data.terminator.kind).map_err(|kind| self.panic(kind, span))
+    }
+
+    fn terminate(&mut self, terminator: &'g TerminatorKind) -> Result<Option<Value>, PanicKind> {
+        match terminator {
+            TerminatorKind::Goto(target) => self.jump(*target)?,
+            TerminatorKind::If { cond, then, otherwise } => match self.eval(cond)? {
+                Value::Bool(cond) => self.jump(if cond { *then } else { *otherwise })?,
+                value => return Err(PanicKind::TypeMismatch { expected: "a `bool`", found: self.display(&value)? }),
+            },
+            TerminatorKind::Call { callee, args, dest, target, .. } => {
+                let args = args.iter().map(|arg| self.eval(arg)).collect::<Result<Vec<_>, _>>()?;
+                match callee {
+                    Callee::Fn { name, .. } | Callee::Method { name, .. } => {
+                        let body = self.gem.callee(callee).ok_or_else(|| PanicKind::UnknownFunction(name.clone()))?;
+                        self.enter(body, args)?;
+                    }
+                    Callee::External(path) => {
+                        let value = intrinsics::call(self, path, &args)?;
+                        self.store(dest, value)?;
+                        self.jump(*target)?;
+                    }
+                }
+            }
+            TerminatorKind::Return => {
+                let mut frame = self.frames.pop().expect("there is a frame while running");
+                let value = frame.locals[0].take().unwrap_or(Value::Void);
+                let Some(caller) = self.frames.last() else { return Ok(Some(value)) };
+                let TerminatorKind::Call { dest, target, .. } = &caller.body.block(caller.block).terminator.kind else {
+                    unreachable!("a caller is always at a call")
+                };
+                self.store(dest, value)?;
+                self.jump(*target)?;
+            }
+            TerminatorKind::Switch { discr, targets } => match self.eval(discr)? {
+                Value::Int(discr, _) => {
+                    let target = usize::try_from(discr).ok().and_then(|discr| targets.get(discr));
+                    self.jump(*target.ok_or(PanicKind::Unreachable)?)?;
+                }
+                value => return Err(PanicKind::TypeMismatch { expected: "a `usize`", found: self.display(&value)? }),
+            },
+            TerminatorKind::Unreachable => return Err(PanicKind::Unreachable),
+        }
+        Ok(None)
+    }
+
+    /// Goes on at the start of `target`, running its phis for the block it comes from.
+    fn jump(&mut self, target: BlockId) -> Result<(), PanicKind> {
+        let Frame { body, block: from, .. } = *self.frame();
+        let phis = &body.block(target).phis;
+        // the phis run at once, so each one reads the values from before any of them
+        let mut values = Vec::with_capacity(phis.len());
+        for phi in phis {
+            let (_, operand) = phi
+                .args
+                .iter()
+                .find(|(block, _)| *block == from)
+                .expect("a validated phi has an operand for every predecessor");
+            values.push((phi.dest, self.eval(operand)?));
+        }
+
+        let frame = self.frame_mut();
+        for (dest, value) in values {
+            frame.locals[dest.index()] = Some(value);
+        }
+        frame.block = target;
+        frame.statement = 0;
+        Ok(())
+    }
+
+    fn eval_rvalue(&self, rvalue: &Rvalue) -> Result<Value, PanicKind> {
+        match rvalue {
+            Rvalue::Use(operand) => self.eval(operand),
+            Rvalue::Ref { place, .. } => self.locate(place).map(Value::Ref),
+            Rvalue::Aggregate { def, operands } => {
+                let fields = operands.iter().map(|operand| self.eval(operand)).collect::<Result<_, _>>()?;
+                Ok(Value::Struct { def: *def, fields })
+            }
+            Rvalue::Variant { def, index, operands } => {
+                let fields = operands.iter().map(|operand| self.eval(operand)).collect::<Result<_, _>>()?;
+                Ok(Value::Enum { def: *def, variant: *index, fields })
+            }
+            Rvalue::Discriminant(operand) => match self.eval(operand)? {
+                Value::Enum { variant, .. } => {
+                    Ok(Value::Int(i128::try_from(variant).expect("fewer variants than `i128::MAX`"), IntTy::Usize))
+                }
+                value => Err(PanicKind::TypeMismatch { expected: "an enum", found: self.display(&value)? }),
+            },
+            Rvalue::Payload { operand, variant, field } => match self.eval(operand)? {
+                Value::Enum { variant: found, mut fields, .. } if found == *variant && *field < fields.len() => {
+                    Ok(fields.swap_remove(*field))
+                }
+                // the arm reading the field only runs for its variant
+                Value::Enum { .. } => Err(PanicKind::Unreachable),
+                value => Err(PanicKind::TypeMismatch { expected: "an enum", found: self.display(&value)? }),
+            },
+            Rvalue::Array(operands) => {
+                Ok(Value::Array(operands.iter().map(|operand| self.eval(operand)).collect::<Result<_, _>>()?))
+            }
+            Rvalue::Index { operands: [base, index] } => match (self.eval(base)?, self.eval(index)?) {
+                (Value::Array(mut elements), Value::Int(index, ty)) => {
+                    let len = elements.len();
+                    match usize::try_from(index).ok().filter(|index| *index < len) {
+                        Some(index) => Ok(elements.swap_remove(index)),
+                        None => Err(PanicKind::IndexOutOfBounds { index: display_int(index, ty), len }),
+                    }
+                }
+                (Value::Array(_), value) | (value, _) => {
+                    Err(PanicKind::TypeMismatch { expected: "an array and an integer", found: self.display(&value)? })
+                }
+            },
+            Rvalue::BinaryOp { op, operands: [lhs, rhs] } => match (self.eval(lhs)?, self.eval(rhs)?) {
+                (Value::Int(lhs, ty), Value::Int(rhs, _)) if op.is_comparison() => {
+                    Ok(Value::Bool(if ty == IntTy::Uexplod {
+                        #[allow(clippy::cast_sign_loss)]
+                        fold::compare(*op, &(lhs as u128), &(rhs as u128))
+                    } else {
+                        fold::compare(*op, &lhs, &rhs)
+                    }))
+                }
+                (Value::Int(lhs, ty), Value::Int(rhs, _)) => {
+                    let expr = || format!("{} {} {}", display_int(lhs, ty), op.symbol(), display_int(rhs, ty));
+                    if matches!(op, BinOp::Div | BinOp::Rem) && rhs == 0 {
+                        return Err(PanicKind::DivisionByZero(expr()));
+                    }
+                    arithmetic(*op, lhs, rhs, ty)
+                        .map(|value| Value::Int(value, ty))
+                        .ok_or_else(|| PanicKind::ArithmeticOverflow { expr: expr(), ty: ty.name() })
+                }
+                (Value::Bool(lhs), Value::Bool(rhs)) => Ok(Value::Bool(fold::compare(*op, &lhs, &rhs))),
+                (Value::Char(lhs), Value::Char(rhs)) => Ok(Value::Bool(fold::compare(*op, &lhs, &rhs))),
+                (value, _) => Err(PanicKind::TypeMismatch { expected: "an integer", found: self.display(&value)? }),
+            },
+            Rvalue::UnaryOp { op, operand } => match (op, self.eval(operand)?) {
+                // only the smallest value of a type overflows when negated, which is negative
+                (UnOp::Neg, Value::Int(value, ty)) => {
+                    arithmetic(BinOp::Sub, 0, value, ty).map(|negated| Value::Int(negated, ty)).ok_or_else(|| {
+                        PanicKind::ArithmeticOverflow { expr: format!("-({})", display_int(value, ty)), ty: ty.name() }
+                    })
+                }
+                (UnOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+                (UnOp::Neg, value) => {
+                    Err(PanicKind::TypeMismatch { expected: "an integer", found: self.display(&value)? })
+                }
+                (UnOp::Not, value) => {
+                    Err(PanicKind::TypeMismatch { expected: "a `bool`", found: self.display(&value)? })
+                }
+            },
+        }
+    }
+
+    fn eval(&self, operand: &Operand) -> Result<Value, PanicKind> {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.locate(place).and_then(|at| self.read(&at).cloned()),
+            Operand::Const(constant) => constant_value(constant),
+        }
+    }
+
+    /// Where `place` of the running call is, following the references it goes through on the way to its fields.
+    fn locate(&self, place: &Place) -> Result<Pointer, PanicKind> {
+        let mut at = Pointer { frame: self.frame().id, local: place.local, projection: Vec::new() };
+        for field in &place.projection {
+            while let Value::Ref(target) = self.read(&at)? {
+                at = target.clone();
+            }
+            at.projection.push(*field);
+        }
+        Ok(at)
+    }
+
+    fn frame_of(&self, id: FrameId) -> Result<&Frame<'g>, PanicKind> {
+        self.frames.iter().rev().find(|frame| frame.id == id).ok_or(PanicKind::DanglingReference)
+    }
+
+    fn read(&self, at: &Pointer) -> Result<&Value, PanicKind> {
+        let frame = self.frame_of(at.frame)?;
+        let mut value = frame.locals[at.local.index()].as_ref().ok_or_else(|| uninitialized(frame, at))?;
+        for index in &at.projection {
+            value = match value {
+                Value::Struct { fields, .. } => {
+                    fields.get(*index).ok_or(PanicKind::OutOfBounds { index: *index, len: fields.len() })?
+                }
+                _ => return Err(PanicKind::OutOfBounds { index: *index, len: 0 }),
+            };
+        }
+        Ok(value)
+    }
+
+    /// Writes `value` to `place` of the running call.
+    fn store(&mut self, place: &Place, value: Value) -> Result<(), PanicKind> {
+        let at = self.locate(place)?;
+        let frame =
+            self.frames.iter_mut().rev().find(|frame| frame.id == at.frame).ok_or(PanicKind::DanglingReference)?;
+        if at.projection.is_empty() {
+            frame.locals[at.local.index()] = Some(value);
+            return Ok(());
+        }
+        let uninitialized = uninitialized(frame, &at);
+        let mut slot = frame.locals[at.local.index()].as_mut().ok_or(uninitialized)?;
+        for index in &at.projection {
+            slot = match slot {
+                Value::Struct { fields, .. } => {
+                    let len = fields.len();
+                    fields.get_mut(*index).ok_or(PanicKind::OutOfBounds { index: *index, len })?
+                }
+                _ => return Err(PanicKind::OutOfBounds { index: *index, len: 0 }),
+            };
+        }
+        *slot = value;
+        Ok(())
+    }
+
+    /// `value` as `println` prints it, e.g. `Point(x = 1, y = 2)` for a struct, `Circle(2)` for the variant
+    /// of an enum and `[1, 2]` for an array.
+    ///
+    /// # Errors
+    /// If `value` is a reference to a call that returned.
+    pub fn display(&self, value: &Value) -> Result<String, PanicKind> {
+        Ok(match value {
+            Value::Void => "()".to_owned(),
+            Value::Int(value, ty) => display_int(*value, *ty),
+            Value::Bool(value) => value.to_string(),
+            Value::Char(value) => value.to_string(),
+            Value::Str(value) => value.to_string(),
+            Value::Struct { def, fields } => {
+                let struct_def = self.gem.struct_def(*def);
+                let name = struct_def.map_or("_", |struct_def| &struct_def.name);
+                let mut displayed = Vec::with_capacity(fields.len());
+                for (index, field) in fields.iter().enumerate() {
+                    let field_name = struct_def.and_then(|struct_def| struct_def.fields.get(index));
+                    let field_name = field_name.map_or_else(|| index.to_string(), |field| field.name.clone());
+                    displayed.push(format!("{field_name} = {}", self.display(field)?));
+                }
+                format!("{name}({})", displayed.join(", "))
+            }
+            Value::Enum { def, variant, fields } => {
+                let variant = self.gem.enum_def(*def).and_then(|enum_def| enum_def.variants.get(*variant));
+                let name = variant.map_or("_", |variant| &variant.name);
+                if fields.is_empty() {
+                    return Ok(name.to_owned());
+                }
+                let fields = fields.iter().map(|field| self.display(field)).collect::<Result<Vec<_>, _>>()?;
+                format!("{name}({})", fields.join(", "))
+            }
+            Value::Array(elements) => {
+                let elements = elements.iter().map(|element| self.display(element)).collect::<Result<Vec<_>, _>>()?;
+                format!("[{}]", elements.join(", "))
+            }
+            Value::Ref(at) => self.display(self.read(at)?)?,
+        })
+    }
+
+    /// A panic at `span` of the running call, with the calls that led to it.
+    fn panic(&self, kind: PanicKind, span: Span) -> Panic {
+        let frame = self.frame();
+        let calls = self.frames.iter().rev().skip(1).map(|caller| CallSite {
+            caller: caller.body.name.clone(),
+            file_id: caller.body.file_id,
+            span: caller.body.block(caller.block).terminator.span,
+        });
+        Panic { kind, function: frame.body.name.clone(), file_id: frame.body.file_id, span, calls: calls.collect() }
+    }
+}
+
+fn constant_value(constant: &Constant) -> Result<Value, PanicKind> {
+    Ok(match &constant.value {
+        ConstValue::Int(value) => {
+            let ty = match constant.ty.peel() {
+                Ty::Int(ty) => *ty,
+                _ => IntTy::DEFAULT,
+            };
+            if !fits(*value, ty) {
+                return Err(PanicKind::Overflow { value: *value, ty: ty.name() });
+            }
+            Value::Int(*value, ty)
+        }
+        ConstValue::Bool(value) => Value::Bool(*value),
+        ConstValue::Char(value) => Value::Char(*value),
+        ConstValue::Str(value) => Value::Str(Rc::from(value.as_str())),
+        ConstValue::Void => Value::Void,
+        ConstValue::Named(name) => return Err(PanicKind::UnknownConstant(name.clone())),
+    })
+}
+
+/// `value` of type `ty` as it is written, a `uexplod` keeping its bits.
+fn display_int(value: i128, ty: IntTy) -> String {
+    #[allow(clippy::cast_sign_loss)]
+    match ty {
+        IntTy::Uexplod => (value as u128).to_string(),
+        _ => value.to_string(),
+    }
+}
+
+/// `lhs op rhs` for the integer type `ty`, `None` if it overflows the type or divides by zero.
+fn arithmetic(op: BinOp, lhs: i128, rhs: i128, ty: IntTy) -> Option<i128> {
+    fold::arithmetic(op, lhs, rhs, ty, usize::BITS)
+}
+
+/// The local `at` points to, read before it was assigned.
+fn uninitialized(frame: &Frame, at: &Pointer) -> PanicKind {
+    let decl = frame.body.local(at.local);
+    PanicKind::Uninitialized(decl.name.clone().unwrap_or_else(|| format!("_{}", at.local.0)))
+}
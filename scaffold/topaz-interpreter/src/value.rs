@@ -0,0 +1,51 @@
+//! `value.rs` - the values a program works with while it runs.
+use std::rc::Rc;
+use topaz_mir::Local;
+use topaz_resolve::def::DefId;
+use topaz_type_checker::ty::IntTy;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// `()`
+    Void,
+    /// An integer of the given type, which it always fits in. A `uexplod` keeps its bits
+    Int(i128, IntTy),
+    Bool(bool),
+    Char(char),
+    Str(Rc<str>),
+    /// A struct, with a value for every field
+    Struct {
+        def: DefId,
+        fields: Vec<Self>,
+    },
+    /// The variant `variant` of an enum, with a value for every field of the variant
+    Enum {
+        def: DefId,
+        variant: usize,
+        fields: Vec<Self>,
+    },
+    Array(Vec<Self>),
+    /// A reference to a place of a call that is running
+    Ref(Pointer),
+}
+
+/// Where a reference points to: a local of a call, or a field of one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pointer {
+    /// The call the local belongs to, which may have returned since
+    pub frame: FrameId,
+    pub local: Local,
+    /// The indices of the fields, outermost first
+    pub projection: Vec<usize>,
+}
+
+/// A call, unique for the whole run so that a reference can tell whether the call it points into returned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FrameId(pub usize);
+
+/// Whether `value` can be a value of `ty`: whether it is between the smallest and largest value of the type.
+#[must_use]
+pub fn fits(value: i128, ty: IntTy) -> bool {
+    // `usize` and `isize` are as wide as a pointer of the machine running the interpreter
+    topaz_mir::fold::fits(value, ty, usize::BITS)
+}
@@ -0,0 +1,371 @@
+use topaz_ast::file::TopazFile;
+use topaz_hir::lower::lower_gem;
+use topaz_interpreter::{Interpreter, Panic, PanicKind, MAX_DEPTH};
+use topaz_mir::build::build_gem;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::transform::{OptLevel, PassManager};
+use topaz_parser_next::Parse;
+use topaz_report::Reporter;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+/// Runs the `main` function of `source` optimized at `level`, returning what it printed and how it ended.
+fn run(source: &str, level: OptLevel) -> (String, Result<(), Panic>) {
+    let file = TopazFile::parse(source).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("test", true);
+    cx.set_prelude(topaz_session::prelude::std_prelude());
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let errors: Vec<_> = session
+        .output
+        .iter()
+        .filter(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)))
+        .map(|(file_id, unit)| unit.build_diagnostic(*file_id).message)
+        .collect();
+    assert!(errors.is_empty(), "{errors:?}");
+    let mut gem = build_gem(&lower_gem(&session));
+    construct_gem(&mut gem);
+    PassManager::new(level).run(&mut gem).expect("invalid MIR");
+
+    let mut out = Vec::new();
+    let main = gem.entry_point(0).expect("no `main`");
+    let result = Interpreter::new(&gem, &mut out).run(main).map(|_| ());
+    (String::from_utf8(out).expect("printed invalid UTF-8"), result)
+}
+
+#[test]
+fn test_hello_world() {
+    let (out, result) = run(include_str!("../../../examples/hello-world.tp"), OptLevel::O0);
+    assert_eq!(result, Ok(()));
+    assert_eq!(out, "hello world\n");
+}
+
+#[test]
+fn test_control_flow() {
+    let source = r#"struct Point { x: int, y: int }
+
+func pick(point: Point, flag: bool) -> int {
+    if flag {
+        return point.x;
+    }
+    return point.y;
+}
+
+func countdown(move from: Point) -> str {
+    let mut going = true;
+    let mut first = true;
+    let mut steps = from;
+    while going {
+        println(steps);
+        if first {
+            steps = Point(x = steps.x, y = 0);
+            first = false;
+        } else {
+            going = false;
+        }
+    }
+    return "done";
+}
+
+public func main() {
+    let point = Point(x = 7, y = 2);
+    let seen = &point;
+    println(pick(point, true));
+    std::io::println(pick(point, false));
+    println(seen);
+    println(countdown(point));
+}
+"#;
+    let expected = "7\n2\nPoint(x = 7, y = 2)\nPoint(x = 7, y = 2)\nPoint(x = 7, y = 0)\ndone\n";
+    for level in [OptLevel::O0, OptLevel::O3] {
+        assert_eq!(run(source, level), (expected.to_owned(), Ok(())), "at {level:?}");
+    }
+}
+
+#[test]
+fn test_fields_through_references() {
+    let source = r#"struct Point { x: int, y: int }
+struct Line { from: Point, to: Point }
+
+func width(line: &Line) -> int {
+    let from = &line.from;
+    return line.to.x - from.x;
+}
+
+public func main() {
+    let line = Line(from = Point(x = 1, y = 2), to = Point(x = 4, y = 6));
+    let borrowed = &line;
+    let twice = &borrowed;
+    println(width(borrowed));
+    println(twice.to.y);
+}
+"#;
+    for level in [OptLevel::O0, OptLevel::O3] {
+        assert_eq!(run(source, level), ("3\n6\n".to_owned(), Ok(())), "at {level:?}");
+    }
+}
+
+#[test]
+fn test_operators() {
+    let source = r#"func collatz(start: ulong) -> int {
+    let mut value = start;
+    let mut steps = 0;
+    while value != 1 {
+        if value % 2 == 0 {
+            value = value / 2;
+        } else {
+            value = 3 * value + 1;
+        }
+        steps = steps + 1;
+    }
+    return steps;
+}
+
+public func main() {
+    println(collatz(27));
+    println(-7 / 2);
+    println(-7 % 2);
+    println(-(-128byte + 1));
+    println(18446744073709551615ulong > 1);
+    println(!(collatz(1) <= 0));
+    println(true != false);
+}
+"#;
+    let expected = "111\n-3\n-1\n127\ntrue\nfalse\ntrue\n";
+    for level in [OptLevel::O0, OptLevel::O3] {
+        assert_eq!(run(source, level), (expected.to_owned(), Ok(())), "at {level:?}");
+    }
+}
+
+#[test]
+fn test_arithmetic_panics() {
+    let panics = [
+        ("let big: int = 2147483647;\n    println(big + 1);", "2147483647 + 1", "int"),
+        ("let small = 0ubyte;\n    println(small - 1ubyte);", "0 - 1", "ubyte"),
+        ("let min = -128byte;\n    println(-min);", "-(-128)", "byte"),
+        ("let min = -128byte;\n    println(min / -1byte);", "-128 / -1", "byte"),
+    ];
+    for (body, expr, ty) in panics {
+        let source = format!("public func main() {{\n    println(\"before\");\n    {body}\n}}\n");
+        let (out, result) = run(&source, OptLevel::O0);
+        assert_eq!(out, "before\n", "{body}");
+        let panic = result.expect_err("`main` should panic");
+        assert_eq!(panic.kind, PanicKind::ArithmeticOverflow { expr: expr.to_owned(), ty }, "{body}");
+    }
+
+    let source = "public func main() {\n    let zero = 0;\n    println(1 / zero);\n}\n";
+    let (_, result) = run(source, OptLevel::O0);
+    let panic = result.expect_err("`main` should panic");
+    assert_eq!(panic.kind, PanicKind::DivisionByZero("1 / 0".to_owned()));
+}
+
+#[test]
+fn test_enums() {
+    let source = r#"enum Shape {
+    Circle(int),
+    Rect(int, int),
+    Empty
+}
+
+enum Suit { Hearts, Spades }
+
+impl Copy for Suit {}
+
+func area(shape: Shape) -> int {
+    match shape {
+        Shape::Circle(radius) => {
+            return 3 * radius * radius;
+        }
+        Shape::Rect(width, height) => {
+            return width * height;
+        }
+        Shape::Empty => {
+            return 0;
+        }
+    }
+}
+
+public func main() {
+    println(area(Shape::Circle(2)));
+    println(area(Shape::Rect(3, 4)));
+    println(area(Shape::Empty));
+    let shape = Shape::Rect(5, 6);
+    println(shape);
+    match shape {
+        Shape::Empty => {
+            println("nothing");
+        }
+        _ => {
+            println("something");
+        }
+    }
+    let suits = [Suit::Spades, Suit::Hearts];
+    println(suits[1]);
+    println(suits);
+}
+"#;
+    let expected = "12\n12\n0\nRect(5, 6)\nsomething\nHearts\n[Spades, Hearts]\n";
+    for level in [OptLevel::O0, OptLevel::O3] {
+        assert_eq!(run(source, level), (expected.to_owned(), Ok(())), "at {level:?}");
+    }
+}
+
+#[test]
+fn test_trait_methods() {
+    let source = r#"struct Coin { cents: int }
+struct Note { value: int }
+
+trait Money {
+    func cents(this) -> int;
+
+    func worth(this) -> str {
+        let cents = Money::cents(this);
+        return "worth $cents";
+    }
+}
+
+impl Money for Coin {
+    func cents(this) -> int = this.cents;
+}
+
+impl Money for Note {
+    func cents(this) -> int = this.value * 100;
+
+    @override
+    func worth(this) -> str = "a note";
+}
+
+public func main() {
+    let coin = Coin(cents = 25);
+    let note = Note(value = 5);
+    println(Money::cents(coin) + Money::cents(note));
+    println(Money::worth(coin));
+    println(Money::worth(note));
+}
+"#;
+    for level in [OptLevel::O0, OptLevel::O3] {
+        assert_eq!(run(source, level), ("525\nworth 25\na note\n".to_owned(), Ok(())), "at {level:?}");
+    }
+}
+
+#[test]
+fn test_drops() {
+    let source = r#"struct Guard { id: int }
+
+struct Pair { first: Guard, second: Guard }
+
+enum Slot {
+    Full(Guard),
+    Empty
+}
+
+impl Drop for Guard {
+    func drop(move this) {
+        println(this.id);
+    }
+}
+
+func take(move guard: Guard) {
+    println("take");
+}
+
+func look(guard: Guard) {
+    println("look");
+}
+
+func make(id: int) -> Guard {
+    return Guard(id = id);
+}
+
+func early(done: bool) {
+    let guard = Guard(id = 10);
+    if done {
+        return;
+    }
+    println("late");
+}
+
+func conditional(flag: bool) {
+    let guard = Guard(id = 20);
+    if flag {
+        take(guard);
+    }
+    println("end");
+}
+
+func unpack(move slot: Slot) {
+    match slot {
+        Slot::Full(guard) => {
+            println("full");
+        }
+        Slot::Empty => {
+            println("empty");
+        }
+    }
+}
+
+public func main() {
+    let a = Guard(id = 1);
+    {
+        let b = Guard(id = 2);
+    }
+    let mut c = Guard(id = 3);
+    c = Guard(id = 4);
+    early(true);
+    early(false);
+    conditional(true);
+    conditional(false);
+    let pair = Pair(first = Guard(id = 5), second = Guard(id = 6));
+    take(pair.first);
+    look(make(7));
+    unpack(Slot::Full(Guard(id = 8)));
+    unpack(Slot::Empty);
+    println("done");
+}
+"#;
+    let expected = "2\n3\n10\nlate\n10\ntake\n20\nend\nend\n20\ntake\n5\nlook\nfull\n8\nempty\ndone\n7\n6\n4\n1\n";
+    for level in [OptLevel::O0, OptLevel::O3] {
+        assert_eq!(run(source, level), (expected.to_owned(), Ok(())), "at {level:?}");
+    }
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    let source = r#"public func main() {
+    let numbers = [1, 2, 3];
+    let mut index = 0;
+    while index < 4 {
+        println(numbers[index]);
+        index = index + 1;
+    }
+}
+"#;
+    for level in [OptLevel::O0, OptLevel::O3] {
+        let (out, result) = run(source, level);
+        assert_eq!(out, "1\n2\n3\n", "at {level:?}");
+        let panic = result.expect_err("`main` should panic");
+        assert_eq!(panic.kind, PanicKind::IndexOutOfBounds { index: "3".to_owned(), len: 3 }, "at {level:?}");
+        assert_eq!(&source[panic.span.start..panic.span.end], "numbers[index]", "at {level:?}");
+    }
+}
+
+#[test]
+fn test_stack_overflow() {
+    let source = r#"func forever(depth: int) -> int {
+    return forever(depth);
+}
+
+public func main() {
+    println(forever(1));
+}
+"#;
+    let (_, result) = run(source, OptLevel::O0);
+    let panic = result.expect_err("`main` should panic");
+    assert_eq!(panic.kind, PanicKind::StackOverflow { depth: MAX_DEPTH });
+    assert_eq!(panic.function, "forever");
+    assert_eq!(panic.calls.len(), MAX_DEPTH - 1);
+    assert_eq!(panic.calls.last().map(|call| call.caller.as_str()), Some("main"));
+}
@@ -13,6 +13,11 @@ pub(crate) fn lowercase_impl(input: TokenStream) -> syn::Result<TokenStream> {
     .into_token_stream())
 }
 
+pub(crate) fn lowercase_str_impl(input: TokenStream) -> syn::Result<TokenStream> {
+    let ident = Parser::parse2(Ident::parse, input)?;
+    Ok(Literal::string(&ident.to_string().to_lowercase()).into_token_stream())
+}
+
 pub(crate) fn charify(input: TokenStream) -> TokenStream {
     Literal::string(&input.to_string()).into_token_stream()
 }
@@ -27,6 +27,12 @@ pub fn lowercase_ident(input: TokenStream) -> TokenStream {
     ident_manipulation::lowercase_impl(input.into()).into_into()
 }
 
+/// Like [`lowercase_ident!`], but produces a string literal.
+#[proc_macro]
+pub fn lowercase_str(input: TokenStream) -> TokenStream {
+    ident_manipulation::lowercase_str_impl(input.into()).into_into()
+}
+
 #[proc_macro]
 pub fn charify(input: TokenStream) -> TokenStream {
     ident_manipulation::charify(input.into()).into()
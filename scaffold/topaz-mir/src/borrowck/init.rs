@@ -0,0 +1,152 @@
+//! `borrowck/init.rs` - definite initialization.
+//!
+//! A binding declared without a value (`let x: int;`) may only be read once it is assigned on
+//! every path leading to the read, e.g. in both branches of an `if` or in every arm of a `match`.
+//! A binding that is not `mut` may be assigned later this way, but only once on every path:
+//! assigning it again, e.g. in a loop, is an error.
+use super::uses;
+use crate::dataflow::{Analysis, BitSet, Results};
+use crate::{Body, Local, Location, MirGem, Statement, StatementKind, Terminator, TerminatorKind};
+use std::collections::HashSet;
+use topaz_ast::location::Span;
+use topaz_type_checker::error::StaticAnalysisError;
+
+/// An assignment to a binding, or a parameter, which is assigned by the caller.
+struct AssignSite {
+    local: Local,
+    location: Option<Location>,
+    span: Span,
+}
+
+fn collect_sites(body: &Body) -> Vec<AssignSite> {
+    let mut sites: Vec<_> =
+        body.args().map(|local| AssignSite { local, location: None, span: body.local(local).span }).collect();
+    let mut push = |local: Option<Local>, location, span| {
+        if let Some(local) = local.filter(|&local| body.local(local).name.is_some()) {
+            sites.push(AssignSite { local, location: Some(location), span });
+        }
+    };
+    for block in body.block_ids() {
+        let data = body.block(block);
+        for (i, statement) in data.statements.iter().enumerate() {
+            if let StatementKind::Assign(place, _) = &statement.kind {
+                push(place.as_local(), Location { block, statement: i }, statement.span);
+            }
+        }
+        if let TerminatorKind::Call { dest, .. } = &data.terminator.kind {
+            push(dest.as_local(), Location { block, statement: data.statements.len() }, data.terminator.span);
+        }
+    }
+    sites
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InitState {
+    /// The assignments that may have happened
+    assigned: BitSet,
+    /// The locals that may not be assigned yet
+    unassigned: BitSet,
+}
+
+struct Init<'a> {
+    sites: &'a [AssignSite],
+}
+
+impl Init<'_> {
+    fn assign(&self, state: &mut InitState, local: Option<Local>, location: Location) {
+        let Some(local) = local else { return };
+        state.unassigned.remove(local.index());
+        for (i, site) in self.sites.iter().enumerate() {
+            if site.location == Some(location) {
+                state.assigned.insert(i);
+            }
+        }
+    }
+}
+
+impl Analysis for Init<'_> {
+    type Domain = InitState;
+
+    fn bottom(&self, body: &Body) -> InitState {
+        InitState { assigned: BitSet::new(self.sites.len()), unassigned: BitSet::new(body.locals.len()) }
+    }
+
+    fn initialize(&self, body: &Body, state: &mut InitState) {
+        for local in body.arg_count + 1..body.locals.len() {
+            state.unassigned.insert(local);
+        }
+        for (i, site) in self.sites.iter().enumerate() {
+            if site.location.is_none() {
+                state.assigned.insert(i);
+            }
+        }
+    }
+
+    fn join(&self, state: &mut InitState, other: &InitState) -> bool {
+        let assigned = state.assigned.union(&other.assigned);
+        let unassigned = state.unassigned.union(&other.unassigned);
+        assigned || unassigned
+    }
+
+    fn statement_effect(&self, state: &mut InitState, statement: &Statement, location: Location) {
+        match &statement.kind {
+            StatementKind::Assign(place, _) => self.assign(state, place.as_local(), location),
+            // a binding declared in a loop is a new one in every iteration
+            StatementKind::StorageDead(local) => {
+                state.unassigned.insert(local.index());
+                for (i, site) in self.sites.iter().enumerate() {
+                    if site.local == *local {
+                        state.assigned.remove(i);
+                    }
+                }
+            }
+            StatementKind::Nop => {}
+        }
+    }
+
+    fn terminator_effect(&self, state: &mut InitState, terminator: &Terminator, location: Location) {
+        if let TerminatorKind::Call { dest, .. } = &terminator.kind {
+            self.assign(state, dest.as_local(), location);
+        }
+    }
+}
+
+/// Reports every read of a binding that may not be assigned yet,
+/// and every assignment to a binding that is not `mut` and may be assigned already.
+pub fn check(gem: &MirGem, body: &Body) -> Vec<StaticAnalysisError> {
+    let sites = collect_sites(body);
+    let states = Results::compute(Init { sites: &sites }, body).all_states(body);
+    let assigned = |state: &InitState, local: Local| {
+        state.assigned.iter().find(|&site| sites[site].local == local).map(|site| &sites[site])
+    };
+    let mut errors = Vec::new();
+    // every binding is reported once, at its first read
+    let mut reported = HashSet::new();
+    for used in uses(gem, body) {
+        let state = &states[used.location.block.0][used.location.statement];
+        let decl = body.local(used.place.local);
+        let Some(name) = &decl.name else { continue };
+        if state.unassigned.contains(used.place.local.index()) && reported.insert(used.place.local) {
+            errors.push(StaticAnalysisError::UninitializedUse {
+                name: name.clone(),
+                possibly: assigned(state, used.place.local).is_some(),
+                decl: decl.span,
+                span: used.span,
+            });
+        }
+    }
+    for site in sites.iter().filter(|site| !body.local(site.local).mutable) {
+        let Some(location) = site.location else { continue };
+        let decl = body.local(site.local);
+        let Some(name) = &decl.name else { continue };
+        if let Some(first) = assigned(&states[location.block.0][location.statement], site.local) {
+            errors.push(StaticAnalysisError::AssignTwice {
+                name: name.clone(),
+                decl: decl.span,
+                first: (first.span != site.span).then_some(first.span),
+                span: site.span,
+            });
+        }
+    }
+    errors
+}
@@ -0,0 +1,462 @@
+//! `borrowck/mod.rs` - the borrow checker, on the bodies as built from the HIR, before their drops are elaborated.
+//!
+//! Every `&x` and `&mut x` of a function body is a loan of `x`, and so is passing `x` to a parameter that is not
+//! `move`, which borrows it. A loan is live as long as some local that may hold the reference
+//! (or anything derived from it) is still going to be read, and while it is live, `x` may not be used in a way
+//! that conflicts with it:
+//! - nothing else may borrow `x` while it is borrowed mutably, and it may not be read
+//! - `x` may not be borrowed mutably, assigned to or moved out of while it is borrowed at all
+//! - `x` may not go out of scope, and a reference to it may not be returned
+//!
+//! Borrowing a field of `x` only conflicts with uses of `x` itself and of that field.
+//! Uses of moved values and of bindings that are not assigned yet are checked separately,
+//! see [`moves`] and [`init`].
+use crate::build::build_body;
+use crate::dataflow::{Analysis, BitSet, Liveness, Results};
+use crate::drops::moved_by_statement;
+use crate::{
+    Body, Local, Location, MirGem, Operand, Place, Rvalue, Statement, StatementKind, Terminator, TerminatorKind,
+};
+use std::collections::{HashSet, VecDeque};
+use topaz_ast::location::Span;
+use topaz_hir::lower::lower_gem;
+use topaz_type_checker::error::StaticAnalysisError;
+use topaz_type_checker::pass::{AnalysisPass, AnalysisSession};
+
+pub mod init;
+pub mod moves;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LoanId(pub usize);
+
+/// A `&place` or `&mut place` of the body, or an argument passed to a parameter that borrows it.
+#[derive(Debug, Clone)]
+pub struct Loan {
+    pub place: Place,
+    pub mutable: bool,
+    pub location: Location,
+    pub span: Span,
+    /// The index of the argument, for a loan taken by a call
+    pub arg: Option<usize>,
+}
+
+/// Every loan of `body`.
+fn collect_loans(gem: &MirGem, body: &Body) -> Vec<Loan> {
+    let mut loans = Vec::new();
+    for block in body.block_ids() {
+        let data = body.block(block);
+        for (i, statement) in data.statements.iter().enumerate() {
+            if let StatementKind::Assign(_, Rvalue::Ref { mutable, place }) = &statement.kind {
+                let location = Location { block, statement: i };
+                loans.push(Loan { place: place.clone(), mutable: *mutable, location, span: statement.span, arg: None });
+            }
+        }
+        let TerminatorKind::Call { args, arg_spans, .. } = &data.terminator.kind else { continue };
+        let location = Location { block, statement: data.statements.len() };
+        for (i, (arg, span)) in args.iter().zip(arg_spans).enumerate() {
+            match arg {
+                Operand::Copy(place) if !gem.is_copy(&gem.place_ty(body, place)) => {
+                    loans.push(Loan { place: place.clone(), mutable: false, location, span: *span, arg: Some(i) });
+                }
+                _ => {}
+            }
+        }
+    }
+    loans
+}
+
+/// The loans each local may hold a reference of.
+///
+/// A reference copied into another local, passed to a function or borrowed again is
+/// held by the result as well, since the result may contain it.
+struct HeldLoans<'a> {
+    loans: &'a [Loan],
+}
+
+impl HeldLoans<'_> {
+    fn taken_at(&self, location: Location) -> impl Iterator<Item = usize> + '_ {
+        (0..self.loans.len()).filter(move |&id| self.loans[id].location == location)
+    }
+}
+
+impl Analysis for HeldLoans<'_> {
+    type Domain = Vec<BitSet>;
+
+    fn bottom(&self, body: &Body) -> Vec<BitSet> {
+        vec![BitSet::new(self.loans.len()); body.locals.len()]
+    }
+
+    fn join(&self, state: &mut Vec<BitSet>, other: &Vec<BitSet>) -> bool {
+        let mut changed = false;
+        for (held, other) in state.iter_mut().zip(other) {
+            changed |= held.union(other);
+        }
+        changed
+    }
+
+    fn statement_effect(&self, state: &mut Vec<BitSet>, statement: &Statement, location: Location) {
+        let StatementKind::Assign(place, rvalue) = &statement.kind else { return };
+        let mut held = BitSet::new(self.loans.len());
+        for read in rvalue.read_places() {
+            held.union(&state[read.local.index()]);
+        }
+        for id in self.taken_at(location) {
+            held.insert(id);
+        }
+        hold(state, place, held);
+    }
+
+    fn terminator_effect(&self, state: &mut Vec<BitSet>, terminator: &Terminator, location: Location) {
+        let TerminatorKind::Call { args, dest, .. } = &terminator.kind else { return };
+        let mut held = BitSet::new(self.loans.len());
+        for arg in args.iter().filter_map(Operand::place) {
+            held.union(&state[arg.local.index()]);
+        }
+        for id in self.taken_at(location) {
+            held.insert(id);
+        }
+        hold(state, dest, held);
+    }
+}
+
+/// Makes `place` hold the loans `held`, in addition to what the rest of its local holds if it is a field.
+fn hold(state: &mut [BitSet], place: &Place, held: BitSet) {
+    if place.projection.is_empty() {
+        state[place.local.index()] = held;
+    } else {
+        state[place.local.index()].union(&held);
+    }
+}
+
+/// How a statement or terminator accesses a place.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Access {
+    Read,
+    Borrow {
+        mutable: bool,
+    },
+    Write,
+    Move,
+    /// The local goes out of scope
+    Dead,
+}
+
+struct BorrowChecker<'a> {
+    gem: &'a MirGem,
+    body: &'a Body,
+    loans: Vec<Loan>,
+    live: Vec<Vec<BitSet>>,
+    held: Vec<Vec<Vec<BitSet>>>,
+    errors: Vec<StaticAnalysisError>,
+    /// Conflicts already reported, by loan and conflicting access, so that every conflict is reported once
+    reported: HashSet<(LoanId, Span)>,
+}
+
+impl<'a> BorrowChecker<'a> {
+    fn new(gem: &'a MirGem, body: &'a Body) -> Self {
+        let loans = collect_loans(gem, body);
+        let live = Results::compute(Liveness, body).all_states(body);
+        let held = Results::compute(HeldLoans { loans: &loans }, body).all_states(body);
+        Self { gem, body, loans, live, held, errors: Vec::new(), reported: HashSet::new() }
+    }
+
+    /// The loans some local that is read later may hold, right before `location`.
+    /// The return value is not counted when `returning`, since [`BorrowChecker::check_return`] reports its loans.
+    fn live_loans(&self, location: Location, returning: bool) -> BitSet {
+        let live = &self.live[location.block.0][location.statement];
+        let held = &self.held[location.block.0][location.statement];
+        let mut loans = BitSet::new(self.loans.len());
+        for local in live.iter().filter(|&local| !(returning && local == Local::RETURN.index())) {
+            loans.union(&held[local]);
+        }
+        loans
+    }
+
+    fn check(mut self) -> Vec<StaticAnalysisError> {
+        for block in self.body.block_ids() {
+            let data = self.body.block(block);
+            for (i, statement) in data.statements.iter().enumerate() {
+                let location = Location { block, statement: i };
+                for (place, access) in self.statement_accesses(statement) {
+                    self.check_access(&place, access, statement.span, location, 0);
+                }
+            }
+            let location = Location { block, statement: data.statements.len() };
+            for (position, (place, access, span)) in
+                terminator_accesses(self.gem, self.body, &data.terminator).into_iter().enumerate()
+            {
+                self.check_access(&place, access, span, location, position);
+            }
+            if data.terminator.kind == TerminatorKind::Return {
+                self.check_return(data.terminator.span, location);
+            }
+        }
+        self.errors
+    }
+
+    /// How `statement` accesses each place, in the order it happens.
+    fn statement_accesses(&self, statement: &Statement) -> Vec<(Place, Access)> {
+        match &statement.kind {
+            StatementKind::Assign(dest, rvalue) => {
+                let moved = moved_by_statement(self.gem, self.body, statement);
+                let mut accesses: Vec<_> = match rvalue {
+                    Rvalue::Ref { mutable, place } => vec![(place.clone(), Access::Borrow { mutable: *mutable })],
+                    rvalue => rvalue
+                        .read_places()
+                        .into_iter()
+                        .map(|place| (place.clone(), if moved.contains(&place) { Access::Move } else { Access::Read }))
+                        .collect(),
+                };
+                accesses.push((dest.clone(), Access::Write));
+                accesses
+            }
+            StatementKind::StorageDead(local) => vec![((*local).into(), Access::Dead)],
+            StatementKind::Nop => Vec::new(),
+        }
+    }
+
+    /// Checks an access to `place` at `location`. The loans taken by the arguments of a call before the
+    /// one at `position` are live during the rest of the call.
+    fn check_access(&mut self, place: &Place, access: Access, span: Span, location: Location, position: usize) {
+        let decl = self.body.local(place.local);
+        if access == (Access::Borrow { mutable: true }) {
+            match self.gem.behind_ref(self.body, place) {
+                Some(false) => self.errors.push(StaticAnalysisError::MutBorrowBehindRef {
+                    name: self.gem.place_name(self.body, place),
+                    span,
+                }),
+                None if decl.name.is_some() && !decl.mutable => {
+                    self.errors.push(StaticAnalysisError::MutBorrowOfImmutable {
+                        name: self.gem.place_name(self.body, place),
+                        span,
+                        decl: decl.span,
+                    });
+                }
+                _ => {}
+            }
+        }
+        // only what the user wrote can be in conflict, temporaries are used right away
+        if decl.name.is_none() {
+            return;
+        }
+        let name = self.gem.place_name(self.body, place);
+
+        let mut live = self.live_loans(location, access == Access::Dead);
+        for (id, loan) in self.loans.iter().enumerate() {
+            if loan.location == location && loan.arg.map_or(false, |arg| arg < position) {
+                live.insert(id);
+            }
+        }
+        let conflict = live.iter().map(LoanId).find(|id| {
+            let loan = &self.loans[id.0];
+            loan.place.overlaps(place)
+                && (loan.location != location || loan.arg.map_or(false, |arg| arg < position))
+                && match access {
+                    Access::Read | Access::Borrow { mutable: false } => loan.mutable,
+                    Access::Borrow { mutable: true } | Access::Write | Access::Move | Access::Dead => true,
+                }
+        });
+        let Some(id) = conflict else { return };
+        if !self.reported.insert((id, span)) {
+            return;
+        }
+        let loan = &self.loans[id.0];
+        let later = self.later_use(id, location);
+        let error = match access {
+            Access::Borrow { mutable } => StaticAnalysisError::BorrowConflict {
+                name,
+                first_mutable: loan.mutable,
+                second_mutable: mutable,
+                borrow: loan.span,
+                span,
+                later,
+            },
+            Access::Dead => StaticAnalysisError::BorrowedValueDropped { name, borrow: loan.span, span, later },
+            Access::Read | Access::Write | Access::Move => StaticAnalysisError::UseWhileBorrowed {
+                name,
+                action: match access {
+                    Access::Read => "use",
+                    Access::Write => "assign to",
+                    _ => "move out of",
+                },
+                borrow_mutable: loan.mutable,
+                borrow: loan.span,
+                span,
+                later,
+            },
+        };
+        self.errors.push(error);
+    }
+
+    /// Every local dies when returning, so the return value may not borrow any of them.
+    fn check_return(&mut self, span: Span, location: Location) {
+        let held = &self.held[location.block.0][location.statement][Local::RETURN.index()];
+        let returned = held.iter().map(LoanId).find(|id| self.body.local(self.loans[id.0].place.local).name.is_some());
+        if let Some(id) = returned {
+            let loan = &self.loans[id.0];
+            self.errors.push(StaticAnalysisError::ReturnsLocalReference {
+                name: self.gem.place_name(self.body, &loan.place),
+                borrow: loan.span,
+                span,
+            });
+        }
+    }
+
+    /// The first place after `location` reading a local that holds `loan`.
+    fn later_use(&self, loan: LoanId, location: Location) -> Option<Span> {
+        let holds = |location: Location, local: Local| {
+            self.held[location.block.0][location.statement][local.index()].contains(loan.0)
+        };
+        let start = self.body.block(location.block);
+        let mut queue = VecDeque::new();
+        if location.statement < start.statements.len() {
+            queue.push_back(Location { statement: location.statement + 1, ..location });
+        } else {
+            // a loan taken by a call is only held once it returns
+            queue
+                .extend(self.body.successors(location.block).into_iter().map(|block| Location { block, statement: 0 }));
+        }
+        let mut visited = HashSet::new();
+        while let Some(location) = queue.pop_front() {
+            if !visited.insert(location) {
+                continue;
+            }
+            let block = self.body.block(location.block);
+            if let Some(statement) = block.statements.get(location.statement) {
+                if let StatementKind::Assign(_, rvalue) = &statement.kind {
+                    if rvalue.read_places().into_iter().any(|place| holds(location, place.local)) {
+                        return Some(statement.span);
+                    }
+                }
+                queue.push_back(Location { statement: location.statement + 1, ..location });
+                continue;
+            }
+            let terminator = &block.terminator;
+            if terminator.kind == TerminatorKind::Return && holds(location, Local::RETURN) {
+                return Some(terminator.span);
+            }
+            let read = terminator_accesses(self.gem, self.body, terminator)
+                .into_iter()
+                .find(|(place, access, _)| *access != Access::Write && holds(location, place.local));
+            if let Some((_, _, span)) = read {
+                return Some(span);
+            }
+            queue
+                .extend(self.body.successors(location.block).into_iter().map(|block| Location { block, statement: 0 }));
+        }
+        None
+    }
+}
+
+/// How `terminator` accesses each place and where, in the order it happens: a call reads its arguments,
+/// borrowing the ones it passes to parameters that are not `move`, and then writes what it returns.
+fn terminator_accesses(gem: &MirGem, body: &Body, terminator: &Terminator) -> Vec<(Place, Access, Span)> {
+    match &terminator.kind {
+        TerminatorKind::Call { args, arg_spans, dest, .. } => {
+            let mut accesses: Vec<_> = args
+                .iter()
+                .zip(arg_spans)
+                .filter_map(|(arg, span)| match arg {
+                    Operand::Copy(place) if gem.is_copy(&gem.place_ty(body, place)) => {
+                        Some((place.clone(), Access::Read, *span))
+                    }
+                    Operand::Copy(place) => Some((place.clone(), Access::Borrow { mutable: false }, *span)),
+                    Operand::Move(place) => Some((place.clone(), Access::Move, *span)),
+                    Operand::Const(_) => None,
+                })
+                .collect();
+            accesses.push((dest.clone(), Access::Write, terminator.span));
+            accesses
+        }
+        kind => kind
+            .operands()
+            .iter()
+            .filter_map(Operand::place)
+            .map(|place| (place.clone(), Access::Read, terminator.span))
+            .collect(),
+    }
+}
+
+/// A place read, or moved out of, right before `location`.
+struct Use<'a> {
+    place: &'a Place,
+    location: Location,
+    span: Span,
+    /// The index of the argument, for a use by a call
+    arg: Option<usize>,
+    moved: bool,
+}
+
+/// Every use of a place in `body`, in program order within each block.
+fn uses<'a>(gem: &MirGem, body: &'a Body) -> Vec<Use<'a>> {
+    let mut uses = Vec::new();
+    for block in body.block_ids() {
+        let data = body.block(block);
+        for (i, statement) in data.statements.iter().enumerate() {
+            let StatementKind::Assign(_, rvalue) = &statement.kind else { continue };
+            let location = Location { block, statement: i };
+            let moved = moved_by_statement(gem, body, statement);
+            uses.extend(rvalue.read_places().into_iter().map(|place| Use {
+                place,
+                location,
+                span: statement.span,
+                arg: None,
+                moved: moved.contains(&place),
+            }));
+        }
+        let location = Location { block, statement: data.statements.len() };
+        match &data.terminator.kind {
+            TerminatorKind::Call { args, arg_spans, .. } => {
+                for (i, (arg, span)) in args.iter().zip(arg_spans).enumerate() {
+                    let Some(place) = arg.place() else { continue };
+                    let moved = matches!(arg, Operand::Move(_));
+                    uses.push(Use { place, location, span: *span, arg: Some(i), moved });
+                }
+            }
+            kind => uses.extend(kind.operands().iter().filter_map(Operand::place).map(|place| Use {
+                place,
+                location,
+                span: data.terminator.span,
+                arg: None,
+                moved: false,
+            })),
+        }
+    }
+    uses
+}
+
+/// Checks the borrows and moves of every function body, see the module documentation.
+#[derive(Default)]
+pub struct BorrowCheck;
+
+impl AnalysisPass for BorrowCheck {
+    fn name(&self) -> &'static str {
+        "borrowck"
+    }
+
+    fn run(&mut self, session: &mut AnalysisSession) {
+        let hir = lower_gem(session);
+        let gem = MirGem {
+            // a default body of a trait is checked once, for every `this`: its copies in the impls
+            // can have no error it does not
+            bodies: hir
+                .functions
+                .iter()
+                .filter(|func| !func.provided || func.impl_id.is_none())
+                .map(|func| build_body(&hir, func))
+                .collect(),
+            structs: hir.structs,
+            enums: hir.enums,
+        };
+        let mut errors = Vec::new();
+        for body in &gem.bodies {
+            let found = BorrowChecker::new(&gem, body).check();
+            let moved = moves::check(&gem, body);
+            let uninit = init::check(&gem, body);
+            errors.extend(found.into_iter().chain(moved).chain(uninit).map(|error| (body.file_id, error)));
+        }
+        for (file_id, error) in errors {
+            session.error(file_id, error);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! `borrowck/moves.rs` - uses of moved values.
+//!
+//! Every move out of a local, or out of a field of it, is a move site. The site stays in effect
+//! until the place is assigned again, and while it may be, nothing overlapping the moved place
+//! may be used: moving `p.x` out of `p` leaves `p.y` usable, but not `p` as a whole.
+use super::{uses, Use};
+use crate::dataflow::{Analysis, BitSet, Results};
+use crate::{Body, Location, MirGem, Place, Statement, StatementKind, Terminator, TerminatorKind};
+use std::collections::HashSet;
+use topaz_ast::location::Span;
+use topaz_type_checker::error::StaticAnalysisError;
+
+/// A move out of a place the user wrote.
+#[derive(Debug, Clone)]
+struct MoveSite {
+    place: Place,
+    location: Location,
+    span: Span,
+    /// The index of the argument, for a move into a call
+    arg: Option<usize>,
+}
+
+/// The places moved out of that the analysis tracks: locals the function owns.
+/// Temporaries are moved right after they are assigned.
+fn collect_sites(body: &Body, uses: &[Use]) -> Vec<MoveSite> {
+    uses.iter()
+        .filter(|used| used.moved)
+        .filter(|used| {
+            let decl = body.local(used.place.local);
+            decl.name.is_some() && !decl.borrowed
+        })
+        .map(|used| MoveSite { place: used.place.clone(), location: used.location, span: used.span, arg: used.arg })
+        .collect()
+}
+
+/// The move sites that may be in effect.
+struct MaybeMoved<'a> {
+    sites: &'a [MoveSite],
+}
+
+impl MaybeMoved<'_> {
+    fn moved_at(&self, state: &mut BitSet, location: Location) {
+        for (i, site) in self.sites.iter().enumerate() {
+            if site.location == location {
+                state.insert(i);
+            }
+        }
+    }
+
+    /// `place` is initialized again, so nothing it contains is moved out of anymore.
+    fn assigned(&self, state: &mut BitSet, place: &Place) {
+        for (i, site) in self.sites.iter().enumerate() {
+            if place.contains(&site.place) {
+                state.remove(i);
+            }
+        }
+    }
+}
+
+impl Analysis for MaybeMoved<'_> {
+    type Domain = BitSet;
+
+    fn bottom(&self, _: &Body) -> BitSet {
+        BitSet::new(self.sites.len())
+    }
+
+    fn join(&self, state: &mut BitSet, other: &BitSet) -> bool {
+        state.union(other)
+    }
+
+    fn statement_effect(&self, state: &mut BitSet, statement: &Statement, location: Location) {
+        self.moved_at(state, location);
+        match &statement.kind {
+            StatementKind::Assign(place, _) => self.assigned(state, place),
+            // the local is gone
+            StatementKind::StorageDead(local) => self.assigned(state, &(*local).into()),
+            StatementKind::Nop => {}
+        }
+    }
+
+    fn terminator_effect(&self, state: &mut BitSet, terminator: &Terminator, location: Location) {
+        self.moved_at(state, location);
+        if let TerminatorKind::Call { dest, .. } = &terminator.kind {
+            self.assigned(state, dest);
+        }
+    }
+}
+
+/// Reports every use of a place that may have been moved out of, every move out of a parameter the function
+/// only borrows, and every move out of a field of a value with a `Drop` impl, which could not be dropped
+/// as a whole anymore.
+pub fn check(gem: &MirGem, body: &Body) -> Vec<StaticAnalysisError> {
+    let uses = uses(gem, body);
+    let sites = collect_sites(body, &uses);
+    let states = Results::compute(MaybeMoved { sites: &sites }, body).all_states(body);
+    let mut errors = Vec::new();
+    // every site is reported once, at its first use
+    let mut reported = HashSet::new();
+    for used in &uses {
+        let mut moved = states[used.location.block.0][used.location.statement].clone();
+        // the arguments of a call are moved into it one after another
+        for (i, earlier) in sites.iter().enumerate() {
+            if earlier.location == used.location && earlier.arg.zip(used.arg).map_or(false, |(a, b)| a < b) {
+                moved.insert(i);
+            }
+        }
+        // the latest move is the one the user is most likely to look for
+        let found = moved.iter().filter(|&i| sites[i].place.overlaps(used.place)).last();
+        if let Some(i) = found.filter(|&i| reported.insert(i)) {
+            let moved = &sites[i];
+            errors.push(StaticAnalysisError::UseAfterMove {
+                place: gem.place_name(body, used.place),
+                moved_place: gem.place_name(body, &moved.place),
+                moved_ty: gem.place_ty(body, &moved.place),
+                partial: moved.place.projection.len() > used.place.projection.len(),
+                moved: moved.span,
+                // only a back edge leads from a later move to an earlier use
+                in_loop: moved.span.start >= used.span.start,
+                span: used.span,
+            });
+        }
+        if !used.moved {
+            continue;
+        }
+        let decl = body.local(used.place.local);
+        let Some(name) = &decl.name else { continue };
+        if decl.borrowed {
+            errors.push(StaticAnalysisError::MoveOutOfBorrowedParam {
+                place: gem.place_name(body, used.place),
+                param: name.clone(),
+                param_span: decl.span,
+                span: used.span,
+            });
+        }
+        let owner = (0..used.place.projection.len())
+            .map(|len| Place { local: used.place.local, projection: used.place.projection[..len].to_vec() })
+            .map(|owner| gem.place_ty(body, &owner))
+            .find(|ty| gem.drop_impl(ty).is_some());
+        if let Some(ty) = owner {
+            errors.push(StaticAnalysisError::MoveOutOfDrop {
+                place: gem.place_name(body, used.place),
+                ty,
+                span: used.span,
+            });
+        }
+    }
+    errors
+}
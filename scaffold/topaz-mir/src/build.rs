@@ -0,0 +1,410 @@
+//! `build.rs` - builds the MIR of a gem from its HIR.
+//!
+//! Every expression is evaluated into an operand, with a temporary for every value in between,
+//! e.g. `let far = scale(origin.x)` becomes `_3 = copy (_2.0); _1 = scale(move _3) -> bb1`.
+//! Calls end their block, since control leaves the function during them.
+//!
+//! A local goes out of scope at the end of the block it is declared in, or when returning, which a
+//! `StorageDead` marks. Locals going out of scope together do so in the reverse order they were declared in.
+use crate::drops::elaborate_gem;
+use crate::{
+    BasicBlock, BlockId, Body, ConstValue, Constant, Local, LocalDecl, MirGem, Operand, Phase, Place, Rvalue,
+    Statement, StatementKind, Terminator, TerminatorKind,
+};
+use std::collections::HashMap;
+use topaz_ast::location::Span;
+use topaz_hir::{Arm, Block, Callee, Expr, ExprKind, Function, HirGem, Lit, Stmt, StmtKind};
+use topaz_resolve::def::DefId;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+/// Builds the body of every function of `gem`, in [`Phase::Built`], with the drops of their values.
+#[must_use]
+pub fn build_gem(gem: &HirGem) -> MirGem {
+    let mut mir = MirGem {
+        // the default bodies of traits are built for every impl, in which `this` is a type
+        bodies: gem
+            .functions
+            .iter()
+            .filter(|func| !func.provided || func.impl_id.is_some())
+            .map(|func| build_body(gem, func))
+            .collect(),
+        structs: gem.structs.clone(),
+        enums: gem.enums.clone(),
+    };
+    elaborate_gem(&mut mir);
+    mir
+}
+
+/// Builds the body of `func`, a function of `gem`, without its drops.
+#[must_use]
+pub fn build_body(gem: &HirGem, func: &Function) -> Body {
+    let mut builder = Builder {
+        gem,
+        locals: Vec::new(),
+        by_def: HashMap::new(),
+        blocks: Vec::new(),
+        current: BlockId::START,
+        scopes: vec![Vec::new()],
+    };
+    builder.new_local(None, func.ret.clone(), true, func.span);
+    for param in &func.params {
+        let local = builder.new_local(Some(param.name.clone()), param.ty.clone(), false, param.span);
+        builder.locals[local.index()].borrowed = !param.moved;
+        builder.by_def.insert(param.def, local);
+        builder.declare(local);
+    }
+    builder.current = builder.new_block();
+    builder.lower_block(&func.body);
+
+    // a function returning a value returns it on every path, which the type checker made sure of
+    let end = Span::from_location(func.span.end, 0);
+    if func.ret == Ty::Void {
+        builder.storage_dead_all(end);
+        builder.terminate(TerminatorKind::Return, end);
+    } else {
+        builder.terminate(TerminatorKind::Unreachable, end);
+    }
+
+    Body {
+        name: func.name.clone(),
+        def: func.def,
+        impl_id: func.impl_id,
+        file_id: func.file_id,
+        span: func.span,
+        locals: builder.locals,
+        arg_count: func.params.len(),
+        blocks: builder
+            .blocks
+            .into_iter()
+            .map(|(statements, terminator)| BasicBlock {
+                phis: Vec::new(),
+                statements,
+                terminator: terminator.expect("every block is terminated"),
+            })
+            .collect(),
+        phase: Phase::Built,
+    }
+}
+
+struct Builder<'a> {
+    gem: &'a HirGem,
+    locals: Vec<LocalDecl>,
+    by_def: HashMap<DefId, Local>,
+    blocks: Vec<(Vec<Statement>, Option<Terminator>)>,
+    current: BlockId,
+    /// The locals declared in every scope entered, innermost last. The outermost one is the parameters'
+    scopes: Vec<Vec<Local>>,
+}
+
+impl Builder<'_> {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((Vec::new(), None));
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn new_local(&mut self, name: Option<String>, ty: Ty, mutable: bool, span: Span) -> Local {
+        self.locals.push(LocalDecl { name, ty, mutable, borrowed: false, span });
+        Local(u32::try_from(self.locals.len() - 1).expect("too many locals"))
+    }
+
+    /// A temporary, which goes out of scope with the innermost scope if it has to be dropped.
+    fn temp(&mut self, ty: Ty, span: Span) -> Local {
+        let copy = self.gem.is_copy(&ty);
+        let temp = self.new_local(None, ty, false, span);
+        if !copy {
+            self.declare(temp);
+        }
+        temp
+    }
+
+    /// Puts `local` in the innermost scope.
+    fn declare(&mut self, local: Local) {
+        self.scopes.last_mut().expect("there is always the scope of the parameters").push(local);
+    }
+
+    fn push_statement(&mut self, kind: StatementKind, span: Span) {
+        self.blocks[self.current.0].0.push(Statement { kind, span });
+    }
+
+    fn assign(&mut self, place: impl Into<Place>, rvalue: Rvalue, span: Span) {
+        self.push_statement(StatementKind::Assign(place.into(), rvalue), span);
+    }
+
+    /// Leaves the innermost scope at `span`, where its locals go out of scope.
+    fn exit_scope(&mut self, span: Span) {
+        let scope = self.scopes.pop().expect("a scope was entered");
+        for local in scope.into_iter().rev() {
+            self.push_statement(StatementKind::StorageDead(local), span);
+        }
+    }
+
+    /// Marks every local in scope as going out of scope at `span`, before returning.
+    fn storage_dead_all(&mut self, span: Span) {
+        let locals: Vec<_> = self.scopes.iter().rev().flat_map(|scope| scope.iter().rev()).copied().collect();
+        for local in locals {
+            self.push_statement(StatementKind::StorageDead(local), span);
+        }
+    }
+
+    fn terminate(&mut self, kind: TerminatorKind, span: Span) {
+        self.blocks[self.current.0].1 = Some(Terminator { kind, span });
+    }
+
+    fn lower_block(&mut self, block: &Block) {
+        self.scopes.push(Vec::new());
+        for stmt in &block.stmts {
+            self.lower_stmt(stmt);
+        }
+        self.exit_scope(Span::from_location(block.span.end, 0));
+    }
+
+    fn lower_stmt(&mut self, Stmt { kind, span }: &Stmt) {
+        match kind {
+            StmtKind::Let { def, name, name_span, mutable, ty, init } => {
+                let local = self.new_local(Some(name.clone()), ty.clone(), *mutable, *name_span);
+                self.by_def.insert(*def, local);
+                self.declare(local);
+                if let Some(init) = init {
+                    self.lower_into(local.into(), init);
+                }
+            }
+            StmtKind::Assign { local, value, .. } => match self.by_def.get(local).copied() {
+                // the assignment is the whole statement, unless a call does it
+                Some(local) if !matches!(value.kind, ExprKind::Call { .. }) => {
+                    let rvalue = self.lower_rvalue(value);
+                    self.assign(local, rvalue, *span);
+                }
+                Some(local) => self.lower_into(local.into(), value),
+                None => {
+                    self.lower_operand(value);
+                }
+            },
+            StmtKind::Expr(expr) => {
+                self.lower_operand(expr);
+            }
+            StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.lower_into(Local::RETURN.into(), value);
+                }
+                self.storage_dead_all(*span);
+                self.terminate(TerminatorKind::Return, *span);
+                // anything after the `return` is unreachable, but still lowered
+                self.current = self.new_block();
+            }
+            StmtKind::While { cond, body } => {
+                let header = self.new_block();
+                self.terminate(TerminatorKind::Goto(header), *span);
+                self.current = header;
+                let cond_span = cond.span;
+                let cond = self.lower_operand(cond);
+                let (then, exit) = (self.new_block(), self.new_block());
+                self.terminate(TerminatorKind::If { cond, then, otherwise: exit }, cond_span);
+                self.current = then;
+                self.lower_block(body);
+                self.terminate(TerminatorKind::Goto(header), *span);
+                self.current = exit;
+            }
+            StmtKind::If { cond, then, otherwise } => {
+                let cond_span = cond.span;
+                let cond = self.lower_operand(cond);
+                let (then_block, join) = (self.new_block(), self.new_block());
+                let else_block = otherwise.as_ref().map(|_| self.new_block());
+                self.terminate(
+                    TerminatorKind::If { cond, then: then_block, otherwise: else_block.unwrap_or(join) },
+                    cond_span,
+                );
+                self.current = then_block;
+                self.lower_block(then);
+                self.terminate(TerminatorKind::Goto(join), then.span);
+                if let (Some(otherwise), Some(else_block)) = (otherwise, else_block) {
+                    self.current = else_block;
+                    self.lower_block(otherwise);
+                    self.terminate(TerminatorKind::Goto(join), otherwise.span);
+                }
+                self.current = join;
+            }
+            StmtKind::Match { scrutinee, arms } => self.lower_match(scrutinee, arms),
+            StmtKind::Block(block) => self.lower_block(block),
+        }
+    }
+
+    /// Switches on the discriminant of `scrutinee` to the first arm matching each variant,
+    /// which starts by binding the fields of the variant.
+    fn lower_match(&mut self, scrutinee: &Expr, arms: &[Arm]) {
+        let place = self.lower_place(scrutinee);
+        let discr = self.temp(Ty::Int(IntTy::Usize), scrutinee.span);
+        self.assign(discr, Rvalue::Discriminant(Operand::Copy(place.clone())), scrutinee.span);
+        let variants = match &scrutinee.ty {
+            Ty::Enum { def, .. } => self.gem.enum_def(*def).map_or(0, |enum_def| enum_def.variants.len()),
+            _ => 0,
+        };
+
+        let (entry, join) = (self.current, self.new_block());
+        let mut targets = vec![None; variants];
+        for arm in arms {
+            let block = self.new_block();
+            self.current = block;
+            self.scopes.push(Vec::new());
+            match arm.variant {
+                Some(index) => {
+                    if let Some(target) = targets.get_mut(index) {
+                        target.get_or_insert(block);
+                    }
+                }
+                None => targets.iter_mut().for_each(|target| {
+                    target.get_or_insert(block);
+                }),
+            }
+            for (field, binding) in arm.bindings.iter().enumerate() {
+                let local = self.new_local(Some(binding.name.clone()), binding.ty.clone(), false, binding.span);
+                self.by_def.insert(binding.def, local);
+                self.declare(local);
+                if let Some(variant) = arm.variant {
+                    let rvalue = Rvalue::Payload { operand: Operand::Copy(place.clone()), variant, field };
+                    self.assign(local, rvalue, binding.span);
+                }
+            }
+            self.lower_block(&arm.body);
+            self.exit_scope(Span::from_location(arm.body.span.end, 0));
+            self.terminate(TerminatorKind::Goto(join), arm.body.span);
+        }
+
+        self.current = entry;
+        // the type checker made sure that every variant is matched
+        let unmatched = self.new_block();
+        let targets = targets.into_iter().map(|target| target.unwrap_or(unmatched)).collect();
+        self.terminate(TerminatorKind::Switch { discr: Operand::Copy(discr.into()), targets }, scrutinee.span);
+        self.current = unmatched;
+        self.terminate(TerminatorKind::Unreachable, scrutinee.span);
+        self.current = join;
+    }
+
+    /// Evaluates `expr` into `dest`.
+    fn lower_into(&mut self, dest: Place, expr: &Expr) {
+        if let ExprKind::Call { callee, args } = &expr.kind {
+            self.lower_call(dest, callee, args, expr.span);
+            return;
+        }
+        let rvalue = self.lower_rvalue(expr);
+        self.assign(dest, rvalue, expr.span);
+    }
+
+    fn lower_rvalue(&mut self, expr: &Expr) -> Rvalue {
+        match &expr.kind {
+            ExprKind::Ref { mutable, expr: borrowed } => {
+                let place = match borrowed.kind {
+                    ExprKind::Local { .. } | ExprKind::Field { .. } => self.lower_place(borrowed),
+                    // a borrowed temporary lives as long as the function, so that the reference can outlive the statement
+                    _ => {
+                        let temp = self.new_local(None, borrowed.ty.clone(), false, borrowed.span);
+                        self.scopes[0].push(temp);
+                        self.lower_into(temp.into(), borrowed);
+                        temp.into()
+                    }
+                };
+                Rvalue::Ref { mutable: *mutable, place }
+            }
+            ExprKind::Struct { def, fields } => Rvalue::Aggregate {
+                def: *def,
+                operands: fields.iter().map(|field| self.lower_operand(field)).collect(),
+            },
+            ExprKind::Variant { def, index, fields } => Rvalue::Variant {
+                def: *def,
+                index: *index,
+                operands: fields.iter().map(|field| self.lower_operand(field)).collect(),
+            },
+            ExprKind::Array(elements) => {
+                Rvalue::Array(elements.iter().map(|element| self.lower_operand(element)).collect())
+            }
+            ExprKind::Index { base, index } => {
+                Rvalue::Index { operands: [self.lower_operand(base), self.lower_operand(index)] }
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                Rvalue::BinaryOp { op: *op, operands: [self.lower_operand(lhs), self.lower_operand(rhs)] }
+            }
+            ExprKind::Unary { op, operand } => Rvalue::UnaryOp { op: *op, operand: self.lower_operand(operand) },
+            _ => Rvalue::Use(self.lower_operand(expr)),
+        }
+    }
+
+    /// The place `expr` is, or a temporary holding its value if it is not one.
+    fn lower_place(&mut self, expr: &Expr) -> Place {
+        match &expr.kind {
+            ExprKind::Local { def, .. } => match self.by_def.get(def) {
+                Some(local) => (*local).into(),
+                // the HIR only refers to the locals of the function it is in
+                None => self.temp(expr.ty.clone(), expr.span).into(),
+            },
+            ExprKind::Field { base, index, .. } => {
+                let mut place = self.lower_place(base);
+                place.projection.push(*index);
+                place
+            }
+            _ => {
+                let temp = self.temp(expr.ty.clone(), expr.span);
+                self.lower_into(temp.into(), expr);
+                temp.into()
+            }
+        }
+    }
+
+    /// Copies or moves `place`, depending on its type.
+    fn use_place(&self, place: Place, ty: &Ty) -> Operand {
+        if self.gem.is_copy(ty) {
+            Operand::Copy(place)
+        } else {
+            Operand::Move(place)
+        }
+    }
+
+    fn lower_operand(&mut self, expr: &Expr) -> Operand {
+        let value = match &expr.kind {
+            ExprKind::Literal(Lit::Int(value)) => ConstValue::Int(*value),
+            ExprKind::Literal(Lit::Bool(value)) => ConstValue::Bool(*value),
+            ExprKind::Literal(Lit::Char(value)) => ConstValue::Char(*value),
+            ExprKind::Literal(Lit::Str(value)) => ConstValue::Str(value.clone()),
+            ExprKind::Const(path) => ConstValue::Named(path.clone()),
+            ExprKind::Error => ConstValue::Void,
+            ExprKind::Local { .. } | ExprKind::Field { .. } => {
+                let place = self.lower_place(expr);
+                return self.use_place(place, &expr.ty);
+            }
+            ExprKind::Call { .. }
+            | ExprKind::Struct { .. }
+            | ExprKind::Variant { .. }
+            | ExprKind::Array(_)
+            | ExprKind::Index { .. }
+            | ExprKind::Ref { .. }
+            | ExprKind::Binary { .. }
+            | ExprKind::Unary { .. } => {
+                let temp = self.temp(expr.ty.clone(), expr.span);
+                self.lower_into(temp.into(), expr);
+                return self.use_place(temp.into(), &expr.ty);
+            }
+        };
+        Operand::Const(Constant { value, ty: expr.ty.clone() })
+    }
+
+    /// Calls `callee`, writing what it returns to `dest`, and continues in a new block.
+    ///
+    /// An argument is moved into a `move` parameter. Any other parameter borrows it, so it is copied,
+    /// and the caller drops it when it goes out of scope.
+    fn lower_call(&mut self, dest: Place, callee: &Callee, args: &[Expr], span: Span) {
+        let params = self.gem.callee(callee).map(|func| &func.params);
+        let arg_spans = args.iter().map(|arg| arg.span).collect();
+        let args = args
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                let moved = params.and_then(|params| params.get(index)).map_or(false, |param| param.moved);
+                match self.lower_operand(arg) {
+                    Operand::Move(place) if !moved => Operand::Copy(place),
+                    operand => operand,
+                }
+            })
+            .collect();
+        let target = self.new_block();
+        self.terminate(TerminatorKind::Call { callee: callee.clone(), args, arg_spans, dest, target }, span);
+        self.current = target;
+    }
+}
@@ -0,0 +1,249 @@
+//! `dataflow.rs` - a generic dataflow framework over a [`Body`], and liveness of locals.
+use crate::{BasicBlock, BlockId, Body, Local, Location, Operand, Statement, StatementKind, Terminator, TerminatorKind};
+use std::collections::VecDeque;
+
+/// A fixed size set of indices, e.g. of locals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    #[must_use]
+    pub fn new(size: usize) -> Self {
+        Self { words: vec![0; (size + 63) / 64] }
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.words[index / 64] &= !(1 << (index % 64));
+    }
+
+    #[must_use]
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Adds every index of `other`, returning whether any was not in the set yet.
+    pub fn union(&mut self, other: &Self) -> bool {
+        let mut changed = false;
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            let new = *word | other;
+            changed |= new != *word;
+            *word = new;
+        }
+        changed
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(i, word)| (0..64).filter(move |bit| word & (1 << bit) != 0).map(move |bit| i * 64 + bit))
+    }
+}
+
+/// A dataflow problem: what is known at every point of a body.
+pub trait Analysis {
+    type Domain: Clone + PartialEq;
+    /// Whether the analysis goes from the end of the body to its start, like liveness
+    const BACKWARD: bool = false;
+
+    /// The state nothing is known in, which every block starts with.
+    fn bottom(&self, body: &Body) -> Self::Domain;
+
+    /// Sets up the state at the start of the body (or at every `return`, going backward).
+    fn initialize(&self, _body: &Body, _state: &mut Self::Domain) {}
+
+    /// Merges `other` into `state`, returning whether `state` changed.
+    fn join(&self, state: &mut Self::Domain, other: &Self::Domain) -> bool;
+
+    fn statement_effect(&self, state: &mut Self::Domain, statement: &Statement, location: Location);
+
+    /// The effect of the terminator, on the way to every successor: a call has returned by then.
+    fn terminator_effect(&self, _state: &mut Self::Domain, _terminator: &Terminator, _location: Location) {}
+}
+
+/// The fixpoint of an [`Analysis`].
+pub struct Results<A: Analysis> {
+    pub analysis: A,
+    /// The state at the start of every block, or at its end going backward
+    entry_sets: Vec<A::Domain>,
+}
+
+impl<A: Analysis> Results<A> {
+    /// Runs `analysis` on `body` until nothing changes anymore.
+    pub fn compute(analysis: A, body: &Body) -> Self {
+        let mut entry_sets = vec![analysis.bottom(body); body.blocks.len()];
+        let predecessors = body.predecessors();
+        if A::BACKWARD {
+            for block in body.block_ids() {
+                if body.block(block).terminator.kind == TerminatorKind::Return {
+                    analysis.initialize(body, &mut entry_sets[block.0]);
+                }
+            }
+        } else if let Some(start) = entry_sets.first_mut() {
+            analysis.initialize(body, start);
+        }
+
+        let mut queue: VecDeque<_> = body.block_ids().collect();
+        if A::BACKWARD {
+            queue.make_contiguous().reverse();
+        }
+        while let Some(block) = queue.pop_front() {
+            let mut state = entry_sets[block.0].clone();
+            apply_block(&analysis, body, block, &mut state);
+            let next = if A::BACKWARD { predecessors[block.0].clone() } else { body.successors(block) };
+            for next in next {
+                if analysis.join(&mut entry_sets[next.0], &state) && !queue.contains(&next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        Self { analysis, entry_sets }
+    }
+
+    /// The state before every statement of `block` and before its terminator, in program order.
+    ///
+    /// Going backward, the state before a statement is the one with its effect applied,
+    /// e.g. the locals live on entry to it.
+    pub fn states_in(&self, body: &Body, block: BlockId) -> Vec<A::Domain> {
+        let BasicBlock { statements, terminator, .. } = body.block(block);
+        let location = |statement| Location { block, statement };
+        let mut state = self.entry_sets[block.0].clone();
+        let mut states = Vec::with_capacity(statements.len() + 1);
+        if A::BACKWARD {
+            self.analysis.terminator_effect(&mut state, terminator, location(statements.len()));
+            states.push(state.clone());
+            for (i, statement) in statements.iter().enumerate().rev() {
+                self.analysis.statement_effect(&mut state, statement, location(i));
+                states.push(state.clone());
+            }
+            states.reverse();
+        } else {
+            for (i, statement) in statements.iter().enumerate() {
+                states.push(state.clone());
+                self.analysis.statement_effect(&mut state, statement, location(i));
+            }
+            states.push(state);
+        }
+        states
+    }
+
+    /// [`Results::states_in`] of every block.
+    pub fn all_states(&self, body: &Body) -> Vec<Vec<A::Domain>> {
+        body.block_ids().map(|block| self.states_in(body, block)).collect()
+    }
+}
+
+fn apply_block<A: Analysis>(analysis: &A, body: &Body, block: BlockId, state: &mut A::Domain) {
+    let BasicBlock { statements, terminator, .. } = body.block(block);
+    let location = |statement| Location { block, statement };
+    if A::BACKWARD {
+        analysis.terminator_effect(state, terminator, location(statements.len()));
+        for (i, statement) in statements.iter().enumerate().rev() {
+            analysis.statement_effect(state, statement, location(i));
+        }
+    } else {
+        for (i, statement) in statements.iter().enumerate() {
+            analysis.statement_effect(state, statement, location(i));
+        }
+        analysis.terminator_effect(state, terminator, location(statements.len()));
+    }
+}
+
+/// The locals that may not have been assigned yet. The destinations of phis count as assigned everywhere,
+/// since the validator checks that they are assigned before they are read.
+pub struct MaybeUnassigned;
+
+impl Analysis for MaybeUnassigned {
+    type Domain = BitSet;
+
+    fn bottom(&self, body: &Body) -> BitSet {
+        BitSet::new(body.locals.len())
+    }
+
+    fn initialize(&self, body: &Body, state: &mut BitSet) {
+        for index in 0..body.locals.len() {
+            if !(1..=body.arg_count).contains(&index) {
+                state.insert(index);
+            }
+        }
+        for phi in body.blocks.iter().flat_map(|block| &block.phis) {
+            state.remove(phi.dest.index());
+        }
+    }
+
+    fn join(&self, state: &mut BitSet, other: &BitSet) -> bool {
+        state.union(other)
+    }
+
+    fn statement_effect(&self, state: &mut BitSet, statement: &Statement, _: Location) {
+        if let StatementKind::Assign(place, _) = &statement.kind {
+            if let Some(local) = place.as_local() {
+                state.remove(local.index());
+            }
+        }
+    }
+
+    fn terminator_effect(&self, state: &mut BitSet, terminator: &Terminator, _: Location) {
+        if let TerminatorKind::Call { dest, .. } = &terminator.kind {
+            if let Some(local) = dest.as_local() {
+                state.remove(local.index());
+            }
+        }
+    }
+}
+
+/// The locals whose current value may still be read later.
+pub struct Liveness;
+
+impl Analysis for Liveness {
+    type Domain = BitSet;
+    const BACKWARD: bool = true;
+
+    fn bottom(&self, body: &Body) -> BitSet {
+        BitSet::new(body.locals.len())
+    }
+
+    fn join(&self, state: &mut BitSet, other: &BitSet) -> bool {
+        state.union(other)
+    }
+
+    fn statement_effect(&self, state: &mut BitSet, statement: &Statement, _: Location) {
+        match &statement.kind {
+            StatementKind::Assign(place, rvalue) => {
+                if let Some(local) = place.as_local() {
+                    state.remove(local.index());
+                }
+                for place in rvalue.read_places() {
+                    state.insert(place.local.index());
+                }
+            }
+            StatementKind::StorageDead(local) => state.remove(local.index()),
+            StatementKind::Nop => {}
+        }
+    }
+
+    fn terminator_effect(&self, state: &mut BitSet, terminator: &Terminator, _: Location) {
+        match &terminator.kind {
+            TerminatorKind::Return => state.insert(Local::RETURN.index()),
+            TerminatorKind::Call { dest, .. } => {
+                if let Some(local) = dest.as_local() {
+                    state.remove(local.index());
+                }
+            }
+            _ => {}
+        }
+        for place in terminator.kind.operands().iter().filter_map(Operand::place) {
+            state.insert(place.local.index());
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! `dominators.rs` - which blocks control always goes through to get to a block.
+//!
+//! Computed with "A Simple, Fast Dominance Algorithm" by Cooper, Harvey and Kennedy.
+use crate::{BlockId, Body};
+
+/// The dominator tree of a body. Blocks that cannot be reached from the start have no dominators.
+#[derive(Debug, Clone)]
+pub struct Dominators {
+    /// The reachable blocks, every block after its predecessors except along back edges
+    reverse_postorder: Vec<BlockId>,
+    /// The immediate dominator of every block, the start block being its own
+    idom: Vec<Option<BlockId>>,
+}
+
+impl Dominators {
+    #[must_use]
+    pub fn compute(body: &Body) -> Self {
+        let reverse_postorder = reverse_postorder(body);
+        let mut order = vec![usize::MAX; body.blocks.len()];
+        for (index, block) in reverse_postorder.iter().enumerate() {
+            order[block.0] = index;
+        }
+
+        let predecessors = body.predecessors();
+        let mut idom = vec![None; body.blocks.len()];
+        idom[BlockId::START.0] = Some(BlockId::START);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in reverse_postorder.iter().skip(1) {
+                let mut new_idom = None;
+                for &predecessor in &predecessors[block.0] {
+                    if idom[predecessor.0].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(current) => intersect(&idom, &order, predecessor, current),
+                    });
+                }
+                if new_idom.is_some() && idom[block.0] != new_idom {
+                    idom[block.0] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        Self { reverse_postorder, idom }
+    }
+
+    /// The blocks reachable from the start, each one after its dominators.
+    #[must_use]
+    pub fn reverse_postorder(&self) -> &[BlockId] {
+        &self.reverse_postorder
+    }
+
+    #[must_use]
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idom[block.0].is_some()
+    }
+
+    /// The immediate dominator of `block`, `None` for the start block and blocks that cannot be reached.
+    #[must_use]
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.idom[block.0].filter(|_| block != BlockId::START)
+    }
+
+    /// Whether every path from the start to `block` goes through `dominator`. Every block dominates itself.
+    #[must_use]
+    pub fn dominates(&self, dominator: BlockId, block: BlockId) -> bool {
+        if !self.is_reachable(block) {
+            return false;
+        }
+        let mut current = block;
+        loop {
+            if current == dominator {
+                return true;
+            }
+            match self.immediate_dominator(current) {
+                Some(idom) => current = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// The blocks `block` immediately dominates, in reverse postorder.
+    #[must_use]
+    pub fn children(&self, block: BlockId) -> Vec<BlockId> {
+        self.reverse_postorder.iter().copied().filter(|&child| self.immediate_dominator(child) == Some(block)).collect()
+    }
+
+    /// The dominance frontier of every block: the blocks where what it dominates meets what it does not.
+    #[must_use]
+    pub fn frontiers(&self, body: &Body) -> Vec<Vec<BlockId>> {
+        let mut frontiers = vec![Vec::new(); body.blocks.len()];
+        for (block, predecessors) in body.predecessors().into_iter().enumerate() {
+            let block = BlockId(block);
+            if predecessors.len() < 2 || !self.is_reachable(block) {
+                continue;
+            }
+            let Some(idom) = self.idom[block.0] else { continue };
+            for predecessor in predecessors {
+                let mut runner = predecessor;
+                while self.is_reachable(runner) && runner != idom {
+                    let frontier: &mut Vec<BlockId> = &mut frontiers[runner.0];
+                    if !frontier.contains(&block) {
+                        frontier.push(block);
+                    }
+                    match self.immediate_dominator(runner) {
+                        Some(next) => runner = next,
+                        None => break,
+                    }
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+fn intersect(idom: &[Option<BlockId>], order: &[usize], mut a: BlockId, mut b: BlockId) -> BlockId {
+    while a != b {
+        while order[a.0] > order[b.0] {
+            a = idom[a.0].expect("processed blocks have a dominator");
+        }
+        while order[b.0] > order[a.0] {
+            b = idom[b.0].expect("processed blocks have a dominator");
+        }
+    }
+    a
+}
+
+fn reverse_postorder(body: &Body) -> Vec<BlockId> {
+    let mut visited = vec![false; body.blocks.len()];
+    let mut postorder = Vec::new();
+    // every block on the stack comes with the number of successors visited, the last one first so that
+    // the first successor comes first in reverse postorder
+    let mut stack = vec![(BlockId::START, 0)];
+    visited[BlockId::START.0] = true;
+    while let Some((block, visited_successors)) = stack.last_mut() {
+        let successors = body.successors(*block);
+        if let Some(&successor) = successors.iter().rev().nth(*visited_successors) {
+            *visited_successors += 1;
+            if !visited[successor.0] {
+                visited[successor.0] = true;
+                stack.push((successor, 0));
+            }
+        } else {
+            postorder.push(*block);
+            stack.pop();
+        }
+    }
+    postorder.reverse();
+    postorder
+}
@@ -0,0 +1,556 @@
+//! `drops.rs` - inserts the calls of the `Drop` impls that run when values are destroyed.
+//!
+//! A value owned by a local is dropped when the local goes out of scope, at its `StorageDead`, and when the
+//! local is assigned a new value, once the new value is evaluated, unless it was moved out of before.
+//! Dropping a value calls the `drop` method of its `Drop` impl, then drops its fields in the order they are declared:
+//!
+//! ```text
+//! _4 = Guard::drop(move _1) -> bb3;
+//! ```
+//!
+//! If a value is moved out of on some paths only, a drop flag (a hidden `bool` local) tracks whether it still has
+//! to be dropped, and the calls are only made if it is set. If a field is moved out of, the other fields are
+//! dropped one by one. The parameters that are not `move` borrow their argument, which the caller drops.
+use crate::dataflow::{Analysis, BitSet, Results};
+use crate::{
+    BasicBlock, BlockId, Body, ConstValue, Constant, Local, LocalDecl, Location, MirGem, Operand, Phase, Place, Rvalue,
+    Statement, StatementKind, Terminator, TerminatorKind,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use topaz_ast::location::Span;
+use topaz_hir::Callee;
+use topaz_resolve::def::DefId;
+use topaz_type_checker::traits::ImplId;
+use topaz_type_checker::ty::Ty;
+
+/// A `Drop` impl that runs when a value is dropped, on the value itself or on a field of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropCall {
+    /// The field the impl runs on, empty for the value itself
+    pub projection: Vec<usize>,
+    pub impl_id: ImplId,
+    /// The type of the field
+    pub ty: Ty,
+}
+
+impl MirGem {
+    /// The impl of `Drop` of the struct or enum `ty`, unless only the blanket impl of `std` applies.
+    #[must_use]
+    pub fn drop_impl(&self, ty: &Ty) -> Option<ImplId> {
+        match ty {
+            Ty::Struct { def, .. } => self.struct_def(*def)?.drop,
+            Ty::Enum { def, .. } => self.enum_def(*def)?.drop,
+            _ => None,
+        }
+    }
+
+    /// Whether dropping a value of type `ty` runs any `Drop` impl.
+    #[must_use]
+    pub fn needs_drop(&self, ty: &Ty) -> bool {
+        !self.drop_glue(ty).is_empty()
+    }
+
+    /// Every `Drop` impl dropping a value of type `ty` runs, in order:
+    /// the impl of the value itself, then those of its fields in the order they are declared.
+    #[must_use]
+    pub fn drop_glue(&self, ty: &Ty) -> Vec<DropCall> {
+        let mut calls = Vec::new();
+        self.collect_glue(ty, &mut Vec::new(), &mut Vec::new(), &mut calls);
+        calls
+    }
+
+    fn collect_glue(&self, ty: &Ty, projection: &mut Vec<usize>, visiting: &mut Vec<DefId>, calls: &mut Vec<DropCall>) {
+        if self.is_copy(ty) {
+            return;
+        }
+        if let Some(impl_id) = self.drop_impl(ty) {
+            calls.push(DropCall { projection: projection.clone(), impl_id, ty: ty.clone() });
+        }
+        // which fields an enum has depends on its variant, so only its own impl runs for now
+        let Ty::Struct { def, .. } = ty else { return };
+        let Some(struct_def) = self.struct_def(*def) else { return };
+        // a struct containing itself can never be built
+        if visiting.contains(def) {
+            return;
+        }
+        visiting.push(*def);
+        for (index, field) in struct_def.fields.iter().enumerate() {
+            projection.push(index);
+            self.collect_glue(&field.ty, projection, visiting, calls);
+            projection.pop();
+        }
+        visiting.pop();
+    }
+}
+
+/// Inserts the drops of every body of `gem`, which are in [`Phase::Built`].
+pub fn elaborate_gem(gem: &mut MirGem) {
+    let mut bodies = std::mem::take(&mut gem.bodies);
+    for body in &mut bodies {
+        *body = elaborate(gem, body);
+    }
+    gem.bodies = bodies;
+}
+
+/// `body`, a body of `gem` in [`Phase::Built`], with its drops, see the module documentation.
+/// The blocks keep their index, the ones that are split continue in new blocks after them.
+#[must_use]
+pub fn elaborate(gem: &MirGem, body: &Body) -> Body {
+    debug_assert_eq!(body.phase, Phase::Built, "the drops are elaborated before SSA form");
+    let elaborator = Elaborator { gem, body, paths: MovePaths::collect(gem, body) };
+    elaborator.elaborate()
+}
+
+/// The places whose initialization is tracked: every local, and every field that is moved out of.
+struct MovePaths {
+    /// The locals first, by index
+    paths: Vec<Place>,
+}
+
+impl MovePaths {
+    fn collect(gem: &MirGem, body: &Body) -> Self {
+        let mut paths: Vec<Place> =
+            (0..body.locals.len()).map(|index| Local(u32::try_from(index).expect("too many locals")).into()).collect();
+        for block in &body.blocks {
+            let statements = block.statements.iter().flat_map(|statement| moved_by_statement(gem, body, statement));
+            for place in statements.chain(moved_by_terminator(&block.terminator)) {
+                if !place.projection.is_empty() && !paths.contains(place) {
+                    paths.push(place.clone());
+                }
+            }
+        }
+        Self { paths }
+    }
+
+    /// The paths `place` contains, including itself.
+    fn within<'a>(&'a self, place: &'a Place) -> impl Iterator<Item = usize> + 'a {
+        (0..self.paths.len()).filter(move |&path| place.contains(&self.paths[path]))
+    }
+
+    /// The innermost path containing `place`.
+    fn enclosing(&self, place: &Place) -> usize {
+        (0..self.paths.len())
+            .filter(|&path| self.paths[path].contains(place))
+            .max_by_key(|&path| self.paths[path].projection.len())
+            .unwrap_or(place.local.index())
+    }
+}
+
+/// The places `statement` moves out of. Taking a field of a variant that is not `Copy` moves out of the
+/// whole enum, which can only be dropped as a whole.
+pub(crate) fn moved_by_statement<'a>(gem: &MirGem, body: &Body, statement: &'a Statement) -> Vec<&'a Place> {
+    let StatementKind::Assign(_, rvalue) = &statement.kind else { return Vec::new() };
+    match rvalue {
+        Rvalue::Payload { operand, variant, field } => {
+            let Some(place) = operand.place() else { return Vec::new() };
+            let ty = gem
+                .enum_of(&gem.place_ty(body, place))
+                .and_then(|enum_def| enum_def.variants.get(*variant)?.fields.get(*field));
+            if ty.map_or(false, |ty| !gem.is_copy(ty)) {
+                vec![place]
+            } else {
+                Vec::new()
+            }
+        }
+        _ => rvalue.operands().iter().filter_map(moved).collect(),
+    }
+}
+
+pub(crate) fn moved_by_terminator(terminator: &Terminator) -> Vec<&Place> {
+    match &terminator.kind {
+        TerminatorKind::Call { args, .. } => args.iter().filter_map(moved).collect(),
+        _ => Vec::new(),
+    }
+}
+
+const fn moved(operand: &Operand) -> Option<&Place> {
+    match operand {
+        Operand::Move(place) => Some(place),
+        Operand::Copy(_) | Operand::Const(_) => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InitState {
+    maybe_init: BitSet,
+    maybe_uninit: BitSet,
+}
+
+impl InitState {
+    fn init(&mut self, path: usize) {
+        self.maybe_init.insert(path);
+        self.maybe_uninit.remove(path);
+    }
+
+    fn uninit(&mut self, path: usize) {
+        self.maybe_init.remove(path);
+        self.maybe_uninit.insert(path);
+    }
+
+    fn apply_moves<'a>(&mut self, paths: &MovePaths, moved: impl IntoIterator<Item = &'a Place>) {
+        for place in moved {
+            for path in paths.within(place) {
+                self.uninit(path);
+            }
+        }
+    }
+
+    fn apply_init(&mut self, paths: &MovePaths, place: &Place) {
+        for path in paths.within(place) {
+            self.init(path);
+        }
+    }
+}
+
+/// Which move paths may be initialized, and which may not be.
+struct Init<'a> {
+    gem: &'a MirGem,
+    body: &'a Body,
+    paths: &'a MovePaths,
+}
+
+impl Analysis for Init<'_> {
+    type Domain = InitState;
+
+    fn bottom(&self, _: &Body) -> InitState {
+        InitState { maybe_init: BitSet::new(self.paths.paths.len()), maybe_uninit: BitSet::new(self.paths.paths.len()) }
+    }
+
+    fn initialize(&self, body: &Body, state: &mut InitState) {
+        for path in 0..self.paths.paths.len() {
+            if (1..=body.arg_count).contains(&self.paths.paths[path].local.index()) {
+                state.init(path);
+            } else {
+                state.uninit(path);
+            }
+        }
+    }
+
+    fn join(&self, state: &mut InitState, other: &InitState) -> bool {
+        let init = state.maybe_init.union(&other.maybe_init);
+        let uninit = state.maybe_uninit.union(&other.maybe_uninit);
+        init || uninit
+    }
+
+    fn statement_effect(&self, state: &mut InitState, statement: &Statement, _: Location) {
+        match &statement.kind {
+            StatementKind::Assign(place, _) => {
+                state.apply_moves(self.paths, moved_by_statement(self.gem, self.body, statement));
+                state.apply_init(self.paths, place);
+            }
+            StatementKind::StorageDead(local) => state.apply_moves(self.paths, [&Place::from(*local)]),
+            StatementKind::Nop => {}
+        }
+    }
+
+    fn terminator_effect(&self, state: &mut InitState, terminator: &Terminator, _: Location) {
+        if let TerminatorKind::Call { dest, .. } = &terminator.kind {
+            state.apply_moves(self.paths, moved_by_terminator(terminator));
+            state.apply_init(self.paths, dest);
+        }
+    }
+}
+
+/// A drop to insert, checking the flag of the move path `flagged` if there is one.
+struct PlannedDrop {
+    place: Place,
+    flagged: Option<usize>,
+}
+
+struct Elaborator<'a> {
+    gem: &'a MirGem,
+    body: &'a Body,
+    paths: MovePaths,
+}
+
+impl Elaborator<'_> {
+    /// Whether what `local` owns is dropped in this body: a parameter that is not `move` is the caller's to
+    /// drop, and the `drop` method of a `Drop` impl does not drop the value it is dropping.
+    fn drops_local(&self, local: Local) -> bool {
+        if self.body.local(local).borrowed {
+            return false;
+        }
+        let is_drop = |impl_id| {
+            self.gem.structs.iter().any(|struct_def| struct_def.drop == Some(impl_id))
+                || self.gem.enums.iter().any(|enum_def| enum_def.drop == Some(impl_id))
+        };
+        let in_drop = self.body.name == "drop" && self.body.impl_id.map_or(false, is_drop);
+        !(in_drop && local.index() == 1)
+    }
+
+    /// The drops of what `place` holds, given what may be initialized.
+    fn plan(&self, place: Place, state: &InitState, drops: &mut Vec<PlannedDrop>) {
+        if !self.drops_local(place.local) {
+            return;
+        }
+        let ty = self.gem.place_ty(self.body, &place);
+        if !self.gem.needs_drop(&ty) {
+            return;
+        }
+        let path = self.paths.enclosing(&place);
+        if !state.maybe_init.contains(path) {
+            return;
+        }
+        let moved_from = self.paths.within(&place).any(|inner| {
+            self.paths.paths[inner].projection.len() > place.projection.len() && state.maybe_uninit.contains(inner)
+        });
+        if moved_from {
+            if let Ty::Struct { def, .. } = &ty {
+                let fields = self.gem.struct_def(*def).map_or(0, |struct_def| struct_def.fields.len());
+                for field in 0..fields {
+                    let mut field_place = place.clone();
+                    field_place.projection.push(field);
+                    self.plan(field_place, state, drops);
+                }
+                return;
+            }
+        }
+        drops.push(PlannedDrop { place, flagged: state.maybe_uninit.contains(path).then_some(path) });
+    }
+
+    /// The drops right before the statement or terminator at `location`, given what may be initialized before it.
+    fn drops_at(&self, location: Location, state: &InitState) -> Vec<PlannedDrop> {
+        let data = self.body.block(location.block);
+        let mut drops = Vec::new();
+        match data.statements.get(location.statement) {
+            Some(Statement { kind: StatementKind::StorageDead(local), .. }) => {
+                self.plan((*local).into(), state, &mut drops);
+            }
+            // the old value is dropped once the new one is evaluated
+            Some(statement @ Statement { kind: StatementKind::Assign(place, _), .. }) => {
+                let mut state = state.clone();
+                state.apply_moves(&self.paths, moved_by_statement(self.gem, self.body, statement));
+                self.plan(place.clone(), &state, &mut drops);
+            }
+            Some(Statement { kind: StatementKind::Nop, .. }) => {}
+            None => {
+                if let TerminatorKind::Call { dest, .. } = &data.terminator.kind {
+                    let mut state = state.clone();
+                    state.apply_moves(&self.paths, moved_by_terminator(&data.terminator));
+                    self.plan(dest.clone(), &state, &mut drops);
+                }
+            }
+        }
+        drops
+    }
+
+    fn elaborate(self) -> Body {
+        let states = Results::compute(Init { gem: self.gem, body: self.body, paths: &self.paths }, self.body)
+            .all_states(self.body);
+        let mut planned = HashMap::new();
+        for block in self.body.block_ids() {
+            for statement in 0..=self.body.block(block).statements.len() {
+                let location = Location { block, statement };
+                planned.insert(location, self.drops_at(location, &states[block.0][statement]));
+            }
+        }
+
+        let mut patch = Patch {
+            gem: self.gem,
+            body: self.body.clone(),
+            blocks: vec![(Vec::new(), None); self.body.blocks.len()],
+            current: BlockId::START,
+            flags: BTreeMap::new(),
+        };
+        // one flag per move path, numbered in the order of the paths
+        let flagged: BTreeSet<_> = planned.values().flatten().filter_map(|drop| drop.flagged).collect();
+        for path in flagged {
+            let span = self.body.local(self.paths.paths[path].local).span;
+            let flag =
+                patch.body.push_local(LocalDecl { name: None, ty: Ty::Bool, mutable: true, borrowed: false, span });
+            patch.flags.insert(path, flag);
+        }
+
+        // the parameters are initialized when the function starts, everything else is not
+        for (&path, &flag) in &patch.flags.clone() {
+            let local = self.paths.paths[path].local;
+            let arg = (1..=self.body.arg_count).contains(&local.index());
+            patch.set_flag(flag, arg, self.body.local(local).span);
+        }
+
+        for block in self.body.block_ids() {
+            if block != BlockId::START {
+                patch.current = block;
+            }
+            let data = self.body.block(block);
+            for (index, statement) in data.statements.iter().enumerate() {
+                let drops = &planned[&Location { block, statement: index }];
+                self.elaborate_statement(&mut patch, statement, drops);
+            }
+            let drops = &planned[&Location { block, statement: data.statements.len() }];
+            self.elaborate_terminator(&mut patch, &data.terminator, drops);
+        }
+
+        let mut body = patch.body;
+        body.blocks = patch
+            .blocks
+            .into_iter()
+            .map(|(statements, terminator)| BasicBlock {
+                phis: Vec::new(),
+                statements,
+                terminator: terminator.expect("every block is terminated"),
+            })
+            .collect();
+        body
+    }
+
+    fn elaborate_statement(&self, patch: &mut Patch, statement: &Statement, drops: &[PlannedDrop]) {
+        let moved = moved_by_statement(self.gem, self.body, statement);
+        match &statement.kind {
+            StatementKind::Assign(place, rvalue) if !drops.is_empty() => {
+                // the new value is evaluated into a temporary, so that the old one can be dropped before it is stored
+                let ty = self.gem.place_ty(self.body, place);
+                let temp = patch.body.push_local(LocalDecl {
+                    name: None,
+                    ty,
+                    mutable: false,
+                    borrowed: false,
+                    span: statement.span,
+                });
+                patch.push(StatementKind::Assign(temp.into(), rvalue.clone()), statement.span);
+                self.clear_flags(patch, &moved, Some(place), statement.span);
+                for drop in drops {
+                    patch.drop(drop, statement.span);
+                }
+                patch.push(
+                    StatementKind::Assign(place.clone(), Rvalue::Use(Operand::Move(temp.into()))),
+                    statement.span,
+                );
+                self.set_flags(patch, place, statement.span);
+            }
+            StatementKind::Assign(place, _) => {
+                patch.push(statement.kind.clone(), statement.span);
+                self.clear_flags(patch, &moved, Some(place), statement.span);
+                self.set_flags(patch, place, statement.span);
+            }
+            StatementKind::StorageDead(local) => {
+                for drop in drops {
+                    patch.drop(drop, statement.span);
+                }
+                patch.push(statement.kind.clone(), statement.span);
+                self.clear_flags(patch, &[&Place::from(*local)], None, statement.span);
+            }
+            StatementKind::Nop => patch.push(statement.kind.clone(), statement.span),
+        }
+    }
+
+    fn elaborate_terminator(&self, patch: &mut Patch, terminator: &Terminator, drops: &[PlannedDrop]) {
+        let span = terminator.span;
+        let TerminatorKind::Call { callee, args, arg_spans, dest, target } = &terminator.kind else {
+            patch.terminate(terminator.kind.clone(), span);
+            return;
+        };
+        // the arguments are moved when calling, what the call returns is stored once it returns
+        self.clear_flags(patch, &moved_by_terminator(terminator), Some(dest), span);
+        let sets_flags = self.paths.within(dest).any(|path| patch.flags.contains_key(&path));
+        if drops.is_empty() && !sets_flags {
+            patch.terminate(terminator.kind.clone(), span);
+            return;
+        }
+
+        let returned = if drops.is_empty() {
+            dest.clone()
+        } else {
+            let ty = self.gem.place_ty(self.body, dest);
+            patch.body.push_local(LocalDecl { name: None, ty, mutable: false, borrowed: false, span }).into()
+        };
+        let landing = patch.new_block();
+        let call = TerminatorKind::Call {
+            callee: callee.clone(),
+            args: args.clone(),
+            arg_spans: arg_spans.clone(),
+            dest: returned.clone(),
+            target: landing,
+        };
+        patch.terminate(call, span);
+        patch.current = landing;
+        if !drops.is_empty() {
+            for drop in drops {
+                patch.drop(drop, span);
+            }
+            patch.push(StatementKind::Assign(dest.clone(), Rvalue::Use(Operand::Move(returned))), span);
+        }
+        self.set_flags(patch, dest, span);
+        patch.terminate(TerminatorKind::Goto(*target), span);
+    }
+
+    /// Clears the flags of the paths within `moved`, but the ones within `assigned`, which are set right after.
+    fn clear_flags(&self, patch: &mut Patch, moved: &[&Place], assigned: Option<&Place>, span: Span) {
+        for place in moved {
+            for path in self.paths.within(place) {
+                let reassigned = assigned.map_or(false, |assigned| assigned.contains(&self.paths.paths[path]));
+                if let (Some(&flag), false) = (patch.flags.get(&path), reassigned) {
+                    patch.set_flag(flag, false, span);
+                }
+            }
+        }
+    }
+
+    fn set_flags(&self, patch: &mut Patch, assigned: &Place, span: Span) {
+        for path in self.paths.within(assigned) {
+            if let Some(&flag) = patch.flags.get(&path) {
+                patch.set_flag(flag, true, span);
+            }
+        }
+    }
+}
+
+/// The blocks of a body being elaborated, which are split where calls are inserted.
+struct Patch<'a> {
+    gem: &'a MirGem,
+    /// The body, with the locals declared so far
+    body: Body,
+    blocks: Vec<(Vec<Statement>, Option<Terminator>)>,
+    /// The block statements are added to
+    current: BlockId,
+    /// The drop flag of every move path that needs one
+    flags: BTreeMap<usize, Local>,
+}
+
+impl Patch<'_> {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((Vec::new(), None));
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn push(&mut self, kind: StatementKind, span: Span) {
+        self.blocks[self.current.0].0.push(Statement { kind, span });
+    }
+
+    fn terminate(&mut self, kind: TerminatorKind, span: Span) {
+        self.blocks[self.current.0].1 = Some(Terminator { kind, span });
+    }
+
+    fn set_flag(&mut self, flag: Local, value: bool, span: Span) {
+        let value = Operand::Const(Constant { value: ConstValue::Bool(value), ty: Ty::Bool });
+        self.push(StatementKind::Assign(flag.into(), Rvalue::Use(value)), span);
+    }
+
+    /// Calls every `Drop` impl dropping `drop` runs, if its flag is set, continuing in a new block.
+    fn drop(&mut self, drop: &PlannedDrop, span: Span) {
+        let join = drop.flagged.map(|path| {
+            let (then, join) = (self.new_block(), self.new_block());
+            let cond = Operand::Copy(self.flags[&path].into());
+            self.terminate(TerminatorKind::If { cond, then, otherwise: join }, span);
+            self.current = then;
+            join
+        });
+        let ty = self.gem.place_ty(&self.body, &drop.place);
+        for DropCall { projection, impl_id, ty } in self.gem.drop_glue(&ty) {
+            let mut place = drop.place.clone();
+            place.projection.extend(projection);
+            let dest =
+                self.body.push_local(LocalDecl { name: None, ty: Ty::Void, mutable: false, borrowed: false, span });
+            let target = self.new_block();
+            let callee = Callee::Method { impl_id, name: format!("{ty}::drop") };
+            let args = vec![Operand::Move(place)];
+            let call = TerminatorKind::Call { callee, args, arg_spans: vec![span], dest: dest.into(), target };
+            self.terminate(call, span);
+            self.current = target;
+        }
+        if let Some(join) = join {
+            self.terminate(TerminatorKind::Goto(join), span);
+            self.current = join;
+        }
+    }
+}
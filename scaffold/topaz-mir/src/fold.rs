@@ -0,0 +1,71 @@
+//! `fold.rs` - the operators computed on constants, the way a program computes them, for the passes folding
+//! them and for the interpreter.
+//!
+//! Integers are `i128`s, a `uexplod` keeping its bits. `usize` and `isize` are as wide as a pointer of the
+//! machine the program runs on, which is given.
+use crate::BinOp;
+use std::cmp::Ordering;
+use topaz_type_checker::ty::IntTy;
+
+/// Whether `value` can be a value of `ty`: whether it is between the smallest and largest value of the type,
+/// where `usize` and `isize` have `ptr_bits` bits.
+#[must_use]
+pub fn fits(value: i128, ty: IntTy, ptr_bits: u32) -> bool {
+    // a `uexplod` keeps its bits, so every `i128` is one
+    if ty == IntTy::Uexplod {
+        return true;
+    }
+    let (min, max) = ty.range(ptr_bits);
+    value >= min && (value < 0 || value.unsigned_abs() <= max)
+}
+
+/// `lhs op rhs` for the arithmetic `op` on integers of type `ty`, `None` if it overflows the type or divides
+/// by zero.
+///
+/// # Panics
+/// If `op` is a comparison.
+#[must_use]
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
+pub fn arithmetic(op: BinOp, lhs: i128, rhs: i128, ty: IntTy, ptr_bits: u32) -> Option<i128> {
+    if ty == IntTy::Uexplod {
+        let (lhs, rhs) = (lhs as u128, rhs as u128);
+        let value = match op {
+            BinOp::Add => lhs.checked_add(rhs),
+            BinOp::Sub => lhs.checked_sub(rhs),
+            BinOp::Mul => lhs.checked_mul(rhs),
+            BinOp::Div => lhs.checked_div(rhs),
+            BinOp::Rem => lhs.checked_rem(rhs),
+            _ => unreachable!("`{}` is a comparison", op.symbol()),
+        };
+        return value.map(|value| value as i128);
+    }
+    let value = match op {
+        BinOp::Add => lhs.checked_add(rhs),
+        BinOp::Sub => lhs.checked_sub(rhs),
+        BinOp::Mul => lhs.checked_mul(rhs),
+        BinOp::Div => lhs.checked_div(rhs),
+        // the remainder of the smallest `explod` by `-1` is `0` too
+        BinOp::Rem if rhs == -1 => Some(0),
+        BinOp::Rem => lhs.checked_rem(rhs),
+        _ => unreachable!("`{}` is a comparison", op.symbol()),
+    };
+    value.filter(|value| fits(*value, ty, ptr_bits))
+}
+
+/// `lhs op rhs` for the comparison `op`.
+///
+/// # Panics
+/// If `op` is arithmetic.
+#[must_use]
+pub fn compare<T: Ord>(op: BinOp, lhs: &T, rhs: &T) -> bool {
+    let ordering = lhs.cmp(rhs);
+    match op {
+        BinOp::Eq => ordering == Ordering::Equal,
+        BinOp::Ne => ordering != Ordering::Equal,
+        BinOp::Lt => ordering == Ordering::Less,
+        BinOp::Le => ordering != Ordering::Greater,
+        BinOp::Gt => ordering == Ordering::Greater,
+        BinOp::Ge => ordering != Ordering::Less,
+        _ => unreachable!("`{}` is not a comparison", op.symbol()),
+    }
+}
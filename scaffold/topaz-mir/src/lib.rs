@@ -0,0 +1,628 @@
+//! The mid-level intermediate representation: every function as a control-flow graph of basic blocks,
+//! each a list of statements doing one thing to one place, ended by a terminator jumping to the next blocks.
+//!
+//! The MIR of a gem is built from its HIR by [`build::build_gem`], which inserts the calls of the `Drop` impls
+//! that run when values are destroyed with [`drops::elaborate`], then brought into SSA form by
+//! [`ssa::construct`], and then optimized by the passes of [`transform`].
+//! [`validate::validate_gem`] checks that it is well-formed after every step, and [`borrowck::BorrowCheck`]
+//! checks the borrows, moves and initialization of the bodies as built.
+//! It is printed in the textual format of `topaz build --emit=mir`:
+//!
+//! ```text
+//! fn pick(_1: bool) -> int {
+//!     debug flag => _1;
+//!     let mut _0: int;
+//!
+//!     bb0: {
+//!         if copy _1 -> [true: bb1, false: bb2];
+//!     }
+//!     ...
+//! }
+//! ```
+use std::collections::HashSet;
+use topaz_ast::location::Span;
+use topaz_hir::Callee;
+use topaz_resolve::def::DefId;
+use topaz_type_checker::traits::ImplId;
+use topaz_type_checker::ty::Ty;
+
+pub mod borrowck;
+pub mod build;
+pub mod dataflow;
+pub mod dominators;
+pub mod drops;
+pub mod fold;
+mod pretty;
+pub mod ssa;
+pub mod transform;
+pub mod validate;
+
+use dataflow::{MaybeUnassigned, Results};
+use dominators::Dominators;
+pub use topaz_hir::{BinOp, Enum, Struct, UnOp};
+
+/// Every function of a gem, and the structs and enums they use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirGem {
+    pub bodies: Vec<Body>,
+    pub structs: Vec<Struct>,
+    pub enums: Vec<Enum>,
+}
+
+impl MirGem {
+    /// The body of the function defined by `def`.
+    #[must_use]
+    pub fn body(&self, def: DefId) -> Option<&Body> {
+        self.bodies.iter().find(|body| body.def == Some(def))
+    }
+
+    /// The index in [`MirGem::bodies`] of the body `callee` calls, if it is in the gem.
+    #[must_use]
+    pub fn callee_index(&self, callee: &Callee) -> Option<usize> {
+        match callee {
+            Callee::Fn { def, .. } => self.bodies.iter().position(|body| body.def == Some(*def)),
+            Callee::Method { impl_id, name } => self.bodies.iter().position(|body| {
+                body.impl_id == Some(*impl_id) && name.rsplit("::").next() == Some(body.name.as_str())
+            }),
+            Callee::External(_) => None,
+        }
+    }
+
+    /// The body `callee` calls, if it is in the gem.
+    #[must_use]
+    pub fn callee(&self, callee: &Callee) -> Option<&Body> {
+        self.callee_index(callee).map(|index| &self.bodies[index])
+    }
+
+    /// The `main` function of the module in the file `file_id`, which a program starts with.
+    #[must_use]
+    pub fn entry_point(&self, file_id: usize) -> Option<&Body> {
+        self.bodies.iter().find(|body| body.name == "main" && body.def.is_some() && body.file_id == file_id)
+    }
+
+    #[must_use]
+    pub fn struct_def(&self, def: DefId) -> Option<&Struct> {
+        self.structs.iter().find(|struct_def| struct_def.def == def)
+    }
+
+    #[must_use]
+    pub fn enum_def(&self, def: DefId) -> Option<&Enum> {
+        self.enums.iter().find(|enum_def| enum_def.def == def)
+    }
+
+    /// The enum `ty` is, if it is one.
+    #[must_use]
+    pub fn enum_of(&self, ty: &Ty) -> Option<&Enum> {
+        match ty {
+            Ty::Enum { def, .. } => self.enum_def(*def),
+            _ => None,
+        }
+    }
+
+    /// Whether using a value of type `ty` copies it, instead of moving it.
+    #[must_use]
+    pub fn is_copy(&self, ty: &Ty) -> bool {
+        topaz_hir::is_copy(&self.structs, &self.enums, ty)
+    }
+
+    /// `place` of `body` as written in the source, e.g. `pair.first`, or `_3.0` for a field of a temporary.
+    #[must_use]
+    pub fn place_name(&self, body: &Body, place: &Place) -> String {
+        let decl = body.local(place.local);
+        let mut name = decl.name.clone().unwrap_or_else(|| place.local.to_string());
+        let mut ty = decl.ty.clone();
+        for &index in &place.projection {
+            let field = match ty.referent() {
+                Ty::Struct { def, .. } => self.struct_def(*def).and_then(|struct_def| struct_def.fields.get(index)),
+                _ => None,
+            };
+            match field {
+                Some(field) => {
+                    name = format!("{name}.{}", field.name);
+                    ty = field.ty.clone();
+                }
+                None => {
+                    name = format!("{name}.{index}");
+                    ty = Ty::Error;
+                }
+            }
+        }
+        name
+    }
+
+    /// The type of `place` in `body`.
+    #[must_use]
+    pub fn place_ty(&self, body: &Body, place: &Place) -> Ty {
+        place.projection.iter().fold(body.local(place.local).ty.clone(), |ty, field| match ty.referent() {
+            Ty::Struct { def, .. } => self
+                .struct_def(*def)
+                .and_then(|struct_def| struct_def.fields.get(*field))
+                .map_or(Ty::Error, |field| field.ty.clone()),
+            _ => Ty::Error,
+        })
+    }
+
+    /// Whether `place` is reached through references, and if so whether they are all `&mut`, which it can
+    /// only be borrowed mutably through.
+    #[must_use]
+    pub fn behind_ref(&self, body: &Body, place: &Place) -> Option<bool> {
+        let mut behind = None;
+        for depth in 0..place.projection.len() {
+            let base = Place { local: place.local, projection: place.projection[..depth].to_vec() };
+            let mut ty = self.place_ty(body, &base);
+            while let Ty::Ref { mutable, ty: pointee } = ty.peel() {
+                behind = Some(behind.unwrap_or(true) && *mutable);
+                ty = (**pointee).clone();
+            }
+        }
+        behind
+    }
+}
+
+/// What has been done to a body so far, which decides what the validator expects of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// As built from the HIR: a local can be assigned any number of times, there are no phis
+    Built,
+    /// Every local but the ones borrowed is assigned exactly once, see [`ssa`]
+    Ssa,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Local(pub u32);
+
+impl Local {
+    /// Where the return value is written to
+    pub const RETURN: Self = Self(0);
+
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalDecl {
+    /// The binding or parameter the local is for, `None` for the return value and temporaries
+    pub name: Option<String>,
+    pub ty: Ty,
+    /// Whether it can be assigned more than once in [`Phase::Built`]
+    pub mutable: bool,
+    /// Whether it is a parameter that is not `move`, which borrows its argument, so the caller drops it
+    pub borrowed: bool,
+    pub span: Span,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+impl BlockId {
+    pub const START: Self = Self(0);
+}
+
+/// A point in a body: right before the statement `statement` of `block`, or before its terminator
+/// if there is no statement at that index.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    pub block: BlockId,
+    pub statement: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Body {
+    pub name: String,
+    /// `None` for methods
+    pub def: Option<DefId>,
+    /// The impl the function is a method of, if it is one
+    pub impl_id: Option<ImplId>,
+    pub file_id: usize,
+    pub span: Span,
+    /// The return value, the parameters, then the bindings and temporaries
+    pub locals: Vec<LocalDecl>,
+    pub arg_count: usize,
+    pub blocks: Vec<BasicBlock>,
+    pub phase: Phase,
+}
+
+impl Body {
+    #[must_use]
+    pub fn local(&self, local: Local) -> &LocalDecl {
+        &self.locals[local.index()]
+    }
+
+    /// The parameters, `_1` to `_n`.
+    pub fn args(&self) -> impl Iterator<Item = Local> {
+        (1..=self.arg_count).map(|index| Local(u32::try_from(index).expect("too many locals")))
+    }
+
+    #[must_use]
+    pub fn block(&self, block: BlockId) -> &BasicBlock {
+        &self.blocks[block.0]
+    }
+
+    pub fn block_ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len()).map(BlockId)
+    }
+
+    #[must_use]
+    pub fn successors(&self, block: BlockId) -> Vec<BlockId> {
+        self.block(block).terminator.kind.successors()
+    }
+
+    /// The predecessors of every block, in the order of the blocks.
+    #[must_use]
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for block in self.block_ids() {
+            for successor in self.successors(block) {
+                if !predecessors[successor.0].contains(&block) {
+                    predecessors[successor.0].push(block);
+                }
+            }
+        }
+        predecessors
+    }
+
+    /// The locals something borrows, which are assigned in place even in SSA form.
+    #[must_use]
+    pub fn borrowed_locals(&self) -> HashSet<Local> {
+        self.blocks
+            .iter()
+            .flat_map(|block| &block.statements)
+            .filter_map(|statement| match &statement.kind {
+                StatementKind::Assign(_, Rvalue::Ref { place, .. }) => Some(place.local),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The locals that may be read before they are assigned, which only a drop guarded by a drop flag does.
+    #[must_use]
+    pub fn maybe_unassigned_reads(&self) -> HashSet<Local> {
+        let states = Results::compute(MaybeUnassigned, self).all_states(self);
+        let mut reads = HashSet::new();
+        for block in self.block_ids() {
+            let data = self.block(block);
+            let statements = data.statements.iter().map(|statement| match &statement.kind {
+                StatementKind::Assign(_, rvalue) => rvalue.operands(),
+                StatementKind::StorageDead(_) | StatementKind::Nop => &[],
+            });
+            for (operands, state) in statements.chain([data.terminator.kind.operands()]).zip(&states[block.0]) {
+                let locals = operands.iter().filter_map(Operand::place).map(|place| place.local);
+                reads.extend(locals.filter(|local| state.contains(local.index())));
+            }
+        }
+        reads
+    }
+
+    /// The locals assigned in place even in SSA form: the return value, the borrowed locals
+    /// and the ones that may be read before they are assigned.
+    #[must_use]
+    pub fn in_place_locals(&self) -> HashSet<Local> {
+        let mut locals = self.borrowed_locals();
+        locals.extend(self.maybe_unassigned_reads());
+        locals.insert(Local::RETURN);
+        locals
+    }
+
+    /// Declares a new local.
+    pub fn push_local(&mut self, decl: LocalDecl) -> Local {
+        self.locals.push(decl);
+        Local(u32::try_from(self.locals.len() - 1).expect("too many locals"))
+    }
+
+    /// Removes the blocks that cannot be reached from the start, e.g. the ones after a `return`,
+    /// renumbering the others in the order they were in.
+    pub fn remove_unreachable_blocks(&mut self) {
+        let dominators = Dominators::compute(self);
+        let mut renumbered = vec![None; self.blocks.len()];
+        let mut next = 0;
+        for block in self.block_ids() {
+            if dominators.is_reachable(block) {
+                renumbered[block.0] = Some(BlockId(next));
+                next += 1;
+            }
+        }
+        if next == self.blocks.len() {
+            return;
+        }
+
+        let blocks = std::mem::take(&mut self.blocks);
+        self.blocks = blocks
+            .into_iter()
+            .zip(&renumbered)
+            .filter(|(_, renumbered)| renumbered.is_some())
+            .map(|(mut block, _)| {
+                for target in block.terminator.kind.successors_mut() {
+                    *target = renumbered[target.0].expect("the successors of a reachable block are reachable");
+                }
+                for phi in &mut block.phis {
+                    phi.args.retain(|(from, _)| renumbered[from.0].is_some());
+                    for (from, _) in &mut phi.args {
+                        *from = renumbered[from.0].expect("kept above");
+                    }
+                }
+                block
+            })
+            .collect();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Only in [`Phase::Ssa`], all run at once when entering the block
+    pub phis: Vec<Phi>,
+    pub statements: Vec<Statement>,
+    pub terminator: Terminator,
+}
+
+/// `dest = phi [bb1: a, bb2: b]`: takes the value of the operand of the block control came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phi {
+    pub dest: Local,
+    /// One operand for every predecessor of the block
+    pub args: Vec<(BlockId, Operand)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatementKind {
+    Assign(Place, Rvalue),
+    /// The local goes out of scope, which drops what it owns. Only in [`Phase::Built`]
+    StorageDead(Local),
+    /// A statement that was removed
+    Nop,
+}
+
+/// A local, or a field of one, e.g. `_1.0.2`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Place {
+    pub local: Local,
+    /// The indices of the fields, outermost first. The field of a reference is the field of what it refers to
+    pub projection: Vec<usize>,
+}
+
+impl From<Local> for Place {
+    fn from(local: Local) -> Self {
+        Self { local, projection: Vec::new() }
+    }
+}
+
+impl Place {
+    #[must_use]
+    pub fn as_local(&self) -> Option<Local> {
+        self.projection.is_empty().then_some(self.local)
+    }
+
+    /// Whether `other` is this place or one of its fields.
+    #[must_use]
+    pub fn contains(&self, other: &Self) -> bool {
+        self.local == other.local && other.projection.starts_with(&self.projection)
+    }
+
+    /// Whether using one of the places uses (a part of) the other.
+    #[must_use]
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.contains(other) || other.contains(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    /// Copies the value. A value that is not `Copy` is only copied to be passed to a parameter
+    /// that is not `move`, which borrows it, or to read its discriminant or the fields of its variant
+    Copy(Place),
+    Move(Place),
+    Const(Constant),
+}
+
+impl Operand {
+    #[must_use]
+    pub const fn place(&self) -> Option<&Place> {
+        match self {
+            Self::Copy(place) | Self::Move(place) => Some(place),
+            Self::Const(_) => None,
+        }
+    }
+
+    #[must_use]
+    pub fn place_mut(&mut self) -> Option<&mut Place> {
+        match self {
+            Self::Copy(place) | Self::Move(place) => Some(place),
+            Self::Const(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constant {
+    pub value: ConstValue,
+    pub ty: Ty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstValue {
+    Int(i128),
+    Bool(bool),
+    Char(char),
+    Str(String),
+    /// `()`
+    Void,
+    /// A constant by its path, which nothing knows the value of yet
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rvalue {
+    Use(Operand),
+    Ref {
+        mutable: bool,
+        place: Place,
+    },
+    /// A struct, with a value for every field
+    Aggregate {
+        def: DefId,
+        operands: Vec<Operand>,
+    },
+    /// The variant `index` of the enum `def`, with a value for every field of the variant
+    Variant {
+        def: DefId,
+        index: usize,
+        operands: Vec<Operand>,
+    },
+    /// The index of the variant of an enum
+    Discriminant(Operand),
+    /// The field `field` of an enum, which has to be of the variant `variant`
+    Payload {
+        operand: Operand,
+        variant: usize,
+        field: usize,
+    },
+    /// `[first, second]`
+    Array(Vec<Operand>),
+    /// `array[index]`, panicking if `index` is out of bounds
+    Index {
+        operands: [Operand; 2],
+    },
+    /// `lhs op rhs`, see [`BinOp`]
+    BinaryOp {
+        op: BinOp,
+        operands: [Operand; 2],
+    },
+    UnaryOp {
+        op: UnOp,
+        operand: Operand,
+    },
+}
+
+impl Rvalue {
+    /// The places it reads, including the place a [`Rvalue::Ref`] borrows.
+    #[must_use]
+    pub fn read_places(&self) -> Vec<&Place> {
+        match self {
+            Self::Ref { place, .. } => vec![place],
+            rvalue => rvalue.operands().iter().filter_map(Operand::place).collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn operands(&self) -> &[Operand] {
+        match self {
+            Self::Use(operand)
+            | Self::UnaryOp { operand, .. }
+            | Self::Discriminant(operand)
+            | Self::Payload { operand, .. } => std::slice::from_ref(operand),
+            Self::Aggregate { operands, .. } | Self::Variant { operands, .. } | Self::Array(operands) => operands,
+            Self::BinaryOp { operands, .. } | Self::Index { operands } => operands,
+            Self::Ref { .. } => &[],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> &mut [Operand] {
+        match self {
+            Self::Use(operand)
+            | Self::UnaryOp { operand, .. }
+            | Self::Discriminant(operand)
+            | Self::Payload { operand, .. } => std::slice::from_mut(operand),
+            Self::Aggregate { operands, .. } | Self::Variant { operands, .. } | Self::Array(operands) => operands,
+            Self::BinaryOp { operands, .. } | Self::Index { operands } => operands,
+            Self::Ref { .. } => &mut [],
+        }
+    }
+
+    /// Whether evaluating it can panic: arithmetic does on overflow, and on dividing by zero,
+    /// and indexing does out of bounds.
+    #[must_use]
+    pub const fn may_panic(&self) -> bool {
+        match self {
+            Self::BinaryOp { op, .. } => !op.is_comparison(),
+            Self::UnaryOp { op, .. } => matches!(op, UnOp::Neg),
+            Self::Index { .. } => true,
+            Self::Use(_)
+            | Self::Ref { .. }
+            | Self::Aggregate { .. }
+            | Self::Variant { .. }
+            | Self::Discriminant(_)
+            | Self::Payload { .. }
+            | Self::Array(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terminator {
+    pub kind: TerminatorKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminatorKind {
+    Goto(BlockId),
+    If {
+        cond: Operand,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    /// Goes to `targets[discr]`, where `discr` is the discriminant of an enum
+    Switch {
+        discr: Operand,
+        targets: Vec<BlockId>,
+    },
+    /// Calls `callee`, writes what it returns to `dest` and continues at `target`
+    Call {
+        callee: Callee,
+        args: Vec<Operand>,
+        /// The expression of every argument, for diagnostics
+        arg_spans: Vec<Span>,
+        dest: Place,
+        target: BlockId,
+    },
+    /// Returns `_0`
+    Return,
+    /// Where control never gets to, e.g. the end of a function that returns on every path
+    Unreachable,
+}
+
+impl TerminatorKind {
+    #[must_use]
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Self::Goto(target) | Self::Call { target, .. } => vec![*target],
+            Self::If { then, otherwise, .. } => vec![*then, *otherwise],
+            Self::Switch { targets, .. } => targets.clone(),
+            Self::Return | Self::Unreachable => Vec::new(),
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Self::Goto(target) | Self::Call { target, .. } => vec![target],
+            Self::If { then, otherwise, .. } => vec![then, otherwise],
+            Self::Switch { targets, .. } => targets.iter_mut().collect(),
+            Self::Return | Self::Unreachable => Vec::new(),
+        }
+    }
+
+    /// The operands the terminator reads.
+    #[must_use]
+    pub fn operands(&self) -> &[Operand] {
+        match self {
+            Self::If { cond, .. } | Self::Switch { discr: cond, .. } => std::slice::from_ref(cond),
+            Self::Call { args, .. } => args,
+            Self::Goto(_) | Self::Return | Self::Unreachable => &[],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> &mut [Operand] {
+        match self {
+            Self::If { cond, .. } | Self::Switch { discr: cond, .. } => std::slice::from_mut(cond),
+            Self::Call { args, .. } => args,
+            Self::Goto(_) | Self::Return | Self::Unreachable => &mut [],
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! `pretty.rs` - the textual format of the MIR, printed by `topaz build --emit=mir`.
+use crate::{
+    BasicBlock, BlockId, Body, ConstValue, Constant, Local, MirGem, Operand, Phi, Place, Rvalue, StatementKind,
+    TerminatorKind,
+};
+use std::fmt::{Display, Formatter, Result};
+use topaz_type_checker::ty::Ty;
+
+impl Display for Local {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "_{}", self.0)
+    }
+}
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl Display for Place {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.local)?;
+        for field in &self.projection {
+            write!(f, ".{field}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match &self.value {
+            ConstValue::Int(value) => write!(f, "const {value}{}", self.ty),
+            ConstValue::Bool(value) => write!(f, "const {value}"),
+            ConstValue::Char(value) => write!(f, "const {value:?}"),
+            ConstValue::Str(value) => write!(f, "const {value:?}"),
+            ConstValue::Void => write!(f, "const ()"),
+            ConstValue::Named(path) => write!(f, "const {path}"),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::Copy(place) => write!(f, "copy {place}"),
+            Self::Move(place) => write!(f, "move {place}"),
+            Self::Const(constant) => write!(f, "{constant}"),
+        }
+    }
+}
+
+impl Display for MirGem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for struct_def in &self.structs {
+            let fields: Vec<_> =
+                struct_def.fields.iter().map(|field| format!("{}: {}", field.name, field.ty)).collect();
+            writeln!(f, "struct {} {{ {} }}", struct_def.name, fields.join(", "))?;
+        }
+        for enum_def in &self.enums {
+            let variants: Vec<_> = enum_def
+                .variants
+                .iter()
+                .map(|variant| {
+                    if variant.fields.is_empty() {
+                        return variant.name.clone();
+                    }
+                    let fields: Vec<_> = variant.fields.iter().map(ToString::to_string).collect();
+                    format!("{}({})", variant.name, fields.join(", "))
+                })
+                .collect();
+            writeln!(f, "enum {} {{ {} }}", enum_def.name, variants.join(", "))?;
+        }
+        for (index, body) in self.bodies.iter().enumerate() {
+            if index > 0 || !self.structs.is_empty() || !self.enums.is_empty() {
+                writeln!(f)?;
+            }
+            self.fmt_body(f, body)?;
+        }
+        Ok(())
+    }
+}
+
+impl MirGem {
+    /// Prints a body in the textual format.
+    fn fmt_body(&self, f: &mut Formatter<'_>, body: &Body) -> Result {
+        let args: Vec<_> = body.args().map(|arg| format!("{arg}: {}", body.local(arg).ty)).collect();
+        write!(f, "fn {}({})", body.name, args.join(", "))?;
+        let ret = &body.local(Local::RETURN).ty;
+        if *ret != Ty::Void {
+            write!(f, " -> {ret}")?;
+        }
+        writeln!(f, " {{")?;
+
+        for (index, decl) in body.locals.iter().enumerate() {
+            if let Some(name) = &decl.name {
+                writeln!(f, "    debug {name} => _{index};")?;
+            }
+        }
+        for (index, decl) in body.locals.iter().enumerate() {
+            if index == 0 || index > body.arg_count {
+                writeln!(f, "    let {}_{index}: {};", if decl.mutable { "mut " } else { "" }, decl.ty)?;
+            }
+        }
+
+        for block in body.block_ids() {
+            writeln!(f)?;
+            self.fmt_block(f, block, body.block(block))?;
+        }
+        writeln!(f, "}}")
+    }
+
+    fn fmt_block(&self, f: &mut Formatter<'_>, id: BlockId, block: &BasicBlock) -> Result {
+        writeln!(f, "    {id}: {{")?;
+        for Phi { dest, args } in &block.phis {
+            let args: Vec<_> = args.iter().map(|(from, operand)| format!("{from}: {operand}")).collect();
+            writeln!(f, "        {dest} = phi [{}];", args.join(", "))?;
+        }
+        for statement in &block.statements {
+            match &statement.kind {
+                StatementKind::Assign(place, rvalue) => writeln!(f, "        {place} = {};", self.rvalue(rvalue))?,
+                StatementKind::StorageDead(local) => writeln!(f, "        StorageDead({local});")?,
+                StatementKind::Nop => writeln!(f, "        nop;")?,
+            }
+        }
+        write!(f, "        ")?;
+        match &block.terminator.kind {
+            TerminatorKind::Goto(target) => writeln!(f, "goto -> {target};")?,
+            TerminatorKind::If { cond, then, otherwise } => {
+                writeln!(f, "if {cond} -> [true: {then}, false: {otherwise}];")?;
+            }
+            TerminatorKind::Switch { discr, targets } => {
+                let targets: Vec<_> =
+                    targets.iter().enumerate().map(|(index, target)| format!("{index}: {target}")).collect();
+                writeln!(f, "switch {discr} -> [{}];", targets.join(", "))?;
+            }
+            TerminatorKind::Call { callee, args, dest, target, .. } => {
+                let args: Vec<_> = args.iter().map(ToString::to_string).collect();
+                writeln!(f, "{dest} = {callee}({}) -> {target};", args.join(", "))?;
+            }
+            TerminatorKind::Return => writeln!(f, "return;")?,
+            TerminatorKind::Unreachable => writeln!(f, "unreachable;")?,
+        }
+        writeln!(f, "    }}")
+    }
+
+    fn rvalue(&self, rvalue: &Rvalue) -> String {
+        match rvalue {
+            Rvalue::Use(operand) => operand.to_string(),
+            Rvalue::Ref { mutable: true, place } => format!("&mut {place}"),
+            Rvalue::Ref { mutable: false, place } => format!("&{place}"),
+            Rvalue::Aggregate { def, operands } => {
+                let Some(struct_def) = self.struct_def(*def) else {
+                    let operands: Vec<_> = operands.iter().map(ToString::to_string).collect();
+                    return format!("{{struct}} {{ {} }}", operands.join(", "));
+                };
+                let fields: Vec<_> = struct_def
+                    .fields
+                    .iter()
+                    .zip(operands)
+                    .map(|(field, operand)| format!("{}: {operand}", field.name))
+                    .collect();
+                format!("{} {{ {} }}", struct_def.name, fields.join(", "))
+            }
+            Rvalue::Variant { def, index, operands } => {
+                let enum_def = self.enum_def(*def);
+                let name = match enum_def.and_then(|enum_def| Some((enum_def, enum_def.variants.get(*index)?))) {
+                    Some((enum_def, variant)) => format!("{}::{}", enum_def.name, variant.name),
+                    None => format!("{{enum}}::{index}"),
+                };
+                if operands.is_empty() {
+                    return name;
+                }
+                let operands: Vec<_> = operands.iter().map(ToString::to_string).collect();
+                format!("{name}({})", operands.join(", "))
+            }
+            Rvalue::Discriminant(operand) => format!("discriminant({operand})"),
+            Rvalue::Payload { operand, variant, field } => format!("({operand} as {variant}).{field}"),
+            Rvalue::Array(operands) => {
+                let operands: Vec<_> = operands.iter().map(ToString::to_string).collect();
+                format!("[{}]", operands.join(", "))
+            }
+            Rvalue::Index { operands: [base, index] } => format!("{base}[{index}]"),
+            Rvalue::BinaryOp { op, operands: [lhs, rhs] } => format!("{op:?}({lhs}, {rhs})"),
+            Rvalue::UnaryOp { op, operand } => format!("{op:?}({operand})"),
+        }
+    }
+}
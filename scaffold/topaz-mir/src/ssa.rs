@@ -0,0 +1,286 @@
+//! `ssa.rs` - brings a body into SSA form, where every local is assigned exactly once.
+//!
+//! A local assigned on several paths gets a new local for every assignment, and a [`Phi`] choosing
+//! between them where the paths meet, following "Efficiently Computing Static Single Assignment Form
+//! and the Control Dependence Graph" by Cytron et al. Phis are only placed where the local is still read
+//! afterwards, so that every path into them assigns it, which the type checker made sure of.
+//!
+//! The return value and borrowed locals keep being assigned in place, since something
+//! else than their name refers to them, and so do the locals that may be read before they are assigned,
+//! which only the drops guarded by a drop flag do. The `StorageDead`s are removed first, as only the
+//! analyses of the body as built need them.
+use crate::dominators::Dominators;
+use crate::{BlockId, Body, Local, MirGem, Operand, Phase, Phi, Place, StatementKind, TerminatorKind};
+use std::collections::{HashMap, HashSet};
+
+/// Brings every body of `gem` into SSA form.
+pub fn construct_gem(gem: &mut MirGem) {
+    let mut bodies = std::mem::take(&mut gem.bodies);
+    for body in &mut bodies {
+        construct(gem, body);
+    }
+    gem.bodies = bodies;
+}
+
+/// Brings `body`, a body of `gem` in [`Phase::Built`], into [`Phase::Ssa`].
+/// The blocks that cannot be reached are removed first.
+pub fn construct(gem: &MirGem, body: &mut Body) {
+    debug_assert_eq!(body.phase, Phase::Built, "the body is already in SSA form");
+    for block in &mut body.blocks {
+        block.statements.retain(|statement| !matches!(statement.kind, StatementKind::StorageDead(_)));
+    }
+    body.remove_unreachable_blocks();
+    let candidates = candidates(body);
+    rename_locals(gem, body, &candidates);
+    body.phase = Phase::Ssa;
+}
+
+/// Gives every assignment to one of `locals` its own local, inserting phis where they meet.
+/// The other locals are left as they are, so that a body already in SSA form can take a local
+/// that is assigned more than once, e.g. the return value of a function being inlined.
+pub(crate) fn rename_locals(gem: &MirGem, body: &mut Body, locals: &HashSet<Local>) {
+    let dominators = Dominators::compute(body);
+    let phis = place_phis(body, &dominators, locals);
+    let mut renamer = Renamer { gem, candidates: locals, phis: &phis, stacks: HashMap::new(), renamed: HashSet::new() };
+    for arg in body.args() {
+        if locals.contains(&arg) {
+            renamer.stacks.entry(arg).or_default().push(arg);
+            renamer.renamed.insert(arg);
+        }
+    }
+    renamer.rename(body, &dominators, BlockId::START);
+
+    for block in &mut body.blocks {
+        for phi in &mut block.phis {
+            phi.args.sort_by_key(|(from, _)| *from);
+        }
+    }
+}
+
+/// The locals that can be renamed: the ones neither returned, borrowed nor read before they are assigned.
+fn candidates(body: &Body) -> HashSet<Local> {
+    let in_place = body.in_place_locals();
+    let mut candidates: HashSet<_> = (1..body.locals.len())
+        .map(|index| Local(u32::try_from(index).expect("too many locals")))
+        .filter(|local| !in_place.contains(local))
+        .collect();
+    for block in &body.blocks {
+        for statement in &block.statements {
+            if let StatementKind::Assign(place, rvalue) = &statement.kind {
+                if let crate::Rvalue::Ref { place: borrowed, .. } = rvalue {
+                    candidates.remove(&borrowed.local);
+                }
+                if !place.projection.is_empty() {
+                    candidates.remove(&place.local);
+                }
+            }
+        }
+        if let TerminatorKind::Call { dest, .. } = &block.terminator.kind {
+            if !dest.projection.is_empty() {
+                candidates.remove(&dest.local);
+            }
+        }
+    }
+    candidates
+}
+
+/// The locals a block assigns as a whole, in order.
+fn assigned_locals(body: &Body, block: BlockId) -> Vec<Local> {
+    let block = body.block(block);
+    let statements = block.statements.iter().filter_map(|statement| match &statement.kind {
+        StatementKind::Assign(place, _) => place.as_local(),
+        StatementKind::StorageDead(_) | StatementKind::Nop => None,
+    });
+    let terminator = match &block.terminator.kind {
+        TerminatorKind::Call { dest, .. } => dest.as_local(),
+        _ => None,
+    };
+    statements.chain(terminator).collect()
+}
+
+/// The locals every block reads before assigning them, and the ones it assigns.
+fn uses_and_defs(body: &Body, block: BlockId) -> (HashSet<Local>, HashSet<Local>) {
+    let (mut uses, mut defs) = (HashSet::new(), HashSet::new());
+    let mut read = |operand: &Operand, defs: &HashSet<Local>| {
+        if let Some(place) = operand.place() {
+            if !defs.contains(&place.local) {
+                uses.insert(place.local);
+            }
+        }
+    };
+    let data = body.block(block);
+    for statement in &data.statements {
+        if let StatementKind::Assign(place, rvalue) = &statement.kind {
+            for operand in rvalue.operands() {
+                read(operand, &defs);
+            }
+            if let Some(local) = place.as_local() {
+                defs.insert(local);
+            }
+        }
+    }
+    for operand in data.terminator.kind.operands() {
+        read(operand, &defs);
+    }
+    if let TerminatorKind::Call { dest, .. } = &data.terminator.kind {
+        if let Some(local) = dest.as_local() {
+            defs.insert(local);
+        }
+    }
+    (uses, defs)
+}
+
+/// The locals read at the start of every block before being assigned.
+fn live_in(body: &Body, dominators: &Dominators) -> Vec<HashSet<Local>> {
+    let blocks: Vec<_> = body.block_ids().map(|block| uses_and_defs(body, block)).collect();
+    let mut live_in: Vec<HashSet<Local>> = blocks.iter().map(|(uses, _)| uses.clone()).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &block in dominators.reverse_postorder().iter().rev() {
+            let live_out: HashSet<Local> =
+                body.successors(block).into_iter().flat_map(|successor| live_in[successor.0].iter().copied()).collect();
+            let (_, defs) = &blocks[block.0];
+            for local in live_out.difference(defs) {
+                changed |= live_in[block.0].insert(*local);
+            }
+        }
+    }
+    live_in
+}
+
+/// Inserts an empty phi for every candidate in the blocks where its assignments meet and it is still read,
+/// returning the local every phi is for.
+fn place_phis(
+    body: &mut Body,
+    dominators: &Dominators,
+    candidates: &HashSet<Local>,
+) -> HashMap<(BlockId, usize), Local> {
+    let frontiers = dominators.frontiers(body);
+    let live_in = live_in(body, dominators);
+
+    let mut def_blocks: HashMap<Local, Vec<BlockId>> = HashMap::new();
+    for &block in dominators.reverse_postorder() {
+        for local in assigned_locals(body, block) {
+            if candidates.contains(&local) {
+                def_blocks.entry(local).or_default().push(block);
+            }
+        }
+    }
+    for arg in body.args() {
+        def_blocks.entry(arg).or_default().push(BlockId::START);
+    }
+
+    let mut locals: Vec<_> = def_blocks.into_iter().filter(|(local, _)| candidates.contains(local)).collect();
+    locals.sort_by_key(|(local, _)| *local);
+    let mut phis = HashMap::new();
+    for (local, mut worklist) in locals {
+        let mut has_phi = HashSet::new();
+        while let Some(block) = worklist.pop() {
+            for &frontier in &frontiers[block.0] {
+                if !live_in[frontier.0].contains(&local) || !has_phi.insert(frontier) {
+                    continue;
+                }
+                let phis_of_block = &mut body.blocks[frontier.0].phis;
+                phis.insert((frontier, phis_of_block.len()), local);
+                phis_of_block.push(Phi { dest: local, args: Vec::new() });
+                worklist.push(frontier);
+            }
+        }
+    }
+    phis
+}
+
+struct Renamer<'a> {
+    gem: &'a MirGem,
+    candidates: &'a HashSet<Local>,
+    /// The local every phi is for, by its block and index
+    phis: &'a HashMap<(BlockId, usize), Local>,
+    /// The local holding the current value of every candidate, innermost last
+    stacks: HashMap<Local, Vec<Local>>,
+    /// The candidates whose first assignment was given their own local already
+    renamed: HashSet<Local>,
+}
+
+impl Renamer<'_> {
+    /// A local for a new assignment to `local`: `local` itself the first time, a copy of it afterwards.
+    fn define(&mut self, body: &mut Body, local: Local, pushed: &mut Vec<Local>) -> Local {
+        let new = if self.renamed.insert(local) { local } else { body.push_local(body.local(local).clone()) };
+        self.stacks.entry(local).or_default().push(new);
+        pushed.push(local);
+        new
+    }
+
+    fn current(&self, local: Local) -> Option<Local> {
+        self.stacks.get(&local).and_then(|stack| stack.last()).copied()
+    }
+
+    fn rename_operand(&self, operand: &mut Operand) {
+        if let Some(place) = operand.place_mut() {
+            self.rename_place(place);
+        }
+    }
+
+    fn rename_place(&self, place: &mut Place) {
+        if self.candidates.contains(&place.local) {
+            // without any assignment before, the local is read uninitialized, which the validator reports
+            if let Some(current) = self.current(place.local) {
+                place.local = current;
+            }
+        }
+    }
+
+    fn rename(&mut self, body: &mut Body, dominators: &Dominators, block: BlockId) {
+        let mut pushed = Vec::new();
+        for index in 0..body.block(block).phis.len() {
+            if let Some(&local) = self.phis.get(&(block, index)) {
+                let dest = self.define(body, local, &mut pushed);
+                body.blocks[block.0].phis[index].dest = dest;
+            }
+        }
+
+        for index in 0..body.block(block).statements.len() {
+            let StatementKind::Assign(place, rvalue) = &mut body.blocks[block.0].statements[index].kind else { continue };
+            rvalue.operands_mut().iter_mut().for_each(|operand| self.rename_operand(operand));
+            if let Some(local) = place.as_local().filter(|local| self.candidates.contains(local)) {
+                let dest = self.define(body, local, &mut pushed);
+                if let StatementKind::Assign(place, _) = &mut body.blocks[block.0].statements[index].kind {
+                    place.local = dest;
+                }
+            } else {
+                self.rename_place(place);
+            }
+        }
+
+        let terminator = &mut body.blocks[block.0].terminator.kind;
+        terminator.operands_mut().iter_mut().for_each(|operand| self.rename_operand(operand));
+        if let TerminatorKind::Call { dest, .. } = terminator {
+            if let Some(local) = dest.as_local().filter(|local| self.candidates.contains(local)) {
+                let new = self.define(body, local, &mut pushed);
+                if let TerminatorKind::Call { dest, .. } = &mut body.blocks[block.0].terminator.kind {
+                    dest.local = new;
+                }
+            }
+        }
+
+        for successor in body.successors(block) {
+            for index in 0..body.block(successor).phis.len() {
+                let Some(&local) = self.phis.get(&(successor, index)) else { continue };
+                let Some(current) = self.current(local) else { continue };
+                let place = Place::from(current);
+                let operand =
+                    if self.gem.is_copy(&body.local(current).ty) { Operand::Copy(place) } else { Operand::Move(place) };
+                body.blocks[successor.0].phis[index].args.push((block, operand));
+            }
+        }
+
+        for child in dominators.children(block) {
+            self.rename(body, dominators, child);
+        }
+        for local in pushed {
+            if let Some(stack) = self.stacks.get_mut(&local) {
+                stack.pop();
+            }
+        }
+    }
+}
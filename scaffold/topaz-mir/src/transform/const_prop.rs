@@ -0,0 +1,168 @@
+//! `const_prop.rs` - replaces reads of locals holding a constant by the constant, folds operators on constants,
+//! and folds branches on constants.
+//!
+//! A constant is computed by an operator on constants, by reading it out of a field of a struct or a variant
+//! built from constants, by the discriminant of a variant, or by a phi whose operands are all the same constant. An operator that would panic is left for the
+//! program to panic on, and so is arithmetic on `usize` and `isize`, whose width depends on the target. Folding a
+//! branch or a switch makes the blocks only it jumped to unreachable, which are removed so that the phis they fed can be
+//! folded too.
+use super::{
+    for_each_body, operands_mut, remove_stale_phi_args, replace_phis, ssa_defs, trivial_phi_value, Def, MirPass,
+};
+use crate::fold::{arithmetic, compare};
+use crate::{BinOp, Body, ConstValue, Constant, Local, MirGem, Operand, Rvalue, StatementKind, TerminatorKind, UnOp};
+use std::collections::HashMap;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+pub struct ConstProp;
+
+impl MirPass for ConstProp {
+    fn name(&self) -> &'static str {
+        "const_prop"
+    }
+
+    fn run(&self, gem: &mut MirGem) {
+        for_each_body(gem, |_, body| while propagate(body) {});
+    }
+}
+
+/// Replaces what is known to be constant once, returning whether anything changed.
+fn propagate(body: &mut Body) -> bool {
+    let defs = ssa_defs(body);
+    let mut constants: HashMap<Local, Constant> = HashMap::new();
+    let mut aggregates: HashMap<Local, Vec<Operand>> = HashMap::new();
+    let mut variants: HashMap<Local, (usize, Vec<Operand>)> = HashMap::new();
+    for (local, def) in &defs {
+        let Def::Statement(block, index) = *def else { continue };
+        match &body.block(block).statements[index].kind {
+            StatementKind::Assign(_, Rvalue::Use(Operand::Const(constant))) => {
+                constants.insert(*local, constant.clone());
+            }
+            StatementKind::Assign(_, Rvalue::Aggregate { operands, .. }) => {
+                aggregates.insert(*local, operands.clone());
+            }
+            StatementKind::Assign(_, Rvalue::Variant { index, operands, .. }) => {
+                variants.insert(*local, (*index, operands.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    let mut changed = false;
+    operands_mut(body, |operand| {
+        let Some(place) = operand.place() else { return };
+        let constant = match place.projection[..] {
+            [] => constants.get(&place.local),
+            [field] => aggregates.get(&place.local).and_then(|operands| match operands.get(field) {
+                Some(Operand::Const(constant)) => Some(constant),
+                _ => None,
+            }),
+            _ => None,
+        };
+        if let Some(constant) = constant {
+            *operand = Operand::Const(constant.clone());
+            changed = true;
+        }
+    });
+
+    for block in &mut body.blocks {
+        for statement in &mut block.statements {
+            let StatementKind::Assign(_, rvalue) = &mut statement.kind else { continue };
+            if let Some(constant) = fold(rvalue).or_else(|| fold_variant(rvalue, &variants)) {
+                *rvalue = Rvalue::Use(Operand::Const(constant));
+                changed = true;
+            }
+        }
+
+        // a phi choosing between the same constant is an assignment of it, which is propagated next time
+        changed |= replace_phis(block, |phi| {
+            trivial_phi_value(phi).filter(|value| matches!(value, Operand::Const(_))).cloned()
+        });
+
+        if let TerminatorKind::If {
+            cond: Operand::Const(Constant { value: ConstValue::Bool(cond), .. }),
+            then,
+            otherwise,
+        } = block.terminator.kind
+        {
+            block.terminator.kind = TerminatorKind::Goto(if cond { then } else { otherwise });
+            changed = true;
+        }
+        if let TerminatorKind::Switch {
+            discr: Operand::Const(Constant { value: ConstValue::Int(discr), .. }),
+            targets,
+        } = &block.terminator.kind
+        {
+            if let Some(&target) = usize::try_from(*discr).ok().and_then(|discr| targets.get(discr)) {
+                block.terminator.kind = TerminatorKind::Goto(target);
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        body.remove_unreachable_blocks();
+        remove_stale_phi_args(body);
+    }
+    changed
+}
+
+/// The constant `rvalue` computes, if it is an operator on constants that does not panic.
+fn fold(rvalue: &Rvalue) -> Option<Constant> {
+    match rvalue {
+        Rvalue::BinaryOp { op, operands: [Operand::Const(lhs), Operand::Const(rhs)] } => {
+            let value = match (&lhs.value, &rhs.value) {
+                (ConstValue::Int(lhs_value), ConstValue::Int(rhs_value)) => {
+                    let Ty::Int(int) = lhs.ty.peel() else { return None };
+                    if op.is_comparison() && *int == IntTy::Uexplod {
+                        #[allow(clippy::cast_sign_loss)]
+                        ConstValue::Bool(compare(*op, &(*lhs_value as u128), &(*rhs_value as u128)))
+                    } else if op.is_comparison() {
+                        ConstValue::Bool(compare(*op, lhs_value, rhs_value))
+                    } else {
+                        ConstValue::Int(arithmetic(*op, *lhs_value, *rhs_value, unsized_int(*int)?, 0)?)
+                    }
+                }
+                (ConstValue::Bool(lhs), ConstValue::Bool(rhs)) => ConstValue::Bool(compare(*op, lhs, rhs)),
+                (ConstValue::Char(lhs), ConstValue::Char(rhs)) => ConstValue::Bool(compare(*op, lhs, rhs)),
+                _ => return None,
+            };
+            let ty = if op.is_comparison() { Ty::Bool } else { lhs.ty.clone() };
+            Some(Constant { value, ty })
+        }
+        Rvalue::UnaryOp { op, operand: Operand::Const(operand) } => {
+            let value = match (op, &operand.value) {
+                (UnOp::Neg, ConstValue::Int(value)) => {
+                    let Ty::Int(int) = operand.ty.peel() else { return None };
+                    ConstValue::Int(arithmetic(BinOp::Sub, 0, *value, unsized_int(*int)?, 0)?)
+                }
+                (UnOp::Not, ConstValue::Bool(value)) => ConstValue::Bool(!value),
+                _ => return None,
+            };
+            Some(Constant { value, ty: operand.ty.clone() })
+        }
+        _ => None,
+    }
+}
+
+/// The discriminant of a variant built in the body, or a constant field of it.
+fn fold_variant(rvalue: &Rvalue, variants: &HashMap<Local, (usize, Vec<Operand>)>) -> Option<Constant> {
+    let (Rvalue::Discriminant(operand) | Rvalue::Payload { operand, .. }) = rvalue else { return None };
+    let local = operand.place()?.as_local()?;
+    let (index, operands) = variants.get(&local)?;
+    match rvalue {
+        Rvalue::Payload { variant, field, .. } if variant == index => match operands.get(*field)? {
+            Operand::Const(constant) => Some(constant.clone()),
+            _ => None,
+        },
+        Rvalue::Discriminant(_) => {
+            Some(Constant { value: ConstValue::Int(i128::try_from(*index).ok()?), ty: Ty::Int(IntTy::Usize) })
+        }
+        _ => None,
+    }
+}
+
+/// `int`, `None` if it is as wide as a pointer of the target, so that the arithmetic is computed without one.
+fn unsized_int(int: IntTy) -> Option<IntTy> {
+    (!matches!(int, IntTy::Usize | IntTy::Isize)).then_some(int)
+}
@@ -0,0 +1,96 @@
+//! `copy_prop.rs` - reads the original instead of a copy of it.
+//!
+//! A local that is only ever assigned a copy of another one, or by a phi all of whose operands are the same,
+//! is replaced by that one everywhere it is read, and so is a field of a struct that was built from a local.
+//! The copies this leaves unread are removed by [`super::dce`].
+//! Only locals in SSA form take part, since a borrowed local can change without being assigned.
+use super::{for_each_body, operands_mut, ssa_defs, trivial_phi_value, Def, MirPass};
+use crate::{BlockId, Body, Local, MirGem, Operand, Place, Rvalue, StatementKind};
+use std::collections::{HashMap, HashSet};
+
+pub struct CopyProp;
+
+impl MirPass for CopyProp {
+    fn name(&self) -> &'static str {
+        "copy_prop"
+    }
+
+    fn run(&self, gem: &mut MirGem) {
+        for_each_body(gem, |gem, body| while propagate(gem, body) {});
+    }
+}
+
+/// Replaces every copy known once, returning whether anything changed.
+fn propagate(gem: &MirGem, body: &mut Body) -> bool {
+    let defs = ssa_defs(body);
+    let mut copies: HashMap<Local, Place> = HashMap::new();
+    let mut aggregates: HashMap<Local, Vec<Operand>> = HashMap::new();
+    let mut phis = HashSet::new();
+    for (local, def) in &defs {
+        let source = match *def {
+            Def::Statement(block, index) => match &body.block(block).statements[index].kind {
+                StatementKind::Assign(_, Rvalue::Use(operand)) => operand.place(),
+                StatementKind::Assign(_, Rvalue::Aggregate { operands, .. }) => {
+                    aggregates.insert(*local, operands.clone());
+                    None
+                }
+                _ => None,
+            },
+            Def::Phi(block, index) => trivial_phi_value(&body.block(block).phis[index]).and_then(Operand::place),
+            Def::Arg | Def::Call(_) => None,
+        };
+        let Some(source) = source.filter(|source| defs.contains_key(&source.local)) else { continue };
+        if body.local(*local).ty == gem.place_ty(body, source) {
+            copies.insert(*local, source.clone());
+            if let Def::Phi(block, index) = *def {
+                phis.insert((block, index));
+            }
+        }
+    }
+
+    let mut changed = false;
+    operands_mut(body, |operand| {
+        let Some(place) = operand.place_mut() else { return };
+        if let Some(original) = original(&defs, &copies, &aggregates, place) {
+            *place = original;
+            changed = true;
+        }
+    });
+
+    // the phis are not read anymore, and would read themselves if they were part of a loop
+    for (index, block) in body.blocks.iter_mut().enumerate() {
+        let mut phi = 0;
+        block.phis.retain(|_| {
+            phi += 1;
+            !phis.contains(&(BlockId(index), phi - 1))
+        });
+    }
+    changed | !phis.is_empty()
+}
+
+/// The place `place` is a copy of, following copies of copies, if it is one.
+fn original(
+    defs: &HashMap<Local, Def>,
+    copies: &HashMap<Local, Place>,
+    aggregates: &HashMap<Local, Vec<Operand>>,
+    place: &Place,
+) -> Option<Place> {
+    let mut current = place.clone();
+    // a chain of copies longer than all of them is a cycle, which only phis that are never reached can form
+    for _ in 0..=copies.len() + aggregates.len() {
+        let next = if let Some(copied) = copies.get(&current.local) {
+            Place { local: copied.local, projection: [&copied.projection[..], &current.projection].concat() }
+        } else if let Some((field, rest)) = current.projection.split_first() {
+            match aggregates.get(&current.local).and_then(|operands| operands.get(*field)).and_then(Operand::place) {
+                Some(field) if defs.contains_key(&field.local) => {
+                    Place { local: field.local, projection: [&field.projection[..], rest].concat() }
+                }
+                _ => break,
+            }
+        } else {
+            break;
+        };
+        current = next;
+    }
+    (current != *place).then_some(current)
+}
@@ -0,0 +1,86 @@
+//! `dce.rs` - removes the assignments and phis whose result is never read, and the locals left unused.
+//!
+//! Building a struct, borrowing, copying and comparing cannot do anything else than produce their value, so all
+//! of them can be removed. Calls stay, since the function called may do something else, and so does the local
+//! they return to. Arithmetic stays too, since it may panic.
+use super::{block_locals_mut, for_each_body, MirPass};
+use crate::{Body, Local, MirGem, Rvalue, StatementKind};
+use std::collections::HashSet;
+
+pub struct DeadCodeElimination;
+
+impl MirPass for DeadCodeElimination {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run(&self, gem: &mut MirGem) {
+        for_each_body(gem, |_, body| {
+            while remove_dead_assignments(body) {}
+            remove_unused_locals(body);
+        });
+    }
+}
+
+/// The locals read anywhere in `body`, borrowed or assigned through a field, which has to be kept.
+fn used_locals(body: &Body) -> HashSet<Local> {
+    let mut used = HashSet::from([Local::RETURN]);
+    for block in &body.blocks {
+        let operands = block.phis.iter().flat_map(|phi| phi.args.iter().map(|(_, operand)| operand));
+        let mut places: Vec<_> = operands.chain(block.terminator.kind.operands()).filter_map(|op| op.place()).collect();
+        for statement in &block.statements {
+            if let StatementKind::Assign(place, rvalue) = &statement.kind {
+                places.extend(rvalue.operands().iter().filter_map(|operand| operand.place()));
+                if let Rvalue::Ref { place, .. } = rvalue {
+                    places.push(place);
+                }
+                if place.as_local().is_none() {
+                    places.push(place);
+                }
+            }
+        }
+        used.extend(places.into_iter().map(|place| place.local));
+    }
+    used
+}
+
+/// Removes the assignments and phis whose result is unused, returning whether there were any.
+/// The assignments they read from may only become unused by that, so this is repeated until there are none.
+fn remove_dead_assignments(body: &mut Body) -> bool {
+    let used = used_locals(body);
+    let mut changed = false;
+    for block in &mut body.blocks {
+        let (phis, statements) = (block.phis.len(), block.statements.len());
+        block.phis.retain(|phi| used.contains(&phi.dest));
+        block.statements.retain(|statement| match &statement.kind {
+            StatementKind::Assign(place, rvalue) => {
+                rvalue.may_panic() || place.as_local().map_or(true, |local| used.contains(&local))
+            }
+            StatementKind::StorageDead(_) | StatementKind::Nop => false,
+        });
+        changed |= phis != block.phis.len() || statements != block.statements.len();
+    }
+    changed
+}
+
+/// Removes the locals that nothing refers to anymore, renumbering the others in the order they were in.
+/// The return value and the parameters are always kept.
+fn remove_unused_locals(body: &mut Body) {
+    let mut referred = vec![false; body.locals.len()];
+    referred[..=body.arg_count].fill(true);
+    for block in &mut body.blocks {
+        block_locals_mut(block, |local| referred[local.index()] = true);
+    }
+
+    let mut renumbered = Vec::with_capacity(body.locals.len());
+    let mut next = 0;
+    for referred in &referred {
+        renumbered.push(Local(next));
+        next += u32::from(*referred);
+    }
+    for block in &mut body.blocks {
+        block_locals_mut(block, |local| *local = renumbered[local.index()]);
+    }
+    let locals = std::mem::take(&mut body.locals);
+    body.locals = locals.into_iter().zip(referred).filter(|(_, referred)| *referred).map(|(decl, _)| decl).collect();
+}
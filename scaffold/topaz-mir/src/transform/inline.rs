@@ -0,0 +1,175 @@
+//! `inline.rs` - replaces calls to small functions by a copy of their body.
+//!
+//! A function is inlined if its size, counted in statements, phis and blocks, is at most the threshold of the
+//! pass, and it is not the function calling it. The copy is made of the functions as they were before the
+//! pass, so that one run inlines every call at most one level deep, which keeps recursion from going on forever.
+//!
+//! The copy assigns the arguments to the parameters in the block of the call, and every `return` in it jumps
+//! to a new block assigning the return value to the destination of the call and going on where the call did.
+//! The return value is assigned on every path to a `return`, so it is renamed into SSA form before that.
+use super::{block_locals_mut, MirPass};
+use crate::ssa::rename_locals;
+use crate::{
+    BasicBlock, BlockId, Body, ConstValue, Constant, Local, LocalDecl, MirGem, Operand, Rvalue, Statement,
+    StatementKind, Terminator, TerminatorKind,
+};
+use std::collections::HashSet;
+use topaz_hir::Callee;
+use topaz_type_checker::ty::Ty;
+
+/// The size of the functions inlined at `-O2`
+pub const SMALL_FUNCTION: usize = 12;
+/// The size of the functions inlined at `-O3`
+pub const LARGE_FUNCTION: usize = 40;
+
+pub struct Inline {
+    threshold: usize,
+}
+
+impl Inline {
+    /// Inlines the functions of at most `threshold` statements, phis and blocks.
+    #[must_use]
+    pub const fn new(threshold: usize) -> Self {
+        Self { threshold }
+    }
+}
+
+impl MirPass for Inline {
+    fn name(&self) -> &'static str {
+        "inline"
+    }
+
+    fn run(&self, gem: &mut MirGem) {
+        let callees: Vec<Body> = gem
+            .bodies
+            .iter()
+            // the start of the copy is jumped to from the call, so it cannot have phis for other predecessors
+            .filter(|body| size(body) <= self.threshold && body.predecessors()[BlockId::START.0].is_empty())
+            .filter_map(|body| with_single_exit(gem, body))
+            .collect();
+
+        let mut bodies = std::mem::take(&mut gem.bodies);
+        for caller in &mut bodies {
+            for block in caller.block_ids().collect::<Vec<_>>() {
+                let TerminatorKind::Call { callee: Callee::Fn { def, .. }, args, .. } = &caller.block(block).terminator.kind
+                else {
+                    continue;
+                };
+                let callee = callees.iter().find(|callee| callee.def == Some(*def));
+                if let Some(callee) = callee.filter(|callee| callee.def != caller.def && callee.arg_count == args.len())
+                {
+                    inline_call(caller, block, callee);
+                }
+            }
+        }
+        gem.bodies = bodies;
+    }
+}
+
+/// The size of `body`: its statements, phis and blocks.
+fn size(body: &Body) -> usize {
+    body.blocks.iter().map(|block| 1 + block.phis.len() + block.statements.len()).sum()
+}
+
+/// A copy of `body` whose `return`s all jump to a last block, which assigns the return value to `_0` and returns.
+/// The return value is held by a new local in the other blocks. `None` if the return value is borrowed.
+fn with_single_exit(gem: &MirGem, body: &Body) -> Option<Body> {
+    let mut body = body.clone();
+    let decl = body.local(Local::RETURN).clone();
+    let (value, renamed) = if decl.ty == Ty::Void {
+        (Operand::Const(Constant { value: ConstValue::Void, ty: Ty::Void }), None)
+    } else {
+        if body.borrowed_locals().contains(&Local::RETURN) {
+            return None;
+        }
+        let value = body.push_local(LocalDecl { mutable: false, ..decl.clone() });
+        for block in &mut body.blocks {
+            block_locals_mut(block, |local| {
+                if *local == Local::RETURN {
+                    *local = value;
+                }
+            });
+        }
+        let operand = if gem.is_copy(&decl.ty) { Operand::Copy(value.into()) } else { Operand::Move(value.into()) };
+        (operand, Some(value))
+    };
+
+    let exit = BlockId(body.blocks.len());
+    for block in &mut body.blocks {
+        if block.terminator.kind == TerminatorKind::Return {
+            block.terminator.kind = TerminatorKind::Goto(exit);
+        }
+    }
+    let assign = StatementKind::Assign(Local::RETURN.into(), Rvalue::Use(value));
+    body.blocks.push(BasicBlock {
+        phis: Vec::new(),
+        statements: vec![Statement { kind: assign, span: body.span }],
+        terminator: Terminator { kind: TerminatorKind::Return, span: body.span },
+    });
+    if let Some(value) = renamed {
+        rename_locals(gem, &mut body, &HashSet::from([value]));
+    }
+    Some(body)
+}
+
+/// Replaces the call ending `block` of `caller` by `callee`, as given by [`with_single_exit`].
+fn inline_call(caller: &mut Body, block: BlockId, callee: &Body) {
+    let call = caller.block(block).terminator.clone();
+    let TerminatorKind::Call { args, dest, target, .. } = call.kind else { return };
+
+    // the inlined locals are temporaries of the caller, declared at the call
+    let locals: Vec<Local> = callee
+        .locals
+        .iter()
+        .enumerate()
+        .map(|(index, decl)| {
+            if index == Local::RETURN.index() {
+                Local::RETURN
+            } else {
+                caller.push_local(LocalDecl { name: None, span: call.span, ..decl.clone() })
+            }
+        })
+        .collect();
+
+    let offset = caller.blocks.len();
+    let exit = BlockId(offset + callee.blocks.len() - 1);
+    for data in &callee.blocks {
+        let mut data = data.clone();
+        block_locals_mut(&mut data, |local| *local = locals[local.index()]);
+        for target in data.terminator.kind.successors_mut() {
+            target.0 += offset;
+        }
+        for phi in &mut data.phis {
+            for (from, _) in &mut phi.args {
+                from.0 += offset;
+            }
+        }
+        for statement in &mut data.statements {
+            statement.span = call.span;
+        }
+        data.terminator.span = call.span;
+        caller.blocks.push(data);
+    }
+
+    let exit_block = &mut caller.blocks[exit.0];
+    if let Some(StatementKind::Assign(place, _)) =
+        exit_block.statements.first_mut().map(|statement| &mut statement.kind)
+    {
+        *place = dest;
+    }
+    exit_block.terminator.kind = TerminatorKind::Goto(target);
+    for phi in &mut caller.blocks[target.0].phis {
+        for (from, _) in &mut phi.args {
+            if *from == block {
+                *from = exit;
+            }
+        }
+    }
+
+    let call_block = &mut caller.blocks[block.0];
+    for (param, arg) in callee.args().zip(args) {
+        let kind = StatementKind::Assign(locals[param.index()].into(), Rvalue::Use(arg));
+        call_block.statements.push(Statement { kind, span: call.span });
+    }
+    call_block.terminator.kind = TerminatorKind::Goto(BlockId(offset));
+}
@@ -0,0 +1,252 @@
+//! `transform/mod.rs` - the optimizations run on the MIR of a gem once it is in SSA form.
+//!
+//! Every optimization is a [`MirPass`]. A [`PassManager`] runs the ones turned on by an [`OptLevel`],
+//! the `-O0` to `-O3` of `topaz build`, always in the same order, and validates the MIR after every pass
+//! so that a pass breaking it is named right away:
+//! - `-O0` runs none of them
+//! - `-O1` simplifies the control flow, propagates constants and copies, and removes dead code
+//! - `-O2` also inlines small functions first
+//! - `-O3` inlines larger functions, and runs everything after the inliner twice
+//!
+//! Every pass can also be turned on or off on its own, e.g. with `-O2 --disable-pass inline`.
+use crate::validate::{validate_gem, ValidationError};
+use crate::{
+    BasicBlock, BlockId, Body, Local, MirGem, Operand, Phase, Phi, Rvalue, Statement, StatementKind, TerminatorKind,
+};
+use std::collections::{HashMap, HashSet};
+
+pub mod const_prop;
+pub mod copy_prop;
+pub mod dce;
+pub mod inline;
+pub mod simplify_cfg;
+
+/// An optimization of the MIR of a gem in SSA form, which keeps it in SSA form.
+pub trait MirPass {
+    /// The name the pass is turned on and off by, e.g. in `topaz build --enable-pass`.
+    fn name(&self) -> &'static str;
+
+    fn run(&self, gem: &mut MirGem);
+}
+
+/// How much to optimize, from `-O0` for nothing to `-O3` for the most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    #[default]
+    O0,
+    O1,
+    O2,
+    O3,
+}
+
+impl OptLevel {
+    /// The level of `-O<level>`, e.g. `"2"`.
+    #[must_use]
+    pub fn from_flag(level: &str) -> Option<Self> {
+        match level {
+            "0" => Some(Self::O0),
+            "1" => Some(Self::O1),
+            "2" => Some(Self::O2),
+            "3" => Some(Self::O3),
+            _ => None,
+        }
+    }
+}
+
+/// The MIR was found to be invalid after a pass, which is a bug of that pass.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("the MIR is invalid after the `{pass}` pass")]
+pub struct InvalidMir {
+    pub pass: &'static str,
+    /// The name of every invalid body, with what is wrong with it
+    pub errors: Vec<(String, ValidationError)>,
+}
+
+/// Runs the passes of an [`OptLevel`], in a fixed order.
+pub struct PassManager {
+    /// Every pass, in the order they run, and whether it is turned on. A pass can appear more than once
+    passes: Vec<(Box<dyn MirPass>, bool)>,
+}
+
+impl PassManager {
+    #[must_use]
+    pub fn new(level: OptLevel) -> Self {
+        let on = |min: OptLevel| level >= min;
+        let threshold = if level >= OptLevel::O3 { inline::LARGE_FUNCTION } else { inline::SMALL_FUNCTION };
+        let mut manager = Self { passes: Vec::new() };
+        manager.register(simplify_cfg::SimplifyCfg, on(OptLevel::O1));
+        manager.register(inline::Inline::new(threshold), on(OptLevel::O2));
+        for _ in 0..if level >= OptLevel::O3 { 2 } else { 1 } {
+            manager
+                .register(const_prop::ConstProp, on(OptLevel::O1))
+                .register(copy_prop::CopyProp, on(OptLevel::O1))
+                .register(dce::DeadCodeElimination, on(OptLevel::O1))
+                .register(simplify_cfg::SimplifyCfg, on(OptLevel::O1));
+        }
+        manager
+    }
+
+    pub fn register(&mut self, pass: impl MirPass + 'static, enabled: bool) -> &mut Self {
+        self.passes.push((Box::new(pass), enabled));
+        self
+    }
+
+    /// The name of every pass, turned on or not, in the order they first run.
+    pub fn pass_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        let mut seen = HashSet::new();
+        self.passes.iter().map(|(pass, _)| pass.name()).filter(move |name| seen.insert(*name))
+    }
+
+    /// Turns the pass called `name` on or off wherever it runs, returning whether there is such a pass.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for (pass, on) in &mut self.passes {
+            if pass.name() == name {
+                *on = enabled;
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Runs every pass that is turned on, validating the MIR after each one.
+    ///
+    /// # Errors
+    /// If a pass leaves the MIR invalid, in which case the later passes are not run.
+    pub fn run(&self, gem: &mut MirGem) -> Result<(), InvalidMir> {
+        debug_assert!(gem.bodies.iter().all(|body| body.phase == Phase::Ssa), "the passes run on SSA form");
+        for (pass, _) in self.passes.iter().filter(|(_, enabled)| *enabled) {
+            pass.run(gem);
+            let errors = validate_gem(gem);
+            if !errors.is_empty() {
+                return Err(InvalidMir { pass: pass.name(), errors });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs `optimize` on every body of `gem`, with the gem it is a body of.
+fn for_each_body(gem: &mut MirGem, mut optimize: impl FnMut(&MirGem, &mut Body)) {
+    let mut bodies = std::mem::take(&mut gem.bodies);
+    for body in &mut bodies {
+        optimize(gem, body);
+    }
+    gem.bodies = bodies;
+}
+
+/// Where a local in SSA form is assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Def {
+    Arg,
+    /// By the phi at the index in the block
+    Phi(BlockId, usize),
+    /// By the statement at the index in the block
+    Statement(BlockId, usize),
+    /// By the call ending the block
+    Call(BlockId),
+}
+
+/// Where every local renamed into SSA form is assigned, leaving out the return value and borrowed locals.
+fn ssa_defs(body: &Body) -> HashMap<Local, Def> {
+    let in_place = body.borrowed_locals();
+    let mut defs: HashMap<_, _> = body.args().map(|arg| (arg, Def::Arg)).collect();
+    for block in body.block_ids() {
+        let data = body.block(block);
+        for (index, phi) in data.phis.iter().enumerate() {
+            defs.insert(phi.dest, Def::Phi(block, index));
+        }
+        for (index, statement) in data.statements.iter().enumerate() {
+            if let StatementKind::Assign(place, _) = &statement.kind {
+                defs.insert(place.local, Def::Statement(block, index));
+            }
+        }
+        if let TerminatorKind::Call { dest, .. } = &data.terminator.kind {
+            defs.insert(dest.local, Def::Call(block));
+        }
+    }
+    defs.retain(|local, _| *local != Local::RETURN && !in_place.contains(local));
+    defs
+}
+
+/// The value every operand of `phi` but the ones reading its own result agree on, if they do.
+fn trivial_phi_value(phi: &Phi) -> Option<&Operand> {
+    let mut values = phi
+        .args
+        .iter()
+        .map(|(_, operand)| operand)
+        .filter(|operand| operand.place().map_or(true, |place| place.local != phi.dest));
+    let first = values.next()?;
+    values.all(|operand| operand == first).then_some(first)
+}
+
+/// Replaces the phis of `block` that `value` knows the value of by an assignment of it at the start
+/// of the block, returning whether there were any.
+fn replace_phis(block: &mut BasicBlock, mut value: impl FnMut(&Phi) -> Option<Operand>) -> bool {
+    let (mut index, mut inserted) = (0, 0);
+    while index < block.phis.len() {
+        let Some(value) = value(&block.phis[index]) else {
+            index += 1;
+            continue;
+        };
+        let phi = block.phis.remove(index);
+        let statement =
+            Statement { kind: StatementKind::Assign(phi.dest.into(), Rvalue::Use(value)), span: block.terminator.span };
+        block.statements.insert(inserted, statement);
+        inserted += 1;
+    }
+    inserted > 0
+}
+
+/// Calls `visit` with every operand of `block`.
+fn block_operands_mut(block: &mut BasicBlock, mut visit: impl FnMut(&mut Operand)) {
+    for phi in &mut block.phis {
+        phi.args.iter_mut().for_each(|(_, operand)| visit(operand));
+    }
+    for statement in &mut block.statements {
+        if let StatementKind::Assign(_, rvalue) = &mut statement.kind {
+            rvalue.operands_mut().iter_mut().for_each(&mut visit);
+        }
+    }
+    block.terminator.kind.operands_mut().iter_mut().for_each(visit);
+}
+
+/// Calls `visit` with every operand of `body`.
+fn operands_mut(body: &mut Body, mut visit: impl FnMut(&mut Operand)) {
+    for block in &mut body.blocks {
+        block_operands_mut(block, &mut visit);
+    }
+}
+
+/// Calls `visit` with every local `block` assigns, reads or borrows.
+fn block_locals_mut(block: &mut BasicBlock, mut visit: impl FnMut(&mut Local)) {
+    for phi in &mut block.phis {
+        visit(&mut phi.dest);
+    }
+    for statement in &mut block.statements {
+        if let StatementKind::Assign(place, rvalue) = &mut statement.kind {
+            visit(&mut place.local);
+            if let Rvalue::Ref { place, .. } = rvalue {
+                visit(&mut place.local);
+            }
+        }
+    }
+    if let TerminatorKind::Call { dest, .. } = &mut block.terminator.kind {
+        visit(&mut dest.local);
+    }
+    block_operands_mut(block, |operand| {
+        if let Some(place) = operand.place_mut() {
+            visit(&mut place.local);
+        }
+    });
+}
+
+/// Removes the operands of phis for blocks that no longer jump to them, e.g. after a branch was folded.
+fn remove_stale_phi_args(body: &mut Body) {
+    let predecessors = body.predecessors();
+    for (block, predecessors) in body.blocks.iter_mut().zip(predecessors) {
+        for phi in &mut block.phis {
+            phi.args.retain(|(from, _)| predecessors.contains(from));
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! `simplify_cfg.rs` - removes the blocks and branches that do nothing.
+//!
+//! - a branch to the same block on both sides becomes a `goto`
+//! - a phi that chooses between the same value is replaced by an assignment of it
+//! - a jump to a block that only jumps on goes straight to where that one goes
+//! - a block only ever jumped to from one other block is merged into the end of it
+//! - the blocks left unreachable are removed
+use super::{for_each_body, remove_stale_phi_args, replace_phis, trivial_phi_value, MirPass};
+use crate::{BasicBlock, Body, MirGem, Rvalue, Statement, StatementKind, Terminator, TerminatorKind};
+
+pub struct SimplifyCfg;
+
+impl MirPass for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "simplify_cfg"
+    }
+
+    fn run(&self, gem: &mut MirGem) {
+        for_each_body(gem, |_, body| simplify(body));
+    }
+}
+
+/// Simplifies the control flow of `body` until nothing is left to simplify.
+pub fn simplify(body: &mut Body) {
+    // blocks that cannot be reached would still count as predecessors, keeping blocks from being merged
+    let mut changed = true;
+    while changed {
+        body.remove_unreachable_blocks();
+        remove_stale_phi_args(body);
+        changed = fold_same_targets(body) | remove_trivial_phis(body) | thread_jumps(body) | merge_blocks(body);
+    }
+    for block in &mut body.blocks {
+        for phi in &mut block.phis {
+            phi.args.sort_by_key(|(from, _)| *from);
+        }
+    }
+}
+
+fn fold_same_targets(body: &mut Body) -> bool {
+    let mut changed = false;
+    for block in &mut body.blocks {
+        if let TerminatorKind::If { then, otherwise, .. } = block.terminator.kind {
+            if then == otherwise {
+                block.terminator.kind = TerminatorKind::Goto(then);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Replaces the phis choosing between the same value by an assignment of it at the start of their block.
+/// A phi whose value is the result of another phi of the block is kept, since all of them run at once.
+fn remove_trivial_phis(body: &mut Body) -> bool {
+    let mut changed = false;
+    for block in &mut body.blocks {
+        let dests: Vec<_> = block.phis.iter().map(|phi| phi.dest).collect();
+        changed |= replace_phis(block, |phi| {
+            trivial_phi_value(phi)
+                .filter(|value| value.place().map_or(true, |place| !dests.contains(&place.local)))
+                .cloned()
+        });
+    }
+    changed
+}
+
+/// Makes the blocks jumping to a block that is empty but for a `goto` jump to where it goes instead.
+/// A block already jumping there as well is left alone if there are phis, which can only take one value from it.
+fn thread_jumps(body: &mut Body) -> bool {
+    let predecessors = body.predecessors();
+    for block in body.block_ids().skip(1) {
+        let data = body.block(block);
+        let TerminatorKind::Goto(target) = data.terminator.kind else { continue };
+        if target == block || !data.phis.is_empty() || !data.statements.is_empty() {
+            continue;
+        }
+        let target_has_phis = !body.block(target).phis.is_empty();
+        let Some(&from) = predecessors[block.0]
+            .iter()
+            .find(|from| **from != block && !(target_has_phis && predecessors[target.0].contains(from)))
+        else {
+            continue;
+        };
+
+        for successor in body.blocks[from.0].terminator.kind.successors_mut() {
+            if *successor == block {
+                *successor = target;
+            }
+        }
+        for phi in &mut body.blocks[target.0].phis {
+            if let Some(value) =
+                phi.args.iter().find(|(arg_from, _)| *arg_from == block).map(|(_, value)| value.clone())
+            {
+                phi.args.push((from, value));
+            }
+        }
+        return true;
+    }
+    false
+}
+
+/// Merges a block into the one before it, if that one is the only one jumping to it and does so with a `goto`.
+fn merge_blocks(body: &mut Body) -> bool {
+    let predecessors = body.predecessors();
+    for block in body.block_ids().skip(1) {
+        let [from] = predecessors[block.0][..] else { continue };
+        if from == block || body.block(from).terminator.kind != TerminatorKind::Goto(block) {
+            continue;
+        }
+
+        let span = body.block(block).terminator.span;
+        let merged = std::mem::replace(
+            &mut body.blocks[block.0],
+            BasicBlock {
+                phis: Vec::new(),
+                statements: Vec::new(),
+                terminator: Terminator { kind: TerminatorKind::Unreachable, span },
+            },
+        );
+        let into = &mut body.blocks[from.0];
+        // the only predecessor is `from`, so every phi has a single operand
+        for phi in merged.phis {
+            let Some((_, value)) = phi.args.into_iter().next() else { continue };
+            let span = into.terminator.span;
+            into.statements.push(Statement { kind: StatementKind::Assign(phi.dest.into(), Rvalue::Use(value)), span });
+        }
+        into.statements.extend(merged.statements);
+        into.terminator = merged.terminator;
+
+        for successor in body.successors(from) {
+            for phi in &mut body.blocks[successor.0].phis {
+                for (arg_from, _) in &mut phi.args {
+                    if *arg_from == block {
+                        *arg_from = from;
+                    }
+                }
+            }
+        }
+        return true;
+    }
+    false
+}
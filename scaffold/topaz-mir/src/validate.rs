@@ -0,0 +1,347 @@
+//! `validate.rs` - checks that a body is well-formed, which every transformation of the MIR has to keep it.
+//!
+//! Errors found here are bugs of the compiler, not of the program being compiled.
+use crate::dominators::Dominators;
+use crate::{BlockId, Body, Local, MirGem, Operand, Phase, Place, Rvalue, StatementKind, TerminatorKind};
+use std::collections::HashMap;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("bb{}: jumps to bb{}, which does not exist", .block.0, .target.0)]
+    MissingBlock { block: BlockId, target: BlockId },
+    #[error("bb{}: refers to `_{}`, which is not declared", .block.0, .local.0)]
+    MissingLocal { block: BlockId, local: Local },
+    #[error("bb{}: `{place}` has no field {field}", .block.0)]
+    NoField { block: BlockId, place: String, field: usize },
+    #[error("bb{}: assigns a value of type `{found}` to `{place}` of type `{expected}`", .block.0)]
+    TypeMismatch { block: BlockId, place: String, expected: Ty, found: Ty },
+    #[error("bb{}: branches on a value of type `{ty}` instead of `bool`", .block.0)]
+    NonBoolCondition { block: BlockId, ty: Ty },
+    #[error("bb{}: switches on a value of type `{ty}` instead of `usize`", .block.0)]
+    NonUsizeDiscriminant { block: BlockId, ty: Ty },
+    #[error("bb{}: passes {found} arguments to `{callee}`, which takes {expected}", .block.0)]
+    ArgumentCount { block: BlockId, callee: String, expected: usize, found: usize },
+    #[error("bb{}: has phis before the body is in SSA form", .block.0)]
+    PhiBeforeSsa { block: BlockId },
+    #[error("bb{}: the phi for `_{}` has operands from {found:?} instead of its predecessors {expected:?}", .block.0, .dest.0)]
+    PhiOperands { block: BlockId, dest: Local, expected: Vec<BlockId>, found: Vec<BlockId> },
+    #[error("`_{}` is assigned more than once in SSA form", .local.0)]
+    AssignedTwice { local: Local },
+    #[error("bb{}: reads `_{}`, which is not assigned on every path to it", .block.0, .local.0)]
+    NotDominated { block: BlockId, local: Local },
+}
+
+/// Validates every body of `gem`, returning the errors found in each one by its name.
+#[must_use]
+pub fn validate_gem(gem: &MirGem) -> Vec<(String, ValidationError)> {
+    gem.bodies.iter().flat_map(|body| validate(gem, body).into_iter().map(|error| (body.name.clone(), error))).collect()
+}
+
+/// Validates `body`, a body of `gem`, for the phase it is in.
+#[must_use]
+pub fn validate(gem: &MirGem, body: &Body) -> Vec<ValidationError> {
+    let mut validator = Validator { gem, body, block: BlockId::START, errors: Vec::new() };
+    if body.blocks.is_empty() {
+        return Vec::new();
+    }
+    validator.check_structure();
+    // the rest could index out of bounds
+    if !validator.errors.is_empty() {
+        return validator.errors;
+    }
+    validator.check_types();
+    if body.phase == Phase::Ssa {
+        validator.check_ssa();
+    }
+    validator.errors
+}
+
+/// Whether a value of type `found` can be stored where `expected` is, every type being compatible with an error.
+fn compatible(expected: &Ty, found: &Ty) -> bool {
+    match (expected, found) {
+        (Ty::Error, _) | (_, Ty::Error) => true,
+        (Ty::Ref { mutable: a_mut, ty: a }, Ty::Ref { mutable: b_mut, ty: b }) => a_mut == b_mut && compatible(a, b),
+        (Ty::Maybe(a), Ty::Maybe(b)) => compatible(a, b),
+        (Ty::Array { element: a, len: a_len }, Ty::Array { element: b, len: b_len }) => {
+            a_len == b_len && compatible(a, b)
+        }
+        (a, b) => a == b,
+    }
+}
+
+struct Validator<'a> {
+    gem: &'a MirGem,
+    body: &'a Body,
+    /// The block being validated
+    block: BlockId,
+    errors: Vec<ValidationError>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, error: ValidationError) {
+        self.errors.push(error);
+    }
+
+    /// Every block, local and field referred to exists, and phis only appear in SSA form.
+    fn check_structure(&mut self) {
+        let body = self.body;
+        for block in body.block_ids() {
+            self.block = block;
+            let data = body.block(block);
+            for target in data.terminator.kind.successors() {
+                if target.0 >= body.blocks.len() {
+                    self.error(ValidationError::MissingBlock { block, target });
+                }
+            }
+            if !data.phis.is_empty() && body.phase < Phase::Ssa {
+                self.error(ValidationError::PhiBeforeSsa { block });
+            }
+
+            let mut places = Vec::new();
+            let mut operands: Vec<&Operand> = data.terminator.kind.operands().iter().collect();
+            for phi in &data.phis {
+                places.push(Place::from(phi.dest));
+                operands.extend(phi.args.iter().map(|(_, operand)| operand));
+            }
+            for statement in &data.statements {
+                match &statement.kind {
+                    StatementKind::Assign(place, rvalue) => {
+                        places.push(place.clone());
+                        operands.extend(rvalue.operands());
+                        if let Rvalue::Ref { place, .. } = rvalue {
+                            places.push(place.clone());
+                        }
+                    }
+                    StatementKind::StorageDead(local) => places.push(Place::from(*local)),
+                    StatementKind::Nop => {}
+                }
+            }
+            if let TerminatorKind::Call { dest, .. } = &data.terminator.kind {
+                places.push(dest.clone());
+            }
+            places.extend(operands.into_iter().filter_map(Operand::place).cloned());
+            for place in &places {
+                self.check_place(place);
+            }
+        }
+    }
+
+    fn check_place(&mut self, place: &Place) {
+        if place.local.index() >= self.body.locals.len() {
+            self.error(ValidationError::MissingLocal { block: self.block, local: place.local });
+            return;
+        }
+        let mut ty = self.body.local(place.local).ty.clone();
+        for (depth, &field) in place.projection.iter().enumerate() {
+            let struct_def = match ty.referent() {
+                Ty::Struct { def, .. } => self.gem.struct_def(*def),
+                _ => None,
+            };
+            match struct_def.and_then(|struct_def| struct_def.fields.get(field)) {
+                Some(field) => ty = field.ty.clone(),
+                None if ty == Ty::Error => return,
+                None => {
+                    let partial = Place { local: place.local, projection: place.projection[..depth].to_vec() };
+                    self.error(ValidationError::NoField { block: self.block, place: partial.to_string(), field });
+                    return;
+                }
+            }
+        }
+    }
+
+    fn operand_ty(&self, operand: &Operand) -> Ty {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.gem.place_ty(self.body, place),
+            Operand::Const(constant) => constant.ty.clone(),
+        }
+    }
+
+    fn rvalue_ty(&self, rvalue: &Rvalue) -> Ty {
+        match rvalue {
+            Rvalue::Use(operand) => self.operand_ty(operand),
+            Rvalue::Ref { mutable, place } => {
+                Ty::Ref { mutable: *mutable, ty: Box::new(self.gem.place_ty(self.body, place)) }
+            }
+            Rvalue::Aggregate { def, .. } => self
+                .gem
+                .struct_def(*def)
+                .map_or(Ty::Error, |struct_def| Ty::Struct { def: *def, name: struct_def.name.clone() }),
+            Rvalue::Variant { def, .. } => self
+                .gem
+                .enum_def(*def)
+                .map_or(Ty::Error, |enum_def| Ty::Enum { def: *def, name: enum_def.name.clone() }),
+            Rvalue::Discriminant(_) => Ty::Int(IntTy::Usize),
+            Rvalue::Payload { operand, variant, field } => self
+                .gem
+                .enum_of(&self.operand_ty(operand))
+                .and_then(|enum_def| enum_def.variants.get(*variant)?.fields.get(*field).cloned())
+                .unwrap_or(Ty::Error),
+            Rvalue::Array(operands) => Ty::Array {
+                element: Box::new(operands.first().map_or(Ty::Error, |operand| self.operand_ty(operand))),
+                len: operands.len(),
+            },
+            Rvalue::Index { operands: [base, _] } => match self.operand_ty(base) {
+                Ty::Array { element, .. } => *element,
+                _ => Ty::Error,
+            },
+            Rvalue::BinaryOp { op, .. } if op.is_comparison() => Ty::Bool,
+            Rvalue::BinaryOp { operands: [operand, _], .. } | Rvalue::UnaryOp { operand, .. } => {
+                self.operand_ty(operand)
+            }
+        }
+    }
+
+    fn check_assign(&mut self, place: &Place, found: Ty) {
+        let expected = self.gem.place_ty(self.body, place);
+        if !compatible(&expected, &found) {
+            self.error(ValidationError::TypeMismatch { block: self.block, place: place.to_string(), expected, found });
+        }
+    }
+
+    /// Every assignment stores a value of the type of its place, conditions are `bool`,
+    /// and calls pass an argument for every parameter.
+    fn check_types(&mut self) {
+        let body = self.body;
+        for block in body.block_ids() {
+            self.block = block;
+            let data = body.block(block);
+            for phi in &data.phis {
+                for (_, operand) in &phi.args {
+                    self.check_assign(&phi.dest.into(), self.operand_ty(operand));
+                }
+            }
+            for statement in &data.statements {
+                if let StatementKind::Assign(place, rvalue) = &statement.kind {
+                    self.check_assign(place, self.rvalue_ty(rvalue));
+                    if let Rvalue::Aggregate { def, operands } = rvalue {
+                        let fields = self.gem.struct_def(*def).map_or(&[][..], |struct_def| &struct_def.fields);
+                        for (field, operand) in fields.iter().zip(operands) {
+                            let found = self.operand_ty(operand);
+                            if !compatible(&field.ty, &found) {
+                                self.error(ValidationError::TypeMismatch {
+                                    block,
+                                    place: format!("{place}.{}", field.name),
+                                    expected: field.ty.clone(),
+                                    found,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            match &data.terminator.kind {
+                TerminatorKind::If { cond, .. } => {
+                    let ty = self.operand_ty(cond);
+                    if !compatible(&Ty::Bool, &ty) {
+                        self.error(ValidationError::NonBoolCondition { block, ty });
+                    }
+                }
+                TerminatorKind::Switch { discr, .. } => {
+                    let ty = self.operand_ty(discr);
+                    if !compatible(&Ty::Int(IntTy::Usize), &ty) {
+                        self.error(ValidationError::NonUsizeDiscriminant { block, ty });
+                    }
+                }
+                TerminatorKind::Call { callee, args, .. } => {
+                    let expected = self.gem.callee(callee).map(|callee| callee.arg_count);
+                    if let Some(expected) = expected.filter(|expected| *expected != args.len()) {
+                        self.error(ValidationError::ArgumentCount {
+                            block,
+                            callee: callee.to_string(),
+                            expected,
+                            found: args.len(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The locals renamed into SSA form are assigned once, before every read,
+    /// and phis have an operand for every predecessor.
+    fn check_ssa(&mut self) {
+        let body = self.body;
+        let dominators = Dominators::compute(body);
+        let predecessors = body.predecessors();
+
+        // where every local is assigned: the block, and the index of the statement or `None` for a phi
+        let mut assigned: HashMap<Local, (BlockId, Option<usize>)> = HashMap::new();
+        let mut twice = Vec::new();
+        for arg in body.args() {
+            assigned.insert(arg, (BlockId::START, None));
+        }
+        let in_place = body.in_place_locals();
+        let in_place = |local: Local| in_place.contains(&local);
+
+        for block in body.block_ids() {
+            let data = body.block(block);
+            let mut defs: Vec<(Local, Option<usize>)> = data.phis.iter().map(|phi| (phi.dest, None)).collect();
+            for (index, statement) in data.statements.iter().enumerate() {
+                if let StatementKind::Assign(place, _) = &statement.kind {
+                    defs.push((place.local, Some(index)));
+                }
+            }
+            if let TerminatorKind::Call { dest, .. } = &data.terminator.kind {
+                defs.push((dest.local, Some(data.statements.len())));
+            }
+            for (local, index) in defs {
+                if !in_place(local) && assigned.insert(local, (block, index)).is_some() {
+                    twice.push(local);
+                }
+            }
+
+            for phi in &data.phis {
+                let found: Vec<_> = phi.args.iter().map(|(from, _)| *from).collect();
+                let mut expected = predecessors[block.0].clone();
+                expected.sort();
+                if found != expected {
+                    self.error(ValidationError::PhiOperands { block, dest: phi.dest, expected, found });
+                }
+            }
+        }
+        twice.sort();
+        twice.dedup();
+        for local in twice {
+            self.error(ValidationError::AssignedTwice { local });
+        }
+
+        // a read is at the end of the predecessor for a phi, or at the index of the statement or terminator
+        let reads_dominated = |local: Local, block: BlockId, index: usize| match assigned.get(&local) {
+            None => false,
+            Some(&(def_block, def_index)) if def_block == block => def_index.map_or(true, |def| def < index),
+            Some(&(def_block, _)) => dominators.dominates(def_block, block),
+        };
+        for block in body.block_ids() {
+            let data = body.block(block);
+            let mut reads = Vec::new();
+            for phi in &data.phis {
+                for (from, operand) in &phi.args {
+                    if let Some(place) = operand.place() {
+                        reads.push((place.local, *from, usize::MAX));
+                    }
+                }
+            }
+            for (index, statement) in data.statements.iter().enumerate() {
+                if let StatementKind::Assign(_, rvalue) = &statement.kind {
+                    for operand in rvalue.operands() {
+                        if let Some(place) = operand.place() {
+                            reads.push((place.local, block, index));
+                        }
+                    }
+                }
+            }
+            for operand in data.terminator.kind.operands() {
+                if let Some(place) = operand.place() {
+                    reads.push((place.local, block, data.statements.len()));
+                }
+            }
+            for (local, at, index) in reads {
+                if !in_place(local) && !reads_dominated(local, at, index) {
+                    self.error(ValidationError::NotDominated { block, local });
+                }
+            }
+        }
+    }
+}
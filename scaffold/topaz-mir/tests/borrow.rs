@@ -0,0 +1,152 @@
+mod common;
+
+use common::messages;
+use topaz_mir::borrowck::BorrowCheck;
+use topaz_type_checker::error::StaticAnalysisError;
+
+const SOURCE: &str = r#"func read(value: &int) {}
+func write(value: &mut int) {}
+func peek(text: &str) {}
+func consume(move text: str) {}
+
+func conflict() {
+    let mut x = 1;
+    let first = &mut x;
+    let second = &x;
+    write(first);
+}
+
+func sequential() {
+    let mut x = 1;
+    let first = &mut x;
+    write(first);
+    let second = &x;
+    read(second);
+    x = 2;
+}
+
+func moved() {
+    let text = "topaz";
+    let borrowed = &text;
+    consume(text);
+    peek(borrowed);
+}
+
+func dangling() {
+    let mut outer = &0;
+    {
+        let inner = 5;
+        outer = &inner;
+    }
+    read(outer);
+}
+
+func escape() -> &int {
+    let local = 1;
+    return &local;
+}
+
+func immutable() {
+    let x = 1;
+    write(&mut x);
+}
+"#;
+
+/// The errors found by borrow checking `source`.
+fn borrow_check(source: &str) -> Vec<StaticAnalysisError> {
+    common::analyze(source, BorrowCheck, common::errors)
+}
+
+#[test]
+fn test_borrow_check() {
+    let errors = borrow_check(SOURCE);
+    assert_eq!(messages(&errors), [
+        "cannot borrow `x` as immutable because it is also borrowed as mutable",
+        "cannot move out of `text` because it is borrowed",
+        "`inner` does not live long enough",
+        "cannot return a reference to local variable `local`",
+        "cannot borrow `x` as mutable, as it is not declared as mutable",
+    ]);
+
+    // the borrow, the conflicting use and the use keeping the borrow alive
+    let labels: Vec<_> = common::labels(SOURCE, &errors[0]).into_iter().map(|(_, text)| text).collect();
+    assert_eq!(labels, ["&x", "&mut x", "first"]);
+    assert!(matches!(&errors[2], StaticAnalysisError::BorrowedValueDropped { later: Some(_), .. }));
+}
+
+#[test]
+fn test_loans_in_loops() {
+    let source = r#"func read(value: &int) {}
+func write(value: &mut int) {}
+
+func looped(flag: bool) {
+    let mut x = 1;
+    let mut held = &0;
+    while flag {
+        write(&mut x);
+        read(held);
+        held = &x;
+    }
+}
+"#;
+    // the loan from the previous iteration is still held by `held`
+    let errors = borrow_check(source);
+    assert_eq!(messages(&errors), ["cannot borrow `x` as mutable because it is also borrowed as immutable"]);
+}
+
+#[test]
+fn test_disjoint_fields() {
+    let source = r#"struct Point { x: int, y: int }
+
+func write(value: &mut int) {}
+
+func disjoint() {
+    let mut point = Point(x = 0, y = 0);
+    let x = &mut point.x;
+    let y = &mut point.y;
+    write(x);
+    write(y);
+}
+"#;
+    assert_eq!(messages(&borrow_check(source)), Vec::<String>::new());
+}
+
+#[test]
+fn test_conflicts_with_one_loan() {
+    let source = r#"func read(value: &int) {}
+func write(value: &mut int) {}
+
+func twice() {
+    let mut x = 1;
+    let first = &mut x;
+    read(&x);
+    x = 2;
+    write(first);
+}
+"#;
+    // every conflicting access is reported, even against a loan reported before
+    let errors = borrow_check(source);
+    assert_eq!(messages(&errors), [
+        "cannot borrow `x` as immutable because it is also borrowed as mutable",
+        "cannot assign to `x` because it is borrowed",
+    ]);
+}
+
+#[test]
+fn test_fields_behind_references() {
+    let source = r#"struct Point { x: int, y: int }
+
+func write(value: &mut int) {}
+
+func through(shared: &Point, exclusive: &mut Point, nested: &&mut Point) {
+    write(&mut exclusive.x);
+    write(&mut shared.x);
+    write(&mut nested.y);
+}
+"#;
+    // a binding that is not `mut` can still lend out a field behind a `&mut`
+    assert_eq!(messages(&borrow_check(source)), [
+        "cannot borrow `shared.x` as mutable, as it is behind a `&` reference",
+        "cannot borrow `nested.y` as mutable, as it is behind a `&` reference",
+    ]);
+}
@@ -0,0 +1,112 @@
+use topaz_ast::file::TopazFile;
+use topaz_ast::location::Span;
+use topaz_hir::lower::lower_gem;
+use topaz_mir::build::build_gem;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::validate::{validate_gem, ValidationError};
+use topaz_mir::{BlockId, Local, MirGem, Place, Rvalue, Statement, StatementKind, TerminatorKind};
+use topaz_parser_next::Parse;
+use topaz_report::Reporter;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+const SOURCE: &str = r#"struct Point { x: int, y: int }
+
+func running(count: int) -> bool {
+    return count < 10;
+}
+
+func next(count: int) -> int {
+    return count * 2 + 1;
+}
+
+func pick(flag: bool, first: Point, second: Point) -> int {
+    let chosen;
+    if flag {
+        chosen = first.x;
+    } else {
+        chosen = second.y;
+    }
+    return chosen;
+}
+
+func count(start: int = 1) -> int {
+    let mut count = start;
+    while running(count) {
+        count = next(count);
+    }
+    return count;
+}
+
+func main() {
+    let origin = Point(y = 2, x = 1);
+    let seen = &origin;
+    let picked = pick(true, origin, Point(x = count(), y = 0));
+    let flipped = !(picked == -1);
+}
+"#;
+
+fn build() -> MirGem {
+    let file = TopazFile::parse(SOURCE).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("test", true);
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let errors: Vec<_> = session
+        .output
+        .iter()
+        .filter(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)))
+        .map(|(file_id, unit)| unit.build_diagnostic(*file_id).message)
+        .collect();
+    assert!(errors.is_empty(), "{errors:?}");
+    build_gem(&lower_gem(&session))
+}
+
+#[test]
+fn test_build() {
+    let gem = build();
+    assert_eq!(validate_gem(&gem), vec![]);
+    insta::assert_snapshot!(gem.to_string());
+}
+
+#[test]
+fn test_ssa() {
+    let mut gem = build();
+    construct_gem(&mut gem);
+    assert_eq!(validate_gem(&gem), vec![]);
+    insta::assert_snapshot!(gem.to_string());
+}
+
+#[test]
+fn test_validate() {
+    let mut gem = build();
+    construct_gem(&mut gem);
+    let body = gem.bodies.iter_mut().find(|body| body.name == "pick").expect("no `pick`");
+
+    // `chosen` is assigned in both branches, by different locals since the body is in SSA form
+    let (then, assigned) = body
+        .block_ids()
+        .find_map(|block| match &body.block(block).statements[..] {
+            [Statement { kind: StatementKind::Assign(place, _), .. }, ..] => Some((block, place.local)),
+            _ => None,
+        })
+        .expect("no assignment");
+    let span = Span::new(0, 0);
+    body.blocks[then.0].statements.push(Statement {
+        kind: StatementKind::Assign(assigned.into(), Rvalue::Use(topaz_mir::Operand::Copy(Place::from(Local(1))))),
+        span,
+    });
+    body.blocks[then.0].terminator.kind = TerminatorKind::Goto(BlockId(99));
+
+    let errors: Vec<_> = validate_gem(&gem).into_iter().map(|(_, error)| error).collect();
+    assert_eq!(errors, vec![ValidationError::MissingBlock { block: then, target: BlockId(99) }]);
+
+    let body = gem.bodies.iter_mut().find(|body| body.name == "pick").expect("no `pick`");
+    body.blocks[then.0].terminator.kind = TerminatorKind::Goto(BlockId(then.0 + 1));
+    let errors: Vec<_> = validate_gem(&gem).into_iter().map(|(_, error)| error.to_string()).collect();
+    assert!(errors.contains(&format!("`{assigned}` is assigned more than once in SSA form")), "{errors:?}");
+    let mismatch = format!("{then}: assigns a value of type `bool` to `{assigned}` of type `int`");
+    assert!(errors.contains(&mismatch), "{errors:?}");
+}
@@ -0,0 +1,55 @@
+//! Helpers shared by the tests of the analyses of function bodies.
+#![allow(dead_code)]
+
+use topaz_ast::file::TopazFile;
+use topaz_parser_next::Parse;
+use topaz_report::Reporter;
+use topaz_resolve::prelude::Prelude;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::error::StaticAnalysisError;
+use topaz_type_checker::infer::TypeInference;
+use topaz_type_checker::pass::{AnalysisPass, AnalysisSession};
+use topaz_type_checker::traits::coherence::Coherence;
+use topaz_type_checker::StaticAnalysisOutputUnit;
+
+/// The traits of `std` the analyses know about, in scope like with the prelude.
+const PRELUDE: &str = "import gem::mem::copy::Copy;\nimport gem::mem::drop::Drop;";
+
+/// Resolves `source`, checks its traits and types and then runs `pass` on it,
+/// returning what `inspect` finds in the session afterwards.
+pub fn analyze<T>(source: &str, mut pass: impl AnalysisPass, inspect: impl FnOnce(&AnalysisSession) -> T) -> T {
+    let file = TopazFile::parse(source).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("test", true);
+    cx.set_prelude(Prelude::from_std_module(&TopazFile::parse(PRELUDE).expect("failed to parse the prelude")));
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let mut session = AnalysisSession::new(&resolutions, &modules);
+    Coherence.run(&mut session);
+    TypeInference.run(&mut session);
+    pass.run(&mut session);
+    inspect(&session)
+}
+
+/// The errors reported in `session`, which has no warnings.
+pub fn errors(session: &AnalysisSession) -> Vec<StaticAnalysisError> {
+    session
+        .output
+        .iter()
+        .map(|(_, unit)| match unit {
+            StaticAnalysisOutputUnit::Error(error) => error.clone(),
+            StaticAnalysisOutputUnit::Warning(warning) => panic!("unexpected warning {warning}"),
+        })
+        .collect()
+}
+
+pub fn messages(errors: &[StaticAnalysisError]) -> Vec<String> {
+    errors.iter().map(ToString::to_string).collect()
+}
+
+/// The message and source text of every label of `error`.
+pub fn labels<'a>(source: &'a str, error: &StaticAnalysisError) -> Vec<(String, &'a str)> {
+    let diagnostic = error.build_diagnostic(0);
+    diagnostic.labels.iter().map(|label| (label.message.clone(), &source[label.range.clone()])).collect()
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use topaz_ast::file::TopazFile;
+use topaz_hir::lower::lower_gem;
+use topaz_hir::Callee;
+use topaz_mir::build::build_gem;
+use topaz_mir::dominators::Dominators;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::validate::validate_gem;
+use topaz_mir::{Body, MirGem, Operand, Place, TerminatorKind};
+use topaz_parser_next::Parse;
+use topaz_report::Reporter;
+use topaz_resolve::prelude::Prelude;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::ty::Ty;
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+const SOURCE: &str = r#"struct Guard { id: int }
+struct Pair { first: Guard, second: Guard }
+
+impl Drop for Guard {
+    func drop(move this) {}
+}
+
+func take(move guard: Guard) {}
+func look(guard: Guard) {}
+func make() -> Guard {
+    return Guard(id = 0);
+}
+
+func scopes() {
+    let a = Guard(id = 1);
+    let b = Guard(id = 2);
+    {
+        let c = Guard(id = 3);
+    }
+}
+
+func early(move guard: Guard, done: bool) {
+    let a = Guard(id = 1);
+    while done {
+        return;
+    }
+    take(guard);
+}
+
+func flagged(more: bool) {
+    let mut a = Guard(id = 1);
+    while more {
+        a = Guard(id = 2);
+        take(a);
+    }
+}
+
+func partial() {
+    let pair = Pair(first = Guard(id = 1), second = Guard(id = 2));
+    take(pair.first);
+    look(make());
+}
+
+func conditional(flag: bool) {
+    let guard = Guard(id = 1);
+    if flag {
+        take(guard);
+    }
+}
+
+func every_branch(flag: bool) {
+    let guard = Guard(id = 1);
+    if flag {
+        take(guard);
+    } else {
+        take(guard);
+    }
+}
+
+func reassigned() {
+    let mut guard = Guard(id = 1);
+    guard = Guard(id = 2);
+}
+
+func refilled(flag: bool) {
+    let mut guard = Guard(id = 1);
+    if flag {
+        take(guard);
+    }
+    guard = Guard(id = 2);
+}
+"#;
+
+/// The traits of `std` the drops depend on, in scope like with the prelude.
+const PRELUDE: &str = "import gem::mem::copy::Copy;\nimport gem::mem::drop::Drop;";
+
+fn build() -> MirGem {
+    let file = TopazFile::parse(SOURCE).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("test", true);
+    cx.set_prelude(Prelude::from_std_module(&TopazFile::parse(PRELUDE).expect("failed to parse the prelude")));
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let errors: Vec<_> = session
+        .output
+        .iter()
+        .filter(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)))
+        .map(|(file_id, unit)| unit.build_diagnostic(*file_id).message)
+        .collect();
+    assert!(errors.is_empty(), "{errors:?}");
+    build_gem(&lower_gem(&session))
+}
+
+/// The source name of `place`, e.g. `pair.second`, or `_6` for a temporary.
+fn place_name(gem: &MirGem, body: &Body, place: &Place) -> String {
+    let decl = body.local(place.local);
+    let mut name = decl.name.clone().unwrap_or_else(|| place.local.to_string());
+    let mut ty = decl.ty.clone();
+    for &index in &place.projection {
+        let Ty::Struct { def, .. } = ty else { panic!("a field of `{ty}`") };
+        let field = &gem.struct_def(def).expect("no struct").fields[index];
+        name = format!("{name}.{}", field.name);
+        ty = field.ty.clone();
+    }
+    name
+}
+
+/// Every drop of `body` in reverse postorder, and the flag it depends on, e.g. `drop(a) if _6`.
+fn drops(gem: &MirGem, body: &Body) -> Vec<String> {
+    // a flagged drop is the only thing its block does, which is only entered if the flag is set
+    let flags: HashMap<_, _> = body
+        .blocks
+        .iter()
+        .filter_map(|block| match &block.terminator.kind {
+            TerminatorKind::If { cond: Operand::Copy(flag) | Operand::Move(flag), then, .. }
+                if body.local(flag.local).name.is_none() && body.block(*then).statements.is_empty() =>
+            {
+                Some((*then, flag.local))
+            }
+            _ => None,
+        })
+        .collect();
+    Dominators::compute(body)
+        .reverse_postorder()
+        .iter()
+        .filter_map(|&block| match &body.block(block).terminator.kind {
+            TerminatorKind::Call { callee: Callee::Method { name, .. }, args, .. } if name.ends_with("::drop") => {
+                let [Operand::Move(place)] = &args[..] else { panic!("dropped {args:?}") };
+                let name = place_name(gem, body, place);
+                Some(match flags.get(&block) {
+                    Some(flag) => format!("drop({name}) if {flag}"),
+                    None => format!("drop({name})"),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_drops() {
+    let gem = build();
+    assert_eq!(validate_gem(&gem), vec![]);
+    let bodies: HashMap<_, _> = gem.bodies.iter().map(|body| (body.name.as_str(), drops(&gem, body))).collect();
+
+    // the inner scope first, then the function's in reverse
+    assert_eq!(bodies["scopes"], ["drop(c)", "drop(b)", "drop(a)"]);
+    // `guard` is only moved out of after the early return
+    assert_eq!(bodies["early"], ["drop(a)", "drop(guard)", "drop(a)"]);
+    // `a` is initialized when the loop is entered, but moved out of in the loop
+    assert_eq!(bodies["flagged"], ["drop(a) if _4", "drop(a) if _4"]);
+    // the temporary only borrowed by `look` is dropped with the function's locals
+    assert_eq!(bodies["partial"], ["drop(_6)", "drop(pair.second)"]);
+    // only dropped at the end of the scope if the branch was not taken
+    assert_eq!(bodies["conditional"], ["drop(guard) if _4"]);
+    assert!(bodies["every_branch"].is_empty(), "{:?}", bodies["every_branch"]);
+    // the old value is dropped once the new one is evaluated
+    assert_eq!(bodies["reassigned"], ["drop(guard)", "drop(guard)"]);
+    // unless it may have been moved out of already
+    assert_eq!(bodies["refilled"], ["drop(guard) if _4", "drop(guard)"]);
+    // nothing is left to drop in the method itself
+    assert!(bodies["drop"].is_empty(), "{:?}", bodies["drop"]);
+}
+
+#[test]
+fn test_ssa() {
+    let mut gem = build();
+    construct_gem(&mut gem);
+    assert_eq!(validate_gem(&gem), vec![]);
+}
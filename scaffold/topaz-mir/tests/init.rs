@@ -0,0 +1,230 @@
+mod common;
+
+use common::{labels, messages};
+use topaz_report::Reporter;
+use topaz_mir::borrowck::BorrowCheck;
+use topaz_type_checker::error::StaticAnalysisError;
+
+const SOURCE: &str = r#"func take(x: int) {}
+
+func deferred(cond: bool) {
+    let x: int;
+    if cond {
+        x = 1;
+    } else if true {
+        x = 2;
+    } else {
+        return;
+    }
+    take(x);
+}
+
+func one_branch(cond: bool) {
+    let x: int;
+    if cond {
+        x = 1;
+    }
+    take(x);
+}
+
+func never() {
+    let x: int;
+    take(x);
+}
+
+func looped(cond: bool) {
+    let x: int;
+    while cond {
+        x = 1;
+    }
+}
+
+func twice(cond: bool) {
+    let x: int;
+    if cond {
+        x = 1;
+    }
+    x = 2;
+    take(x);
+}
+
+func fresh(cond: bool) {
+    while cond {
+        let x: int;
+        x = 1;
+        take(x);
+    }
+}
+
+func mutable(cond: bool) {
+    let mut x: int;
+    x = 1;
+    x = 2;
+    take(x);
+}
+"#;
+
+/// The errors found in `source`, which has no warnings.
+fn check(source: &str) -> Vec<StaticAnalysisError> {
+    common::analyze(source, BorrowCheck, common::errors)
+}
+
+#[test]
+fn test_init() {
+    let errors = check(SOURCE);
+    assert_eq!(messages(&errors), [
+        "used binding `x` is possibly-uninitialized",
+        "used binding `x` isn't initialized",
+        "cannot assign twice to immutable variable `x`",
+        "cannot assign twice to immutable variable `x`",
+    ]);
+
+    assert_eq!(labels(SOURCE, &errors[0]), [
+        ("`x` used here but it is possibly-uninitialized".to_owned(), "x"),
+        ("binding declared here but left uninitialized".to_owned(), "x"),
+    ]);
+    assert_eq!(labels(SOURCE, &errors[2]).len(), 2, "the first assignment is this one, in a previous iteration");
+    assert_eq!(labels(SOURCE, &errors[3])[2], ("first assignment to `x`".to_owned(), "x = 1;"));
+}
+
+#[test]
+fn test_if_without_else() {
+    let source = r#"func take(x: int) {}
+
+func main(cond: bool) {
+    let x: int;
+    if cond {
+        x = 1;
+    }
+    take(x);
+}
+"#;
+    let errors = check(source);
+    assert_eq!(messages(&errors), ["used binding `x` is possibly-uninitialized"]);
+    assert_eq!(labels(source, &errors[0])[0], ("`x` used here but it is possibly-uninitialized".to_owned(), "x"));
+}
+
+#[test]
+fn test_assign_in_while() {
+    let source = r#"func take(x: int) {}
+
+func main(cond: bool) {
+    let mut x: int;
+    while cond {
+        x = 1;
+        take(x);
+    }
+    take(x);
+}
+"#;
+    // the loop may not run at all, but inside of it `x` is assigned before it is read
+    let errors = check(source);
+    assert_eq!(messages(&errors), ["used binding `x` is possibly-uninitialized"]);
+    let StaticAnalysisError::UninitializedUse { span, .. } = &errors[0] else { panic!("expected E039") };
+    assert_eq!(span.start, source.rfind("x);").unwrap(), "only the read after the loop");
+}
+
+#[test]
+fn test_assigned_once_on_every_branch() {
+    let source = r#"enum Shape {
+    Circle(int),
+    Rect(int, int),
+    Empty
+}
+
+func take(x: int) {}
+
+func branches(cond: bool) {
+    let x: int;
+    if cond {
+        x = 1;
+    } else {
+        x = 2;
+    }
+    take(x);
+}
+
+func arms(shape: Shape) {
+    let x: int;
+    match shape {
+        Shape::Circle(radius) => {
+            x = radius;
+        }
+        Shape::Rect(width, height) => {
+            x = width;
+        }
+        _ => {
+            x = 0;
+        }
+    }
+    take(x);
+}
+"#;
+    assert_eq!(messages(&check(source)), Vec::<String>::new());
+}
+
+#[test]
+fn test_match() {
+    let source = r#"enum Shape {
+    Circle(int),
+    Empty
+}
+
+func take(x: int) {}
+
+func missing_arm(shape: Shape) {
+    let x: int;
+    match shape {
+        Shape::Circle(radius) => {
+            x = radius;
+        }
+        Shape::Empty => {}
+    }
+    take(x);
+}
+
+func after_every_arm(shape: Shape) {
+    let x: int;
+    match shape {
+        Shape::Circle(radius) => {
+            x = radius;
+        }
+        Shape::Empty => {
+            x = 0;
+        }
+    }
+    x = 1;
+}
+
+func unassigned_scrutinee() {
+    let shape: Shape;
+    match shape {
+        _ => {}
+    }
+}
+"#;
+    let errors = check(source);
+    assert_eq!(messages(&errors), [
+        "used binding `x` is possibly-uninitialized",
+        "cannot assign twice to immutable variable `x`",
+        "used binding `shape` isn't initialized",
+    ]);
+    // an assignment in either arm may be the first one
+    assert_eq!(labels(source, &errors[1]).len(), 3);
+}
+
+#[test]
+fn test_assign_twice_in_loop() {
+    let source = r#"func main(cond: bool) {
+    let x: int;
+    while cond {
+        x = 1;
+    }
+}
+"#;
+    let errors = check(source);
+    assert_eq!(messages(&errors), ["cannot assign twice to immutable variable `x`"]);
+    let StaticAnalysisError::AssignTwice { first, .. } = &errors[0] else { panic!("expected E040, found {:?}", errors[0]) };
+    assert_eq!(*first, None, "the first assignment is this one, in a previous iteration");
+    assert_eq!(errors[0].build_diagnostic(0).code.as_deref(), Some("E040"));
+}
@@ -0,0 +1,196 @@
+mod common;
+
+use common::{labels, messages};
+use topaz_report::Reporter;
+use topaz_mir::borrowck::BorrowCheck;
+use topaz_type_checker::error::StaticAnalysisError;
+
+const SOURCE: &str = r#"struct Name { first: str, last: str }
+struct Point { x: int, y: int }
+struct Line { from: Point, to: Point }
+struct Label { text: str }
+
+impl Copy for Point {}
+impl Copy for Label {}
+
+func take(move name: Name) {}
+func take_str(move text: str) {}
+func look(name: Name) {}
+
+func twice() {
+    let name = Name(first = "ada", last = "lovelace");
+    take(name);
+    look(name);
+}
+
+func partial() {
+    let name = Name(first = "ada", last = "lovelace");
+    take_str(name.first);
+    take_str(name.last);
+    look(name);
+}
+
+func looped() {
+    let name = Name(first = "ada", last = "lovelace");
+    while true {
+        take(name);
+    }
+}
+
+func reassigned() {
+    let mut name = Name(first = "ada", last = "lovelace");
+    take(name);
+    name = Name(first = "grace", last = "hopper");
+    look(name);
+}
+
+func borrowed(name: Name) {
+    take_str(name.first);
+}
+
+func copied() {
+    let line = Line(from = Point(x = 0, y = 0), to = Point(x = 1, y = 1));
+    let from = line.from;
+    let again = line.from;
+    let x = line.to.x;
+    take_line(line);
+}
+
+func take_line(move line: Line) {}
+"#;
+
+/// The errors found by checking the moves of `source`: every use of a value that may have been
+/// moved out of, and every move out of a parameter the function only borrows.
+fn move_check(source: &str) -> Vec<StaticAnalysisError> {
+    common::analyze(source, BorrowCheck, common::errors)
+}
+
+/// The message of every label of `error`.
+fn label_messages(source: &str, error: &StaticAnalysisError) -> Vec<String> {
+    labels(source, error).into_iter().map(|(message, _)| message).collect()
+}
+
+#[test]
+fn test_moves() {
+    let errors = move_check(SOURCE);
+    assert_eq!(messages(&errors), [
+        "the trait `Copy` cannot be implemented for `Label`",
+        "use of moved value: `name`",
+        "use of partially moved value: `name`",
+        "use of moved value: `name`",
+        "cannot move out of `name.first`, which the function only borrows",
+    ]);
+
+    assert_eq!(label_messages(SOURCE, &errors[2]), ["value used here after partial move", "value moved here"]);
+    assert_eq!(errors[2].build_diagnostic(0).notes, [
+        "move occurs because `name.last` has type `str`, which is not `Copy`"
+    ]);
+    assert_eq!(label_messages(SOURCE, &errors[3]), [
+        "value used here after move",
+        "value moved here, in previous iteration of loop"
+    ]);
+}
+
+const NAME: &str = r#"struct Name { first: str, last: str }
+
+func take(move name: Name) {}
+func look(name: Name) {}
+"#;
+
+#[test]
+fn test_conditional_move() {
+    let source = format!(
+        "{NAME}{}",
+        r#"
+func conditional(flag: bool) {
+    let name = Name(first = "ada", last = "lovelace");
+    if flag {
+        take(name);
+    }
+    look(name);
+}
+
+func every_branch(flag: bool) {
+    let name = Name(first = "ada", last = "lovelace");
+    if flag {
+        take(name);
+    } else {
+        look(name);
+        take(name);
+    }
+}
+"#
+    );
+    // moved on one path is enough, moving on every path without a later use is fine
+    let errors = move_check(&source);
+    assert_eq!(messages(&errors), ["use of moved value: `name`"]);
+    assert_eq!(label_messages(&source, &errors[0]), ["value used here after move", "value moved here"]);
+}
+
+#[test]
+fn test_move_in_loop() {
+    let source = format!(
+        "{NAME}{}",
+        r#"
+func looped(flag: bool) {
+    let name = Name(first = "ada", last = "lovelace");
+    while flag {
+        look(name);
+        take(name);
+    }
+}
+
+func fresh(flag: bool) {
+    while flag {
+        let name = Name(first = "ada", last = "lovelace");
+        take(name);
+    }
+}
+
+func refilled(flag: bool) {
+    let mut name = Name(first = "ada", last = "lovelace");
+    while flag {
+        take(name);
+        name = Name(first = "grace", last = "hopper");
+    }
+}
+"#
+    );
+    // a binding declared in the loop, or assigned again before the next iteration, may be moved every time
+    let errors = move_check(&source);
+    assert_eq!(messages(&errors), ["use of moved value: `name`"]);
+    assert_eq!(label_messages(&source, &errors[0]), [
+        "value used here after move",
+        "value moved here, in previous iteration of loop"
+    ]);
+    let used = errors[0].build_diagnostic(0).labels[0].range.clone();
+    assert_eq!(&source[used.start - 5..used.end], "look(name");
+}
+
+#[test]
+fn test_move_out_of_drop() {
+    let source = r#"struct Guard { id: int }
+struct Pair { first: Guard, second: Guard }
+struct Holder { guard: Guard }
+
+impl Drop for Guard {
+    func drop(move this) {}
+}
+
+impl Drop for Holder {
+    func drop(move this) {}
+}
+
+func take(move guard: Guard) {}
+
+func pair(move pair: Pair) {
+    take(pair.first);
+}
+
+func holder(move holder: Holder) {
+    take(holder.guard);
+}
+"#;
+    // the fields of a value without a `Drop` impl of its own can be moved out of
+    assert_eq!(messages(&move_check(source)), ["cannot move out of `holder.guard`, because `Holder` implements `Drop`"]);
+}
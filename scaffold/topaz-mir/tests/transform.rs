@@ -0,0 +1,203 @@
+use topaz_ast::file::TopazFile;
+use topaz_hir::lower::lower_gem;
+use topaz_mir::build::build_gem;
+use topaz_mir::ssa::construct_gem;
+use topaz_mir::transform::{OptLevel, PassManager};
+use topaz_mir::MirGem;
+use topaz_parser_next::Parse;
+use topaz_report::Reporter;
+use topaz_resolve::{resolve_gem, ResolveContext, SourceModule};
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+fn build(source: &str) -> MirGem {
+    let file = TopazFile::parse(source).expect("failed to parse");
+    let modules = [SourceModule { file_id: 0, path: vec![], file: &file }];
+    let mut cx = ResolveContext::new("test", true);
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+
+    let session = StaticAnalyzer::with_default_passes(&resolutions, &modules).run();
+    let errors: Vec<_> = session
+        .output
+        .iter()
+        .filter(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)))
+        .map(|(file_id, unit)| unit.build_diagnostic(*file_id).message)
+        .collect();
+    assert!(errors.is_empty(), "{errors:?}");
+    let mut gem = build_gem(&lower_gem(&session));
+    construct_gem(&mut gem);
+    gem
+}
+
+/// The MIR of `source` before and after running only the pass called `pass`.
+fn run_pass(source: &str, pass: &str) -> (String, String) {
+    let mut gem = build(source);
+    let before = gem.to_string();
+    let mut manager = PassManager::new(OptLevel::O0);
+    assert!(manager.set_enabled(pass, true), "no pass called `{pass}`");
+    manager.run(&mut gem).expect("invalid MIR");
+    (before, gem.to_string())
+}
+
+#[test]
+fn test_const_prop() {
+    let (before, after) = run_pass(
+        r#"struct Point { x: int, y: int }
+
+func pick() -> int {
+    let flag = true;
+    let mut value = 1;
+    if flag {
+        value = 2;
+    } else {
+        value = 3;
+    }
+    let point = Point(x = value, y = 4);
+    return point.x;
+}
+"#,
+        "const_prop",
+    );
+    insta::assert_snapshot!("const_prop_before", before);
+    insta::assert_snapshot!("const_prop_after", after);
+}
+
+#[test]
+fn test_const_prop_operators() {
+    let (before, after) = run_pass(
+        r#"func compute() -> int {
+    let width = 6 * 7;
+    let mut value = width - 2;
+    let far = width > 40;
+    if !(far == (value == 0)) {
+        value = -value;
+    }
+    let small: byte = 100;
+    let overflowing = small + small;
+    let zero = 0;
+    let divided = value / zero;
+    return value % 3;
+}
+"#,
+        "const_prop",
+    );
+    insta::assert_snapshot!("const_prop_operators_before", before);
+    insta::assert_snapshot!("const_prop_operators_after", after);
+}
+
+#[test]
+fn test_copy_prop() {
+    let (before, after) = run_pass(
+        r#"struct Point { x: int, y: int }
+
+func pick(move first: Point, flag: bool) -> int {
+    let copied = first;
+    let again = copied;
+    let mut same = again.x;
+    if flag {
+        same = again.x;
+    }
+    let point = Point(x = same, y = again.y);
+    return point.x;
+}
+"#,
+        "copy_prop",
+    );
+    insta::assert_snapshot!("copy_prop_before", before);
+    insta::assert_snapshot!("copy_prop_after", after);
+}
+
+#[test]
+fn test_dce() {
+    let (before, after) = run_pass(
+        r#"struct Point { x: int, y: int }
+
+func next(value: int) -> int {
+    return value;
+}
+
+func pick(first: int) -> int {
+    let unused = Point(x = first, y = 2);
+    let borrowed = &unused;
+    let kept = first;
+    let ignored = next(kept);
+    return kept;
+}
+"#,
+        "dce",
+    );
+    insta::assert_snapshot!("dce_before", before);
+    insta::assert_snapshot!("dce_after", after);
+}
+
+#[test]
+fn test_simplify_cfg() {
+    let (before, after) = run_pass(
+        r#"func running(count: int) -> bool {
+    return true;
+}
+
+func next(count: int) -> int {
+    return count;
+}
+
+func count(start: int) -> int {
+    let mut count = start;
+    while running(count) {
+        if running(start) {
+            count = next(count);
+        } else {
+        }
+    }
+    return count;
+}
+"#,
+        "simplify_cfg",
+    );
+    insta::assert_snapshot!("simplify_cfg_before", before);
+    insta::assert_snapshot!("simplify_cfg_after", after);
+}
+
+const INLINE: &str = r#"struct Point { x: int, y: int }
+
+func first(point: Point, flag: bool) -> int {
+    if flag {
+        return point.x;
+    }
+    return point.y;
+}
+
+func log(value: int) {
+}
+
+func main() {
+    let point = Point(x = 1, y = 2);
+    let x = first(point, true);
+    log(x);
+}
+"#;
+
+#[test]
+fn test_inline() {
+    let (before, after) = run_pass(INLINE, "inline");
+    insta::assert_snapshot!("inline_before", before);
+    insta::assert_snapshot!("inline_after", after);
+}
+
+#[test]
+fn test_levels() {
+    let mut gem = build(INLINE);
+    PassManager::new(OptLevel::O2).run(&mut gem).expect("invalid MIR");
+    insta::assert_snapshot!("inline_o2", gem.to_string());
+
+    let mut unoptimized = build(INLINE);
+    PassManager::new(OptLevel::O0).run(&mut unoptimized).expect("invalid MIR");
+    assert_eq!(unoptimized, build(INLINE));
+
+    let mut manager = PassManager::new(OptLevel::O3);
+    assert!(manager.set_enabled("inline", false));
+    assert!(!manager.set_enabled("unroll", false));
+    let mut gem = build(INLINE);
+    manager.run(&mut gem).expect("invalid MIR");
+    assert!(gem.to_string().contains(" = first("), "{gem}");
+}
@@ -2,6 +2,7 @@ use crate::TopazParseError;
 use lalrpop_util::state_machine::TokenTriple;
 use logos::{Logos, Span, SpannedIter};
 use topaz_ast::ident::Ident;
+use topaz_ast::literal::number::{DecimalDigit, NormalNumber, NumberSign, NumberSuffix};
 use topaz_ast::{CustomTokens, WithSpannable};
 
 pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
@@ -25,6 +26,40 @@ pub enum Token {
     This,
     #[token("gem")]
     Gem,
+    #[token("super")]
+    Super,
+    #[token("as")]
+    As,
+    #[token("return")]
+    Return,
+    #[token("typealias")]
+    TypeAlias,
+    #[token("trait")]
+    Trait,
+    #[token("impl")]
+    Impl,
+    #[token("for")]
+    For,
+    #[token("maybe")]
+    Maybe,
+    #[token("move")]
+    Move,
+    #[token("struct")]
+    Struct,
+    #[token("enum")]
+    Enum,
+    #[token("match")]
+    Match,
+    #[token("while")]
+    While,
+    #[token("if")]
+    If,
+    #[token("else")]
+    Else,
+    #[token("true")]
+    True,
+    #[token("false")]
+    False,
 
     // Punctuation
     #[token("::")]
@@ -45,19 +80,60 @@ pub enum Token {
     OpenCurly,
     #[token("}")]
     CloseCurly,
+    #[token("[")]
+    OpenBracket,
+    #[token("]")]
+    CloseBracket,
     #[token(r#"""#)]
     StrDelim,
     #[token("->")]
     Arrow,
+    #[token("=>")]
+    FatArrow,
     #[token(";")]
     Semi,
+    #[token("@")]
+    At,
+    #[token("@!")]
+    AtBang,
+    #[token("&")]
+    Ampersand,
+    #[token("_")]
+    Underscore,
+    #[token("<")]
+    Less,
+    #[token(">")]
+    Greater,
+    #[token("<=")]
+    LessEqual,
+    #[token(">=")]
+    GreaterEqual,
+    #[token("==")]
+    EqualEqual,
+    #[token("!=")]
+    NotEqual,
+    #[token("!")]
+    Bang,
+    #[token("+")]
+    Plus,
+    #[token("-")]
+    Minus,
+    #[token("*")]
+    Star,
+    #[token("/")]
+    Slash,
+    #[token("%")]
+    Percent,
 
-    #[regex(r"[a-zA-Z_][a-zA-Z\d_]*", |lex| Ident::new(lex.slice()))]
+    #[regex(r"[a-zA-Z_][a-zA-Z\d_]*", |lex| Ident::new(lex.slice()).spanned(lex.span().into()))]
     Ident(Ident),
 
     #[regex(r#""[^\n"]*""#, |lex| lex.slice().trim_matches('"').to_owned())]
     LitStr(String),
 
+    #[regex(r"\d+(usize|isize|ubyte|byte|ushort|short|uint|int|ulong|long|uexplod|explod)?", |lex| number(lex.slice()))]
+    Number(NormalNumber),
+
     #[token("byte")]
     TypeByte,
     #[token("ubyte")]
@@ -68,6 +144,24 @@ pub enum Token {
     TypeUint,
     #[token("str")]
     TypeString,
+    #[token("usize")]
+    TypeUsize,
+    #[token("isize")]
+    TypeIsize,
+    #[token("ushort")]
+    TypeUshort,
+    #[token("short")]
+    TypeShort,
+    #[token("ulong")]
+    TypeUlong,
+    #[token("long")]
+    TypeLong,
+    #[token("uexplod")]
+    TypeUexplod,
+    #[token("explod")]
+    TypeExplod,
+    #[token("bool")]
+    TypeBool,
 
     #[error]
     #[regex(r"[ \t\n]+", logos::skip)]
@@ -76,6 +170,23 @@ pub enum Token {
 
 impl CustomTokens for Token {}
 
+/// Splits a decimal literal like `42ubyte` into its digits and suffix.
+fn number(slice: &str) -> Option<NormalNumber> {
+    let suffix_start = slice.find(|c: char| !c.is_ascii_digit()).unwrap_or(slice.len());
+    let (digits, suffix) = slice.split_at(suffix_start);
+    let digits = digits
+        .bytes()
+        .map(|digit| DecimalDigit::from_value(digit - b'0'))
+        .collect::<Option<_>>()?;
+    let suffix = if suffix.is_empty() {
+        NumberSuffix::None
+    } else {
+        NumberSuffix::from_name(suffix)?
+    };
+
+    Some(NormalNumber(NumberSign::Positive, digits, suffix))
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum LexError {
     #[error("Invalid token: {0}")]
@@ -1,8 +1,11 @@
 #![feature(default_free_fn)]
 #![feature(box_patterns)]
 
-use lalrpop_util::ParseError;
-use topaz_ast::location::WithSpan;
+pub use lalrpop_util::ParseError;
+use topaz_ast::expr::{Expr, ExprInterpolated, ExprLit, ExprVarAccess};
+use topaz_ast::ident::Ident;
+use topaz_ast::literal::{Literal, LiteralString};
+use topaz_ast::location::{Span, WithSpan};
 use topaz_ast::punctuated::Punctuated;
 use topaz_ast::Tokens;
 lalrpop_util::lalrpop_mod!(pub(crate) grammar, "/src/grammar.rs");
@@ -34,3 +37,35 @@ fn make_real<T: Tokens, P: Tokens>(mut t: (Vec<T>, T)) -> Punctuated<T, P> {
     t.0.push(t.1);
     Punctuated::from_segments(t.0)
 }
+
+/// The string literal of `content`, which starts at `start` in the source, or an [`ExprInterpolated`] if
+/// it has variables in it, like `$name`. A `$` not followed by an identifier is kept as it is, e.g. in `"$5"`.
+fn interpolate(content: String, start: usize) -> Expr {
+    let text = |from: usize, to: usize| {
+        let literal = Literal::String(LiteralString::from(&content[from..to]));
+        WithSpan::new(Expr::Literal(ExprLit(literal)), Span::new(start + from, start + to))
+    };
+    let mut parts = Vec::new();
+    // where the text not added to the parts yet starts, and where to look for the next `$`
+    let (mut pending, mut next) = (0, 0);
+    while let Some(offset) = content[next..].find('$') {
+        let name_start = next + offset + 1;
+        let len = content[name_start..].find(|c: char| !c.is_ascii_alphanumeric() && c != '_');
+        let name_end = len.map_or(content.len(), |len| name_start + len);
+        next = name_start;
+        let Some(name) = Ident::new_checked(&content[name_start..name_end]) else { continue };
+        if pending < name_start - 1 {
+            parts.push(text(pending, name_start - 1));
+        }
+        let span = Span::new(start + name_start, start + name_end);
+        parts.push(WithSpan::new(Expr::VariableAccess(ExprVarAccess(name.spanned(span).into())), span));
+        (pending, next) = (name_end, name_end);
+    }
+    if parts.is_empty() {
+        return Expr::Literal(ExprLit(Literal::String(LiteralString::from(content))));
+    }
+    if pending < content.len() {
+        parts.push(text(pending, content.len()));
+    }
+    Expr::Interpolated(ExprInterpolated(parts))
+}
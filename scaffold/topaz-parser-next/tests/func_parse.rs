@@ -2,12 +2,13 @@
 
 use std::default::default;
 use topaz_ast::block::Block;
-use topaz_ast::expr::{Expr, ExprLit};
+use topaz_ast::expr::{Expr, ExprLit, ExprVarAccess};
 use topaz_ast::file::TopazFile;
 use topaz_ast::ident::Ident;
-use topaz_ast::item::func::Func;
+use topaz_ast::item::func::{Func, FuncBody};
 use topaz_ast::item::Item;
 use topaz_ast::literal::{Literal, LiteralString};
+use topaz_ast::location::{Span, WithSpan};
 use topaz_ast::path::Path;
 use topaz_ast::punctuated::Punctuated;
 use topaz_ast::statement::func_call::{FuncCallArg, FuncCallStmt};
@@ -30,22 +31,46 @@ pub fn test_func_parse() {
     assert_eq!(
         parsed.unwrap(),
         TopazFile {
-            items: vec![Item::Func(Func(
+            attributes: Vec::new(),
+            items: vec![WithSpan::new(Item::Func(Func(
                 default(),
                 default(),
                 Ident::new("main"),
                 Vec::new(),
                 None,
-                Block(Surround::new(vec![Statement::FuncCall(FuncCallStmt(
+                FuncBody::Block(Block(Surround::new(vec![WithSpan::new(Statement::FuncCall(FuncCallStmt(
                     Path::parse("println").unwrap(),
                     Surround::new(Punctuated::single(FuncCallArg(
                         None,
-                        Expr::Literal(ExprLit(Literal::String(LiteralString(StringLit::new(
+                        WithSpan::new(Expr::Literal(ExprLit(Literal::String(LiteralString(StringLit::new(
                             String::from("Hello world!")
-                        )))))
+                        ))))), Span::new(27, 41))
                     )))
-                ))]))
-            ))]
+                )), Span::new(19, 43))]), vec![Vec::new()]))
+            )), Span::new(1, 45))],
+            item_attributes: vec![Vec::new()],
         }
     );
 }
+
+#[test]
+pub fn test_interpolation_parse() {
+    let source = r#"func greeting(name: str) -> str = "hi $name, it is $5 $";"#;
+    let parsed = TopazFile::parse(source).expect("failed to parse");
+    let Item::Func(Func(.., FuncBody::Expr(_, body))) = &parsed.items[0].value else { panic!("not an expression body") };
+    let Expr::Interpolated(interpolated) = &body.value else { panic!("not an interpolated string") };
+
+    // a `$` not followed by an identifier is text, and every part spans its own text
+    let text = |text: &str| Expr::Literal(ExprLit(Literal::String(LiteralString::from(text))));
+    let parts: Vec<_> = interpolated.parts().map(|part| &source[part.span.start..part.span.end]).collect();
+    assert_eq!(parts, ["hi ", "name", ", it is $5 $"]);
+    let parts: Vec<_> = interpolated.parts().map(|part| &part.value).collect();
+    assert_eq!(parts[0], &text("hi "));
+    assert!(matches!(parts[1], Expr::VariableAccess(ExprVarAccess(path)) if path.to_string() == "name"));
+    assert_eq!(parts[2], &text(", it is $5 $"));
+
+    // without variables, it is a string literal
+    let parsed = TopazFile::parse(r#"func price() -> str = "$5";"#).expect("failed to parse");
+    let Item::Func(Func(.., FuncBody::Expr(_, body))) = &parsed.items[0].value else { panic!("not an expression body") };
+    assert_eq!(body.value, text("$5"));
+}
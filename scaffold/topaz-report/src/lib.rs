@@ -1,7 +1,7 @@
 use codespan_reporting::diagnostic::{Label, LabelStyle, Severity};
 use codespan_reporting::{
     diagnostic::Diagnostic,
-    files::SimpleFiles,
+    files::{Files, SimpleFiles},
     term::{
         self,
         termcolor::{ColorChoice, StandardStream},
@@ -10,6 +10,9 @@ use codespan_reporting::{
 };
 use std::ops::Range;
 use std::process::exit;
+use suggestion::Suggestion;
+
+pub mod suggestion;
 
 /// Stores basic `codespan_reporting` structs for reporting errors.
 pub struct ReporterState<'f> {
@@ -31,6 +34,11 @@ impl<'f> ReporterState<'f> {
         }
     }
 
+    pub fn emit<'a, F: Files<'a, FileId = usize>>(&self, files: &'a F, diagnostic: &Diagnostic<usize>) {
+        term::emit(&mut self.writer.lock(), &self.config, files, diagnostic)
+            .expect("emit() failed");
+    }
+
     pub fn emit_global_error(&self, msg: &str) {
         term::emit(
             &mut self.writer.lock(),
@@ -93,10 +101,10 @@ impl Default for ReporterState<'_> {
 }
 
 pub trait Reporter<'source> {
-    fn emit_diagnostic(
+    fn emit_diagnostic<'f, F: Files<'f, FileId = usize>>(
         &self,
         reporter: &ReporterState,
-        files: &SimpleFiles<&str, &str>,
+        files: &'f F,
         file_id: usize,
     ) {
         term::emit(
@@ -109,4 +117,9 @@ pub trait Reporter<'source> {
     }
 
     fn build_diagnostic(&self, file_id: usize) -> Diagnostic<usize>;
+
+    /// Edits fixing the problem, see [`suggestion::apply_suggestions`].
+    fn suggestions(&self) -> Vec<Suggestion> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,112 @@
+//! `suggestion.rs` - edits attached to diagnostics, which tools can apply on their own.
+use codespan_reporting::diagnostic::Label;
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Applicability {
+    /// The edit is certainly what the user wants and can be applied without a review
+    MachineApplicable,
+    /// The edit is likely correct, but it may change what the program does
+    MaybeIncorrect,
+}
+
+/// Replaces the source in `span` with `replacement`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Range<usize>,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+impl Suggestion {
+    pub fn removal(message: impl Into<String>, span: impl Into<Range<usize>>) -> Self {
+        Self::replacement(message, span, "")
+    }
+
+    pub fn replacement(
+        message: impl Into<String>,
+        span: impl Into<Range<usize>>,
+        replacement: impl Into<String>,
+    ) -> Self {
+        Self {
+            message: message.into(),
+            span: span.into(),
+            replacement: replacement.into(),
+            applicability: Applicability::MachineApplicable,
+        }
+    }
+
+    #[must_use]
+    pub fn maybe_incorrect(self) -> Self {
+        Self {
+            applicability: Applicability::MaybeIncorrect,
+            ..self
+        }
+    }
+
+    /// A label pointing at the code the suggestion changes.
+    #[must_use]
+    pub fn label(&self, file_id: usize) -> Label<usize> {
+        let message = if self.replacement.is_empty() {
+            format!("help: {}", self.message)
+        } else {
+            format!("help: {}: `{}`", self.message, self.replacement)
+        };
+        Label::secondary(file_id, self.span.clone()).with_message(message)
+    }
+}
+
+/// Applies every machine applicable suggestion to `source`.
+///
+/// A removal leaving nothing but whitespace on its lines removes the lines, newline included.
+/// Suggestions overlapping an already applied one are skipped.
+#[must_use]
+pub fn apply_suggestions<'a>(
+    source: &str,
+    suggestions: impl IntoIterator<Item = &'a Suggestion>,
+) -> String {
+    let mut suggestions: Vec<_> = suggestions
+        .into_iter()
+        .filter(|suggestion| suggestion.applicability == Applicability::MachineApplicable)
+        .map(|suggestion| {
+            let span = if suggestion.replacement.is_empty() {
+                whole_lines(source, &suggestion.span)
+            } else {
+                suggestion.span.clone()
+            };
+            (span, &suggestion.replacement)
+        })
+        .collect();
+    suggestions.sort_by_key(|(span, _)| (span.start, span.end));
+
+    let mut output = String::with_capacity(source.len());
+    let mut position = 0;
+    for (span, replacement) in suggestions {
+        if span.start < position {
+            continue;
+        }
+        output.push_str(&source[position..span.start]);
+        output.push_str(replacement);
+        position = span.end;
+    }
+    output.push_str(&source[position..]);
+    output
+}
+
+/// `span` with the indentation before it and the newline after it, if there is only whitespace
+/// between them and the code in `span`, so that removing it does not leave an empty line.
+fn whole_lines(source: &str, span: &Range<usize>) -> Range<usize> {
+    let start = source[..span.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let end = source[span.end..]
+        .find('\n')
+        .map_or(source.len(), |newline| span.end + newline + 1);
+    let blank = |text: &str| text.trim().is_empty();
+    if blank(&source[start..span.start]) && blank(&source[span.end..end]) {
+        start..end
+    } else {
+        span.clone()
+    }
+}
@@ -0,0 +1,28 @@
+use topaz_report::suggestion::{apply_suggestions, Suggestion};
+
+#[test]
+fn test_apply_suggestions() {
+    let source = "let mut x = 1; let y = 2;";
+    let suggestions = [
+        Suggestion::removal("remove the binding", 15..25),
+        Suggestion::removal("remove `mut`", 4..8),
+        Suggestion::replacement("prefix it with an underscore", 4..9, "_x").maybe_incorrect(),
+        // overlaps with the removal of `mut`
+        Suggestion::replacement("rename it", 6..9, "z"),
+    ];
+
+    assert_eq!(apply_suggestions(source, &suggestions), "let x = 1; ");
+}
+
+#[test]
+fn test_remove_lines() {
+    // a removal leaving its lines empty takes their indentation and newline with it
+    let source = "{\n    let x = 1;\n    let y = 2; let z = 3;\n    f(\n        x\n    );\n}";
+    let suggestions = [
+        Suggestion::removal("remove `x`", 6..16),
+        Suggestion::removal("remove `y`", 21..31),
+        Suggestion::removal("remove the call", 47..66),
+    ];
+
+    assert_eq!(apply_suggestions(source, &suggestions), "{\n     let z = 3;\n}");
+}
@@ -0,0 +1,104 @@
+use crate::module::ModuleId;
+use topaz_ast::ident::Ident;
+use topaz_ast::location::Span;
+
+/// Identifies a single definition (an item, an import or a local binding) in a gem.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DefId(pub u32);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DefKind {
+    Module,
+    Func,
+    TypeAlias,
+    Trait,
+    Struct,
+    Enum,
+    /// A variant of an enum, named through it like `Shape::Circle`
+    Variant,
+    /// A method of a trait, named through it like `Default::default`
+    Method,
+    /// A generic parameter of an impl or a type alias
+    TypeParam,
+    Import,
+    /// A function parameter
+    Param,
+    /// A `let` binding
+    Local,
+}
+
+impl DefKind {
+    #[must_use]
+    pub const fn descr(&self) -> &'static str {
+        match self {
+            Self::Module => "module",
+            Self::Func => "function",
+            Self::TypeAlias => "type alias",
+            Self::Trait => "trait",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::Variant => "variant",
+            Self::Method => "method",
+            Self::TypeParam => "type parameter",
+            Self::Import => "import",
+            Self::Param => "parameter",
+            Self::Local => "local variable",
+        }
+    }
+
+    #[must_use]
+    pub const fn is_item(&self) -> bool {
+        matches!(self, Self::Module | Self::Func | Self::TypeAlias | Self::Trait | Self::Struct | Self::Enum)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DefData {
+    pub kind: DefKind,
+    pub name: Ident,
+    /// The module the definition lives in.
+    pub module: ModuleId,
+    pub file_id: usize,
+}
+
+impl DefData {
+    #[must_use]
+    pub const fn span(&self) -> Span {
+        self.name.span()
+    }
+}
+
+/// Arena of every definition found while resolving a gem.
+#[derive(Debug, Default)]
+pub struct Definitions {
+    defs: Vec<DefData>,
+}
+
+impl Definitions {
+    pub fn add(&mut self, data: DefData) -> DefId {
+        self.defs.push(data);
+        DefId(u32::try_from(self.defs.len() - 1).expect("too many definitions"))
+    }
+
+    #[must_use]
+    pub fn get(&self, id: DefId) -> &DefData {
+        &self.defs[id.0 as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (DefId, &DefData)> {
+        self.defs
+            .iter()
+            .enumerate()
+            .map(|(i, data)| (DefId(i as u32), data))
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+}
@@ -1,31 +1,273 @@
-use topaz_ast::ident::Ident;
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use std::collections::{HashMap, HashSet};
 use string_interner::{StringInterner, symbol::SymbolU32};
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_ast::location::Span;
 use topaz_ast::path::{AsClause, Path};
+use topaz_report::Reporter;
+
+pub mod def;
+pub mod module;
+pub mod prelude;
+mod resolver;
+
+use def::{DefId, Definitions};
+use module::{ModuleId, ModuleTree};
+use prelude::Prelude;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Namespace {
     Core,
     Std,
     Gem(SymbolU32)
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ResolvedPath { Path(Namespace, Vec<Ident>, Option<AsClause>) }
 
-#[derive(thiserror::Error, Debug)]
+/// What a path resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Res {
+    /// An item or a local binding of the gem being resolved
+    Def(DefId),
+    Module(ModuleId),
+    /// Something in a namespace outside of the gem being resolved
+    External(ResolvedPath),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
 pub enum ResolveError {
     #[error("Namespace not found: {0}")]
-    NamespaceNotFound(String),
+    NamespaceNotFound(String, Span),
     #[error("Empty path")]
     EmptyPath,
     #[error("Reference to stdlib path in a no-std context")]
-    NoStd
+    NoStd(Span),
+    #[error("`{name}` is part of the std prelude, which is not available in a no-std gem")]
+    StdPrelude { name: String, span: Span },
+    #[error("Unknown attribute `@{name}`")]
+    UnknownAttribute { name: String, span: Span },
+    #[error("`@!{name}` can only be used in the root module of a gem")]
+    MisplacedAttribute { name: String, span: Span },
+    #[error("Cannot find `{name}` in {}", .scope.as_deref().unwrap_or("this scope"))]
+    Unresolved {
+        name: String,
+        /// The module the name was looked up in, `None` for the current scope
+        scope: Option<String>,
+        span: Span,
+    },
+    #[error("Expected a module, found {kind} `{name}`")]
+    NotAModule {
+        name: String,
+        kind: &'static str,
+        span: Span,
+    },
+    #[error("`{name}` is defined multiple times")]
+    Duplicate {
+        name: String,
+        /// The file and the span of the first definition
+        first: (usize, Span),
+        span: Span,
+    },
+}
+
+impl<'source> Reporter<'source> for ResolveError {
+    fn build_diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+        match self {
+            Self::Unresolved { name, scope, span } => Diagnostic::error()
+                .with_code("E004")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message(
+                    scope.as_ref().map_or_else(
+                        || "not found in this scope".to_owned(),
+                        |scope| format!("not found in `{scope}`"),
+                    ),
+                )])
+                .with_notes(vec![format!("`{name}` has to be declared or imported before use")]),
+            Self::NamespaceNotFound(name, span) => Diagnostic::error()
+                .with_code("E005")
+                .with_message(format!("namespace `{name}` not found"))
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message("not a module, a gem or a dependency")]),
+            Self::NoStd(span) => Diagnostic::error()
+                .with_code("E006")
+                .with_message("reference to the standard library in a no-std gem")
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message("`std` is not available here")])
+                .with_notes(vec!["consider using `core` instead".to_owned()]),
+            Self::StdPrelude { name, span } => Diagnostic::error()
+                .with_code("E006")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message("not found in this scope")])
+                .with_notes(vec![format!("consider defining `{name}` in this gem or importing it from `core`")]),
+            Self::UnknownAttribute { span, .. } => Diagnostic::error()
+                .with_code("E015")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("unknown attribute")])
+                .with_notes(vec![
+                    format!(
+                        "the file attributes are `@!{}` and `@!{}`, before the first item",
+                        prelude::NO_STD,
+                        prelude::NO_PRELUDE
+                    ),
+                    "the level of lints is set with `@allow`, `@warn`, `@deny` and `@forbid` in front of an item \
+                     or a statement, or with `@!allow` and so on for the whole file"
+                        .to_owned(),
+                ]),
+            Self::MisplacedAttribute { span, .. } => Diagnostic::error()
+                .with_code("E015")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message("not allowed in this module")]),
+            Self::NotAModule { span, .. } => Diagnostic::error()
+                .with_code("E008")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("not a module")]),
+            Self::Duplicate { name, first, span } => Diagnostic::error()
+                .with_code("E007")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message(format!("`{name}` redefined here")),
+                    Label::secondary(first.0, first.1)
+                        .with_message(format!("previous definition of `{name}` here")),
+                ]),
+            Self::EmptyPath => Diagnostic::bug().with_message(self.to_string()),
+        }
+    }
 }
 
 pub struct ResolveContext {
     intern: StringInterner,
     has_std: bool,
-    gems: Vec<SymbolU32>
+    /// The gem being resolved.
+    gem: SymbolU32,
+    gems: Vec<SymbolU32>,
+    /// Names in scope in every file of a gem with a standard library
+    prelude: Option<Prelude>,
+}
+
+impl ResolveContext {
+    #[must_use]
+    pub fn new(gem: &str, has_std: bool) -> Self {
+        let mut intern = StringInterner::new();
+        let gem = intern.get_or_intern(gem);
+        Self {
+            intern,
+            has_std,
+            gem,
+            gems: vec![gem],
+            prelude: None,
+        }
+    }
+
+    /// Injects `prelude` into every file that does not opt out of it with `@no_prelude`.
+    ///
+    /// The prelude is a part of the standard library, so it's only used if the gem has one.
+    pub fn set_prelude(&mut self, prelude: Prelude) {
+        self.prelude = Some(prelude);
+    }
+
+    #[must_use]
+    pub const fn prelude(&self) -> Option<&Prelude> {
+        self.prelude.as_ref()
+    }
+
+    /// Makes the gem `name` visible as a namespace.
+    pub fn add_gem(&mut self, name: &str) -> SymbolU32 {
+        let symbol = self.intern.get_or_intern(name);
+        if !self.gems.contains(&symbol) {
+            self.gems.push(symbol);
+        }
+        symbol
+    }
+
+    #[must_use]
+    pub fn gem_symbol(&self, name: &str) -> Option<SymbolU32> {
+        self.intern.get(name).filter(|symbol| self.gems.contains(symbol))
+    }
+
+    #[must_use]
+    pub fn gem_name(&self, symbol: SymbolU32) -> Option<&str> {
+        self.intern.resolve(symbol)
+    }
+
+    #[must_use]
+    pub const fn current_gem(&self) -> SymbolU32 {
+        self.gem
+    }
+
+    #[must_use]
+    pub const fn has_std(&self) -> bool {
+        self.has_std
+    }
+}
+
+/// Determines the namespace an absolute path starts in.
+///
+/// `gem::a::b` starts in the current gem, `std::mem::drop` in the standard library,
+/// and `other::thing` in the gem `other`, if it's known to the context.
+pub fn resolve(cx: &mut ResolveContext, path: &Path) -> Result<ResolvedPath, ResolveError> {
+    let mut segments = path.segments();
+    let first = segments.next().ok_or(ResolveError::EmptyPath)?;
+    let namespace = match first.value() {
+        "core" => Namespace::Core,
+        "std" if cx.has_std => Namespace::Std,
+        "std" => return Err(ResolveError::NoStd(first.span())),
+        "gem" => Namespace::Gem(cx.gem),
+        name => Namespace::Gem(
+            cx.gem_symbol(name)
+                .ok_or_else(|| ResolveError::NamespaceNotFound(name.to_owned(), first.span()))?,
+        ),
+    };
+
+    Ok(ResolvedPath::Path(namespace, segments.copied().collect(), None))
 }
 
-pub fn resolve(cx: &mut ResolveContext, path: Path) -> Result<ResolvedPath, ResolveError> {
+/// A parsed file and the module it declares.
+#[derive(Clone)]
+pub struct SourceModule<'a> {
+    pub file_id: usize,
+    /// Module path relative to the gem root, empty for the root module itself
+    pub path: Vec<Ident>,
+    pub file: &'a TopazFile,
+}
+
+/// The result of resolving every name in a gem.
+#[derive(Debug, Default)]
+pub struct Resolutions {
+    pub defs: Definitions,
+    pub modules: ModuleTree,
+    /// What each path (keyed by its file and span) resolved to
+    pub paths: HashMap<(usize, Span), Res>,
+    /// The definition each declared name (keyed by its file and span) introduced
+    pub decls: HashMap<(usize, Span), DefId>,
+    /// The variants of every enum, in the order they are declared
+    pub variants: HashMap<DefId, Vec<DefId>>,
+    /// The methods of every trait, in the order they are declared
+    pub methods: HashMap<DefId, Vec<DefId>>,
+    /// Imports that at least one path was resolved through
+    pub used_imports: HashSet<DefId>,
+    pub errors: Vec<(usize, ResolveError)>,
+}
+
+impl Resolutions {
+    #[must_use]
+    pub fn res_of(&self, file_id: usize, path: &Path) -> Option<&Res> {
+        self.paths.get(&(file_id, path.span()))
+    }
+
+    #[must_use]
+    pub fn decl_of(&self, file_id: usize, name: &Ident) -> Option<DefId> {
+        self.decls.get(&(file_id, name.span())).copied()
+    }
+}
 
+/// Builds the module tree of a gem and resolves every path in it.
+pub fn resolve_gem<'a>(
+    cx: &mut ResolveContext,
+    modules: impl IntoIterator<Item = SourceModule<'a>>,
+) -> Resolutions {
+    resolver::Resolver::new(cx).run(modules.into_iter().collect())
 }
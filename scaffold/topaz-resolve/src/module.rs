@@ -0,0 +1,114 @@
+use crate::def::DefId;
+use crate::Res;
+use std::collections::HashMap;
+use topaz_ast::ident::Ident;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ModuleId(pub u32);
+
+/// A name declared in a module's item scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub res: Res,
+    /// The `import` this binding was introduced by, if any.
+    pub import: Option<DefId>,
+}
+
+#[derive(Debug, Default)]
+pub struct ModuleData {
+    /// `None` for the gem root.
+    pub name: Option<Ident>,
+    pub parent: Option<ModuleId>,
+    pub children: HashMap<Ident, ModuleId>,
+    pub names: HashMap<Ident, Binding>,
+    /// The file the module was declared by, if any.
+    /// Directories without a file of their own do not have one.
+    pub file_id: Option<usize>,
+}
+
+/// The module hierarchy of a single gem.
+///
+/// `gem::mem::drop` is the item `drop` in the module `mem`,
+/// which is a child of the root module.
+#[derive(Debug)]
+pub struct ModuleTree {
+    modules: Vec<ModuleData>,
+}
+
+impl Default for ModuleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleTree {
+    pub const ROOT: ModuleId = ModuleId(0);
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            modules: vec![ModuleData::default()],
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, id: ModuleId) -> &ModuleData {
+        &self.modules[id.0 as usize]
+    }
+
+    pub fn get_mut(&mut self, id: ModuleId) -> &mut ModuleData {
+        &mut self.modules[id.0 as usize]
+    }
+
+    #[must_use]
+    pub fn child(&self, parent: ModuleId, name: &Ident) -> Option<ModuleId> {
+        self.get(parent).children.get(name).copied()
+    }
+
+    /// Returns the module at `path` (relative to the root), creating every missing module on the way.
+    pub fn get_or_insert(&mut self, path: &[Ident]) -> ModuleId {
+        let mut current = Self::ROOT;
+        for segment in path {
+            current = if let Some(child) = self.child(current, segment) {
+                child
+            } else {
+                self.modules.push(ModuleData {
+                    name: Some(*segment),
+                    parent: Some(current),
+                    ..ModuleData::default()
+                });
+                let id = ModuleId(u32::try_from(self.modules.len() - 1).expect("too many modules"));
+                self.get_mut(current).children.insert(*segment, id);
+                id
+            };
+        }
+        current
+    }
+
+    /// Returns the module at `path` (relative to the root), if it exists.
+    #[must_use]
+    pub fn find(&self, path: &[Ident]) -> Option<ModuleId> {
+        path.iter()
+            .try_fold(Self::ROOT, |current, segment| self.child(current, segment))
+    }
+
+    /// The segments leading from the root to `id`.
+    #[must_use]
+    pub fn path_of(&self, id: ModuleId) -> Vec<Ident> {
+        let mut path = Vec::new();
+        let mut current = Some(id);
+        while let Some(module) = current.map(|id| self.get(id)) {
+            path.extend(module.name);
+            current = module.parent;
+        }
+        path.reverse();
+        path
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ModuleId, &ModuleData)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .map(|(i, data)| (ModuleId(i as u32), data))
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{Namespace, ResolvedPath};
+use std::collections::HashMap;
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_ast::item::import::Import;
+use topaz_ast::item::Item;
+
+/// File attribute disabling the prelude in a single file.
+pub const NO_PRELUDE: &str = "no_prelude";
+/// Gem attribute of the root module, disabling the standard library (and so the prelude).
+pub const NO_STD: &str = "no_std";
+
+/// The names every file of a gem can use without importing them.
+#[derive(Debug, Clone, Default)]
+pub struct Prelude {
+    names: HashMap<Ident, ResolvedPath>,
+}
+
+impl Prelude {
+    /// Builds the prelude from the imports of the prelude module of the standard library.
+    ///
+    /// The module is a part of the standard library, so `gem::` paths in it
+    /// refer to the `std` namespace.
+    #[must_use]
+    pub fn from_std_module(file: &TopazFile) -> Self {
+        let names = file
+            .items
+            .iter()
+            .filter_map(|item| match &item.value {
+                Item::Import(import) => Some(import),
+                _ => None,
+            })
+            .filter_map(|import @ Import(path, _)| {
+                let mut segments = path.segments();
+                let namespace = match segments.next()?.value() {
+                    "gem" | "std" => Namespace::Std,
+                    "core" => Namespace::Core,
+                    _ => return None,
+                };
+                let resolved = ResolvedPath::Path(namespace, segments.copied().collect(), None);
+                Some((*import.binding()?, resolved))
+            })
+            .collect();
+
+        Self { names }
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &Ident) -> Option<&ResolvedPath> {
+        self.names.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &Ident> {
+        self.names.keys()
+    }
+}
@@ -0,0 +1,496 @@
+use crate::def::{DefData, DefId, DefKind};
+use crate::module::{Binding, ModuleId, ModuleTree};
+use crate::prelude::{NO_PRELUDE, NO_STD};
+use crate::{resolve, Res, ResolveContext, ResolveError, Resolutions, SourceModule};
+use std::collections::HashMap;
+use topaz_ast::attribute::Attribute;
+use topaz_ast::block::Block;
+use topaz_ast::expr::ExprVarAccess;
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_ast::item::func::{Func, FuncArg};
+use topaz_ast::item::enum_def::{Enum, Variant};
+use topaz_ast::item::impl_def::Impl;
+use topaz_ast::item::import::Import;
+use topaz_ast::item::struct_def::Struct;
+use topaz_ast::item::trait_def::{RequiredMethod, Trait};
+use topaz_ast::item::type_alias::TypeAlias;
+use topaz_ast::item::Item;
+use topaz_ast::path::Path;
+use topaz_ast::pattern::{ArmPattern, Pattern};
+use topaz_ast::statement::control::MatchArm;
+use topaz_ast::statement::func_call::FuncCallStmt;
+use topaz_ast::statement::variable::{AssignStmt, LetStmt};
+use topaz_ast::types::TypePath;
+use topaz_ast::visit::{self, Visit};
+
+/// Local bindings of a single block or function signature.
+type Rib = HashMap<Ident, DefId>;
+
+struct PendingImport<'a> {
+    module: ModuleId,
+    file_id: usize,
+    def: DefId,
+    import: &'a Import,
+    prelude: bool,
+}
+
+pub(crate) struct Resolver<'cx> {
+    cx: &'cx mut ResolveContext,
+    out: Resolutions,
+    module: ModuleId,
+    file_id: usize,
+    /// Whether the prelude is in scope in the current file
+    prelude: bool,
+    ribs: Vec<Rib>,
+}
+
+impl<'cx> Resolver<'cx> {
+    pub(crate) fn new(cx: &'cx mut ResolveContext) -> Self {
+        Self {
+            cx,
+            out: Resolutions::default(),
+            module: ModuleTree::ROOT,
+            file_id: 0,
+            prelude: false,
+            ribs: Vec::new(),
+        }
+    }
+
+    pub(crate) fn run(mut self, sources: Vec<SourceModule>) -> Resolutions {
+        // `@no_std` has to be known before anything is resolved
+        for source in &sources {
+            self.check_attributes(source);
+        }
+
+        let mut imports = Vec::new();
+        for source in &sources {
+            self.collect(source, &mut imports);
+        }
+        self.resolve_imports(imports);
+        for source in &sources {
+            self.module = self.out.modules.find(&source.path).unwrap_or(ModuleTree::ROOT);
+            self.file_id = source.file_id;
+            self.prelude = self.uses_prelude(source.file);
+            self.visit_file(source.file);
+        }
+        self.out
+    }
+
+    fn check_attributes(&mut self, source: &SourceModule) {
+        self.file_id = source.file_id;
+        for attribute in &source.file.attributes {
+            let (name, span) = (attribute.name().to_owned(), attribute.1.span());
+            match attribute.name() {
+                NO_STD if source.path.is_empty() => self.cx.has_std = false,
+                NO_STD => self.error(ResolveError::MisplacedAttribute { name, span }),
+                NO_PRELUDE => {}
+                _ if attribute.is_lint_level() => {}
+                _ => self.error(ResolveError::UnknownAttribute { name, span }),
+            }
+        }
+        for attributes in &source.file.item_attributes {
+            self.check_lint_attributes(attributes);
+        }
+    }
+
+    /// Items and statements only take lint levels, see [`Attribute::is_lint_level`].
+    fn check_lint_attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes.iter().filter(|attribute| !attribute.is_lint_level()) {
+            self.error(ResolveError::UnknownAttribute {
+                name: attribute.name().to_owned(),
+                span: attribute.1.span(),
+            });
+        }
+    }
+
+    fn uses_prelude(&self, file: &TopazFile) -> bool {
+        self.cx.has_std && self.cx.prelude.is_some() && file.attribute(NO_PRELUDE).is_none()
+    }
+
+    fn error(&mut self, error: ResolveError) {
+        self.out.errors.push((self.file_id, error));
+    }
+
+    /// Declares every item of `source` in its module.
+    fn collect<'a>(&mut self, source: &SourceModule<'a>, imports: &mut Vec<PendingImport<'a>>) {
+        let module = self.out.modules.get_or_insert(&source.path);
+        self.out.modules.get_mut(module).file_id = Some(source.file_id);
+        self.module = module;
+        self.file_id = source.file_id;
+        let prelude = self.uses_prelude(source.file);
+
+        for item in &source.file.items {
+            let (kind, name) = match &item.value {
+                Item::Func(Func(_, _, name, ..)) => (DefKind::Func, *name),
+                Item::TypeAlias(TypeAlias(_, _, _, name, _)) => (DefKind::TypeAlias, *name),
+                Item::Struct(Struct(_, _, name, _)) => (DefKind::Struct, *name),
+                Item::Enum(enum_def @ Enum(_, _, name, _)) => {
+                    let def = self.define(DefKind::Enum, *name);
+                    self.bind(module, *name, Binding {
+                        res: Res::Def(def),
+                        import: None,
+                    });
+                    let variants = enum_def.variants().map(|Variant(name, _)| self.define(DefKind::Variant, *name));
+                    let variants = variants.collect();
+                    self.out.variants.insert(def, variants);
+                    continue;
+                }
+                Item::Trait(trait_def @ Trait(_, _, name, _)) => {
+                    let def = self.define(DefKind::Trait, *name);
+                    self.bind(module, *name, Binding {
+                        res: Res::Def(def),
+                        import: None,
+                    });
+                    // named through the trait, but like the methods of impls they declare nothing where they are written
+                    let methods = trait_def.items().map(|item| {
+                        self.out.defs.add(DefData {
+                            kind: DefKind::Method,
+                            name: *item.name(),
+                            module,
+                            file_id: source.file_id,
+                        })
+                    });
+                    let methods = methods.collect();
+                    self.out.methods.insert(def, methods);
+                    continue;
+                }
+                // impls are not named, their methods are only reachable through the trait
+                Item::Impl(_) => continue,
+                Item::Import(import) => {
+                    let Some(name) = import.binding() else {
+                        self.error(ResolveError::EmptyPath);
+                        continue;
+                    };
+                    let def = self.define(DefKind::Import, *name);
+                    imports.push(PendingImport {
+                        module,
+                        file_id: source.file_id,
+                        def,
+                        import,
+                        prelude,
+                    });
+                    continue;
+                }
+            };
+            let def = self.define(kind, name);
+            self.bind(module, name, Binding {
+                res: Res::Def(def),
+                import: None,
+            });
+        }
+    }
+
+    /// Resolves imports until no more can be resolved,
+    /// as an import may refer to a name brought in by another import.
+    fn resolve_imports(&mut self, mut pending: Vec<PendingImport>) {
+        loop {
+            let before = pending.len();
+            let mut failed = Vec::new();
+            for import in pending {
+                self.module = import.module;
+                self.file_id = import.file_id;
+                self.prelude = import.prelude;
+                match self.resolve_path(&import.import.0, false) {
+                    Ok(res) => {
+                        let name = self.out.defs.get(import.def).name;
+                        self.bind(import.module, name, Binding {
+                            res,
+                            import: Some(import.def),
+                        });
+                    }
+                    Err(error) => failed.push((import, error)),
+                }
+            }
+
+            if failed.len() == before {
+                for (import, error) in failed {
+                    self.out.errors.push((import.file_id, error));
+                }
+                break;
+            }
+            pending = failed.into_iter().map(|(import, _)| import).collect();
+        }
+    }
+
+    fn define(&mut self, kind: DefKind, name: Ident) -> DefId {
+        let def = self.out.defs.add(DefData {
+            kind,
+            name,
+            module: self.module,
+            file_id: self.file_id,
+        });
+        self.out.decls.insert((self.file_id, name.span()), def);
+        def
+    }
+
+    fn bind(&mut self, module: ModuleId, name: Ident, binding: Binding) {
+        let names = &mut self.out.modules.get_mut(module).names;
+        if let Some(previous) = names.get(&name) {
+            let first = previous
+                .import
+                .or(match previous.res {
+                    Res::Def(def) => Some(def),
+                    _ => None,
+                })
+                .map(|def| self.out.defs.get(def))
+                .map_or((self.file_id, name.span()), |data| (data.file_id, data.span()));
+            self.error(ResolveError::Duplicate {
+                name: name.to_string(),
+                first,
+                span: name.span(),
+            });
+            return;
+        }
+        names.insert(name, binding);
+    }
+
+    fn declare_local(&mut self, kind: DefKind, name: Ident) {
+        let def = self.define(kind, name);
+        // shadowing replaces the previous binding of the innermost scope
+        self.ribs
+            .last_mut()
+            .expect("local declared outside of a scope")
+            .insert(name, def);
+    }
+
+    /// Looks `name` up in the item scope of `module`, marking the import it came from as used.
+    fn lookup_in_module(&mut self, module: ModuleId, name: &Ident) -> Option<Res> {
+        let data = self.out.modules.get(module);
+        if let Some(binding) = data.names.get(name) {
+            let res = binding.res.clone();
+            if let Some(import) = binding.import {
+                self.out.used_imports.insert(import);
+            }
+            return Some(res);
+        }
+        data.children.get(name).map(|child| Res::Module(*child))
+    }
+
+    fn resolve_path(&mut self, path: &Path, allow_locals: bool) -> Result<Res, ResolveError> {
+        let segments: Vec<Ident> = path.segments().copied().collect();
+        let Some((first, rest)) = segments.split_first() else {
+            return Err(ResolveError::EmptyPath);
+        };
+
+        if rest.is_empty() {
+            // values only see locals, and types only see type parameters
+            let local = self.ribs.iter().rev().find_map(|rib| {
+                rib.get(first)
+                    .filter(|def| (self.out.defs.get(**def).kind == DefKind::TypeParam) != allow_locals)
+            });
+            if let Some(def) = local {
+                return Ok(Res::Def(*def));
+            }
+        }
+
+        let mut current = match first.value() {
+            "gem" => Res::Module(ModuleTree::ROOT),
+            "this" => Res::Module(self.module),
+            "super" => Res::Module(
+                self.out
+                    .modules
+                    .get(self.module)
+                    .parent
+                    .ok_or_else(|| ResolveError::NamespaceNotFound("super".to_owned(), first.span()))?,
+            ),
+            _ => match self.lookup_in_module(self.module, first) {
+                Some(res) => res,
+                // the prelude comes last, so that everything else shadows it
+                None => match self.cx.prelude().and_then(|prelude| prelude.get(first)) {
+                    Some(prelude_path) if self.prelude => Res::External(prelude_path.clone()),
+                    Some(_) if !self.cx.has_std => {
+                        return Err(ResolveError::StdPrelude {
+                            name: first.to_string(),
+                            span: first.span(),
+                        })
+                    }
+                    _ if rest.is_empty() => {
+                        return Err(ResolveError::Unresolved {
+                            name: first.to_string(),
+                            scope: None,
+                            span: first.span(),
+                        })
+                    }
+                    _ => return resolve(self.cx, path).map(Res::External),
+                },
+            },
+        };
+
+        let mut previous = first;
+        for segment in rest {
+            current = match current {
+                Res::Module(module) => {
+                    self.lookup_in_module(module, segment)
+                        .ok_or_else(|| ResolveError::Unresolved {
+                            name: segment.to_string(),
+                            scope: Some(self.module_name(module)),
+                            span: segment.span(),
+                        })?
+                }
+                // the variants of an enum and the methods of a trait are named through it
+                Res::Def(def) if matches!(self.out.defs.get(def).kind, DefKind::Enum | DefKind::Trait) => {
+                    self.member(def, segment).ok_or_else(|| ResolveError::Unresolved {
+                        name: segment.to_string(),
+                        scope: Some(previous.to_string()),
+                        span: segment.span(),
+                    })?
+                }
+                Res::Def(def) => {
+                    return Err(ResolveError::NotAModule {
+                        name: previous.to_string(),
+                        kind: self.out.defs.get(def).kind.descr(),
+                        span: previous.span(),
+                    })
+                }
+                Res::External(crate::ResolvedPath::Path(namespace, mut segments, alias)) => {
+                    segments.push(*segment);
+                    Res::External(crate::ResolvedPath::Path(namespace, segments, alias))
+                }
+            };
+            previous = segment;
+        }
+
+        Ok(current)
+    }
+
+    /// The variant `name` of the enum `def`, or the method `name` of the trait `def`.
+    fn member(&self, def: DefId, name: &Ident) -> Option<Res> {
+        let members = self.out.variants.get(&def).or_else(|| self.out.methods.get(&def))?;
+        let member = members.iter().find(|member| self.out.defs.get(**member).name == *name)?;
+        Some(Res::Def(*member))
+    }
+
+    fn module_name(&self, module: ModuleId) -> String {
+        std::iter::once("gem".to_owned())
+            .chain(self.out.modules.path_of(module).iter().map(ToString::to_string))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    fn record(&mut self, path: &Path, allow_locals: bool) {
+        match self.resolve_path(path, allow_locals) {
+            Ok(res) => {
+                self.out.paths.insert((self.file_id, path.span()), res);
+            }
+            Err(error) => self.error(error),
+        }
+    }
+}
+
+impl Visit for Resolver<'_> {
+    fn visit_func(&mut self, Func(_, _, _, args, ret, body): &Func) {
+        self.ribs.push(Rib::new());
+        for arg in args {
+            self.visit_func_arg(arg);
+        }
+        if let Some((_, ty)) = ret {
+            self.visit_type(&ty.value);
+        }
+        visit::walk_func_body(self, body);
+        self.ribs.pop();
+    }
+
+    fn visit_func_arg(&mut self, FuncArg(_, pattern, ty, default): &FuncArg) {
+        self.visit_type(&ty.value);
+        if let Some((_, default)) = default {
+            self.visit_expr(&default.value);
+        }
+        match pattern {
+            Pattern::Ident(name) => self.declare_local(DefKind::Param, *name),
+        }
+    }
+
+    fn visit_required_method(&mut self, RequiredMethod(_, _, _, _, args, ret): &RequiredMethod) {
+        // there is no body, so the parameters are never declared
+        for FuncArg(_, _, ty, _) in args {
+            self.visit_type(&ty.value);
+        }
+        if let Some((_, ty)) = ret {
+            self.visit_type(&ty.value);
+        }
+    }
+
+    fn visit_impl(&mut self, impl_def @ Impl(_, generics, trait_path, _, ty, _): &Impl) {
+        self.ribs.push(Rib::new());
+        for generic in generics {
+            self.declare_local(DefKind::TypeParam, *generic);
+        }
+        self.record(trait_path, false);
+        self.visit_type(&ty.value);
+        for method in impl_def.methods() {
+            self.visit_method(method);
+        }
+        self.ribs.pop();
+    }
+
+    fn visit_typealias(&mut self, alias @ TypeAlias(_, _, _, _, aliased): &TypeAlias) {
+        self.ribs.push(Rib::new());
+        for param in alias.params() {
+            self.declare_local(DefKind::TypeParam, *param);
+        }
+        self.visit_type(aliased);
+        self.ribs.pop();
+    }
+
+    fn visit_import(&mut self, _import: &Import) {
+        // resolved up-front, see `resolve_imports`
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        for attributes in &block.1 {
+            self.check_lint_attributes(attributes);
+        }
+        self.ribs.push(Rib::new());
+        visit::walk_block(self, block);
+        self.ribs.pop();
+    }
+
+    fn visit_let_stmt(&mut self, LetStmt(_, _, name, ty, init): &LetStmt) {
+        if let Some((_, ty)) = ty {
+            self.visit_type(&ty.value);
+        }
+        if let Some((_, init)) = init {
+            self.visit_expr(&init.value);
+        }
+        self.declare_local(DefKind::Local, *name);
+    }
+
+    fn visit_assign_stmt(&mut self, AssignStmt(place, _, value): &AssignStmt) {
+        self.visit_expr(&value.value);
+        self.record(place, true);
+    }
+
+    fn visit_match_arm(&mut self, MatchArm(pattern, _, body): &MatchArm) {
+        self.ribs.push(Rib::new());
+        if let ArmPattern::Variant(variant, bindings) = &pattern.value {
+            // a pattern names a variant, which no local can stand for
+            self.record(variant, false);
+            for binding in bindings {
+                self.declare_local(DefKind::Local, *binding);
+            }
+        }
+        self.visit_block(&body.value);
+        self.ribs.pop();
+    }
+
+    fn visit_func_call(&mut self, call: &FuncCallStmt) {
+        self.record(&call.0, true);
+        for arg in call.1.content().iter() {
+            self.visit_func_call_arg(arg);
+        }
+    }
+
+    fn visit_expr_var_access(&mut self, ExprVarAccess(path): &ExprVarAccess) {
+        self.record(path, true);
+    }
+
+    fn visit_type_path(&mut self, type_path: &TypePath) {
+        // `this` is the type a method is defined on
+        let path = &type_path.path;
+        if path.len() != 1 || path.first().map(Ident::value) != Some("this") {
+            self.record(path, false);
+        }
+        self.visit_type_arguments(&type_path.arguments);
+    }
+}
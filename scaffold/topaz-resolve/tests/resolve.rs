@@ -0,0 +1,151 @@
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_parser_next::Parse;
+use topaz_resolve::def::DefKind;
+use topaz_resolve::prelude::Prelude;
+use topaz_resolve::{resolve_gem, Res, ResolveContext, ResolveError, SourceModule};
+
+const MAIN: &str = r#"
+import gem::mem::drop;
+
+func main(value: int) {
+    let value = value;
+    {
+        let value = "shadowed";
+        drop(value);
+    }
+    drop(value);
+    missing(value);
+}
+"#;
+
+const MEM: &str = r#"
+func drop(thing: int) {}
+"#;
+
+fn parse(source: &str) -> TopazFile {
+    TopazFile::parse(source).expect("failed to parse")
+}
+
+#[test]
+fn test_resolve_gem() {
+    let main = parse(MAIN);
+    let mem = parse(MEM);
+    let mut cx = ResolveContext::new("test", true);
+
+    let resolutions = resolve_gem(&mut cx, [
+        SourceModule { file_id: 0, path: vec![], file: &main },
+        SourceModule { file_id: 1, path: vec![Ident::new("mem")], file: &mem },
+    ]);
+
+    // the only error is the call to `missing`
+    assert_eq!(resolutions.errors.len(), 1, "{:#?}", resolutions.errors);
+    let (file_id, error) = &resolutions.errors[0];
+    assert_eq!(*file_id, 0);
+    let ResolveError::Unresolved { name, scope: None, span } = error else {
+        panic!("unexpected error {error:?}");
+    };
+    assert_eq!(name, "missing");
+    assert_eq!(&MAIN[span.start..span.end], "missing");
+
+    // every `value` argument refers to the innermost binding in scope
+    let mut uses: Vec<_> = resolutions
+        .paths
+        .iter()
+        .filter(|((file_id, span), _)| *file_id == 0 && &MAIN[span.start..span.end] == "value")
+        .map(|((_, span), res)| (span.start, res.clone()))
+        .collect();
+    uses.sort_by_key(|(start, _)| *start);
+    let kinds: Vec<_> = uses
+        .iter()
+        .map(|(start, res)| match res {
+            Res::Def(def) => {
+                let data = resolutions.defs.get(*def);
+                assert!(data.span().start < *start, "binding declared after its use");
+                data.kind
+            }
+            _ => panic!("`value` resolved to {res:?}"),
+        })
+        .collect();
+    assert_eq!(kinds, [DefKind::Param, DefKind::Local, DefKind::Local, DefKind::Local]);
+    assert_ne!(uses[1].1, uses[2].1, "the inner `value` should shadow the outer one");
+    assert_eq!(uses[2].1, uses[3].1);
+
+    // `drop` resolves through the import to the function in `mem`
+    let drops: Vec<_> = resolutions
+        .paths
+        .iter()
+        .filter(|((_, span), _)| &MAIN[span.start..span.end] == "drop")
+        .map(|(_, res)| res.clone())
+        .collect();
+    assert!(!drops.is_empty());
+    for res in drops {
+        let Res::Def(def) = res else { panic!("`drop` resolved to {res:?}") };
+        let data = resolutions.defs.get(def);
+        assert_eq!((data.kind, data.file_id), (DefKind::Func, 1));
+    }
+}
+
+#[test]
+fn test_resolve_namespaces() {
+    let file = parse("import std::mem::drop; import nowhere::thing;");
+
+    let mut cx = ResolveContext::new("test", false);
+    let resolutions = resolve_gem(&mut cx, [SourceModule { file_id: 0, path: vec![], file: &file }]);
+    let errors: Vec<_> = resolutions.errors.iter().map(|(_, e)| e.clone()).collect();
+
+    assert!(matches!(errors[0], ResolveError::NoStd(_)), "{errors:?}");
+    assert!(matches!(&errors[1], ResolveError::NamespaceNotFound(name, _) if name == "nowhere"), "{errors:?}");
+}
+
+#[test]
+fn test_prelude() {
+    let prelude = Prelude::from_std_module(&parse("import gem::mem::drop::Drop; import gem::default::Default;"));
+    let uses = "func a(x: Drop) {} func b(x: Default) {}";
+    let shadowed = parse(&format!("@!no_prelude\nfunc Default() {{}} {uses}"));
+    let root = parse(uses);
+
+    let mut cx = ResolveContext::new("test", true);
+    cx.set_prelude(prelude.clone());
+    let resolutions = resolve_gem(&mut cx, [
+        SourceModule { file_id: 0, path: vec![], file: &root },
+        SourceModule { file_id: 1, path: vec![Ident::new("other")], file: &shadowed },
+    ]);
+
+    // `Drop` is only in scope where the prelude is, and items shadow the prelude
+    let errors: Vec<_> = resolutions.errors.iter().map(|(file_id, e)| (*file_id, e.clone())).collect();
+    assert!(matches!(&errors[..], [(1, ResolveError::Unresolved { name, .. })] if name == "Drop"), "{errors:?}");
+    let kinds: Vec<_> = resolutions.paths.iter().map(|((file_id, _), res)| (*file_id, res.clone())).collect();
+    assert!(kinds.iter().any(|(file_id, res)| *file_id == 1 && matches!(res, Res::Def(_))));
+    assert_eq!(kinds.iter().filter(|(file_id, res)| *file_id == 0 && matches!(res, Res::External(_))).count(), 2);
+
+    // the prelude is a part of std, so a no-std gem has to define the names itself
+    let no_std = parse(&format!("@!no_std\n{uses}"));
+    let mut cx = ResolveContext::new("test", true);
+    cx.set_prelude(prelude);
+    let resolutions = resolve_gem(&mut cx, [SourceModule { file_id: 0, path: vec![], file: &no_std }]);
+    assert!(!cx.has_std());
+    assert_eq!(resolutions.errors.len(), 2);
+    assert!(resolutions.errors.iter().all(|(_, e)| matches!(e, ResolveError::StdPrelude { .. })));
+}
+
+#[test]
+fn test_attributes() {
+    // `@!` sets an attribute of the file, the first item has its own
+    let root = parse("@!unknown\n@no_std\nfunc main() {}");
+    let module = parse("@!no_std\n");
+
+    let mut cx = ResolveContext::new("test", true);
+    let resolutions = resolve_gem(&mut cx, [
+        SourceModule { file_id: 0, path: vec![], file: &root },
+        SourceModule { file_id: 1, path: vec![Ident::new("module")], file: &module },
+    ]);
+    let errors: Vec<_> = resolutions.errors.iter().map(|(_, e)| e.clone()).collect();
+
+    assert!(cx.has_std(), "`@!no_std` outside of the root module and `@no_std` on an item should be ignored");
+    assert!(matches!(&errors[..], [
+        ResolveError::UnknownAttribute { name: unknown, .. },
+        ResolveError::UnknownAttribute { name: item, .. },
+        ResolveError::MisplacedAttribute { name: no_std, .. },
+    ] if unknown == "unknown" && item == "no_std" && no_std == "no_std"), "{errors:?}");
+}
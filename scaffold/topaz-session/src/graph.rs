@@ -0,0 +1,274 @@
+//! `graph.rs` - the gems of a build and the dependencies between them.
+use crate::lockfile::{LockedGem, Lockfile};
+use crate::manifest::Manifest;
+use crate::prelude::std_prelude;
+use crate::GemError;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use topaz_resolve::ResolveContext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GemId(pub usize);
+
+#[derive(Debug)]
+pub struct GemNode {
+    pub manifest: Manifest,
+    /// The canonical path of the gem directory
+    pub dir: PathBuf,
+    /// The gems this one declares as dependencies, in manifest order
+    pub dependencies: Vec<GemId>,
+}
+
+/// Every gem reachable from the root gem, without cycles and with one version per name.
+#[derive(Debug)]
+pub struct GemGraph {
+    gems: Vec<GemNode>,
+    /// Dependencies before their dependents
+    order: Vec<GemId>,
+}
+
+impl GemGraph {
+    pub const ROOT: GemId = GemId(0);
+
+    /// Loads the gem in `dir` and, transitively, all of its dependencies.
+    ///
+    /// The versions of the dependencies are pinned by the lockfile in `dir`, if there is one:
+    /// a dependency found at the path it was locked at has to still have the locked version.
+    pub fn load(dir: &Path) -> Result<Self, Vec<GemError>> {
+        let locked = Lockfile::load(dir).map_err(|error| vec![error])?;
+        Self::load_with(dir, locked)
+    }
+
+    /// Like [`GemGraph::load`], ignoring the lockfile, e.g. to write a new one.
+    pub fn load_unlocked(dir: &Path) -> Result<Self, Vec<GemError>> {
+        Self::load_with(dir, None)
+    }
+
+    fn load_with(dir: &Path, locked: Option<Lockfile>) -> Result<Self, Vec<GemError>> {
+        let mut builder = Builder { locked, ..Builder::default() };
+        builder.visit(dir);
+        builder.check_conflicts();
+
+        if builder.errors.is_empty() {
+            Ok(Self {
+                gems: builder.gems,
+                order: builder.order,
+            })
+        } else {
+            Err(builder.errors)
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, id: GemId) -> &GemNode {
+        &self.gems[id.0]
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &GemNode {
+        self.get(Self::ROOT)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.gems.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.gems.is_empty()
+    }
+
+    #[must_use]
+    pub fn find(&self, name: &str) -> Option<GemId> {
+        self.gems.iter().position(|gem| gem.manifest.name() == name).map(GemId)
+    }
+
+    /// The order gems have to be built in: every gem comes after its dependencies.
+    pub fn build_order(&self) -> impl Iterator<Item = GemId> + '_ {
+        self.order.iter().copied()
+    }
+
+    /// A resolve context for the gem `id`, in which only its declared dependencies
+    /// and the std prelude are visible.
+    #[must_use]
+    pub fn resolve_context(&self, id: GemId) -> ResolveContext {
+        let gem = self.get(id);
+        let mut cx = ResolveContext::new(gem.manifest.name(), gem.manifest.has_std());
+        cx.set_prelude(std_prelude());
+        for dependency in &gem.dependencies {
+            cx.add_gem(self.get(*dependency).manifest.name());
+        }
+        cx
+    }
+
+    /// The lockfile describing this graph, with paths relative to the root gem.
+    #[must_use]
+    pub fn lockfile(&self) -> Lockfile {
+        let root = &self.root().dir;
+        let mut gems: Vec<_> = self
+            .gems
+            .iter()
+            .map(|gem| {
+                let mut dependencies: Vec<_> = gem
+                    .dependencies
+                    .iter()
+                    .map(|id| self.get(*id).manifest.name().to_owned())
+                    .collect();
+                dependencies.sort();
+                LockedGem {
+                    name: gem.manifest.name().to_owned(),
+                    version: gem.manifest.gem.version.clone(),
+                    path: relative_path(root, &gem.dir),
+                    dependencies,
+                }
+            })
+            .collect();
+        gems.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Lockfile {
+            version: Lockfile::VERSION,
+            gems,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Builder {
+    gems: Vec<GemNode>,
+    order: Vec<GemId>,
+    by_dir: HashMap<PathBuf, GemId>,
+    /// The gems currently being visited, from the root down
+    stack: Vec<GemId>,
+    /// The lockfile of the root gem, pinning the versions of its dependencies
+    locked: Option<Lockfile>,
+    errors: Vec<GemError>,
+}
+
+impl Builder {
+    fn visit(&mut self, dir: &Path) -> Option<GemId> {
+        let dir = match fs::canonicalize(dir) {
+            Ok(dir) => dir,
+            Err(error) => {
+                self.errors.push(GemError::Io {
+                    path: dir.to_path_buf(),
+                    message: error.to_string(),
+                });
+                return None;
+            }
+        };
+
+        if let Some(&id) = self.by_dir.get(&dir) {
+            if let Some(position) = self.stack.iter().position(|gem| *gem == id) {
+                let cycle = self.stack[position..]
+                    .iter()
+                    .chain([&id])
+                    .map(|gem| self.gems[gem.0].manifest.name().to_owned())
+                    .collect();
+                self.errors.push(GemError::Cycle(cycle));
+            }
+            return Some(id);
+        }
+
+        let manifest = match Manifest::load(&dir) {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                self.errors.push(error);
+                return None;
+            }
+        };
+        let id = GemId(self.gems.len());
+        if id != GemGraph::ROOT {
+            self.check_locked(&manifest, &dir);
+        }
+        let declared = manifest.dependencies.clone();
+        self.gems.push(GemNode {
+            manifest,
+            dir: dir.clone(),
+            dependencies: Vec::new(),
+        });
+        self.by_dir.insert(dir.clone(), id);
+        self.stack.push(id);
+
+        for (name, dependency) in declared {
+            let Some(dep_id) = self.visit(&dir.join(&dependency.path)) else { continue };
+            let found = &self.gems[dep_id.0].manifest;
+
+            if found.name() != name {
+                self.errors.push(GemError::NameMismatch {
+                    dependent: self.gems[id.0].manifest.name().to_owned(),
+                    expected: name,
+                    found: found.name().to_owned(),
+                });
+                continue;
+            }
+            if let Some(required) = dependency.version {
+                if !required.matches(&found.gem.version) {
+                    self.errors.push(GemError::VersionMismatch {
+                        dependent: self.gems[id.0].manifest.name().to_owned(),
+                        name,
+                        required,
+                        found: found.gem.version.clone(),
+                    });
+                    continue;
+                }
+            }
+            self.gems[id.0].dependencies.push(dep_id);
+        }
+
+        self.stack.pop();
+        self.order.push(id);
+        Some(id)
+    }
+
+    /// Checks that a dependency found at the path it was locked at still has the locked version.
+    ///
+    /// A dependency found somewhere else was moved in a manifest, so its entry is outdated and gets replaced.
+    fn check_locked(&mut self, manifest: &Manifest, dir: &Path) {
+        let (Some(locked), Some(root)) = (&self.locked, self.gems.first()) else { return };
+        let path = relative_path(&root.dir, dir);
+        let Some(entry) = locked.gems.iter().find(|gem| gem.name == manifest.name() && gem.path == path) else {
+            return;
+        };
+        if entry.version != manifest.gem.version {
+            self.errors.push(GemError::Locked {
+                name: entry.name.clone(),
+                locked: entry.version.clone(),
+                found: manifest.gem.version.clone(),
+            });
+        }
+    }
+
+    /// Gems share a namespace by name, so a name can only refer to a single gem.
+    fn check_conflicts(&mut self) {
+        for (i, gem) in self.gems.iter().enumerate() {
+            let name = gem.manifest.name();
+            if let Some(first) = self.gems[..i].iter().find(|first| first.manifest.name() == name) {
+                self.errors.push(GemError::VersionConflict {
+                    name: name.to_owned(),
+                    first: Box::new((first.manifest.gem.version.clone(), first.dir.clone())),
+                    second: Box::new((gem.manifest.gem.version.clone(), gem.dir.clone())),
+                });
+            }
+        }
+    }
+}
+
+/// The path of `to` relative to `from`, with `/` separators.
+fn relative_path(from: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let segments: Vec<String> = std::iter::repeat("..".to_owned())
+        .take(from.len() - common)
+        .chain(to[common..].iter().map(|c| c.as_os_str().to_string_lossy().into_owned()))
+        .collect();
+
+    if segments.is_empty() {
+        ".".to_owned()
+    } else {
+        segments.join("/")
+    }
+}
@@ -0,0 +1,317 @@
+//! `lib.rs` - a compilation session: every file of a gem, parsed, with a stable ID.
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::files::SimpleFiles;
+use semver::{Version, VersionReq};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_ast::item::import::Import;
+use topaz_ast::item::Item;
+use topaz_ast::location::Span;
+use topaz_parser_next::{Parse, ParseError, TopazParseError};
+use topaz_report::{Reporter, ReporterState};
+use topaz_resolve::SourceModule;
+
+pub mod graph;
+pub mod loader;
+pub mod lockfile;
+pub mod manifest;
+pub mod prelude;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoadError {
+    #[error("cannot read `{}`: {message}", .path.display())]
+    Io { path: PathBuf, message: String },
+    #[error("`{}` is not a valid module name", .path.display())]
+    InvalidModuleName { path: PathBuf },
+    #[error("module `{module}` is declared by both `{}` and `{}`", .first.display(), .second.display())]
+    DuplicateModule {
+        module: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    #[error("module `{module}` not found")]
+    MissingModule {
+        module: String,
+        span: Span,
+        candidates: Vec<PathBuf>,
+    },
+    #[error("{0}")]
+    Parse(TopazParseError),
+}
+
+impl<'source> Reporter<'source> for LoadError {
+    fn build_diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+        match self {
+            Self::Io { .. } | Self::InvalidModuleName { .. } => {
+                Diagnostic::error().with_message(self.to_string())
+            }
+            Self::DuplicateModule { .. } => Diagnostic::error()
+                .with_code("E009")
+                .with_message(self.to_string())
+                .with_notes(vec!["consider removing or renaming one of the files".to_owned()]),
+            Self::MissingModule { span, candidates, .. } => Diagnostic::error()
+                .with_code("E010")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("no such module")])
+                .with_notes(vec![format!(
+                    "to create the module, add one of: {}",
+                    candidates
+                        .iter()
+                        .map(|path| format!("`{}`", path.display()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )]),
+            Self::Parse(TopazParseError::Lexer(token)) => Diagnostic::error()
+                .with_code("E000")
+                .with_message(format!("unknown token `{}`", token.value))
+                .with_labels(vec![Label::primary(file_id, token.span)]),
+            Self::Parse(TopazParseError::Parse(error)) => {
+                let (message, range) = match error.as_ref() {
+                    ParseError::InvalidToken { location } => {
+                        ("invalid token".to_owned(), *location..*location)
+                    }
+                    ParseError::UnrecognizedEOF { location, expected } => (
+                        format!("unexpected end of file, expected {}", expected.join(", ")),
+                        *location..*location,
+                    ),
+                    ParseError::UnrecognizedToken { token: (start, token, end), expected } => (
+                        format!("unexpected {token:?}, expected {}", expected.join(", ")),
+                        *start..*end,
+                    ),
+                    ParseError::ExtraToken { token: (start, token, end) } => {
+                        (format!("extra token {token:?}"), *start..*end)
+                    }
+                    ParseError::User { error } => (error.to_string(), 0..0),
+                };
+                Diagnostic::error()
+                    .with_code("E001")
+                    .with_message(message)
+                    .with_labels(vec![Label::primary(file_id, range)])
+            }
+        }
+    }
+}
+
+/// An error in the manifests or the dependency graph of a build.
+#[derive(thiserror::Error, Debug)]
+pub enum GemError {
+    #[error("cannot read `{}`: {message}", .path.display())]
+    Io { path: PathBuf, message: String },
+    #[error("invalid manifest `{}`: {message}", .path.display())]
+    InvalidManifest { path: PathBuf, message: String },
+    #[error("invalid lockfile `{}`: {message}", .path.display())]
+    InvalidLockfile { path: PathBuf, message: String },
+    #[error("gem `{dependent}` depends on `{expected}`, but the gem at its path is called `{found}`")]
+    NameMismatch {
+        dependent: String,
+        expected: String,
+        found: String,
+    },
+    #[error("gem `{dependent}` requires `{name}` {required}, but found version {found}")]
+    VersionMismatch {
+        dependent: String,
+        name: String,
+        required: VersionReq,
+        found: Version,
+    },
+    #[error("cyclic dependency between gems: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("gem `{name}` is locked at version {locked}, but found version {found}")]
+    Locked { name: String, locked: Version, found: Version },
+    #[error("conflicting versions of gem `{name}`: {} and {}", .first.0, .second.0)]
+    VersionConflict {
+        name: String,
+        first: Box<(Version, PathBuf)>,
+        second: Box<(Version, PathBuf)>,
+    },
+}
+
+impl<'source> Reporter<'source> for GemError {
+    fn build_diagnostic(&self, _file_id: usize) -> Diagnostic<usize> {
+        let diagnostic = Diagnostic::error().with_message(self.to_string());
+        match self {
+            Self::Io { .. } => diagnostic,
+            Self::InvalidManifest { .. } | Self::InvalidLockfile { .. } | Self::NameMismatch { .. } => {
+                diagnostic.with_code("E011")
+            }
+            Self::Cycle(_) => diagnostic
+                .with_code("E012")
+                .with_notes(vec!["gems cannot depend on themselves, even indirectly".to_owned()]),
+            Self::VersionConflict { first, second, .. } => diagnostic.with_code("E013").with_notes(vec![
+                format!("version {} is at `{}`", first.0, first.1.display()),
+                format!("version {} is at `{}`", second.0, second.1.display()),
+                "every gem in a build has to refer to the same copy of a dependency".to_owned(),
+            ]),
+            Self::VersionMismatch { name, .. } => diagnostic
+                .with_code("E014")
+                .with_notes(vec![format!("update the version requirement of `{name}` or its path")]),
+            Self::Locked { .. } => diagnostic.with_code("E043").with_notes(vec![
+                "run `topaz update` to lock the versions found, or restore the locked version".to_owned(),
+            ]),
+        }
+    }
+}
+
+/// A parsed file of the session.
+#[derive(Debug)]
+pub struct LoadedModule {
+    pub file_id: usize,
+    /// Module path relative to the gem root
+    pub module: Vec<Ident>,
+    pub ast: TopazFile,
+}
+
+/// Holds every source file of a compilation.
+///
+/// Files are added in a deterministic order, so their IDs are stable between runs
+/// and can be used to refer to them in diagnostics.
+pub struct Session {
+    pub files: SimpleFiles<String, String>,
+    pub modules: Vec<LoadedModule>,
+    /// Every module of the gem, including directories without a file of their own
+    known_modules: HashSet<Vec<Ident>>,
+    /// Where the modules were loaded from
+    root: PathBuf,
+    /// Errors without a file are stored with a file ID of `usize::MAX`
+    pub errors: Vec<(usize, LoadError)>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub const NO_FILE: usize = usize::MAX;
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            files: SimpleFiles::new(),
+            modules: Vec::new(),
+            known_modules: HashSet::from([Vec::new()]),
+            root: PathBuf::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Loads the gem at `path`.
+    ///
+    /// `path` may be a single file (the root module), a source directory, or a gem directory
+    /// containing a `src` directory.
+    pub fn load(&mut self, path: &Path) {
+        if path.is_file() {
+            self.root = path.parent().unwrap_or(path).to_path_buf();
+            self.load_file(path, Vec::new());
+        } else {
+            let src = path.join("src");
+            self.load_dir(if src.is_dir() { &src } else { path });
+        }
+        self.check_imports();
+    }
+
+    /// Loads every module under the source root `root`.
+    pub fn load_dir(&mut self, root: &Path) {
+        let discovered = loader::discover(root);
+        self.root = root.to_path_buf();
+        self.known_modules.extend(discovered.modules);
+        self.errors
+            .extend(discovered.errors.into_iter().map(|error| (Self::NO_FILE, error)));
+        for file in discovered.files {
+            self.load_file(&file.path, file.module);
+        }
+    }
+
+    fn load_file(&mut self, path: &Path, module: Vec<Ident>) {
+        match fs::read_to_string(path) {
+            Ok(source) => {
+                self.add_source(path.display().to_string(), source, module);
+            }
+            Err(error) => self.errors.push((
+                Self::NO_FILE,
+                LoadError::Io {
+                    path: path.to_path_buf(),
+                    message: error.to_string(),
+                },
+            )),
+        }
+    }
+
+    /// Adds and parses the source of the module `module`, returning its file ID.
+    pub fn add_source(&mut self, name: String, source: String, module: Vec<Ident>) -> usize {
+        let parsed = TopazFile::parse(&source);
+        let file_id = self.files.add(name, source);
+        self.known_modules.insert(module.clone());
+        match parsed {
+            Ok(ast) => self.modules.push(LoadedModule {
+                file_id,
+                module,
+                ast,
+            }),
+            Err(error) => self.errors.push((file_id, LoadError::Parse(error))),
+        }
+        file_id
+    }
+
+    /// Reports imports of modules that have no file or directory.
+    pub fn check_imports(&mut self) {
+        let mut missing = Vec::new();
+        for loaded in &self.modules {
+            for item in &loaded.ast.items {
+                let Item::Import(Import(path, _)) = &item.value else { continue };
+                let segments: Vec<Ident> = path.segments().copied().collect();
+                let Some((first, rest)) = segments.split_first() else { continue };
+                let mut module = match first.value() {
+                    "gem" => Vec::new(),
+                    "this" => loaded.module.clone(),
+                    "super" => {
+                        let mut parent = loaded.module.clone();
+                        parent.pop();
+                        parent
+                    }
+                    _ => continue,
+                };
+                // the last segment is an item, everything before it has to be a module
+                for segment in rest.iter().take(rest.len().saturating_sub(1)) {
+                    module.push(*segment);
+                    if !self.known_modules.contains(&module) {
+                        missing.push((
+                            loaded.file_id,
+                            LoadError::MissingModule {
+                                module: loader::module_name(&module),
+                                span: segment.span(),
+                                candidates: loader::candidate_files(&self.root, &module),
+                            },
+                        ));
+                        break;
+                    }
+                }
+            }
+        }
+        self.errors.extend(missing);
+    }
+
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// The parsed modules, ready to be resolved.
+    pub fn source_modules(&self) -> impl Iterator<Item = SourceModule> {
+        self.modules.iter().map(|loaded| SourceModule {
+            file_id: loaded.file_id,
+            path: loaded.module.clone(),
+            file: &loaded.ast,
+        })
+    }
+
+    pub fn emit_errors(&self, reporter: &ReporterState) {
+        for (file_id, error) in &self.errors {
+            error.emit_diagnostic(reporter, &self.files, *file_id);
+        }
+    }
+}
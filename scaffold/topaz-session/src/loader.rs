@@ -0,0 +1,138 @@
+//! `loader.rs` - maps the files of a gem to its module hierarchy.
+//!
+//! Given the source root `src/`:
+//! - `src/lib.tp` or `src/main.tp` is the root module,
+//! - `src/mem.tp` and `src/mem/mod.tp` are the module `mem`,
+//! - `src/mem/drop.tp` is the module `mem::drop`.
+//!
+//! A directory does not need a file of its own to be a module.
+use crate::LoadError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use topaz_ast::ident::Ident;
+
+pub const SOURCE_EXTENSION: &str = "tp";
+/// File names of the root module, relative to the source root.
+pub const ROOT_MODULE_FILES: [&str; 2] = ["lib.tp", "main.tp"];
+/// The name of the file declaring the module of its directory.
+pub const DIR_MODULE_FILE: &str = "mod.tp";
+
+/// A source file and the module it declares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleFile {
+    pub path: PathBuf,
+    pub module: Vec<Ident>,
+}
+
+/// The modules found under a source root.
+#[derive(Debug, Default)]
+pub struct Discovered {
+    /// Sorted by path, so that file IDs stay the same between runs.
+    pub files: Vec<ModuleFile>,
+    /// Every module, including directories without a file of their own.
+    pub modules: Vec<Vec<Ident>>,
+    pub errors: Vec<LoadError>,
+}
+
+/// Walks `root` and collects every module of the gem.
+#[must_use]
+pub fn discover(root: &Path) -> Discovered {
+    let mut discovered = Discovered::default();
+    discovered.modules.push(Vec::new());
+    walk(root, &[], true, &mut discovered);
+
+    discovered.files.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut duplicates = Vec::new();
+    for (i, file) in discovered.files.iter().enumerate() {
+        if let Some(first) = discovered.files[..i].iter().find(|f| f.module == file.module) {
+            duplicates.push(LoadError::DuplicateModule {
+                module: module_name(&file.module),
+                first: first.path.clone(),
+                second: file.path.clone(),
+            });
+        }
+    }
+    discovered.errors.extend(duplicates);
+    discovered
+}
+
+fn walk(dir: &Path, module: &[Ident], is_root: bool, out: &mut Discovered) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            out.errors.push(LoadError::Io {
+                path: dir.to_path_buf(),
+                message: error.to_string(),
+            });
+            return;
+        }
+    };
+    let mut entries: Vec<PathBuf> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+    entries.sort();
+
+    for path in entries {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            let Some(name) = Ident::new_checked(file_name) else {
+                out.errors.push(LoadError::InvalidModuleName { path });
+                continue;
+            };
+            let child = [module, &[name]].concat();
+            out.modules.push(child.clone());
+            walk(&path, &child, false, out);
+            continue;
+        }
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SOURCE_EXTENSION) {
+            continue;
+        }
+
+        let declared = if (is_root && ROOT_MODULE_FILES.contains(&file_name))
+            || (!is_root && file_name == DIR_MODULE_FILE)
+        {
+            module.to_vec()
+        } else {
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            let Some(name) = Ident::new_checked(stem) else {
+                out.errors.push(LoadError::InvalidModuleName { path });
+                continue;
+            };
+            let declared = [module, &[name]].concat();
+            if !out.modules.contains(&declared) {
+                out.modules.push(declared.clone());
+            }
+            declared
+        };
+
+        out.files.push(ModuleFile {
+            path,
+            module: declared,
+        });
+    }
+}
+
+/// The files a module at `module` could be declared by.
+#[must_use]
+pub fn candidate_files(root: &Path, module: &[Ident]) -> Vec<PathBuf> {
+    let Some((last, parents)) = module.split_last() else {
+        return ROOT_MODULE_FILES.iter().map(|file| root.join(file)).collect();
+    };
+    let dir = parents
+        .iter()
+        .fold(root.to_path_buf(), |dir, segment| dir.join(segment.value()));
+    vec![
+        dir.join(format!("{last}.{SOURCE_EXTENSION}")),
+        dir.join(last.value()).join(DIR_MODULE_FILE),
+    ]
+}
+
+#[must_use]
+pub fn module_name(module: &[Ident]) -> String {
+    std::iter::once("gem")
+        .chain(module.iter().map(Ident::value))
+        .collect::<Vec<_>>()
+        .join("::")
+}
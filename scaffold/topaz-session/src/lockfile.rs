@@ -0,0 +1,83 @@
+//! `lockfile.rs` - `Gem.lock`, the resolved dependency graph of a gem.
+//!
+//! The lockfile is written next to the manifest of the root gem and is only rewritten
+//! when the graph changes, so it can be checked into version control.
+use crate::GemError;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub const LOCKFILE: &str = "Gem.lock";
+
+const HEADER: &str = "# This file is generated by topaz, do not edit it by hand.\n";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    /// Sorted by name
+    #[serde(rename = "gem", default)]
+    pub gems: Vec<LockedGem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedGem {
+    pub name: String,
+    pub version: Version,
+    /// Relative to the root gem
+    pub path: String,
+    /// Names of the direct dependencies, sorted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+impl Lockfile {
+    /// The version of the lockfile format.
+    pub const VERSION: u32 = 1;
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let lockfile: Self = toml::from_str(source).map_err(|error| error.to_string())?;
+        if lockfile.version != Self::VERSION {
+            return Err(format!("unsupported lockfile version {}", lockfile.version));
+        }
+        Ok(lockfile)
+    }
+
+    /// Reads the lockfile in `dir`, if there is one.
+    pub fn load(dir: &Path) -> Result<Option<Self>, GemError> {
+        let path = dir.join(LOCKFILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let source = fs::read_to_string(&path).map_err(|error| GemError::Io {
+            path: path.clone(),
+            message: error.to_string(),
+        })?;
+        Self::parse(&source)
+            .map(Some)
+            .map_err(|message| GemError::InvalidLockfile { path, message })
+    }
+
+    /// Writes the lockfile to `dir`, unless it's already up to date.
+    ///
+    /// Returns whether the file was written.
+    pub fn store(&self, dir: &Path) -> Result<bool, GemError> {
+        if matches!(Self::load(dir), Ok(Some(existing)) if existing == *self) {
+            return Ok(false);
+        }
+        let path = dir.join(LOCKFILE);
+        fs::write(&path, self.to_string()).map_err(|error| GemError::Io {
+            path,
+            message: error.to_string(),
+        })?;
+        Ok(true)
+    }
+}
+
+impl std::fmt::Display for Lockfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(HEADER)?;
+        let body = toml::to_string(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&body)
+    }
+}
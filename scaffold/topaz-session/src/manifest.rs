@@ -0,0 +1,115 @@
+//! `manifest.rs` - the gem manifest, `Gem.toml`.
+//!
+//! ```toml
+//! [gem]
+//! name = "app"
+//! version = "0.1.0"
+//! edition = "2023"  # optional
+//! no_std = false    # optional
+//!
+//! [dependencies]
+//! util = { path = "../util", version = "0.1" }  # `version` is optional
+//! ```
+use crate::GemError;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use topaz_ast::ident::check_identifier;
+
+pub const MANIFEST_FILE: &str = "Gem.toml";
+
+/// Names that always refer to something else as the first segment of a path.
+pub const RESERVED_NAMES: [&str; 5] = ["core", "std", "gem", "this", "super"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub gem: GemInfo,
+    /// Sorted by name, so that the graph is built in the same order every time.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GemInfo {
+    pub name: String,
+    pub version: Version,
+    #[serde(default)]
+    pub edition: Edition,
+    #[serde(default)]
+    pub no_std: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// Relative to the directory of the manifest declaring the dependency
+    pub path: PathBuf,
+    pub version: Option<VersionReq>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum Edition {
+    #[default]
+    #[serde(rename = "2023")]
+    E2023,
+}
+
+impl Display for Edition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::E2023 => f.write_str("2023"),
+        }
+    }
+}
+
+impl Manifest {
+    /// Parses and validates the source of a manifest.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let manifest: Self = toml::from_str(source).map_err(|error| error.to_string())?;
+
+        check_name(&manifest.gem.name)?;
+        for name in manifest.dependencies.keys() {
+            check_name(name)?;
+            if *name == manifest.gem.name {
+                return Err(format!("gem `{name}` cannot depend on itself"));
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Reads the manifest in the gem directory `dir`.
+    pub fn load(dir: &Path) -> Result<Self, GemError> {
+        let path = dir.join(MANIFEST_FILE);
+        let source = fs::read_to_string(&path).map_err(|error| GemError::Io {
+            path: path.clone(),
+            message: error.to_string(),
+        })?;
+        Self::parse(&source).map_err(|message| GemError::InvalidManifest { path, message })
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.gem.name
+    }
+
+    #[must_use]
+    pub const fn has_std(&self) -> bool {
+        !self.gem.no_std
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if RESERVED_NAMES.contains(&name) {
+        Err(format!("`{name}` is reserved and cannot be used as a gem name"))
+    } else if !check_identifier(name) {
+        Err(format!("`{name}` is not a valid gem name, it has to be an identifier"))
+    } else {
+        Ok(())
+    }
+}
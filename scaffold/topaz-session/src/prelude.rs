@@ -0,0 +1,18 @@
+//! `prelude.rs` - the prelude of the standard library, `tstd/src/prelude.tp`.
+use topaz_ast::file::TopazFile;
+use topaz_parser_next::Parse;
+use topaz_resolve::prelude::Prelude;
+
+/// Every import of this module is in scope in every file of a gem with a standard library.
+pub const STD_PRELUDE: &str = include_str!("../../../library/tstd/src/prelude.tp");
+
+/// Parses the prelude of the standard library.
+///
+/// # Panics
+///
+/// If the prelude module shipped with the compiler does not parse.
+#[must_use]
+pub fn std_prelude() -> Prelude {
+    let file = TopazFile::parse(STD_PRELUDE).expect("the std prelude should parse");
+    Prelude::from_std_module(&file)
+}
@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use topaz_session::graph::GemGraph;
+use topaz_session::lockfile::Lockfile;
+use topaz_session::manifest::{Edition, Manifest};
+use topaz_session::GemError;
+
+/// Creates a fresh directory named `name` holding one gem per `(directory, manifest)` pair.
+fn workspace(name: &str, gems: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("topaz-gems-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (dir, manifest) in gems {
+        let dir = root.join(dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("Gem.toml"), manifest).unwrap();
+    }
+    root
+}
+
+fn names(graph: &GemGraph) -> Vec<&str> {
+    graph.build_order().map(|id| graph.get(id).manifest.name()).collect()
+}
+
+fn errors(root: &Path, gem: &str) -> Vec<GemError> {
+    GemGraph::load(&root.join(gem)).expect_err("the graph should not load")
+}
+
+#[test]
+fn test_manifest() {
+    let manifest = Manifest::parse(
+        r#"
+        [gem]
+        name = "app"
+        version = "1.2.0"
+        no_std = true
+
+        [dependencies]
+        util = { path = "../util", version = "1" }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(manifest.name(), "app");
+    assert_eq!(manifest.gem.edition, Edition::E2023);
+    assert!(!manifest.has_std());
+    assert!(manifest.dependencies["util"].version.is_some());
+
+    let reserved = Manifest::parse("[gem]\nname = \"std\"\nversion = \"0.1.0\"");
+    assert!(reserved.unwrap_err().contains("reserved"));
+    let unknown = Manifest::parse("[gem]\nname = \"a\"\nversion = \"0.1.0\"\nlicense = \"MIT\"");
+    assert!(unknown.is_err());
+}
+
+#[test]
+fn test_graph_and_lockfile() {
+    let root = workspace("graph", &[
+        ("app", "[gem]\nname = \"app\"\nversion = \"0.1.0\"\n[dependencies]\nnet = { path = \"../net\" }\nutil = { path = \"../util\", version = \"0.2\" }"),
+        ("net", "[gem]\nname = \"net\"\nversion = \"1.0.0\"\nno_std = true\n[dependencies]\nutil = { path = \"../util\" }"),
+        ("util", "[gem]\nname = \"util\"\nversion = \"0.2.1\""),
+    ]);
+
+    let graph = GemGraph::load(&root.join("app")).unwrap();
+    assert_eq!(graph.len(), 3);
+    assert_eq!(names(&graph), ["util", "net", "app"]);
+
+    // each gem only sees the gems it declares
+    let net = graph.find("net").unwrap();
+    let cx = graph.resolve_context(net);
+    assert!(cx.gem_symbol("util").is_some());
+    assert!(cx.gem_symbol("app").is_none());
+    assert!(!cx.has_std());
+    let cx = graph.resolve_context(GemGraph::ROOT);
+    assert!(cx.gem_symbol("net").is_some() && cx.gem_symbol("util").is_some());
+
+    let lockfile = graph.lockfile();
+    let locked: Vec<_> = lockfile.gems.iter().map(|gem| (gem.name.as_str(), gem.path.as_str())).collect();
+    assert_eq!(locked, [("app", "."), ("net", "../net"), ("util", "../util")]);
+    assert_eq!(lockfile.gems[0].dependencies, ["net", "util"]);
+    assert_eq!(Lockfile::parse(&lockfile.to_string()).unwrap(), lockfile);
+
+    let dir = root.join("app");
+    assert!(lockfile.store(&dir).unwrap());
+    assert!(!lockfile.store(&dir).unwrap(), "an unchanged lockfile should not be rewritten");
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_locked_versions() {
+    let root = workspace("locked", &[
+        ("app", "[gem]\nname = \"app\"\nversion = \"0.1.0\"\n[dependencies]\nutil = { path = \"../util\", version = \"1\" }"),
+        ("util", "[gem]\nname = \"util\"\nversion = \"1.0.0\""),
+        ("util-next", "[gem]\nname = \"util\"\nversion = \"1.1.0\""),
+    ]);
+    let app = root.join("app");
+    GemGraph::load(&app).unwrap().lockfile().store(&app).unwrap();
+
+    // a new version still matches the requirement, but the lockfile pins the old one
+    fs::write(root.join("util/Gem.toml"), "[gem]\nname = \"util\"\nversion = \"1.1.0\"").unwrap();
+    let errors = errors(&root, "app");
+    assert!(matches!(
+        &errors[..],
+        [GemError::Locked { name, locked, found }] if name == "util" && locked.minor == 0 && found.minor == 1
+    ), "{errors:?}");
+    let graph = GemGraph::load_unlocked(&app).unwrap();
+    assert!(graph.lockfile().store(&app).unwrap());
+    assert!(GemGraph::load(&app).is_ok());
+
+    // moving a dependency replaces its entry
+    fs::write(app.join("Gem.toml"), "[gem]\nname = \"app\"\nversion = \"0.1.0\"\n[dependencies]\nutil = { path = \"../util-next\" }").unwrap();
+    let graph = GemGraph::load(&app).unwrap();
+    assert_eq!(graph.lockfile().gems[1].path, "../util-next");
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_cycle() {
+    let root = workspace("cycle", &[
+        ("a", "[gem]\nname = \"a\"\nversion = \"0.1.0\"\n[dependencies]\nb = { path = \"../b\" }"),
+        ("b", "[gem]\nname = \"b\"\nversion = \"0.1.0\"\n[dependencies]\na = { path = \"../a\" }"),
+    ]);
+
+    let errors = errors(&root, "a");
+    assert!(matches!(&errors[..], [GemError::Cycle(cycle)] if cycle == &["a", "b", "a"]), "{errors:?}");
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_version_conflicts() {
+    let root = workspace("conflict", &[
+        ("app", "[gem]\nname = \"app\"\nversion = \"0.1.0\"\n[dependencies]\nnet = { path = \"../net\" }\nutil = { path = \"../util-1\", version = \"2\" }"),
+        ("net", "[gem]\nname = \"net\"\nversion = \"0.1.0\"\n[dependencies]\nutil = { path = \"../util-2\" }"),
+        ("util-1", "[gem]\nname = \"util\"\nversion = \"1.0.0\""),
+        ("util-2", "[gem]\nname = \"util\"\nversion = \"2.0.0\""),
+    ]);
+
+    let errors = errors(&root, "app");
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(matches!(
+        &errors[0],
+        GemError::VersionMismatch { dependent, name, .. } if dependent == "app" && name == "util"
+    ));
+    let GemError::VersionConflict { name, first, second } = &errors[1] else {
+        panic!("expected a version conflict, got {:?}", errors[1]);
+    };
+    assert_eq!(name, "util");
+    assert_eq!((first.0.major, second.0.major), (2, 1));
+
+    fs::remove_dir_all(root).unwrap();
+}
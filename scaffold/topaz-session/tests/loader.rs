@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use topaz_ast::ident::Ident;
+use topaz_session::{LoadError, Session};
+
+/// Creates a fresh gem directory named `name` with the given files.
+fn gem(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("topaz-session-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (path, source) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    root
+}
+
+fn modules(session: &Session) -> Vec<String> {
+    session
+        .modules
+        .iter()
+        .map(|module| topaz_session::loader::module_name(&module.module))
+        .collect()
+}
+
+#[test]
+fn test_module_hierarchy() {
+    let root = gem("hierarchy", &[
+        ("src/lib.tp", "import gem::mem::drop::drop;"),
+        ("src/default.tp", ""),
+        ("src/mem/drop.tp", "public func drop() {}"),
+    ]);
+
+    let mut session = Session::new();
+    session.load(&root);
+
+    assert!(!session.has_errors(), "{:?}", session.errors);
+    assert_eq!(modules(&session), ["gem::default", "gem", "gem::mem::drop"]);
+    // file IDs follow the order of the paths
+    let ids: Vec<_> = session.modules.iter().map(|module| module.file_id).collect();
+    assert_eq!(ids, [0, 1, 2]);
+    assert_eq!(session.modules[2].module, [Ident::new("mem"), Ident::new("drop")]);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_duplicate_and_missing_modules() {
+    let root = gem("errors", &[
+        ("src/main.tp", "import gem::nowhere::thing;"),
+        ("src/mem.tp", ""),
+        ("src/mem/mod.tp", ""),
+    ]);
+
+    let mut session = Session::new();
+    session.load(&root);
+
+    let errors: Vec<_> = session.errors.iter().map(|(_, error)| error).collect();
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(matches!(
+        errors[0],
+        LoadError::DuplicateModule { module, .. } if module == "gem::mem"
+    ));
+    let LoadError::MissingModule { module, candidates, .. } = errors[1] else {
+        panic!("expected a missing module, got {:?}", errors[1]);
+    };
+    assert_eq!(module, "gem::nowhere");
+    assert_eq!(candidates, &[
+        root.join("src/nowhere.tp"),
+        root.join("src/nowhere").join("mod.tp"),
+    ]);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_single_file() {
+    let root = gem("single", &[("hello.tp", "public func main() {}")]);
+
+    let mut session = Session::new();
+    session.load(&Path::new(&root).join("hello.tp"));
+
+    assert!(!session.has_errors());
+    assert_eq!(modules(&session), ["gem"]);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_std_prelude() {
+    let prelude = topaz_session::prelude::std_prelude();
+    let mut names: Vec<_> = prelude.names().map(ToString::to_string).collect();
+    names.sort();
+    assert_eq!(names, ["Copy", "Default", "Drop", "println"]);
+}
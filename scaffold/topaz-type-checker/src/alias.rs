@@ -0,0 +1,234 @@
+//! `alias.rs` - expansion of type aliases.
+//!
+//! Every alias is expanded once, up-front, into a [`Ty`] with its generic parameters
+//! as [`Ty::Param`]s. A use of the alias substitutes its type arguments into that,
+//! and is kept as a [`Ty::Alias`] so that diagnostics can show both names.
+use crate::error::StaticAnalysisError;
+use crate::pass::{AnalysisPass, AnalysisSession};
+use crate::ty::{Ty, THIS};
+use std::collections::HashMap;
+use topaz_ast::item::type_alias::TypeAlias;
+use topaz_ast::item::Item;
+use topaz_ast::types::{NormalTypeArguments, Type, TypeArguments, TypePath};
+use topaz_ast::visit::{self, Visit};
+use topaz_resolve::def::{DefId, DefKind};
+use topaz_resolve::{Res, Resolutions, SourceModule};
+
+/// A type alias with everything in it expanded.
+#[derive(Debug, Clone)]
+pub struct ExpandedAlias {
+    pub params: Vec<String>,
+    /// [`Ty::Error`] for aliases that refer to themselves
+    pub ty: Ty,
+}
+
+/// Every type alias of a gem.
+#[derive(Debug, Default)]
+pub struct AliasEnv {
+    pub aliases: HashMap<DefId, ExpandedAlias>,
+    /// Aliases that refer to themselves, with the file they are defined in
+    cycles: Vec<(usize, StaticAnalysisError)>,
+}
+
+impl AliasEnv {
+    #[must_use]
+    pub fn collect(resolutions: &Resolutions, modules: &[SourceModule]) -> Self {
+        let mut expander = Expander {
+            resolutions,
+            sources: HashMap::new(),
+            env: Self::default(),
+            stack: Vec::new(),
+        };
+        for module in modules {
+            for item in &module.file.items {
+                let Item::TypeAlias(alias @ TypeAlias(_, _, _, name, _)) = &item.value else { continue };
+                if let Some(def) = resolutions.decl_of(module.file_id, name) {
+                    expander.sources.insert(def, (module.file_id, alias));
+                }
+            }
+        }
+
+        let mut defs: Vec<_> = expander.sources.keys().copied().collect();
+        defs.sort();
+        for def in defs {
+            expander.expand(def);
+        }
+        expander.env
+    }
+
+    /// Lowers a type of the file `file_id`, expanding the aliases in it.
+    #[must_use]
+    pub fn lower(&self, resolutions: &Resolutions, file_id: usize, ty: &Type) -> Ty {
+        Ty::lower(ty, &mut |path| {
+            lower_path(resolutions, file_id, path, &mut |def, args| {
+                let alias = self.aliases.get(&def)?;
+                Some(instantiate(alias, args))
+            })
+        })
+    }
+}
+
+/// Lowers `path`, with `alias` lowering the uses of aliases given their arguments.
+fn lower_path(
+    resolutions: &Resolutions,
+    file_id: usize,
+    TypePath { path, arguments }: &TypePath,
+    alias: &mut dyn FnMut(DefId, Vec<Ty>) -> Option<Ty>,
+) -> Ty {
+    let res = resolutions.res_of(file_id, path);
+    let Some(Res::Def(def)) = res else {
+        // the type a method is defined on, see `crate::infer`
+        if path.len() == 1 && path.to_string() == THIS {
+            return Ty::Param(THIS.to_owned());
+        }
+        return Ty::Named(path.to_string());
+    };
+    let data = resolutions.defs.get(*def);
+    match data.kind {
+        DefKind::TypeParam => Ty::Param(path.to_string()),
+        DefKind::Struct => Ty::Struct {
+            def: *def,
+            name: data.name.to_string(),
+        },
+        DefKind::Enum => Ty::Enum {
+            def: *def,
+            name: data.name.to_string(),
+        },
+        DefKind::TypeAlias => {
+            let args = type_args(arguments)
+                .map(|arg| Ty::lower(arg, &mut |path| lower_path(resolutions, file_id, path, alias)))
+                .collect::<Vec<_>>();
+            let name = if args.is_empty() {
+                path.to_string()
+            } else {
+                let args: Vec<_> = args.iter().map(ToString::to_string).collect();
+                format!("{path}<{}>", args.join(", "))
+            };
+            let ty = alias(*def, args).unwrap_or(Ty::Error);
+            Ty::Alias { name, ty: Box::new(ty) }
+        }
+        _ => Ty::Named(path.to_string()),
+    }
+}
+
+fn type_args(arguments: &TypeArguments) -> impl Iterator<Item = &Type> {
+    let args = match arguments {
+        TypeArguments::Normal(NormalTypeArguments(args)) => Some(args.content().iter()),
+        _ => None,
+    };
+    args.into_iter().flatten()
+}
+
+/// The expansion of `alias` with `args` as its parameters,
+/// or [`Ty::Error`] if there are not as many as it has (see [`Aliases`]).
+fn instantiate(alias: &ExpandedAlias, args: Vec<Ty>) -> Ty {
+    if args.len() != alias.params.len() {
+        return Ty::Error;
+    }
+    alias.ty.subst(&alias.params.iter().cloned().zip(args).collect())
+}
+
+struct Expander<'a> {
+    resolutions: &'a Resolutions,
+    sources: HashMap<DefId, (usize, &'a TypeAlias)>,
+    env: AliasEnv,
+    /// The aliases being expanded, each one refers to the next one
+    stack: Vec<DefId>,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, def: DefId) -> Option<ExpandedAlias> {
+        if let Some(alias) = self.env.aliases.get(&def) {
+            return Some(alias.clone());
+        }
+        let (file_id, alias @ TypeAlias(_, _, _, name, aliased)) = *self.sources.get(&def)?;
+
+        if let Some(start) = self.stack.iter().position(|on_stack| *on_stack == def) {
+            let mut cycle: Vec<_> = self.stack[start..]
+                .iter()
+                .map(|def| self.resolutions.defs.get(*def).name.to_string())
+                .collect();
+            cycle.push(name.to_string());
+            self.env.cycles.push((file_id, StaticAnalysisError::CyclicAlias {
+                name: name.to_string(),
+                cycle,
+                span: name.span(),
+            }));
+            // every alias of the cycle expands to an error from now on
+            for def in &self.stack[start..] {
+                let params = self.sources[def].1.params().map(ToString::to_string).collect();
+                self.env.aliases.insert(*def, ExpandedAlias { params, ty: Ty::Error });
+            }
+            return None;
+        }
+
+        self.stack.push(def);
+        let resolutions = self.resolutions;
+        let ty = Ty::lower(aliased, &mut |path| {
+            lower_path(resolutions, file_id, path, &mut |def, args| {
+                let alias = self.expand(def)?;
+                Some(instantiate(&alias, args))
+            })
+        });
+        self.stack.pop();
+
+        // an alias of the cycle may have been given up on in the meantime
+        let expanded = self.env.aliases.entry(def).or_insert(ExpandedAlias {
+            params: alias.params().map(ToString::to_string).collect(),
+            ty,
+        });
+        Some(expanded.clone())
+    }
+}
+
+/// Reports aliases that refer to themselves, and uses of aliases with the wrong number of type arguments.
+#[derive(Default)]
+pub struct Aliases;
+
+impl AnalysisPass for Aliases {
+    fn name(&self) -> &'static str {
+        "aliases"
+    }
+
+    fn run(&mut self, session: &mut AnalysisSession) {
+        let mut errors = session.aliases.cycles.clone();
+        for module in session.modules {
+            let mut uses = AliasUses {
+                session,
+                file_id: module.file_id,
+                errors: Vec::new(),
+            };
+            uses.visit_file(module.file);
+            errors.extend(uses.errors.into_iter().map(|error| (module.file_id, error)));
+        }
+        for (file_id, error) in errors {
+            session.error(file_id, error);
+        }
+    }
+}
+
+struct AliasUses<'a, 's> {
+    session: &'s AnalysisSession<'a>,
+    file_id: usize,
+    errors: Vec<StaticAnalysisError>,
+}
+
+impl Visit for AliasUses<'_, '_> {
+    fn visit_type_path(&mut self, type_path: &TypePath) {
+        let resolutions = self.session.resolutions;
+        if let Some(Res::Def(def)) = resolutions.res_of(self.file_id, &type_path.path) {
+            if let Some(alias) = self.session.aliases.aliases.get(def) {
+                let found = type_args(&type_path.arguments).count();
+                if found != alias.params.len() {
+                    self.errors.push(StaticAnalysisError::TypeArgumentCount {
+                        name: type_path.path.to_string(),
+                        expected: alias.params.len(),
+                        found,
+                        span: type_path.path.span(),
+                    });
+                }
+            }
+        }
+        visit::walk_type_path(self, type_path);
+    }
+}
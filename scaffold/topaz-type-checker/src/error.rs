@@ -1,12 +1,663 @@
-use codespan_reporting::diagnostic::Diagnostic;
+use crate::lint::levels::{Level, LevelSource, LintLevel};
+use crate::ty::{IntTy, Ty};
+use crate::warning::StaticAnalysisWarning;
+use codespan_reporting::diagnostic::{Diagnostic, Label};
 use thiserror::Error;
+use topaz_ast::location::Span;
 use topaz_report::Reporter;
 
-#[derive(Error, Copy, Clone, Debug, PartialEq, Eq)]
-pub enum StaticAnalysisError {}
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum StaticAnalysisError {
+    #[error("mismatched types: expected {}, found {}", .expected.describe(), .found.describe())]
+    TypeMismatch {
+        expected: Ty,
+        found: Ty,
+        /// What the expected type comes from, e.g. a type annotation
+        expected_span: Option<Span>,
+        found_span: Span,
+    },
+    #[error("cannot infer the type of `{name}`")]
+    CannotInfer { name: String, span: Span },
+    #[error("`{name}` takes {} but {found} {} passed", arguments(*.required, *.total), if *.found == 1 { "was" } else { "were" })]
+    ArgumentCount {
+        name: String,
+        required: usize,
+        total: usize,
+        found: usize,
+        /// The required parameters no argument was passed for
+        missing: Vec<String>,
+        span: Span,
+    },
+    #[error("`{function}` has no parameter named `{name}`")]
+    UnknownArgument {
+        name: String,
+        function: String,
+        span: Span,
+    },
+    #[error("`return` without a value in a function returning `{expected}`")]
+    MissingReturnValue {
+        expected: String,
+        span: Span,
+        ret_span: Option<Span>,
+    },
+    #[error("`{name}` does not return a value on every path")]
+    MissingReturn {
+        name: String,
+        expected: String,
+        /// The return type annotation
+        span: Span,
+    },
+    #[error("conflicting implementations of trait `{trait_name}` for type `{self_ty}`")]
+    ConflictingImpls {
+        trait_name: String,
+        self_ty: String,
+        /// The file and header of the impl this one overlaps with
+        first: (usize, Span),
+        span: Span,
+    },
+    #[error("only traits defined in the current gem can be implemented for types defined outside of it")]
+    OrphanImpl {
+        trait_name: String,
+        self_ty: String,
+        /// Where the trait comes from, e.g. "`std`"
+        from: &'static str,
+        span: Span,
+    },
+    #[error("not all required methods of `{trait_name}` are implemented")]
+    MissingMethods {
+        trait_name: String,
+        missing: Vec<String>,
+        span: Span,
+    },
+    #[error("method `{method}` is not a member of trait `{trait_name}`")]
+    NotAMember {
+        method: String,
+        trait_name: String,
+        /// Whether the method is marked `@override`
+        overrides: bool,
+        span: Span,
+    },
+    #[error("type alias `{name}` refers to itself")]
+    CyclicAlias {
+        name: String,
+        /// The aliases of the cycle, starting and ending with `name`
+        cycle: Vec<String>,
+        span: Span,
+    },
+    #[error("`{name}` takes {expected} type argument{} but {found} {} given", if *.expected == 1 { "" } else { "s" }, if *.found == 1 { "was" } else { "were" })]
+    TypeArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    #[error("expected a trait, found {kind} `{name}`")]
+    NotATrait {
+        name: String,
+        kind: &'static str,
+        span: Span,
+    },
+    #[error("cannot borrow `{name}` as {} {}", mutability(*.second_mutable), if *.first_mutable && *.second_mutable { "more than once at a time".to_owned() } else { format!("because it is also borrowed as {}", mutability(*.first_mutable)) })]
+    BorrowConflict {
+        name: String,
+        first_mutable: bool,
+        second_mutable: bool,
+        /// The first borrow
+        borrow: Span,
+        span: Span,
+        /// Where the first borrow is used after the second one
+        later: Option<Span>,
+    },
+    #[error("cannot {action} `{name}` because it is borrowed")]
+    UseWhileBorrowed {
+        name: String,
+        /// e.g. "move out of"
+        action: &'static str,
+        borrow_mutable: bool,
+        borrow: Span,
+        span: Span,
+        later: Option<Span>,
+    },
+    #[error("`{name}` does not live long enough")]
+    BorrowedValueDropped {
+        name: String,
+        borrow: Span,
+        /// The end of the scope of `name`
+        span: Span,
+        later: Option<Span>,
+    },
+    #[error("cannot return a reference to local variable `{name}`")]
+    ReturnsLocalReference { name: String, borrow: Span, span: Span },
+    #[error("cannot borrow `{name}` as mutable, as it is not declared as mutable")]
+    MutBorrowOfImmutable {
+        name: String,
+        span: Span,
+        /// The declaration of `name`
+        decl: Span,
+    },
+    #[error("cannot borrow `{name}` as mutable, as it is behind a `&` reference")]
+    MutBorrowBehindRef {
+        name: String,
+        span: Span,
+    },
+    #[error("use of {}moved value: `{place}`", if *.partial { "partially " } else { "" })]
+    UseAfterMove {
+        place: String,
+        /// What was moved: `place` itself, or a field of it if the move is `partial`
+        moved_place: String,
+        moved_ty: Ty,
+        partial: bool,
+        moved: Span,
+        /// Whether the move happens later in a loop, so it is only reached by the next iteration
+        in_loop: bool,
+        span: Span,
+    },
+    #[error("cannot move out of `{place}`, which the function only borrows")]
+    MoveOutOfBorrowedParam {
+        place: String,
+        /// The parameter `place` belongs to
+        param: String,
+        param_span: Span,
+        span: Span,
+    },
+    #[error("no field `{field}` on type {}", .ty.describe())]
+    NoField { field: String, ty: Ty, span: Span },
+    #[error("cannot apply `{op}` to {}", .ty.describe())]
+    InvalidOperand {
+        op: String,
+        ty: Ty,
+        /// What the operator takes instead, e.g. "signed integers"
+        expected: &'static str,
+        span: Span,
+    },
+    #[error("cannot `match` on {}", .ty.describe())]
+    MatchNotEnum { ty: Ty, span: Span },
+    #[error("`{name}` is not a variant of `{enum_name}`")]
+    NotAVariant { name: String, enum_name: String, span: Span },
+    #[error("`{variant}` has {expected} field{}, but the pattern binds {found}", if *.expected == 1 { "" } else { "s" })]
+    PatternFieldCount {
+        variant: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    #[error("non-exhaustive patterns: {} not covered", .missing.iter().map(|name| format!("`{name}`")).collect::<Vec<_>>().join(", "))]
+    NonExhaustiveMatch {
+        enum_name: String,
+        /// The variants no arm matches
+        missing: Vec<String>,
+        span: Span,
+    },
+    #[error("cannot move out of an array of {}", .ty.describe())]
+    MoveOutOfArray {
+        /// The type of the elements
+        ty: Ty,
+        span: Span,
+    },
+    #[error("cannot move out of a field of {}, which is behind a reference", .ty.describe())]
+    MoveOutOfRef {
+        /// The type of the field
+        ty: Ty,
+        span: Span,
+    },
+    #[error("literal out of range for `{}`", .ty.name())]
+    LiteralOutOfRange {
+        ty: IntTy,
+        span: Span,
+    },
+    #[error("cannot find constant `{name}` in this scope")]
+    UnknownConst {
+        name: String,
+        span: Span,
+    },
+    #[error("the trait `{trait_name}` is not implemented for `{ty}`")]
+    Unimplemented {
+        trait_name: String,
+        /// The method called, as written
+        method: String,
+        /// What `this` stands for in the call
+        ty: Ty,
+        span: Span,
+    },
+    #[error("the trait `Copy` cannot be implemented for `{self_ty}`")]
+    NonCopyField {
+        self_ty: String,
+        field: String,
+        field_ty: Ty,
+        /// The file and type annotation of the field
+        field_span: (usize, Span),
+        span: Span,
+    },
+    #[error("used binding `{name}` {}", if *.possibly { "is possibly-uninitialized" } else { "isn't initialized" })]
+    UninitializedUse {
+        name: String,
+        /// Whether the binding is assigned on some paths to the use, but not all of them
+        possibly: bool,
+        decl: Span,
+        span: Span,
+    },
+    #[error("cannot assign twice to immutable variable `{name}`")]
+    AssignTwice {
+        name: String,
+        decl: Span,
+        /// The assignment that may have happened before, unless it is this one in an earlier iteration of a loop
+        first: Option<Span>,
+        span: Span,
+    },
+    /// A lint at the `deny` or `forbid` level
+    #[error("{warning}")]
+    DeniedLint {
+        warning: Box<StaticAnalysisWarning>,
+        level: LintLevel,
+    },
+    #[error("`@{}({name})` incompatible with previous forbid", .level.name())]
+    OverruledForbid {
+        level: Level,
+        name: String,
+        span: Span,
+        forbid: LintLevel,
+    },
+    #[error("malformed lint attribute `@{name}`")]
+    MalformedLintAttribute { name: String, span: Span },
+    #[error("cannot move out of `{place}`, because {} implements `Drop`", .ty.describe())]
+    MoveOutOfDrop { place: String, ty: Ty, span: Span },
+}
+
+const fn mutability(mutable: bool) -> &'static str {
+    if mutable {
+        "mutable"
+    } else {
+        "immutable"
+    }
+}
+
+/// The labels of a borrow conflict: the borrow, the conflicting use, and the later use keeping the borrow alive.
+fn borrow_labels(
+    file_id: usize,
+    (borrow, borrow_message): (Span, String),
+    (span, message): (Span, String),
+    later: Option<Span>,
+) -> Vec<Label<usize>> {
+    let mut labels = vec![
+        Label::primary(file_id, span).with_message(message),
+        Label::secondary(file_id, borrow).with_message(borrow_message),
+    ];
+    labels.extend(later.map(|later| Label::secondary(file_id, later).with_message("borrow later used here")));
+    labels
+}
+
+fn arguments(required: usize, total: usize) -> String {
+    let plural = if total == 1 { "argument" } else { "arguments" };
+    if required == total {
+        format!("{total} {plural}")
+    } else {
+        format!("{required} to {total} {plural}")
+    }
+}
 
 impl<'source> Reporter<'source> for StaticAnalysisError {
-    fn build_diagnostic(&self, _file_id: usize) -> Diagnostic<usize> {
-        todo!()
+    fn build_diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+        match self {
+            Self::TypeMismatch {
+                expected,
+                found,
+                expected_span,
+                found_span,
+            } => {
+                let (expected, found) = (expected.describe(), found.describe());
+                let mut labels = vec![Label::primary(file_id, *found_span)
+                    .with_message(format!("expected {expected}, found {found}"))];
+                if let Some(span) = expected_span {
+                    labels.push(
+                        Label::secondary(file_id, *span)
+                            .with_message(format!("expected {expected} because of this")),
+                    );
+                }
+                Diagnostic::error()
+                    .with_code("E016")
+                    .with_message("mismatched types")
+                    .with_labels(labels)
+            }
+            Self::CannotInfer { name, span } => Diagnostic::error()
+                .with_code("E017")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message("type annotations needed")])
+                .with_notes(vec![format!("consider giving `{name}` a type, e.g. `{name}: int`")]),
+            Self::ArgumentCount { missing, span, .. } => {
+                let message = if missing.is_empty() {
+                    "too many arguments".to_owned()
+                } else {
+                    let missing: Vec<_> = missing.iter().map(|name| format!("`{name}`")).collect();
+                    format!("missing {}", missing.join(", "))
+                };
+                Diagnostic::error()
+                    .with_code("E018")
+                    .with_message(self.to_string())
+                    .with_labels(vec![Label::primary(file_id, *span).with_message(message)])
+            }
+            Self::UnknownArgument { span, .. } => Diagnostic::error()
+                .with_code("E019")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("unknown parameter")]),
+            Self::MissingReturnValue {
+                expected,
+                span,
+                ret_span,
+            } => {
+                let mut labels = vec![Label::primary(file_id, *span)
+                    .with_message(format!("expected a value of type `{expected}`"))];
+                labels.extend(ret_span.map(|ret_span| {
+                    Label::secondary(file_id, ret_span).with_message("because of this return type")
+                }));
+                Diagnostic::error()
+                    .with_code("E020")
+                    .with_message(self.to_string())
+                    .with_labels(labels)
+            }
+            Self::MissingReturn { expected, span, .. } => Diagnostic::error()
+                .with_code("E021")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message(format!("expected `{expected}` because of this return type"))])
+                .with_notes(vec!["the body can reach its end without a `return`".to_owned()]),
+            Self::ConflictingImpls { self_ty, first, span, .. } => Diagnostic::error()
+                .with_code("E022")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message(format!("conflicting implementation for `{self_ty}`")),
+                    Label::secondary(first.0, first.1).with_message("first implementation here"),
+                ]),
+            Self::OrphanImpl {
+                trait_name,
+                self_ty,
+                from,
+                span,
+            } => Diagnostic::error()
+                .with_code("E023")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message(format!("`{trait_name}` is from {from}, and `{self_ty}` is not defined in this gem"))])
+                .with_notes(vec![format!("implement `{trait_name}` for a type of this gem instead")]),
+            Self::MissingMethods { missing, span, .. } => {
+                let missing: Vec<_> = missing.iter().map(|name| format!("`{name}`")).collect();
+                Diagnostic::error()
+                    .with_code("E024")
+                    .with_message(self.to_string())
+                    .with_labels(vec![Label::primary(file_id, *span)
+                        .with_message(format!("missing {}", missing.join(", ")))])
+            }
+            Self::NotAMember { overrides, span, .. } => Diagnostic::error()
+                .with_code("E025")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message(if *overrides {
+                    "marked `@override`, but overrides nothing"
+                } else {
+                    "not a member of the trait"
+                })]),
+            Self::CyclicAlias { cycle, span, .. } => {
+                let cycle: Vec<_> = cycle.iter().map(|name| format!("`{name}`")).collect();
+                Diagnostic::error()
+                    .with_code("E027")
+                    .with_message(self.to_string())
+                    .with_labels(vec![Label::primary(file_id, *span).with_message("expanding this alias never ends")])
+                    .with_notes(vec![format!("the cycle is {}", cycle.join(" -> "))])
+            }
+            Self::TypeArgumentCount { expected, span, .. } => Diagnostic::error()
+                .with_code("E028")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message(format!("expected {expected} type argument{}", if *expected == 1 { "" } else { "s" }))]),
+            Self::NotATrait { kind, span, .. } => Diagnostic::error()
+                .with_code("E026")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message(format!("not a trait, but a {kind}"))]),
+            Self::BorrowConflict {
+                name,
+                first_mutable,
+                second_mutable,
+                borrow,
+                span,
+                later,
+            } => {
+                let (first, second) = if *first_mutable && *second_mutable {
+                    ("first mutable borrow occurs here".to_owned(), "second mutable borrow occurs here".to_owned())
+                } else {
+                    (
+                        format!("{} borrow occurs here", mutability(*first_mutable)),
+                        format!("{} borrow of `{name}` occurs here", mutability(*second_mutable)),
+                    )
+                };
+                Diagnostic::error()
+                    .with_code("E029")
+                    .with_message(self.to_string())
+                    .with_labels(borrow_labels(file_id, (*borrow, first), (*span, second), *later))
+            }
+            Self::UseWhileBorrowed {
+                name,
+                action,
+                borrow_mutable,
+                borrow,
+                span,
+                later,
+            } => {
+                let borrowed = format!("`{name}` is {}borrowed here", if *borrow_mutable { "mutably " } else { "" });
+                Diagnostic::error()
+                    .with_code("E030")
+                    .with_message(self.to_string())
+                    .with_labels(borrow_labels(file_id, (*borrow, borrowed), (*span, format!("{action} borrowed `{name}`")), *later))
+            }
+            Self::BorrowedValueDropped { name, borrow, span, later } => Diagnostic::error()
+                .with_code("E031")
+                .with_message(self.to_string())
+                .with_labels(borrow_labels(
+                    file_id,
+                    (*borrow, "borrowed value does not live long enough".to_owned()),
+                    (*span, format!("`{name}` dropped here while still borrowed")),
+                    *later,
+                )),
+            Self::ReturnsLocalReference { name, borrow, span } => Diagnostic::error()
+                .with_code("E032")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message("returns a reference to data owned by the current function"),
+                    Label::secondary(file_id, *borrow).with_message(format!("`{name}` is borrowed here")),
+                ]),
+            Self::UseAfterMove {
+                moved_place,
+                moved_ty,
+                partial,
+                moved,
+                in_loop,
+                span,
+                ..
+            } => Diagnostic::error()
+                .with_code("E034")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message(if *partial {
+                        "value used here after partial move"
+                    } else {
+                        "value used here after move"
+                    }),
+                    Label::secondary(file_id, *moved).with_message(if *in_loop {
+                        "value moved here, in previous iteration of loop"
+                    } else {
+                        "value moved here"
+                    }),
+                ])
+                .with_notes(vec![format!(
+                    "move occurs because `{moved_place}` has type {}, which is not `Copy`",
+                    moved_ty.describe()
+                )]),
+            Self::MoveOutOfBorrowedParam {
+                param,
+                param_span,
+                span,
+                ..
+            } => Diagnostic::error()
+                .with_code("E035")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message("move occurs here"),
+                    Label::secondary(file_id, *param_span)
+                        .with_message(format!("help: consider taking ownership with `move {param}`")),
+                ]),
+            Self::NoField { span, .. } => Diagnostic::error()
+                .with_code("E036")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("unknown field")]),
+            Self::InvalidOperand { op, ty, expected, span } => Diagnostic::error()
+                .with_code("E044")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message(format!("this is of type `{ty}`"))])
+                .with_notes(vec![format!("`{op}` takes {expected}")]),
+            Self::MatchNotEnum { ty, span } => Diagnostic::error()
+                .with_code("E045")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message(format!("this is of type `{ty}`"))])
+                .with_notes(vec!["only enums can be matched on".to_owned()]),
+            Self::NotAVariant { enum_name, span, .. } => Diagnostic::error()
+                .with_code("E046")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message(format!("expected a variant of `{enum_name}`"))
+                ]),
+            Self::PatternFieldCount { expected, span, .. } => Diagnostic::error()
+                .with_code("E047")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message(format!("expected {expected} binding{}", if *expected == 1 { "" } else { "s" }))]),
+            Self::NonExhaustiveMatch { enum_name, missing, span } => Diagnostic::error()
+                .with_code("E048")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message(format!("this is a `{enum_name}`"))])
+                .with_notes(vec![format!(
+                    "add an arm for {}, or a `_` arm matching every other variant",
+                    if missing.len() == 1 { "it" } else { "each of them" }
+                )]),
+            Self::MoveOutOfArray { span, .. } => Diagnostic::error()
+                .with_code("E049")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("cannot move an element out of this")])
+                .with_notes(vec!["indexing copies the element, so only arrays of `Copy` elements can be indexed".to_owned()]),
+            Self::MoveOutOfRef { span, .. } => Diagnostic::error()
+                .with_code("E050")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("cannot move out of this field")])
+                .with_notes(vec!["fields are copied out of references, so only `Copy` ones can be read".to_owned()]),
+            Self::LiteralOutOfRange { ty, span } => {
+                let (min, max) = ty.range(IntTy::MAX_PTR_BITS);
+                Diagnostic::error()
+                    .with_code("E052")
+                    .with_message(self.to_string())
+                    .with_labels(vec![Label::primary(file_id, *span).with_message(format!("not a `{}`", ty.name()))])
+                    .with_notes(vec![format!("`{}` ranges from `{min}` to `{max}`", ty.name())])
+            }
+            Self::UnknownConst { span, .. } => Diagnostic::error()
+                .with_code("E053")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("not found in this scope")])
+                .with_notes(vec!["constants cannot be declared yet".to_owned()]),
+            Self::Unimplemented { trait_name, method, ty, span } => Diagnostic::error()
+                .with_code("E054")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message(format!("`{method}` is called on `{ty}` here"))])
+                .with_notes(vec![format!("a method of a trait is called on the types it is implemented for, with an `impl {trait_name} for {ty}`")]),
+            Self::NonCopyField {
+                field,
+                field_ty,
+                field_span,
+                span,
+                ..
+            } => Diagnostic::error()
+                .with_code("E037")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message("`Copy` is implemented here"),
+                    Label::secondary(field_span.0, field_span.1)
+                        .with_message(format!("this field has type {}, which is not `Copy`", field_ty.describe())),
+                ])
+                .with_notes(vec![format!("a copy of the struct would have to copy `{field}` too")]),
+            Self::UninitializedUse {
+                name,
+                possibly,
+                decl,
+                span,
+            } => Diagnostic::error()
+                .with_code("E039")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message(if *possibly {
+                        format!("`{name}` used here but it is possibly-uninitialized")
+                    } else {
+                        format!("`{name}` used here but it isn't initialized")
+                    }),
+                    Label::secondary(file_id, *decl).with_message("binding declared here but left uninitialized"),
+                ])
+                .with_notes(vec![format!("help: assign `{name}` a value on every path before it is used")]),
+            Self::AssignTwice {
+                name,
+                decl,
+                first,
+                span,
+            } => {
+                let mut labels = vec![
+                    Label::primary(file_id, *span).with_message("cannot assign twice to immutable variable"),
+                    Label::secondary(file_id, *decl)
+                        .with_message(format!("help: consider making this binding mutable: `mut {name}`")),
+                ];
+                labels.extend(
+                    first.map(|first| Label::secondary(file_id, first).with_message(format!("first assignment to `{name}`"))),
+                );
+                Diagnostic::error()
+                    .with_code("E040")
+                    .with_message(self.to_string())
+                    .with_labels(labels)
+            }
+            Self::DeniedLint { warning, level } => warning.build_diagnostic_at(file_id, level),
+            Self::OverruledForbid { span, forbid, .. } => {
+                let overruled = Label::primary(file_id, *span).with_message("overruled by previous forbid");
+                let (labels, notes) = match &forbid.source {
+                    LevelSource::Attribute { file_id, span, .. } => {
+                        (vec![Label::secondary(*file_id, *span).with_message("`forbid` level set here")], Vec::new())
+                    }
+                    LevelSource::CommandLine { name } => {
+                        (Vec::new(), vec![format!("`forbid` level set on the command line with `-F {name}`")])
+                    }
+                    LevelSource::Default => (Vec::new(), Vec::new()),
+                };
+                Diagnostic::error()
+                    .with_code("E041")
+                    .with_message(self.to_string())
+                    .with_labels(vec![overruled])
+                    .with_labels(labels)
+                    .with_notes(notes)
+            }
+            Self::MalformedLintAttribute { name, span } => Diagnostic::error()
+                .with_code("E042")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("expected the lints in parentheses")])
+                .with_notes(vec![format!("e.g. `@{name}(dead_code)`")]),
+            Self::MoveOutOfDrop { span, .. } => Diagnostic::error()
+                .with_code("E038")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("cannot move out of here")])
+                .with_notes(vec![
+                    "a value with a `Drop` impl is dropped as a whole, so none of its fields can be moved out of it"
+                        .to_owned(),
+                ]),
+            Self::MutBorrowOfImmutable { name, span, decl } => Diagnostic::error()
+                .with_code("E033")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span).with_message("cannot borrow as mutable"),
+                    Label::secondary(file_id, *decl).with_message(format!("help: consider changing this to `mut {name}`")),
+                ]),
+            Self::MutBorrowBehindRef { span, .. } => Diagnostic::error()
+                .with_code("E051")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span).with_message("cannot borrow as mutable")])
+                .with_notes(vec!["only a field behind `&mut` references can be borrowed mutably".to_owned()]),
+        }
     }
 }
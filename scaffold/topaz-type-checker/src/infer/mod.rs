@@ -0,0 +1,750 @@
+//! `infer/mod.rs` - Hindley-Milner style type inference, local to every function body.
+//!
+//! Every `let` without an annotation gets a fresh type variable, which is bound by its
+//! initializer or by any later use. Unsuffixed integer literals get an integer variable,
+//! defaulting to `int` if nothing else constrains it.
+use crate::alias::AliasEnv;
+use crate::error::StaticAnalysisError;
+use crate::pass::{item_bodies, AnalysisPass, AnalysisSession};
+use crate::structs::{EnumDef, StructEnv};
+use crate::traits::{SelectionError, TraitEnv, TraitRef};
+use crate::ty::{IntTy, Ty, THIS};
+use std::collections::HashMap;
+use signature::{collect_signatures, FnSig};
+use table::InferTable;
+use topaz_ast::block::Block;
+use topaz_ast::expr::{
+    BinOp, Expr, ExprArray, ExprBinary, ExprBorrow, ExprConstAccess, ExprField, ExprIndex, ExprLit, ExprUnary,
+    ExprVarAccess, UnOp,
+};
+use topaz_ast::ident::Ident;
+use topaz_ast::item::func::{Func, FuncArg, FuncBody};
+use topaz_ast::literal::number::NumberSuffix;
+use topaz_ast::literal::Literal;
+use topaz_ast::location::{Span, WithSpan};
+use topaz_ast::path::Path;
+use topaz_ast::pattern::ArmPattern;
+use topaz_ast::statement::control::{ElseBranch, IfStmt, MatchArm, MatchStmt, ReturnStmt, WhileStmt};
+use topaz_ast::statement::func_call::{FuncCallArg, FuncCallStmt};
+use topaz_ast::statement::variable::{AssignStmt, LetStmt};
+use topaz_ast::statement::Statement;
+use topaz_ast::types::Type;
+use topaz_resolve::def::DefId;
+use topaz_resolve::{Res, Resolutions};
+
+pub mod signature;
+pub mod table;
+
+/// The types found by [`TypeInference`].
+#[derive(Debug, Default)]
+pub struct TypeckResults {
+    /// The type of every expression, keyed by its file and span
+    pub exprs: HashMap<(usize, Span), Ty>,
+    /// The type of every parameter and `let` binding
+    pub locals: HashMap<DefId, Ty>,
+    /// The signature of every function of the gem
+    pub signatures: HashMap<DefId, FnSig>,
+    /// What `this` stands for in every call of a method of a trait, keyed by the file and span of the path called
+    pub methods: HashMap<(usize, Span), Ty>,
+}
+
+impl TypeckResults {
+    #[must_use]
+    pub fn expr_ty(&self, file_id: usize, expr: &WithSpan<Expr>) -> Option<&Ty> {
+        self.exprs.get(&(file_id, expr.span))
+    }
+}
+
+/// Infers the type of every local and expression, see [`TypeckResults`].
+#[derive(Default)]
+pub struct TypeInference;
+
+impl AnalysisPass for TypeInference {
+    fn name(&self) -> &'static str {
+        "infer"
+    }
+
+    fn run(&mut self, session: &mut AnalysisSession) {
+        let signatures = collect_signatures(session.resolutions, &session.aliases, &session.structs, session.modules);
+        let mut errors = Vec::new();
+        for module in session.modules {
+            for item in &module.file.items {
+                for (func, this) in item_bodies(&item.value) {
+                    let this = this.map(|ty| session.aliases.lower(session.resolutions, module.file_id, ty));
+                    let mut infer = FnInfer::new(
+                        session.resolutions,
+                        &session.aliases,
+                        &session.structs,
+                        &session.traits,
+                        &signatures,
+                        module.file_id,
+                        this,
+                    );
+                    infer.check_func(func);
+                    let found = infer.finish(&mut session.types);
+                    errors.extend(found.into_iter().map(|error| (module.file_id, error)));
+                }
+            }
+        }
+        session.types.signatures = signatures;
+        for (file_id, error) in errors {
+            session.error(file_id, error);
+        }
+    }
+}
+
+struct Local {
+    name: Ident,
+    ty: Ty,
+    /// Where the type of the local comes from: its annotation, or the first value given to it
+    origin: Option<Span>,
+}
+
+/// Inference state of a single function.
+struct FnInfer<'a> {
+    resolutions: &'a Resolutions,
+    aliases: &'a AliasEnv,
+    structs: &'a StructEnv,
+    traits: &'a TraitEnv,
+    signatures: &'a HashMap<DefId, FnSig>,
+    file_id: usize,
+    /// What `this` stands for, in a method of an impl
+    this: HashMap<String, Ty>,
+    /// The return type of the function, and its annotation
+    ret: (Ty, Option<Span>),
+    table: InferTable,
+    locals: HashMap<DefId, Local>,
+    exprs: Vec<(Span, Ty)>,
+    /// The integer literals, checked against the range of their type once it is known
+    literals: Vec<IntLiteral>,
+    /// The calls of methods of traits, checked to have an impl once the type `this` stands for is known
+    methods: Vec<MethodCall>,
+    errors: Vec<StaticAnalysisError>,
+}
+
+struct MethodCall {
+    trait_def: DefId,
+    /// The path called, e.g. `Shape::area`
+    path: String,
+    this: Ty,
+    span: Span,
+}
+
+struct IntLiteral {
+    /// The value without its sign, `None` if it does not fit in a `u128`
+    magnitude: Option<u128>,
+    negative: bool,
+    ty: Ty,
+    span: Span,
+}
+
+impl IntLiteral {
+    fn fits(&self, int: IntTy) -> bool {
+        let (min, max) = int.range(IntTy::MAX_PTR_BITS);
+        self.magnitude.map_or(false, |magnitude| magnitude <= if self.negative { min.unsigned_abs() } else { max })
+    }
+}
+
+impl<'a> FnInfer<'a> {
+    fn new(
+        resolutions: &'a Resolutions,
+        aliases: &'a AliasEnv,
+        structs: &'a StructEnv,
+        traits: &'a TraitEnv,
+        signatures: &'a HashMap<DefId, FnSig>,
+        file_id: usize,
+        this: Option<Ty>,
+    ) -> Self {
+        Self {
+            resolutions,
+            aliases,
+            structs,
+            traits,
+            signatures,
+            file_id,
+            this: this.map(|ty| (THIS.to_owned(), ty)).into_iter().collect(),
+            ret: (Ty::Void, None),
+            table: InferTable::default(),
+            locals: HashMap::new(),
+            exprs: Vec::new(),
+            literals: Vec::new(),
+            methods: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn check_func(&mut self, func @ Func(_, _, name, args, _, body): &Func) {
+        let sig = FnSig::from_ast(self.file_id, func, |ty| self.lower(ty));
+        for (param, FuncArg(_, _, _, default)) in sig.params.iter().zip(args) {
+            if let Some((_, default)) = default {
+                self.check_expr(default, &param.ty, Some(param.span));
+            }
+            self.declare(&param.name, param.ty.clone(), Some(param.span));
+        }
+        self.ret = (sig.ret, sig.ret_span);
+
+        let block = match body {
+            FuncBody::Block(block) => block,
+            // the expression is returned
+            FuncBody::Expr(_, expr) => {
+                let (ret, origin) = self.ret.clone();
+                self.check_expr(expr, &ret, origin);
+                return;
+            }
+        };
+        self.check_block(block);
+        if !block.diverges() && !matches!(self.ret.0.peel(), Ty::Void | Ty::Error) {
+            self.errors.push(StaticAnalysisError::MissingReturn {
+                name: name.to_string(),
+                expected: self.ret.0.to_string(),
+                span: self.ret.1.unwrap_or_else(|| name.span()),
+            });
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        for statement in block.statements() {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, WithSpan { value: statement, span }: &WithSpan<Statement>) {
+        match statement {
+            Statement::Let(LetStmt(_, _, name, ty, init)) => {
+                let (local_ty, mut origin) = match ty {
+                    Some((_, ty)) => (self.lower(&ty.value), Some(ty.span)),
+                    None => (self.table.new_var(), None),
+                };
+                if let Some((_, init)) = init {
+                    self.check_expr(init, &local_ty, origin);
+                    origin = origin.or(Some(init.span));
+                }
+                self.declare(name, local_ty, origin);
+            }
+            Statement::Assign(AssignStmt(place, _, value)) => {
+                let Some(def) = self.local_of(place) else {
+                    self.infer_expr(value);
+                    return;
+                };
+                let (expected, origin) = {
+                    let local = &self.locals[&def];
+                    (local.ty.clone(), local.origin)
+                };
+                self.check_expr(value, &expected, origin);
+                if let Some(local) = self.locals.get_mut(&def) {
+                    local.origin = local.origin.or(Some(value.span));
+                }
+            }
+            Statement::FuncCall(call) => {
+                self.infer_call(call);
+            }
+            Statement::Return(ReturnStmt(_, value)) => {
+                let (expected, origin) = self.ret.clone();
+                match value {
+                    Some(value) => self.check_expr(value, &expected, origin),
+                    None if !matches!(self.table.resolve(&expected).peel(), Ty::Void | Ty::Error) => {
+                        self.errors.push(StaticAnalysisError::MissingReturnValue {
+                            expected: expected.to_string(),
+                            span: *span,
+                            ret_span: origin,
+                        });
+                    }
+                    None => {}
+                }
+            }
+            Statement::While(WhileStmt(_, condition, body)) => {
+                self.check_expr(condition, &Ty::Bool, None);
+                self.check_block(body);
+            }
+            Statement::If(if_stmt) => self.check_if(if_stmt),
+            Statement::Match(match_stmt) => self.check_match(match_stmt),
+            Statement::Block(block) => self.check_block(block),
+        }
+    }
+
+    /// The scrutinee has to be an enum, every arm a variant of it binding each of its fields, and every
+    /// variant has to be matched by an arm, unless there is a `_` arm.
+    fn check_match(&mut self, MatchStmt(_, scrutinee, arms): &MatchStmt) {
+        let found = self.infer_expr(scrutinee);
+        let resolved = self.table.resolve(&found);
+        let enum_def = self.structs.enum_of(&resolved);
+        if enum_def.is_none() && !matches!(resolved.peel(), Ty::Error | Ty::Var(_)) {
+            self.errors.push(StaticAnalysisError::MatchNotEnum {
+                ty: resolved,
+                span: scrutinee.span,
+            });
+        }
+
+        let mut covered = vec![false; enum_def.map_or(0, |enum_def| enum_def.variants.len())];
+        let mut wildcard = false;
+        for MatchArm(pattern, _, body) in arms.content() {
+            match &pattern.value {
+                ArmPattern::Variant(path, bindings) => {
+                    let fields = match (enum_def, self.variant_of(path)) {
+                        (Some(enum_def), Some((variant_enum, index))) if variant_enum.def == enum_def.def => {
+                            covered[index] = true;
+                            let variant = &enum_def.variants[index];
+                            if variant.fields.len() != bindings.len() {
+                                self.errors.push(StaticAnalysisError::PatternFieldCount {
+                                    variant: path.to_string(),
+                                    expected: variant.fields.len(),
+                                    found: bindings.len(),
+                                    span: pattern.span,
+                                });
+                            }
+                            variant.fields.iter().map(|field| field.ty.clone()).collect()
+                        }
+                        // an unresolved path was already reported
+                        (Some(enum_def), _) if self.resolutions.res_of(self.file_id, path).is_some() => {
+                            self.errors.push(StaticAnalysisError::NotAVariant {
+                                name: path.to_string(),
+                                enum_name: enum_def.name.to_string(),
+                                span: path.span(),
+                            });
+                            Vec::new()
+                        }
+                        _ => Vec::new(),
+                    };
+                    for (i, binding) in bindings.iter().enumerate() {
+                        let ty = fields.get(i).cloned().unwrap_or(Ty::Error);
+                        self.declare(binding, ty, None);
+                    }
+                }
+                ArmPattern::Wildcard(_) => wildcard = true,
+            }
+            self.check_block(&body.value);
+        }
+
+        let Some(enum_def) = enum_def else { return };
+        let missing: Vec<_> = enum_def
+            .variants
+            .iter()
+            .zip(&covered)
+            .filter(|(_, covered)| !**covered)
+            .map(|(variant, _)| variant.name.to_string())
+            .collect();
+        if !wildcard && !missing.is_empty() {
+            self.errors.push(StaticAnalysisError::NonExhaustiveMatch {
+                enum_name: enum_def.name.to_string(),
+                missing,
+                span: scrutinee.span,
+            });
+        }
+    }
+
+    /// The enum `path` is a variant of and its index, if it is one.
+    fn variant_of(&self, path: &Path) -> Option<(&'a EnumDef, usize)> {
+        match self.resolutions.res_of(self.file_id, path)? {
+            Res::Def(def) => self.structs.variant(*def),
+            _ => None,
+        }
+    }
+
+    fn check_if(&mut self, IfStmt(_, condition, then, otherwise): &IfStmt) {
+        self.check_expr(condition, &Ty::Bool, None);
+        self.check_block(&then.value);
+        match otherwise {
+            Some((_, ElseBranch::Block(block))) => self.check_block(&block.value),
+            Some((_, ElseBranch::If(if_stmt))) => self.check_if(if_stmt),
+            None => {}
+        }
+    }
+
+    fn lower(&self, ty: &Type) -> Ty {
+        self.aliases.lower(self.resolutions, self.file_id, ty).subst(&self.this)
+    }
+
+    fn declare(&mut self, name: &Ident, ty: Ty, origin: Option<Span>) {
+        if let Some(def) = self.resolutions.decl_of(self.file_id, name) {
+            self.locals.insert(def, Local { name: *name, ty, origin });
+        }
+    }
+
+    /// The local variable `path` refers to, if it is one.
+    fn local_of(&self, path: &Path) -> Option<DefId> {
+        match self.resolutions.res_of(self.file_id, path)? {
+            Res::Def(def) if self.locals.contains_key(def) => Some(*def),
+            _ => None,
+        }
+    }
+
+    /// Infers the type of `expr` and makes it `expected`, which comes from `origin`.
+    fn check_expr(&mut self, expr: &WithSpan<Expr>, expected: &Ty, origin: Option<Span>) {
+        let found = self.infer_expr(expr);
+        self.unify(expected, &found, origin, expr.span);
+    }
+
+    fn unify(&mut self, expected: &Ty, found: &Ty, origin: Option<Span>, span: Span) {
+        if let Err(error) = self.table.unify(expected, found) {
+            self.errors.push(StaticAnalysisError::TypeMismatch {
+                expected: error.expected,
+                found: error.found,
+                expected_span: origin,
+                found_span: span,
+            });
+        }
+    }
+
+    fn infer_expr(&mut self, expr: &WithSpan<Expr>) -> Ty {
+        let ty = self.infer_expr_kind(&expr.value);
+        if let Expr::Literal(ExprLit(Literal::Number(number))) = &expr.value {
+            self.literals.push(IntLiteral {
+                magnitude: number.magnitude(),
+                negative: number.is_negative(),
+                ty: ty.clone(),
+                span: expr.span,
+            });
+        }
+        self.exprs.push((expr.span, ty.clone()));
+        ty
+    }
+
+    fn infer_expr_kind(&mut self, expr: &Expr) -> Ty {
+        match expr {
+            Expr::Literal(ExprLit(literal)) => match literal {
+                Literal::String(_) => Ty::Str,
+                Literal::Bool(_) => Ty::Bool,
+                Literal::Char(_) => Ty::Char,
+                Literal::Number(number) => match int_ty(number.suffix()) {
+                    Some(int) => Ty::Int(int),
+                    None => self.table.new_int_var(),
+                },
+            },
+            Expr::Borrow(ExprBorrow(_, mutable, borrowed)) => Ty::Ref {
+                mutable: mutable.is_some(),
+                ty: Box::new(self.infer_place(borrowed).0),
+            },
+            Expr::VariableAccess(ExprVarAccess(path)) => {
+                if let Some(def) = self.local_of(path) {
+                    return self.locals[&def].ty.clone();
+                }
+                let Some((enum_def, index)) = self.variant_of(path) else { return Ty::Error };
+                // a variant with fields is built by calling it
+                let variant = &enum_def.variants[index];
+                if !variant.fields.is_empty() {
+                    self.errors.push(StaticAnalysisError::ArgumentCount {
+                        name: variant.name.to_string(),
+                        required: variant.fields.len(),
+                        total: variant.fields.len(),
+                        found: 0,
+                        missing: variant.fields.iter().map(|field| field.name.to_string()).collect(),
+                        span: path.span(),
+                    });
+                }
+                enum_def.ty()
+            }
+            // nothing declares constants yet
+            Expr::ConstAccess(ExprConstAccess(path)) => {
+                self.errors.push(StaticAnalysisError::UnknownConst { name: path.to_string(), span: path.span() });
+                Ty::Error
+            }
+            Expr::FuncCall(call) => self.infer_call(call),
+            Expr::Field(field) => {
+                let (ty, behind_ref) = self.infer_field(field);
+                let resolved = self.table.resolve(&ty);
+                if behind_ref && !self.structs.is_copy(&resolved) {
+                    let ExprField(base, _, name) = field;
+                    self.errors.push(StaticAnalysisError::MoveOutOfRef {
+                        ty: resolved,
+                        span: Span::new(base.span.start, name.span().end),
+                    });
+                }
+                ty
+            }
+            Expr::Array(array) => self.infer_array(array),
+            Expr::Index(ExprIndex(base, index)) => self.infer_index(base, index),
+            // the variables are formatted like `println` prints them
+            Expr::Interpolated(interpolated) => {
+                for part in interpolated.parts() {
+                    self.infer_expr(part);
+                }
+                Ty::Str
+            }
+            Expr::Binary(ExprBinary(lhs, op, rhs)) => self.infer_binary(lhs, *op, rhs),
+            Expr::Unary(ExprUnary(op, operand)) => {
+                let found = self.infer_expr(operand);
+                // a negative literal, e.g. `-128byte`, which would not fit its type before being negated
+                if let (UnOp::Neg(_), Expr::Literal(ExprLit(Literal::Number(_)))) = (op, &operand.value) {
+                    let literal = self.literals.last_mut().expect("just inferred");
+                    literal.negative = !literal.negative;
+                }
+                let (ty, expected) = match op {
+                    UnOp::Neg(_) => (self.table.new_int_var(), "a signed integer"),
+                    UnOp::Not(_) => (Ty::Bool, "a `bool`"),
+                };
+                let resolved = self.table.resolve(&found);
+                let unsigned = matches!(resolved.peel(), Ty::Int(int) if !int.is_signed());
+                // the result has the type it would have had, so that no more errors follow
+                if self.table.unify(&ty, &found).is_err() || unsigned {
+                    self.invalid_operand(&op.to_string(), &found, expected, operand.span);
+                }
+                ty
+            }
+        }
+    }
+
+    /// Like [`Self::infer_expr`], for an expression that is borrowed or has a field taken, which is not
+    /// moved out of even behind a reference. Also tells whether it is behind one.
+    fn infer_place(&mut self, expr: &WithSpan<Expr>) -> (Ty, bool) {
+        let Expr::Field(field) = &expr.value else { return (self.infer_expr(expr), false) };
+        let (ty, behind_ref) = self.infer_field(field);
+        self.exprs.push((expr.span, ty.clone()));
+        (ty, behind_ref)
+    }
+
+    /// A field is accessed through any number of references to its struct, e.g. on a `&S`.
+    fn infer_field(&mut self, ExprField(base, _, field): &ExprField) -> (Ty, bool) {
+        let (base, behind_ref) = self.infer_place(base);
+        let base = self.table.resolve(&base);
+        let behind_ref = behind_ref || matches!(base.peel(), Ty::Ref { .. });
+        if let Some(field) = self.structs.field(base.referent(), field.value()) {
+            return (field.ty.clone(), behind_ref);
+        }
+        if !matches!(base.referent(), Ty::Error | Ty::Var(_)) {
+            self.errors.push(StaticAnalysisError::NoField { field: field.to_string(), ty: base, span: field.span() });
+        }
+        (Ty::Error, behind_ref)
+    }
+
+    /// Every element has the type of the first one.
+    fn infer_array(&mut self, array: &ExprArray) -> Ty {
+        let element = self.table.new_var();
+        let mut first = None;
+        let mut len = 0;
+        for value in array.elements() {
+            self.check_expr(value, &element, first);
+            first = first.or(Some(value.span));
+            len += 1;
+        }
+        Ty::Array {
+            element: Box::new(element),
+            len,
+        }
+    }
+
+    /// Indexing takes an array and an integer, and copies the element out of the array.
+    fn infer_index(&mut self, base: &WithSpan<Expr>, index: &WithSpan<Expr>) -> Ty {
+        let found = self.infer_expr(base);
+        let int = self.table.new_int_var();
+        self.check_expr(index, &int, None);
+        let resolved = self.table.resolve(&found);
+        match resolved.peel() {
+            Ty::Array { element, .. } => {
+                if !self.structs.is_copy(element) {
+                    self.errors.push(StaticAnalysisError::MoveOutOfArray {
+                        ty: (**element).clone(),
+                        span: base.span,
+                    });
+                }
+                (**element).clone()
+            }
+            Ty::Error | Ty::Var(_) => Ty::Error,
+            _ => {
+                self.invalid_operand("[]", &found, "an array", base.span);
+                Ty::Error
+            }
+        }
+    }
+
+    /// Arithmetic takes two integers of the same type and produces another one. A comparison
+    /// takes two values of the same type, integers or characters, or `bool`s to only compare them
+    /// for equality.
+    fn infer_binary(&mut self, lhs: &WithSpan<Expr>, op: BinOp, rhs: &WithSpan<Expr>) -> Ty {
+        let found = self.infer_expr(lhs);
+        if !op.is_comparison() {
+            let int = self.table.new_int_var();
+            if self.table.unify(&int, &found).is_err() {
+                let expected = "integers of the same type";
+                self.invalid_operand(&op.to_string(), &found, expected, lhs.span);
+                self.infer_expr(rhs);
+                return int;
+            }
+            self.check_expr(rhs, &int, Some(lhs.span));
+            return int;
+        }
+
+        self.check_expr(rhs, &found, Some(lhs.span));
+        let resolved = self.table.resolve(&found);
+        let ordered = matches!(
+            resolved.peel(),
+            Ty::Int(_) | Ty::IntVar(_) | Ty::Char | Ty::Var(_) | Ty::Error
+        );
+        let (valid, expected) = match op {
+            BinOp::Eq(_) | BinOp::Ne(_) => (
+                ordered || *resolved.peel() == Ty::Bool,
+                "integers, characters or `bool`s of the same type",
+            ),
+            _ => (ordered, "integers or characters of the same type"),
+        };
+        if !valid {
+            self.invalid_operand(&op.to_string(), &found, expected, lhs.span);
+        }
+        Ty::Bool
+    }
+
+    fn invalid_operand(&mut self, op: &str, ty: &Ty, expected: &'static str, span: Span) {
+        self.errors.push(StaticAnalysisError::InvalidOperand {
+            op: op.to_owned(),
+            ty: self.table.resolve(ty),
+            expected,
+            span,
+        });
+    }
+
+    fn infer_call(&mut self, FuncCallStmt(callee, args): &FuncCallStmt) -> Ty {
+        let def = match self.resolutions.res_of(self.file_id, callee) {
+            Some(Res::Def(def)) => Some(*def),
+            _ => None,
+        };
+        // nothing is known about functions outside of the gem
+        let Some(sig) = def.and_then(|def| self.signatures.get(&def)) else {
+            for arg in args.content().iter() {
+                self.infer_expr(&arg.1);
+            }
+            return Ty::Error;
+        };
+
+        // `this` stands for the type the trait is implemented for, whichever the arguments make it
+        let instantiated;
+        let sig = match def.and_then(|def| self.traits.method(def)) {
+            Some((trait_def, _)) => {
+                let this = self.table.new_var();
+                self.methods.push(MethodCall {
+                    trait_def: trait_def.def,
+                    path: callee.to_string(),
+                    this: this.clone(),
+                    span: callee.span(),
+                });
+                instantiated = sig.subst(&HashMap::from([(THIS.to_owned(), this)]));
+                &instantiated
+            }
+            None => sig,
+        };
+
+        let mut passed = vec![false; sig.params.len()];
+        let mut positional = 0;
+        let mut extra = 0;
+        let mut unknown = 0;
+        for FuncCallArg(named, value) in args.content().iter() {
+            let index = match named {
+                Some((name, _)) => {
+                    let Some(index) = sig.params.iter().position(|param| param.name.value() == name.value()) else {
+                        self.errors.push(StaticAnalysisError::UnknownArgument {
+                            name: name.to_string(),
+                            function: sig.name.to_string(),
+                            span: name.span(),
+                        });
+                        self.infer_expr(value);
+                        unknown += 1;
+                        continue;
+                    };
+                    index
+                }
+                None => {
+                    positional += 1;
+                    positional - 1
+                }
+            };
+            if passed.get(index).copied().unwrap_or(true) {
+                extra += 1;
+                self.infer_expr(value);
+                continue;
+            }
+            passed[index] = true;
+
+            let param = &sig.params[index];
+            // the annotation can only be labelled if it is in the same file
+            let origin = (sig.file_id == self.file_id).then_some(param.span);
+            self.check_expr(value, &param.ty, origin);
+        }
+
+        let missing: Vec<_> = sig
+            .params
+            .iter()
+            .zip(&passed)
+            .filter(|(param, passed)| !**passed && !param.has_default)
+            .map(|(param, _)| param.name.to_string())
+            .collect();
+        if extra > 0 || !missing.is_empty() {
+            self.errors.push(StaticAnalysisError::ArgumentCount {
+                name: sig.name.to_string(),
+                required: sig.required_params(),
+                total: sig.params.len(),
+                found: args.content().iter().count() - unknown,
+                missing,
+                span: callee.span(),
+            });
+        }
+
+        sig.ret.clone()
+    }
+
+    /// Substitutes the final types into the results, returning the errors found,
+    /// including the locals nothing constrained.
+    fn finish(self, types: &mut TypeckResults) -> Vec<StaticAnalysisError> {
+        let mut errors = self.errors;
+
+        let mut locals: Vec<_> = self.locals.into_iter().collect();
+        locals.sort_by_key(|(def, _)| *def);
+        for (def, local) in locals {
+            let ty = self.table.resolve_final(&local.ty).unwrap_or_else(|| {
+                errors.push(StaticAnalysisError::CannotInfer {
+                    name: local.name.to_string(),
+                    span: local.name.span(),
+                });
+                Ty::Error
+            });
+            types.locals.insert(def, ty);
+        }
+        for (span, ty) in self.exprs {
+            let ty = self.table.resolve_final(&ty).unwrap_or(Ty::Error);
+            types.exprs.insert((self.file_id, span), ty);
+        }
+        for method in self.methods {
+            let Some(this) = self.table.resolve_final(&method.this) else {
+                errors.push(StaticAnalysisError::CannotInfer { name: THIS.to_owned(), span: method.span });
+                continue;
+            };
+            // a method of a trait or a generic impl calls another one on its own parameter, which any type may stand
+            // for, or something failed to check
+            let unimplemented = !matches!(this.referent(), Ty::Param(_) | Ty::Error)
+                && self.traits.select(&TraitRef::Local(method.trait_def), &this) == Err(SelectionError::Unimplemented);
+            if unimplemented {
+                errors.push(StaticAnalysisError::Unimplemented {
+                    trait_name: self.traits.traits[&method.trait_def].name.to_string(),
+                    method: method.path,
+                    ty: this.clone(),
+                    span: method.span,
+                });
+            }
+            types.methods.insert((self.file_id, method.span), this);
+        }
+        for literal in self.literals {
+            let Some(Ty::Int(int)) = self.table.resolve_final(&literal.ty).as_ref().map(Ty::peel).cloned() else {
+                continue;
+            };
+            if !literal.fits(int) {
+                errors.push(StaticAnalysisError::LiteralOutOfRange { ty: int, span: literal.span });
+            }
+        }
+        errors
+    }
+}
+
+const fn int_ty(suffix: &NumberSuffix) -> Option<IntTy> {
+    Some(match suffix {
+        NumberSuffix::None => return None,
+        NumberSuffix::Usize => IntTy::Usize,
+        NumberSuffix::Isize => IntTy::Isize,
+        NumberSuffix::Ubyte => IntTy::Ubyte,
+        NumberSuffix::Byte => IntTy::Byte,
+        NumberSuffix::Ushort => IntTy::Ushort,
+        NumberSuffix::Short => IntTy::Short,
+        NumberSuffix::Uint => IntTy::Uint,
+        NumberSuffix::Int => IntTy::Int,
+        NumberSuffix::Ulong => IntTy::Ulong,
+        NumberSuffix::Long => IntTy::Long,
+        NumberSuffix::Uexplod => IntTy::Uexplod,
+        NumberSuffix::Explod => IntTy::Explod,
+    })
+}
@@ -0,0 +1,128 @@
+use crate::alias::AliasEnv;
+use crate::structs::StructEnv;
+use crate::ty::Ty;
+use std::collections::HashMap;
+use topaz_ast::ident::Ident;
+use topaz_ast::item::func::{Func, FuncArg, Method};
+use topaz_ast::item::trait_def::{RequiredMethod, TraitItem};
+use topaz_ast::item::Item;
+use topaz_ast::location::{Span, WithSpan};
+use topaz_ast::pattern::Pattern;
+use topaz_ast::types::Type;
+use topaz_resolve::def::DefId;
+use topaz_resolve::{Resolutions, SourceModule};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub name: Ident,
+    pub ty: Ty,
+    /// The type annotation
+    pub span: Span,
+    /// Whether the parameter can be left out of a call
+    pub has_default: bool,
+    /// Whether the function takes ownership of the argument, instead of borrowing it
+    pub moved: bool,
+}
+
+/// The parameter and return types of a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FnSig {
+    pub name: Ident,
+    pub file_id: usize,
+    pub params: Vec<Param>,
+    /// `()` for functions without a `-> Type`
+    pub ret: Ty,
+    /// The return type annotation, if there is one
+    pub ret_span: Option<Span>,
+}
+
+impl FnSig {
+    /// The signature of `func`, with `lower` lowering the types in it.
+    pub fn from_ast(file_id: usize, Func(_, _, name, args, ret, _): &Func, lower: impl Fn(&Type) -> Ty) -> Self {
+        Self::new(file_id, name, args, ret.as_ref().map(|(_, ty)| ty), lower)
+    }
+
+    /// The signature of a method of a trait, in which `this` is the type implementing it.
+    pub fn from_trait_item(file_id: usize, item: &TraitItem, lower: impl Fn(&Type) -> Ty) -> Self {
+        match item {
+            TraitItem::Required(RequiredMethod(_, _, _, name, args, ret)) => {
+                Self::new(file_id, name, args, ret.as_ref().map(|(_, ty)| ty), lower)
+            }
+            TraitItem::Provided(Method(_, func)) => Self::from_ast(file_id, &func.value, lower),
+        }
+    }
+
+    fn new(file_id: usize, name: &Ident, args: &[FuncArg], ret: Option<&WithSpan<Type>>, lower: impl Fn(&Type) -> Ty) -> Self {
+        let params = args
+            .iter()
+            .map(|arg @ FuncArg(_, pattern, ty, default)| {
+                let Pattern::Ident(name) = pattern;
+                Param {
+                    name: *name,
+                    ty: lower(&ty.value),
+                    span: ty.span,
+                    has_default: default.is_some(),
+                    moved: arg.is_move(),
+                }
+            })
+            .collect();
+
+        Self {
+            name: *name,
+            file_id,
+            params,
+            ret: ret.map_or(Ty::Void, |ty| lower(&ty.value)),
+            ret_span: ret.map(|ty| ty.span),
+        }
+    }
+
+    /// The signature with the parameters in `params` replaced by their types, see [`Ty::subst`].
+    #[must_use]
+    pub fn subst(&self, params: &HashMap<String, Ty>) -> Self {
+        Self {
+            params: self.params.iter().map(|param| Param { ty: param.ty.subst(params), ..param.clone() }).collect(),
+            ret: self.ret.subst(params),
+            ..self.clone()
+        }
+    }
+
+    /// The number of arguments a call has to pass.
+    #[must_use]
+    pub fn required_params(&self) -> usize {
+        self.params.iter().filter(|param| !param.has_default).count()
+    }
+}
+
+/// Collects the signature of every function of a gem and method of a trait, and of every struct and variant
+/// called as one.
+#[must_use]
+pub fn collect_signatures(
+    resolutions: &Resolutions,
+    aliases: &AliasEnv,
+    structs: &StructEnv,
+    modules: &[SourceModule],
+) -> HashMap<DefId, FnSig> {
+    let mut signatures: HashMap<_, _> = modules
+        .iter()
+        .flat_map(|module| {
+            let lower = |ty: &Type| aliases.lower(resolutions, module.file_id, ty);
+            module.file.items.iter().flat_map(move |item| match &item.value {
+                Item::Func(func) => {
+                    let def = resolutions.decl_of(module.file_id, &func.2);
+                    def.map(|def| (def, FnSig::from_ast(module.file_id, func, lower))).into_iter().collect()
+                }
+                Item::Trait(trait_def) => {
+                    let methods = resolutions.decl_of(module.file_id, &trait_def.2).and_then(|def| resolutions.methods.get(&def));
+                    let items = trait_def.items().zip(methods.into_iter().flatten());
+                    items.map(|(item, def)| (*def, FnSig::from_trait_item(module.file_id, item, lower))).collect()
+                }
+                _ => Vec::new(),
+            })
+        })
+        .collect();
+    signatures.extend(structs.structs.values().map(|struct_def| (struct_def.def, struct_def.constructor())));
+    signatures.extend(structs.enums.values().flat_map(|enum_def| {
+        (0..enum_def.variants.len()).map(|index| (enum_def.variants[index].def, enum_def.constructor(index)))
+    }));
+    signatures
+}
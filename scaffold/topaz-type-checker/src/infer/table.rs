@@ -0,0 +1,147 @@
+use crate::ty::{IntTy, Ty, TyVar};
+
+/// Substitution of the inference variables of a single function.
+#[derive(Debug, Default)]
+pub struct InferTable {
+    /// What each variable is bound to, if anything yet
+    bindings: Vec<Option<Ty>>,
+}
+
+/// The types of a failed unification, with every bound variable substituted.
+#[derive(Debug)]
+pub struct TypeError {
+    pub expected: Ty,
+    pub found: Ty,
+}
+
+impl InferTable {
+    pub fn new_var(&mut self) -> Ty {
+        Ty::Var(self.fresh())
+    }
+
+    pub fn new_int_var(&mut self) -> Ty {
+        Ty::IntVar(self.fresh())
+    }
+
+    fn fresh(&mut self) -> TyVar {
+        self.bindings.push(None);
+        TyVar(u32::try_from(self.bindings.len() - 1).expect("too many type variables"))
+    }
+
+    fn binding(&self, var: TyVar) -> Option<&Ty> {
+        self.bindings[var.0 as usize].as_ref()
+    }
+
+    /// Follows bound variables until reaching a type that is not one.
+    #[must_use]
+    pub fn shallow_resolve(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Var(var) | Ty::IntVar(var) => match self.binding(*var) {
+                Some(bound) => self.shallow_resolve(bound),
+                None => ty.clone(),
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    /// Substitutes every bound variable in `ty`, leaving unbound ones in place.
+    #[must_use]
+    pub fn resolve(&self, ty: &Ty) -> Ty {
+        match self.shallow_resolve(ty) {
+            Ty::Ref { mutable, ty } => Ty::Ref {
+                mutable,
+                ty: Box::new(self.resolve(&ty)),
+            },
+            Ty::Maybe(ty) => Ty::Maybe(Box::new(self.resolve(&ty))),
+            Ty::Array { element, len } => Ty::Array {
+                element: Box::new(self.resolve(&element)),
+                len,
+            },
+            Ty::Alias { name, ty } => Ty::Alias {
+                name,
+                ty: Box::new(self.resolve(&ty)),
+            },
+            ty => ty,
+        }
+    }
+
+    /// Like [`InferTable::resolve`], but defaults unbound integer variables to `int`
+    /// and returns `None` if any other variable is still unbound.
+    #[must_use]
+    pub fn resolve_final(&self, ty: &Ty) -> Option<Ty> {
+        match self.shallow_resolve(ty) {
+            Ty::Var(_) => None,
+            Ty::IntVar(_) => Some(Ty::Int(IntTy::DEFAULT)),
+            Ty::Ref { mutable, ty } => Some(Ty::Ref {
+                mutable,
+                ty: Box::new(self.resolve_final(&ty)?),
+            }),
+            Ty::Maybe(ty) => Some(Ty::Maybe(Box::new(self.resolve_final(&ty)?))),
+            Ty::Array { element, len } => Some(Ty::Array {
+                element: Box::new(self.resolve_final(&element)?),
+                len,
+            }),
+            Ty::Alias { name, ty } => Some(Ty::Alias {
+                name,
+                ty: Box::new(self.resolve_final(&ty)?),
+            }),
+            ty => Some(ty),
+        }
+    }
+
+    /// Makes `found` and `expected` the same type.
+    pub fn unify(&mut self, expected: &Ty, found: &Ty) -> Result<(), TypeError> {
+        if self.unify_inner(expected, found) {
+            Ok(())
+        } else {
+            Err(TypeError {
+                expected: self.resolve(expected),
+                found: self.resolve(found),
+            })
+        }
+    }
+
+    fn unify_inner(&mut self, a: &Ty, b: &Ty) -> bool {
+        // aliases are only kept for diagnostics
+        let (a, b) = (self.shallow_resolve(a.peel()), self.shallow_resolve(b.peel()));
+        match (a.peel(), b.peel()) {
+            (Ty::Error, _) | (_, Ty::Error) => true,
+            (Ty::Var(x), Ty::Var(y)) | (Ty::IntVar(x), Ty::IntVar(y)) if x == y => true,
+            (Ty::Var(var), other) | (other, Ty::Var(var)) => {
+                if self.occurs(*var, other) {
+                    return false;
+                }
+                self.bindings[var.0 as usize] = Some(other.clone());
+                true
+            }
+            (Ty::IntVar(var), other) | (other, Ty::IntVar(var)) => {
+                if !other.is_integer() {
+                    return false;
+                }
+                self.bindings[var.0 as usize] = Some(other.clone());
+                true
+            }
+            (
+                Ty::Ref { mutable: a_mut, ty: a_ty },
+                Ty::Ref { mutable: b_mut, ty: b_ty },
+            ) => a_mut == b_mut && self.unify_inner(a_ty, b_ty),
+            (Ty::Maybe(a), Ty::Maybe(b)) => self.unify_inner(a, b),
+            (
+                Ty::Array { element: a, len: a_len },
+                Ty::Array { element: b, len: b_len },
+            ) => a_len == b_len && self.unify_inner(a, b),
+            (a, b) => a == b,
+        }
+    }
+
+    /// Whether `var` appears in `ty`, which would make binding it an infinite type.
+    fn occurs(&self, var: TyVar, ty: &Ty) -> bool {
+        match self.shallow_resolve(ty) {
+            Ty::Var(other) => other == var,
+            Ty::Ref { ty, .. } | Ty::Maybe(ty) | Ty::Alias { ty, .. } | Ty::Array { element: ty, .. } => {
+                self.occurs(var, &ty)
+            }
+            _ => false,
+        }
+    }
+}
@@ -1,12 +1,20 @@
-use codespan_reporting::{diagnostic::Diagnostic, files::SimpleFiles};
+use codespan_reporting::diagnostic::Diagnostic;
 use error::StaticAnalysisError;
 
-mod error;
-mod warning;
-pub mod borrow;
+pub mod alias;
+pub mod error;
+pub mod warning;
+pub mod infer;
+pub mod lint;
+pub mod pass;
+pub mod structs;
+pub mod traits;
+pub mod ty;
 
-use topaz_ast::ProgramUnit;
+use lint::levels::Level;
+use pass::{AnalysisPass, AnalysisSession};
 use topaz_report::Reporter;
+use topaz_resolve::{Resolutions, SourceModule};
 use warning::StaticAnalysisWarning;
 
 pub enum StaticAnalysisOutputUnit {
@@ -25,59 +33,76 @@ impl<'source> Reporter<'source> for StaticAnalysisOutputUnit {
 
 pub type StaticAnalysisOutput = Vec<(usize, StaticAnalysisOutputUnit)>;
 
+/// Runs analysis passes, in the order they were registered, on a resolved gem.
 pub struct StaticAnalyzer<'a> {
-    current_file_id: usize,
-    initial_ast: ProgramUnit,
-
-    #[allow(dead_code)]
-    files: &'a SimpleFiles<&'a str, &'a str>,
-
-    pub output: StaticAnalysisOutput,
+    session: AnalysisSession<'a>,
+    passes: Vec<Box<dyn AnalysisPass>>,
+    /// Lint levels set on the command line, later ones replace earlier ones
+    lint_flags: Vec<(Level, String)>,
 }
 
 impl<'a> StaticAnalyzer<'a> {
-    pub fn new(
-        initial_file_id: usize,
-        initial_ast: ProgramUnit,
-        files: &'a SimpleFiles<&'a str, &'a str>,
-    ) -> Self {
+    /// An analyzer without any passes.
+    #[must_use]
+    pub fn new(resolutions: &'a Resolutions, modules: &'a [SourceModule<'a>]) -> Self {
         Self {
-            current_file_id: initial_file_id,
-            initial_ast,
-            files,
-            output: vec![],
+            session: AnalysisSession::new(resolutions, modules),
+            passes: Vec::new(),
+            lint_flags: Vec::new(),
         }
     }
 
-    pub fn analyze(&mut self) {
-        for tlstmt in self.initial_ast.top_level_statements.iter() {
-            match &tlstmt.1 {
-                topaz_ast::TopLevelStatement::Import(filename) => {
-                    self.output.push((
-                        self.current_file_id,
-                        StaticAnalysisOutputUnit::Warning(
-                            StaticAnalysisWarning::ImportAfterFirstTopLevelStatement(
-                                filename.path.span,
-                            ),
-                        ),
-                    ));
-                }
-                topaz_ast::TopLevelStatement::TraitDecl(t) => {
-                    if let Some(p) = t.public {
-                        self.output.push((
-                            self.current_file_id,
-                            StaticAnalysisOutputUnit::Warning(
-                                StaticAnalysisWarning::UnnecessatopazVisibilityQualifier(p),
-                            ),
-                        ));
-                    }
-                }
-                _ => {}
-            }
+    /// An analyzer running every built-in pass.
+    #[must_use]
+    pub fn with_default_passes(resolutions: &'a Resolutions, modules: &'a [SourceModule<'a>]) -> Self {
+        let mut analyzer = Self::new(resolutions, modules);
+        analyzer
+            .register(pass::ImportOrder::default())
+            .register(pass::Lints)
+            .register(alias::Aliases)
+            .register(traits::coherence::Coherence)
+            .register(infer::TypeInference);
+        analyzer
+    }
+
+    pub fn register(&mut self, pass: impl AnalysisPass + 'static) -> &mut Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Sets the level of a lint, a lint group or `warnings` for the whole gem,
+    /// like `-D warnings` does on the command line.
+    pub fn set_lint_level(&mut self, level: Level, name: impl Into<String>) -> &mut Self {
+        self.lint_flags.push((level, name.into()));
+        self
+    }
+
+    /// The names of the registered passes, in the order they run.
+    pub fn pass_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes.iter().map(|pass| pass.name())
+    }
+
+    /// Keeps only the passes `keep` returns `true` for.
+    pub fn retain_passes(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.passes.retain(|pass| keep(pass.name()));
+    }
+
+    /// Runs every pass, returning the session with what they found out about the gem and reported,
+    /// for the later stages of the compiler to build on.
+    #[must_use]
+    pub fn run(mut self) -> AnalysisSession<'a> {
+        lint::levels::collect(&mut self.session, &self.lint_flags);
+        for pass in &mut self.passes {
+            pass.run(&mut self.session);
         }
+        self.session
     }
 
-    pub fn add(&mut self, output: StaticAnalysisOutputUnit) {
-        self.output.push((self.current_file_id, output));
+    /// Runs every pass, returning everything they reported, grouped by file.
+    #[must_use]
+    pub fn analyze(self) -> StaticAnalysisOutput {
+        let mut output = self.run().output;
+        output.sort_by_key(|(file_id, _)| *file_id);
+        output
     }
 }
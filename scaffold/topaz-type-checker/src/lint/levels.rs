@@ -0,0 +1,332 @@
+//! `lint/levels.rs` - how severely every lint is reported, where in the gem.
+//!
+//! Levels are set, from the outermost to the innermost scope:
+//! - on the command line, e.g. `-D warnings`,
+//! - for the whole gem, by the attributes in front of the root module,
+//! - for a module and the modules in it, by the attributes in front of its file,
+//! - for an item or a statement, by the attributes in front of it.
+//!
+//! An inner level replaces an outer one, unless the outer one is `forbid`.
+use super::registry::{self, WARNINGS};
+use crate::error::StaticAnalysisError;
+use crate::pass::AnalysisSession;
+use crate::warning::StaticAnalysisWarning;
+use codespan_reporting::diagnostic::Label;
+use std::collections::HashMap;
+use topaz_ast::attribute::Attribute;
+use topaz_ast::block::Block;
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_ast::item::func::Method;
+use topaz_ast::location::Span;
+use topaz_ast::visit::Visit;
+use topaz_resolve::SourceModule;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Level {
+    /// The lint is not reported
+    Allow,
+    /// The lint is reported as a warning
+    Warn,
+    /// The lint is reported as an error
+    Deny,
+    /// Like `Deny`, and no inner scope can change the level anymore
+    Forbid,
+}
+
+impl Level {
+    /// The level set by an attribute named `name`, e.g. `deny`.
+    #[must_use]
+    pub fn from_attribute(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Self::Allow),
+            "warn" => Some(Self::Warn),
+            "deny" => Some(Self::Deny),
+            "forbid" => Some(Self::Forbid),
+            _ => None,
+        }
+    }
+
+    /// The name of the attribute setting the level.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Warn => "warn",
+            Self::Deny => "deny",
+            Self::Forbid => "forbid",
+        }
+    }
+
+    /// The command line flag setting the level, e.g. `-D`.
+    #[must_use]
+    pub const fn flag(self) -> &'static str {
+        match self {
+            Self::Allow => "-A",
+            Self::Warn => "-W",
+            Self::Deny => "-D",
+            Self::Forbid => "-F",
+        }
+    }
+}
+
+/// Where the level of a lint was set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LevelSource {
+    /// The default level of the lint, see [`registry::LINTS`]
+    Default,
+    /// `@deny(unused)`, where `name` is the lint, the group or `warnings`, as written in the attribute
+    Attribute { name: String, file_id: usize, span: Span },
+    /// `-D warnings`, where `name` is the lint, the group or `warnings`, as given on the command line
+    CommandLine { name: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintLevel {
+    pub level: Level,
+    pub source: LevelSource,
+}
+
+impl LintLevel {
+    /// The level of `lint` if nothing changes it.
+    #[must_use]
+    pub fn default_of(lint: &str) -> Self {
+        Self {
+            level: registry::find(lint).map_or(Level::Warn, |lint| lint.default),
+            source: LevelSource::Default,
+        }
+    }
+
+    /// The labels and notes telling where the level of `lint` was set.
+    #[must_use]
+    pub fn explain(&self, lint: &str) -> (Vec<Label<usize>>, Vec<String>) {
+        let level = self.level.name();
+        match &self.source {
+            LevelSource::Default => (Vec::new(), vec![format!("`@{level}({lint})` on by default")]),
+            LevelSource::Attribute { name, file_id, span } => {
+                let label = Label::secondary(*file_id, *span).with_message("the lint level is defined here");
+                let notes = if name == lint {
+                    Vec::new()
+                } else {
+                    vec![format!("`@{level}({lint})` implied by `@{level}({name})`")]
+                };
+                (vec![label], notes)
+            }
+            LevelSource::CommandLine { name } => {
+                let flag = self.level.flag();
+                let note = if name == lint {
+                    format!("requested on the command line with `{flag} {lint}`")
+                } else {
+                    format!("`{flag} {lint}` implied by `{flag} {name}`")
+                };
+                (Vec::new(), vec![note])
+            }
+        }
+    }
+}
+
+/// The level of every lint, and `warnings`, that is not at its default level.
+type LevelMap = HashMap<&'static str, LintLevel>;
+
+/// The levels of the lints in every scope of a gem, collected by [`collect`].
+#[derive(Debug, Default)]
+pub struct LintLevels {
+    /// Set on the command line and in front of the root module
+    gem: LevelMap,
+    /// Set in front of a file or the modules it is in, by file
+    modules: HashMap<usize, LevelMap>,
+    /// Set in front of items and statements, by file. Each scope has the levels of the scopes around it
+    scopes: HashMap<usize, Vec<(Span, LevelMap)>>,
+}
+
+impl LintLevels {
+    /// The level `warning` is reported at.
+    #[must_use]
+    pub fn level(&self, file_id: usize, warning: &StaticAnalysisWarning) -> LintLevel {
+        let span = warning.span();
+        let innermost = self.scopes.get(&file_id).and_then(|scopes| {
+            scopes
+                .iter()
+                .filter(|(scope, _)| scope.start <= span.start && span.end <= scope.end)
+                .min_by_key(|(scope, _)| scope.end - scope.start)
+        });
+        let map = innermost
+            .map(|(_, map)| map)
+            .or_else(|| self.modules.get(&file_id))
+            .unwrap_or(&self.gem);
+
+        let lint = warning.lint_name();
+        let level = map.get(lint).cloned().unwrap_or_else(|| LintLevel::default_of(lint));
+        match map.get(WARNINGS) {
+            Some(warnings) if level.level == Level::Warn => warnings.clone(),
+            _ => level,
+        }
+    }
+}
+
+/// Collects the levels set on the `command_line` and by the attributes of the gem into
+/// `session.lint_levels`, reporting lint names that are not known and levels overruling `forbid`.
+///
+/// Unknown lints on the command line are ignored, they are reported by whoever parses it.
+pub fn collect(session: &mut AnalysisSession, command_line: &[(Level, String)]) {
+    let all_modules = session.modules;
+    let mut collector = Collector {
+        file_id: 0,
+        stack: Vec::new(),
+        scopes: Vec::new(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let mut gem = LevelMap::new();
+    for (level, name) in command_line {
+        let source = LevelSource::CommandLine { name: name.clone() };
+        // a later flag cannot overrule an earlier `-F`
+        let _ = set(&mut gem, *level, name, source);
+    }
+    let root = all_modules.iter().find(|module| module.path.is_empty());
+    if let Some(root) = root {
+        collector.file_id = root.file_id;
+        collector.apply(&mut gem, &root.file.attributes);
+    }
+
+    // outer modules first, so every module starts from the levels of the one it is in
+    let mut modules: Vec<&SourceModule> = all_modules.iter().collect();
+    modules.sort_by_key(|module| module.path.len());
+    let mut by_path: HashMap<&[Ident], LevelMap> = HashMap::new();
+    let mut levels = LintLevels::default();
+    for module in modules {
+        let parent = (0..module.path.len())
+            .rev()
+            .find_map(|len| by_path.get(&module.path[..len]))
+            .unwrap_or(&gem);
+        let mut map = parent.clone();
+        collector.file_id = module.file_id;
+        if !module.path.is_empty() {
+            collector.apply(&mut map, &module.file.attributes);
+        }
+
+        collector.stack.push(map.clone());
+        collector.visit_file(module.file);
+        collector.stack.clear();
+        levels.scopes.insert(module.file_id, std::mem::take(&mut collector.scopes));
+        levels.modules.insert(module.file_id, map.clone());
+        by_path.insert(&module.path, map);
+    }
+    levels.gem = gem;
+
+    session.lint_levels = levels;
+    for (file_id, error) in collector.errors {
+        session.error(file_id, error);
+    }
+    for (file_id, warning) in collector.warnings {
+        session.warn(file_id, warning);
+    }
+}
+
+/// Sets `level` for the lints `name` stands for, unless one of them is forbidden.
+fn set(map: &mut LevelMap, level: Level, name: &str, source: LevelSource) -> Result<(), LintLevel> {
+    let lints = if name == WARNINGS {
+        vec![WARNINGS]
+    } else {
+        registry::expand(name).unwrap_or_default()
+    };
+    let forbidden = lints
+        .iter()
+        .filter_map(|lint| map.get(lint))
+        .find(|current| current.level == Level::Forbid && level != Level::Forbid);
+    if let Some(forbidden) = forbidden {
+        return Err(forbidden.clone());
+    }
+    for lint in lints {
+        map.insert(lint, LintLevel { level, source: source.clone() });
+    }
+    Ok(())
+}
+
+/// Collects the scopes of a file, keeping the levels of the scopes it is in on a stack.
+struct Collector {
+    file_id: usize,
+    stack: Vec<LevelMap>,
+    scopes: Vec<(Span, LevelMap)>,
+    errors: Vec<(usize, StaticAnalysisError)>,
+    warnings: Vec<(usize, StaticAnalysisWarning)>,
+}
+
+impl Collector {
+    /// Applies the lint level attributes among `attributes` to `map`.
+    fn apply(&mut self, map: &mut LevelMap, attributes: &[Attribute]) {
+        for attribute in attributes {
+            let Some(level) = Level::from_attribute(attribute.name()) else { continue };
+            if attribute.args().next().is_none() {
+                self.errors.push((self.file_id, StaticAnalysisError::MalformedLintAttribute {
+                    name: attribute.name().to_owned(),
+                    span: attribute.1.span(),
+                }));
+            }
+            for arg in attribute.args() {
+                let (name, span) = (arg.value().to_owned(), arg.span());
+                if !registry::is_known(&name) {
+                    self.warnings.push((self.file_id, StaticAnalysisWarning::UnknownLint { name, span }));
+                    continue;
+                }
+                let source = LevelSource::Attribute {
+                    name: name.clone(),
+                    file_id: self.file_id,
+                    span,
+                };
+                if let Err(forbid) = set(map, level, &name, source) {
+                    self.errors.push((self.file_id, StaticAnalysisError::OverruledForbid {
+                        level,
+                        name,
+                        span,
+                        forbid,
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Opens the scope of an item or a statement if `attributes` set any level,
+    /// returning whether it did.
+    fn enter(&mut self, attributes: &[Attribute], span: Span) -> bool {
+        if !attributes.iter().any(Attribute::is_lint_level) {
+            return false;
+        }
+        let mut map = self.stack.last().cloned().unwrap_or_default();
+        self.apply(&mut map, attributes);
+        self.scopes.push((span, map.clone()));
+        self.stack.push(map);
+        true
+    }
+
+    fn exit(&mut self, entered: bool) {
+        if entered {
+            self.stack.pop();
+        }
+    }
+}
+
+impl Visit for Collector {
+    fn visit_file(&mut self, file: &TopazFile) {
+        for (attributes, item) in file.item_attributes.iter().zip(&file.items) {
+            let entered = self.enter(attributes, item.span);
+            self.visit_item(&item.value);
+            self.exit(entered);
+        }
+    }
+
+    fn visit_method(&mut self, Method(attributes, func): &Method) {
+        let entered = self.enter(attributes, func.span);
+        self.visit_func(&func.value);
+        self.exit(entered);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        for (attributes, statement) in block.attributed_statements() {
+            let entered = self.enter(attributes, statement.span);
+            self.visit_statement(&statement.value);
+            self.exit(entered);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! `lint/mod.rs` - warnings about code that is valid, but most likely not what was meant.
+//!
+//! Lints run on a gem whose names are resolved, see [`topaz_resolve::resolve_gem`].
+//! Every lint is listed in [`registry`], [`levels`] decides whether and how it is reported.
+use crate::warning::StaticAnalysisWarning;
+use topaz_resolve::{Resolutions, SourceModule};
+
+pub mod levels;
+pub mod registry;
+mod unreachable;
+mod unused;
+mod usage;
+
+pub use usage::Usage;
+
+/// Runs every lint on the modules of a gem.
+///
+/// The warnings are sorted by file and position.
+#[must_use]
+pub fn lint_gem(
+    resolutions: &Resolutions,
+    modules: &[SourceModule],
+) -> Vec<(usize, StaticAnalysisWarning)> {
+    let usage = Usage::collect(resolutions, modules);
+
+    let mut warnings = Vec::new();
+    for module in modules {
+        let mut lints = unused::UnusedLints {
+            resolutions,
+            usage: &usage,
+            file_id: module.file_id,
+            root: module.path.is_empty(),
+            warnings: Vec::new(),
+        };
+        lints.check_file(module.file);
+        warnings.extend(lints.warnings.into_iter().map(|warning| (module.file_id, warning)));
+
+        warnings.extend(
+            unreachable::check_file(module.file)
+                .into_iter()
+                .map(|warning| (module.file_id, warning)),
+        );
+    }
+
+    warnings.sort_by_key(|(file_id, warning)| (*file_id, warning.span().start));
+    warnings
+}
+
+/// Names starting with an underscore are never reported as unused.
+fn is_silenced(name: &str) -> bool {
+    name.starts_with('_')
+}
@@ -0,0 +1,95 @@
+//! `lint/registry.rs` - every lint, with its default level and what it reports.
+use super::levels::Level;
+
+/// A lint, named by [`crate::warning::StaticAnalysisWarning::lint_name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lint {
+    pub name: &'static str,
+    pub default: Level,
+    pub code: &'static str,
+    pub description: &'static str,
+}
+
+pub const LINTS: &[Lint] = &[
+    Lint {
+        name: "imports_after_fst_tlstmt",
+        default: Level::Warn,
+        code: "W001",
+        description: "imports after another item of a file",
+    },
+    Lint {
+        name: "unnecessatopaz_visibility_qualifier",
+        default: Level::Warn,
+        code: "W002",
+        description: "visibility qualifiers that are implied anyway",
+    },
+    Lint {
+        name: "unused_imports",
+        default: Level::Warn,
+        code: "W003",
+        description: "imported names that are never used",
+    },
+    Lint {
+        name: "unused_variables",
+        default: Level::Warn,
+        code: "W004",
+        description: "bindings and parameters that are never read",
+    },
+    Lint {
+        name: "unused_mut",
+        default: Level::Warn,
+        code: "W005",
+        description: "`mut` bindings that are never assigned or borrowed mutably",
+    },
+    Lint {
+        name: "dead_code",
+        default: Level::Warn,
+        code: "W006",
+        description: "private functions that are never called",
+    },
+    Lint {
+        name: "unreachable_code",
+        default: Level::Warn,
+        code: "W007",
+        description: "statements that are never run",
+    },
+    Lint {
+        name: "unknown_lints",
+        default: Level::Warn,
+        code: "W008",
+        description: "lint names that are not known in lint level attributes",
+    },
+];
+
+/// Every lint currently at the `warn` level, `@deny(warnings)` turns every warning into an error.
+pub const WARNINGS: &str = "warnings";
+
+/// Groups of lints, which can be used wherever a lint name can.
+pub const GROUPS: &[(&str, &[&str])] = &[(
+    "unused",
+    &["unused_imports", "unused_variables", "unused_mut", "dead_code", "unreachable_code"],
+)];
+
+#[must_use]
+pub fn find(name: &str) -> Option<&'static Lint> {
+    LINTS.iter().find(|lint| lint.name == name)
+}
+
+/// The lints `name` stands for: the lint itself, or the lints of a group.
+/// `None` if `name` is neither, or [`WARNINGS`].
+#[must_use]
+pub fn expand(name: &str) -> Option<Vec<&'static str>> {
+    if let Some(lint) = find(name) {
+        return Some(vec![lint.name]);
+    }
+    GROUPS
+        .iter()
+        .find(|(group, _)| *group == name)
+        .map(|(_, lints)| lints.to_vec())
+}
+
+/// Whether `name` can be given a level: a lint, a group or [`WARNINGS`].
+#[must_use]
+pub fn is_known(name: &str) -> bool {
+    name == WARNINGS || expand(name).is_some()
+}
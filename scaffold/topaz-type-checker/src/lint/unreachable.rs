@@ -0,0 +1,33 @@
+use crate::warning::StaticAnalysisWarning;
+use topaz_ast::block::Block;
+use topaz_ast::file::TopazFile;
+use topaz_ast::location::Span;
+use topaz_ast::visit::{self, Visit};
+
+/// Statements following a `return`, or a block that always returns.
+pub(super) fn check_file(file: &TopazFile) -> Vec<StaticAnalysisWarning> {
+    let mut lint = Unreachable::default();
+    lint.visit_file(file);
+    lint.warnings
+}
+
+#[derive(Default)]
+struct Unreachable {
+    warnings: Vec<StaticAnalysisWarning>,
+}
+
+impl Visit for Unreachable {
+    fn visit_block(&mut self, block: &Block) {
+        let statements: Vec<_> = block.statements().collect();
+        let diverging = statements.iter().position(|statement| statement.value.diverges());
+        if let Some(index) = diverging {
+            if let (Some(first), Some(last)) = (statements.get(index + 1), statements.last()) {
+                self.warnings.push(StaticAnalysisWarning::Unreachable {
+                    span: Span::new(first.span.start, last.span.end),
+                    cause: statements[index].span,
+                });
+            }
+        }
+        visit::walk_block(self, block);
+    }
+}
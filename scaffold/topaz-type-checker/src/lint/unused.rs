@@ -0,0 +1,138 @@
+use super::usage::ENTRY_POINT;
+use super::{is_silenced, Usage};
+use crate::warning::StaticAnalysisWarning;
+use topaz_ast::block::Block;
+use topaz_ast::expr::{Expr, ExprBinary, ExprBorrow, ExprField, ExprUnary, UnOp};
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_ast::item::func::{Func, FuncArg};
+use topaz_ast::item::trait_def::RequiredMethod;
+use topaz_ast::item::Item;
+use topaz_ast::location::{Span, WithSpan};
+use topaz_ast::pattern::Pattern;
+use topaz_ast::statement::variable::LetStmt;
+use topaz_ast::statement::Statement;
+use topaz_ast::visibility::Visibility;
+use topaz_ast::visit::{self, Visit};
+use topaz_resolve::Resolutions;
+
+/// Unused imports, variables, parameters and `mut`s, and private functions that are never called.
+pub(super) struct UnusedLints<'a> {
+    pub(super) resolutions: &'a Resolutions,
+    pub(super) usage: &'a Usage,
+    pub(super) file_id: usize,
+    /// Whether the file is the root module of the gem
+    pub(super) root: bool,
+    pub(super) warnings: Vec<StaticAnalysisWarning>,
+}
+
+impl UnusedLints<'_> {
+    pub(super) fn check_file(&mut self, file: &TopazFile) {
+        for item in &file.items {
+            match &item.value {
+                Item::Import(import) => self.check_import(import.binding(), item.span),
+                Item::Func(func) => self.check_func(func, item.span),
+                // methods are used through their trait
+                Item::TypeAlias(_) | Item::Trait(_) | Item::Impl(_) | Item::Struct(_) | Item::Enum(_) => {}
+            }
+        }
+        self.visit_file(file);
+    }
+
+    fn is_used(&self, name: &Ident) -> bool {
+        self.resolutions
+            .decl_of(self.file_id, name)
+            .map_or(true, |def| self.usage.used.contains(&def))
+    }
+
+    fn check_import(&mut self, binding: Option<&Ident>, item: Span) {
+        let Some(name) = binding else { return };
+        let Some(def) = self.resolutions.decl_of(self.file_id, name) else { return };
+        if !self.resolutions.used_imports.contains(&def) && !is_silenced(name.value()) {
+            self.warnings.push(StaticAnalysisWarning::UnusedImport {
+                name: name.to_string(),
+                span: name.span(),
+                item,
+            });
+        }
+    }
+
+    fn check_func(&mut self, Func(_, visibility, name, ..): &Func, item: Span) {
+        let private = matches!(visibility, Visibility::Private(_));
+        let entry_point = self.root && name.value() == ENTRY_POINT;
+        let live = self.resolutions.decl_of(self.file_id, name).map_or(true, |def| self.usage.live.contains(&def));
+        if private && !entry_point && !live && !is_silenced(name.value()) {
+            self.warnings.push(StaticAnalysisWarning::DeadFunction {
+                name: name.to_string(),
+                span: name.span(),
+                item,
+            });
+        }
+    }
+
+    fn check_let(&mut self, LetStmt(_, mutable, name, _, init): &LetStmt, statement: Span) {
+        if is_silenced(name.value()) {
+            return;
+        }
+        let Some(def) = self.resolutions.decl_of(self.file_id, name) else { return };
+
+        if !self.usage.used.contains(&def) {
+            let pure = init.as_ref().map_or(true, |(_, init)| is_pure(&init.value));
+            self.warnings.push(StaticAnalysisWarning::UnusedVariable {
+                name: name.to_string(),
+                span: name.span(),
+                statement: pure.then_some(statement),
+            });
+        } else if let Some(mutable) = mutable {
+            if !self.usage.mutated.contains(&def) {
+                self.warnings.push(StaticAnalysisWarning::UnusedMut {
+                    name: name.to_string(),
+                    span: Span::new(mutable.span.start, name.span().start),
+                });
+            }
+        }
+    }
+}
+
+impl Visit for UnusedLints<'_> {
+    fn visit_func_arg(&mut self, arg @ FuncArg(_, pattern, ..): &FuncArg) {
+        let Pattern::Ident(name) = pattern;
+        let receiver = name.value() == "this";
+        if !receiver && !self.is_used(name) && !is_silenced(name.value()) {
+            self.warnings.push(StaticAnalysisWarning::UnusedParameter {
+                name: name.to_string(),
+                span: name.span(),
+            });
+        }
+        visit::walk_func_arg(self, arg);
+    }
+
+    fn visit_required_method(&mut self, _method: &RequiredMethod) {
+        // the parameters have no body to be used in
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        for WithSpan { value, span } in block.statements() {
+            if let Statement::Let(let_stmt) = value {
+                self.check_let(let_stmt, *span);
+            }
+            self.visit_statement(value);
+        }
+    }
+}
+
+/// Whether evaluating `expr` has no effect besides producing a value.
+fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(_) | Expr::VariableAccess(_) | Expr::ConstAccess(_) => true,
+        Expr::Borrow(ExprBorrow(_, _, borrowed)) => is_pure(&borrowed.value),
+        Expr::Field(ExprField(base, ..)) => is_pure(&base.value),
+        // arithmetic panics on overflow
+        Expr::Binary(ExprBinary(lhs, op, rhs)) => op.is_comparison() && is_pure(&lhs.value) && is_pure(&rhs.value),
+        Expr::Unary(ExprUnary(op, operand)) => matches!(op, UnOp::Not(_)) && is_pure(&operand.value),
+        Expr::Array(array) => array.elements().all(|element| is_pure(&element.value)),
+        Expr::Interpolated(interpolated) => interpolated.parts().all(|part| is_pure(&part.value)),
+        // indexing panics out of bounds
+        Expr::FuncCall(_) | Expr::Index(_) => false,
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::{HashMap, HashSet};
+use topaz_ast::expr::{Expr, ExprBorrow, ExprField, ExprVarAccess};
+use topaz_ast::item::func::Func;
+use topaz_ast::path::Path;
+use topaz_ast::statement::func_call::FuncCallStmt;
+use topaz_ast::statement::variable::AssignStmt;
+use topaz_ast::types::TypePath;
+use topaz_ast::visibility::Visibility;
+use topaz_ast::visit::{self, Visit};
+use topaz_resolve::def::DefId;
+use topaz_resolve::{Res, Resolutions, SourceModule};
+
+/// How the definitions of a gem are used.
+#[derive(Debug, Default)]
+pub struct Usage {
+    /// Definitions that are read, called or named as a type somewhere
+    pub used: HashSet<DefId>,
+    /// Definitions that are assigned to or borrowed mutably
+    pub mutated: HashSet<DefId>,
+    /// Functions reachable from the public functions, `main` of the root module, and the methods of traits
+    /// and impls, through the functions they call
+    pub live: HashSet<DefId>,
+}
+
+/// The function every gem with a `main.tp` starts at.
+pub(super) const ENTRY_POINT: &str = "main";
+
+impl Usage {
+    #[must_use]
+    pub fn collect(resolutions: &Resolutions, modules: &[SourceModule]) -> Self {
+        let mut collector = Collector {
+            resolutions,
+            file_id: 0,
+            func: None,
+            root_module: false,
+            usage: Self::default(),
+            uses: HashMap::new(),
+            roots: Vec::new(),
+        };
+        for module in modules {
+            collector.file_id = module.file_id;
+            collector.root_module = module.path.is_empty();
+            collector.visit_file(module.file);
+        }
+
+        let Collector { mut usage, mut uses, roots, .. } = collector;
+        let mut queue = roots;
+        while let Some(def) = queue.pop() {
+            if usage.live.insert(def) {
+                queue.extend(uses.remove(&def).into_iter().flatten());
+            }
+        }
+        usage
+    }
+}
+
+struct Collector<'r> {
+    resolutions: &'r Resolutions,
+    file_id: usize,
+    /// The function being visited: calling itself does not make a function used
+    func: Option<DefId>,
+    /// Whether the file being visited is the root module of the gem
+    root_module: bool,
+    usage: Usage,
+    /// The definitions every function uses
+    uses: HashMap<DefId, HashSet<DefId>>,
+    /// What is live no matter what: the public functions, `main`, and what is used outside of functions
+    roots: Vec<DefId>,
+}
+
+impl Collector<'_> {
+    fn def_of(&self, path: &Path) -> Option<DefId> {
+        match self.resolutions.res_of(self.file_id, path) {
+            Some(Res::Def(def)) => Some(*def),
+            _ => None,
+        }
+    }
+
+    fn use_path(&mut self, path: &Path) {
+        let Some(def) = self.def_of(path) else { return };
+        match self.func {
+            Some(func) if func == def => {}
+            Some(func) => {
+                self.usage.used.insert(def);
+                self.uses.entry(func).or_default().insert(def);
+            }
+            None => {
+                self.usage.used.insert(def);
+                self.roots.push(def);
+            }
+        }
+    }
+
+    fn mutate_path(&mut self, path: &Path) {
+        if let Some(def) = self.def_of(path) {
+            self.usage.mutated.insert(def);
+        }
+    }
+}
+
+impl Visit for Collector<'_> {
+    fn visit_func(&mut self, func @ Func(_, visibility, name, ..): &Func) {
+        let def = self.resolutions.decl_of(self.file_id, name);
+        let entry_point = self.root_module && name.value() == ENTRY_POINT;
+        if let Some(def) = def.filter(|_| !matches!(visibility, Visibility::Private(_)) || entry_point) {
+            self.roots.push(def);
+        }
+        let previous = std::mem::replace(&mut self.func, def);
+        visit::walk_func(self, func);
+        self.func = previous;
+    }
+
+    fn visit_expr_var_access(&mut self, ExprVarAccess(path): &ExprVarAccess) {
+        self.use_path(path);
+    }
+
+    fn visit_expr_borrow(&mut self, borrow @ ExprBorrow(_, mutable, borrowed): &ExprBorrow) {
+        // borrowing a field mutably mutates the variable it belongs to
+        if let (Some(_), Some(path)) = (mutable, place_root(&borrowed.value)) {
+            self.mutate_path(path);
+        }
+        visit::walk_expr_borrow(self, borrow);
+    }
+
+    fn visit_assign_stmt(&mut self, AssignStmt(place, _, value): &AssignStmt) {
+        self.visit_expr(&value.value);
+        self.mutate_path(place);
+    }
+
+    fn visit_func_call(&mut self, call: &FuncCallStmt) {
+        self.use_path(&call.0);
+        for arg in call.1.content().iter() {
+            self.visit_func_call_arg(arg);
+        }
+    }
+
+    fn visit_type_path(&mut self, type_path: &TypePath) {
+        self.use_path(&type_path.path);
+        self.visit_type_arguments(&type_path.arguments);
+    }
+}
+
+/// The variable a place like `thing.field.field` starts at.
+fn place_root(expr: &Expr) -> Option<&Path> {
+    match expr {
+        Expr::VariableAccess(ExprVarAccess(path)) => Some(path),
+        Expr::Field(ExprField(base, ..)) => place_root(&base.value),
+        _ => None,
+    }
+}
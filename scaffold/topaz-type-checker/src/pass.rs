@@ -0,0 +1,160 @@
+//! `pass.rs` - the analysis passes run by [`crate::StaticAnalyzer`].
+use crate::alias::AliasEnv;
+use crate::error::StaticAnalysisError;
+use crate::infer::TypeckResults;
+use crate::lint::levels::{Level, LintLevels};
+use crate::lint::lint_gem;
+use crate::structs::StructEnv;
+use crate::traits::TraitEnv;
+use crate::warning::StaticAnalysisWarning;
+use crate::{StaticAnalysisOutput, StaticAnalysisOutputUnit};
+use topaz_ast::file::TopazFile;
+use topaz_ast::item::func::{Func, Method};
+use topaz_ast::item::trait_def::TraitItem;
+use topaz_ast::item::Item;
+use topaz_ast::types::Type;
+use topaz_ast::visit::Visit;
+use topaz_resolve::{Resolutions, SourceModule};
+
+/// State shared by every pass of one analysis: the resolved gem, what earlier passes
+/// found out about it and everything reported so far.
+pub struct AnalysisSession<'a> {
+    pub resolutions: &'a Resolutions,
+    pub modules: &'a [SourceModule<'a>],
+    /// Every type alias, expanded before any pass runs
+    pub aliases: AliasEnv,
+    /// Every struct, collected before any pass runs. Which ones are `Copy`
+    /// is filled in by [`crate::traits::coherence::Coherence`]
+    pub structs: StructEnv,
+    /// Filled in by [`crate::infer::TypeInference`]
+    pub types: TypeckResults,
+    /// Filled in by [`crate::traits::coherence::Coherence`]
+    pub traits: TraitEnv,
+    /// Filled in by [`crate::lint::levels::collect`] before any pass runs, every lint is at its default level until then
+    pub lint_levels: LintLevels,
+    pub output: StaticAnalysisOutput,
+}
+
+impl<'a> AnalysisSession<'a> {
+    #[must_use]
+    pub fn new(resolutions: &'a Resolutions, modules: &'a [SourceModule<'a>]) -> Self {
+        let aliases = AliasEnv::collect(resolutions, modules);
+        Self {
+            resolutions,
+            modules,
+            structs: StructEnv::collect(resolutions, &aliases, modules),
+            aliases,
+            types: TypeckResults::default(),
+            traits: TraitEnv::default(),
+            lint_levels: LintLevels::default(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Reports `warning` at the level of its lint where it is: not at all, as a warning or as an error.
+    pub fn warn(&mut self, file_id: usize, warning: StaticAnalysisWarning) {
+        let level = self.lint_levels.level(file_id, &warning);
+        let unit = match level.level {
+            Level::Allow => return,
+            Level::Warn => StaticAnalysisOutputUnit::Warning(warning),
+            Level::Deny | Level::Forbid => StaticAnalysisOutputUnit::Error(StaticAnalysisError::DeniedLint {
+                warning: Box::new(warning),
+                level,
+            }),
+        };
+        self.output.push((file_id, unit));
+    }
+
+    pub fn error(&mut self, file_id: usize, error: StaticAnalysisError) {
+        self.output.push((file_id, StaticAnalysisOutputUnit::Error(error)));
+    }
+
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.output
+            .iter()
+            .any(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)))
+    }
+}
+
+/// The functions of `item` that have a body: a function, the methods of an impl,
+/// or the provided methods of a trait. Methods of an impl come with the type `this` stands for in them.
+#[must_use]
+pub fn item_bodies(item: &Item) -> Vec<(&Func, Option<&Type>)> {
+    match item {
+        Item::Func(func) => vec![(func, None)],
+        Item::Impl(impl_def) => impl_def
+            .methods()
+            .map(|Method(_, func)| (&func.value, Some(&impl_def.4.value)))
+            .collect(),
+        Item::Trait(trait_def) => trait_def
+            .items()
+            .filter_map(|item| match item {
+                TraitItem::Provided(Method(_, func)) => Some((&func.value, None)),
+                TraitItem::Required(_) => None,
+            })
+            .collect(),
+        Item::Import(_) | Item::TypeAlias(_) | Item::Struct(_) | Item::Enum(_) => Vec::new(),
+    }
+}
+
+/// A single step of the analysis.
+pub trait AnalysisPass {
+    /// The name the pass is selected by, e.g. in `topaz analyze --pass`.
+    fn name(&self) -> &'static str;
+
+    fn run(&mut self, session: &mut AnalysisSession);
+}
+
+/// `W001`: imports have to come before every other item of a file.
+#[derive(Default)]
+pub struct ImportOrder {
+    seen_other_item: bool,
+    warnings: Vec<StaticAnalysisWarning>,
+}
+
+impl AnalysisPass for ImportOrder {
+    fn name(&self) -> &'static str {
+        "import_order"
+    }
+
+    fn run(&mut self, session: &mut AnalysisSession) {
+        for module in session.modules {
+            self.visit_file(module.file);
+            for warning in self.warnings.drain(..) {
+                session.warn(module.file_id, warning);
+            }
+        }
+    }
+}
+
+impl Visit for ImportOrder {
+    fn visit_file(&mut self, file: &TopazFile) {
+        self.seen_other_item = false;
+        for item in &file.items {
+            match &item.value {
+                Item::Import(_) if self.seen_other_item => self
+                    .warnings
+                    .push(StaticAnalysisWarning::ImportAfterFirstTopLevelStatement(item.span)),
+                Item::Import(_) => {}
+                _ => self.seen_other_item = true,
+            }
+        }
+    }
+}
+
+/// The lints of [`crate::lint`].
+#[derive(Default)]
+pub struct Lints;
+
+impl AnalysisPass for Lints {
+    fn name(&self) -> &'static str {
+        "lints"
+    }
+
+    fn run(&mut self, session: &mut AnalysisSession) {
+        for (file_id, warning) in lint_gem(session.resolutions, session.modules) {
+            session.warn(file_id, warning);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! `structs.rs` - the structs and enums of a gem, and which types are copied instead of moved.
+//!
+//! Primitives and shared references are always copied, and arrays are if their elements are.
+//! A struct or an enum is moved, unless it implements `std`'s `Copy` trait, see [`crate::traits::coherence`].
+use crate::alias::AliasEnv;
+use crate::infer::signature::{FnSig, Param};
+use crate::ty::Ty;
+use std::collections::{HashMap, HashSet};
+use topaz_ast::ident::Ident;
+use topaz_ast::item::enum_def::{Enum, Variant};
+use topaz_ast::item::struct_def::{Struct, StructField};
+use topaz_ast::item::Item;
+use topaz_ast::location::Span;
+use topaz_resolve::def::DefId;
+use topaz_resolve::{Resolutions, SourceModule};
+
+/// The path of the `Copy` trait in the standard library.
+pub const COPY: [&str; 3] = ["mem", "copy", "Copy"];
+
+#[derive(Debug, Clone)]
+pub struct FieldDef {
+    pub name: Ident,
+    pub ty: Ty,
+    /// The type annotation
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructDef {
+    pub def: DefId,
+    pub name: Ident,
+    pub file_id: usize,
+    pub fields: Vec<FieldDef>,
+}
+
+impl StructDef {
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<&FieldDef> {
+        self.fields.iter().find(|field| field.name.value() == name)
+    }
+
+    /// The signature of the struct called as a function, taking ownership of every field.
+    #[must_use]
+    pub fn constructor(&self) -> FnSig {
+        FnSig {
+            name: self.name,
+            file_id: self.file_id,
+            params: self
+                .fields
+                .iter()
+                .map(|field| Param {
+                    name: field.name,
+                    ty: field.ty.clone(),
+                    span: field.span,
+                    has_default: false,
+                    moved: true,
+                })
+                .collect(),
+            ret: Ty::Struct {
+                def: self.def,
+                name: self.name.to_string(),
+            },
+            ret_span: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantDef {
+    pub def: DefId,
+    pub name: Ident,
+    /// The fields, named `_0`, `_1`, ... after their index
+    pub fields: Vec<FieldDef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub def: DefId,
+    pub name: Ident,
+    pub file_id: usize,
+    pub variants: Vec<VariantDef>,
+}
+
+impl EnumDef {
+    #[must_use]
+    pub fn ty(&self) -> Ty {
+        Ty::Enum {
+            def: self.def,
+            name: self.name.to_string(),
+        }
+    }
+
+    /// The signature of the variant at `index` called as a function, taking ownership of every field.
+    #[must_use]
+    pub fn constructor(&self, index: usize) -> FnSig {
+        let variant = &self.variants[index];
+        FnSig {
+            name: variant.name,
+            file_id: self.file_id,
+            params: variant
+                .fields
+                .iter()
+                .map(|field| Param {
+                    name: field.name,
+                    ty: field.ty.clone(),
+                    span: field.span,
+                    has_default: false,
+                    moved: true,
+                })
+                .collect(),
+            ret: self.ty(),
+            ret_span: None,
+        }
+    }
+}
+
+/// Every struct and enum of a gem.
+#[derive(Debug, Default)]
+pub struct StructEnv {
+    pub structs: HashMap<DefId, StructDef>,
+    pub enums: HashMap<DefId, EnumDef>,
+    /// The enum and index of every variant
+    pub variants: HashMap<DefId, (DefId, usize)>,
+    /// The structs and enums implementing `Copy`
+    pub copy: HashSet<DefId>,
+}
+
+impl StructEnv {
+    #[must_use]
+    pub fn collect(resolutions: &Resolutions, aliases: &AliasEnv, modules: &[SourceModule]) -> Self {
+        let mut env = Self::default();
+        for module in modules {
+            for item in &module.file.items {
+                if let Item::Enum(enum_def) = &item.value {
+                    env.collect_enum(resolutions, aliases, module.file_id, enum_def);
+                    continue;
+                }
+                let Item::Struct(struct_def @ Struct(_, _, name, _)) = &item.value else { continue };
+                let Some(def) = resolutions.decl_of(module.file_id, name) else { continue };
+                let fields = struct_def
+                    .fields()
+                    .map(|StructField(name, ty)| FieldDef {
+                        name: *name,
+                        ty: aliases.lower(resolutions, module.file_id, &ty.value),
+                        span: ty.span,
+                    })
+                    .collect();
+                env.structs.insert(def, StructDef {
+                    def,
+                    name: *name,
+                    file_id: module.file_id,
+                    fields,
+                });
+            }
+        }
+        env
+    }
+
+    fn collect_enum(&mut self, resolutions: &Resolutions, aliases: &AliasEnv, file_id: usize, enum_def: &Enum) {
+        let Some(def) = resolutions.decl_of(file_id, &enum_def.2) else { return };
+        let mut variants = vec![];
+        for Variant(name, fields) in enum_def.variants() {
+            let Some(variant) = resolutions.decl_of(file_id, name) else { continue };
+            let fields = fields
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| FieldDef {
+                    name: Ident::new(&format!("_{i}")).spanned(ty.span),
+                    ty: aliases.lower(resolutions, file_id, &ty.value),
+                    span: ty.span,
+                })
+                .collect();
+            self.variants.insert(variant, (def, variants.len()));
+            variants.push(VariantDef {
+                def: variant,
+                name: *name,
+                fields,
+            });
+        }
+        self.enums.insert(def, EnumDef {
+            def,
+            name: enum_def.2,
+            file_id,
+            variants,
+        });
+    }
+
+    /// The enum `ty` is, if it is one.
+    #[must_use]
+    pub fn enum_of(&self, ty: &Ty) -> Option<&EnumDef> {
+        match ty.peel() {
+            Ty::Enum { def, .. } => self.enums.get(def),
+            _ => None,
+        }
+    }
+
+    /// The enum `def` is a variant of, and the index of the variant in it.
+    #[must_use]
+    pub fn variant(&self, def: DefId) -> Option<(&EnumDef, usize)> {
+        let (enum_def, index) = self.variants.get(&def)?;
+        Some((&self.enums[enum_def], *index))
+    }
+
+    /// The struct `ty` is, if it is one.
+    #[must_use]
+    pub fn struct_of(&self, ty: &Ty) -> Option<&StructDef> {
+        match ty.peel() {
+            Ty::Struct { def, .. } => self.structs.get(def),
+            _ => None,
+        }
+    }
+
+    /// The field `name` of `ty`, if it is a struct that has one, or an enum with a variant that has one.
+    /// The fields of a variant are named after it, e.g. `Circle.0`.
+    #[must_use]
+    pub fn field(&self, ty: &Ty, name: &str) -> Option<&FieldDef> {
+        if let Some(enum_def) = self.enum_of(ty) {
+            let (variant, index) = name.split_once('.')?;
+            let variant = enum_def.variants.iter().find(|def| def.name.value() == variant)?;
+            return variant.fields.get(index.parse::<usize>().ok()?);
+        }
+        self.struct_of(ty)?.field(name)
+    }
+
+    /// Whether using a value of type `ty` copies it, instead of moving it.
+    ///
+    /// Types not known yet are assumed to be copied, so that errors are not reported twice.
+    #[must_use]
+    pub fn is_copy(&self, ty: &Ty) -> bool {
+        match ty.peel() {
+            Ty::Void | Ty::Int(_) | Ty::Char | Ty::Bool | Ty::Var(_) | Ty::IntVar(_) | Ty::Error => true,
+            Ty::Ref { mutable, .. } => !mutable,
+            Ty::Maybe(ty) | Ty::Array { element: ty, .. } => self.is_copy(ty),
+            Ty::Struct { def, .. } | Ty::Enum { def, .. } => self.copy.contains(def),
+            Ty::Str | Ty::Named(_) | Ty::Param(_) => false,
+            Ty::Alias { .. } => unreachable!("peeled"),
+        }
+    }
+}
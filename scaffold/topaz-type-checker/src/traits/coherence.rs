@@ -0,0 +1,174 @@
+//! `traits/coherence.rs` - checks that the impls of a gem are well-formed and never overlap.
+use super::{overlap, ImplDef, TraitEnv, TraitRef};
+use crate::error::StaticAnalysisError;
+use crate::pass::{AnalysisPass, AnalysisSession};
+use crate::structs::COPY;
+use topaz_resolve::{Namespace, Res};
+
+/// Collects the [`TraitEnv`] of the gem and checks every impl in it:
+/// - the implemented path has to be a trait
+/// - a trait of another gem can only be implemented for a type of this gem (the orphan rule)
+/// - every method without a default body has to be implemented,
+///   and every implemented method has to be one of the trait
+/// - no two impls of the same trait may apply to the same type
+/// - a struct implementing `std`'s `Copy` only has fields that are `Copy`
+#[derive(Default)]
+pub struct Coherence;
+
+impl AnalysisPass for Coherence {
+    fn name(&self) -> &'static str {
+        "traits"
+    }
+
+    fn run(&mut self, session: &mut AnalysisSession) {
+        let env = TraitEnv::collect(session.resolutions, &session.aliases, session.modules);
+        for impl_def in &env.impls {
+            let errors = match &impl_def.trait_ref {
+                Some(trait_ref) => check_impl(&env, impl_def, trait_ref),
+                None => not_a_trait(session, impl_def).into_iter().collect(),
+            };
+            for error in errors {
+                session.error(impl_def.file_id, error);
+            }
+        }
+        check_overlap(session, &env);
+        mark_copy(session, &env);
+        session.traits = env;
+    }
+}
+
+fn not_a_trait(session: &AnalysisSession, impl_def: &ImplDef) -> Option<StaticAnalysisError> {
+    let kind = match session.resolutions.paths.get(&(impl_def.file_id, impl_def.trait_span))? {
+        Res::Def(def) => session.resolutions.defs.get(*def).kind.descr(),
+        Res::Module(_) => "module",
+        // anything outside of the gem is assumed to be a trait
+        Res::External(_) => return None,
+    };
+    Some(StaticAnalysisError::NotATrait {
+        name: impl_def.trait_name.clone(),
+        kind,
+        span: impl_def.trait_span,
+    })
+}
+
+fn check_impl(env: &TraitEnv, impl_def: &ImplDef, trait_ref: &TraitRef) -> Vec<StaticAnalysisError> {
+    let trait_name = impl_def.trait_name.clone();
+    let trait_def = match trait_ref {
+        TraitRef::Local(def) => match env.traits.get(def) {
+            Some(trait_def) => trait_def,
+            None => return Vec::new(),
+        },
+        TraitRef::External(namespace, _) => {
+            if impl_def.local_self_ty {
+                return Vec::new();
+            }
+            return vec![StaticAnalysisError::OrphanImpl {
+                trait_name,
+                self_ty: impl_def.self_ty.to_string(),
+                from: match namespace {
+                    Namespace::Core => "`core`",
+                    Namespace::Std => "`std`",
+                    Namespace::Gem(_) => "another gem",
+                },
+                span: impl_def.header,
+            }];
+        }
+    };
+
+    let mut errors = Vec::new();
+    let missing: Vec<_> = trait_def
+        .methods
+        .iter()
+        .filter(|method| !method.provided)
+        .filter(|method| !impl_def.methods.iter().any(|implemented| implemented.name.value() == method.name.value()))
+        .map(|method| method.name.to_string())
+        .collect();
+    if !missing.is_empty() {
+        errors.push(StaticAnalysisError::MissingMethods {
+            trait_name: trait_name.clone(),
+            missing,
+            span: impl_def.header,
+        });
+    }
+    for method in &impl_def.methods {
+        if trait_def.method(&method.name).is_none() {
+            errors.push(StaticAnalysisError::NotAMember {
+                method: method.name.to_string(),
+                trait_name: trait_name.clone(),
+                overrides: method.overrides,
+                span: method.name.span(),
+            });
+        }
+    }
+    errors
+}
+
+/// Reports every impl that applies to a type an earlier impl of the same trait already applies to.
+fn check_overlap(session: &mut AnalysisSession, env: &TraitEnv) {
+    for (i, later) in env.impls.iter().enumerate() {
+        let Some(trait_ref) = &later.trait_ref else { continue };
+        if later.fallback {
+            continue;
+        }
+        let first = env.impls[..i]
+            .iter()
+            .filter(|earlier| earlier.trait_ref.as_ref() == Some(trait_ref) && !earlier.fallback)
+            .find(|earlier| overlap(earlier, later));
+        if let Some(first) = first {
+            session.error(later.file_id, StaticAnalysisError::ConflictingImpls {
+                trait_name: later.trait_name.clone(),
+                self_ty: later.self_ty.to_string(),
+                first: (first.file_id, first.header),
+                span: later.header,
+            });
+        }
+    }
+}
+
+/// Marks the structs and enums implementing `Copy` as copied, then checks that each of their fields is copied too.
+fn mark_copy(session: &mut AnalysisSession, env: &TraitEnv) {
+    let structs = &session.structs;
+    let impls: Vec<_> = env
+        .impls
+        .iter()
+        .filter(|impl_def| impl_def.trait_ref.as_ref().map_or(false, |trait_ref| trait_ref.is_std(&COPY)))
+        .filter_map(|impl_def| {
+            let def = match structs.struct_of(&impl_def.self_ty) {
+                Some(struct_def) => struct_def.def,
+                None => structs.enum_of(&impl_def.self_ty)?.def,
+            };
+            Some((impl_def, def))
+        })
+        .collect();
+    session.structs.copy.extend(impls.iter().map(|(_, def)| *def));
+
+    for (impl_def, def) in impls {
+        let structs = &session.structs;
+        // the fields of a variant are named after it, e.g. `Circle.0`
+        let (file_id, fields): (_, Vec<_>) = match structs.structs.get(&def) {
+            Some(struct_def) => (struct_def.file_id, struct_def.fields.iter().map(|field| (field.name.to_string(), field)).collect()),
+            None => {
+                let enum_def = &structs.enums[&def];
+                let fields = enum_def
+                    .variants
+                    .iter()
+                    .flat_map(|variant| {
+                        variant.fields.iter().enumerate().map(move |(i, field)| (format!("{}.{i}", variant.name), field))
+                    })
+                    .collect();
+                (enum_def.file_id, fields)
+            }
+        };
+        let field = fields.into_iter().find(|(_, field)| !structs.is_copy(&field.ty));
+        if let Some((name, field)) = field {
+            let error = StaticAnalysisError::NonCopyField {
+                self_ty: impl_def.self_ty.to_string(),
+                field: name,
+                field_ty: field.ty.clone(),
+                field_span: (file_id, field.span),
+                span: impl_def.header,
+            };
+            session.error(impl_def.file_id, error);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! `traits/mod.rs` - the traits and impls of a gem, and selection of the impl that applies to a type.
+//!
+//! An impl may be generic over its self type (`impl<T> Drop for T`), in which case it applies
+//! to every type its self type can be instantiated to. See [`coherence`] for the checks
+//! that make sure at most one impl applies.
+use crate::alias::AliasEnv;
+use crate::infer::table::InferTable;
+use crate::ty::Ty;
+use std::collections::HashMap;
+use topaz_ast::ident::Ident;
+use topaz_ast::item::func::{Func, Method};
+use topaz_ast::item::impl_def::Impl;
+use topaz_ast::item::trait_def::{Trait, TraitItem};
+use topaz_ast::item::Item;
+use topaz_ast::location::{Span, WithSpan};
+use topaz_ast::types::{Type, TypeReference};
+use topaz_resolve::def::{DefId, DefKind};
+use topaz_resolve::{Namespace, Res, ResolvedPath, Resolutions, SourceModule};
+
+pub mod coherence;
+
+/// Marks a method as overriding one of the implemented trait.
+pub const OVERRIDE: &str = "override";
+/// Marks the impl of `std`'s `Drop` for every type, which any other impl of `Drop` takes precedence over.
+pub const STD_BLANKET_DROP: &str = "std_blanket_drop";
+/// The path of the `Drop` trait in the standard library.
+pub const DROP: [&str; 3] = ["mem", "drop", "Drop"];
+
+#[derive(Debug, Clone)]
+pub struct TraitMethod {
+    /// What `Trait::method` resolves to
+    pub def: DefId,
+    pub name: Ident,
+    /// Whether the trait has a default body for the method
+    pub provided: bool,
+}
+
+/// A trait defined in the gem.
+#[derive(Debug, Clone)]
+pub struct TraitDef {
+    pub def: DefId,
+    pub name: Ident,
+    pub file_id: usize,
+    pub methods: Vec<TraitMethod>,
+}
+
+impl TraitDef {
+    #[must_use]
+    pub fn method(&self, name: &Ident) -> Option<&TraitMethod> {
+        self.methods.iter().find(|method| method.name.value() == name.value())
+    }
+}
+
+/// The trait an impl implements.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TraitRef {
+    Local(DefId),
+    /// A trait of another gem, by its path in that gem
+    External(Namespace, Vec<String>),
+}
+
+impl TraitRef {
+    /// Whether this is the trait at `path` in the standard library.
+    #[must_use]
+    pub fn is_std(&self, path: &[&str]) -> bool {
+        matches!(self, Self::External(Namespace::Std, segments) if segments.iter().eq(path))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImplMethod {
+    pub name: Ident,
+    /// Whether the method is marked `@override`
+    pub overrides: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ImplId(pub usize);
+
+#[derive(Debug, Clone)]
+pub struct ImplDef {
+    pub file_id: usize,
+    /// From `impl` to the end of the self type
+    pub header: Span,
+    /// The implemented trait, as written
+    pub trait_name: String,
+    pub trait_span: Span,
+    /// `None` if the path does not resolve to a trait
+    pub trait_ref: Option<TraitRef>,
+    pub generics: Vec<Ident>,
+    /// The type the trait is implemented for, with the generics as [`Ty::Param`]s
+    pub self_ty: Ty,
+    /// Whether the self type is defined in the gem
+    pub local_self_ty: bool,
+    pub methods: Vec<ImplMethod>,
+    /// Whether any other impl applying to the same type takes precedence, see [`STD_BLANKET_DROP`]
+    pub fallback: bool,
+}
+
+impl ImplDef {
+    fn from_ast(resolutions: &Resolutions, aliases: &AliasEnv, file_id: usize, item: Span, impl_def @ Impl(_, generics, trait_path, _, ty, _): &Impl) -> Self {
+        let trait_ref = match resolutions.res_of(file_id, trait_path) {
+            Some(Res::Def(def)) if resolutions.defs.get(*def).kind == DefKind::Trait => Some(TraitRef::Local(*def)),
+            Some(Res::External(ResolvedPath::Path(namespace, segments, _))) => Some(TraitRef::External(
+                *namespace,
+                segments.iter().map(ToString::to_string).collect(),
+            )),
+            _ => None,
+        };
+
+        Self {
+            file_id,
+            header: Span::new(item.start, ty.span.end),
+            trait_name: trait_path.to_string(),
+            trait_span: trait_path.span(),
+            trait_ref,
+            generics: generics.clone(),
+            self_ty: aliases.lower(resolutions, file_id, &ty.value),
+            local_self_ty: is_local(resolutions, file_id, &ty.value),
+            methods: impl_def
+                .methods()
+                .map(|method @ Method(_, WithSpan { value: Func(_, _, name, ..), .. })| ImplMethod {
+                    name: *name,
+                    overrides: method.has_attribute(OVERRIDE),
+                })
+                .collect(),
+            fallback: impl_def.methods().any(|method| method.has_attribute(STD_BLANKET_DROP)),
+        }
+    }
+}
+
+/// Whether `ty` is (a reference to) a type defined in the gem.
+fn is_local(resolutions: &Resolutions, file_id: usize, ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => matches!(
+            resolutions.res_of(file_id, &path.path),
+            // aliases are not types of their own
+            Some(Res::Def(def)) if matches!(resolutions.defs.get(*def).kind, kind if kind.is_item() && kind != DefKind::TypeAlias)
+        ),
+        Type::Reference(TypeReference { referenced, .. }) => is_local(resolutions, file_id, referenced),
+        _ => false,
+    }
+}
+
+/// Why no single impl could be selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionError {
+    Unimplemented,
+    /// More than one impl applies, which coherence checking reports
+    Ambiguous(Vec<ImplId>),
+}
+
+/// Every trait and impl of a gem.
+#[derive(Debug, Default)]
+pub struct TraitEnv {
+    pub traits: HashMap<DefId, TraitDef>,
+    pub impls: Vec<ImplDef>,
+}
+
+impl TraitEnv {
+    #[must_use]
+    pub fn collect(resolutions: &Resolutions, aliases: &AliasEnv, modules: &[SourceModule]) -> Self {
+        let mut env = Self::default();
+        for module in modules {
+            for item in &module.file.items {
+                match &item.value {
+                    Item::Trait(trait_def @ Trait(_, _, name, _)) => {
+                        let Some(def) = resolutions.decl_of(module.file_id, name) else { continue };
+                        let methods = trait_def
+                            .items()
+                            .zip(resolutions.methods.get(&def).into_iter().flatten())
+                            .map(|(item, method)| TraitMethod {
+                                def: *method,
+                                name: *item.name(),
+                                provided: matches!(item, TraitItem::Provided(_)),
+                            })
+                            .collect();
+                        env.traits.insert(def, TraitDef {
+                            def,
+                            name: *name,
+                            file_id: module.file_id,
+                            methods,
+                        });
+                    }
+                    Item::Impl(impl_def) => {
+                        env.impls.push(ImplDef::from_ast(resolutions, aliases, module.file_id, item.span, impl_def));
+                    }
+                    Item::Import(_) | Item::Func(_) | Item::TypeAlias(_) | Item::Struct(_) | Item::Enum(_) => {}
+                }
+            }
+        }
+        env
+    }
+
+    /// The trait the method `def` is declared in, and the method.
+    #[must_use]
+    pub fn method(&self, def: DefId) -> Option<(&TraitDef, &TraitMethod)> {
+        self.traits.values().find_map(|trait_def| {
+            trait_def.methods.iter().find(|method| method.def == def).map(|method| (trait_def, method))
+        })
+    }
+
+    #[must_use]
+    pub fn get(&self, id: ImplId) -> &ImplDef {
+        &self.impls[id.0]
+    }
+
+    /// The impls of `trait_ref`.
+    pub fn impls_of<'a>(&'a self, trait_ref: &'a TraitRef) -> impl Iterator<Item = (ImplId, &'a ImplDef)> + 'a {
+        self.impls
+            .iter()
+            .enumerate()
+            .filter(move |(_, impl_def)| impl_def.trait_ref.as_ref() == Some(trait_ref))
+            .map(|(i, impl_def)| (ImplId(i), impl_def))
+    }
+
+    /// Finds the impl of `trait_ref` that applies to `ty`.
+    ///
+    /// An impl marked as a fallback is only selected if no other impl applies.
+    pub fn select(&self, trait_ref: &TraitRef, ty: &Ty) -> Result<ImplId, SelectionError> {
+        let (fallbacks, candidates): (Vec<_>, Vec<_>) = self
+            .impls_of(trait_ref)
+            .filter(|(_, impl_def)| matches(&impl_def.self_ty, ty, &mut HashMap::new()))
+            .partition(|(_, impl_def)| impl_def.fallback);
+
+        let candidates = if candidates.is_empty() { fallbacks } else { candidates };
+        match &candidates[..] {
+            [] => Err(SelectionError::Unimplemented),
+            [(id, _)] => Ok(*id),
+            _ => Err(SelectionError::Ambiguous(candidates.iter().map(|(id, _)| *id).collect())),
+        }
+    }
+
+    /// The impl of `std`'s `Drop` for `ty`, unless only the blanket impl applies, which does nothing.
+    #[must_use]
+    pub fn drop_impl(&self, ty: &Ty) -> Option<ImplId> {
+        let drop = TraitRef::External(Namespace::Std, DROP.map(str::to_owned).to_vec());
+        let id = self.select(&drop, ty).ok()?;
+        (!self.get(id).fallback).then_some(id)
+    }
+}
+
+/// Whether `pattern` can be instantiated to `ty`, binding its parameters in `params`.
+fn matches(pattern: &Ty, ty: &Ty, params: &mut HashMap<String, Ty>) -> bool {
+    match (pattern.peel(), ty.peel()) {
+        (Ty::Param(name), _) => match params.get(name) {
+            Some(bound) => bound.expanded() == ty.expanded(),
+            None => {
+                params.insert(name.clone(), ty.clone());
+                true
+            }
+        },
+        (Ty::Error, _) | (_, Ty::Error) => true,
+        (
+            Ty::Ref { mutable: a_mut, ty: a },
+            Ty::Ref { mutable: b_mut, ty: b },
+        ) => a_mut == b_mut && matches(a, b, params),
+        (Ty::Maybe(a), Ty::Maybe(b)) => matches(a, b, params),
+        (pattern, ty) => pattern == ty,
+    }
+}
+
+/// Whether some type is an instance of the self types of both `a` and `b`.
+fn overlap(a: &ImplDef, b: &ImplDef) -> bool {
+    if *a.self_ty.peel() == Ty::Error || *b.self_ty.peel() == Ty::Error {
+        return false;
+    }
+    let mut table = InferTable::default();
+    let a_ty = instantiate(&a.self_ty, &mut HashMap::new(), &mut table);
+    let b_ty = instantiate(&b.self_ty, &mut HashMap::new(), &mut table);
+    table.unify(&a_ty, &b_ty).is_ok()
+}
+
+/// Replaces every parameter of `ty` by a fresh variable, the same one for each use.
+fn instantiate(ty: &Ty, vars: &mut HashMap<String, Ty>, table: &mut InferTable) -> Ty {
+    match ty {
+        Ty::Param(name) => vars.entry(name.clone()).or_insert_with(|| table.new_var()).clone(),
+        Ty::Ref { mutable, ty } => Ty::Ref {
+            mutable: *mutable,
+            ty: Box::new(instantiate(ty, vars, table)),
+        },
+        Ty::Maybe(ty) => Ty::Maybe(Box::new(instantiate(ty, vars, table))),
+        Ty::Alias { ty, .. } => instantiate(ty, vars, table),
+        ty => ty.clone(),
+    }
+}
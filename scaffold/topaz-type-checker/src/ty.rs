@@ -0,0 +1,262 @@
+//! `ty.rs` - types as the type checker sees them.
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use topaz_ast::types::{
+    PrimitiveType, Type, TypeArray, TypeMaybe, TypeMaybeSome, TypeMaybeUnknown, TypePath, TypeReference,
+};
+use topaz_resolve::def::DefId;
+
+/// A sized integer type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IntTy {
+    Usize,
+    Isize,
+    Ubyte,
+    Byte,
+    Ushort,
+    Short,
+    Uint,
+    Int,
+    Ulong,
+    Long,
+    Uexplod,
+    Explod,
+}
+
+impl IntTy {
+    /// The type unsuffixed integer literals default to.
+    pub const DEFAULT: Self = Self::Int;
+    /// How wide `usize` and `isize` are on the target with the widest pointers, which literals are checked against.
+    pub const MAX_PTR_BITS: u32 = 64;
+
+    #[must_use]
+    pub const fn is_signed(self) -> bool {
+        matches!(self, Self::Isize | Self::Byte | Self::Short | Self::Int | Self::Long | Self::Explod)
+    }
+
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Usize => "usize",
+            Self::Isize => "isize",
+            Self::Ubyte => "ubyte",
+            Self::Byte => "byte",
+            Self::Ushort => "ushort",
+            Self::Short => "short",
+            Self::Uint => "uint",
+            Self::Int => "int",
+            Self::Ulong => "ulong",
+            Self::Long => "long",
+            Self::Uexplod => "uexplod",
+            Self::Explod => "explod",
+        }
+    }
+
+    /// The smallest and the largest value of the type, where `usize` and `isize` have `ptr_bits` bits.
+    #[must_use]
+    pub const fn range(self, ptr_bits: u32) -> (i128, u128) {
+        let bits = match self {
+            Self::Ubyte | Self::Byte => 8,
+            Self::Ushort | Self::Short => 16,
+            Self::Uint | Self::Int => 32,
+            Self::Ulong | Self::Long => 64,
+            Self::Usize | Self::Isize => ptr_bits,
+            Self::Uexplod => return (0, u128::MAX),
+            Self::Explod => return (i128::MIN, i128::MAX.unsigned_abs()),
+        };
+        if self.is_signed() {
+            (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+        } else {
+            (0, (1 << bits) - 1)
+        }
+    }
+}
+
+/// The name of the [`Ty::Param`] the type `this` is lowered to, standing for the type a method is defined on.
+pub const THIS: &str = "this";
+
+/// An inference variable, see [`crate::infer`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TyVar(pub u32);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Ty {
+    /// `()`
+    Void,
+    Int(IntTy),
+    Char,
+    Str,
+    Bool,
+    Ref { mutable: bool, ty: Box<Ty> },
+    /// `maybe T`
+    Maybe(Box<Ty>),
+    /// A struct of the gem, by its name
+    Struct { def: DefId, name: String },
+    /// An enum of the gem, by its name
+    Enum { def: DefId, name: String },
+    /// `[T; len]`
+    Array { element: Box<Ty>, len: usize },
+    /// A type referred to by its path, compared by name
+    Named(String),
+    /// A generic parameter of an impl or a type alias, or `this` in a method
+    Param(String),
+    /// A type alias, as written (e.g. `Pair<int>`), and the type it expands to.
+    /// Everything but diagnostics sees through it, see [`Ty::peel`]
+    Alias { name: String, ty: Box<Ty> },
+    /// Any type, not known yet
+    Var(TyVar),
+    /// Some integer type, not known yet
+    IntVar(TyVar),
+    /// The type of something that already failed to check; unifies with everything
+    Error,
+}
+
+impl Ty {
+    /// Lowers a type written in the source, comparing paths by name.
+    #[must_use]
+    pub fn from_ast(ty: &Type) -> Self {
+        Self::lower(ty, &mut |path| Self::Named(path.path.to_string()))
+    }
+
+    /// Lowers a type written in the source, with `path` lowering the paths in it.
+    pub fn lower(ty: &Type, path: &mut impl FnMut(&TypePath) -> Self) -> Self {
+        match ty {
+            Type::Primitive(primitive) => match primitive {
+                PrimitiveType::Void => Self::Void,
+                PrimitiveType::Usize => Self::Int(IntTy::Usize),
+                PrimitiveType::Isize => Self::Int(IntTy::Isize),
+                PrimitiveType::Ubyte => Self::Int(IntTy::Ubyte),
+                PrimitiveType::Byte => Self::Int(IntTy::Byte),
+                PrimitiveType::Ushort => Self::Int(IntTy::Ushort),
+                PrimitiveType::Short => Self::Int(IntTy::Short),
+                PrimitiveType::Uint => Self::Int(IntTy::Uint),
+                PrimitiveType::Int => Self::Int(IntTy::Int),
+                PrimitiveType::Ulong => Self::Int(IntTy::Ulong),
+                PrimitiveType::Long => Self::Int(IntTy::Long),
+                PrimitiveType::Uexplod => Self::Int(IntTy::Uexplod),
+                PrimitiveType::Explod => Self::Int(IntTy::Explod),
+                PrimitiveType::Char => Self::Char,
+                PrimitiveType::String => Self::Str,
+                PrimitiveType::Bool => Self::Bool,
+            },
+            Type::Path(type_path) => path(type_path),
+            Type::Reference(TypeReference {
+                mutability,
+                referenced,
+                ..
+            }) => Self::Ref {
+                mutable: mutability.is_some(),
+                ty: Box::new(Self::lower(referenced, path)),
+            },
+            Type::Maybe(
+                TypeMaybe::Unknown(TypeMaybeUnknown { real_type, .. })
+                | TypeMaybe::Some(TypeMaybeSome { real_type, .. }),
+            ) => Self::Maybe(Box::new(Self::lower(real_type, path))),
+            Type::Array(TypeArray { element, len, .. }) => match len.to_usize() {
+                Some(len) => Self::Array {
+                    element: Box::new(Self::lower(element, path)),
+                    len,
+                },
+                None => Self::Error,
+            },
+            // not supported by the checker yet
+            Type::Func(_) | Type::Maybe(TypeMaybe::Nope) => Self::Error,
+        }
+    }
+
+    #[must_use]
+    pub fn is_integer(&self) -> bool {
+        matches!(self.peel(), Self::Int(_) | Self::IntVar(_))
+    }
+
+    /// The type without the aliases around it.
+    #[must_use]
+    pub fn peel(&self) -> &Self {
+        match self {
+            Self::Alias { ty, .. } => ty.peel(),
+            ty => ty,
+        }
+    }
+
+    /// The type without the aliases and references around it, e.g. `S` for `&&S`.
+    #[must_use]
+    pub fn referent(&self) -> &Self {
+        match self.peel() {
+            Self::Ref { ty, .. } => ty.referent(),
+            ty => ty,
+        }
+    }
+
+    /// Replaces the parameters in `params` by their types.
+    #[must_use]
+    pub fn subst(&self, params: &HashMap<String, Self>) -> Self {
+        match self {
+            Self::Param(name) => params.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Self::Ref { mutable, ty } => Self::Ref {
+                mutable: *mutable,
+                ty: Box::new(ty.subst(params)),
+            },
+            Self::Maybe(ty) => Self::Maybe(Box::new(ty.subst(params))),
+            Self::Array { element, len } => Self::Array {
+                element: Box::new(element.subst(params)),
+                len: *len,
+            },
+            Self::Alias { name, ty } => Self::Alias {
+                name: name.clone(),
+                ty: Box::new(ty.subst(params)),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    /// The type in backticks for diagnostics, followed by what it expands to if it contains aliases,
+    /// e.g. ``` `&Meters` (aka `&long`) ```
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let expanded = self.expanded();
+        if expanded == *self {
+            format!("`{self}`")
+        } else {
+            format!("`{self}` (aka `{expanded}`)")
+        }
+    }
+
+    /// The type with every alias in it expanded.
+    #[must_use]
+    pub fn expanded(&self) -> Self {
+        match self {
+            Self::Alias { ty, .. } => ty.expanded(),
+            Self::Ref { mutable, ty } => Self::Ref {
+                mutable: *mutable,
+                ty: Box::new(ty.expanded()),
+            },
+            Self::Maybe(ty) => Self::Maybe(Box::new(ty.expanded())),
+            Self::Array { element, len } => Self::Array {
+                element: Box::new(element.expanded()),
+                len: *len,
+            },
+            ty => ty.clone(),
+        }
+    }
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Void => write!(f, "()"),
+            Self::Int(int) => write!(f, "{}", int.name()),
+            Self::Char => write!(f, "char"),
+            Self::Str => write!(f, "str"),
+            Self::Bool => write!(f, "bool"),
+            Self::Ref { mutable: true, ty } => write!(f, "&mut {ty}"),
+            Self::Ref { mutable: false, ty } => write!(f, "&{ty}"),
+            Self::Maybe(ty) => write!(f, "maybe {ty}"),
+            Self::Array { element, len } => write!(f, "[{element}; {len}]"),
+            Self::Struct { name, .. } | Self::Enum { name, .. } | Self::Named(name) | Self::Param(name) | Self::Alias { name, .. } => {
+                write!(f, "{name}")
+            }
+            Self::Var(_) | Self::Error => write!(f, "_"),
+            Self::IntVar(_) => write!(f, "{{integer}}"),
+        }
+    }
+}
@@ -1,36 +1,197 @@
-use codespan_reporting::diagnostic::{Diagnostic, Label};
+use crate::lint::levels::LintLevel;
+use codespan_reporting::diagnostic::{Diagnostic, Label, Severity};
 use thiserror::Error;
 use topaz_ast::location::Span;
+use topaz_report::suggestion::Suggestion;
 use topaz_report::Reporter;
 
-#[derive(Error, Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
 pub enum StaticAnalysisWarning {
     #[error("unnecessatopaz visibility qualifier in {0}")]
     UnnecessatopazVisibilityQualifier(Span),
     #[error("import after first top level statement in {0}")]
     ImportAfterFirstTopLevelStatement(Span),
+    #[error("unused import `{name}`")]
+    UnusedImport {
+        name: String,
+        span: Span,
+        /// The whole `import` item
+        item: Span,
+    },
+    #[error("unused variable `{name}`")]
+    UnusedVariable {
+        name: String,
+        span: Span,
+        /// The `let` statement, if it can be removed without losing side effects
+        statement: Option<Span>,
+    },
+    #[error("unused parameter `{name}`")]
+    UnusedParameter { name: String, span: Span },
+    #[error("variable `{name}` does not need to be mutable")]
+    UnusedMut {
+        name: String,
+        /// From `mut` up to the name of the variable
+        span: Span,
+    },
+    #[error("function `{name}` is never called")]
+    DeadFunction {
+        name: String,
+        span: Span,
+        /// The whole function
+        item: Span,
+    },
+    #[error("unreachable statement")]
+    Unreachable {
+        span: Span,
+        /// The statement control never continues after
+        cause: Span,
+    },
+    #[error("unknown lint: `{name}`")]
+    UnknownLint { name: String, span: Span },
 }
 
-impl<'source> Reporter<'source> for StaticAnalysisWarning {
-    fn build_diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+impl StaticAnalysisWarning {
+    /// The name of the lint emitting the warning.
+    #[must_use]
+    pub const fn lint_name(&self) -> &'static str {
+        match self {
+            Self::UnnecessatopazVisibilityQualifier(_) => "unnecessatopaz_visibility_qualifier",
+            Self::ImportAfterFirstTopLevelStatement(_) => "imports_after_fst_tlstmt",
+            Self::UnusedImport { .. } => "unused_imports",
+            Self::UnusedVariable { .. } | Self::UnusedParameter { .. } => "unused_variables",
+            Self::UnusedMut { .. } => "unused_mut",
+            Self::DeadFunction { .. } => "dead_code",
+            Self::Unreachable { .. } => "unreachable_code",
+            Self::UnknownLint { .. } => "unknown_lints",
+        }
+    }
+
+    #[must_use]
+    pub const fn span(&self) -> Span {
+        match self {
+            Self::UnnecessatopazVisibilityQualifier(span)
+            | Self::ImportAfterFirstTopLevelStatement(span)
+            | Self::UnusedImport { span, .. }
+            | Self::UnusedVariable { span, .. }
+            | Self::UnusedParameter { span, .. }
+            | Self::UnusedMut { span, .. }
+            | Self::DeadFunction { span, .. }
+            | Self::Unreachable { span, .. }
+            | Self::UnknownLint { span, .. } => *span,
+        }
+    }
+
+    /// The diagnostic of the warning reported at `level`, which is an error at `deny` and `forbid`.
+    #[must_use]
+    pub fn build_diagnostic_at(&self, file_id: usize, level: &LintLevel) -> Diagnostic<usize> {
+        let mut diagnostic = self.diagnostic(file_id);
+        if level.level != crate::lint::levels::Level::Warn {
+            diagnostic.severity = Severity::Error;
+        }
+        let (labels, notes) = level.explain(self.lint_name());
+        diagnostic.labels.extend(labels);
+        diagnostic.notes.splice(0..0, notes);
+        diagnostic
+    }
+
+    /// The diagnostic without the level of the lint.
+    fn diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+        let suggestions = self
+            .suggestions()
+            .iter()
+            .map(|suggestion| suggestion.label(file_id))
+            .collect::<Vec<_>>();
+
         match self {
             Self::ImportAfterFirstTopLevelStatement(span) => Diagnostic::warning()
                 .with_code("W001")
                 .with_message("found import after another top level statement")
                 .with_labels(vec![Label::primary(file_id, *span)
-                    .with_message("consider placing it at the beginning of the file")])
-                .with_notes(vec![
-                    "`#[warn(imports_after_fst_tlstmt)]` on by default".to_owned()
-                ]),
+                    .with_message("consider placing it at the beginning of the file")]),
             Self::UnnecessatopazVisibilityQualifier(span) => Diagnostic::warning()
                 .with_code("W002")
                 .with_message("unnecessatopaz visibility qualifier found")
                 .with_labels(vec![Label::primary(file_id, *span)
-                    .with_message("consider removing `pub`, because it's implied")])
-                .with_notes(vec![
-                    "`#[warn(unnecessatopaz_visibility_qualifier)]` on by default`".to_owned(),
-                ]),
-            // _ => todo!(),
+                    .with_message("consider removing `pub`, because it's implied")]),
+            Self::UnusedImport { span, .. } => Diagnostic::warning()
+                .with_code("W003")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)])
+                .with_labels(suggestions),
+            Self::UnusedVariable { span, .. } | Self::UnusedParameter { span, .. } => {
+                Diagnostic::warning()
+                    .with_code("W004")
+                    .with_message(self.to_string())
+                    .with_labels(vec![Label::primary(file_id, *span)])
+                    .with_labels(suggestions)
+                    .with_notes(vec!["names starting with an underscore are never reported".to_owned()])
+            }
+            Self::UnusedMut { span, .. } => Diagnostic::warning()
+                .with_code("W005")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message("the variable is never assigned or borrowed mutably")])
+                .with_labels(suggestions),
+            Self::DeadFunction { span, .. } => Diagnostic::warning()
+                .with_code("W006")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)
+                    .with_message("private, and not called anywhere in the gem")])
+                .with_labels(suggestions),
+            Self::Unreachable { span, cause } => Diagnostic::warning()
+                .with_code("W007")
+                .with_message(self.to_string())
+                .with_labels(vec![
+                    Label::primary(file_id, *span),
+                    Label::secondary(file_id, *cause)
+                        .with_message("any code following this statement is unreachable"),
+                ])
+                .with_labels(suggestions),
+            Self::UnknownLint { span, .. } => Diagnostic::warning()
+                .with_code("W008")
+                .with_message(self.to_string())
+                .with_labels(vec![Label::primary(file_id, *span)])
+                .with_notes(vec!["`topaz lints` lists the known lints and lint groups".to_owned()]),
+        }
+    }
+}
+
+impl<'source> Reporter<'source> for StaticAnalysisWarning {
+    fn build_diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+        self.build_diagnostic_at(file_id, &LintLevel::default_of(self.lint_name()))
+    }
+
+    fn suggestions(&self) -> Vec<Suggestion> {
+        match self {
+            Self::UnusedImport { item, .. } => {
+                vec![Suggestion::removal("remove the unused import", *item)]
+            }
+            Self::UnusedVariable {
+                statement: Some(statement),
+                ..
+            } => vec![Suggestion::removal("remove the unused variable", *statement)],
+            Self::UnusedVariable { name, span, .. } => vec![Suggestion::replacement(
+                "if this is intentional, prefix it with an underscore",
+                *span,
+                format!("_{name}"),
+            )],
+            // callers passing it by name, `f(name = x)`, would have to be renamed too
+            Self::UnusedParameter { name, span } => vec![Suggestion::replacement(
+                "if this is intentional, prefix it with an underscore",
+                *span,
+                format!("_{name}"),
+            )
+            .maybe_incorrect()],
+            Self::UnusedMut { span, .. } => vec![Suggestion::removal("remove `mut`", *span)],
+            Self::DeadFunction { item, .. } => {
+                vec![Suggestion::removal("remove the function", *item)]
+            }
+            Self::Unreachable { span, .. } => {
+                vec![Suggestion::removal("remove the unreachable code", *span)]
+            }
+            Self::UnnecessatopazVisibilityQualifier(_)
+            | Self::ImportAfterFirstTopLevelStatement(_)
+            | Self::UnknownLint { .. } => Vec::new(),
         }
     }
 }
@@ -0,0 +1,108 @@
+mod common;
+
+use common::{analyze, errors};
+use topaz_type_checker::alias::Aliases;
+use topaz_type_checker::error::StaticAnalysisError;
+use topaz_type_checker::infer::TypeInference;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+const SOURCE: &str = r#"typealias Meters = long;
+typealias Distance = Meters;
+typealias Pair<T> = &maybe T;
+typealias Loop = Again;
+typealias Again = Loop;
+typealias Broken = Pair;
+
+func main() {
+    let far: Distance = 5;
+    let text: Meters = "far";
+    let pair: Pair<int> = 1;
+}
+"#;
+
+#[test]
+fn test_aliases() {
+    let (errors, far) = analyze(SOURCE, &mut [&mut Aliases, &mut TypeInference], |session| {
+        let far = session.types.locals.values().find(|ty| ty.to_string() == "Distance").cloned();
+        (errors(session), far.expect("no local of type `Distance`"))
+    });
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(messages, [
+        "type alias `Loop` refers to itself",
+        "`Pair` takes 1 type argument but 0 were given",
+        "mismatched types: expected `Meters` (aka `long`), found `str`",
+        "mismatched types: expected `Pair<int>` (aka `&maybe int`), found `{integer}`",
+    ]);
+    assert!(matches!(
+        &errors[0],
+        StaticAnalysisError::CyclicAlias { cycle, .. } if cycle == &["Loop", "Again", "Loop"]
+    ));
+
+    // an alias of an alias expands all the way down, but keeps its name
+    assert_eq!(far.expanded(), Ty::Int(IntTy::Long));
+}
+
+/// Every error reported for `source` by expanding its aliases and checking its functions.
+fn alias_errors(source: &str) -> Vec<StaticAnalysisError> {
+    analyze(source, &mut [&mut Aliases, &mut TypeInference], errors)
+}
+
+#[test]
+fn test_generic_aliases() {
+    // the parameters of an alias are substituted in the aliases it refers to
+    let errors = alias_errors(
+        r#"typealias Pair<T> = [T; 2];
+typealias Maybe<T> = maybe T;
+typealias Both<T> = Pair<Maybe<T>>;
+typealias Flipped<T, U> = Pair<U>;
+
+func main() {
+    let both: Both<int> = 1;
+    let flipped: Flipped<int, &str> = 1;
+}
+"#,
+    );
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(messages, [
+        "mismatched types: expected `Both<int>` (aka `[maybe int; 2]`), found `{integer}`",
+        "mismatched types: expected `Flipped<int, &str>` (aka `[&str; 2]`), found `{integer}`",
+    ]);
+}
+
+#[test]
+fn test_cyclic_aliases() {
+    let errors = alias_errors(
+        r#"typealias Itself = &Itself;
+typealias Ping = Pong;
+typealias Pong = maybe Ping;
+typealias Wrap<T> = maybe T;
+typealias Knot = Wrap<Knot>;
+"#,
+    );
+    let cycles: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            StaticAnalysisError::CyclicAlias { cycle, .. } => cycle.join(" -> "),
+            error => panic!("unexpected error {error}"),
+        })
+        .collect();
+    assert_eq!(cycles, ["Itself -> Itself", "Ping -> Pong -> Ping", "Knot -> Knot"]);
+}
+
+#[test]
+fn test_type_argument_count() {
+    let errors = alias_errors(
+        r#"typealias Meters = long;
+typealias Pair<T> = [T; 2];
+typealias Missing = Pair;
+typealias Extra = Pair<int, long>;
+typealias Given = Meters<int>;
+"#,
+    );
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(messages, [
+        "`Pair` takes 1 type argument but 0 were given",
+        "`Pair` takes 1 type argument but 2 were given",
+        "`Meters` takes 0 type arguments but 1 was given",
+    ]);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::resolve;
+use topaz_type_checker::pass::{AnalysisPass, AnalysisSession};
+use topaz_type_checker::warning::StaticAnalysisWarning;
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+const SOURCE: &str = r#"func main() {}
+
+import std::mem::drop;
+"#;
+
+/// Records the passes that ran before it.
+struct Probe(&'static str);
+
+impl AnalysisPass for Probe {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn run(&mut self, session: &mut AnalysisSession) {
+        let seen = session.output.len();
+        let span = session.modules[0].file.items[0].span;
+        session.warn(0, StaticAnalysisWarning::UnnecessatopazVisibilityQualifier(span));
+        assert_eq!(seen + 1, session.output.len());
+    }
+}
+
+#[test]
+fn test_pipeline() {
+    resolve(&[("", SOURCE)], None, |resolutions, modules| {
+        let mut analyzer = StaticAnalyzer::with_default_passes(resolutions, modules);
+        analyzer.register(Probe("probe"));
+        let passes: Vec<_> = analyzer.pass_names().collect();
+        assert_eq!(passes, ["import_order", "lints", "aliases", "traits", "infer", "probe"]);
+
+        let output = analyzer.analyze();
+        let warnings: Vec<_> = output
+            .iter()
+            .map(|(_, unit)| match unit {
+                StaticAnalysisOutputUnit::Warning(warning) => warning.lint_name(),
+                StaticAnalysisOutputUnit::Error(error) => panic!("unexpected error {error}"),
+            })
+            .collect();
+        assert_eq!(warnings, [
+            "imports_after_fst_tlstmt",
+            "unused_imports",
+            "unnecessatopaz_visibility_qualifier"
+        ]);
+
+        let mut analyzer = StaticAnalyzer::with_default_passes(resolutions, modules);
+        analyzer.retain_passes(|name| name == "lints");
+        assert_eq!(analyzer.analyze().len(), 1);
+    });
+}
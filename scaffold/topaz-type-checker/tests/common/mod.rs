@@ -0,0 +1,69 @@
+//! Helpers shared by the tests of the analyses, building the gems they run on.
+#![allow(dead_code)]
+
+use topaz_ast::file::TopazFile;
+use topaz_ast::ident::Ident;
+use topaz_parser_next::Parse;
+use topaz_resolve::prelude::Prelude;
+use topaz_resolve::{resolve_gem, ResolveContext, Resolutions, SourceModule};
+use topaz_type_checker::error::StaticAnalysisError;
+use topaz_type_checker::pass::{AnalysisPass, AnalysisSession};
+use topaz_type_checker::StaticAnalysisOutputUnit;
+
+pub fn parse(source: &str) -> TopazFile {
+    TopazFile::parse(source).expect("failed to parse")
+}
+
+/// Resolves a gem, the source of each module by its name, the root module first with an empty one,
+/// and returns what `inspect` finds in it. What `prelude`, a module of `std`, imports is in scope everywhere.
+pub fn resolve<T>(
+    sources: &[(&str, &str)],
+    prelude: Option<&str>,
+    inspect: impl FnOnce(&Resolutions, &[SourceModule]) -> T,
+) -> T {
+    let files: Vec<_> = sources.iter().map(|(_, source)| parse(source)).collect();
+    let modules: Vec<_> = sources
+        .iter()
+        .zip(&files)
+        .enumerate()
+        .map(|(file_id, ((name, _), file))| {
+            let path = if name.is_empty() { vec![] } else { vec![Ident::new(name)] };
+            SourceModule { file_id, path, file }
+        })
+        .collect();
+    let mut cx = ResolveContext::new("test", true);
+    if let Some(prelude) = prelude {
+        cx.set_prelude(Prelude::from_std_module(&parse(prelude)));
+    }
+    let resolutions = resolve_gem(&mut cx, modules.clone());
+    assert!(resolutions.errors.is_empty(), "{:?}", resolutions.errors);
+    inspect(&resolutions, &modules)
+}
+
+/// Resolves `source`, the root module of a gem, and runs `passes` on it in order,
+/// returning what `inspect` finds in the session afterwards.
+pub fn analyze<T>(
+    source: &str,
+    passes: &mut [&mut dyn AnalysisPass],
+    inspect: impl FnOnce(&AnalysisSession) -> T,
+) -> T {
+    resolve(&[("", source)], None, |resolutions, modules| {
+        let mut session = AnalysisSession::new(resolutions, modules);
+        for pass in passes {
+            pass.run(&mut session);
+        }
+        inspect(&session)
+    })
+}
+
+/// The errors reported in `session`, which has no warnings.
+pub fn errors(session: &AnalysisSession) -> Vec<StaticAnalysisError> {
+    session
+        .output
+        .iter()
+        .map(|(_, unit)| match unit {
+            StaticAnalysisOutputUnit::Error(error) => error.clone(),
+            StaticAnalysisOutputUnit::Warning(warning) => panic!("unexpected warning {warning}"),
+        })
+        .collect()
+}
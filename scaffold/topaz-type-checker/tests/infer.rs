@@ -0,0 +1,369 @@
+mod common;
+
+use common::{analyze, errors};
+use topaz_type_checker::error::StaticAnalysisError;
+use topaz_type_checker::infer::TypeInference;
+use topaz_type_checker::traits::coherence::Coherence;
+use topaz_type_checker::ty::{IntTy, Ty};
+
+const SOURCE: &str = r#"func main(limit: long) {
+    let default = 1;
+    let later;
+    later = 2ubyte;
+    let unified = 3;
+    let wide: long = unified;
+    let text: str = 4;
+    let borrowed = &mut limit;
+    let unknown;
+}
+"#;
+
+/// The errors found, and the inferred type of each local by name.
+fn infer(source: &str) -> (Vec<StaticAnalysisError>, Vec<(String, Ty)>) {
+    // the impls a call of a method of a trait is checked against come first
+    analyze(source, &mut [&mut Coherence, &mut TypeInference], |session| {
+        let mut locals: Vec<_> = session
+            .types
+            .locals
+            .iter()
+            .map(|(def, ty)| (*def, session.resolutions.defs.get(*def).name.to_string(), ty.clone()))
+            .collect();
+        locals.sort_by_key(|(def, ..)| *def);
+
+        (errors(session), locals.into_iter().map(|(_, name, ty)| (name, ty)).collect())
+    })
+}
+
+fn span_of(source: &str, needle: &str) -> (usize, usize) {
+    let start = source.find(needle).expect("needle not in source");
+    (start, start + needle.len())
+}
+
+#[test]
+fn test_inference() {
+    let (errors, locals) = infer(SOURCE);
+
+    let long = Ty::Int(IntTy::Long);
+    assert_eq!(locals, [
+        ("limit".to_owned(), long.clone()),
+        ("default".to_owned(), Ty::Int(IntTy::Int)),
+        ("later".to_owned(), Ty::Int(IntTy::Ubyte)),
+        ("unified".to_owned(), long.clone()),
+        ("wide".to_owned(), long.clone()),
+        ("text".to_owned(), Ty::Str),
+        ("borrowed".to_owned(), Ty::Ref { mutable: true, ty: Box::new(long) }),
+        ("unknown".to_owned(), Ty::Error),
+    ]);
+
+    let [StaticAnalysisError::TypeMismatch { expected, found, expected_span, found_span }, StaticAnalysisError::CannotInfer { name, .. }] =
+        &errors[..]
+    else {
+        panic!("unexpected errors {errors:?}");
+    };
+    assert_eq!((expected.to_string(), found.to_string()), ("str".to_owned(), "{integer}".to_owned()));
+    let annotation = span_of(SOURCE, "str = 4");
+    assert_eq!(expected_span.map(|span| (span.start, span.end)), Some((annotation.0, annotation.0 + 3)));
+    assert_eq!((found_span.start, found_span.end), (annotation.0 + 6, annotation.0 + 7));
+    assert_eq!(name, "unknown");
+}
+
+#[test]
+fn test_borrowed_expression_types() {
+    let source = "func main(limit: long) {\n    let borrowed = &mut limit;\n}\n";
+    analyze(source, &mut [&mut TypeInference], |session| {
+        let type_at = |(start, end): (usize, usize)| {
+            session.types.exprs.iter().find(|((_, span), _)| (span.start, span.end) == (start, end)).map(|(_, ty)| ty)
+        };
+        let borrow = span_of(source, "&mut limit");
+        let long = Ty::Int(IntTy::Long);
+        assert_eq!(type_at(borrow), Some(&Ty::Ref { mutable: true, ty: Box::new(long.clone()) }));
+        // the borrowed expression has a type of its own
+        assert_eq!(type_at((borrow.0 + 5, borrow.1)), Some(&long));
+    });
+}
+
+#[test]
+fn test_mismatch_with_inferred_origin() {
+    let source = "func main() {\n    let value = \"text\";\n    value = 5;\n}\n";
+    let (errors, _) = infer(source);
+
+    let [StaticAnalysisError::TypeMismatch { expected, expected_span, .. }] = &errors[..] else {
+        panic!("unexpected errors {errors:?}");
+    };
+    assert_eq!(expected, &Ty::Str);
+    // the type of `value` comes from its initializer
+    let init = span_of(source, "\"text\"");
+    assert_eq!(expected_span.map(|span| (span.start, span.end)), Some(init));
+}
+
+const CALLS: &str = r#"func add(a: int, b: int = 1) -> int {
+    return a;
+}
+
+func greet(name: str) {}
+
+func broken() -> int {
+    let x = 1;
+}
+
+func early(flag: int) -> long {
+    return;
+}
+
+func main() {
+    let sum = add(1);
+    let wide: long = add(1, 2);
+    greet(5);
+    greet();
+    greet("a", "b");
+    greet(nick = "a");
+    add(b = 2, a = 3);
+    return 1;
+}
+"#;
+
+#[test]
+fn test_signatures() {
+    let (errors, locals) = infer(CALLS);
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+
+    assert_eq!(messages, [
+        "`broken` does not return a value on every path",
+        "`return` without a value in a function returning `long`",
+        "mismatched types: expected `long`, found `int`",
+        "mismatched types: expected `str`, found `{integer}`",
+        "`greet` takes 1 argument but 0 were passed",
+        "`greet` takes 1 argument but 2 were passed",
+        "`greet` has no parameter named `nick`",
+        "`greet` takes 1 argument but 0 were passed",
+        "mismatched types: expected `()`, found `{integer}`",
+    ]);
+    assert!(matches!(
+        &errors[4],
+        StaticAnalysisError::ArgumentCount { missing, .. } if missing == &["name"]
+    ));
+    // the call takes the return type of the function
+    assert!(locals.contains(&("sum".to_owned(), Ty::Int(IntTy::Int))));
+
+    // the mismatch points at the annotation of the parameter
+    let StaticAnalysisError::TypeMismatch { expected_span: Some(span), .. } = &errors[3] else {
+        panic!("expected a mismatch, got {:?}", errors[3]);
+    };
+    assert_eq!(&CALLS[span.start..span.end], "str");
+}
+
+const OPERATORS: &str = r#"func main(count: uint, flag: bool) {
+    let sum = 1 + 2 * 3;
+    let wide = 4long - sum;
+    let negative = -count;
+    let compared = (sum < 5) == flag;
+    let not = !sum;
+    let text = "a" + "b";
+    let ordered = flag < true;
+}
+"#;
+
+#[test]
+fn test_operators() {
+    let (errors, locals) = infer(OPERATORS);
+
+    let long = Ty::Int(IntTy::Long);
+    let types: Vec<_> = locals.iter().map(|(name, ty)| (name.as_str(), ty)).collect();
+    // the operands of arithmetic have the same type as its result
+    assert!(types.contains(&("sum", &long)), "{types:?}");
+    assert!(types.contains(&("wide", &long)), "{types:?}");
+    assert!(types.contains(&("compared", &Ty::Bool)), "{types:?}");
+
+    let invalid: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            StaticAnalysisError::InvalidOperand { op, ty, span, .. } => {
+                (op.as_str(), ty.clone(), &OPERATORS[span.start..span.end])
+            }
+            error => panic!("unexpected error {error:?}"),
+        })
+        .collect();
+    assert_eq!(
+        invalid,
+        [
+            ("-", Ty::Int(IntTy::Uint), "count"),
+            ("!", long, "sum"),
+            ("+", Ty::Str, "\"a\""),
+            ("<", Ty::Bool, "flag"),
+        ]
+    );
+}
+
+const ENUMS: &str = r#"enum Shape {
+    Circle(int),
+    Rect(int, int),
+    Empty
+}
+
+enum Suit { Hearts }
+
+func main(count: int) {
+    match Shape::Circle(1) {
+        Shape::Circle(radius, extra) => {}
+        Suit::Hearts => {}
+    }
+    match count {
+        _ => {}
+    }
+    let numbers = [1, 2];
+    let second = numbers[1];
+    let shapes = [Shape::Empty];
+    let first = shapes[0];
+}
+"#;
+
+const LITERALS: &str = r#"func main() {
+    let small: ubyte = 300ubyte;
+    let inferred: ubyte = 256;
+    let defaulted = 2147483648;
+    let lowest = -128byte;
+    let below = -129byte;
+    let widest = 340282366920938463463374607431768211455uexplod;
+    let beyond = 340282366920938463463374607431768211456uexplod;
+    let size = 18446744073709551616usize;
+}
+"#;
+
+#[test]
+fn test_literal_ranges() {
+    let (errors, _) = infer(LITERALS);
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            StaticAnalysisError::LiteralOutOfRange { ty, span } => {
+                format!("{} `{}`", ty.name(), &LITERALS[span.start..span.end])
+            }
+            error => panic!("unexpected error {error:?}"),
+        })
+        .collect();
+    // the type of a literal is known once the function is inferred, and a negative one fits one more value
+    assert_eq!(
+        errors,
+        [
+            "ubyte `300ubyte`",
+            "ubyte `256`",
+            "int `2147483648`",
+            "byte `129byte`",
+            "uexplod `340282366920938463463374607431768211456uexplod`",
+            "usize `18446744073709551616usize`",
+        ]
+    );
+}
+
+const REFERENCES: &str = r#"struct Inner { value: int, name: str }
+struct Outer { inner: Inner, count: long }
+
+func read(outer: &Outer, twice: &&Outer) {
+    let count = outer.count;
+    let value = twice.inner.value;
+    let inner = &outer.inner;
+    let name = outer.inner.name;
+}
+"#;
+
+#[test]
+fn test_fields_through_references() {
+    let (errors, locals) = infer(REFERENCES);
+
+    let types: Vec<_> = locals.iter().map(|(name, ty)| (name.as_str(), ty)).collect();
+    let inner = types.iter().find(|(name, _)| *name == "inner").expect("no `inner`").1;
+    assert!(types.contains(&("count", &Ty::Int(IntTy::Long))), "{types:?}");
+    assert!(types.contains(&("value", &Ty::Int(IntTy::DEFAULT))), "{types:?}");
+    assert!(matches!(inner, Ty::Ref { mutable: false, ty } if ty.to_string() == "Inner"), "{inner:?}");
+
+    // only the field that is not `Copy` cannot be read, it can be borrowed
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            StaticAnalysisError::MoveOutOfRef { ty, span } => format!("{ty} `{}`", &REFERENCES[span.start..span.end]),
+            error => panic!("unexpected error {error:?}"),
+        })
+        .collect();
+    assert_eq!(errors, ["str `outer.inner.name`"]);
+}
+
+#[test]
+fn test_enums_and_arrays() {
+    let (errors, locals) = infer(ENUMS);
+
+    let types: Vec<_> = locals.iter().map(|(name, ty)| (name.as_str(), ty)).collect();
+    let int = Ty::Int(IntTy::DEFAULT);
+    assert!(types.contains(&("numbers", &Ty::Array { element: Box::new(int.clone()), len: 2 })), "{types:?}");
+    assert!(types.contains(&("second", &int)), "{types:?}");
+
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            StaticAnalysisError::PatternFieldCount { variant, expected, found, span } => {
+                format!("{variant} {expected} {found} `{}`", &ENUMS[span.start..span.end])
+            }
+            StaticAnalysisError::NotAVariant { name, enum_name, span } => {
+                format!("{name} of {enum_name} `{}`", &ENUMS[span.start..span.end])
+            }
+            StaticAnalysisError::NonExhaustiveMatch { missing, span, .. } => {
+                format!("missing {missing:?} `{}`", &ENUMS[span.start..span.end])
+            }
+            StaticAnalysisError::MatchNotEnum { ty, span } => format!("{ty} `{}`", &ENUMS[span.start..span.end]),
+            StaticAnalysisError::MoveOutOfArray { ty, span } => format!("{ty} `{}`", &ENUMS[span.start..span.end]),
+            error => panic!("unexpected error {error:?}"),
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "Shape::Circle 1 2 `Shape::Circle(radius, extra)`",
+            "Suit::Hearts of Shape `Suit::Hearts`",
+            "missing [\"Rect\", \"Empty\"] `Shape::Circle(1)`",
+            "int `count`",
+            "Shape `shapes`",
+        ]
+    );
+}
+
+const TRAIT_METHODS: &str = r#"trait Area {
+    func area(this) -> int;
+
+    func twice(this) -> int {
+        return Area::area(this) * 2;
+    }
+}
+
+impl Area for long {
+    func area(this) -> int = 1;
+}
+
+func main() {
+    let one = Area::area(2long);
+    let two = Area::twice(3long);
+    let text = Area::area("square");
+    let literal = Area::area(4);
+}
+"#;
+
+#[test]
+fn test_trait_methods() {
+    let (errors, locals) = infer(TRAIT_METHODS);
+
+    // a method of a trait returns what the trait declares, whatever `this` is
+    let types: Vec<_> = locals.iter().map(|(name, ty)| (name.as_str(), ty)).collect();
+    for name in ["one", "two", "text", "literal"] {
+        assert!(types.contains(&(name, &Ty::Int(IntTy::Int))), "{types:?}");
+    }
+
+    // only types the trait is implemented for can be passed, an integer literal being an `int` unless told otherwise
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            StaticAnalysisError::Unimplemented { trait_name, method, ty, span } => {
+                format!("{trait_name} {method} {ty} `{}`", &TRAIT_METHODS[span.start..span.end])
+            }
+            error => panic!("unexpected error {error:?}"),
+        })
+        .collect();
+    assert_eq!(errors, ["Area Area::area str `Area::area`", "Area Area::area int `Area::area`"]);
+}
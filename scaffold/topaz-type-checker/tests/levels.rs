@@ -0,0 +1,234 @@
+mod common;
+
+use codespan_reporting::diagnostic::Diagnostic;
+use common::resolve;
+use topaz_report::Reporter;
+use topaz_type_checker::lint::levels::Level;
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+const MAIN: &str = r#"@!deny(unused_variables)
+@!allow(not_a_lint)
+import gem::util::take;
+
+func main() {
+    let kept = 1;
+    @allow(unused_variables)
+    let quiet = 2;
+    @warn(unused)
+    let loud = 3;
+    take(1);
+    helper();
+}
+
+@forbid(dead_code)
+private func helper() {}
+
+@deny(dead_code)
+private func unused() {}
+"#;
+
+const UTIL: &str = r#"@!allow(unused)
+public func ignore() {
+    let ignored = 1;
+}
+
+@forbid(unused_mut)
+public func stubborn() {
+    @allow(unused_mut)
+    let mut fixed = 1;
+    take(fixed);
+}
+
+public func take(value: int) {}
+"#;
+
+/// The severity and diagnostic of everything reported for `sources`, the source of each module by its name, the
+/// root module first with an empty one.
+fn diagnostics(sources: &[(&str, &str)], flags: &[(Level, &str)]) -> Vec<(&'static str, Diagnostic<usize>)> {
+    resolve(sources, None, |resolutions, modules| {
+        let mut analyzer = StaticAnalyzer::with_default_passes(resolutions, modules);
+        analyzer.retain_passes(|name| name == "lints");
+        for (level, name) in flags {
+            analyzer.set_lint_level(*level, *name);
+        }
+        analyzer
+            .analyze()
+            .iter()
+            .map(|(file_id, unit)| {
+                let severity = match unit {
+                    StaticAnalysisOutputUnit::Warning(_) => "warning",
+                    StaticAnalysisOutputUnit::Error(_) => "error",
+                };
+                (severity, unit.build_diagnostic(*file_id))
+            })
+            .collect()
+    })
+}
+
+/// The severity, code and message of everything reported for `sources`.
+fn report(sources: &[(&str, &str)], flags: &[(Level, &str)]) -> Vec<(&'static str, String, String)> {
+    diagnostics(sources, flags)
+        .into_iter()
+        .map(|(severity, diagnostic)| (severity, diagnostic.code.unwrap_or_default(), diagnostic.message))
+        .collect()
+}
+
+/// The severity, code and message of everything reported for `MAIN` and `UTIL`.
+fn analyze(flags: &[(Level, &str)]) -> Vec<(&'static str, String, String)> {
+    report(&[("", MAIN), ("util", UTIL)], flags)
+}
+
+/// The severity, code and label messages of `reported`.
+fn labels(reported: &[(&'static str, Diagnostic<usize>)]) -> Vec<(&'static str, String, Vec<String>)> {
+    reported
+        .iter()
+        .map(|(severity, diagnostic)| {
+            let labels = diagnostic.labels.iter().map(|label| label.message.clone()).collect();
+            (*severity, diagnostic.code.clone().unwrap_or_default(), labels)
+        })
+        .collect()
+}
+
+fn owned(expected: &[(&'static str, &str, &str)]) -> Vec<(&'static str, String, String)> {
+    expected
+        .iter()
+        .map(|(severity, code, message)| (*severity, (*code).to_owned(), (*message).to_owned()))
+        .collect()
+}
+
+#[test]
+fn test_levels() {
+    assert_eq!(analyze(&[]), owned(&[
+        ("warning", "W008", "unknown lint: `not_a_lint`"),
+        ("error", "W004", "unused variable `kept`"),
+        ("warning", "W004", "unused variable `loud`"),
+        ("error", "W006", "function `unused` is never called"),
+        ("error", "E041", "`@allow(unused_mut)` incompatible with previous forbid"),
+        ("error", "W005", "variable `fixed` does not need to be mutable"),
+    ]));
+}
+
+#[test]
+fn test_command_line() {
+    // the attributes of the gem replace the command line, `warnings` turns what is left into errors
+    let reported = analyze(&[(Level::Allow, "unused_variables"), (Level::Deny, "warnings")]);
+    assert_eq!(reported, owned(&[
+        ("error", "W008", "unknown lint: `not_a_lint`"),
+        ("error", "W004", "unused variable `kept`"),
+        ("error", "W004", "unused variable `loud`"),
+        ("error", "W006", "function `unused` is never called"),
+        ("error", "E041", "`@allow(unused_mut)` incompatible with previous forbid"),
+        ("error", "W005", "variable `fixed` does not need to be mutable"),
+    ]));
+
+    // every attribute setting an `unused` lint to something else than `forbid` is overruled
+    let reported = analyze(&[(Level::Forbid, "unused")]);
+    assert_eq!(reported.iter().filter(|(_, code, _)| code == "E041").count(), 6);
+}
+
+#[test]
+fn test_unknown_lint() {
+    // the known lints of an attribute still apply, and groups and `warnings` are known
+    let source = r#"func main() {
+    @allow(unused_variables, not_a_lint)
+    let quiet = 1;
+    @warn(unused, warnings)
+    let loud = 2;
+}
+"#;
+    assert_eq!(report(&[("", source)], &[]), owned(&[
+        ("warning", "W008", "unknown lint: `not_a_lint`"),
+        ("warning", "W004", "unused variable `loud`"),
+    ]));
+    let reported = diagnostics(&[("", source)], &[]);
+    assert_eq!(reported[0].1.labels[0].range, 43..53);
+}
+
+#[test]
+fn test_malformed_lint_attribute() {
+    // the attribute sets no level, so the default one applies
+    let source = r#"func main() {
+    @deny
+    let value = 1;
+}
+"#;
+    let reported = diagnostics(&[("", source)], &[]);
+    assert_eq!(report(&[("", source)], &[]), owned(&[
+        ("error", "E042", "malformed lint attribute `@deny`"),
+        ("warning", "W004", "unused variable `value`"),
+    ]));
+    assert_eq!(reported[0].1.notes, ["e.g. `@deny(dead_code)`"]);
+    assert_eq!(reported[1].1.notes[0], "`@warn(unused_variables)` on by default");
+}
+
+#[test]
+fn test_attributes_over_command_line() {
+    // an attribute overrides the command line in the item it is in front of only
+    let source = r#"@allow(unused_variables)
+func quiet() {
+    let value = 1;
+}
+
+func loud() {
+    let value = 1;
+}
+
+@warn(dead_code)
+private func unused() {}
+"#;
+    let reported = report(&[("", source)], &[(Level::Deny, "unused_variables"), (Level::Allow, "dead_code")]);
+    assert_eq!(reported, owned(&[
+        ("error", "W004", "unused variable `value`"),
+        ("warning", "W006", "function `unused` is never called"),
+    ]));
+}
+
+#[test]
+fn test_file_and_first_item_attributes() {
+    // the `@!` attributes are the file's, the first item can still have attributes of its own
+    let source = r#"@!deny(unused_variables)
+@!allow(dead_code)
+@warn(unused_variables)
+func first() {
+    let warned = 1;
+}
+
+func second() {
+    let denied = 2;
+}
+"#;
+    assert_eq!(report(&[("", source)], &[]), owned(&[
+        ("warning", "W004", "unused variable `warned`"),
+        ("error", "W004", "unused variable `denied`"),
+    ]));
+}
+
+#[test]
+fn test_forbid() {
+    // a forbid holds in everything nested in it, however deep an allow is, and can be repeated
+    let source = r#"@forbid(unused_variables)
+func outer() {
+    @forbid(unused_variables)
+    let kept = 1;
+    @allow(unused)
+    let hidden = 2;
+}
+"#;
+    let lint_level =
+        vec!["".to_owned(), "help: remove the unused variable".to_owned(), "the lint level is defined here".to_owned()];
+    let overruled = vec!["overruled by previous forbid".to_owned(), "`forbid` level set here".to_owned()];
+    assert_eq!(labels(&diagnostics(&[("", source)], &[])), [
+        ("error", "E041".to_owned(), overruled),
+        ("error", "W004".to_owned(), lint_level.clone()),
+        ("error", "W004".to_owned(), lint_level),
+    ]);
+
+    // a forbid on the command line has no span to point at
+    let source = "func main() {\n    @allow(unused_variables)\n    let value = 1;\n}\n";
+    let reported = diagnostics(&[("", source)], &[(Level::Forbid, "unused_variables")]);
+    assert_eq!(labels(&reported), [
+        ("error", "E041".to_owned(), vec!["overruled by previous forbid".to_owned()]),
+        ("error", "W004".to_owned(), vec!["".to_owned(), "help: remove the unused variable".to_owned()]),
+    ]);
+    assert_eq!(reported[0].1.notes, ["`forbid` level set on the command line with `-F unused_variables`"]);
+}
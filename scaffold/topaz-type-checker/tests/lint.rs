@@ -0,0 +1,260 @@
+mod common;
+
+use common::resolve;
+use topaz_report::suggestion::apply_suggestions;
+use topaz_report::Reporter;
+use topaz_type_checker::lint::lint_gem;
+use topaz_type_checker::warning::StaticAnalysisWarning;
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
+
+const MAIN: &str = r#"import gem::util::shout;
+import gem::util::whisper;
+
+func main() {
+    shout("hi");
+    {
+        return;
+    }
+    let after = "never";
+    main();
+}
+"#;
+
+const UTIL: &str = r#"public func shout(value: str) {
+    helper(value, value, value);
+}
+
+public func whisper() {}
+
+private func helper(value: str, _ignored: str, extra: str) {
+    let mut copy = value;
+    let mut changed = value;
+    changed = copy;
+    let result = helper(changed, value, value);
+    let unread = "quiet";
+    let _silent = "quiet";
+    helper(&mut changed, value, value);
+}
+
+private func dead() {
+    dead();
+}
+
+private func ping() {
+    pong();
+}
+
+private func pong() {
+    ping();
+    helper("", "", "");
+}
+"#;
+
+const UTIL_FIXED: &str = r#"public func shout(value: str) {
+    helper(value, value, value);
+}
+
+public func whisper() {}
+
+private func helper(value: str, _ignored: str, extra: str) {
+    let copy = value;
+    let mut changed = value;
+    changed = copy;
+    let _result = helper(changed, value, value);
+    let _silent = "quiet";
+    helper(&mut changed, value, value);
+}
+
+
+
+"#;
+
+fn lint() -> Vec<(usize, StaticAnalysisWarning)> {
+    resolve(&[("", MAIN), ("util", UTIL)], None, lint_gem)
+}
+
+#[test]
+fn test_lints() {
+    let warnings = lint();
+    let reported: Vec<_> = warnings
+        .iter()
+        .map(|(file_id, warning)| (*file_id, warning.lint_name(), warning.to_string()))
+        .collect();
+
+    assert_eq!(reported, [
+        (0, "unused_imports", "unused import `whisper`".to_owned()),
+        (0, "unreachable_code", "unreachable statement".to_owned()),
+        (0, "unused_variables", "unused variable `after`".to_owned()),
+        (1, "unused_variables", "unused parameter `extra`".to_owned()),
+        (1, "unused_mut", "variable `copy` does not need to be mutable".to_owned()),
+        (1, "unused_variables", "unused variable `result`".to_owned()),
+        (1, "unused_variables", "unused variable `unread`".to_owned()),
+        (1, "dead_code", "function `dead` is never called".to_owned()),
+        (1, "dead_code", "function `ping` is never called".to_owned()),
+        (1, "dead_code", "function `pong` is never called".to_owned()),
+    ]);
+
+    // `result` is initialized by a call, so removing it would lose a side effect
+    assert!(matches!(
+        &warnings[5].1,
+        StaticAnalysisWarning::UnusedVariable { statement: None, .. }
+    ));
+    let diagnostic = warnings[0].1.build_diagnostic(0);
+    assert_eq!(diagnostic.code.as_deref(), Some("W003"));
+}
+
+#[test]
+fn test_apply_suggestions() {
+    let suggestions: Vec<_> = lint()
+        .iter()
+        .filter(|(file_id, _)| *file_id == 1)
+        .flat_map(|(_, warning)| warning.suggestions())
+        .collect();
+
+    assert_eq!(apply_suggestions(UTIL, &suggestions), UTIL_FIXED);
+}
+
+/// The code and message of every warning reported for `source`, the root module of a gem,
+/// and `source` with their suggestions applied.
+fn fix(source: &str) -> (Vec<(String, String)>, String) {
+    resolve(&[("", source)], None, |resolutions, modules| {
+        let mut analyzer = StaticAnalyzer::with_default_passes(resolutions, modules);
+        analyzer.retain_passes(|name| name == "lints");
+        let mut reported = Vec::new();
+        let mut suggestions = Vec::new();
+        for (_, unit) in analyzer.analyze() {
+            let StaticAnalysisOutputUnit::Warning(warning) = unit else { panic!("unexpected error") };
+            let diagnostic = warning.build_diagnostic(0);
+            reported.push((diagnostic.code.unwrap_or_default(), diagnostic.message));
+            suggestions.extend(warning.suggestions());
+        }
+        (reported, apply_suggestions(source, &suggestions))
+    })
+}
+
+fn owned(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected.iter().map(|(code, message)| ((*code).to_owned(), (*message).to_owned())).collect()
+}
+
+#[test]
+fn test_unused_imports() {
+    let (reported, fixed) = fix(r#"import std::io::println;
+import std::io::print;
+
+func main() {
+    println("used");
+}
+"#);
+    assert_eq!(reported, owned(&[("W003", "unused import `print`")]));
+    assert_eq!(fixed, r#"import std::io::println;
+
+func main() {
+    println("used");
+}
+"#);
+}
+
+#[test]
+fn test_unused_variables() {
+    // a binding initialized by a call is renamed instead of removed, and a parameter renamed only by hand
+    let (reported, fixed) = fix(r#"func main() {
+    let unused = 1;
+    let called = main();
+    let _silent = 2;
+}
+
+func take(value: int) {}
+"#);
+    assert_eq!(reported, owned(&[
+        ("W004", "unused variable `unused`"),
+        ("W004", "unused variable `called`"),
+        ("W004", "unused parameter `value`"),
+    ]));
+    assert_eq!(fixed, r#"func main() {
+    let _called = main();
+    let _silent = 2;
+}
+
+func take(value: int) {}
+"#);
+}
+
+#[test]
+fn test_unused_mut() {
+    let (reported, fixed) = fix(r#"func main() {
+    let mut fixed = 1;
+    let mut changed = 2;
+    changed = fixed;
+    std::io::println(changed);
+}
+"#);
+    assert_eq!(reported, owned(&[("W005", "variable `fixed` does not need to be mutable")]));
+    assert_eq!(fixed, r#"func main() {
+    let fixed = 1;
+    let mut changed = 2;
+    changed = fixed;
+    std::io::println(changed);
+}
+"#);
+}
+
+#[test]
+fn test_dead_code() {
+    // functions only calling each other are dead too
+    let (reported, fixed) = fix(r#"func main() {
+    helper();
+}
+
+private func helper() {}
+
+private func dead() {
+    cycle();
+}
+
+private func cycle() {
+    dead();
+}
+"#);
+    assert_eq!(reported, owned(&[
+        ("W006", "function `dead` is never called"),
+        ("W006", "function `cycle` is never called"),
+    ]));
+    assert_eq!(fixed, r#"func main() {
+    helper();
+}
+
+private func helper() {}
+
+
+"#);
+}
+
+#[test]
+fn test_unreachable_code() {
+    let (reported, fixed) = fix(r#"func main() {
+    std::io::println("before");
+    return;
+    std::io::println("after");
+    std::io::println("again");
+}
+"#);
+    assert_eq!(reported, owned(&[("W007", "unreachable statement")]));
+    assert_eq!(fixed, r#"func main() {
+    std::io::println("before");
+    return;
+}
+"#);
+}
+
+#[test]
+fn test_unknown_lints() {
+    // the known lints of the attribute still apply, and nothing is suggested
+    let source = r#"@allow(not_a_lint, unused_variables)
+func main() {
+    let quiet = 1;
+}
+"#;
+    let (reported, fixed) = fix(source);
+    assert_eq!(reported, owned(&[("W008", "unknown lint: `not_a_lint`")]));
+    assert_eq!(fixed, source);
+}
@@ -0,0 +1,319 @@
+mod common;
+
+use common::{analyze, resolve};
+use topaz_report::Reporter;
+use topaz_type_checker::pass::{AnalysisPass, AnalysisSession};
+use topaz_type_checker::traits::coherence::Coherence;
+use topaz_type_checker::traits::{SelectionError, TraitRef};
+use topaz_type_checker::ty::{IntTy, Ty};
+use topaz_type_checker::StaticAnalysisOutputUnit;
+
+const SHAPES: &str = r#"public trait Area {
+    func area(this) -> int;
+
+    func describe(this) -> str {
+        return "shape";
+    }
+}
+"#;
+
+const MAIN: &str = r#"import gem::shapes::Area;
+
+impl<T> Drop for T {
+    public func drop(this) {}
+}
+
+impl Area for int {
+    func area(this) -> int {
+        return 1;
+    }
+
+    @override
+    func perimeter(this) -> int {
+        return 4;
+    }
+}
+
+impl Area for long {}
+
+impl<T> Area for &T {
+    func area(this) -> int {
+        return 0;
+    }
+}
+
+impl Area for &int {
+    func area(this) -> int {
+        return 0;
+    }
+}
+
+impl main for int {}
+
+func main() {}
+"#;
+
+/// Checks the coherence of `main`, next to `SHAPES` and with `Drop` in scope, and returns what `inspect` finds.
+fn coherence<T>(main: &str, inspect: impl FnOnce(&AnalysisSession) -> T) -> T {
+    let sources = [("", main), ("shapes", SHAPES)];
+    resolve(&sources, Some("import gem::mem::drop::Drop;"), |resolutions, modules| {
+        let mut session = AnalysisSession::new(resolutions, modules);
+        Coherence.run(&mut session);
+        inspect(&session)
+    })
+}
+
+#[test]
+fn test_coherence() {
+    coherence(MAIN, |session| {
+        let messages: Vec<_> = session
+            .output
+            .iter()
+            .map(|(file_id, unit)| match unit {
+                StaticAnalysisOutputUnit::Error(error) => (*file_id, error.to_string()),
+                StaticAnalysisOutputUnit::Warning(warning) => panic!("unexpected warning {warning}"),
+            })
+            .collect();
+        assert_eq!(messages, [
+            (0, "only traits defined in the current gem can be implemented for types defined outside of it".to_owned()),
+            (0, "method `perimeter` is not a member of trait `Area`".to_owned()),
+            (0, "not all required methods of `Area` are implemented".to_owned()),
+            (0, "expected a trait, found function `main`".to_owned()),
+            (0, "conflicting implementations of trait `Area` for type `&int`".to_owned()),
+        ]);
+        assert_eq!(session.traits.impls.len(), 6);
+    });
+}
+
+/// What `tstd` defines, minus what does not parse yet.
+const STD: &str = r#"public trait Drop {
+    public func drop(move this);
+}
+
+impl<T> Drop for T {
+    @std_blanket_drop
+    @override
+    public func drop(move this) {}
+}
+
+impl Drop for str {
+    public func drop(move this) {}
+}
+
+public trait Default {
+    func default() -> this;
+}
+
+impl<T> Default for maybe T {
+    func default() -> maybe T {}
+}
+"#;
+
+#[test]
+fn test_selection() {
+    analyze(STD, &mut [&mut Coherence], |session| {
+        // the blanket impl of `Drop` does not conflict with the one for `str`
+        assert!(session.output.is_empty());
+
+        let env = &session.traits;
+        let trait_ref = |name: &str| {
+            let def = env.traits.values().find(|def| def.name.value() == name).expect("trait not found").def;
+            TraitRef::Local(def)
+        };
+        let (drop, default) = (trait_ref("Drop"), trait_ref("Default"));
+        let int = Ty::Int(IntTy::Int);
+
+        // the blanket impl is only selected if no other impl applies
+        assert_eq!(env.select(&drop, &Ty::Str).map(|id| env.get(id).self_ty.clone()), Ok(Ty::Str));
+        assert_eq!(env.select(&drop, &int).map(|id| env.get(id).fallback), Ok(true));
+
+        let maybe_int = Ty::Maybe(Box::new(int.clone()));
+        assert!(env.select(&default, &maybe_int).is_ok());
+        assert_eq!(env.select(&default, &int), Err(SelectionError::Unimplemented));
+    });
+}
+
+/// The code, message and label messages of every error coherence reports for `main`, next to `SHAPES`.
+fn coherence_errors(main: &str) -> Vec<(String, String, Vec<String>)> {
+    coherence(main, |session| {
+        session
+            .output
+            .iter()
+            .map(|(file_id, unit)| {
+                assert!(matches!(unit, StaticAnalysisOutputUnit::Error(_)), "unexpected warning");
+                let diagnostic = unit.build_diagnostic(*file_id);
+                let labels = diagnostic.labels.into_iter().map(|label| label.message).collect();
+                (diagnostic.code.unwrap_or_default(), diagnostic.message, labels)
+            })
+            .collect()
+    })
+}
+
+#[test]
+fn test_conflicting_impls() {
+    let errors = coherence_errors(
+        r#"import gem::shapes::Area;
+
+impl Area for int {
+    func area(this) -> int {
+        return 1;
+    }
+}
+
+impl Area for int {
+    func area(this) -> int {
+        return 2;
+    }
+}
+
+impl<T> Area for &T {
+    func area(this) -> int {
+        return 0;
+    }
+}
+
+impl Area for &long {
+    func area(this) -> int {
+        return 0;
+    }
+}
+
+impl Area for maybe int {
+    func area(this) -> int {
+        return 0;
+    }
+}
+"#,
+    );
+    assert_eq!(errors, [
+        (
+            "E022".to_owned(),
+            "conflicting implementations of trait `Area` for type `int`".to_owned(),
+            vec!["conflicting implementation for `int`".to_owned(), "first implementation here".to_owned()],
+        ),
+        (
+            "E022".to_owned(),
+            "conflicting implementations of trait `Area` for type `&long`".to_owned(),
+            vec!["conflicting implementation for `&long`".to_owned(), "first implementation here".to_owned()],
+        ),
+    ]);
+}
+
+#[test]
+fn test_orphan_impl() {
+    // a trait of the gem for any type, or any trait for a type of the gem, is fine
+    let errors = coherence_errors(
+        r#"import gem::shapes::Area;
+
+struct Point { x: int }
+
+impl Drop for Point {
+    public func drop(move this) {}
+}
+
+impl Area for str {
+    func area(this) -> int {
+        return 0;
+    }
+}
+
+impl Drop for int {
+    public func drop(move this) {}
+}
+
+impl<T> Drop for maybe T {
+    public func drop(move this) {}
+}
+"#,
+    );
+    let message = "only traits defined in the current gem can be implemented for types defined outside of it";
+    assert_eq!(errors, [
+        (
+            "E023".to_owned(),
+            message.to_owned(),
+            vec!["`Drop` is from `std`, and `int` is not defined in this gem".to_owned()]
+        ),
+        (
+            "E023".to_owned(),
+            message.to_owned(),
+            vec!["`Drop` is from `std`, and `maybe T` is not defined in this gem".to_owned()]
+        ),
+    ]);
+}
+
+#[test]
+fn test_missing_methods() {
+    // `describe` is provided by the trait
+    let errors = coherence_errors("import gem::shapes::Area;\n\nimpl Area for long {}\n");
+    assert_eq!(errors, [(
+        "E024".to_owned(),
+        "not all required methods of `Area` are implemented".to_owned(),
+        vec!["missing `area`".to_owned()]
+    )]);
+}
+
+#[test]
+fn test_not_a_member() {
+    let errors = coherence_errors(
+        r#"import gem::shapes::Area;
+
+impl Area for int {
+    func area(this) -> int {
+        return 1;
+    }
+
+    func volume(this) -> int {
+        return 0;
+    }
+
+    @override
+    func perimeter(this) -> int {
+        return 4;
+    }
+
+    @override
+    func describe(this) -> str {
+        return "int";
+    }
+}
+"#,
+    );
+    assert_eq!(errors, [
+        (
+            "E025".to_owned(),
+            "method `volume` is not a member of trait `Area`".to_owned(),
+            vec!["not a member of the trait".to_owned()]
+        ),
+        (
+            "E025".to_owned(),
+            "method `perimeter` is not a member of trait `Area`".to_owned(),
+            vec!["marked `@override`, but overrides nothing".to_owned()]
+        ),
+    ]);
+}
+
+#[test]
+fn test_not_a_trait() {
+    let errors = coherence_errors(
+        r#"struct Point { x: int }
+
+func main() {}
+
+impl main for int {}
+
+impl Point for int {}
+"#,
+    );
+    assert_eq!(errors, [
+        (
+            "E026".to_owned(),
+            "expected a trait, found function `main`".to_owned(),
+            vec!["not a trait, but a function".to_owned()]
+        ),
+        (
+            "E026".to_owned(),
+            "expected a trait, found struct `Point`".to_owned(),
+            vec!["not a trait, but a struct".to_owned()]
+        ),
+    ]);
+}
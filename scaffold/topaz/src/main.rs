@@ -1,12 +1,27 @@
-use clap::{arg, Command};
+use clap::{arg, ArgAction, ArgMatches, Command};
 use codespan_reporting::files::SimpleFiles;
 use std::{fs, process::exit};
 use std::fmt::Debug;
+use std::path::Path;
 use topaz_ast::file::TopazFile;
 use topaz_ast::location::WithSpan;
 use topaz_parser_next::lex::{Lexer, Token};
+use topaz_codegen::{Backend, GemType, Output, PANIC_STATUS};
+use topaz_hir::HirGem;
+use topaz_interpreter::Interpreter;
+use topaz_mir::transform::{OptLevel, PassManager};
+use topaz_mir::{Body, MirGem};
 use topaz_parser_next::Parse;
 use topaz_report::{Reporter, ReporterState};
+use topaz_resolve::def::DefId;
+use topaz_resolve::{resolve_gem, ResolveContext};
+use topaz_session::graph::GemGraph;
+use topaz_session::manifest::{Manifest, MANIFEST_FILE};
+use topaz_session::{GemError, Session};
+use topaz_type_checker::lint::levels::Level;
+use topaz_type_checker::lint::registry;
+use topaz_type_checker::pass::AnalysisSession;
+use topaz_type_checker::{StaticAnalysisOutputUnit, StaticAnalyzer};
 
 fn cli() -> Command {
     Command::new("topaz")
@@ -22,9 +37,15 @@ fn cli() -> Command {
         .subcommand(
             Command::new("parse")
                 .about("Convert the source code into AST and print it")
-                .arg(arg!(<PATH> "source file path"))
+                .arg(arg!(<PATH> "source file or gem directory path"))
                 .arg_required_else_help(true),
         )
+        .subcommand(
+            lint_args(Command::new("check")
+                .about("Load every module of a gem and its dependencies and resolve the names in them")
+                .arg(arg!(<PATH> "source file or gem directory path"))
+                .arg_required_else_help(true)),
+        )
         .subcommand(
             Command::new("graphviz")
                 .about("Parse source code and print AST in graphviz format")
@@ -32,11 +53,102 @@ fn cli() -> Command {
                 .arg_required_else_help(true),
         )
         .subcommand(
-            Command::new("analyze")
-                .about("Parse source code and analyze AST using static analysis tools")
-                .arg(arg!(<PATH> "source file path"))
+            lint_args(Command::new("analyze")
+                .about("Resolve a gem and analyze it using static analysis passes")
+                .arg(arg!(<PATH> "source file or gem directory path"))
+                .arg(arg!(--pass <NAME> "only run the given analysis pass").action(ArgAction::Append))
+                .arg_required_else_help(true)),
+        )
+        .subcommand(
+            mir_pass_args(lint_args(Command::new("build")
+                .about("Analyze a gem and compile it to an intermediate representation, an object file or a program")
+                .arg(arg!(<PATH> "source file or gem directory path"))
+                .arg(arg!(--emit <KIND> "what to output")
+                    .value_parser(["hir", "mir", "c", "llvm-ir", "llvm-bc", "asm", "obj", "link"])
+                    .default_value("mir"))
+                .arg(arg!(-o --output <FILE> "where to write the output, printed or named after the gem if not given"))
+                .arg(arg!(--"gem-type" <TYPE> "what to link the gem into")
+                    .value_parser(["bin", "staticlib", "dylib"])
+                    .default_value("bin"))
+                .arg(arg!(--backend <NAME> "what to compile the gem with, LLVM if topaz was built with it and C otherwise")
+                    .value_parser(["llvm", "c", "gcc"]))
+                .arg(arg!(--target <TRIPLE> "the triple of the system to compile for, the host if not given"))
+                .arg(arg!(--"target-cpu" <CPU> "the CPU to generate code for, as LLVM names it"))
+                .arg(arg!(--"target-features" <FEATURES> "the CPU features to enable or disable, e.g. `+avx2,-sse4.1`"))
+                .arg(arg!(-g --debug "generate DWARF debug information"))
+                .arg_required_else_help(true))),
+        )
+        .subcommand(
+            mir_pass_args(lint_args(Command::new("run")
+                .about("Analyze a gem and run its `main` function with the MIR interpreter")
+                .arg(arg!(<PATH> "source file or gem directory path"))
+                .arg(arg!(--jit "compile the gem into memory with libgccjit and run it, rather than interpret it"))
+                .arg_required_else_help(true))),
+        )
+        .subcommand(
+            Command::new("update")
+                .about("Resolve the dependencies of a gem again and lock the versions found in its lockfile")
+                .arg(arg!(<PATH> "gem directory path"))
                 .arg_required_else_help(true),
         )
+        .subcommand(
+            Command::new("lints")
+                .about("List the lints, their default levels and the lint groups")
+        )
+}
+
+/// `-A`, `-W`, `-D` and `-F`, setting the level of lints for the whole gem.
+fn lint_args(command: Command) -> Command {
+    command
+        .arg(arg!(-A --allow <LINT> "do not report the given lint").action(ArgAction::Append))
+        .arg(arg!(-W --warn <LINT> "report the given lint as a warning").action(ArgAction::Append))
+        .arg(arg!(-D --deny <LINT> "report the given lint as an error").action(ArgAction::Append))
+        .arg(arg!(-F --forbid <LINT> "report the given lint as an error, in every scope").action(ArgAction::Append))
+}
+
+/// `-O`, `--enable-pass` and `--disable-pass`, choosing the MIR passes to run.
+fn mir_pass_args(command: Command) -> Command {
+    command
+        .arg(arg!(-O --"opt-level" <LEVEL> "how much to optimize the MIR")
+            .value_parser(["0", "1", "2", "3"])
+            .default_value("0"))
+        .arg(arg!(--"enable-pass" <NAME> "run the given MIR pass, whatever the optimization level").action(ArgAction::Append))
+        .arg(arg!(--"disable-pass" <NAME> "do not run the given MIR pass").action(ArgAction::Append))
+}
+
+/// The lint levels given on the command line, in the order they were given.
+fn lint_flags(reporter: &ReporterState, matches: &ArgMatches) -> Vec<(Level, String)> {
+    let mut flags = Vec::new();
+    for (id, level) in [("allow", Level::Allow), ("warn", Level::Warn), ("deny", Level::Deny), ("forbid", Level::Forbid)] {
+        let (Some(names), Some(indices)) = (matches.get_many::<String>(id), matches.indices_of(id)) else { continue };
+        flags.extend(indices.zip(names).map(|(index, name)| (index, level, name.clone())));
+    }
+    flags.sort_by_key(|(index, ..)| *index);
+
+    if let Some((_, level, unknown)) = flags.iter().find(|(_, _, name)| !registry::is_known(name)) {
+        reporter.emit_global_error(&format!(
+            "unknown lint `{unknown}` given with `{}`, see `topaz lints` for the known lints",
+            level.flag()
+        ));
+        exit(1);
+    }
+    flags.into_iter().map(|(_, level, name)| (level, name)).collect()
+}
+
+/// The MIR passes to run: the ones of `-O`, and the ones turned on or off by `--enable-pass` and `--disable-pass`.
+fn mir_passes(reporter: &ReporterState, matches: &ArgMatches) -> PassManager {
+    let level = matches.get_one::<String>("opt-level").and_then(|level| OptLevel::from_flag(level)).unwrap_or_default();
+    let mut passes = PassManager::new(level);
+    for (id, enabled) in [("enable-pass", true), ("disable-pass", false)] {
+        for name in matches.get_many::<String>(id).into_iter().flatten() {
+            if !passes.set_enabled(name, enabled) {
+                let known: Vec<_> = passes.pass_names().collect();
+                reporter.emit_global_error(&format!("unknown MIR pass `{name}`, expected one of: {}", known.join(", ")));
+                exit(1);
+            }
+        }
+    }
+    passes
 }
 
 fn main() {
@@ -75,68 +187,86 @@ fn main() {
             }
         }
         Some(("parse", sub_matches)) => {
-            let filepath = sub_matches.get_one::<String>("PATH").unwrap();
+            let path = sub_matches.get_one::<String>("PATH").unwrap();
+            let session = load_session(&reporter, path);
 
-            match fs::read_to_string(filepath) {
-                Ok(contents) => {
-                    let file_id = files.add(filepath, &contents);
-                    let mut ast = TopazFile::parse(&contents);
-
-                    match ast {
-                        Ok(file) => {
-                            println!("{:#?}", file);
-                        }
-                        Err(e) => {
-                            println!("{e}");
-                            reporter
-                                .emit_global_error("cannot output AST due to the previous errors");
+            for module in &session.modules {
+                println!("// {}", topaz_session::loader::module_name(&module.module));
+                println!("{:#?}", module.ast);
+            }
+        }
+        Some(("check", sub_matches)) => {
+            let path = sub_matches.get_one::<String>("PATH").unwrap();
+            let lints = lint_flags(&reporter, sub_matches);
 
-                            exit(1);
-                        }
-                    }
-                }
-                Err(_) => {
-                    reporter.emit_global_error("cannot read given file");
-                    exit(1);
-                }
+            if !check_path(&reporter, path, &lints, None, |_, _| true) {
+                exit(1);
             }
         }
         Some(("analyze", sub_matches)) => {
-            /*
-            let filepath = sub_matches.get_one::<String>("PATH").unwrap();
-
-            match fs::read_to_string(filepath) {
-                Ok(contents) => {
-                    let file_id = files.add(filepath, &contents);
-                    let mut parser = Parser::new(&contents);
+            let path = sub_matches.get_one::<String>("PATH").unwrap();
+            let passes: Option<Vec<String>> = sub_matches.get_many::<String>("pass").map(|passes| passes.cloned().collect());
+            let lints = lint_flags(&reporter, sub_matches);
 
-                    let ast = parser.parse();
-
-                    match ast {
-                        Ok(program_unit) => {
-                            let mut analyzer = StaticAnalyzer::new(file_id, program_unit, &files);
-                            analyzer.analyze();
-                            for e in &analyzer.output {
-                                e.1.emit_diagnostic(&reporter, &files, e.0);
-                            }
-                        }
-                        Err(e) => {
-                            e.emit_diagnostic(&reporter, &files, file_id);
+            if !check_path(&reporter, path, &lints, passes.as_deref(), |_, _| true) {
+                exit(1);
+            }
+        }
+        Some(("build", sub_matches)) => {
+            let path = sub_matches.get_one::<String>("PATH").unwrap();
+            let options = BuildOptions {
+                name: gem_name(path),
+                emit: sub_matches.get_one::<String>("emit").unwrap(),
+                output: sub_matches.get_one::<String>("output").map(String::as_str),
+                gem_type: sub_matches.get_one::<String>("gem-type").unwrap(),
+                backend: sub_matches.get_one::<String>("backend").map(String::as_str),
+                target: sub_matches.get_one::<String>("target").map(String::as_str),
+                target_cpu: sub_matches.get_one::<String>("target-cpu").map(String::as_str),
+                target_features: sub_matches.get_one::<String>("target-features").map(String::as_str),
+                debug: sub_matches.get_flag("debug"),
+            };
+            let lints = lint_flags(&reporter, sub_matches);
+            let passes = mir_passes(&reporter, sub_matches);
 
-                            reporter
-                                .emit_global_error("cannot output AST due to the previous errors");
+            if !check_path(&reporter, path, &lints, None, |session, analysis| match options.emit {
+                "hir" | "mir" => emit_ir(&reporter, analysis, &options, &passes),
+                _ => compile(&reporter, session, analysis, &options, &passes),
+            }) {
+                exit(1);
+            }
+        }
+        Some(("run", sub_matches)) => {
+            let path = sub_matches.get_one::<String>("PATH").unwrap();
+            let lints = lint_flags(&reporter, sub_matches);
+            let passes = mir_passes(&reporter, sub_matches);
+            let jit = sub_matches.get_flag("jit");
 
-                            exit(1);
-                        }
-                    }
-                }
-                Err(_) => {
-                    reporter.emit_global_error("cannot read given file");
-                    exit(1);
-                }
+            let mut panicked = false;
+            if !check_path(&reporter, path, &lints, None, |session, analysis| {
+                run_main(&reporter, session, analysis, &passes, jit, &mut panicked)
+            }) {
+                exit(if panicked { PANIC_STATUS } else { 1 });
+            }
+        }
+        Some(("update", sub_matches)) => {
+            let path = sub_matches.get_one::<String>("PATH").unwrap();
+            let graph = match GemGraph::load_unlocked(Path::new(path)) {
+                Ok(graph) => graph,
+                Err(errors) => emit_gem_errors(&reporter, errors),
+            };
+            store_lockfile(&reporter, &graph);
+        }
+        Some(("lints", _)) => {
+            println!("{:<40} {:<8} {:<6} description", "name", "default", "code");
+            for lint in registry::LINTS {
+                println!("{:<40} {:<8} {:<6} {}", lint.name, lint.default.name(), lint.code, lint.description);
+            }
+            println!();
+            println!("{:<40} lints", "group");
+            println!("{:<40} every lint at the `warn` level", registry::WARNINGS);
+            for (group, lints) in registry::GROUPS {
+                println!("{group:<40} {}", lints.join(", "));
             }
-             */
-            println!("no analyze for you :))))))))))0")
         }
         Some(("graphviz", sub_matches)) => {
             /*
@@ -176,3 +306,416 @@ fn main() {
         _ => {}
     }
 }
+
+/// Loads every module at `path`, exiting if any of them cannot be loaded or parsed.
+fn load_session(reporter: &ReporterState, path: &str) -> Session {
+    if !Path::new(path).exists() {
+        reporter.emit_global_error("cannot read given file");
+        exit(1);
+    }
+
+    let mut session = Session::new();
+    session.load(Path::new(path));
+    if session.has_errors() {
+        session.emit_errors(reporter);
+        reporter.emit_global_error("cannot continue due to the previous errors");
+        exit(1);
+    }
+
+    session
+}
+
+/// Resolves the names of a loaded gem and analyzes it, returning whether it is free of errors.
+fn check_gem(reporter: &ReporterState, session: &Session, cx: &mut ResolveContext, lints: &[(Level, String)]) -> bool {
+    check_gem_with(reporter, session, cx, lints, None, |_| true)
+}
+
+/// Like [`check_gem`], running only the analysis `passes` if given,
+/// and then `and_then` with the analyzed gem if it is free of errors.
+fn check_gem_with(
+    reporter: &ReporterState,
+    session: &Session,
+    cx: &mut ResolveContext,
+    lints: &[(Level, String)],
+    passes: Option<&[String]>,
+    and_then: impl FnOnce(&AnalysisSession) -> bool,
+) -> bool {
+    let modules: Vec<_> = session.source_modules().collect();
+    let resolutions = resolve_gem(cx, modules.clone());
+    for (file_id, error) in &resolutions.errors {
+        error.emit_diagnostic(reporter, &session.files, *file_id);
+    }
+    if !resolutions.errors.is_empty() {
+        return false;
+    }
+
+    let mut analyzer = StaticAnalyzer::with_default_passes(&resolutions, &modules);
+    analyzer.register(topaz_mir::borrowck::BorrowCheck);
+    for (level, name) in lints {
+        analyzer.set_lint_level(*level, name);
+    }
+    if let Some(passes) = passes {
+        if let Some(unknown) = passes.iter().find(|name| !analyzer.pass_names().any(|pass| pass == *name)) {
+            let known: Vec<_> = analyzer.pass_names().collect();
+            reporter.emit_global_error(&format!(
+                "unknown analysis pass `{unknown}`, expected one of: {}",
+                known.join(", ")
+            ));
+            return false;
+        }
+        analyzer.retain_passes(|pass| passes.iter().any(|name| name == pass));
+    }
+
+    let mut analysis = analyzer.run();
+    analysis.output.sort_by_key(|(file_id, _)| *file_id);
+    for (file_id, unit) in &analysis.output {
+        unit.emit_diagnostic(reporter, &session.files, *file_id);
+    }
+
+    let has_errors = analysis
+        .output
+        .iter()
+        .any(|(_, unit)| matches!(unit, StaticAnalysisOutputUnit::Error(_)));
+    !has_errors && and_then(&analysis)
+}
+
+/// The options of `topaz build`.
+struct BuildOptions<'a> {
+    name: String,
+    emit: &'a str,
+    output: Option<&'a str>,
+    gem_type: &'a str,
+    backend: Option<&'a str>,
+    #[cfg_attr(not(feature = "llvm"), allow(dead_code))]
+    target: Option<&'a str>,
+    #[cfg_attr(not(feature = "llvm"), allow(dead_code))]
+    target_cpu: Option<&'a str>,
+    #[cfg_attr(not(feature = "llvm"), allow(dead_code))]
+    target_features: Option<&'a str>,
+    #[cfg_attr(not(feature = "llvm"), allow(dead_code))]
+    debug: bool,
+}
+
+/// Lowers an analyzed gem and outputs it as `hir` or `mir` optimized by `passes`, returning whether it could be.
+fn emit_ir(reporter: &ReporterState, analysis: &AnalysisSession, options: &BuildOptions, passes: &PassManager) -> bool {
+    let hir = topaz_hir::lower::lower_gem(analysis);
+    let text = if options.emit == "hir" {
+        hir.to_string()
+    } else {
+        let Some(mir) = lower_mir(reporter, &hir, passes) else { return false };
+        mir.to_string()
+    };
+
+    let Some(output) = options.output else {
+        print!("{text}");
+        return true;
+    };
+    if let Err(error) = fs::write(output, text) {
+        reporter.emit_global_error(&format!("cannot write `{output}`: {error}"));
+        return false;
+    }
+    true
+}
+
+/// The backend `topaz build` compiles with if `--backend` is not given.
+const DEFAULT_BACKEND: &str = if cfg!(feature = "llvm") { "llvm" } else { "c" };
+
+/// Compiles an analyzed gem with a backend and writes it as `options.emit`, returning whether it could be.
+fn compile(
+    reporter: &ReporterState,
+    session: &Session,
+    analysis: &AnalysisSession,
+    options: &BuildOptions,
+    passes: &PassManager,
+) -> bool {
+    let backend = options.backend.unwrap_or(match options.emit {
+        "c" => "c",
+        "llvm-ir" | "llvm-bc" => "llvm",
+        _ => DEFAULT_BACKEND,
+    });
+    let supported = match options.emit {
+        "c" => backend == "c",
+        "llvm-ir" | "llvm-bc" => backend == "llvm",
+        _ => true,
+    };
+    if !supported {
+        reporter.emit_global_error(&format!("the `{backend}` backend cannot emit `{}`", options.emit));
+        return false;
+    }
+
+    let hir = topaz_hir::lower::lower_gem(analysis);
+    let Some(mir) = lower_mir(reporter, &hir, passes) else { return false };
+    let gem_type = match options.gem_type {
+        "staticlib" => GemType::StaticLib,
+        "dylib" => GemType::SharedLib,
+        _ => GemType::Bin,
+    };
+    let output = match options.emit {
+        "asm" => Output::Assembly,
+        "obj" => Output::Object,
+        _ => Output::Linked(gem_type),
+    };
+
+    // a program starts with the `main` function of its root module, which libraries do not need
+    let root = session.source_modules().find(|module| module.path.is_empty()).map(|module| module.file_id);
+    let main = root.and_then(|file_id| mir.entry_point(file_id)).filter(|_| gem_type == GemType::Bin);
+    if options.emit == "link" && gem_type == GemType::Bin && main.is_none() {
+        reporter.emit_global_error("there is no `main` function in the root module to start the program with");
+        return false;
+    }
+    if main.map_or(false, |main| main.arg_count > 0) {
+        reporter.emit_global_error("`main` cannot take parameters");
+        return false;
+    }
+
+    let entry = main.and_then(|main| main.def);
+    let result = match backend {
+        "c" => compile_c(options, &mir, entry, output),
+        "gcc" => compile_gcc(options, &mir, entry, output),
+        _ => compile_llvm(session, options, &mir, entry, output, root),
+    };
+    if let Err(error) = result {
+        reporter.emit_global_error(&error);
+        return false;
+    }
+    true
+}
+
+/// Builds `output` with `backend`, written to `-o` or a file named after the gem.
+fn build_with(
+    backend: &impl Backend,
+    options: &BuildOptions,
+    mir: &MirGem,
+    entry: Option<DefId>,
+    output: Output,
+) -> Result<(), String> {
+    let path = options.output.map_or_else(|| output.file_name(&options.name), str::to_owned);
+    backend.build(mir, &options.name, entry, output, Path::new(&path)).map_err(|error| error.to_string())
+}
+
+/// Compiles the MIR of a gem with the C backend and the C compiler of the system, or only generates the C.
+fn compile_c(options: &BuildOptions, mir: &MirGem, entry: Option<DefId>, output: Output) -> Result<(), String> {
+    use topaz_c_codegen::CBackend;
+
+    if options.emit != "c" {
+        return build_with(&CBackend, options, mir, entry, output);
+    }
+    let path = options.output.map_or_else(|| format!("{}.c", options.name), str::to_owned);
+    topaz_c_codegen::build(mir, &options.name, entry, topaz_c_codegen::Output::Source, Path::new(&path))
+        .map_err(|error| error.to_string())
+}
+
+/// Compiles the MIR of a gem with the libgccjit backend.
+#[cfg(feature = "gcc")]
+fn compile_gcc(options: &BuildOptions, mir: &MirGem, entry: Option<DefId>, output: Output) -> Result<(), String> {
+    build_with(&topaz_gcc_codegen::GccBackend, options, mir, entry, output)
+}
+
+#[cfg(not(feature = "gcc"))]
+fn compile_gcc(options: &BuildOptions, _mir: &MirGem, _entry: Option<DefId>, _output: Output) -> Result<(), String> {
+    Err(format!(
+        "cannot emit `{}`, topaz was built without the libgccjit backend (enable the `gcc` feature)",
+        options.emit
+    ))
+}
+
+/// Compiles the MIR of a gem with the LLVM backend, for the target of `options`.
+#[cfg(feature = "llvm")]
+fn compile_llvm(
+    session: &Session,
+    options: &BuildOptions,
+    mir: &MirGem,
+    entry: Option<DefId>,
+    output: Output,
+    root: Option<usize>,
+) -> Result<(), String> {
+    use topazc_llvm::target::Target;
+    use topazc_llvm::{FileKind, LlvmBackend, Sources};
+
+    let mut target = options.target.map_or_else(Target::host, Target::from_triple).map_err(|error| error.to_string())?;
+    if let Some(cpu) = options.target_cpu {
+        target = target.with_cpu(cpu);
+    }
+    if let Some(features) = options.target_features {
+        target = target.with_features(features);
+    }
+    let sources = options.debug.then(|| Sources { files: &session.files, root: root.unwrap_or_default() });
+
+    let kind = match options.emit {
+        "llvm-ir" => FileKind::LlvmIr,
+        "llvm-bc" => FileKind::Bitcode,
+        _ => return build_with(&LlvmBackend { target, sources }, options, mir, entry, output),
+    };
+    let path = options.output.map_or_else(|| format!("{}.{}", options.name, kind.extension()), str::to_owned);
+    topazc_llvm::build(mir, &options.name, &target, entry, sources, topazc_llvm::Output::File(kind), Path::new(&path))
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(not(feature = "llvm"))]
+fn compile_llvm(
+    _session: &Session,
+    options: &BuildOptions,
+    _mir: &MirGem,
+    _entry: Option<DefId>,
+    _output: Output,
+    _root: Option<usize>,
+) -> Result<(), String> {
+    Err(format!(
+        "cannot emit `{}`, topaz was built without the LLVM backend (enable the `llvm` feature or use `--backend c`)",
+        options.emit
+    ))
+}
+
+/// Builds the MIR of a gem and optimizes it with `passes`, reporting the errors if it is found to be invalid.
+fn lower_mir(reporter: &ReporterState, hir: &HirGem, passes: &PassManager) -> Option<MirGem> {
+    let mut mir = topaz_mir::build::build_gem(hir);
+    topaz_mir::ssa::construct_gem(&mut mir);
+    let errors = topaz_mir::validate::validate_gem(&mir);
+    for (name, error) in &errors {
+        reporter.emit_global_error(&format!("invalid MIR for `{name}`: {error}"));
+    }
+    if !errors.is_empty() {
+        return None;
+    }
+    if let Err(invalid) = passes.run(&mut mir) {
+        for (name, error) in &invalid.errors {
+            reporter.emit_global_error(&format!("invalid MIR for `{name}` after the `{}` pass: {error}", invalid.pass));
+        }
+        return None;
+    }
+    Some(mir)
+}
+
+/// Runs the `main` function of the root module of an analyzed gem, returning whether it ran to the end.
+/// `panicked` is set if it stopped because the program panicked. With `jit`, the gem is compiled into memory
+/// and run there rather than interpreted.
+fn run_main(
+    reporter: &ReporterState,
+    session: &Session,
+    analysis: &AnalysisSession,
+    passes: &PassManager,
+    jit: bool,
+    panicked: &mut bool,
+) -> bool {
+    let hir = topaz_hir::lower::lower_gem(analysis);
+    let Some(mir) = lower_mir(reporter, &hir, passes) else { return false };
+    let root = session.source_modules().find(|module| module.path.is_empty()).map(|module| module.file_id);
+    let Some(main) = root.and_then(|file_id| mir.entry_point(file_id)) else {
+        reporter.emit_global_error("there is no `main` function in the root module to run");
+        return false;
+    };
+    if main.arg_count > 0 {
+        reporter.emit_global_error("`main` cannot take parameters");
+        return false;
+    }
+    if jit {
+        return run_jit(reporter, &mir, main);
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    match Interpreter::new(&mir, &mut stdout).run(main) {
+        Ok(_) => true,
+        Err(panic) => {
+            panic.emit_diagnostic(reporter, &session.files, panic.file_id);
+            *panicked = true;
+            false
+        }
+    }
+}
+
+/// Compiles a gem into memory with libgccjit and runs its `main` function, returning whether it could be.
+#[cfg(feature = "gcc")]
+fn run_jit(reporter: &ReporterState, mir: &MirGem, main: &Body) -> bool {
+    use topaz_codegen::{Jit, JitCode};
+
+    let code = match topaz_gcc_codegen::GccBackend.jit(mir) {
+        Ok(code) => code,
+        Err(error) => {
+            reporter.emit_global_error(&error.to_string());
+            return false;
+        }
+    };
+    // SAFETY: `main` takes and returns nothing
+    if !unsafe { code.call(&main.name) } {
+        reporter.emit_global_error("`main` is not in the compiled code");
+        return false;
+    }
+    true
+}
+
+#[cfg(not(feature = "gcc"))]
+fn run_jit(reporter: &ReporterState, _mir: &MirGem, _main: &Body) -> bool {
+    reporter.emit_global_error(
+        "cannot compile `main` into memory, topaz was built without the libgccjit backend (enable the `gcc` feature)",
+    );
+    false
+}
+
+/// Checks the gem at `path`, and then runs `and_then` with its session and analysis if it is free of errors,
+/// returning whether both succeeded.
+///
+/// A gem directory with a manifest is checked with the dependencies the manifest declares, which are checked
+/// first, and the versions pinned by its lockfile. Anything else is checked as a gem of its own, with `std`.
+fn check_path(
+    reporter: &ReporterState,
+    path: &str,
+    lints: &[(Level, String)],
+    passes: Option<&[String]>,
+    and_then: impl FnOnce(&Session, &AnalysisSession) -> bool,
+) -> bool {
+    if !Path::new(path).join(MANIFEST_FILE).is_file() {
+        let session = load_session(reporter, path);
+        let mut cx = ResolveContext::new(&gem_name(path), true);
+        cx.set_prelude(topaz_session::prelude::std_prelude());
+        return check_gem_with(reporter, &session, &mut cx, lints, passes, |analysis| and_then(&session, analysis));
+    }
+
+    let graph = match GemGraph::load(Path::new(path)) {
+        Ok(graph) => graph,
+        Err(errors) => emit_gem_errors(reporter, errors),
+    };
+    store_lockfile(reporter, &graph);
+
+    for id in graph.build_order().filter(|id| *id != GemGraph::ROOT) {
+        let gem = graph.get(id);
+        let session = load_session(reporter, &gem.dir.display().to_string());
+        if !check_gem(reporter, &session, &mut graph.resolve_context(id), lints) {
+            reporter.emit_global_error(&format!(
+                "could not check gem `{}` due to the previous errors",
+                gem.manifest.name()
+            ));
+            return false;
+        }
+    }
+    let session = load_session(reporter, &graph.root().dir.display().to_string());
+    let mut cx = graph.resolve_context(GemGraph::ROOT);
+    check_gem_with(reporter, &session, &mut cx, lints, passes, |analysis| and_then(&session, analysis))
+}
+
+/// Reports the errors of a gem graph that could not be loaded, and exits.
+fn emit_gem_errors(reporter: &ReporterState, errors: Vec<GemError>) -> ! {
+    let no_files = SimpleFiles::<String, String>::new();
+    for error in errors {
+        error.emit_diagnostic(reporter, &no_files, Session::NO_FILE);
+    }
+    exit(1);
+}
+
+/// Writes the lockfile of the root gem of `graph`, if it changed.
+fn store_lockfile(reporter: &ReporterState, graph: &GemGraph) {
+    if let Err(error) = graph.lockfile().store(&graph.root().dir) {
+        error.emit_diagnostic(reporter, &SimpleFiles::<String, String>::new(), Session::NO_FILE);
+    }
+}
+
+/// The name of the gem at `path`: the name in its manifest, the name of the gem directory, or the file stem.
+fn gem_name(path: &str) -> String {
+    if let Ok(manifest) = Manifest::load(Path::new(path)) {
+        return manifest.name().to_owned();
+    }
+    let path = Path::new(path);
+    path.file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or("main")
+        .to_owned()
+}
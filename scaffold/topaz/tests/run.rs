@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use topaz_codegen::PANIC_STATUS;
+
+const PANICS: &str = r#"func at(index: int) -> int {
+    let values = [1, 2, 3];
+    return values[index];
+}
+
+public func main() {
+    println("before");
+    println(at(3));
+    println("after");
+}
+"#;
+
+/// Writes `source` to a file of its own, as the tests run at the same time, and runs `topaz run` on it.
+fn run(test: &str, source: &str, jit: bool) -> Output {
+    let path = std::env::temp_dir().join(format!("topaz-run-{test}-{}.tp", std::process::id()));
+    std::fs::write(&path, source).expect("failed to write the source file");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_topaz"));
+    command.arg("run").arg(&path);
+    if jit {
+        command.arg("--jit");
+    }
+    command.output().expect("failed to run topaz")
+}
+
+fn hello_world() -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples/hello-world.tp");
+    std::fs::read_to_string(path).expect("failed to read the example")
+}
+
+#[test]
+fn test_interpret() {
+    let output = run("interpret", &hello_world(), false);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello world\n");
+
+    let output = run("interpret-panic", PANICS, false);
+    assert_eq!(output.status.code(), Some(PANIC_STATUS));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+}
+
+#[cfg(feature = "gcc")]
+#[test]
+fn test_jit() {
+    let output = run("jit", &hello_world(), true);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello world\n");
+
+    let output = run("jit-panic", PANICS, true);
+    assert_eq!(output.status.code(), Some(PANIC_STATUS));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+}
+
+#[cfg(not(feature = "gcc"))]
+#[test]
+fn test_jit_without_backend() {
+    let output = run("jit", &hello_world(), true);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("without the libgccjit backend"));
+}